@@ -174,5 +174,40 @@ fn bench_line_access(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_file_opening, bench_line_access);
+fn bench_line_skip(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("line_skip");
+    group.sample_size(20);
+    group.measurement_time(std::time::Duration::from_secs(3));
+
+    // Short lines are the worst case: one newline every few bytes
+    let fixture = NamedTempFile::new().expect("failed to create temp file");
+    let content: String = (0..200_000).map(|i| format!("{:06}\n", i)).collect();
+    std::fs::write(fixture.path(), content).unwrap();
+    let accessor =
+        rt.block_on(async { FileAccessorFactory::create(fixture.path()).await.unwrap() });
+    let end = accessor.file_size();
+
+    group.bench_function("forward/10k_lines", |b| {
+        b.iter(|| {
+            let top = rt.block_on(async { accessor.next_page_start(0, 10_000).await.unwrap() });
+            black_box(top);
+        });
+    });
+    group.bench_function("backward/10k_lines", |b| {
+        b.iter(|| {
+            let top = rt.block_on(async { accessor.prev_page_start(end, 10_000).await.unwrap() });
+            black_box(top);
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_file_opening,
+    bench_line_access,
+    bench_line_skip
+);
 criterion_main!(benches);
@@ -0,0 +1,60 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rlless::input::{InputAction, InputStateMachine, Keymap, SharedKeymap};
+
+/// Keys a session typically sends in navigation mode, mixing bound and unbound ones
+fn key_stream() -> Vec<KeyEvent> {
+    [
+        (KeyCode::Char('j'), KeyModifiers::NONE),
+        (KeyCode::Char('k'), KeyModifiers::NONE),
+        (KeyCode::Char('d'), KeyModifiers::CONTROL),
+        (KeyCode::PageDown, KeyModifiers::NONE),
+        (KeyCode::Char('G'), KeyModifiers::SHIFT),
+        (KeyCode::Char('n'), KeyModifiers::NONE),
+        (KeyCode::F(5), KeyModifiers::NONE),
+        (KeyCode::Char('x'), KeyModifiers::ALT),
+    ]
+    .into_iter()
+    .map(|(code, modifiers)| KeyEvent::new(code, modifiers))
+    .collect()
+}
+
+/// Every ASCII key under every modifier combination bound, plus the function keys
+fn large_keymap() -> Keymap {
+    let mut keymap = Keymap::new();
+    for prefix in ["", "ctrl-", "alt-", "ctrl-alt-"] {
+        for ch in ('a'..='z').chain('0'..='9') {
+            keymap
+                .bind(&format!("{prefix}{ch}"), InputAction::NextMatch)
+                .unwrap();
+        }
+    }
+    for n in 1..=12 {
+        keymap.bind(&format!("f{n}"), InputAction::GoToEnd).unwrap();
+    }
+    keymap
+}
+
+fn bench_key_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("key_lookup");
+    let keys = key_stream();
+
+    for (name, keymap) in [("built_in", None), ("large_keymap", Some(large_keymap()))] {
+        let mut machine = InputStateMachine::new();
+        if let Some(keymap) = keymap {
+            machine = machine.with_keymap(SharedKeymap::new(keymap));
+        }
+        group.bench_function(BenchmarkId::new("navigation_keys", name), |b| {
+            b.iter(|| {
+                for key in &keys {
+                    black_box(machine.handle_key_event(*key));
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_key_lookup);
+criterion_main!(benches);
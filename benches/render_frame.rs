@@ -0,0 +1,81 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ratatui::backend::TestBackend;
+use ratatui::widgets::Paragraph;
+use ratatui::Terminal;
+use rlless::render::protocol::HighlightKind;
+use rlless::render::ui::{ColorTheme, LineCache, TerminalUI, ViewState};
+
+const WIDTH: u16 = 240;
+const HEIGHT: u16 = 70;
+
+/// A full 240×70 page of log lines wider than the screen, each with a few search and filter
+/// highlights
+fn full_viewport() -> ViewState {
+    let mut view_state = ViewState::new("/bench/app.log", WIDTH, HEIGHT);
+    let rows = usize::from(view_state.lines_per_page());
+    let lines: Vec<String> = (0..rows)
+        .map(|i| {
+            format!(
+                "2024-06-01T12:{:02}:{:02}.123Z ERROR [worker-{i}] request failed: timeout after \
+                 30s talking to upstream; retrying with backoff {}",
+                i % 60,
+                (i * 7) % 60,
+                "x".repeat(200)
+            )
+        })
+        .collect();
+    let highlights = lines
+        .iter()
+        .map(|line| {
+            let error = line.find("ERROR").unwrap();
+            let timeout = line.find("timeout").unwrap();
+            vec![
+                (0, 24, HighlightKind::Filter),
+                (error, error + 5, HighlightKind::Search),
+                (timeout, timeout + 7, HighlightKind::Search),
+            ]
+        })
+        .collect();
+    view_state.update_viewport_content(lines, highlights);
+    view_state
+}
+
+fn bench_span_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("span_construction");
+    let view_state = full_viewport();
+    let theme = ColorTheme::default();
+
+    group.bench_function(BenchmarkId::new("content_lines", "240x70"), |b| {
+        b.iter(|| black_box(TerminalUI::content_lines(&view_state, &theme)));
+    });
+    let mut cache = LineCache::default();
+    group.bench_function(BenchmarkId::new("line_cache_hit", "240x70"), |b| {
+        b.iter(|| black_box(cache.lines(&view_state, &theme).len()));
+    });
+
+    // Whole frames, as drawn when only the status line changed
+    let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+    group.bench_function(BenchmarkId::new("frame_uncached", "240x70"), |b| {
+        b.iter(|| {
+            terminal
+                .draw(|frame| {
+                    let lines = TerminalUI::content_lines(&view_state, &theme);
+                    frame.render_widget(Paragraph::new(lines), frame.size());
+                })
+                .unwrap();
+        });
+    });
+    let mut cache = LineCache::default();
+    group.bench_function(BenchmarkId::new("frame_cached", "240x70"), |b| {
+        b.iter(|| {
+            terminal
+                .draw(|frame| TerminalUI::draw_frame(frame, &view_state, &theme, &mut cache))
+                .unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_span_construction);
+criterion_main!(benches);
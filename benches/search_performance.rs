@@ -1,14 +1,25 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
-use rlless::file_handler::{FileAccessor, FileAccessorFactory};
-use rlless::search::{RipgrepEngine, SearchEngine, SearchOptions};
+use ratatui::backend::TestBackend;
+use ratatui::widgets::Paragraph;
+use ratatui::Terminal;
+use rlless::file_handler::{FileAccessor, FileAccessorFactory, SEGMENT_BYTES};
+use rlless::render::protocol::{
+    HighlightKind, RequestId, SearchCommand, SearchHighlightSpec, SearchResponse, ViewportRequest,
+};
+use rlless::render::ui::{ColorTheme, TerminalUI, ViewState};
+use rlless::search::{
+    search_worker_loop, MemoryBudget, PatternModifiers, RipgrepEngine, SearchEngine, SearchOptions,
+};
+use rlless::util::CancellationToken;
 use std::fmt::Write as _;
 use std::io::{BufWriter, Write};
 use std::sync::Arc;
 use std::time::Duration;
 use tempfile::NamedTempFile;
 use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
 
 const KB: usize = 1024;
 const MB: usize = 1024 * KB;
@@ -134,8 +145,9 @@ fn bench_search_patterns(c: &mut Criterion) {
             |b, eng| {
                 let options = SearchOptions::default();
                 b.iter(|| {
-                    let result =
-                        rt.block_on(async { eng.search_from("timeout", 0, &options, None).await });
+                    let result = rt.block_on(async {
+                        eng.search_from("timeout", 0, &options, None, None).await
+                    });
                     let _ = black_box(result);
                 });
             },
@@ -152,7 +164,7 @@ fn bench_search_patterns(c: &mut Criterion) {
                 };
                 b.iter(|| {
                     let result = rt.block_on(async {
-                        eng.search_from(r"timeout|connection_failed", 0, &options, None)
+                        eng.search_from(r"timeout|connection_failed", 0, &options, None, None)
                             .await
                     });
                     let _ = black_box(result);
@@ -170,8 +182,9 @@ fn bench_search_patterns(c: &mut Criterion) {
                     ..Default::default()
                 };
                 b.iter(|| {
-                    let result =
-                        rt.block_on(async { eng.search_from("ERROR", 0, &options, None).await });
+                    let result = rt.block_on(async {
+                        eng.search_from("ERROR", 0, &options, None, None).await
+                    });
                     let _ = black_box(result);
                 });
             },
@@ -185,7 +198,7 @@ fn bench_search_patterns(c: &mut Criterion) {
             };
             b.iter(|| {
                 let result =
-                    rt.block_on(async { eng.search_from("auth", 0, &options, None).await });
+                    rt.block_on(async { eng.search_from("auth", 0, &options, None, None).await });
                 let _ = black_box(result);
             });
         });
@@ -216,8 +229,11 @@ fn bench_search_navigation(c: &mut Criterion) {
         let engine = Arc::clone(&engine);
         b.iter(|| {
             // Start from middle of file
-            let result =
-                rt.block_on(async { engine.search_from("timeout", 1000, &options, None).await });
+            let result = rt.block_on(async {
+                engine
+                    .search_from("timeout", 1000, &options, None, None)
+                    .await
+            });
             let _ = black_box(result);
         });
     });
@@ -228,8 +244,11 @@ fn bench_search_navigation(c: &mut Criterion) {
         let engine = Arc::clone(&engine);
         b.iter(|| {
             // Start from near end of file
-            let result =
-                rt.block_on(async { engine.search_prev("timeout", 2000, &options, None).await });
+            let result = rt.block_on(async {
+                engine
+                    .search_prev("timeout", 2000, &options, None, None)
+                    .await
+            });
             let _ = black_box(result);
         });
     });
@@ -243,7 +262,7 @@ fn bench_search_navigation(c: &mut Criterion) {
         b.iter(|| {
             let result = rt.block_on(async {
                 engine
-                    .search_from("connection_failed", 0, &options, None)
+                    .search_from("connection_failed", 0, &options, None, None)
                     .await
             });
             let _ = black_box(result);
@@ -272,7 +291,7 @@ fn bench_search_caching(c: &mut Criterion) {
     // Warm up cache with first search
     let options = SearchOptions::default();
     rt.block_on(async {
-        let _ = engine.search_from("timeout", 0, &options, None).await;
+        let _ = engine.search_from("timeout", 0, &options, None, None).await;
     });
 
     // Test cache hit performance
@@ -280,7 +299,7 @@ fn bench_search_caching(c: &mut Criterion) {
         let engine = Arc::clone(&engine);
         b.iter(|| {
             let result =
-                rt.block_on(async { engine.search_from("timeout", 0, &options, None).await });
+                rt.block_on(async { engine.search_from("timeout", 0, &options, None, None).await });
             let _ = black_box(result);
         });
     });
@@ -293,7 +312,7 @@ fn bench_search_caching(c: &mut Criterion) {
             counter += 1;
             let pattern = format!("user_{}", counter % 1000);
             let result =
-                rt.block_on(async { engine.search_from(&pattern, 0, &options, None).await });
+                rt.block_on(async { engine.search_from(&pattern, 0, &options, None, None).await });
             let _ = black_box(result);
         });
     });
@@ -332,7 +351,7 @@ fn bench_complex_regex_patterns(c: &mut Criterion) {
                 };
                 b.iter(|| {
                     let result = rt.block_on(async {
-                        eng.search_from(r"IPv4: 192\.168\.1\.\d{1,3}", 0, &options, None)
+                        eng.search_from(r"IPv4: 192\.168\.1\.\d{1,3}", 0, &options, None, None)
                             .await
                     });
                     let _ = black_box(result);
@@ -356,6 +375,7 @@ fn bench_complex_regex_patterns(c: &mut Criterion) {
                             0,
                             &options,
                             None,
+                            None,
                         )
                         .await
                     });
@@ -380,6 +400,7 @@ fn bench_complex_regex_patterns(c: &mut Criterion) {
                             0,
                             &options,
                             None,
+                            None,
                         )
                         .await
                     });
@@ -404,6 +425,7 @@ fn bench_complex_regex_patterns(c: &mut Criterion) {
                             0,
                             &options,
                             None,
+                            None,
                         )
                         .await
                     });
@@ -428,6 +450,7 @@ fn bench_complex_regex_patterns(c: &mut Criterion) {
                             0,
                             &options,
                             None,
+                            None,
                         )
                         .await
                     });
@@ -474,7 +497,8 @@ fn bench_random_start_positions(c: &mut Criterion) {
                     // Generate random start byte position (avoid last 10% to ensure matches)
                     let start_byte = rng.gen_range(0..file_size.saturating_sub(file_size / 10));
                     let result = rt.block_on(async {
-                        eng.search_from("timeout", start_byte, &options, None).await
+                        eng.search_from("timeout", start_byte, &options, None, None)
+                            .await
                     });
                     let _ = black_box(result);
                 });
@@ -499,6 +523,7 @@ fn bench_random_start_positions(c: &mut Criterion) {
                             start_byte,
                             &options,
                             None,
+                            None,
                         )
                         .await
                     });
@@ -520,8 +545,14 @@ fn bench_random_start_positions(c: &mut Criterion) {
                 b.iter(|| {
                     let start_byte = rng.gen_range(0..file_size.saturating_sub(file_size / 5));
                     let result = rt.block_on(async {
-                        eng.search_from(r"IPv4: 192\.168\.1\.\d{1,3}", start_byte, &options, None)
-                            .await
+                        eng.search_from(
+                            r"IPv4: 192\.168\.1\.\d{1,3}",
+                            start_byte,
+                            &options,
+                            None,
+                            None,
+                        )
+                        .await
                     });
                     let _ = black_box(result);
                 });
@@ -539,7 +570,8 @@ fn bench_random_start_positions(c: &mut Criterion) {
                     // For backward search, start from middle to end of file
                     let start_byte = rng.gen_range(file_size / 2..file_size);
                     let result = rt.block_on(async {
-                        eng.search_prev("timeout", start_byte, &options, None).await
+                        eng.search_prev("timeout", start_byte, &options, None, None)
+                            .await
                     });
                     let _ = black_box(result);
                 });
@@ -558,12 +590,319 @@ fn bench_random_start_positions(c: &mut Criterion) {
                 b.iter(|| {
                     let start_byte = rng.gen_range(file_size / 4..3 * file_size / 4);
                     let result = rt.block_on(async {
-                        eng.search_from("ERROR", start_byte, &options, None).await
+                        eng.search_from("ERROR", start_byte, &options, None, None)
+                            .await
                     });
                     let _ = black_box(result);
                 });
             },
         );
+
+        // Test 6: Pattern that is not in the file, from the top, on one thread and split across
+        // threads; only mapped files big enough to split show a difference
+        for (label, parallel) in [("rare_single", false), ("rare_parallel", true)] {
+            group.bench_with_input(BenchmarkId::new(label, &size_label), &engine, |b, eng| {
+                let options = SearchOptions {
+                    parallel,
+                    ..Default::default()
+                };
+                b.iter(|| {
+                    let result = rt.block_on(async {
+                        eng.search_from(r"session=\d+ expired", 0, &options, None, None)
+                            .await
+                    });
+                    let _ = black_box(result);
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// Start a search worker over `path` on `rt`; it runs whenever the runtime is driven.
+fn spawn_worker(
+    rt: &Runtime,
+    path: &std::path::Path,
+) -> (mpsc::Sender<SearchCommand>, mpsc::Receiver<SearchResponse>) {
+    rt.block_on(async {
+        let accessor: Arc<dyn FileAccessor> =
+            Arc::new(FileAccessorFactory::create(path).await.unwrap());
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        let (cmd_tx, cmd_rx) = mpsc::channel(4);
+        let (resp_tx, resp_rx) = mpsc::channel(4);
+        tokio::spawn(search_worker_loop(
+            cmd_rx,
+            resp_tx,
+            accessor,
+            engine,
+            MemoryBudget::default(),
+        ));
+        (cmd_tx, resp_rx)
+    })
+}
+
+/// Send one `LoadViewport` to the worker and return the resolved top byte and EOF flag.
+fn load_viewport(
+    rt: &Runtime,
+    cmd_tx: &mpsc::Sender<SearchCommand>,
+    resp_rx: &mut mpsc::Receiver<SearchResponse>,
+    request_id: RequestId,
+    top: ViewportRequest,
+    highlights: &Arc<SearchHighlightSpec>,
+) -> (u64, bool) {
+    rt.block_on(async {
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top,
+                page_lines: 50,
+                highlights: Some(Arc::clone(highlights)),
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match resp_rx.recv().await {
+            Some(SearchResponse::ViewportLoaded {
+                top_byte, at_eof, ..
+            }) => (top_byte, at_eof),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    })
+}
+
+fn bench_viewport_highlights(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("viewport_highlights");
+    group.sample_size(10);
+
+    let fixture = create_fixture(5 * MB, 100);
+    let file_size = std::fs::metadata(fixture.path()).unwrap().len();
+    let (cmd_tx, mut resp_rx) = spawn_worker(&rt, fixture.path());
+    // Matches on every line, so each highlighted line costs a full matcher pass
+    let spec = Arc::new(SearchHighlightSpec {
+        pattern: Arc::from(r"Thread-\d+|user_\d+"),
+        options: SearchOptions {
+            regex_mode: true,
+            ..Default::default()
+        },
+        modifiers: PatternModifiers::default(),
+    });
+    let mut request_id: RequestId = 0;
+
+    // Held-key scrolling: all but one line of each viewport was highlighted for the previous one
+    group.bench_function(BenchmarkId::new("scroll_one_line", "5MB"), |b| {
+        let mut anchor = 0;
+        b.iter(|| {
+            request_id += 1;
+            let top = ViewportRequest::RelativeLines { anchor, lines: 1 };
+            let (top_byte, at_eof) =
+                load_viewport(&rt, &cmd_tx, &mut resp_rx, request_id, top, &spec);
+            anchor = if at_eof { 0 } else { top_byte };
+            black_box(top_byte);
+        });
+    });
+
+    // Random jumps never reuse a previous viewport and pay for every line
+    group.bench_function(BenchmarkId::new("random_jump", "5MB"), |b| {
+        let mut rng = ChaCha8Rng::seed_from_u64(47);
+        b.iter(|| {
+            request_id += 1;
+            let top = ViewportRequest::Absolute(rng.gen_range(0..file_size));
+            let result = load_viewport(&rt, &cmd_tx, &mut resp_rx, request_id, top, &spec);
+            black_box(result);
+        });
+    });
+
+    // Pages of 4KB lines, where a `String` per row used to dominate matching
+    let wide = NamedTempFile::new().unwrap();
+    let mut content = String::with_capacity(4 * MB);
+    for line_num in 0..1000 {
+        let mut line = format!(
+            "2024-01-01T10:00:00.000 [Thread-{:02}] [INFO ] api - ",
+            line_num % 16 + 1
+        );
+        while line.len() < 4 * KB {
+            let _ = write!(line, "user_{:04} payload ", line.len() % 10000);
+        }
+        // Exactly 4KB with the newline, so row starts are multiples of 4KB
+        line.truncate(4 * KB - 1);
+        content.push_str(&line);
+        content.push('\n');
+    }
+    std::fs::write(wide.path(), &content).unwrap();
+    let wide_size = content.len() as u64;
+    let (wide_tx, mut wide_rx) = spawn_worker(&rt, wide.path());
+    group.bench_function(BenchmarkId::new("random_jump", "50x4KB"), |b| {
+        let mut rng = ChaCha8Rng::seed_from_u64(47);
+        b.iter(|| {
+            request_id += 1;
+            let top = ViewportRequest::Absolute(rng.gen_range(0..wide_size));
+            let result = load_viewport(&rt, &wide_tx, &mut wide_rx, request_id, top, &spec);
+            black_box(result);
+        });
+    });
+
+    // The matching alone: a page in place against a `String` per row
+    let accessor: Arc<dyn FileAccessor> =
+        rt.block_on(async { Arc::new(FileAccessorFactory::create(wide.path()).await.unwrap()) });
+    let engine = RipgrepEngine::new(Arc::clone(&accessor));
+    group.bench_function(BenchmarkId::new("page_matches", "50x4KB"), |b| {
+        let mut rng = ChaCha8Rng::seed_from_u64(48);
+        b.iter(|| {
+            let top = rng.gen_range(0..1000u64 - 50) * 4 * KB as u64;
+            let page = rt.block_on(accessor.read_from_byte_raw(top, 50)).unwrap();
+            let matches = engine
+                .get_matches_in(&spec.pattern, &page.bytes, &page.rows, &spec.options)
+                .unwrap();
+            black_box(matches);
+        });
+    });
+    group.bench_function(BenchmarkId::new("line_matches", "50x4KB"), |b| {
+        let mut rng = ChaCha8Rng::seed_from_u64(48);
+        b.iter(|| {
+            let top = rng.gen_range(0..1000u64 - 50) * 4 * KB as u64;
+            let lines = rt.block_on(accessor.read_from_byte(top, 50)).unwrap();
+            let matches: Vec<_> = lines
+                .iter()
+                .map(|line| {
+                    engine
+                        .get_line_matches(&spec.pattern, line, &spec.options)
+                        .unwrap()
+                })
+                .collect();
+            black_box(matches);
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_dense_highlights(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("dense_highlights");
+    group.sample_size(10);
+
+    // 20KB lines where a single-letter pattern matches thousands of times per line
+    let fixture = NamedTempFile::new().unwrap();
+    let line = "the tree sees every edge ".repeat(20 * KB / 25);
+    std::fs::write(fixture.path(), format!("{line}\n").repeat(200)).unwrap();
+    let file_size = std::fs::metadata(fixture.path()).unwrap().len();
+    let (cmd_tx, mut resp_rx) = spawn_worker(&rt, fixture.path());
+    let spec = Arc::new(SearchHighlightSpec {
+        pattern: Arc::from("e"),
+        options: SearchOptions::default(),
+        modifiers: PatternModifiers::default(),
+    });
+    let mut request_id: RequestId = 0;
+
+    group.bench_function(BenchmarkId::new("random_jump", "4MB"), |b| {
+        let mut rng = ChaCha8Rng::seed_from_u64(47);
+        b.iter(|| {
+            request_id += 1;
+            let top = ViewportRequest::Absolute(rng.gen_range(0..file_size));
+            let result = load_viewport(&rt, &cmd_tx, &mut resp_rx, request_id, top, &spec);
+            black_box(result);
+        });
+    });
+
+    // Drawing a frame of the densest viewport: every row carries the maximum span count
+    let mut view_state = ViewState::new(fixture.path(), 200, 51);
+    rt.block_on(async {
+        request_id += 1;
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top: ViewportRequest::Absolute(0),
+                page_lines: 50,
+                highlights: Some(Arc::clone(&spec)),
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match resp_rx.recv().await {
+            Some(SearchResponse::ViewportLoaded {
+                lines, highlights, ..
+            }) => view_state.update_viewport_content(lines, highlights),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    });
+    let theme = ColorTheme::default();
+    let mut terminal = Terminal::new(TestBackend::new(200, 50)).unwrap();
+    group.bench_function(BenchmarkId::new("render_frame", "50x4KB"), |b| {
+        b.iter(|| {
+            terminal
+                .draw(|frame| {
+                    let lines = TerminalUI::content_lines(&view_state, &theme);
+                    frame.render_widget(Paragraph::new(lines), frame.size());
+                })
+                .unwrap();
+        });
+    });
+
+    // Segment-long rows of wide and combining characters, the slowest to cut at the screen width
+    let wide_row: String = "日志行 e\u{301}rror "
+        .chars()
+        .cycle()
+        .scan(0, |len, ch| {
+            *len += ch.len_utf8();
+            (*len <= SEGMENT_BYTES).then_some(ch)
+        })
+        .collect();
+    let spans: Vec<_> = wide_row
+        .char_indices()
+        .step_by(16)
+        .map(|(start, ch)| (start, start + ch.len_utf8(), HighlightKind::Search))
+        .collect();
+    let mut view_state = ViewState::new(fixture.path(), 200, 51);
+    view_state.update_viewport_content(vec![wide_row; 50], vec![spans; 50]);
+    group.bench_function(BenchmarkId::new("render_frame", "50x4KB-wide"), |b| {
+        b.iter(|| {
+            terminal
+                .draw(|frame| {
+                    let lines = TerminalUI::content_lines(&view_state, &theme);
+                    frame.render_widget(Paragraph::new(lines), frame.size());
+                })
+                .unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_pattern_sets(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("pattern_sets");
+    group.sample_size(10);
+
+    let fixture = create_fixture(5 * MB, 100);
+    let accessor =
+        rt.block_on(async { FileAccessorFactory::create(fixture.path()).await.unwrap() });
+    let engine = RipgrepEngine::new(Arc::new(accessor) as Arc<dyn FileAccessor>);
+    // A thousand session ids; only the last one is near the end of the file
+    let mut set: Vec<String> = (0..999u64)
+        .map(|i| format!("sess_{:08x}", 0xf000_0000 + i))
+        .collect();
+    set.push("sess_00009c40".to_string());
+    let set = set.join("\n");
+
+    for (name, regex_mode) in [("aho_corasick", false), ("alternation", true)] {
+        let options = SearchOptions {
+            regex_mode,
+            pattern_set: true,
+            ..Default::default()
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let result =
+                    rt.block_on(async { engine.search_from(&set, 0, &options, None, None).await });
+                let _ = black_box(result);
+            });
+        });
     }
 
     group.finish();
@@ -575,6 +914,9 @@ criterion_group!(
     bench_search_navigation,
     bench_search_caching,
     bench_complex_regex_patterns,
-    bench_random_start_positions
+    bench_random_start_positions,
+    bench_viewport_highlights,
+    bench_dense_highlights,
+    bench_pattern_sets
 );
 criterion_main!(benches);
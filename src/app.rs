@@ -4,17 +4,26 @@
 //! delegates input handling and heavy data operations to background tasks while
 //! keeping rendering single-threaded.
 
+use crate::bookmarks::{dropped_notice, store_path, Bookmarks};
 use crate::error::{Result, RllessError};
-use crate::file_handler::{FileAccessor, FileAccessorFactory};
+use crate::file_handler::{FileAccessor, FileAccessorFactory, LineTerminator};
 use crate::input::spawn_input_thread;
-use crate::input::InputAction;
-use crate::render::protocol::SearchHighlightSpec;
+use crate::input::{InputAction, PrecisionScroll, SearchDirection, SharedKeymap};
 use crate::render::protocol::{RequestId, SearchCommand, SearchResponse, ViewportRequest};
+use crate::render::protocol::{SearchContext, SearchHighlightSpec};
 use crate::render::service::{RenderCoordinator, RenderLoopState};
-use crate::render::ui::{UIRenderer, ViewState};
+use crate::render::ui::codepoints::DEFAULT_PLACEHOLDER;
+use crate::render::ui::{
+    ChromeMode, InvalidKeyFeedback, JumpTarget, PositionStyle, UIRenderer, ViewState,
+};
 use crate::search::worker::search_worker_loop;
-use crate::search::{RipgrepEngine, SearchOptions};
-use std::path::Path;
+use crate::search::{
+    MemoryBudget, PatternLibrary, PatternModifiers, PatternSet, RipgrepEngine, SearchOptions,
+};
+use crate::settings::Settings;
+use crate::util::CancellationToken;
+use grep_regex::RegexMatcher;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
@@ -25,22 +34,256 @@ pub struct Application {
     file_accessor: Arc<dyn FileAccessor>,
     ui_renderer: Box<dyn UIRenderer>,
     render_state: RenderLoopState,
+    position_style: PositionStyle,
+    chrome: ChromeMode,
+    show_active_pattern: bool,
+    placeholder: Option<char>,
+    follow: bool,
+    // Searched for once the first page is shown; see `with_pattern_set`
+    pattern_set: Option<PatternSet>,
+    history_exclude: Vec<RegexMatcher>,
+    keymap: SharedKeymap,
+    precision_scroll: PrecisionScroll,
+    line_numbers: bool,
+    wrap: bool,
+    start_byte: u64,
+    start_search: Option<Arc<SearchHighlightSpec>>,
+    cache_budget: MemoryBudget,
+    // Where `:bookmark` labels are kept between runs; see `with_state_dir`
+    state_dir: Option<PathBuf>,
 }
 
 impl Application {
     /// Create application by initializing and wiring components together
+    ///
+    /// Lines end at `terminator`. With `low_memory` set, an in-memory source is spilled to a
+    /// temp-file mapping in the background once loaded.
     pub async fn new(
         file_path: &Path,
         ui_renderer: Box<dyn UIRenderer>,
         search_options: SearchOptions,
+        low_memory: bool,
+        terminator: LineTerminator,
     ) -> Result<Self> {
-        let file_accessor: Arc<dyn FileAccessor> =
-            Arc::new(FileAccessorFactory::create(file_path).await?);
-        Ok(Self {
+        let accessor = Arc::new(
+            FileAccessorFactory::create(file_path)
+                .await?
+                .with_line_terminator(terminator),
+        );
+        if low_memory {
+            let spill_accessor = Arc::clone(&accessor);
+            tokio::spawn(async move {
+                // Best effort: on failure the content simply stays in memory.
+                let _ = spill_accessor.spill_to_disk().await;
+            });
+        }
+
+        Ok(Self::with_accessor(accessor, ui_renderer, search_options))
+    }
+
+    /// Create application showing piped input as it arrives on stdin
+    ///
+    /// With `low_memory` set, the input is collected in a temp file rather than in memory.
+    pub fn from_stdin(
+        ui_renderer: Box<dyn UIRenderer>,
+        search_options: SearchOptions,
+        low_memory: bool,
+        terminator: LineTerminator,
+    ) -> Self {
+        let (accessor, input) = FileAccessorFactory::create_from_stdin(low_memory, terminator);
+        let mut app = Self::with_accessor(accessor, ui_renderer, search_options);
+        app.render_state.watch_input(input.subscribe());
+        app
+    }
+
+    fn with_accessor(
+        file_accessor: Arc<dyn FileAccessor>,
+        ui_renderer: Box<dyn UIRenderer>,
+        search_options: SearchOptions,
+    ) -> Self {
+        let cache_budget = MemoryBudget::default();
+        let mut render_state = RenderLoopState::new(search_options);
+        render_state.set_cache_budget(cache_budget.clone());
+        Self {
             file_accessor,
             ui_renderer,
-            render_state: RenderLoopState::new(search_options),
-        })
+            render_state,
+            position_style: PositionStyle::default(),
+            chrome: ChromeMode::default(),
+            show_active_pattern: false,
+            placeholder: Some(DEFAULT_PLACEHOLDER),
+            follow: false,
+            pattern_set: None,
+            history_exclude: Vec::new(),
+            keymap: SharedKeymap::default(),
+            precision_scroll: PrecisionScroll::default(),
+            line_numbers: false,
+            wrap: false,
+            start_byte: 0,
+            start_search: None,
+            cache_budget,
+            state_dir: None,
+        }
+    }
+
+    /// Start with the status line position shown in `style` (`-P` cycles it at runtime)
+    pub fn with_position_style(mut self, style: PositionStyle) -> Self {
+        self.position_style = style;
+        self
+    }
+
+    /// Start in `chrome` mode (`P` toggles presentation mode at runtime)
+    pub fn with_chrome(mut self, chrome: ChromeMode) -> Self {
+        self.chrome = chrome;
+        self
+    }
+
+    /// Keep the active search pattern at the right of the status line
+    pub fn with_show_active_pattern(mut self, show: bool) -> Self {
+        self.show_active_pattern = show;
+        self
+    }
+
+    /// Character drawn in place of codepoints terminals disagree about, or `None` to draw them
+    /// as they are
+    pub fn with_placeholder(mut self, placeholder: Option<char>) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+
+    /// Start out following data appended to the file (`F` toggles it at runtime)
+    pub fn with_follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// Ring the terminal bell when data arriving while following matches the active search
+    pub fn with_match_bell(mut self, enabled: bool) -> Self {
+        self.render_state.set_match_bell(enabled);
+        self
+    }
+
+    /// Answer keys that do nothing with the bell, a status line flash or nothing at all
+    pub fn with_invalid_key_feedback(mut self, feedback: InvalidKeyFeedback) -> Self {
+        self.render_state.set_invalid_key_feedback(feedback);
+        self
+    }
+
+    /// Put the matching line of a search jump on the row `target` asks for
+    pub fn with_jump_target(mut self, target: JumpTarget) -> Self {
+        self.render_state.set_jump_target(target);
+        self
+    }
+
+    /// Say once when the file grows while not following it
+    pub fn with_growth_hint(mut self, enabled: bool) -> Self {
+        self.render_state.set_growth_hint(enabled);
+        self
+    }
+
+    /// Jump to the first match of the search pattern typed so far, undone by Esc
+    pub fn with_incremental_search(mut self, enabled: bool) -> Self {
+        self.render_state.set_incremental_search(enabled);
+        self
+    }
+
+    /// While following the tail, move on to a newer file matching `pattern` when one appears
+    pub fn with_latest(mut self, pattern: &Path) -> Self {
+        self.render_state.set_latest(pattern.to_path_buf());
+        self
+    }
+
+    /// Start by searching for any entry of `set`
+    pub fn with_pattern_set(mut self, set: PatternSet) -> Self {
+        self.pattern_set = Some(set);
+        self
+    }
+
+    /// Never record search patterns matching any of `exclude` in the search history
+    pub fn with_history_exclude(mut self, exclude: Vec<RegexMatcher>) -> Self {
+        self.history_exclude = exclude;
+        self
+    }
+
+    /// Resolve navigation keys through `keymap` before the built-in bindings
+    pub fn with_keymap(mut self, keymap: SharedKeymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// Confirm new searches whose pattern matches more than `limit` times per megabyte near the
+    /// viewport; `None` turns the check off
+    pub fn with_frequent_match_limit(mut self, limit: Option<u64>) -> Self {
+        self.render_state.set_frequent_match_limit(limit);
+        self
+    }
+
+    /// Start log records (`[`/`]`) at lines matching the regex `pattern`
+    pub fn with_record_start(mut self, pattern: &str) -> Self {
+        self.render_state.set_record_start(pattern);
+        self
+    }
+
+    /// Named patterns that searches can use as `@name`
+    pub fn with_pattern_library(mut self, patterns: PatternLibrary) -> Self {
+        self.render_state.set_pattern_library(patterns);
+        self
+    }
+
+    /// Start with wheel notches moving one line each, unmerged (`-m` toggles it at runtime)
+    pub fn with_precise_scroll(mut self, enabled: bool) -> Self {
+        self.precision_scroll = PrecisionScroll::new(enabled);
+        self.render_state
+            .set_precision_scroll(self.precision_scroll.clone());
+        self
+    }
+
+    /// Start with the line-number gutter shown (`-N` toggles it at runtime)
+    pub fn with_line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
+        self
+    }
+
+    /// Start with long lines wrapped onto several rows (`-S` toggles it at runtime)
+    pub fn with_wrap(mut self, enabled: bool) -> Self {
+        self.wrap = enabled;
+        self
+    }
+
+    /// Open the file at the line containing `byte` instead of the top (a `:loc` locator)
+    pub fn with_start_byte(mut self, byte: u64) -> Self {
+        self.start_byte = byte;
+        self
+    }
+
+    /// Start with `pattern` as the active search, highlighted and ready for `n` and `N`
+    pub fn with_search(mut self, pattern: &str, options: SearchOptions) -> Self {
+        self.start_search = Some(Arc::new(SearchHighlightSpec {
+            pattern: Arc::from(pattern),
+            options,
+            modifiers: PatternModifiers::default(),
+        }));
+        self
+    }
+
+    /// Cap the memory held by the search worker's caches at `bytes`
+    pub fn with_cache_budget(mut self, bytes: usize) -> Self {
+        self.cache_budget = MemoryBudget::new(bytes);
+        self.render_state
+            .set_cache_budget(self.cache_budget.clone());
+        self
+    }
+
+    /// Keep each file's bookmarks under `dir` between runs; `None` keeps them for this run only
+    pub fn with_state_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.state_dir = dir;
+        self
+    }
+
+    /// Startup settings and their provenance, listed by `:options`
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.render_state.set_settings(settings);
+        self
     }
 
     /// Run the application using the multi-threaded input/search architecture
@@ -50,14 +293,29 @@ impl Application {
         let (width, height) = self.ui_renderer.get_terminal_size()?;
         let file_path = self.file_accessor.file_path().to_path_buf();
         let mut view_state = ViewState::new(file_path, width, height);
+        view_state.position_style = self.position_style;
+        view_state.chrome = self.chrome;
+        view_state.show_active_pattern = self.show_active_pattern;
+        view_state.placeholder = self.placeholder;
+        view_state.status_line.precise_scroll = self.precision_scroll.is_enabled();
+        view_state.set_line_numbers(self.line_numbers);
+        view_state.set_wrap(self.wrap);
+        view_state.set_sections(self.file_accessor.sections().to_vec());
+        let bookmark_notice = self.load_bookmarks(&mut view_state);
 
         let (input_tx, mut input_rx) = mpsc::unbounded_channel::<InputAction>();
         let (mut search_tx, search_rx) = mpsc::channel::<SearchCommand>(64);
         let (search_resp_tx, mut search_resp_rx) = mpsc::channel::<SearchResponse>(64);
 
         let shutdown_flag = Arc::new(AtomicBool::new(false));
-        let input_thread =
-            spawn_input_thread(input_tx, shutdown_flag.clone(), Duration::from_millis(12));
+        let input_thread = spawn_input_thread(
+            input_tx,
+            shutdown_flag.clone(),
+            Duration::from_millis(12),
+            self.history_exclude.clone(),
+            self.keymap.clone(),
+            self.precision_scroll.clone(),
+        );
 
         let worker_accessor = Arc::clone(&self.file_accessor);
         let worker_engine = RipgrepEngine::new(Arc::clone(&self.file_accessor));
@@ -66,25 +324,61 @@ impl Application {
             search_resp_tx,
             worker_accessor,
             worker_engine,
+            self.cache_budget.clone(),
         ));
 
         let mut next_request_id: RequestId = 1;
         #[allow(unused_assignments)]
         let mut latest_view_request: Option<RequestId> = None;
         let mut latest_search_request: Option<RequestId> = None;
-        let mut search_cancel_flag: Option<Arc<AtomicBool>> = None;
+        let mut search_cancel_flag: Option<CancellationToken> = None;
         let mut pending_search_state: Option<(RequestId, Arc<SearchHighlightSpec>)> = None;
 
-        // Prime the viewport with initial content
+        // Prime the viewport with initial content, or with the end of the file when following
+        let initial_top = if self.follow {
+            self.render_state.set_following(true, &mut view_state);
+            ViewportRequest::EndOfFile
+        } else {
+            ViewportRequest::Absolute(self.start_byte)
+        };
+        if let Some(search) = self.start_search.take() {
+            self.render_state
+                .set_search(Arc::clone(&search), &mut view_state);
+            search_tx
+                .send(SearchCommand::UpdateSearchContext(SearchContext {
+                    pattern: Arc::clone(&search.pattern),
+                    direction: SearchDirection::Forward,
+                    options: search.options.clone(),
+                    last_match_byte: None,
+                }))
+                .await
+                .map_err(|_| RllessError::other("search worker unavailable"))?;
+        }
+        if self.line_numbers {
+            search_tx
+                .send(SearchCommand::ShowLineNumbers(true))
+                .await
+                .map_err(|_| RllessError::other("search worker unavailable"))?;
+        }
+        if self.position_style == PositionStyle::Lines {
+            search_tx
+                .send(SearchCommand::ShowLinePosition(true))
+                .await
+                .map_err(|_| RllessError::other("search worker unavailable"))?;
+        }
+        view_state.expect_page(initial_top);
         let initial_req = next_request_id;
         next_request_id += 1;
         latest_view_request = Some(initial_req);
         search_tx
             .send(SearchCommand::LoadViewport {
                 request_id: initial_req,
-                top: ViewportRequest::Absolute(0),
+                top: initial_top,
                 page_lines: view_state.lines_per_page() as usize,
                 highlights: self.render_state.highlight_spec(),
+                filter: self.render_state.filter_spec(),
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
             })
             .await
             .map_err(|_| RllessError::other("search worker unavailable"))?;
@@ -103,6 +397,22 @@ impl Application {
                 )
                 .await?;
         }
+        if let Some(notice) = bookmark_notice {
+            view_state.status_line.set_message(notice);
+        }
+        if let Some(set) = self.pattern_set.take() {
+            self.render_state
+                .search_pattern_set(
+                    &set,
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                )
+                .await?;
+        }
 
         RenderCoordinator::run(
             &mut self.render_state,
@@ -121,11 +431,33 @@ impl Application {
 
         // Graceful shutdown
         shutdown_flag.store(true, Ordering::SeqCst);
-        let _ = search_tx.send(SearchCommand::Shutdown).await;
+        if search_tx.try_send(SearchCommand::Shutdown).is_err() {
+            // The worker is behind a full queue; don't make quitting wait for it
+            search_handle.abort();
+        }
         search_handle.await.ok();
         let _ = input_thread.join();
 
         self.ui_renderer.cleanup()?;
         Ok(())
     }
+
+    /// Start with the file's saved bookmarks, returning a notice when some no longer fit in it
+    fn load_bookmarks(&mut self, view_state: &mut ViewState) -> Option<String> {
+        let (Some(state_dir), Some(identity)) = (&self.state_dir, self.file_accessor.identity())
+        else {
+            return None;
+        };
+        let store = store_path(state_dir, identity);
+        match Bookmarks::load(store, self.file_accessor.file_size()) {
+            Ok((bookmarks, dropped)) => {
+                self.render_state.set_bookmarks(bookmarks, view_state);
+                (dropped > 0).then(|| dropped_notice(dropped))
+            }
+            Err(error) => {
+                log::warn!("app: loading bookmarks failed: {}", error.chain());
+                None
+            }
+        }
+    }
 }
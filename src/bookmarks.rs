@@ -0,0 +1,232 @@
+//! Named bookmarks (`:bookmark`), kept per file between sessions.
+//!
+//! A bookmark labels the line starting at a byte offset. Each file's bookmarks are stored in a
+//! file of their own under the state directory, named by a hash of the file's canonical path, so
+//! opening the file by any path finds them. A store that does not parse is moved aside by
+//! `load_lenient` and the file starts without bookmarks.
+//!
+//! The store is plain text: a `#` line naming the file, then one `byte<TAB>label` line per
+//! bookmark in file order.
+
+use crate::error::{Result, RllessError};
+use crate::file_handler::identity::FileIdentity;
+use crate::util::{load_lenient, write_atomic};
+use std::path::{Path, PathBuf};
+
+/// A labelled line, by the byte offset it starts at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub byte: u64,
+    pub label: String,
+}
+
+/// One file's bookmarks in file order, and where they are saved
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    entries: Vec<Bookmark>,
+    /// `None` for input with no file behind it, whose bookmarks last only this session
+    store: Option<PathBuf>,
+}
+
+impl Bookmarks {
+    /// Bookmarks saved in `store`, without those at or past `file_size`
+    ///
+    /// Returns how many were dropped: the file shrank or was replaced since they were added.
+    pub fn load(store: PathBuf, file_size: u64) -> Result<(Self, usize)> {
+        let entries = load_lenient(&store, parse)?.unwrap_or_default();
+        let mut bookmarks = Self {
+            entries,
+            store: Some(store),
+        };
+        let dropped = bookmarks.drop_past(file_size);
+        Ok((bookmarks, dropped))
+    }
+
+    pub fn entries(&self) -> &[Bookmark] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Bookmark number `number`, counting from 1 in file order
+    pub fn get(&self, number: usize) -> Option<&Bookmark> {
+        self.entries.get(number.checked_sub(1)?)
+    }
+
+    /// Offsets of every bookmark, in file order
+    pub fn offsets(&self) -> Vec<u64> {
+        self.entries.iter().map(|bookmark| bookmark.byte).collect()
+    }
+
+    /// Label the line at `byte`, replacing its label if it has one, and return its number
+    ///
+    /// Tabs and line breaks in `label` become spaces, as the store keeps one bookmark per line.
+    pub fn add(&mut self, byte: u64, label: &str) -> usize {
+        let label: String = label
+            .chars()
+            .map(|ch| if ch.is_control() { ' ' } else { ch })
+            .collect();
+        let index = self
+            .entries
+            .partition_point(|bookmark| bookmark.byte < byte);
+        match self.entries.get_mut(index) {
+            Some(existing) if existing.byte == byte => existing.label = label,
+            _ => self.entries.insert(index, Bookmark { byte, label }),
+        }
+        index + 1
+    }
+
+    /// Forget bookmarks at or past `file_size`, returning how many there were
+    pub fn drop_past(&mut self, file_size: u64) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|bookmark| bookmark.byte < file_size);
+        before - self.entries.len()
+    }
+
+    /// Write the bookmarks to their store, if they have one
+    pub fn save(&self, file: &Path) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        if let Some(dir) = store.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                RllessError::file_error(format!("Failed to create {}", dir.display()), e)
+            })?;
+        }
+        let mut text = format!("# rlless bookmarks for {}\n", file.display());
+        for bookmark in &self.entries {
+            text.push_str(&format!("{}\t{}\n", bookmark.byte, bookmark.label));
+        }
+        write_atomic(store, text.as_bytes())
+    }
+}
+
+/// Status line notice for `count` bookmarks dropped by `drop_past`
+pub fn dropped_notice(count: usize) -> String {
+    match count {
+        1 => "1 bookmark past the end of the file dropped".to_string(),
+        _ => format!("{} bookmarks past the end of the file dropped", count),
+    }
+}
+
+fn parse(bytes: &[u8]) -> std::result::Result<Vec<Bookmark>, String> {
+    let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+    let mut entries: Vec<Bookmark> = text
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.is_empty())
+        .map(|line| {
+            let (byte, label) = line
+                .split_once('\t')
+                .ok_or_else(|| format!("no tab in {:?}", line))?;
+            let byte = byte
+                .parse()
+                .map_err(|_| format!("bad offset in {:?}", line))?;
+            Ok(Bookmark {
+                byte,
+                label: label.to_string(),
+            })
+        })
+        .collect::<std::result::Result<_, String>>()?;
+    entries.sort_by_key(|bookmark| bookmark.byte);
+    entries.dedup_by_key(|bookmark| bookmark.byte);
+    Ok(entries)
+}
+
+/// Where the bookmarks of the file `identity` are saved under `state_dir`
+pub fn store_path(state_dir: &Path, identity: &FileIdentity) -> PathBuf {
+    // FNV-1a: stable across Rust releases, unlike the standard library's hasher
+    let hash = identity
+        .canonical_path
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    state_dir.join("bookmarks").join(format!("{:016x}", hash))
+}
+
+/// Directory for files rlless keeps between runs: `$XDG_STATE_HOME/rlless`, or
+/// `~/.local/state/rlless`; `None` when neither variable is set
+pub fn state_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(base.join("rlless"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn bookmarks_are_kept_in_file_order_and_numbered_from_one() {
+        let mut bookmarks = Bookmarks::default();
+        assert_eq!(bookmarks.add(500, "suspect GC pause"), 1);
+        assert_eq!(bookmarks.add(100, "deploy\tstarted"), 1);
+        assert_eq!(bookmarks.add(500, "GC pause"), 2);
+        assert_eq!(bookmarks.offsets(), [100, 500]);
+        assert_eq!(bookmarks.get(1).unwrap().label, "deploy started");
+        assert_eq!(bookmarks.get(2).unwrap().label, "GC pause");
+        assert_eq!(bookmarks.get(0), None);
+        assert_eq!(bookmarks.get(3), None);
+        // Nowhere to save them
+        bookmarks.save(Path::new("app.log")).unwrap();
+    }
+
+    #[test]
+    fn bookmarks_persist_and_drop_offsets_past_a_shrunk_file() {
+        let dir = TempDir::new().unwrap();
+        let store = dir.path().join("bookmarks").join("0123");
+        let (mut bookmarks, dropped) = Bookmarks::load(store.clone(), 1000).unwrap();
+        assert!(bookmarks.is_empty());
+        assert_eq!(dropped, 0);
+
+        bookmarks.add(10, "first");
+        bookmarks.add(900, "near the end");
+        bookmarks.save(Path::new("/var/log/app.log")).unwrap();
+        let text = std::fs::read_to_string(&store).unwrap();
+        assert_eq!(
+            text,
+            "# rlless bookmarks for /var/log/app.log\n10\tfirst\n900\tnear the end\n"
+        );
+
+        let (reloaded, dropped) = Bookmarks::load(store.clone(), 1000).unwrap();
+        assert_eq!(reloaded.entries(), bookmarks.entries());
+        assert_eq!(dropped, 0);
+
+        let (mut truncated, dropped) = Bookmarks::load(store, 500).unwrap();
+        assert_eq!(dropped, 1);
+        assert_eq!(truncated.offsets(), [10]);
+        assert_eq!(truncated.drop_past(5), 1);
+        assert!(truncated.is_empty());
+    }
+
+    #[test]
+    fn unreadable_store_starts_empty() {
+        let dir = TempDir::new().unwrap();
+        let store = dir.path().join("store");
+        std::fs::write(&store, "not a bookmark\n").unwrap();
+        let (bookmarks, _) = Bookmarks::load(store.clone(), 100).unwrap();
+        assert!(bookmarks.is_empty());
+        assert!(!store.exists());
+    }
+
+    #[test]
+    fn store_is_named_by_the_canonical_path() {
+        let identity = |path: &str| FileIdentity {
+            canonical_path: PathBuf::from(path),
+            key: crate::file_handler::identity::FileKey::Path(PathBuf::from(path)),
+        };
+        let dir = Path::new("/state");
+        let store = store_path(dir, &identity("/var/log/app.log"));
+        assert!(store.starts_with("/state/bookmarks"));
+        assert_eq!(store, store_path(dir, &identity("/var/log/app.log")));
+        assert_ne!(store, store_path(dir, &identity("/var/log/app.log.1")));
+    }
+}
@@ -0,0 +1,297 @@
+//! Command-line arguments of the `rlless` binary.
+
+use clap::{Arg, ArgAction, ArgGroup, Command};
+use rlless::render::service::DEFAULT_RECORD_START;
+use rlless::render::ui::codepoints::parse_placeholder;
+use rlless::render::ui::JumpTarget;
+use std::path::PathBuf;
+
+/// Every flag rlless accepts
+pub fn command() -> Command {
+    Command::new("rlless")
+        .version(rlless::VERSION)
+        .about("A high-performance terminal log viewer for large files")
+        .long_about(
+            "rlless is a terminal-based log viewer that can handle extremely large files \
+             (40GB+) with SIMD-optimized search and memory-efficient streaming.",
+        )
+        .arg(
+            Arg::new("file")
+                .help("Path to the log file to view; `-` or none reads piped input")
+                .index(1),
+        )
+        .arg(
+            Arg::new("latest")
+                .long("latest")
+                .value_name("PATTERN")
+                .help("Open the most recently modified file matching PATTERN, e.g. 'logs/app-*.log'")
+                .conflicts_with("file"),
+        )
+        .arg(
+            Arg::new("ignore-case")
+                .short('i')
+                .long("ignore-case")
+                .help("Perform case-insensitive searches by default")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("smart-case")
+                .long("smart-case")
+                .help("Ignore case unless the pattern has an uppercase character (toggle with -s)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("literal")
+                .long("literal")
+                .help("Treat search patterns as literal strings")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("regex")
+                .long("regex")
+                .help("Treat search patterns as regular expressions (default)")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("literal"),
+        )
+        .arg(
+            Arg::new("word")
+                .long("word")
+                .short('w')
+                .help("Match whole words only")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("multiline")
+                .long("multiline")
+                .short('U')
+                .help("Let searches match across line ends")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("low-memory")
+                .long("low-memory")
+                .help("Move files loaded into memory to a temp-file mapping after startup")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("position")
+                .long("position")
+                .value_name("STYLE")
+                .value_parser(["percent", "lines", "byte"])
+                .default_value("percent")
+                .help("How the status line shows the position (cycle at runtime with -P)"),
+        )
+        .arg(
+            Arg::new("presentation")
+                .long("presentation")
+                .help("Hide the status line for screen sharing (toggle at runtime with P)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("follow")
+                .long("follow")
+                .help("Start following data appended to the file, like tail -f (toggle with F)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("precise-scroll")
+                .long("precise-scroll")
+                .help("Scroll one line per mouse wheel notch, without merging bursts (toggle with -m)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("line-numbers")
+                .short('N')
+                .long("line-numbers")
+                .help("Show line numbers beside the text (toggle with -N)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("wrap")
+                .long("wrap")
+                .help("Wrap long lines onto several rows instead of cutting them (toggle with -S)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("jump-target")
+                .short('j')
+                .long("jump-target")
+                .value_name("N|center")
+                .value_parser(|value: &str| {
+                    JumpTarget::parse(value)
+                        .ok_or_else(|| format!("expected a number of lines or 'center', got '{}'", value))
+                })
+                .help("Keep N lines above a search match, or put it in the middle of the screen (change with -j)"),
+        )
+        .arg(
+            Arg::new("title")
+                .long("title")
+                .help("Name the file, filter and search in the terminal window title")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("null")
+                .long("null")
+                .help("Treat the input as NUL-separated records instead of newline-separated lines")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("show-active-pattern")
+                .long("show-active-pattern")
+                .help("Keep the active search pattern at the right of the status line")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-bell")
+                .long("no-bell")
+                .help("Don't ring the bell when data arriving in follow mode matches the search")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("invalid-key")
+                .long("invalid-key")
+                .value_name("FEEDBACK")
+                .value_parser(["bell", "flash", "none"])
+                .default_value("bell")
+                .help("How to answer a key that does nothing: ring the bell, flash the status line or nothing"),
+        )
+        .arg(
+            Arg::new("no-growth-hint")
+                .long("no-growth-hint")
+                .help("Don't point out that the file is growing while not following it")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-incsearch")
+                .long("no-incsearch")
+                .help("Don't jump to the first match while a search pattern is still being typed")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("placeholder")
+                .long("placeholder")
+                .value_name("CHAR")
+                .value_parser(parse_placeholder)
+                .help("Draw CHAR in place of private-use and unassigned codepoints (default \u{FFFD})"),
+        )
+        .arg(
+            Arg::new("no-placeholder")
+                .long("no-placeholder")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("placeholder")
+                .help("Draw private-use and unassigned codepoints as they are"),
+        )
+        .arg(
+            Arg::new("accessible")
+                .long("accessible")
+                .help("Line-mode pager for screen readers: no full-screen UI, commands read as lines")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("history-exclude")
+                .long("history-exclude")
+                .value_name("REGEX")
+                .action(ArgAction::Append)
+                .help("Search for patterns matching REGEX without keeping them in the history"),
+        )
+        .arg(
+            Arg::new("frequent-match-limit")
+                .long("frequent-match-limit")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("20000")
+                .help("Ask before searching for a pattern with more than N matches per MB (0 disables)"),
+        )
+        .arg(
+            Arg::new("record-start")
+                .long("record-start")
+                .value_name("REGEX")
+                .default_value(DEFAULT_RECORD_START)
+                .help("Lines matching REGEX start a log record, for the [ and ] keys"),
+        )
+        .arg(
+            Arg::new("pattern")
+                .long("pattern")
+                .value_name("NAME=REGEX")
+                .action(ArgAction::Append)
+                .help("Name REGEX so searches can use it as @NAME (see :patterns for built-ins)"),
+        )
+        .arg(
+            Arg::new("patterns-from")
+                .long("patterns-from")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Start by searching for any line of FILE, taken as fixed strings (like :loadset)"),
+        )
+        .arg(
+            Arg::new("keymap")
+                .long("keymap")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Load key bindings from FILE, one `chord command` pair per line"),
+        )
+        .arg(
+            Arg::new("cache-budget")
+                .long("cache-budget")
+                .value_name("MIB")
+                .value_parser(clap::value_parser!(u64).range(1..=4096))
+                .default_value("32")
+                .help("Memory the search worker's caches may hold, in MiB"),
+        )
+        .arg(
+            Arg::new("list-matches")
+                .long("list-matches")
+                .value_name("PATTERN")
+                .conflicts_with_all(["export-range", "multiline"])
+                .help("Print start byte, end byte, line number and line of every match, then exit"),
+        )
+        .arg(
+            Arg::new("max-matches")
+                .long("max-matches")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .requires("list-matches")
+                .help("Stop listing after N matches"),
+        )
+        .arg(
+            Arg::new("inspect")
+                .long("inspect")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("export-range")
+                .help("Report compression, encoding, line endings and an estimated line count from a sample, then exit"),
+        )
+        .group(ArgGroup::new("headless").args(["list-matches", "inspect"]))
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .requires("headless")
+                .help("Print --list-matches records or the --inspect report as JSON"),
+        )
+        .arg(
+            Arg::new("export-range")
+                .long("export-range")
+                .value_name("START:END")
+                .help("Write the raw bytes of a range to stdout and exit (END may be omitted)"),
+        )
+        .arg(
+            Arg::new("normalize-newlines")
+                .long("normalize-newlines")
+                .help("Convert CRLF to LF and terminate the last line when exporting")
+                .action(ArgAction::SetTrue)
+                .requires("export-range"),
+        )
+        .arg(
+            Arg::new("log-file")
+                .long("log-file")
+                .value_name("PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Append diagnostic logs to PATH (level from RUST_LOG, default info)"),
+        )
+        .arg(
+            Arg::new("log-sensitive")
+                .long("log-sensitive")
+                .action(ArgAction::SetTrue)
+                .help("Allow search patterns, line contents and regex crate records in diagnostic logs"),
+        )
+}
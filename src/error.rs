@@ -9,7 +9,14 @@
 //! - **Context preservation**: Include relevant information for debugging  
 //! - **Extensibility**: Easy to add new error variants as features grow
 //! - **Consistency**: Standardized Result type across all modules
+//!
+//! ## Rendering
+//!
+//! Errors wrap their cause through `source()`, so nothing is lost on the way up. The log gets the
+//! whole chain from `RllessError::chain` (`outer: middle: errno`); the status line gets the one
+//! clause from `RllessError::summary`.
 
+use std::error::Error as StdError;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -49,7 +56,16 @@ pub enum RllessError {
 
     /// Search operation errors
     #[error("Search operation failed: {message}")]
-    SearchError { message: String },
+    SearchError {
+        message: String,
+        #[source]
+        source: Option<Box<dyn StdError + Send + Sync>>,
+    },
+
+    /// A search pattern that does not parse as a regex; `position` is the 1-based character
+    /// where the problem starts
+    #[error("regex error: {message} at position {position}")]
+    Regex { message: String, position: usize },
 
     /// UI and terminal related errors
     #[error("UI operation failed: {message}")]
@@ -63,6 +79,14 @@ pub enum RllessError {
     #[error("Invalid argument: {message}")]
     InvalidArgument { message: String },
 
+    /// What was being attempted when another error occurred
+    #[error("{message}")]
+    Context {
+        message: String,
+        #[source]
+        source: Box<RllessError>,
+    },
+
     /// Cooperative cancellation request
     #[error("Operation cancelled")]
     Cancelled,
@@ -105,16 +129,18 @@ impl RllessError {
     pub fn search(message: impl Into<String>) -> Self {
         Self::SearchError {
             message: message.into(),
+            source: None,
         }
     }
 
     /// Create a SearchError with a descriptive message and source error
     pub fn search_error(
         message: impl Into<String>,
-        _source: Box<dyn std::error::Error + Send + Sync>,
+        source: Box<dyn StdError + Send + Sync>,
     ) -> Self {
         Self::SearchError {
             message: message.into(),
+            source: Some(source),
         }
     }
 
@@ -136,6 +162,61 @@ impl RllessError {
     pub fn cancelled() -> Self {
         Self::Cancelled
     }
+
+    /// Wrap this error with what was being attempted, e.g. `Failed to reopen the rotated file`
+    ///
+    /// Cancellation passes through unwrapped, so callers can keep matching on it.
+    pub fn context(self, message: impl Into<String>) -> Self {
+        match self {
+            Self::Cancelled => self,
+            source => Self::Context {
+                message: message.into(),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// The error followed by each of its causes, `outer: middle: errno`, for the log
+    pub fn chain(&self) -> String {
+        let mut rendered = self.to_string();
+        let mut cause = self.source();
+        while let Some(error) = cause {
+            let text = error.to_string();
+            // Messages that already quote their cause (`Invalid regex pattern: {e}`) add nothing
+            if !rendered.ends_with(&text) {
+                rendered.push_str(": ");
+                rendered.push_str(&text);
+            }
+            cause = error.source();
+        }
+        rendered
+    }
+
+    /// One clause for the status line: the outermost message, with the reason an I/O error
+    /// at the root of the chain gave
+    pub fn summary(&self) -> String {
+        let head = match self {
+            Self::FileError { message, .. } => message.clone(),
+            other => other.to_string(),
+        };
+        let mut root: &(dyn StdError + 'static) = self;
+        while let Some(cause) = root.source() {
+            root = cause;
+        }
+        match root.downcast_ref::<std::io::Error>() {
+            Some(io) => format!("{} ({})", head, io_reason(io)),
+            None => head,
+        }
+    }
+}
+
+/// `No such file or directory` rather than `No such file or directory (os error 2)`
+fn io_reason(error: &std::io::Error) -> String {
+    let text = error.to_string();
+    match text.rfind(" (os error ") {
+        Some(at) => text[..at].to_string(),
+        None => text,
+    }
 }
 
 // Automatic conversion from io::Error to RllessError
@@ -212,6 +293,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_nested_error_renderings() {
+        let io_err = std::io::Error::from_raw_os_error(2);
+        let error = RllessError::file_error("Failed to open file: /var/log/app.log", io_err)
+            .context("Failed to reopen the rotated file");
+
+        assert_eq!(
+            error.chain(),
+            "Failed to reopen the rotated file: File operation failed: Failed to open file: \
+             /var/log/app.log: No such file or directory (os error 2)"
+        );
+        assert_eq!(
+            error.summary(),
+            "Failed to reopen the rotated file (No such file or directory)"
+        );
+
+        // Without an I/O error at the root the summary is just the message
+        let error = RllessError::search("Pattern too long");
+        assert_eq!(error.summary(), "Search operation failed: Pattern too long");
+        assert_eq!(error.chain(), error.to_string());
+        let file_error = RllessError::file_error(
+            "Failed to read keymap",
+            std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(
+            file_error.summary(),
+            "Failed to read keymap (permission denied)"
+        );
+
+        // A message that already quotes its source is not repeated
+        let parse = "a(".parse::<i32>().unwrap_err();
+        let error = RllessError::search_error(format!("Invalid number: {}", parse), parse.into());
+        assert_eq!(
+            error.chain(),
+            "Search operation failed: Invalid number: invalid digit found in string"
+        );
+        assert!(matches!(
+            RllessError::cancelled().context("ignored"),
+            RllessError::Cancelled
+        ));
+    }
+
     #[test]
     fn test_result_type_alias() {
         fn returns_result() -> Result<String> {
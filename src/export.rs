@@ -0,0 +1,523 @@
+//! Range export for piping file content to other tools.
+//!
+//! Exports stream the original bytes straight from the `FileAccessor` in bounded chunks instead
+//! of reconstructing lines, so the output is byte-for-byte identical to the source range (a final
+//! line without `\n` stays unterminated, CRLF endings are kept). Line reconstruction is opt-in via
+//! `ExportOptions::normalize_newlines`.
+//!
+//! The same chunked writer backs `--export-range` and the viewer's `:save` and `:pipe`, which
+//! pass an `ExportControl` to follow its progress and to stop it between chunks.
+
+use crate::error::{Result, RllessError};
+use crate::file_handler::meta::human_size;
+use crate::file_handler::{FileAccessor, ScanProgress};
+use crate::util::check_cancelled;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+/// Number of bytes read from the accessor per write to the sink.
+const EXPORT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Half-open byte range `[start, end)` to export. `end: None` means "until EOF".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl ByteRange {
+    /// The whole file
+    pub const ALL: Self = Self {
+        start: 0,
+        end: None,
+    };
+
+    /// Parse a `START:END` or `START:` range specification.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = || RllessError::InvalidArgument {
+            message: format!(
+                "invalid byte range '{}': expected START:END or START:",
+                spec
+            ),
+        };
+
+        let (start, end) = spec.split_once(':').ok_or_else(invalid)?;
+        let start = start.trim().parse::<u64>().map_err(|_| invalid())?;
+        let end = match end.trim() {
+            "" => None,
+            value => Some(value.parse::<u64>().map_err(|_| invalid())?),
+        };
+
+        if end.is_some_and(|end| end < start) {
+            return Err(invalid());
+        }
+
+        Ok(Self { start, end })
+    }
+
+    /// Clamp the range to the given file size, returning concrete `(start, end)` offsets.
+    fn resolve(&self, file_size: u64) -> (u64, u64) {
+        let end = self.end.unwrap_or(file_size).min(file_size);
+        (self.start.min(end), end)
+    }
+}
+
+/// Options controlling how exported bytes are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Rewrite CRLF line endings to LF and terminate the final line with `\n`.
+    pub normalize_newlines: bool,
+}
+
+/// Cancellation and progress reporting for an export that may run for a while.
+#[derive(Clone, Copy, Default)]
+pub struct ExportControl<'a> {
+    /// Checked between chunks; once set the export stops with `RllessError::Cancelled`.
+    pub cancel_flag: Option<&'a AtomicBool>,
+    /// Called after each chunk with the number of bytes of the range read so far.
+    pub progress: Option<ScanProgress<'a>>,
+}
+
+/// Stream a byte range from `accessor` into `sink`.
+///
+/// Returns the number of bytes written to the sink (which differs from the range length only
+/// when newlines are normalized).
+pub async fn export_range<W: Write>(
+    accessor: &dyn FileAccessor,
+    range: ByteRange,
+    options: ExportOptions,
+    sink: &mut W,
+) -> Result<u64> {
+    export_range_with(accessor, range, options, sink, ExportControl::default()).await
+}
+
+/// Stream a byte range from `accessor` into `sink` under `control`.
+///
+/// What was written before a cancellation or an error stays in `sink`.
+pub async fn export_range_with<W: Write>(
+    accessor: &dyn FileAccessor,
+    range: ByteRange,
+    options: ExportOptions,
+    sink: &mut W,
+    control: ExportControl<'_>,
+) -> Result<u64> {
+    export_range_chunked(accessor, range, options, sink, EXPORT_CHUNK_SIZE, control).await
+}
+
+/// `bytes` written in `elapsed`, as in `9.6 GiB in 41s`
+pub fn throughput(bytes: u64, elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs_f64();
+    let elapsed = if seconds < 10.0 {
+        format!("{:.1}s", seconds)
+    } else if seconds < 60.0 {
+        format!("{}s", seconds as u64)
+    } else {
+        format!("{}m {:02}s", seconds as u64 / 60, seconds as u64 % 60)
+    };
+    format!("{} in {}", human_size(bytes), elapsed)
+}
+
+/// Whether output to `stream` should be a plain dump instead of the interactive viewer.
+///
+/// When stdout is redirected to a file or pipe, the terminal UI would only write escape
+/// sequences into it while waiting for keys nobody sees, so rlless copies the file like `cat`.
+pub fn is_plain_output(stream: &impl IsTerminal) -> bool {
+    !stream.is_terminal()
+}
+
+/// Copy the whole file (decompressed, if it was compressed) into `sink` unchanged.
+pub async fn dump_file<W: Write>(accessor: &dyn FileAccessor, sink: &mut W) -> Result<u64> {
+    export_range(accessor, ByteRange::ALL, ExportOptions::default(), sink).await
+}
+
+async fn export_range_chunked<W: Write>(
+    accessor: &dyn FileAccessor,
+    range: ByteRange,
+    options: ExportOptions,
+    sink: &mut W,
+    chunk_size: usize,
+    control: ExportControl<'_>,
+) -> Result<u64> {
+    let (start, end) = range.resolve(accessor.file_size());
+    let mut position = start;
+    let mut normalizer = options.normalize_newlines.then(NewlineNormalizer::default);
+    let mut scratch = Vec::new();
+    let mut written = 0u64;
+
+    while position < end {
+        check_cancelled(control.cancel_flag)?;
+        let want = (end - position).min(chunk_size as u64) as usize;
+        let chunk = accessor.read_bytes(position, want).await?;
+        if chunk.is_empty() {
+            break;
+        }
+        position += chunk.len() as u64;
+
+        let output = match normalizer.as_mut() {
+            Some(normalizer) => {
+                scratch.clear();
+                normalizer.push(&chunk, &mut scratch);
+                scratch.as_slice()
+            }
+            None => chunk.as_slice(),
+        };
+        sink.write_all(output)?;
+        written += output.len() as u64;
+        if let Some(progress) = control.progress {
+            progress(position - start);
+        }
+    }
+
+    if let Some(normalizer) = normalizer {
+        scratch.clear();
+        normalizer.finish(&mut scratch);
+        sink.write_all(&scratch)?;
+        written += scratch.len() as u64;
+    }
+
+    Ok(written)
+}
+
+/// Incremental CRLF -> LF rewriter that tolerates `\r\n` pairs split across chunks.
+#[derive(Debug, Default)]
+struct NewlineNormalizer {
+    pending_cr: bool,
+    last_byte: Option<u8>,
+}
+
+impl NewlineNormalizer {
+    fn push(&mut self, chunk: &[u8], out: &mut Vec<u8>) {
+        let mut rest = chunk;
+        if self.pending_cr {
+            self.pending_cr = false;
+            if rest.first() != Some(&b'\n') {
+                out.push(b'\r');
+            }
+        }
+
+        while let Some(cr) = memchr::memchr(b'\r', rest) {
+            out.extend_from_slice(&rest[..cr]);
+            match rest.get(cr + 1) {
+                Some(b'\n') => {}
+                Some(_) => out.push(b'\r'),
+                None => self.pending_cr = true,
+            }
+            rest = &rest[cr + 1..];
+        }
+        out.extend_from_slice(rest);
+
+        if let Some(&last) = out.last() {
+            self.last_byte = Some(last);
+        }
+    }
+
+    fn finish(self, out: &mut Vec<u8>) {
+        if self.pending_cr {
+            // A trailing CR is the remains of a CRLF terminator; normalize it like the rest.
+            out.push(b'\n');
+        } else if self.last_byte.is_some_and(|last| last != b'\n') {
+            out.push(b'\n');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileAccessorFactory;
+    use std::sync::atomic::Ordering;
+    use tempfile::NamedTempFile;
+
+    fn create_test_file(content: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().expect("Failed to create temp file");
+        file.write_all(content).expect("Failed to write test data");
+        file.flush().expect("Failed to flush test data");
+        file
+    }
+
+    async fn export_all(content: &[u8], options: ExportOptions, chunk_size: usize) -> Vec<u8> {
+        let file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let mut out = Vec::new();
+        let control = ExportControl::default();
+        let written = export_range_chunked(
+            &accessor,
+            ByteRange::ALL,
+            options,
+            &mut out,
+            chunk_size,
+            control,
+        )
+        .await
+        .unwrap();
+        assert_eq!(written, out.len() as u64);
+        out
+    }
+
+    #[test]
+    fn test_byte_range_parse() {
+        assert_eq!(
+            ByteRange::parse("10:20").unwrap(),
+            ByteRange {
+                start: 10,
+                end: Some(20)
+            }
+        );
+        assert_eq!(
+            ByteRange::parse("5:").unwrap(),
+            ByteRange {
+                start: 5,
+                end: None
+            }
+        );
+        assert!(ByteRange::parse("20:10").is_err());
+        assert!(ByteRange::parse("abc").is_err());
+        assert!(ByteRange::parse("1:x").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_raw_export_is_byte_exact() {
+        let fixtures: [&[u8]; 3] = [
+            b"first\nsecond\n",
+            b"first\nsecond without newline",
+            b"crlf one\r\ncrlf two\r\n",
+        ];
+
+        for fixture in fixtures {
+            for chunk_size in [1, 4, EXPORT_CHUNK_SIZE] {
+                let out = export_all(fixture, ExportOptions::default(), chunk_size).await;
+                assert_eq!(out, fixture);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partial_range_export() {
+        let file = create_test_file(b"alpha\nbeta\ngamma");
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+
+        let mut out = Vec::new();
+        let range = ByteRange::parse("6:13").unwrap();
+        export_range(&accessor, range, ExportOptions::default(), &mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, b"beta\nga");
+
+        // Ranges past EOF are clamped rather than failing
+        let mut out = Vec::new();
+        let range = ByteRange::parse("11:1000").unwrap();
+        export_range(&accessor, range, ExportOptions::default(), &mut out)
+            .await
+            .unwrap();
+        assert_eq!(out, b"gamma");
+    }
+
+    #[tokio::test]
+    async fn test_normalized_export_rewrites_line_endings() {
+        let options = ExportOptions {
+            normalize_newlines: true,
+        };
+
+        // Chunk size 9 splits the first "\r\n" pair across reads
+        for chunk_size in [1, 9, EXPORT_CHUNK_SIZE] {
+            let out = export_all(b"crlf one\r\ncrlf two\r\n", options, chunk_size).await;
+            assert_eq!(out, b"crlf one\ncrlf two\n");
+
+            let out = export_all(b"first\nno newline", options, chunk_size).await;
+            assert_eq!(out, b"first\nno newline\n");
+
+            let out = export_all(b"already\nnormal\n", options, chunk_size).await;
+            assert_eq!(out, b"already\nnormal\n");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirected_output_is_dumped_verbatim() {
+        let file = create_test_file(b"one\r\ntwo");
+        // A regular file is what `rlless log > out.txt` writes to
+        assert!(is_plain_output(file.as_file()));
+
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let mut out = Vec::new();
+        assert_eq!(dump_file(&accessor, &mut out).await.unwrap(), 8);
+        assert_eq!(out, b"one\r\ntwo");
+    }
+
+    /// 10 GiB of `x` lines that are made up on read, never stored
+    #[derive(Debug)]
+    struct SyntheticAccessor {
+        path: std::path::PathBuf,
+    }
+
+    const SYNTHETIC_SIZE: u64 = 10 << 30;
+
+    #[async_trait::async_trait]
+    impl FileAccessor for SyntheticAccessor {
+        async fn read_from_byte(&self, _start_byte: u64, _max_lines: usize) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn read_bytes(&self, start_byte: u64, length: usize) -> Result<Vec<u8>> {
+            let end = (start_byte + length as u64).min(SYNTHETIC_SIZE);
+            Ok((start_byte..end)
+                .map(|byte| if byte % 64 == 63 { b'\n' } else { b'x' })
+                .collect())
+        }
+
+        async fn find_next_match(
+            &self,
+            _start_byte: u64,
+            _search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            _cancel_flag: Option<&AtomicBool>,
+            _progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            Ok(None)
+        }
+
+        async fn find_prev_match(
+            &self,
+            _start_byte: u64,
+            _search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            _cancel_flag: Option<&AtomicBool>,
+            _progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            Ok(None)
+        }
+
+        fn file_size(&self) -> u64 {
+            SYNTHETIC_SIZE
+        }
+
+        fn file_path(&self) -> &std::path::Path {
+            &self.path
+        }
+
+        fn ends_with_newline(&self) -> bool {
+            true
+        }
+
+        fn file_meta(&self) -> Option<crate::file_handler::FileMeta> {
+            None
+        }
+
+        fn sections(&self) -> &[u64] {
+            &[]
+        }
+
+        async fn last_page_start(&self, _max_lines: usize) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn next_page_start(&self, _current_byte: u64, _lines_to_skip: usize) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn prev_page_start(&self, _current_byte: u64, _lines_to_skip: usize) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    /// Sink that takes its time and cancels the export after `cancel_after` writes
+    struct SlowSink<'a> {
+        written: u64,
+        writes: usize,
+        cancel_after: usize,
+        cancel_flag: &'a AtomicBool,
+    }
+
+    impl Write for SlowSink<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            std::thread::sleep(Duration::from_millis(5));
+            self.written += buf.len() as u64;
+            self.writes += 1;
+            if self.writes == self.cancel_after {
+                self.cancel_flag.store(true, Ordering::Relaxed);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_export_stops_between_chunks() {
+        let accessor = SyntheticAccessor {
+            path: "synthetic.log".into(),
+        };
+        let cancel_flag = AtomicBool::new(false);
+        let mut sink = SlowSink {
+            written: 0,
+            writes: 0,
+            cancel_after: 3,
+            cancel_flag: &cancel_flag,
+        };
+        let reports = parking_lot::Mutex::new(Vec::new());
+        let progress = |done: u64| reports.lock().push(done);
+        let control = ExportControl {
+            cancel_flag: Some(&cancel_flag),
+            progress: Some(&progress),
+        };
+
+        let result = export_range_with(
+            &accessor,
+            ByteRange::ALL,
+            ExportOptions::default(),
+            &mut sink,
+            control,
+        )
+        .await;
+        assert!(matches!(result, Err(RllessError::Cancelled)));
+        // Only the chunks started before the flag was set were read, each bounded in size
+        assert_eq!(sink.writes, 3);
+        assert_eq!(sink.written, 3 * EXPORT_CHUNK_SIZE as u64);
+        let chunk = EXPORT_CHUNK_SIZE as u64;
+        assert_eq!(*reports.lock(), [chunk, 2 * chunk, 3 * chunk]);
+    }
+
+    #[tokio::test]
+    async fn test_export_reports_progress_to_the_end_of_the_range() {
+        let accessor = SyntheticAccessor {
+            path: "synthetic.log".into(),
+        };
+        let start = SYNTHETIC_SIZE - 10 * 1024 * 1024;
+        let range = ByteRange { start, end: None };
+        let reports = parking_lot::Mutex::new(Vec::new());
+        let progress = |done: u64| reports.lock().push(done);
+        let control = ExportControl {
+            cancel_flag: None,
+            progress: Some(&progress),
+        };
+
+        let mut out = Vec::new();
+        let written = export_range_with(
+            &accessor,
+            range,
+            ExportOptions::default(),
+            &mut out,
+            control,
+        )
+        .await
+        .unwrap();
+        assert_eq!(written, SYNTHETIC_SIZE - start);
+        assert!(out.ends_with(b"xx\n"));
+        let reports = reports.lock();
+        assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(reports.last(), Some(&(SYNTHETIC_SIZE - start)));
+    }
+
+    #[test]
+    fn test_throughput_summary() {
+        assert_eq!(
+            throughput(10_307_921_510, Duration::from_secs(41)),
+            "9.6 GiB in 41s"
+        );
+        assert_eq!(
+            throughput(2048, Duration::from_millis(250)),
+            "2.0 KiB in 0.2s"
+        );
+        assert_eq!(throughput(0, Duration::from_secs(125)), "0 B in 2m 05s");
+    }
+}
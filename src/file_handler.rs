@@ -7,17 +7,38 @@
 //! - `accessor`: Core FileAccessor trait and access strategies
 //! - `adaptive`: Adaptive file accessor supporting in-memory, mmap, and compressed files
 //! - `compression`: Compression format detection and decompression utilities
+//! - `identity`: Telling whether two paths lead to the same file (symlinks, relative paths)
+//! - `latest`: Finding the newest file matching a wildcard pattern (`--latest`)
+//! - `meta`: File metadata (modification time, size, permissions) for the info display
+//! - `piped`: Reading log data piped to stdin into a growing accessor
+//! - `pressure`: Memory pressure detection for spilling in-memory sources to disk
+//! - `terminator`: The byte that ends a line (`--null`)
 //! - `validation`: File validation utilities
 
 pub mod accessor;
 pub mod adaptive;
 pub mod compression;
 pub mod factory;
+pub mod identity;
+pub mod latest;
+pub mod meta;
+pub mod piped;
+pub mod pressure;
+pub mod terminator;
 pub mod validation;
 
 // Re-export public API for convenient access
-pub use accessor::FileAccessor;
-pub use adaptive::AdaptiveFileAccessor;
-pub use compression::{decompress_file, detect_compression, DecompressionResult};
+pub use accessor::{FileAccessor, IndexingProgress, RawPage, ScanProgress, SCAN_PROGRESS_STRIDE};
+pub(crate) use adaptive::segment_cut;
+pub use adaptive::{AdaptiveFileAccessor, SEGMENT_BYTES};
+pub use compression::{
+    decompress_file, decompress_prefix, detect_compression, CompressionType, DecompressionResult,
+};
 pub use factory::FileAccessorFactory;
+pub use identity::{FileIdentity, FileKey};
+pub use latest::newest_match;
+pub use meta::FileMeta;
+pub use piped::{PipeProgress, PipedInput, STDIN_PATH};
+pub use pressure::cgroup_memory_pressure;
+pub use terminator::LineTerminator;
 pub use validation::validate_file_path;
@@ -5,15 +5,97 @@
 //! navigation for optimal performance with large files.
 
 use crate::error::Result;
+use crate::file_handler::identity::FileIdentity;
+use crate::file_handler::meta::FileMeta;
+use crate::file_handler::terminator::LineTerminator;
 use async_trait::async_trait;
+use std::ops::Range;
 use std::path::Path;
 use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// Called by a long match scan, about every `SCAN_PROGRESS_STRIDE` bytes, with the number of
+/// bytes it has covered so far
+pub type ScanProgress<'a> = &'a (dyn Fn(u64) + Send + Sync);
+
+/// Bytes a match scan covers between calls to its `ScanProgress`
+pub const SCAN_PROGRESS_STRIDE: u64 = 64 * 1024 * 1024;
+
+/// How much of the content has been indexed for line numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexingProgress {
+    pub indexed_bytes: u64,
+    pub total_bytes: u64,
+}
+
+impl IndexingProgress {
+    /// Share indexed, from 0.0 to 1.0; empty content counts as fully indexed
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            1.0
+        } else {
+            (self.indexed_bytes as f64 / self.total_bytes as f64) as f32
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.indexed_bytes >= self.total_bytes
+    }
+}
+
+/// A page of rows as the bytes they were read from
+///
+/// The viewport matches and measures pages in this form, and only turns rows into `String`s
+/// for display.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawPage {
+    /// The file's bytes from the first row's start to the last row's end
+    pub bytes: Vec<u8>,
+    /// Each row's range within `bytes`, without its terminator
+    pub rows: Vec<Range<usize>>,
+}
+
+impl RawPage {
+    /// A page of `lines`, each followed by a newline
+    pub fn from_lines(lines: &[String]) -> Self {
+        let mut page = Self {
+            bytes: Vec::with_capacity(lines.iter().map(|line| line.len() + 1).sum()),
+            rows: Vec::with_capacity(lines.len()),
+        };
+        for line in lines {
+            let start = page.bytes.len();
+            page.bytes.extend_from_slice(line.as_bytes());
+            page.rows.push(start..page.bytes.len());
+            page.bytes.push(b'\n');
+        }
+        page
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The bytes of each row
+    pub fn row_bytes(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.rows.iter().map(|row| &self.bytes[row.clone()])
+    }
+
+    /// The rows as text for display, invalid UTF-8 replaced
+    pub fn to_lines(&self) -> Vec<String> {
+        self.row_bytes()
+            .map(|row| String::from_utf8_lossy(row).into_owned())
+            .collect()
+    }
+}
 
 /// Core trait for file access operations using byte-based navigation
 ///
 /// This trait provides a unified interface for both small files (loaded into memory)
 /// and large files (memory-mapped) using byte positions instead of line numbers.
 /// All implementations must be thread-safe.
+///
+/// "Lines" are display rows: implementations may split very long lines into several rows
+/// (see `SEGMENT_BYTES`), in which case paging and match positions address those rows.
 #[async_trait]
 pub trait FileAccessor: Send + Sync {
     /// Read lines starting from a specific byte position
@@ -35,11 +117,45 @@ pub trait FileAccessor: Send + Sync {
     /// Used for viewport rendering, navigation (PageUp/Down, Go to End)
     async fn read_from_byte(&self, start_byte: u64, max_lines: usize) -> Result<Vec<String>>;
 
+    /// Read lines starting from a specific byte position, as bytes
+    ///
+    /// # Arguments
+    /// * `start_byte` - Byte position to start reading from (0-based)
+    /// * `max_lines` - Maximum number of lines to read
+    ///
+    /// # Returns
+    /// * The same rows as `read_from_byte`, as one buffer with a range per row
+    /// * Invalid UTF-8 is left as it is, where `read_from_byte` may fail on it
+    /// * The default builds the page from `read_from_byte`
+    ///
+    /// # Usage
+    /// Used for viewport loads, which match a page without a `String` per row
+    async fn read_from_byte_raw(&self, start_byte: u64, max_lines: usize) -> Result<RawPage> {
+        let lines = self.read_from_byte(start_byte, max_lines).await?;
+        Ok(RawPage::from_lines(&lines))
+    }
+
+    /// Read raw bytes starting from a specific byte position
+    ///
+    /// # Arguments
+    /// * `start_byte` - Byte position to start reading from (0-based)
+    /// * `length` - Maximum number of bytes to read
+    ///
+    /// # Returns
+    /// * The exact file bytes in the requested range (no line reconstruction)
+    /// * May be shorter than `length` if EOF reached
+    /// * Empty vector if `start_byte` is beyond EOF
+    ///
+    /// # Usage
+    /// Used for exporting ranges where output must match the original bytes
+    async fn read_bytes(&self, start_byte: u64, length: usize) -> Result<Vec<u8>>;
+
     /// Find next occurrence using a search function from byte position
     ///
     /// # Arguments
     /// * `start_byte` - Byte position to start searching from (inclusive)
     /// * `search_fn` - Function that returns match ranges for a given line
+    /// * `progress` - Told how far the scan has got, counting from `start_byte`
     ///
     /// # Returns
     /// * Some(byte_position) if matches found - byte position of line containing match
@@ -55,13 +171,41 @@ pub trait FileAccessor: Send + Sync {
         start_byte: u64,
         search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
         cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
     ) -> Result<Option<u64>>;
 
+    /// Find next occurrence like `find_next_match`, on several threads where that pays off
+    ///
+    /// # Arguments
+    /// * Same as `find_next_match`
+    ///
+    /// # Returns
+    /// * Exactly what `find_next_match` returns: the first match at or after `start_byte`
+    /// * The default runs `find_next_match`
+    ///
+    /// # Performance
+    /// * Mapped and decompressed content with more than a few tens of MB left to scan is cut
+    ///   into chunks searched on one thread per core; see `adaptive::parallel`
+    ///
+    /// # Usage
+    /// Used for forward search when `SearchOptions::parallel` is set
+    async fn find_next_match_parallel(
+        &self,
+        start_byte: u64,
+        search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+        cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
+    ) -> Result<Option<u64>> {
+        self.find_next_match(start_byte, search_fn, cancel_flag, progress)
+            .await
+    }
+
     /// Find previous occurrence using a search function searching backward from byte position
     ///
     /// # Arguments
     /// * `start_byte` - Byte position to start searching from (exclusive, searches backward from here)
     /// * `search_fn` - Function that returns match ranges for a given line
+    /// * `progress` - Told how far the scan has got, counting back from `start_byte`
     ///
     /// # Returns
     /// * Some(byte_position) if matches found - byte position of line containing match
@@ -77,6 +221,7 @@ pub trait FileAccessor: Send + Sync {
         start_byte: u64,
         search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
         cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
     ) -> Result<Option<u64>>;
 
     /// Get the total file size in bytes
@@ -100,6 +245,90 @@ pub trait FileAccessor: Send + Sync {
     /// Used for display purposes, error messages, file operations
     fn file_path(&self) -> &Path;
 
+    /// Whether the file ends with a line terminator (a newline, or NUL with `--null`)
+    ///
+    /// # Returns
+    /// * `false` if the final line is unterminated
+    /// * `true` otherwise, including for empty files
+    ///
+    /// # Performance
+    /// * O(1) - read from the last byte
+    ///
+    /// # Usage
+    /// Used to mark an unterminated final line (`[noeol]`) when it is on screen
+    fn ends_with_newline(&self) -> bool;
+
+    /// Get the file's on-disk metadata
+    ///
+    /// # Returns
+    /// * Modification time, size and permissions captured when the file was opened
+    /// * `None` if the accessor is not backed by a file on disk
+    ///
+    /// # Usage
+    /// Used for the file info display (`=` / Ctrl-G)
+    fn file_meta(&self) -> Option<FileMeta>;
+
+    /// Get the identity of the file on disk
+    ///
+    /// # Returns
+    /// * Canonical path and device/inode key resolved when the file was opened
+    /// * `None` if the accessor is not backed by a file on disk
+    ///
+    /// # Usage
+    /// Used to recognise the open file under another path, and for the info display
+    fn identity(&self) -> Option<&FileIdentity> {
+        None
+    }
+
+    /// Get the byte offsets where the file's sections start
+    ///
+    /// # Returns
+    /// * Ascending offsets, the first being 0, one per gzip member the content was decoded from
+    /// * Empty for plain files and other compression formats
+    ///
+    /// # Usage
+    /// Used for section jumps (`{` / `}`) and the member index on the status line
+    fn sections(&self) -> &[u64];
+
+    /// Get the line number of the line containing a byte position
+    ///
+    /// # Returns
+    /// * 1-based number counting line ends, so every row of a split long line gets its number
+    /// * `None` if it is not known yet, e.g. while a huge file is being indexed in the
+    ///   background, or if the accessor does not count lines (the default)
+    ///
+    /// # Usage
+    /// Used for the line-number gutter (`-N`)
+    fn line_number_for_byte(&self, _byte: u64) -> Option<u64> {
+        None
+    }
+
+    /// Get how far line numbers have been indexed
+    ///
+    /// # Returns
+    /// * Bytes from the start whose line numbers `line_number_for_byte` answers without
+    ///   further indexing, out of the content's size
+    /// * `None` if the accessor does not count lines (the default)
+    ///
+    /// # Usage
+    /// The one measure of indexing progress for the status line; cheap enough to poll
+    fn indexing_progress(&self) -> Option<IndexingProgress> {
+        None
+    }
+
+    /// Get the byte that ends a line
+    ///
+    /// # Returns
+    /// * `LineTerminator::Nul` for NUL-separated records (`--null`)
+    /// * `LineTerminator::Newline` otherwise (the default)
+    ///
+    /// # Usage
+    /// Used by scans outside the accessor that split content into lines themselves, and to
+    /// open a rotated file the same way again
+    fn line_terminator(&self) -> LineTerminator {
+        LineTerminator::Newline
+    }
+
     /// Calculate the last page byte position for "Go to End" functionality
     ///
     /// # Arguments
@@ -140,4 +369,27 @@ pub trait FileAccessor: Send + Sync {
     /// # Usage
     /// Used for PageUp navigation
     async fn prev_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64>;
+
+    /// Pick up data appended to the file since it was opened or last refreshed
+    ///
+    /// # Returns
+    /// * `true` if the file grew; `file_size` and reads cover the new data from then on
+    /// * `false` if it did not, or if the accessor cannot follow its file (the default), e.g.
+    ///   for decompressed content
+    ///
+    /// # Usage
+    /// Polled while following the end of the file (`F`, `--follow`)
+    async fn refresh(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Accessor fixed on the current content, for the calls of a single operation
+    ///
+    /// # Returns
+    /// * `Some` for accessors whose content can be swapped while in use; its calls skip the
+    ///   swap lock and never see a swap half way through an operation
+    /// * `None` if reading through `self` is already as cheap (the default)
+    fn snapshot(&self) -> Option<Arc<dyn FileAccessor>> {
+        None
+    }
 }
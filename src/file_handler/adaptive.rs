@@ -2,52 +2,40 @@
 //!
 //! This module provides a single implementation that adapts its internal strategy
 //! based on file characteristics determined by the FileAccessorFactory.
+//!
+//! - `source`: where the bytes live (`ByteSource`)
+//! - `append`: the buffer piped input is read into (`AppendBuffer`)
+//! - `pinned`: reads on one version of the content (`PinnedAccessor`)
+//! - `line_index`: line numbers of byte offsets (`LineIndex`)
+//! - `rows`: splitting lines into display rows of at most `SEGMENT_BYTES`
+//! - `parallel`: forward match scans split across threads
+
+mod append;
+mod line_index;
+mod parallel;
+mod pinned;
+mod rows;
+mod source;
+
+pub use append::{AppendBuffer, SharedBytes};
+pub use pinned::PinnedAccessor;
+pub(crate) use rows::segment_cut;
+pub use rows::SEGMENT_BYTES;
+pub use source::{ByteSource, MappedTempFile};
 
 use crate::error::{Result, RllessError};
-use crate::file_handler::accessor::FileAccessor;
+use crate::file_handler::accessor::{FileAccessor, IndexingProgress, RawPage, ScanProgress};
+use crate::file_handler::identity::FileIdentity;
+use crate::file_handler::meta::FileMeta;
+use crate::file_handler::terminator::LineTerminator;
 use async_trait::async_trait;
 use memmap2::Mmap;
-use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
-use tempfile::NamedTempFile;
-
-/// Internal byte source strategy for AdaptiveFileAccessor
-#[derive(Debug)]
-pub enum ByteSource {
-    /// Content loaded entirely into memory (for files < 50MB)
-    InMemory(Vec<u8>),
-    /// Content accessed via memory mapping (for files ≥ 50MB)
-    MemoryMapped(Mmap),
-    /// Compressed file decompressed to temp file and memory-mapped
-    /// The temp file is kept alive to prevent deletion
-    Compressed {
-        mmap: Mmap,
-        _temp_file: NamedTempFile,
-    },
-}
-
-impl ByteSource {
-    /// Get the underlying bytes as a slice regardless of storage strategy
-    fn as_bytes(&self) -> &[u8] {
-        match self {
-            ByteSource::InMemory(vec) => vec.as_slice(),
-            ByteSource::MemoryMapped(mmap) => &mmap[..],
-            ByteSource::Compressed { mmap, .. } => &mmap[..],
-        }
-    }
-
-    /// Convert bytes to String
-    fn bytes_to_string(&self, bytes: &[u8]) -> Result<String> {
-        std::str::from_utf8(bytes)
-            .map(|s| s.to_string())
-            .map_err(|e| {
-                crate::error::RllessError::file_error(
-                    "Invalid UTF-8 in file",
-                    std::io::Error::new(std::io::ErrorKind::InvalidData, e),
-                )
-            })
-    }
-}
+use parking_lot::RwLock;
+use pinned::{Content, FileInfo};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 /// Adaptive file accessor that uses different internal strategies
 ///
@@ -57,9 +45,10 @@ impl ByteSource {
 /// or decompressed from a compressed format.
 #[derive(Debug)]
 pub struct AdaptiveFileAccessor {
-    pub(crate) source: ByteSource,
-    file_size: u64,
-    file_path: std::path::PathBuf,
+    // Behind a lock so the content can be swapped (see `spill_to_disk`) without changing the
+    // `FileAccessor` API. Reads go through a `PinnedAccessor` holding one version of it.
+    content: RwLock<Arc<Content>>,
+    info: Arc<FileInfo>,
 }
 
 impl AdaptiveFileAccessor {
@@ -69,49 +58,114 @@ impl AdaptiveFileAccessor {
     /// * `source` - The internal byte source strategy to use
     /// * `file_size` - Size of the file content in bytes
     /// * `file_path` - Path to the original file
-    pub fn new(source: ByteSource, file_size: u64, file_path: std::path::PathBuf) -> Self {
+    pub fn new(source: ByteSource, file_size: u64, file_path: PathBuf) -> Self {
         Self {
-            source,
-            file_size,
-            file_path,
+            content: RwLock::new(Arc::new(Content { source, file_size })),
+            info: Arc::new(FileInfo {
+                file_path,
+                meta: None,
+                identity: None,
+                sections: Vec::new(),
+                refreshable: false,
+                lines: Arc::default(),
+                terminator: LineTerminator::default(),
+            }),
         }
     }
-}
 
-#[async_trait]
-impl FileAccessor for AdaptiveFileAccessor {
-    async fn read_from_byte(&self, start_byte: u64, max_lines: usize) -> Result<Vec<String>> {
-        let bytes = self.source.as_bytes();
-        if start_byte as usize >= bytes.len() {
-            return Ok(Vec::new());
-        }
+    /// Attach the on-disk metadata reported by `file_meta`
+    pub fn with_meta(mut self, meta: FileMeta) -> Self {
+        Arc::make_mut(&mut self.info).meta = Some(meta);
+        self
+    }
 
-        let mut lines = Vec::new();
-        let mut current_pos = start_byte as usize;
-        let mut lines_read = 0;
+    /// Attach the identity resolved when the file was opened
+    pub fn with_identity(mut self, identity: FileIdentity) -> Self {
+        Arc::make_mut(&mut self.info).identity = Some(identity);
+        self
+    }
 
-        while lines_read < max_lines && current_pos < bytes.len() {
-            // Find the end of the current line
-            let line_end = memchr::memchr(b'\n', &bytes[current_pos..])
-                .map(|pos| current_pos + pos)
-                .unwrap_or(bytes.len());
+    /// Attach the section start offsets reported by `sections`
+    pub fn with_sections(mut self, sections: Vec<u64>) -> Self {
+        Arc::make_mut(&mut self.info).sections = sections;
+        self
+    }
 
-            // Extract the line content (without newline)
-            let line_bytes = &bytes[current_pos..line_end];
-            let line_str = self.source.bytes_to_string(line_bytes)?;
+    /// Let `refresh` pick up data appended to the file at `file_path`, which the content must
+    /// be an unmodified copy or mapping of
+    pub fn with_refresh(mut self) -> Self {
+        Arc::make_mut(&mut self.info).refreshable = true;
+        self
+    }
 
-            lines.push(line_str);
-            lines_read += 1;
+    /// End lines at `terminator` instead of a newline
+    pub fn with_line_terminator(mut self, terminator: LineTerminator) -> Self {
+        Arc::make_mut(&mut self.info).terminator = terminator;
+        self
+    }
 
-            // Move to the start of the next line
-            current_pos = if line_end < bytes.len() {
-                line_end + 1 // Skip the newline character
-            } else {
-                break; // End of file
-            };
+    /// Reader of the current content
+    pub fn pin(&self) -> PinnedAccessor {
+        PinnedAccessor {
+            content: Arc::clone(&self.content.read()),
+            info: Arc::clone(&self.info),
+        }
+    }
+
+    /// Replace the content with `source`, e.g. once more piped input has been read
+    ///
+    /// Reads already in flight finish on the old content.
+    pub fn replace_source(&self, source: ByteSource) {
+        let content = Content {
+            file_size: source.as_bytes().len() as u64,
+            source,
+        };
+        *self.content.write() = Arc::new(content);
+    }
+
+    /// Move an in-memory source into a memory-mapped temp file and free the heap buffer
+    ///
+    /// The copy runs on the blocking pool so it stays off the render/search hot path. Reads
+    /// already in flight keep the old buffer alive until they finish; later reads use the
+    /// mapping. Content is identical before and after the swap.
+    ///
+    /// # Returns
+    /// * `true` if the source was spilled, `false` if it was not held in memory
+    pub async fn spill_to_disk(&self) -> Result<bool> {
+        let current = Arc::clone(&self.content.read());
+        match &current.source {
+            ByteSource::InMemory(data) if !data.is_empty() => {}
+            ByteSource::Streamed(data) if !data.as_slice().is_empty() => {}
+            _ => return Ok(false),
         }
 
-        Ok(lines)
+        let spilled = tokio::task::spawn_blocking(move || -> Result<Content> {
+            Ok(Content {
+                source: ByteSource::spill(current.source.as_bytes())?,
+                file_size: current.file_size,
+            })
+        })
+        .await
+        .map_err(|e| RllessError::other(format!("Spill task failed: {}", e)))??;
+        let file_size = spilled.file_size;
+        *self.content.write() = Arc::new(spilled);
+        log::info!("accessor: spilled {} bytes to a temp file", file_size);
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl FileAccessor for AdaptiveFileAccessor {
+    async fn read_from_byte(&self, start_byte: u64, max_lines: usize) -> Result<Vec<String>> {
+        self.pin().read_from_byte(start_byte, max_lines).await
+    }
+
+    async fn read_from_byte_raw(&self, start_byte: u64, max_lines: usize) -> Result<RawPage> {
+        self.pin().read_from_byte_raw(start_byte, max_lines).await
+    }
+
+    async fn read_bytes(&self, start_byte: u64, length: usize) -> Result<Vec<u8>> {
+        self.pin().read_bytes(start_byte, length).await
     }
 
     async fn find_next_match(
@@ -119,44 +173,23 @@ impl FileAccessor for AdaptiveFileAccessor {
         start_byte: u64,
         search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
         cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
     ) -> Result<Option<u64>> {
-        let bytes = self.source.as_bytes();
-        if start_byte as usize >= bytes.len() {
-            return Ok(None);
-        }
-
-        let mut current_pos = start_byte as usize;
-
-        while current_pos < bytes.len() {
-            if cancel_flag
-                .map(|flag| flag.load(Ordering::Relaxed))
-                .unwrap_or(false)
-            {
-                return Err(RllessError::cancelled());
-            }
-            // Find the end of the current line
-            let line_end = memchr::memchr(b'\n', &bytes[current_pos..])
-                .map(|pos| current_pos + pos)
-                .unwrap_or(bytes.len());
-
-            // Extract the line content
-            let line_bytes = &bytes[current_pos..line_end];
-            if let Ok(line_str) = std::str::from_utf8(line_bytes) {
-                let matches = search_fn(line_str);
-                if !matches.is_empty() {
-                    return Ok(Some(current_pos as u64));
-                }
-            }
-
-            // Move to the start of the next line
-            current_pos = if line_end < bytes.len() {
-                line_end + 1
-            } else {
-                break;
-            };
-        }
+        self.pin()
+            .find_next_match(start_byte, search_fn, cancel_flag, progress)
+            .await
+    }
 
-        Ok(None)
+    async fn find_next_match_parallel(
+        &self,
+        start_byte: u64,
+        search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+        cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
+    ) -> Result<Option<u64>> {
+        self.pin()
+            .find_next_match_parallel(start_byte, search_fn, cancel_flag, progress)
+            .await
     }
 
     async fn find_prev_match(
@@ -164,140 +197,111 @@ impl FileAccessor for AdaptiveFileAccessor {
         start_byte: u64,
         search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
         cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
     ) -> Result<Option<u64>> {
-        let bytes = self.source.as_bytes();
-        if start_byte == 0 {
-            return Ok(None);
-        }
-
-        // Start from one byte before start_byte to exclude current line
-        let mut search_pos = (start_byte as usize).min(bytes.len()).saturating_sub(1);
-
-        // Search backward line by line
-        loop {
-            if cancel_flag
-                .map(|flag| flag.load(Ordering::Relaxed))
-                .unwrap_or(false)
-            {
-                return Err(RllessError::cancelled());
-            }
-            // Find the start of the line containing search_pos
-            let line_start = if search_pos == 0 {
-                0
-            } else {
-                // Look for newline before search_pos
-                match memchr::memrchr(b'\n', &bytes[0..search_pos]) {
-                    Some(newline_pos) => newline_pos + 1, // Start of line is after the newline
-                    None => 0, // No newline found, this is the first line
-                }
-            };
+        self.pin()
+            .find_prev_match(start_byte, search_fn, cancel_flag, progress)
+            .await
+    }
 
-            // search_pos should be at a newline, so it's the end of the line we want
-            let line_end = search_pos;
+    fn file_size(&self) -> u64 {
+        self.content.read().file_size
+    }
 
-            // Extract and check the line content
-            let line_bytes = &bytes[line_start..line_end];
-            if let Ok(line_str) = std::str::from_utf8(line_bytes) {
-                let matches = search_fn(line_str);
-                if !matches.is_empty() {
-                    return Ok(Some(line_start as u64));
-                }
-            }
+    fn file_path(&self) -> &Path {
+        &self.info.file_path
+    }
 
-            // Move to search the previous line
-            if line_start == 0 {
-                return Ok(None); // No more lines to search
-            }
-            search_pos = line_start - 1; // Move to the byte before this line starts
-        }
+    fn ends_with_newline(&self) -> bool {
+        self.pin().ends_with_newline()
     }
 
-    fn file_size(&self) -> u64 {
-        self.file_size
+    fn file_meta(&self) -> Option<FileMeta> {
+        self.info.meta
     }
 
-    fn file_path(&self) -> &Path {
-        &self.file_path
+    fn identity(&self) -> Option<&FileIdentity> {
+        self.info.identity.as_ref()
     }
 
-    async fn last_page_start(&self, max_lines: usize) -> Result<u64> {
-        let bytes = self.source.as_bytes();
-        if bytes.is_empty() || max_lines == 0 {
-            return Ok(0);
-        }
+    fn sections(&self) -> &[u64] {
+        &self.info.sections
+    }
 
-        let mut search_pos = bytes.len();
+    fn line_terminator(&self) -> LineTerminator {
+        self.info.terminator
+    }
 
-        // Skip trailing newline if present (it doesn't count as a line separator)
-        if bytes.last() == Some(&b'\n') {
-            search_pos = search_pos.saturating_sub(1);
-        }
+    fn line_number_for_byte(&self, byte: u64) -> Option<u64> {
+        self.pin().line_number_for_byte(byte)
+    }
 
-        // Find max_lines newline characters from the end
-        for _ in 0..max_lines {
-            match memchr::memrchr(b'\n', &bytes[0..search_pos]) {
-                Some(newline_pos) => {
-                    search_pos = newline_pos;
-                }
-                None => {
-                    // We hit the start of the file without finding enough newlines
-                    return Ok(0);
-                }
-            }
-        }
+    fn indexing_progress(&self) -> Option<IndexingProgress> {
+        self.pin().indexing_progress()
+    }
 
-        // Return position after the last found newline
-        Ok((search_pos + 1) as u64)
+    async fn last_page_start(&self, max_lines: usize) -> Result<u64> {
+        self.pin().last_page_start(max_lines).await
     }
 
     async fn next_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
-        let bytes = self.source.as_bytes();
-        let mut pos = current_byte as usize;
-        let mut lines_skipped = 0;
-
-        while pos < bytes.len() && lines_skipped < lines_to_skip {
-            // Find the next newline
-            if let Some(newline_pos) = memchr::memchr(b'\n', &bytes[pos..]) {
-                pos += newline_pos + 1; // Move past the newline
-                lines_skipped += 1;
-            } else {
-                // No more newlines, we're at the end
-                break;
-            }
-        }
-
-        // If we couldn't complete the full skip due to EOF, return file_size
-        if lines_skipped < lines_to_skip {
-            Ok(self.file_size) // Return EOF indicator
-        } else {
-            Ok(pos as u64) // Return new position
-        }
+        self.pin()
+            .next_page_start(current_byte, lines_to_skip)
+            .await
     }
 
     async fn prev_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
-        let bytes = self.source.as_bytes();
-        if current_byte == 0 || lines_to_skip == 0 {
-            return Ok(0);
-        }
+        self.pin()
+            .prev_page_start(current_byte, lines_to_skip)
+            .await
+    }
 
-        // Start from one byte before current_byte to exclude current line
-        let mut search_pos = (current_byte as usize).saturating_sub(1);
-
-        // Find lines_to_skip newlines going backward
-        for _ in 0..lines_to_skip {
-            match memchr::memrchr(b'\n', &bytes[0..search_pos]) {
-                Some(newline_pos) => {
-                    search_pos = newline_pos;
-                }
-                None => {
-                    // We hit the start of the file without finding enough newlines
-                    return Ok(0);
-                }
-            }
+    async fn refresh(&self) -> Result<bool> {
+        if !self.info.refreshable {
+            return Ok(false);
         }
+        let path = self.info.file_path.clone();
+        let known_size = self.file_size();
+        // Appended data shows up in a new mapping of the whole file, whatever the source was
+        let grown = tokio::task::spawn_blocking(move || -> Result<Option<ByteSource>> {
+            let file = File::open(&path).map_err(|e| {
+                RllessError::file_error(format!("Failed to open file: {}", path.display()), e)
+            })?;
+            let size = file
+                .metadata()
+                .map_err(|e| RllessError::file_error("Failed to get file metadata", e))?
+                .len();
+            if size <= known_size {
+                return Ok(None);
+            }
+            let mmap = unsafe {
+                Mmap::map(&file).map_err(|e| {
+                    RllessError::file_error(
+                        format!("Failed to memory map file: {}", path.display()),
+                        e,
+                    )
+                })?
+            };
+            Ok(Some(ByteSource::MemoryMapped(mmap)))
+        })
+        .await
+        .map_err(|e| RllessError::other(format!("Refresh task failed: {}", e)))?
+        .map_err(|e| e.context("Failed to check the file for appended data"))?;
+
+        let Some(source) = grown else {
+            return Ok(false);
+        };
+        self.replace_source(source);
+        log::debug!(
+            "accessor: grew from {} to {} bytes",
+            known_size,
+            self.file_size()
+        );
+        Ok(true)
+    }
 
-        // Return position after the last found newline
-        Ok((search_pos + 1) as u64)
+    fn snapshot(&self) -> Option<Arc<dyn FileAccessor>> {
+        Some(Arc::new(self.pin()))
     }
 }
 
@@ -311,230 +315,13 @@ mod tests {
     use tempfile::NamedTempFile;
 
     /// Create a temporary test file with known content
-    fn create_test_file(content: &[u8]) -> NamedTempFile {
+    pub(super) fn create_test_file(content: &[u8]) -> NamedTempFile {
         let mut file = NamedTempFile::new().expect("Failed to create temp file");
         file.write_all(content).expect("Failed to write test data");
         file.flush().expect("Failed to flush test data");
         file
     }
 
-    #[tokio::test]
-    async fn test_adaptive_accessor_small_file() {
-        let content = b"line1\nline2\nline3\n";
-        let temp_file = create_test_file(content);
-
-        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
-
-        assert_eq!(accessor.file_size(), content.len() as u64);
-        assert_eq!(accessor.file_path(), temp_file.path());
-
-        // Should use InMemory for small file
-        match &accessor.source {
-            ByteSource::InMemory(_) => {} // Expected
-            _ => panic!("Small file should use InMemory variant"),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_adaptive_accessor_read_from_byte() {
-        let content = b"line1\nline2\nline3\n";
-        let temp_file = create_test_file(content);
-        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
-
-        // Read from beginning
-        let lines = accessor.read_from_byte(0, 2).await.unwrap();
-        assert_eq!(lines, vec!["line1", "line2"]);
-
-        // Read from middle (byte 6 is start of "line2")
-        let lines = accessor.read_from_byte(6, 2).await.unwrap();
-        assert_eq!(lines, vec!["line2", "line3"]);
-
-        // Read from end of file
-        let lines = accessor.read_from_byte(100, 2).await.unwrap();
-        assert!(lines.is_empty());
-
-        // Read with limit
-        let lines = accessor.read_from_byte(0, 1).await.unwrap();
-        assert_eq!(lines, vec!["line1"]);
-    }
-
-    #[tokio::test]
-    async fn test_adaptive_accessor_find_next_match() {
-        let content = b"error line\nnormal line\nerror again\n";
-        let temp_file = create_test_file(content);
-        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
-
-        let error_search = |line: &str| {
-            let mut matches = Vec::new();
-            let mut start = 0;
-            while let Some(pos) = line[start..].find("error") {
-                let match_start = start + pos;
-                let match_end = match_start + "error".len();
-                matches.push((match_start, match_end));
-                start = match_end;
-            }
-            matches
-        };
-
-        // Find first match
-        let result = accessor
-            .find_next_match(0, &error_search, None)
-            .await
-            .unwrap();
-        assert_eq!(result, Some(0));
-
-        // Find second match
-        let result = accessor
-            .find_next_match(15, &error_search, None)
-            .await
-            .unwrap();
-        assert!(result.is_some());
-        let byte_pos = result.unwrap();
-        assert!(byte_pos > 15);
-
-        // No match found
-        let no_match_search = |_line: &str| Vec::new();
-        let result = accessor
-            .find_next_match(0, &no_match_search, None)
-            .await
-            .unwrap();
-        assert!(result.is_none());
-    }
-
-    #[tokio::test]
-    async fn test_adaptive_accessor_find_prev_match() {
-        let content = b"error line\nnormal line\nerror again\n";
-        let temp_file = create_test_file(content);
-        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
-
-        let error_search = |line: &str| {
-            let mut matches = Vec::new();
-            let mut start = 0;
-            while let Some(pos) = line[start..].find("error") {
-                let match_start = start + pos;
-                let match_end = match_start + "error".len();
-                matches.push((match_start, match_end));
-                start = match_end;
-            }
-            matches
-        };
-
-        // Find match searching backward from end
-        let result = accessor
-            .find_prev_match(100, &error_search, None)
-            .await
-            .unwrap();
-        assert!(result.is_some());
-
-        // No match from beginning
-        let result = accessor
-            .find_prev_match(0, &error_search, None)
-            .await
-            .unwrap();
-        assert!(result.is_none());
-    }
-
-    #[tokio::test]
-    async fn test_adaptive_accessor_navigation_methods() {
-        let content = b"line1\nline2\nline3\nline4\nline5\n";
-        let temp_file = create_test_file(content);
-        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
-
-        // Test last page start
-        let last_pos = accessor.last_page_start(3).await.unwrap();
-        assert_eq!(last_pos, 12); // Should start at "line3"
-
-        // Test next page start - normal case
-        let next_pos = accessor.next_page_start(0, 2).await.unwrap();
-        assert_eq!(next_pos, 12); // Should be at start of "line3"
-
-        // Test next page start - at end of file (should return file_size when can't complete skip)
-        let end_pos = accessor.next_page_start(24, 2).await.unwrap(); // Start of "line5", try to skip 2 lines
-        assert_eq!(end_pos, 30); // Should return file_size (30) since we can only skip 1 line, not 2
-
-        // Test prev page start
-        let prev_pos = accessor.prev_page_start(next_pos, 2).await.unwrap();
-        assert_eq!(prev_pos, 0); // Should go back to start
-    }
-
-    #[tokio::test]
-    async fn test_adaptive_accessor_empty_file() {
-        let content = b"";
-        let temp_file = create_test_file(content);
-
-        // FileAccessorFactory should reject empty files during validation
-        let result = FileAccessorFactory::create(temp_file.path()).await;
-        assert!(result.is_err());
-
-        let error = result.err().unwrap();
-        match error {
-            crate::error::RllessError::FileError { message, .. } => {
-                assert!(message.contains("File is empty"));
-            }
-            _ => panic!("Expected FileError for empty file"),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_adaptive_accessor_single_line_no_newline() {
-        let content = b"single line without newline";
-        let temp_file = create_test_file(content);
-        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
-
-        let lines = accessor.read_from_byte(0, 5).await.unwrap();
-        assert_eq!(lines, vec!["single line without newline"]);
-
-        let last_pos = accessor.last_page_start(1).await.unwrap();
-        assert_eq!(last_pos, 0);
-    }
-
-    #[tokio::test]
-    async fn test_adaptive_accessor_next_page_start_edge_cases() {
-        // Test file ending with newline
-        let content = b"A\nB\nC\n";
-        let temp_file = create_test_file(content);
-        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
-
-        // Normal navigation
-        let pos = accessor.next_page_start(0, 1).await.unwrap();
-        assert_eq!(pos, 2); // Start of "B"
-
-        let pos = accessor.next_page_start(2, 1).await.unwrap();
-        assert_eq!(pos, 4); // Start of "C"
-
-        // At last line - should return file_size since we can't skip a full line
-        let pos = accessor.next_page_start(4, 1).await.unwrap();
-        assert_eq!(pos, 6); // Returns file_size (6) indicating EOF
-    }
-
-    #[tokio::test]
-    async fn test_adaptive_accessor_last_page_start_comprehensive() {
-        // Test case: "A\nB\nC\nD\nE\n" (5 lines, ends with newline)
-        let content = b"A\nB\nC\nD\nE\n";
-        let temp_file = create_test_file(content);
-        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
-
-        // Request 1 line: should get last line (E)
-        let last_pos = accessor.last_page_start(1).await.unwrap();
-        assert_eq!(last_pos, 8); // Start of "E"
-
-        // Request 2 lines: should get D and E
-        let last_pos = accessor.last_page_start(2).await.unwrap();
-        assert_eq!(last_pos, 6); // Start of "D"
-
-        // Request 3 lines: should get C, D, and E
-        let last_pos = accessor.last_page_start(3).await.unwrap();
-        assert_eq!(last_pos, 4); // Start of "C"
-
-        // Request 5 lines: should get all lines from beginning
-        let last_pos = accessor.last_page_start(5).await.unwrap();
-        assert_eq!(last_pos, 0); // Start of "A"
-
-        // Request more than available: should get all lines from beginning
-        let last_pos = accessor.last_page_start(10).await.unwrap();
-        assert_eq!(last_pos, 0);
-    }
-
     #[tokio::test]
     async fn test_adaptive_accessor_compressed_file() {
         // Create a small compressed file
@@ -553,9 +340,9 @@ mod tests {
 
         // Should use InMemory for small compressed file after decompression
         assert!(
-            matches!(accessor.source, ByteSource::InMemory(_)),
+            matches!(accessor.pin().source(), ByteSource::InMemory(_)),
             "expected in-memory accessor for small compressed file, found {:?}",
-            accessor.source
+            accessor.pin().source()
         );
 
         // Test that decompression worked
@@ -566,25 +353,126 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_adaptive_accessor_string_conversion() {
-        let content = b"test line for borrowing\n";
-        let temp_file = create_test_file(content);
+    async fn test_spill_to_disk_preserves_content() {
+        let content: Vec<u8> = (0..2000)
+            .flat_map(|i| format!("spill line {i}\n").into_bytes())
+            .collect();
+        let temp_file = create_test_file(&content);
         let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+        assert!(matches!(accessor.pin().source(), ByteSource::InMemory(_)));
+
+        let size = accessor.file_size();
+        let before_bytes = accessor.read_bytes(0, size as usize).await.unwrap();
+        let before_lines = accessor.read_from_byte(1000, 50).await.unwrap();
+        let before_last = accessor.last_page_start(10).await.unwrap();
+
+        assert!(accessor.spill_to_disk().await.unwrap());
+        assert!(matches!(
+            accessor.pin().source(),
+            ByteSource::Spilled { .. }
+        ));
+
+        assert_eq!(accessor.file_size(), size);
+        assert_eq!(before_bytes, content);
+        assert_eq!(
+            accessor.read_bytes(0, size as usize).await.unwrap(),
+            before_bytes
+        );
+        assert_eq!(
+            accessor.read_from_byte(1000, 50).await.unwrap(),
+            before_lines
+        );
+        assert_eq!(accessor.last_page_start(10).await.unwrap(), before_last);
 
-        let lines = accessor.read_from_byte(0, 1).await.unwrap();
+        // Only in-memory sources are spilled
+        assert!(!accessor.spill_to_disk().await.unwrap());
+    }
 
-        // Verify we get the expected string
-        assert_eq!(lines[0], "test line for borrowing");
+    #[tokio::test]
+    async fn test_snapshot_keeps_its_source_across_a_spill() {
+        let content = b"first\nsecond\n".repeat(100);
+        let temp_file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+        let pinned = accessor.pin();
+
+        assert!(accessor.spill_to_disk().await.unwrap());
+        assert!(matches!(pinned.source(), ByteSource::InMemory(_)));
+        assert!(matches!(
+            accessor.pin().source(),
+            ByteSource::Spilled { .. }
+        ));
+
+        let snapshot = accessor.snapshot().unwrap();
+        assert_eq!(snapshot.file_size(), accessor.file_size());
+        assert_eq!(snapshot.file_path(), accessor.file_path());
+        assert_eq!(
+            snapshot.read_from_byte(6, 2).await.unwrap(),
+            vec!["second", "first"]
+        );
+        assert_eq!(pinned.read_bytes(0, content.len()).await.unwrap(), content);
     }
 
-    #[test]
-    fn test_byte_source_variants() {
-        let vec_data = vec![65, 10, 66, 10]; // "A\nB\n"
-        let in_memory = ByteSource::InMemory(vec_data);
+    #[tokio::test]
+    async fn test_spill_while_reading_in_background() {
+        let content = b"alpha\nbeta\ngamma\n".repeat(500);
+        let temp_file = create_test_file(&content);
+        let accessor = Arc::new(FileAccessorFactory::create(temp_file.path()).await.unwrap());
+
+        let spiller = {
+            let accessor = Arc::clone(&accessor);
+            tokio::spawn(async move { accessor.spill_to_disk().await })
+        };
+        for _ in 0..100 {
+            let lines = accessor.read_from_byte(0, 3).await.unwrap();
+            assert_eq!(lines, vec!["alpha", "beta", "gamma"]);
+        }
+        assert!(spiller.await.unwrap().unwrap());
+        assert_eq!(
+            accessor.read_bytes(0, content.len()).await.unwrap(),
+            content
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_picks_up_appended_data() {
+        for force_mmap in [false, true] {
+            let mut temp_file = create_test_file(b"one\ntwo\n");
+            let accessor = FileAccessorFactory::create_with_strategy(temp_file.path(), force_mmap)
+                .await
+                .unwrap();
+            assert!(!accessor.refresh().await.unwrap());
+
+            temp_file.write_all(b"three\n").unwrap();
+            temp_file.flush().unwrap();
+            assert!(accessor.refresh().await.unwrap());
+            assert_eq!(accessor.file_size(), 14);
+            assert_eq!(accessor.last_page_start(1).await.unwrap(), 8);
+            assert_eq!(
+                accessor.read_from_byte(0, 10).await.unwrap(),
+                vec!["one", "two", "three"]
+            );
+            assert!(!accessor.refresh().await.unwrap());
+        }
+    }
 
-        assert_eq!(in_memory.as_bytes(), &[65, 10, 66, 10]);
+    #[tokio::test]
+    async fn test_refresh_leaves_decompressed_content_alone() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut encoder = GzEncoder::new(
+            std::fs::File::create(temp_file.path()).unwrap(),
+            Compression::default(),
+        );
+        encoder.write_all(b"compressed\n").unwrap();
+        encoder.finish().unwrap();
 
-        let string_result = in_memory.bytes_to_string(&[65]).unwrap();
-        assert_eq!(string_result, "A");
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(temp_file.path())
+            .unwrap()
+            .write_all(b"garbage")
+            .unwrap();
+        assert!(!accessor.refresh().await.unwrap());
+        assert_eq!(accessor.file_size(), 11);
     }
 }
@@ -0,0 +1,141 @@
+//! Buffer that piped input is appended to while readers share what it already holds.
+//!
+//! Each `SharedBytes` handed out covers the bytes appended before it was made, which never
+//! change again, so making one is an `Arc` clone rather than a copy of everything read so far.
+//! The only copies are when the buffer fills up and moves to one twice its size; versions made
+//! before that keep the old buffer alive.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Heap buffer of fixed capacity, written by one `AppendBuffer` and read through `SharedBytes`
+struct Storage {
+    ptr: *mut u8,
+    capacity: usize,
+}
+
+// SAFETY: the owning `AppendBuffer` only writes at or past its length, and every `SharedBytes`
+// only reads below the length the buffer had when it was made, so no byte is read and written
+// at the same time.
+unsafe impl Send for Storage {}
+unsafe impl Sync for Storage {}
+
+impl Storage {
+    fn new(capacity: usize) -> Self {
+        let ptr = Box::into_raw(vec![0u8; capacity].into_boxed_slice()) as *mut u8;
+        Self { ptr, capacity }
+    }
+}
+
+impl Drop for Storage {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` and `capacity` describe the boxed slice made in `new`
+        unsafe {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                self.ptr,
+                self.capacity,
+            )));
+        }
+    }
+}
+
+/// Growable byte buffer whose contents can be shared without copying while it keeps growing
+pub struct AppendBuffer {
+    storage: Arc<Storage>,
+    len: usize,
+}
+
+impl AppendBuffer {
+    /// Empty buffer with room for `capacity` bytes before it first grows
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            storage: Arc::new(Storage::new(capacity)),
+            len: 0,
+        }
+    }
+
+    /// Add `bytes` at the end, moving to a larger buffer when they do not fit
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        if self.storage.capacity - self.len < bytes.len() {
+            let grown = Storage::new((self.storage.capacity * 2).max(self.len + bytes.len()));
+            // SAFETY: the first `len` bytes are written and no longer change; `grown` is new
+            unsafe { std::ptr::copy_nonoverlapping(self.storage.ptr, grown.ptr, self.len) };
+            self.storage = Arc::new(grown);
+        }
+        // SAFETY: the range fits the capacity and lies past `len`, where no reader looks
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                self.storage.ptr.add(self.len),
+                bytes.len(),
+            );
+        }
+        self.len += bytes.len();
+    }
+
+    /// Bytes appended so far
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: the first `len` bytes are written and only ever read from here on
+        unsafe { std::slice::from_raw_parts(self.storage.ptr, self.len) }
+    }
+
+    /// Bytes appended so far, sharing the buffer
+    pub fn share(&self) -> SharedBytes {
+        SharedBytes {
+            storage: Arc::clone(&self.storage),
+            len: self.len,
+        }
+    }
+}
+
+/// Bytes an `AppendBuffer` held when `share` was called
+pub struct SharedBytes {
+    storage: Arc<Storage>,
+    len: usize,
+}
+
+impl SharedBytes {
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: these bytes were written before `share` and the buffer never writes them again
+        unsafe { std::slice::from_raw_parts(self.storage.ptr, self.len) }
+    }
+}
+
+impl fmt::Debug for SharedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedBytes")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_bytes_keep_their_content_as_the_buffer_grows() {
+        let mut buffer = AppendBuffer::with_capacity(8);
+        buffer.extend_from_slice(b"one\n");
+        let first = buffer.share();
+        buffer.extend_from_slice(b"two\n");
+        let second = buffer.share();
+        // Both versions read the same allocation
+        assert_eq!(first.as_slice().as_ptr(), second.as_slice().as_ptr());
+
+        buffer.extend_from_slice(b"three\n");
+        assert_eq!(buffer.as_slice(), b"one\ntwo\nthree\n");
+        assert_eq!(first.as_slice(), b"one\n");
+        assert_eq!(second.as_slice(), b"one\ntwo\n");
+        drop(buffer);
+        assert_eq!(second.as_slice(), b"one\ntwo\n");
+    }
+
+    #[test]
+    fn test_empty_buffer() {
+        let mut buffer = AppendBuffer::with_capacity(0);
+        assert!(buffer.share().as_slice().is_empty());
+        buffer.extend_from_slice(b"grown from nothing");
+        assert_eq!(buffer.share().as_slice(), b"grown from nothing");
+    }
+}
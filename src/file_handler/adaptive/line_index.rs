@@ -0,0 +1,252 @@
+//! Line numbers of byte offsets, for the `-N` gutter.
+//!
+//! Counting newlines up to an offset is a linear scan, so the counts are kept at every
+//! `STRIDE` bytes and a lookup only scans from the checkpoint before it. The content only ever
+//! grows by appending, so checkpoints stay valid across content swaps and live in `FileInfo`.
+//!
+//! Checkpoints near the start are built on the spot. A lookup far past the last checkpoint
+//! (after `G` in a multi-gigabyte file) would stall the viewport, so it returns `None` and the
+//! rest of the content is indexed on a background thread instead.
+//!
+//! How far the checkpoints reach is also kept in an atomic, so progress displays can poll the
+//! coverage without contending for the lock the indexing thread takes.
+
+use super::pinned::Content;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Distance between checkpoints
+const STRIDE: usize = 1 << 20;
+
+/// Most bytes a lookup indexes itself before handing the work to a background thread
+const BLOCKING_LIMIT: usize = 32 << 20;
+
+/// Newline counts at every `STRIDE` bytes of an accessor's content
+#[derive(Debug)]
+pub(super) struct LineIndex {
+    // Entry `k` is the number of newlines in the first `k * STRIDE` bytes
+    checkpoints: Mutex<Vec<u64>>,
+    // Bytes the checkpoints cover, `(checkpoints.len() - 1) * STRIDE`
+    covered: AtomicU64,
+    indexing: AtomicBool,
+}
+
+impl Default for LineIndex {
+    fn default() -> Self {
+        Self {
+            checkpoints: Mutex::new(vec![0]),
+            covered: AtomicU64::new(0),
+            indexing: AtomicBool::new(false),
+        }
+    }
+}
+
+impl LineIndex {
+    /// 1-based number of the line containing `byte`, lines ending at `sep`, or `None` while it is
+    /// still being indexed
+    pub(super) fn line_number(
+        self: &Arc<Self>,
+        content: &Arc<Content>,
+        byte: u64,
+        sep: u8,
+    ) -> Option<u64> {
+        let bytes = content.source.as_bytes();
+        let byte = (byte as usize).min(bytes.len());
+        let stride = byte / STRIDE;
+
+        let checkpoint = {
+            let mut checkpoints = self.checkpoints.lock();
+            if checkpoints.len() <= stride {
+                if (stride + 1 - checkpoints.len()) * STRIDE > BLOCKING_LIMIT {
+                    drop(checkpoints);
+                    self.index_in_background(Arc::clone(content), sep);
+                    return None;
+                }
+                while checkpoints.len() <= stride {
+                    let next = checkpoints.len();
+                    let before = checkpoints[next - 1];
+                    self.push(&mut checkpoints, before + count_stride(bytes, next, sep));
+                }
+            }
+            checkpoints[stride]
+        };
+        let newlines = memchr::memchr_iter(sep, &bytes[stride * STRIDE..byte]).count() as u64;
+        Some(checkpoint + newlines + 1)
+    }
+
+    /// Bytes of content `total_bytes` long whose line numbers are known without further indexing
+    ///
+    /// Lookups count the bytes past the last checkpoint themselves, so once those are less than
+    /// a stride the whole content counts as indexed. Reads no lock.
+    pub(super) fn indexed_byte_count(&self, total_bytes: u64) -> u64 {
+        let covered = self.covered.load(Ordering::Acquire);
+        if total_bytes.saturating_sub(covered) < STRIDE as u64 {
+            total_bytes
+        } else {
+            covered
+        }
+    }
+
+    /// Add the next checkpoint, under the lock
+    fn push(&self, checkpoints: &mut Vec<u64>, newlines: u64) {
+        checkpoints.push(newlines);
+        let covered = ((checkpoints.len() - 1) * STRIDE) as u64;
+        self.covered.store(covered, Ordering::Release);
+    }
+
+    /// Build the checkpoints for all of `content`, unless a thread is already at it
+    ///
+    /// The lock is only held to add each checkpoint, so lookups near the start keep answering.
+    fn index_in_background(self: &Arc<Self>, content: Arc<Content>, sep: u8) {
+        if self.indexing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let index = Arc::clone(self);
+        std::thread::spawn(move || {
+            let bytes = content.source.as_bytes();
+            let last = bytes.len() / STRIDE;
+            loop {
+                let next = index.checkpoints.lock().len();
+                if next > last {
+                    break;
+                }
+                let counted = count_stride(bytes, next, sep);
+                let mut checkpoints = index.checkpoints.lock();
+                // A lookup may have added it meanwhile
+                if checkpoints.len() == next {
+                    let before = checkpoints[next - 1];
+                    index.push(&mut checkpoints, before + counted);
+                }
+            }
+            log::debug!("accessor: indexed lines of {} bytes", bytes.len());
+            index.indexing.store(false, Ordering::Release);
+        });
+    }
+}
+
+/// Line ends in the stride that ends at checkpoint `next`
+fn count_stride(bytes: &[u8], next: usize, sep: u8) -> u64 {
+    memchr::memchr_iter(sep, &bytes[(next - 1) * STRIDE..next * STRIDE]).count() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::adaptive::ByteSource;
+    use std::time::{Duration, Instant};
+
+    fn content(bytes: Vec<u8>) -> Arc<Content> {
+        Arc::new(Content {
+            file_size: bytes.len() as u64,
+            source: ByteSource::InMemory(bytes),
+        })
+    }
+
+    /// Lines of `width - 1` bytes and a newline each
+    fn lines(count: usize, width: usize) -> Vec<u8> {
+        let mut line = vec![b'x'; width - 1];
+        line.push(b'\n');
+        line.repeat(count)
+    }
+
+    #[test]
+    fn numbers_count_newlines_before_the_byte() {
+        let index = Arc::new(LineIndex::default());
+        let short = content(b"one\ntwo\n\nfour".to_vec());
+        let numbers: Vec<_> = [0, 3, 4, 8, 9, 12, 100]
+            .iter()
+            .map(|&byte| index.line_number(&short, byte, b'\n'))
+            .collect();
+        let expected = [1, 1, 2, 3, 4, 4, 4].map(Some);
+        assert_eq!(numbers, expected);
+
+        // Across checkpoints, and after the content grew
+        let index = Arc::new(LineIndex::default());
+        let small = content(lines(STRIDE / 64 * 3, 64));
+        assert_eq!(
+            index.line_number(&small, (STRIDE * 2) as u64, b'\n'),
+            Some(2 * 16384 + 1)
+        );
+        let mut longer = lines(STRIDE / 64 * 3, 64);
+        longer.extend(lines(10, 64));
+        let grown = content(longer);
+        let end = grown.file_size - 64;
+        assert_eq!(index.line_number(&grown, end, b'\n'), Some(3 * 16384 + 10));
+    }
+
+    #[test]
+    fn far_lookups_are_answered_once_indexed_in_the_background() {
+        let index = Arc::new(LineIndex::default());
+        let content = content(lines(BLOCKING_LIMIT / 16 * 2, 16));
+        let last_line = content.file_size - 16;
+        assert_eq!(index.line_number(&content, last_line, b'\n'), None);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let number = loop {
+            if let Some(number) = index.line_number(&content, last_line, b'\n') {
+                break number;
+            }
+            assert!(Instant::now() < deadline, "indexing did not finish");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(number, (BLOCKING_LIMIT / 16 * 2) as u64);
+    }
+
+    #[test]
+    fn coverage_counts_the_bytes_a_lookup_needs_no_indexing_for() {
+        let index = Arc::new(LineIndex::default());
+        let total = (STRIDE * 3 + 10) as u64;
+        assert_eq!(index.indexed_byte_count(total), 0);
+        // Less than a stride is counted on every lookup
+        assert_eq!(index.indexed_byte_count(10), 10);
+
+        let content = content(lines(STRIDE / 64 * 3 + 10, 64));
+        index.line_number(&content, STRIDE as u64 + 5, b'\n');
+        assert_eq!(index.indexed_byte_count(total), STRIDE as u64);
+        index.line_number(&content, content.file_size - 1, b'\n');
+        assert_eq!(
+            index.indexed_byte_count(content.file_size),
+            content.file_size
+        );
+    }
+
+    #[test]
+    fn coverage_is_readable_while_the_background_thread_indexes() {
+        let index = Arc::new(LineIndex::default());
+        let content = content(lines(BLOCKING_LIMIT / 16 * 4, 16));
+        let total = content.file_size;
+        assert_eq!(index.line_number(&content, total - 1, b'\n'), None);
+
+        // Pollers on other threads see the coverage only ever grow, up to the whole content
+        let pollers: Vec<_> = (0..4)
+            .map(|_| {
+                let index = Arc::clone(&index);
+                std::thread::spawn(move || {
+                    let deadline = Instant::now() + Duration::from_secs(10);
+                    let mut last = 0;
+                    while last < total {
+                        let covered = index.indexed_byte_count(total);
+                        assert!(
+                            covered >= last,
+                            "coverage went from {} to {}",
+                            last,
+                            covered
+                        );
+                        assert!(covered <= total);
+                        last = covered;
+                        assert!(Instant::now() < deadline, "indexing did not finish");
+                        std::thread::yield_now();
+                    }
+                })
+            })
+            .collect();
+        for poller in pollers {
+            poller.join().unwrap();
+        }
+        assert_eq!(
+            index.line_number(&content, total - 1, b'\n'),
+            Some((BLOCKING_LIMIT / 16 * 4) as u64)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Forward match scans split across threads.
+//!
+//! A forward search for a rare pattern from the top of a large file is bound by how fast one core
+//! can run the matcher over every line. `find_next_match` instead cuts the rest of the content
+//! into chunks of about `CHUNK_BYTES`, each holding the lines that start inside it, and a thread
+//! per core claims chunks in file order. A thread stops claiming, and drops the chunk it is on,
+//! once that chunk begins after the first match found so far. Every chunk before the one holding
+//! that match is still scanned to its end, so the result is the first match in the file, exactly
+//! what scanning line by line from the origin finds, whichever thread finishes first.
+
+use super::rows::segment_containing;
+use crate::error::Result;
+use crate::file_handler::accessor::{ScanProgress, SCAN_PROGRESS_STRIDE};
+use crate::util::check_cancelled;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// Content left to scan below which one thread does the whole scan
+pub(super) const PARALLEL_MIN_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Bytes per chunk claimed by a thread
+const CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+type SearchFn<'a> = &'a (dyn for<'b> Fn(&'b str) -> Vec<(usize, usize)> + Send + Sync);
+
+/// Row of the first match at or after `start`, scanning chunks on one thread per core
+pub(super) fn find_next_match(
+    bytes: &[u8],
+    sep: u8,
+    start: usize,
+    search_fn: SearchFn<'_>,
+    cancel_flag: Option<&AtomicBool>,
+    progress: Option<ScanProgress<'_>>,
+) -> Result<Option<usize>> {
+    let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    find_in_chunks(
+        bytes,
+        sep,
+        start,
+        CHUNK_BYTES,
+        threads,
+        search_fn,
+        cancel_flag,
+        progress,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn find_in_chunks(
+    bytes: &[u8],
+    sep: u8,
+    start: usize,
+    chunk_bytes: usize,
+    threads: usize,
+    search_fn: SearchFn<'_>,
+    cancel_flag: Option<&AtomicBool>,
+    progress: Option<ScanProgress<'_>>,
+) -> Result<Option<usize>> {
+    if start >= bytes.len() {
+        return Ok(None);
+    }
+    let chunks = (bytes.len() - start).div_ceil(chunk_bytes);
+    let next_chunk = AtomicUsize::new(0);
+    // Row of the first match found so far, `usize::MAX` for none
+    let best = AtomicUsize::new(usize::MAX);
+    let scanned = AtomicU64::new(0);
+
+    let worker = || -> Result<()> {
+        loop {
+            let index = next_chunk.fetch_add(1, Ordering::Relaxed);
+            if index >= chunks {
+                return Ok(());
+            }
+            let from = line_start_at(bytes, sep, start, start + index * chunk_bytes);
+            let to = line_start_at(bytes, sep, start, start + (index + 1) * chunk_bytes);
+            // Chunks are claimed in order, so every later one begins past the match too
+            if from > best.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            if let Some(row) = scan_chunk(bytes, sep, from, to, search_fn, cancel_flag, &best)? {
+                best.fetch_min(row, Ordering::Relaxed);
+            }
+
+            let length = (to - from) as u64;
+            let covered = scanned.fetch_add(length, Ordering::Relaxed) + length;
+            if let Some(progress) = progress.filter(|_| {
+                covered / SCAN_PROGRESS_STRIDE > (covered - length) / SCAN_PROGRESS_STRIDE
+            }) {
+                progress(covered);
+            }
+        }
+    };
+
+    std::thread::scope(|scope| {
+        let helpers: Vec<_> = (1..threads.min(chunks))
+            .map(|_| scope.spawn(worker))
+            .collect();
+        let own = worker();
+        helpers
+            .into_iter()
+            .map(|helper| {
+                helper
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .fold(own, Result::and)
+    })?;
+    Ok(Some(best.into_inner()).filter(|&row| row != usize::MAX))
+}
+
+/// First line start at or after `pos`, counting `start` as one
+fn line_start_at(bytes: &[u8], sep: u8, start: usize, pos: usize) -> usize {
+    if pos <= start {
+        start
+    } else if pos >= bytes.len() {
+        bytes.len()
+    } else if bytes[pos - 1] == sep {
+        pos
+    } else {
+        memchr::memchr(sep, &bytes[pos..]).map_or(bytes.len(), |at| pos + at + 1)
+    }
+}
+
+/// Row of the first match in the lines starting in `[from, to)`
+///
+/// Gives up with `None` once `best` holds a match before `from`, which no match here can beat.
+fn scan_chunk(
+    bytes: &[u8],
+    sep: u8,
+    from: usize,
+    to: usize,
+    search_fn: SearchFn<'_>,
+    cancel_flag: Option<&AtomicBool>,
+    best: &AtomicUsize,
+) -> Result<Option<usize>> {
+    let mut pos = from;
+    while pos < to {
+        check_cancelled(cancel_flag)?;
+        if best.load(Ordering::Relaxed) < from {
+            return Ok(None);
+        }
+        let line_end = memchr::memchr(sep, &bytes[pos..]).map_or(bytes.len(), |at| pos + at);
+        if let Ok(line) = std::str::from_utf8(&bytes[pos..line_end]) {
+            if let Some(match_start) = search_fn(line).iter().map(|&(start, _)| start).min() {
+                return Ok(Some(segment_containing(bytes, pos, line_end, match_start)));
+            }
+        }
+        pos = line_end + 1;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::SEGMENT_BYTES;
+
+    /// The row a line-by-line scan from `start` finds
+    fn sequential(bytes: &[u8], start: usize, needle: &str) -> Option<usize> {
+        let mut line_start = start;
+        for line in bytes[start..].split(|&byte| byte == b'\n') {
+            let line_end = line_start + line.len();
+            if let Some(at) = std::str::from_utf8(line).unwrap().find(needle) {
+                return Some(segment_containing(bytes, line_start, line_end, at));
+            }
+            line_start = line_end + 1;
+        }
+        None
+    }
+
+    #[test]
+    fn chunked_scans_find_the_first_match_from_the_origin() {
+        let mut content = String::new();
+        for line in 0..400 {
+            match line % 97 {
+                13 => content.push_str("rare needle here\n"),
+                50 => content.push_str(&"x".repeat(SEGMENT_BYTES + 100)),
+                _ => content.push_str(&format!("line {line} of filler\n")),
+            }
+            if line == 150 {
+                content.push_str(&format!("{}needle\n", "y".repeat(SEGMENT_BYTES * 2)));
+            }
+        }
+        let bytes = content.as_bytes();
+        let search_fn = |line: &str| {
+            line.match_indices("needle")
+                .map(|(at, _)| (at, at + 6))
+                .collect()
+        };
+
+        for start in (0..bytes.len()).step_by(997).chain([bytes.len() - 1]) {
+            let expected = sequential(bytes, start, "needle");
+            for (chunk_bytes, threads) in [(64, 4), (1000, 3), (5000, 8), (bytes.len(), 2)] {
+                let found = find_in_chunks(
+                    bytes,
+                    b'\n',
+                    start,
+                    chunk_bytes,
+                    threads,
+                    &search_fn,
+                    None,
+                    None,
+                )
+                .unwrap();
+                assert_eq!(found, expected, "start {start}, chunks of {chunk_bytes}");
+            }
+        }
+        let found = find_in_chunks(bytes, b'\n', 0, 64, 4, &|_| Vec::new(), None, None);
+        assert_eq!(found.unwrap(), None);
+    }
+
+    #[test]
+    fn cancelling_stops_every_thread() {
+        let content = "no match on this line\n".repeat(10_000);
+        let cancel = AtomicBool::new(true);
+        let found = find_in_chunks(
+            content.as_bytes(),
+            b'\n',
+            0,
+            1000,
+            4,
+            &|_| Vec::new(),
+            Some(&cancel),
+            None,
+        );
+        assert!(found.is_err());
+    }
+}
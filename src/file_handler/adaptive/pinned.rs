@@ -0,0 +1,732 @@
+//! Reads on one version of an `AdaptiveFileAccessor`'s content.
+
+use super::line_index::LineIndex;
+use super::parallel;
+use super::rows::{row_bounds, rows_back, rows_forward, segment_containing};
+use super::source::ByteSource;
+use crate::error::Result;
+use crate::file_handler::accessor::{
+    FileAccessor, IndexingProgress, RawPage, ScanProgress, SCAN_PROGRESS_STRIDE,
+};
+use crate::file_handler::identity::FileIdentity;
+use crate::file_handler::meta::FileMeta;
+use crate::file_handler::terminator::LineTerminator;
+use crate::util::check_cancelled;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+/// One version of the accessor's content
+#[derive(Debug)]
+pub(super) struct Content {
+    pub(super) source: ByteSource,
+    pub(super) file_size: u64,
+}
+
+/// What stays the same across content swaps
+#[derive(Debug, Clone)]
+pub(super) struct FileInfo {
+    pub(super) file_path: PathBuf,
+    pub(super) meta: Option<FileMeta>,
+    pub(super) identity: Option<FileIdentity>,
+    // Uncompressed start offsets of the gzip members the content was decoded from
+    pub(super) sections: Vec<u64>,
+    // The content is the file at `file_path` as is, so growth can be picked up by remapping it
+    pub(super) refreshable: bool,
+    pub(super) lines: Arc<LineIndex>,
+    pub(super) terminator: LineTerminator,
+}
+
+/// `AdaptiveFileAccessor` reads on one fixed version of the content, without locking
+///
+/// Handed out by `AdaptiveFileAccessor::pin` and, as a `FileAccessor`, by `snapshot`. It keeps
+/// that version alive, so hold it for an operation rather than for the session.
+#[derive(Debug, Clone)]
+pub struct PinnedAccessor {
+    pub(super) content: Arc<Content>,
+    pub(super) info: Arc<FileInfo>,
+}
+
+impl PinnedAccessor {
+    /// Storage strategy of the pinned content
+    #[cfg(test)]
+    pub(crate) fn source(&self) -> &ByteSource {
+        &self.content.source
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.content.source.as_bytes()
+    }
+
+    /// The byte lines end at
+    fn sep(&self) -> u8 {
+        self.info.terminator.byte()
+    }
+}
+
+#[async_trait]
+impl FileAccessor for PinnedAccessor {
+    async fn read_from_byte(&self, start_byte: u64, max_lines: usize) -> Result<Vec<String>> {
+        let source = &self.content.source;
+        let bytes = source.as_bytes();
+        if start_byte as usize >= bytes.len() {
+            return Ok(Vec::new());
+        }
+
+        let sep = self.sep();
+        let mut lines = Vec::new();
+        let mut current_pos = start_byte as usize;
+
+        while lines.len() < max_lines && current_pos < bytes.len() {
+            let (row_end, next_row) = row_bounds(bytes, current_pos, sep);
+            let row_bytes = &bytes[current_pos..row_end];
+
+            // A jump can land inside a multi-byte character
+            let is_segment =
+                next_row == Some(row_end) || (current_pos > 0 && bytes[current_pos - 1] != sep);
+            let row = match source.bytes_to_string(row_bytes) {
+                Ok(row) => row,
+                Err(_) if is_segment => String::from_utf8_lossy(row_bytes).into_owned(),
+                Err(error) => return Err(error),
+            };
+            lines.push(row);
+
+            match next_row {
+                Some(next) => current_pos = next,
+                None => break, // End of file
+            }
+        }
+
+        Ok(lines)
+    }
+
+    async fn read_from_byte_raw(&self, start_byte: u64, max_lines: usize) -> Result<RawPage> {
+        let bytes = self.bytes();
+        let start = start_byte as usize;
+        if start >= bytes.len() {
+            return Ok(RawPage::default());
+        }
+
+        let sep = self.sep();
+        let mut rows = Vec::new();
+        let mut current_pos = start;
+        let mut end = start;
+        while rows.len() < max_lines && current_pos < bytes.len() {
+            let (row_end, next_row) = row_bounds(bytes, current_pos, sep);
+            rows.push(current_pos - start..row_end - start);
+            end = row_end;
+            match next_row {
+                Some(next) => current_pos = next,
+                None => break,
+            }
+        }
+
+        Ok(RawPage {
+            bytes: bytes[start..end].to_vec(),
+            rows,
+        })
+    }
+
+    async fn read_bytes(&self, start_byte: u64, length: usize) -> Result<Vec<u8>> {
+        let bytes = self.bytes();
+        let start = (start_byte as usize).min(bytes.len());
+        let end = start.saturating_add(length).min(bytes.len());
+        Ok(bytes[start..end].to_vec())
+    }
+
+    async fn find_next_match(
+        &self,
+        start_byte: u64,
+        search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+        cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
+    ) -> Result<Option<u64>> {
+        let bytes = self.bytes();
+        let sep = self.sep();
+        if start_byte as usize >= bytes.len() {
+            return Ok(None);
+        }
+
+        let mut current_pos = start_byte as usize;
+        let mut next_report = start_byte + SCAN_PROGRESS_STRIDE;
+
+        while current_pos < bytes.len() {
+            check_cancelled(cancel_flag)?;
+            if let Some(progress) = progress.filter(|_| current_pos as u64 >= next_report) {
+                progress(current_pos as u64 - start_byte);
+                next_report = current_pos as u64 + SCAN_PROGRESS_STRIDE;
+            }
+            // Find the end of the current line
+            let line_end = memchr::memchr(sep, &bytes[current_pos..])
+                .map(|pos| current_pos + pos)
+                .unwrap_or(bytes.len());
+
+            // Extract the line content
+            let line_bytes = &bytes[current_pos..line_end];
+            if let Ok(line_str) = std::str::from_utf8(line_bytes) {
+                let first_match = search_fn(line_str).iter().map(|&(start, _)| start).min();
+                if let Some(match_start) = first_match {
+                    let row = segment_containing(bytes, current_pos, line_end, match_start);
+                    return Ok(Some(row as u64));
+                }
+            }
+
+            // Move to the start of the next line
+            current_pos = if line_end < bytes.len() {
+                line_end + 1
+            } else {
+                break;
+            };
+        }
+
+        Ok(None)
+    }
+
+    async fn find_next_match_parallel(
+        &self,
+        start_byte: u64,
+        search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+        cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
+    ) -> Result<Option<u64>> {
+        // In-memory and spilled content is under the factory's mapping threshold; piped input
+        // keeps the single-threaded scan
+        let mapped = matches!(
+            self.content.source,
+            ByteSource::MemoryMapped(_) | ByteSource::Compressed { .. }
+        );
+        let remaining = self.content.file_size.saturating_sub(start_byte);
+        if !mapped || remaining < parallel::PARALLEL_MIN_BYTES {
+            return self
+                .find_next_match(start_byte, search_fn, cancel_flag, progress)
+                .await;
+        }
+        let row = parallel::find_next_match(
+            self.bytes(),
+            self.sep(),
+            start_byte as usize,
+            search_fn,
+            cancel_flag,
+            progress,
+        )?;
+        Ok(row.map(|row| row as u64))
+    }
+
+    async fn find_prev_match(
+        &self,
+        start_byte: u64,
+        search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+        cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
+    ) -> Result<Option<u64>> {
+        let bytes = self.bytes();
+        let sep = self.sep();
+        if start_byte == 0 {
+            return Ok(None);
+        }
+
+        // Exclusive end of the region still to be searched; everything before `start_byte`
+        let mut region_end = (start_byte as usize).min(bytes.len());
+        let mut next_report = (region_end as u64).saturating_sub(SCAN_PROGRESS_STRIDE);
+
+        // Search backward line by line
+        while region_end > 0 {
+            check_cancelled(cancel_flag)?;
+            if let Some(progress) = progress.filter(|_| (region_end as u64) <= next_report) {
+                progress(start_byte - region_end as u64);
+                next_report = (region_end as u64).saturating_sub(SCAN_PROGRESS_STRIDE);
+            }
+            // The region ends either at a line's newline or inside a long line
+            let line_end = if bytes[region_end - 1] == sep {
+                region_end - 1
+            } else {
+                region_end
+            };
+            let line_start = memchr::memrchr(sep, &bytes[..line_end]).map_or(0, |pos| pos + 1);
+
+            // Extract and check the line content
+            let line_bytes = &bytes[line_start..line_end];
+            if let Ok(line_str) = std::str::from_utf8(line_bytes) {
+                let last_match = search_fn(line_str).iter().map(|&(start, _)| start).max();
+                if let Some(match_start) = last_match {
+                    let row = segment_containing(bytes, line_start, line_end, match_start);
+                    return Ok(Some(row as u64));
+                }
+            }
+
+            // Move to search the previous line
+            region_end = line_start;
+        }
+
+        Ok(None)
+    }
+
+    fn file_size(&self) -> u64 {
+        self.content.file_size
+    }
+
+    fn file_path(&self) -> &Path {
+        &self.info.file_path
+    }
+
+    fn ends_with_newline(&self) -> bool {
+        !matches!(self.bytes().last(), Some(&byte) if byte != self.sep())
+    }
+
+    fn file_meta(&self) -> Option<FileMeta> {
+        self.info.meta
+    }
+
+    fn identity(&self) -> Option<&FileIdentity> {
+        self.info.identity.as_ref()
+    }
+
+    fn sections(&self) -> &[u64] {
+        &self.info.sections
+    }
+
+    fn line_terminator(&self) -> LineTerminator {
+        self.info.terminator
+    }
+
+    fn line_number_for_byte(&self, byte: u64) -> Option<u64> {
+        self.info.lines.line_number(&self.content, byte, self.sep())
+    }
+
+    fn indexing_progress(&self) -> Option<IndexingProgress> {
+        let total_bytes = self.content.file_size;
+        Some(IndexingProgress {
+            indexed_bytes: self.info.lines.indexed_byte_count(total_bytes),
+            total_bytes,
+        })
+    }
+
+    async fn last_page_start(&self, max_lines: usize) -> Result<u64> {
+        let bytes = self.bytes();
+        if bytes.is_empty() || max_lines == 0 {
+            return Ok(0);
+        }
+
+        // Step back `max_lines` rows from the end; a trailing newline does not start a row
+        Ok(rows_back(bytes, bytes.len(), max_lines, self.sep()) as u64)
+    }
+
+    async fn next_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
+        let bytes = self.bytes();
+        // Running out of rows returns the EOF indicator
+        Ok(
+            rows_forward(bytes, current_byte as usize, lines_to_skip, self.sep())
+                .map_or(self.content.file_size, |pos| pos as u64),
+        )
+    }
+
+    async fn prev_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
+        let bytes = self.bytes();
+        if current_byte == 0 || lines_to_skip == 0 {
+            return Ok(0);
+        }
+
+        Ok(rows_back(
+            bytes,
+            (current_byte as usize).min(bytes.len()),
+            lines_to_skip,
+            self.sep(),
+        ) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::adaptive::tests::create_test_file;
+    use crate::file_handler::factory::FileAccessorFactory;
+    use crate::file_handler::SEGMENT_BYTES;
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_small_file() {
+        let content = b"line1\nline2\nline3\n";
+        let temp_file = create_test_file(content);
+
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        assert_eq!(accessor.file_size(), content.len() as u64);
+        assert_eq!(accessor.file_path(), temp_file.path());
+
+        // Should use InMemory for small file
+        match accessor.pin().source() {
+            ByteSource::InMemory(_) => {} // Expected
+            _ => panic!("Small file should use InMemory variant"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_read_from_byte() {
+        let content = b"line1\nline2\nline3\n";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        // Read from beginning
+        let lines = accessor.read_from_byte(0, 2).await.unwrap();
+        assert_eq!(lines, vec!["line1", "line2"]);
+
+        // Read from middle (byte 6 is start of "line2")
+        let lines = accessor.read_from_byte(6, 2).await.unwrap();
+        assert_eq!(lines, vec!["line2", "line3"]);
+
+        // Read from end of file
+        let lines = accessor.read_from_byte(100, 2).await.unwrap();
+        assert!(lines.is_empty());
+
+        // Read with limit
+        let lines = accessor.read_from_byte(0, 1).await.unwrap();
+        assert_eq!(lines, vec!["line1"]);
+    }
+
+    #[tokio::test]
+    async fn test_raw_pages_hold_the_rows_read_from_byte_gives() {
+        let long = "x".repeat(SEGMENT_BYTES + 10);
+        let content = format!("line1\n{long}\n\nlast");
+        let temp_file = create_test_file(content.as_bytes());
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        for (start, max_lines) in [(0, 10), (0, 2), (6, 3), (6 + SEGMENT_BYTES as u64, 10)] {
+            let page = accessor.read_from_byte_raw(start, max_lines).await.unwrap();
+            let lines = accessor.read_from_byte(start, max_lines).await.unwrap();
+            assert_eq!(page.to_lines(), lines, "{start}, {max_lines}");
+            let end = page.rows.last().map_or(0, |row| row.end);
+            assert_eq!(page.bytes.len(), end);
+            assert_eq!(
+                &page.bytes[..],
+                &content.as_bytes()[start as usize..][..end]
+            );
+        }
+        assert!(accessor
+            .read_from_byte_raw(100_000, 2)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Invalid UTF-8 is kept for matching rather than failing the read
+        let temp_file = create_test_file(b"ok\n\xff\xfe\n");
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+        assert!(accessor.read_from_byte(0, 2).await.is_err());
+        let page = accessor.read_from_byte_raw(0, 2).await.unwrap();
+        assert_eq!(
+            page.row_bytes().collect::<Vec<_>>(),
+            [&b"ok"[..], b"\xff\xfe"]
+        );
+        assert_eq!(page.to_lines(), ["ok", "\u{fffd}\u{fffd}"]);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_read_bytes() {
+        let content = b"line1\r\nline2";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        assert_eq!(accessor.read_bytes(0, 7).await.unwrap(), b"line1\r\n");
+        // Short read at EOF keeps the missing trailing newline as-is
+        assert_eq!(accessor.read_bytes(7, 100).await.unwrap(), b"line2");
+        assert!(accessor.read_bytes(100, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_find_next_match() {
+        let content = b"error line\nnormal line\nerror again\n";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        let error_search = |line: &str| {
+            let mut matches = Vec::new();
+            let mut start = 0;
+            while let Some(pos) = line[start..].find("error") {
+                let match_start = start + pos;
+                let match_end = match_start + "error".len();
+                matches.push((match_start, match_end));
+                start = match_end;
+            }
+            matches
+        };
+
+        // Find first match
+        let result = accessor
+            .find_next_match(0, &error_search, None, None)
+            .await
+            .unwrap();
+        assert_eq!(result, Some(0));
+
+        // Find second match
+        let result = accessor
+            .find_next_match(15, &error_search, None, None)
+            .await
+            .unwrap();
+        assert!(result.is_some());
+        let byte_pos = result.unwrap();
+        assert!(byte_pos > 15);
+
+        // No match found
+        let no_match_search = |_line: &str| Vec::new();
+        let result = accessor
+            .find_next_match(0, &no_match_search, None, None)
+            .await
+            .unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_find_prev_match() {
+        let content = b"error line\nnormal line\nerror again\n";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        let error_search = |line: &str| {
+            let mut matches = Vec::new();
+            let mut start = 0;
+            while let Some(pos) = line[start..].find("error") {
+                let match_start = start + pos;
+                let match_end = match_start + "error".len();
+                matches.push((match_start, match_end));
+                start = match_end;
+            }
+            matches
+        };
+
+        // Find match searching backward from end
+        let result = accessor
+            .find_prev_match(100, &error_search, None, None)
+            .await
+            .unwrap();
+        assert!(result.is_some());
+
+        // No match from beginning
+        let result = accessor
+            .find_prev_match(0, &error_search, None, None)
+            .await
+            .unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_match_scans_report_their_progress() {
+        use crate::file_handler::adaptive::AdaptiveFileAccessor;
+        use std::sync::Mutex;
+
+        // A needle at each end of two and a half strides of filler
+        let mut content = b"needle\n".to_vec();
+        let filler = [vec![b'a'; 1023], vec![b'\n']].concat();
+        while (content.len() as u64) < SCAN_PROGRESS_STRIDE * 5 / 2 {
+            content.extend_from_slice(&filler);
+        }
+        let last = content.len() as u64;
+        content.extend_from_slice(b"needle\n");
+        let size = content.len() as u64;
+        let accessor =
+            AdaptiveFileAccessor::new(ByteSource::InMemory(content), size, PathBuf::from("/test"));
+        let needle = |line: &str| {
+            line.find("needle")
+                .map(|at| (at, at + 6))
+                .into_iter()
+                .collect()
+        };
+        let reports = Mutex::new(Vec::new());
+        let progress = |scanned| reports.lock().unwrap().push(scanned);
+
+        let found = accessor
+            .find_next_match(7, &needle, None, Some(&progress))
+            .await
+            .unwrap();
+        assert_eq!(found, Some(last));
+        let forward = std::mem::take(&mut *reports.lock().unwrap());
+        assert_eq!(forward.len(), 2);
+        assert!(
+            forward[0] >= SCAN_PROGRESS_STRIDE && forward[1] >= forward[0] + SCAN_PROGRESS_STRIDE
+        );
+        assert!(forward[1] < last);
+
+        let found = accessor
+            .find_prev_match(last, &needle, None, Some(&progress))
+            .await
+            .unwrap();
+        assert_eq!(found, Some(0));
+        let backward = reports.into_inner().unwrap();
+        assert_eq!(backward.len(), 2);
+        assert!(
+            backward[0] >= SCAN_PROGRESS_STRIDE
+                && backward[1] >= backward[0] + SCAN_PROGRESS_STRIDE
+        );
+
+        // Without a reporter the scan is the same
+        let found = accessor
+            .find_next_match(7, &needle, None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(last));
+    }
+
+    #[tokio::test]
+    async fn test_parallel_scans_of_mapped_content_find_the_first_match() {
+        use crate::file_handler::adaptive::AdaptiveFileAccessor;
+
+        // Needles early, in the middle and near the end of content big enough to split
+        let filler = [vec![b'a'; 1023], vec![b'\n']].concat();
+        let mut content = Vec::new();
+        let mut needles = Vec::new();
+        while (content.len() as u64) < super::parallel::PARALLEL_MIN_BYTES * 3 / 2 {
+            if [1000, 20_000, 40_000].contains(&(content.len() / 1024)) {
+                needles.push(content.len() as u64);
+                content.extend_from_slice(b"a needle\n");
+            }
+            content.extend_from_slice(&filler);
+        }
+        let mut map = memmap2::MmapMut::map_anon(content.len()).unwrap();
+        map.copy_from_slice(&content);
+        let source = ByteSource::MemoryMapped(map.make_read_only().unwrap());
+        let accessor =
+            AdaptiveFileAccessor::new(source, content.len() as u64, PathBuf::from("/test"));
+        let needle = |line: &str| {
+            line.find("needle")
+                .map(|at| (at, at + 6))
+                .into_iter()
+                .collect()
+        };
+
+        for start in [
+            0,
+            needles[0] + 1,
+            needles[1],
+            needles[1] + 1,
+            needles[2] + 1,
+        ] {
+            let expected = accessor
+                .find_next_match(start, &needle, None, None)
+                .await
+                .unwrap();
+            let found = accessor
+                .find_next_match_parallel(start, &needle, None, None)
+                .await
+                .unwrap();
+            assert_eq!(found, expected, "from {start}");
+        }
+
+        let cancel = AtomicBool::new(true);
+        let found = accessor
+            .find_next_match_parallel(0, &needle, Some(&cancel), None)
+            .await;
+        assert!(found.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_navigation_methods() {
+        let content = b"line1\nline2\nline3\nline4\nline5\n";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        // Test last page start
+        let last_pos = accessor.last_page_start(3).await.unwrap();
+        assert_eq!(last_pos, 12); // Should start at "line3"
+
+        // Test next page start - normal case
+        let next_pos = accessor.next_page_start(0, 2).await.unwrap();
+        assert_eq!(next_pos, 12); // Should be at start of "line3"
+
+        // Test next page start - at end of file (should return file_size when can't complete skip)
+        let end_pos = accessor.next_page_start(24, 2).await.unwrap(); // Start of "line5", try to skip 2 lines
+        assert_eq!(end_pos, 30); // Should return file_size (30) since we can only skip 1 line, not 2
+
+        // Test prev page start
+        let prev_pos = accessor.prev_page_start(next_pos, 2).await.unwrap();
+        assert_eq!(prev_pos, 0); // Should go back to start
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_empty_file() {
+        let content = b"";
+        let temp_file = create_test_file(content);
+
+        // FileAccessorFactory should reject empty files during validation
+        let result = FileAccessorFactory::create(temp_file.path()).await;
+        assert!(result.is_err());
+
+        let error = result.err().unwrap();
+        match error {
+            crate::error::RllessError::FileError { message, .. } => {
+                assert!(message.contains("File is empty"));
+            }
+            _ => panic!("Expected FileError for empty file"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_single_line_no_newline() {
+        let content = b"single line without newline";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        let lines = accessor.read_from_byte(0, 5).await.unwrap();
+        assert_eq!(lines, vec!["single line without newline"]);
+
+        let last_pos = accessor.last_page_start(1).await.unwrap();
+        assert_eq!(last_pos, 0);
+        assert!(!accessor.ends_with_newline());
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_next_page_start_edge_cases() {
+        // Test file ending with newline
+        let content = b"A\nB\nC\n";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        // Normal navigation
+        let pos = accessor.next_page_start(0, 1).await.unwrap();
+        assert_eq!(pos, 2); // Start of "B"
+
+        let pos = accessor.next_page_start(2, 1).await.unwrap();
+        assert_eq!(pos, 4); // Start of "C"
+
+        // At last line - should return file_size since we can't skip a full line
+        let pos = accessor.next_page_start(4, 1).await.unwrap();
+        assert_eq!(pos, 6); // Returns file_size (6) indicating EOF
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_last_page_start_comprehensive() {
+        // Test case: "A\nB\nC\nD\nE\n" (5 lines, ends with newline)
+        let content = b"A\nB\nC\nD\nE\n";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        // Request 1 line: should get last line (E)
+        let last_pos = accessor.last_page_start(1).await.unwrap();
+        assert_eq!(last_pos, 8); // Start of "E"
+
+        // Request 2 lines: should get D and E
+        let last_pos = accessor.last_page_start(2).await.unwrap();
+        assert_eq!(last_pos, 6); // Start of "D"
+
+        // Request 3 lines: should get C, D, and E
+        let last_pos = accessor.last_page_start(3).await.unwrap();
+        assert_eq!(last_pos, 4); // Start of "C"
+
+        // Request 5 lines: should get all lines from beginning
+        let last_pos = accessor.last_page_start(5).await.unwrap();
+        assert_eq!(last_pos, 0); // Start of "A"
+
+        // Request more than available: should get all lines from beginning
+        let last_pos = accessor.last_page_start(10).await.unwrap();
+        assert_eq!(last_pos, 0);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_accessor_string_conversion() {
+        let content = b"test line for borrowing\n";
+        let temp_file = create_test_file(content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        let lines = accessor.read_from_byte(0, 1).await.unwrap();
+
+        // Verify we get the expected string
+        assert_eq!(lines[0], "test line for borrowing");
+    }
+}
@@ -0,0 +1,399 @@
+//! Display rows: lines, with lines longer than a segment split into several rows.
+//!
+//! Everything here works on the raw bytes of the content, so it serves every `ByteSource`.
+
+/// Lines longer than this are presented as several display rows ("segments") of this size
+///
+/// Paging, line counts and match positions then operate on segments, so a file that is a single
+/// huge line (minified JSON dumps) can still be scrolled, jumped through and searched. Segments
+/// are measured from the line start and end on a character boundary, so a few bytes short when
+/// a multi-byte character straddles the cut. Paging forward from a jump inside a line measures
+/// from where it landed; paging back realigns with the segments of the line.
+pub const SEGMENT_BYTES: usize = 4096;
+
+/// Where a segment starting at `pos` is cut: `SEGMENT_BYTES` on, backed off to a character start
+///
+/// Backs off at most 3 bytes; without a character start there (invalid UTF-8) the cut stays at
+/// `SEGMENT_BYTES`.
+pub(crate) fn segment_cut(bytes: &[u8], pos: usize) -> usize {
+    let nominal = pos + SEGMENT_BYTES;
+    if nominal >= bytes.len() {
+        return bytes.len();
+    }
+    let is_continuation = |byte: u8| byte & 0xC0 == 0x80;
+    let mut cut = nominal;
+    while cut > nominal - 3 && is_continuation(bytes[cut]) {
+        cut -= 1;
+    }
+    if cut == nominal || bytes[cut] & 0xC0 == 0xC0 {
+        cut
+    } else {
+        nominal
+    }
+}
+
+/// Row starts of the line part `[start, end)`, one per segment and at least one
+pub(super) fn segment_starts(
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+) -> impl Iterator<Item = usize> + '_ {
+    std::iter::successors(Some(start), move |&pos| {
+        Some(segment_cut(bytes, pos)).filter(|&cut| cut < end)
+    })
+}
+
+/// Start of the segment of the line part `[start, end)` holding byte `offset` of it
+pub(super) fn segment_containing(bytes: &[u8], start: usize, end: usize, offset: usize) -> usize {
+    segment_starts(bytes, start, end)
+        .take_while(|&row| row <= start + offset)
+        .last()
+        .unwrap_or(start)
+}
+
+/// End of the display row starting at `pos`, and the start of the following row
+///
+/// A row ends at the next `sep` (the line terminator, see `LineTerminator`), or at the segment
+/// cut when the line continues past that.
+/// The following row is `None` when the row runs to the end of the file.
+pub(super) fn row_bounds(bytes: &[u8], pos: usize, sep: u8) -> (usize, Option<usize>) {
+    let window_end = (pos + SEGMENT_BYTES + 1).min(bytes.len());
+    match memchr::memchr(sep, &bytes[pos..window_end]) {
+        Some(offset) => (pos + offset, Some(pos + offset + 1)),
+        None if pos + SEGMENT_BYTES >= bytes.len() => (bytes.len(), None),
+        None => {
+            let cut = segment_cut(bytes, pos);
+            (cut, Some(cut))
+        }
+    }
+}
+
+/// Start of the row `rows` rows below the row starting at `pos`, `None` past the last row
+///
+/// Same result as following `row_bounds` `rows` times. A row ends at a newline at most a segment
+/// past its start, so every line ending within a segment-sized window takes exactly one row: the
+/// newlines there are counted in bulk rather than found one at a time, which keeps large skips
+/// over short lines cheap.
+pub(super) fn rows_forward(
+    bytes: &[u8],
+    mut pos: usize,
+    mut rows: usize,
+    sep: u8,
+) -> Option<usize> {
+    while rows > 0 {
+        if pos >= bytes.len() {
+            return None;
+        }
+        let window = &bytes[pos..(pos + SEGMENT_BYTES + 1).min(bytes.len())];
+        let newlines = memchr::memchr_iter(sep, window).count();
+        if newlines == 0 {
+            // A long line, or the unterminated last one: a segment per row
+            if pos + SEGMENT_BYTES >= bytes.len() {
+                return None;
+            }
+            pos = segment_cut(bytes, pos);
+            rows -= 1;
+        } else if rows <= newlines {
+            let newline = memchr::memchr_iter(sep, window).nth(rows - 1)?;
+            return Some(pos + newline + 1);
+        } else {
+            pos += memchr::memrchr(sep, window)? + 1;
+            rows -= newlines;
+        }
+    }
+    Some(pos)
+}
+
+/// Start of the row `rows` rows above the row starting at `pos`, stopping at the top
+///
+/// The backward counterpart of `rows_forward`: lines ending within a segment before a line end
+/// are one row each and are counted in bulk. The segments of a long line are found from its
+/// start.
+pub(super) fn rows_back(bytes: &[u8], mut pos: usize, mut rows: usize, sep: u8) -> usize {
+    while rows > 0 && pos > 0 {
+        // A long line's rows before `pos`, or a whole line when at a line end; a trailing
+        // newline does not start a row
+        let (line_start, line_end) = if pos < bytes.len() && bytes[pos - 1] != sep {
+            (
+                memchr::memrchr(sep, &bytes[..pos]).map_or(0, |n| n + 1),
+                pos,
+            )
+        } else {
+            let line_end = if bytes[pos - 1] == sep { pos - 1 } else { pos };
+            let lo = line_end.saturating_sub(SEGMENT_BYTES + 1);
+            let window = &bytes[lo..line_end];
+            let newlines = memchr::memchr_iter(sep, window).count();
+            if newlines > 0 {
+                if rows <= newlines {
+                    return match memchr::memrchr_iter(sep, window).nth(rows - 1) {
+                        Some(newline) => lo + newline + 1,
+                        None => lo,
+                    };
+                }
+                pos = lo + memchr::memchr(sep, window).map_or(0, |n| n + 1);
+                rows -= newlines;
+                continue;
+            }
+            // The line starts at `lo` or earlier and may span several rows
+            (
+                memchr::memrchr(sep, &bytes[..lo]).map_or(0, |n| n + 1),
+                line_end,
+            )
+        };
+
+        let line_rows = segment_starts(bytes, line_start, line_end).count();
+        if rows < line_rows {
+            return segment_starts(bytes, line_start, line_end)
+                .nth(line_rows - rows)
+                .unwrap_or(line_start);
+        }
+        rows -= line_rows;
+        pos = line_start;
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::adaptive::tests::create_test_file;
+    use crate::file_handler::factory::FileAccessorFactory;
+    use crate::file_handler::FileAccessor;
+
+    /// Start of the display row before the row starting at `pos` (`pos > 0`)
+    fn prev_row_start(bytes: &[u8], pos: usize) -> usize {
+        // Walk the rows of the line holding the byte before `pos` up to the last one before it
+        let line_end = if bytes[pos - 1] == b'\n' {
+            pos - 1
+        } else {
+            pos
+        };
+        let mut row = memchr::memrchr(b'\n', &bytes[..line_end]).map_or(0, |n| n + 1);
+        while let (_, Some(next)) = row_bounds(bytes, row, b'\n') {
+            if next >= pos {
+                break;
+            }
+            row = next;
+        }
+        row
+    }
+
+    /// Minified JSON on a single line without a trailing newline, spanning several segments.
+    ///
+    /// A `"needle"` key is placed in the third segment and a two-byte character straddles the
+    /// first segment boundary, so every later segment starts a byte early.
+    fn minified_json_fixture() -> (Vec<u8>, usize) {
+        let mut json = String::from("[");
+        while json.len() < SEGMENT_BYTES - 1 {
+            json.push_str("{\"k\":1},");
+        }
+        json.truncate(SEGMENT_BYTES - 1);
+        json.push('é');
+        while json.len() < 2 * SEGMENT_BYTES + 100 {
+            json.push_str("{\"k\":2},");
+        }
+        let needle_at = json.len();
+        json.push_str("{\"needle\":true}");
+        while json.len() < 3 * SEGMENT_BYTES + SEGMENT_BYTES / 2 {
+            json.push_str(",{\"k\":3}");
+        }
+        json.push(']');
+        (json.into_bytes(), needle_at)
+    }
+
+    #[tokio::test]
+    async fn test_single_huge_line_pages_by_segment() {
+        let (content, _) = minified_json_fixture();
+        let file_size = content.len() as u64;
+        let seg = SEGMENT_BYTES as u64;
+        let temp_file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+
+        let rows = accessor.read_from_byte(0, 10).await.unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[..3].iter().all(|row| row.len() >= SEGMENT_BYTES - 1));
+        // The straddling character moves whole into the second row
+        assert_eq!(rows[0].len(), SEGMENT_BYTES - 1);
+        assert!(rows[1].starts_with('é'));
+        assert!(rows.iter().all(|row| !row.contains('\u{FFFD}')));
+
+        assert_eq!(accessor.next_page_start(0, 1).await.unwrap(), seg - 1);
+        assert_eq!(
+            accessor.next_page_start(seg - 1, 2).await.unwrap(),
+            3 * seg - 1
+        );
+        assert_eq!(accessor.next_page_start(0, 4).await.unwrap(), file_size);
+        assert_eq!(accessor.last_page_start(2).await.unwrap(), 2 * seg - 1);
+        assert_eq!(
+            accessor.prev_page_start(3 * seg - 1, 1).await.unwrap(),
+            2 * seg - 1
+        );
+        assert_eq!(accessor.prev_page_start(3 * seg - 1, 9).await.unwrap(), 0);
+
+        // A jump into the middle of the line pages forward from where it landed, and back
+        // onto the line's own segments
+        let middle = file_size / 2;
+        assert_eq!(
+            accessor.next_page_start(middle, 1).await.unwrap(),
+            middle + seg
+        );
+        assert_eq!(accessor.prev_page_start(middle, 1).await.unwrap(), seg - 1);
+        assert_eq!(accessor.prev_page_start(middle, 2).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_single_huge_line_matches_resolve_to_segments() {
+        let (content, needle_at) = minified_json_fixture();
+        let needle_segment = (2 * SEGMENT_BYTES - 1) as u64;
+        assert!((needle_segment as usize..3 * SEGMENT_BYTES - 1).contains(&needle_at));
+        let temp_file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(temp_file.path()).await.unwrap();
+        let search_fn = |line: &str| {
+            line.match_indices("needle")
+                .map(|(start, m)| (start, start + m.len()))
+                .collect::<Vec<_>>()
+        };
+
+        let next = accessor
+            .find_next_match(0, &search_fn, None, None)
+            .await
+            .unwrap();
+        assert_eq!(next, Some(needle_segment));
+        let prev = accessor
+            .find_prev_match(content.len() as u64, &search_fn, None, None)
+            .await
+            .unwrap();
+        assert_eq!(prev, Some(needle_segment));
+        // Only the part of the line before the starting segment is searched backward
+        let prev = accessor
+            .find_prev_match(needle_segment, &search_fn, None, None)
+            .await
+            .unwrap();
+        assert_eq!(prev, None);
+    }
+
+    #[test]
+    fn test_row_boundaries_around_segment_size() {
+        let seg = SEGMENT_BYTES;
+        // A line of exactly one segment plus its newline stays a single row
+        let mut exact = vec![b'a'; seg];
+        exact.extend_from_slice(b"\nb");
+        assert_eq!(row_bounds(&exact, 0, b'\n'), (seg, Some(seg + 1)));
+        assert_eq!(prev_row_start(&exact, seg + 1), 0);
+
+        // One byte more spills into a continuation row
+        let mut over = vec![b'a'; seg + 1];
+        over.push(b'\n');
+        assert_eq!(row_bounds(&over, 0, b'\n'), (seg, Some(seg)));
+        assert_eq!(row_bounds(&over, seg, b'\n'), (seg + 1, Some(seg + 2)));
+        assert_eq!(prev_row_start(&over, seg + 2), seg);
+        assert_eq!(prev_row_start(&over, seg), 0);
+
+        // A character straddling the cut moves whole into the next row
+        let mut straddle = vec![b'a'; seg - 2];
+        straddle.extend_from_slice("€b\n".as_bytes());
+        assert_eq!(row_bounds(&straddle, 0, b'\n'), (seg - 2, Some(seg - 2)));
+        assert_eq!(
+            row_bounds(&straddle, seg - 2, b'\n'),
+            (seg + 2, Some(seg + 3))
+        );
+        assert_eq!(prev_row_start(&straddle, seg - 2), 0);
+
+        // Invalid UTF-8 is cut at the segment size
+        let mut invalid = vec![0x80; seg + 1];
+        invalid.push(b'\n');
+        assert_eq!(row_bounds(&invalid, 0, b'\n'), (seg, Some(seg)));
+    }
+
+    #[test]
+    fn test_bulk_row_skips_match_row_by_row_stepping() {
+        use rand::{Rng, SeedableRng};
+
+        fn naive_forward(bytes: &[u8], mut pos: usize, rows: usize) -> Option<usize> {
+            for _ in 0..rows {
+                if pos >= bytes.len() {
+                    return None;
+                }
+                pos = row_bounds(bytes, pos, b'\n').1?;
+            }
+            Some(pos)
+        }
+
+        fn naive_back(bytes: &[u8], mut pos: usize, rows: usize) -> usize {
+            for _ in 0..rows {
+                if pos == 0 {
+                    break;
+                }
+                pos = prev_row_start(bytes, pos);
+            }
+            pos
+        }
+
+        // About `len` bytes of ASCII, or of characters up to four bytes long
+        fn fill(rng: &mut impl Rng, len: usize) -> Vec<u8> {
+            if rng.gen_bool(0.5) {
+                return vec![b'x'; len];
+            }
+            let mut text = String::new();
+            while text.len() < len {
+                text.push(['x', 'é', '€', '😀'][rng.gen_range(0..4)]);
+            }
+            text.into_bytes()
+        }
+
+        let seg = SEGMENT_BYTES;
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(3249);
+        for case in 0..200 {
+            // Short lines, empty lines and lines around and well past a segment
+            let mut bytes = Vec::new();
+            for _ in 0..rng.gen_range(0..40) {
+                let len = match rng.gen_range(0..6) {
+                    0 => 0,
+                    1 | 2 => rng.gen_range(1..20),
+                    3 => seg + rng.gen_range(0..3) - 1,
+                    _ => rng.gen_range(1..4 * seg),
+                };
+                bytes.extend(fill(&mut rng, len));
+                bytes.push(b'\n');
+            }
+            if rng.gen_bool(0.5) {
+                let len = rng.gen_range(1..3 * seg);
+                bytes.extend(fill(&mut rng, len));
+            }
+
+            // Row starts as the worker produces them, plus arbitrary offsets
+            let mut starts = vec![0, bytes.len()];
+            let mut pos = 0;
+            while let Some(next) = naive_forward(&bytes, pos, 1) {
+                // Paging back retraces paging forward
+                if next < bytes.len() {
+                    assert_eq!(
+                        rows_back(&bytes, next, 1, b'\n'),
+                        pos,
+                        "case {case}: back from {next}"
+                    );
+                }
+                starts.push(next);
+                pos = next;
+            }
+            for _ in 0..10 {
+                starts.push(rng.gen_range(0..=bytes.len()));
+            }
+
+            for &start in &starts {
+                for rows in [0, 1, 2, 3, 7, 50, rng.gen_range(0..200)] {
+                    assert_eq!(
+                        rows_forward(&bytes, start, rows, b'\n'),
+                        naive_forward(&bytes, start, rows),
+                        "case {case}: {rows} rows forward from {start}"
+                    );
+                    assert_eq!(
+                        rows_back(&bytes, start, rows, b'\n'),
+                        naive_back(&bytes, start, rows),
+                        "case {case}: {rows} rows back from {start}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Where an `AdaptiveFileAccessor`'s bytes live: memory, a mapping of the file, or a mapping of
+//! a temp file holding decompressed, spilled or piped content.
+
+use super::SharedBytes;
+use crate::error::{Result, RllessError};
+use memmap2::Mmap;
+use std::io::Write;
+use std::sync::Arc;
+use tempfile::{NamedTempFile, TempPath};
+
+/// Internal byte source strategy for AdaptiveFileAccessor
+#[derive(Debug)]
+pub enum ByteSource {
+    /// Content loaded entirely into memory (for files < 50MB)
+    InMemory(Vec<u8>),
+    /// Content accessed via memory mapping (for files ≥ 50MB)
+    MemoryMapped(Mmap),
+    /// Compressed file decompressed to temp file and memory-mapped
+    /// The temp file is kept alive to prevent deletion
+    Compressed {
+        mmap: Mmap,
+        _temp_file: MappedTempFile,
+    },
+    /// In-memory content written out to a temp file and memory-mapped under memory pressure
+    /// The temp file is kept alive to prevent deletion
+    Spilled {
+        mmap: Mmap,
+        _temp_file: MappedTempFile,
+    },
+    /// Piped input still held in memory; each version shares the buffer the input is read into
+    /// and covers what had been read when it was made
+    Streamed(SharedBytes),
+    /// Piped input collected in a temp file that is still being appended to; each version maps
+    /// what had been written when it was made
+    Piped {
+        mmap: Mmap,
+        _temp_file: Arc<MappedTempFile>,
+    },
+}
+
+/// Temp file backing a memory mapping, created by `MappedTempFile::map`
+///
+/// On Unix the path is removed as soon as the file is mapped, so tmp cleaners such as
+/// systemd-tmpfiles cannot delete the data out from under a long-running session; the open
+/// descriptor keeps it alive until the source is dropped. Other platforms cannot remove an open
+/// file, so there the path stays until drop.
+#[derive(Debug)]
+pub struct MappedTempFile {
+    file: std::fs::File,
+    #[cfg(not(unix))]
+    _path: TempPath,
+}
+
+impl MappedTempFile {
+    /// Memory-map the written `temp_file` and take over its lifetime
+    pub fn map(temp_file: NamedTempFile) -> Result<(Mmap, Self)> {
+        let (file, path) = temp_file.into_parts();
+        let mmap = unsafe {
+            Mmap::map(&file)
+                .map_err(|e| RllessError::file_error("Failed to memory map temp file", e))?
+        };
+        Ok((mmap, Self::unlink(file, path)?))
+    }
+
+    /// Map the file again, covering whatever has been written to it since
+    pub fn remap(&self) -> Result<Mmap> {
+        unsafe {
+            Mmap::map(&self.file)
+                .map_err(|e| RllessError::file_error("Failed to memory map temp file", e))
+        }
+    }
+
+    #[cfg(unix)]
+    fn unlink(file: std::fs::File, path: TempPath) -> Result<Self> {
+        path.close()
+            .map_err(|e| RllessError::file_error("Failed to unlink temp file", e))?;
+        Ok(Self { file })
+    }
+
+    #[cfg(not(unix))]
+    fn unlink(file: std::fs::File, path: TempPath) -> Result<Self> {
+        Ok(Self { file, _path: path })
+    }
+}
+
+impl ByteSource {
+    /// Get the underlying bytes as a slice regardless of storage strategy
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        match self {
+            ByteSource::InMemory(vec) => vec.as_slice(),
+            ByteSource::Streamed(bytes) => bytes.as_slice(),
+            ByteSource::MemoryMapped(mmap) => &mmap[..],
+            ByteSource::Compressed { mmap, .. } => &mmap[..],
+            ByteSource::Spilled { mmap, .. } => &mmap[..],
+            ByteSource::Piped { mmap, .. } => &mmap[..],
+        }
+    }
+
+    /// Write `bytes` to a fresh temp file and memory-map it
+    pub(super) fn spill(bytes: &[u8]) -> Result<Self> {
+        let mut temp_file = NamedTempFile::new()
+            .map_err(|e| RllessError::file_error("Failed to create spill file", e))?;
+        temp_file
+            .write_all(bytes)
+            .and_then(|_| temp_file.flush())
+            .map_err(|e| RllessError::file_error("Failed to write spill file", e))?;
+
+        let (mmap, temp_file) = MappedTempFile::map(temp_file)?;
+        Ok(ByteSource::Spilled {
+            mmap,
+            _temp_file: temp_file,
+        })
+    }
+
+    /// Convert bytes to String
+    pub(super) fn bytes_to_string(&self, bytes: &[u8]) -> Result<String> {
+        std::str::from_utf8(bytes)
+            .map(|s| s.to_string())
+            .map_err(|e| {
+                crate::error::RllessError::file_error(
+                    "Invalid UTF-8 in file",
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::adaptive::tests::create_test_file;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mapped_temp_file_survives_path_removal() {
+        let temp_file = create_test_file(b"decompressed content\n");
+        let path = temp_file.path().to_path_buf();
+
+        let (mmap, handle) = MappedTempFile::map(temp_file).unwrap();
+        // Nothing left on disk for a tmp cleaner to delete, yet the data stays readable
+        assert!(!path.exists());
+        assert_eq!(&mmap[..], b"decompressed content\n");
+        drop(handle);
+        assert_eq!(&mmap[..], b"decompressed content\n");
+    }
+
+    #[test]
+    fn test_byte_source_variants() {
+        let vec_data = vec![65, 10, 66, 10]; // "A\nB\n"
+        let in_memory = ByteSource::InMemory(vec_data);
+
+        assert_eq!(in_memory.as_bytes(), &[65, 10, 66, 10]);
+
+        let string_result = in_memory.bytes_to_string(&[65]).unwrap();
+        assert_eq!(string_result, "A");
+    }
+}
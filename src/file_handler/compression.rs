@@ -2,9 +2,16 @@
 //!
 //! This module provides compression format detection using magic numbers (file signatures)
 //! and decompression utilities for common compression formats used with log files.
+//!
+//! Gzip files are decoded one member at a time, since rotated logs are often concatenated with
+//! `cat a.gz b.gz > all.gz`. The uncompressed offset where each member starts is reported
+//! alongside the content so the viewer can jump between members.
+
+mod gzip;
 
 use crate::error::{Result, RllessError};
-use async_compression::tokio::bufread::{BzDecoder, GzipDecoder, XzDecoder, ZstdDecoder};
+use async_compression::tokio::bufread::{BzDecoder, XzDecoder, ZstdDecoder};
+use gzip::{decode_gzip_members, gzip_prefix};
 use std::path::Path;
 use tempfile::NamedTempFile;
 use tokio::fs::File;
@@ -125,10 +132,14 @@ pub enum DecompressionResult {
 /// # Strategy
 /// - Files < 10MB compressed: decompress to memory
 /// - Files ≥ 10MB compressed: decompress to temp file
+///
+/// # Returns
+/// * The decompressed content
+/// * Uncompressed offsets where each gzip member starts (first is 0); empty for other formats
 pub async fn decompress_file(
     path: &Path,
     compression: CompressionType,
-) -> Result<DecompressionResult> {
+) -> Result<(DecompressionResult, Vec<u64>)> {
     if !compression.is_compressed() {
         return Err(RllessError::file_error(
             "decompress_file called with no compression",
@@ -147,28 +158,79 @@ pub async fn decompress_file(
 
     if compressed_size < MEMORY_THRESHOLD {
         // Small compressed file: decompress to memory
-        let data = decompress_to_memory(path, compression).await?;
-        Ok(DecompressionResult::InMemory(data))
+        let (data, sections) = decompress_to_memory(path, compression).await?;
+        Ok((DecompressionResult::InMemory(data), sections))
     } else {
         // Large compressed file: decompress to temp file
-        let temp_file = decompress_to_temp_file(path, compression).await?;
-        Ok(DecompressionResult::TempFile(temp_file))
+        let (temp_file, sections) = decompress_to_temp_file(path, compression).await?;
+        Ok((DecompressionResult::TempFile(temp_file), sections))
+    }
+}
+
+/// Decompress at most the first `limit` bytes of a file
+///
+/// Returns the bytes and whether they are the whole decompressed content. Only as much input as
+/// needed is decoded, so this stays fast on huge archives. Uncompressed files are read as they
+/// are.
+pub async fn decompress_prefix(
+    path: &Path,
+    compression: CompressionType,
+    limit: usize,
+) -> Result<(Vec<u8>, bool)> {
+    let file = File::open(path)
+        .await
+        .map_err(|e| RllessError::file_error("Failed to open compressed file", e))?;
+    let file = BufReader::new(file);
+
+    if compression == CompressionType::Gzip {
+        return gzip_prefix(file, limit)
+            .await
+            .map_err(|e| RllessError::file_error("Failed to decompress file", e));
     }
+
+    let mut decoder: Box<dyn AsyncRead + Unpin + Send> = match compression {
+        CompressionType::Bzip2 => Box::new(BzDecoder::new(file)),
+        CompressionType::Xz => Box::new(XzDecoder::new(file)),
+        CompressionType::Zstd => Box::new(ZstdDecoder::new(file)),
+        CompressionType::Gzip => unreachable!("Gzip is decoded member by member"),
+        CompressionType::None => Box::new(file),
+    };
+
+    // One byte past the limit tells whether anything is left
+    let mut data = Vec::new();
+    (&mut decoder)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| RllessError::file_error("Failed to decompress file", e))?;
+    let complete = data.len() <= limit;
+    data.truncate(limit);
+    Ok((data, complete))
 }
 
 /// Decompress a file entirely into memory
-async fn decompress_to_memory(path: &Path, compression: CompressionType) -> Result<Vec<u8>> {
+async fn decompress_to_memory(
+    path: &Path,
+    compression: CompressionType,
+) -> Result<(Vec<u8>, Vec<u64>)> {
     let file = File::open(path)
         .await
         .map_err(|e| RllessError::file_error("Failed to open compressed file", e))?;
     let file = BufReader::new(file);
 
     let mut data = Vec::new();
-    let mut decoder: Box<dyn AsyncRead + Unpin> = match compression {
-        CompressionType::Gzip => Box::new(GzipDecoder::new(file)),
+    if compression == CompressionType::Gzip {
+        let sections = decode_gzip_members(file, &mut data)
+            .await
+            .map_err(|e| RllessError::file_error("Failed to decompress file", e))?;
+        return Ok((data, sections));
+    }
+
+    let mut decoder: Box<dyn AsyncRead + Unpin + Send> = match compression {
         CompressionType::Bzip2 => Box::new(BzDecoder::new(file)),
         CompressionType::Xz => Box::new(XzDecoder::new(file)),
         CompressionType::Zstd => Box::new(ZstdDecoder::new(file)),
+        CompressionType::Gzip => unreachable!("Gzip is decoded member by member"),
         CompressionType::None => unreachable!("Should not decompress uncompressed files"),
     };
 
@@ -177,14 +239,14 @@ async fn decompress_to_memory(path: &Path, compression: CompressionType) -> Resu
         .await
         .map_err(|e| RllessError::file_error("Failed to decompress file", e))?;
 
-    Ok(data)
+    Ok((data, Vec::new()))
 }
 
 /// Decompress a file to a temporary file
 async fn decompress_to_temp_file(
     path: &Path,
     compression: CompressionType,
-) -> Result<NamedTempFile> {
+) -> Result<(NamedTempFile, Vec<u64>)> {
     let file = File::open(path)
         .await
         .map_err(|e| RllessError::file_error("Failed to open compressed file", e))?;
@@ -201,28 +263,35 @@ async fn decompress_to_temp_file(
         .map_err(|e| RllessError::file_error("Failed to open temp file for writing", e))?;
     let mut temp_writer = BufWriter::new(temp_file_handle);
 
-    // Create decoder
-    let mut decoder: Box<dyn AsyncRead + Unpin> = match compression {
-        CompressionType::Gzip => Box::new(GzipDecoder::new(file)),
-        CompressionType::Bzip2 => Box::new(BzDecoder::new(file)),
-        CompressionType::Xz => Box::new(XzDecoder::new(file)),
-        CompressionType::Zstd => Box::new(ZstdDecoder::new(file)),
-        CompressionType::None => unreachable!("Should not decompress uncompressed files"),
+    let sections = if compression == CompressionType::Gzip {
+        decode_gzip_members(file, &mut temp_writer)
+            .await
+            .map_err(|e| RllessError::file_error("Failed to decompress file", e))?
+    } else {
+        // Create decoder
+        let mut decoder: Box<dyn AsyncRead + Unpin + Send> = match compression {
+            CompressionType::Bzip2 => Box::new(BzDecoder::new(file)),
+            CompressionType::Xz => Box::new(XzDecoder::new(file)),
+            CompressionType::Zstd => Box::new(ZstdDecoder::new(file)),
+            CompressionType::Gzip => unreachable!("Gzip is decoded member by member"),
+            CompressionType::None => unreachable!("Should not decompress uncompressed files"),
+        };
+
+        // Use optimized copy operation instead of manual buffering
+        // This uses tokio's internal optimizations and larger buffers
+        tokio::io::copy(&mut decoder, &mut temp_writer)
+            .await
+            .map_err(|e| RllessError::file_error("Failed to decompress file", e))?;
+        Vec::new()
     };
 
-    // Use optimized copy operation instead of manual buffering
-    // This uses tokio's internal optimizations and larger buffers
-    tokio::io::copy(&mut decoder, &mut temp_writer)
-        .await
-        .map_err(|e| RllessError::file_error("Failed to decompress file", e))?;
-
     // Ensure all data is written to disk
     temp_writer
         .flush()
         .await
         .map_err(|e| RllessError::file_error("Failed to flush temp file", e))?;
 
-    Ok(temp_file)
+    Ok((temp_file, sections))
 }
 
 #[cfg(test)]
@@ -340,7 +409,7 @@ mod tests {
             .await
             .unwrap();
 
-        match result {
+        match result.0 {
             DecompressionResult::InMemory(data) => {
                 assert_eq!(data, test_data);
             }
@@ -375,10 +444,11 @@ mod tests {
             encoder.finish().unwrap();
         }
 
-        let result = decompress_to_memory(temp_file.path(), CompressionType::Gzip)
+        let (result, sections) = decompress_to_memory(temp_file.path(), CompressionType::Gzip)
             .await
             .unwrap();
         assert_eq!(result, test_data);
+        assert_eq!(sections, vec![0]);
     }
 
     #[tokio::test]
@@ -395,7 +465,7 @@ mod tests {
             encoder.finish().unwrap();
         }
 
-        let temp_file = decompress_to_temp_file(compressed_file.path(), CompressionType::Gzip)
+        let (temp_file, _) = decompress_to_temp_file(compressed_file.path(), CompressionType::Gzip)
             .await
             .unwrap();
 
@@ -0,0 +1,144 @@
+//! Gzip files made of several members.
+
+use async_compression::tokio::bufread::GzipDecoder;
+use std::io;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, BufReader};
+
+/// `decompress_prefix` for gzip, decoding members until the limit is passed
+pub(super) async fn gzip_prefix(
+    mut reader: BufReader<File>,
+    limit: usize,
+) -> io::Result<(Vec<u8>, bool)> {
+    let mut data = Vec::new();
+    let mut first = true;
+    while data.len() <= limit && starts_gzip_member(&mut reader, first).await? {
+        first = false;
+        let mut decoder = GzipDecoder::new(reader);
+        (&mut decoder)
+            .take((limit + 1 - data.len()) as u64)
+            .read_to_end(&mut data)
+            .await?;
+        reader = decoder.into_inner();
+    }
+    let complete = data.len() <= limit;
+    data.truncate(limit);
+    Ok((data, complete))
+}
+
+/// Whether another gzip member follows in `reader`
+///
+/// Bytes after the last member that do not start another one, such as the zero padding tape and
+/// block devices leave, are ignored as `gzip -d` does. The first member is always attempted so a
+/// file that is not gzip at all reaches the decoder, which reports it.
+async fn starts_gzip_member(reader: &mut BufReader<File>, first: bool) -> io::Result<bool> {
+    let next = reader.fill_buf().await?;
+    let starts_member = match next {
+        [] => false,
+        _ if first => true,
+        [0x1f] | [0x1f, 0x8b, ..] => true,
+        _ => false,
+    };
+    if !starts_member && !next.is_empty() {
+        log::debug!("compression: ignoring bytes after the last gzip member");
+    }
+    Ok(starts_member)
+}
+
+/// Decode every gzip member in `reader` into `writer`
+///
+/// Returns the uncompressed offset where each member starts.
+pub(super) async fn decode_gzip_members<W: AsyncWrite + Unpin>(
+    mut reader: BufReader<File>,
+    writer: &mut W,
+) -> io::Result<Vec<u64>> {
+    let mut sections = Vec::new();
+    let mut offset = 0u64;
+    // The decoder only consumes its own member, so the next one is still buffered in `reader`
+    while starts_gzip_member(&mut reader, sections.is_empty()).await? {
+        sections.push(offset);
+        let mut decoder = GzipDecoder::new(reader);
+        offset += tokio::io::copy(&mut decoder, writer).await?;
+        reader = decoder.into_inner();
+    }
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_handler::compression::{
+        decompress_prefix, decompress_to_memory, decompress_to_temp_file, CompressionType,
+    };
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use std::path::Path;
+
+    /// Three gzip members concatenated like `cat a.gz b.gz c.gz`
+    fn write_multi_member_gzip(path: &Path, members: &[&[u8]]) {
+        let mut file = std::fs::File::create(path).unwrap();
+        for member in members {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(member).unwrap();
+            file.write_all(&encoder.finish().unwrap()).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gzip_members_are_decoded_with_their_offsets() {
+        let members: [&[u8]; 3] = [b"first\n", b"second member\n", b"third\n"];
+        let compressed_file = tempfile::NamedTempFile::new().unwrap();
+        write_multi_member_gzip(compressed_file.path(), &members);
+
+        let (data, sections) = decompress_to_memory(compressed_file.path(), CompressionType::Gzip)
+            .await
+            .unwrap();
+        assert_eq!(data, b"first\nsecond member\nthird\n");
+        assert_eq!(sections, vec![0, 6, 20]);
+
+        let (temp_file, sections) =
+            decompress_to_temp_file(compressed_file.path(), CompressionType::Gzip)
+                .await
+                .unwrap();
+        assert_eq!(std::fs::read(temp_file.path()).unwrap(), data);
+        assert_eq!(sections, vec![0, 6, 20]);
+    }
+
+    #[tokio::test]
+    async fn test_padding_after_the_last_gzip_member_is_ignored() {
+        let compressed_file = tempfile::NamedTempFile::new().unwrap();
+        write_multi_member_gzip(compressed_file.path(), &[b"first\n", b"second\n"]);
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(compressed_file.path())
+            .unwrap();
+        file.write_all(&[0; 512]).unwrap();
+
+        let (data, sections) = decompress_to_memory(compressed_file.path(), CompressionType::Gzip)
+            .await
+            .unwrap();
+        assert_eq!(data, b"first\nsecond\n");
+        assert_eq!(sections, vec![0, 6]);
+        let prefix = decompress_prefix(compressed_file.path(), CompressionType::Gzip, 1024).await;
+        assert_eq!(prefix.unwrap(), (data, true));
+    }
+
+    #[tokio::test]
+    async fn test_decompress_prefix_stops_at_the_limit() {
+        let compressed_file = tempfile::NamedTempFile::new().unwrap();
+        write_multi_member_gzip(compressed_file.path(), &[b"first\n", b"second\n"]);
+
+        let prefix =
+            |limit| decompress_prefix(compressed_file.path(), CompressionType::Gzip, limit);
+        assert_eq!(prefix(8).await.unwrap(), (b"first\nse".to_vec(), false));
+        // Every member is decoded when the limit allows it
+        assert_eq!(
+            prefix(1024).await.unwrap(),
+            (b"first\nsecond\n".to_vec(), true)
+        );
+        assert_eq!(
+            prefix(13).await.unwrap(),
+            (b"first\nsecond\n".to_vec(), true)
+        );
+    }
+}
@@ -4,13 +4,19 @@
 //! that automatically handle file size, compression detection, and platform optimization.
 
 use crate::error::{Result, RllessError};
-use crate::file_handler::adaptive::{AdaptiveFileAccessor, ByteSource};
+use crate::file_handler::accessor::FileAccessor;
+use crate::file_handler::adaptive::{AdaptiveFileAccessor, ByteSource, MappedTempFile};
 use crate::file_handler::compression::{decompress_file, detect_compression, DecompressionResult};
+use crate::file_handler::identity::FileIdentity;
+use crate::file_handler::meta::FileMeta;
+use crate::file_handler::piped::PipedInput;
+use crate::file_handler::terminator::LineTerminator;
 use crate::file_handler::validation::validate_file_path;
 use memmap2::Mmap;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::sync::Arc;
 
 /// Factory for creating AdaptiveFileAccessor instances
 ///
@@ -49,52 +55,75 @@ impl FileAccessorFactory {
     /// 1. Validate file (existence, permissions, reasonable size)
     /// 2. Detect and handle compression transparently
     /// 3. Select `ByteSource` strategy based on file size
+    /// 4. Resolve the file's identity (canonical path, device and inode), once for its lifetime
     ///
     /// # Errors
     /// * File validation errors (non-existent, empty, too large, not readable)
     /// * Compression detection/decompression errors
     /// * Memory mapping failures
     pub async fn create(path: &Path) -> Result<AdaptiveFileAccessor> {
+        let accessor = Self::open(path)
+            .await?
+            .with_identity(resolve_identity(path)?);
+        log::info!(
+            "accessor: opened {} bytes ({} sections)",
+            accessor.file_size(),
+            accessor.sections().len()
+        );
+        Ok(accessor)
+    }
+
+    /// Start collecting piped input from stdin into an accessor that grows as it arrives
+    ///
+    /// Input moves from memory to a temp file past the size threshold, or from the start with
+    /// `low_memory` set. See `PipedInput`.
+    pub fn create_from_stdin(
+        low_memory: bool,
+        terminator: LineTerminator,
+    ) -> (Arc<AdaptiveFileAccessor>, PipedInput) {
+        let threshold = if low_memory {
+            0
+        } else {
+            Self::MEMORY_THRESHOLD
+        };
+        PipedInput::spawn(std::io::stdin(), threshold, terminator)
+    }
+
+    async fn open(path: &Path) -> Result<AdaptiveFileAccessor> {
         // 1. Validate file first (existence, permissions, reasonable size)
         validate_file_path(path)?;
+        let meta = FileMeta::read(path)?;
 
         // 2. Detect compression format
         let compression_type = detect_compression(path).await?;
 
         if compression_type.is_compressed() {
             // Handle compressed files
-            match decompress_file(path, compression_type).await? {
+            let (decompressed, sections) = decompress_file(path, compression_type).await?;
+            match decompressed {
                 DecompressionResult::InMemory(data) => {
                     let file_size = data.len() as u64;
                     let source = ByteSource::InMemory(data);
-                    Ok(AdaptiveFileAccessor::new(
-                        source,
-                        file_size,
-                        path.to_path_buf(),
-                    ))
+                    Ok(
+                        AdaptiveFileAccessor::new(source, file_size, path.to_path_buf())
+                            .with_meta(meta)
+                            .with_sections(sections),
+                    )
                 }
                 DecompressionResult::TempFile(temp_file) => {
-                    // Memory map the temp file
-                    let temp_file_handle = temp_file
-                        .reopen()
-                        .map_err(|e| RllessError::file_error("Failed to reopen temp file", e))?;
-
-                    let mmap = unsafe {
-                        Mmap::map(&temp_file_handle).map_err(|e| {
-                            RllessError::file_error("Failed to memory map temp file", e)
-                        })?
-                    };
+                    // Memory map the temp file and drop its path so tmp cleaners cannot reach it
+                    let (mmap, temp_file) = MappedTempFile::map(temp_file)?;
 
                     let file_size = mmap.len() as u64;
                     let source = ByteSource::Compressed {
                         mmap,
                         _temp_file: temp_file,
                     };
-                    Ok(AdaptiveFileAccessor::new(
-                        source,
-                        file_size,
-                        path.to_path_buf(),
-                    ))
+                    Ok(
+                        AdaptiveFileAccessor::new(source, file_size, path.to_path_buf())
+                            .with_meta(meta)
+                            .with_sections(sections),
+                    )
                 }
             }
         } else {
@@ -116,11 +145,11 @@ impl FileAccessorFactory {
                     .map_err(|e| RllessError::file_error("Failed to read file", e))?;
 
                 let source = ByteSource::InMemory(content);
-                Ok(AdaptiveFileAccessor::new(
-                    source,
-                    file_size,
-                    path.to_path_buf(),
-                ))
+                Ok(
+                    AdaptiveFileAccessor::new(source, file_size, path.to_path_buf())
+                        .with_meta(meta)
+                        .with_refresh(),
+                )
             } else {
                 // Large file: use memory mapping
                 let mmap = unsafe {
@@ -133,11 +162,11 @@ impl FileAccessorFactory {
                 };
 
                 let source = ByteSource::MemoryMapped(mmap);
-                Ok(AdaptiveFileAccessor::new(
-                    source,
-                    file_size,
-                    path.to_path_buf(),
-                ))
+                Ok(
+                    AdaptiveFileAccessor::new(source, file_size, path.to_path_buf())
+                        .with_meta(meta)
+                        .with_refresh(),
+                )
             }
         }
     }
@@ -182,11 +211,11 @@ impl FileAccessorFactory {
             };
 
             let source = ByteSource::MemoryMapped(mmap);
-            Ok(AdaptiveFileAccessor::new(
-                source,
-                file_size,
-                path.to_path_buf(),
-            ))
+            Ok(
+                AdaptiveFileAccessor::new(source, file_size, path.to_path_buf())
+                    .with_identity(resolve_identity(path)?)
+                    .with_refresh(),
+            )
         } else {
             // Force in-memory
             let mut content = Vec::new();
@@ -195,15 +224,21 @@ impl FileAccessorFactory {
                 .map_err(|e| RllessError::file_error("Failed to read file", e))?;
 
             let source = ByteSource::InMemory(content);
-            Ok(AdaptiveFileAccessor::new(
-                source,
-                file_size,
-                path.to_path_buf(),
-            ))
+            Ok(
+                AdaptiveFileAccessor::new(source, file_size, path.to_path_buf())
+                    .with_identity(resolve_identity(path)?)
+                    .with_refresh(),
+            )
         }
     }
 }
 
+fn resolve_identity(path: &Path) -> Result<FileIdentity> {
+    FileIdentity::resolve(path).map_err(|e| {
+        RllessError::file_error(format!("Failed to resolve path: {}", path.display()), e)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,7 +280,7 @@ mod tests {
         assert_eq!(lines[0], "line1");
 
         // Verify it's using InMemory strategy
-        match &accessor.source {
+        match accessor.pin().source() {
             ByteSource::InMemory(_) => {} // Expected
             _ => panic!("Small file should use InMemory variant"),
         }
@@ -261,7 +296,7 @@ mod tests {
             .unwrap();
 
         // Verify it's using MemoryMapped strategy for large files
-        match &accessor.source {
+        match accessor.pin().source() {
             ByteSource::MemoryMapped(_) => {} // Expected
             _ => panic!("Large file should use MemoryMapped variant"),
         }
@@ -324,12 +359,12 @@ mod tests {
             .unwrap();
 
         // Verify forced strategies
-        match &mmap_accessor.source {
+        match mmap_accessor.pin().source() {
             ByteSource::MemoryMapped(_) => {} // Expected
             _ => panic!("Should be forced to MemoryMapped"),
         }
 
-        match &memory_accessor.source {
+        match memory_accessor.pin().source() {
             ByteSource::InMemory(_) => {} // Expected
             _ => panic!("Should be forced to InMemory"),
         }
@@ -376,7 +411,7 @@ mod tests {
         let small_accessor = FileAccessorFactory::create(small_file.path())
             .await
             .unwrap();
-        match &small_accessor.source {
+        match small_accessor.pin().source() {
             ByteSource::InMemory(_) => {} // Expected
             _ => panic!("Small file should use InMemory variant"),
         }
@@ -386,10 +421,32 @@ mod tests {
         let large_accessor = FileAccessorFactory::create(large_file.path())
             .await
             .unwrap();
-        match &large_accessor.source {
+        match large_accessor.pin().source() {
             ByteSource::MemoryMapped(_) => {} // Expected
             _ => panic!("Large file should use MemoryMapped variant"),
         }
         assert_eq!(large_accessor.file_size(), threshold);
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinked_file_keeps_its_path_and_resolves_its_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("app.log");
+        std::fs::write(&target, "line\n").unwrap();
+        let link = dir.path().join("current.log");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let accessor = FileAccessorFactory::create(&link).await.unwrap();
+        assert_eq!(accessor.file_path(), link.as_path());
+        let identity = accessor
+            .identity()
+            .expect("file-backed accessor has an identity");
+        assert_eq!(
+            identity.canonical_path,
+            std::fs::canonicalize(&target).unwrap()
+        );
+        let direct = FileAccessorFactory::create(&target).await.unwrap();
+        assert!(identity.same_file(direct.identity().unwrap()));
+    }
 }
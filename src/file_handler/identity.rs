@@ -0,0 +1,108 @@
+//! Telling whether two paths lead to the same file.
+//!
+//! A file opened as `app.log`, `./logs/../app.log` or through a symlink is still one file. The
+//! path the user typed stays on the status line; anything that remembers or compares files goes
+//! by `FileKey` instead, and the info display shows the canonical path.
+
+use std::fs::Metadata;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What identifies a file on disk regardless of the path used to reach it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileKey {
+    /// Device and inode numbers, on Unix
+    Inode { device: u64, inode: u64 },
+    /// Canonical path, on platforms without inode numbers
+    Path(PathBuf),
+}
+
+impl FileKey {
+    /// Key of the file at `path`, given its metadata (with symlinks followed)
+    ///
+    /// Only resolves `path` on platforms without inode numbers.
+    pub fn new(path: &Path, metadata: &Metadata) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            let _ = path;
+            Ok(Self::from_metadata(metadata))
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = metadata;
+            Ok(FileKey::Path(std::fs::canonicalize(path)?))
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_metadata(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        FileKey::Inode {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        }
+    }
+}
+
+/// A file's canonical path together with its key, resolved once when it is opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileIdentity {
+    /// Absolute path with symlinks, `.` and `..` resolved
+    pub canonical_path: PathBuf,
+    pub key: FileKey,
+}
+
+impl FileIdentity {
+    /// Resolve the file at `path`
+    pub fn resolve(path: &Path) -> io::Result<Self> {
+        let canonical_path = std::fs::canonicalize(path)?;
+        #[cfg(unix)]
+        let key = FileKey::from_metadata(&std::fs::metadata(&canonical_path)?);
+        #[cfg(not(unix))]
+        let key = FileKey::Path(canonical_path.clone());
+        Ok(Self {
+            canonical_path,
+            key,
+        })
+    }
+
+    /// Whether `self` and `other` are the same file
+    pub fn same_file(&self, other: &FileIdentity) -> bool {
+        self.key == other.key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_and_relative_paths_resolve_to_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("app.log");
+        std::fs::write(&target, "one\n").unwrap();
+        let link = dir.path().join("current.log");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let dotted = dir.path().join("sub").join("..").join("app.log");
+
+        let direct = FileIdentity::resolve(&target).unwrap();
+        for path in [&link, &dotted] {
+            let identity = FileIdentity::resolve(path).unwrap();
+            assert!(identity.same_file(&direct), "{}", path.display());
+            assert_eq!(identity.canonical_path, direct.canonical_path);
+        }
+        assert_eq!(
+            direct.canonical_path,
+            std::fs::canonicalize(dir.path()).unwrap().join("app.log")
+        );
+        let metadata = std::fs::metadata(&link).unwrap();
+        assert_eq!(FileKey::new(&link, &metadata).unwrap(), direct.key);
+
+        // A file with the same content elsewhere is another file
+        let copy = dir.path().join("copy.log");
+        std::fs::copy(&target, &copy).unwrap();
+        assert!(!FileIdentity::resolve(&copy).unwrap().same_file(&direct));
+    }
+}
@@ -0,0 +1,133 @@
+//! Picking the newest of a family of rotated files.
+//!
+//! `--latest 'logs/app-*.log'` opens whichever matching file was modified last. Wildcards are
+//! only expanded in the file name: `*` matches any run of characters and `?` a single one, while
+//! the directory part is taken literally.
+
+use crate::error::{Result, RllessError};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Regular file matching `pattern` with the latest modification time, `None` if nothing matches
+///
+/// Files modified at the same instant are ordered by name, so `app-2024-06-02.log` wins over
+/// `app-2024-06-01.log`.
+pub fn newest_match(pattern: &Path) -> Result<Option<PathBuf>> {
+    let name_pattern = pattern
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| {
+            RllessError::other(format!("No file name pattern in {}", pattern.display()))
+        })?;
+    let dir = match pattern.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| RllessError::file_error(format!("Failed to list {}", dir.display()), e))?;
+
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    for entry in entries.flatten() {
+        let matches = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| wildcard_match(name_pattern, name));
+        if !matches {
+            continue;
+        }
+        // Entries that vanish or turn out not to be files are skipped rather than reported
+        let Ok(meta) = std::fs::metadata(entry.path()) else {
+            continue;
+        };
+        let Ok(modified) = meta.modified() else {
+            continue;
+        };
+        if !meta.is_file() {
+            continue;
+        }
+        let candidate = (modified, dir.join(entry.file_name()));
+        if newest.as_ref() < Some(&candidate) {
+            newest = Some(candidate);
+        }
+    }
+    Ok(newest.map(|(_, path)| path))
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of characters and `?` for one
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&ch) if ch == '?' || ch == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and retry
+                Some((after_star, matched)) => {
+                    backtrack = Some((after_star, matched + 1));
+                    p = after_star;
+                    n = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    fn touch(dir: &Path, name: &str, age_secs: u64) {
+        let file = File::create(dir.join(name)).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_newest_matching_file_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(dir.path(), "app-2024-06-01.log", 300);
+        touch(dir.path(), "app-2024-06-02.log", 100);
+        // Renamed by hand after rotation, so older by name but newest by mtime
+        touch(dir.path(), "app-old.log", 10);
+        touch(dir.path(), "other-2024-06-03.log", 0);
+        std::fs::create_dir(dir.path().join("app-dir.log")).unwrap();
+
+        let pattern = dir.path().join("app-2024-*.log");
+        assert_eq!(
+            newest_match(&pattern).unwrap(),
+            Some(dir.path().join("app-2024-06-02.log"))
+        );
+        let pattern = dir.path().join("app-*.log");
+        assert_eq!(
+            newest_match(&pattern).unwrap(),
+            Some(dir.path().join("app-old.log"))
+        );
+        assert_eq!(newest_match(&dir.path().join("*.gz")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(wildcard_match("app-*.log", "app-2024-06-01.log"));
+        assert!(wildcard_match("app-*.log", "app-.log"));
+        assert!(wildcard_match("app-??.log", "app-01.log"));
+        assert!(!wildcard_match("app-??.log", "app-1.log"));
+        assert!(wildcard_match("*log*", "a.log.1"));
+        assert!(!wildcard_match("app-*.log", "app-1.log.gz"));
+        assert!(wildcard_match("*.log", "a.b.log"));
+        assert!(wildcard_match("plain.log", "plain.log"));
+    }
+}
@@ -0,0 +1,166 @@
+//! File metadata for the info display.
+//!
+//! Captured from `std::fs::Metadata` when the accessor is created, so showing it never touches
+//! the filesystem again.
+
+use crate::error::{Result, RllessError};
+use chrono::{DateTime, Local};
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Modification time, size and permissions of the file being viewed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMeta {
+    /// Size on disk in bytes; for compressed files this is the compressed size
+    pub size: u64,
+    /// Last modification time, if the platform reports one
+    pub modified: Option<SystemTime>,
+    /// Unix permission bits, `None` on platforms without them
+    pub mode: Option<u32>,
+    pub readonly: bool,
+}
+
+impl FileMeta {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode())
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            mode,
+            readonly: metadata.permissions().readonly(),
+        }
+    }
+
+    /// Read the metadata of the file at `path`
+    pub fn read(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .map_err(|e| RllessError::file_error("Failed to read file metadata", e))?;
+        Ok(Self::from_metadata(&metadata))
+    }
+
+    /// One-line summary such as `modified 2024-05-01 13:45:10 | 1.5 MiB | -rw-r--r--`
+    ///
+    /// `format_time` renders the modification time, normally `format_local_time`.
+    pub fn describe(&self, format_time: impl Fn(SystemTime) -> String) -> String {
+        let modified = self
+            .modified
+            .map_or_else(|| "unknown".to_string(), format_time);
+        let permissions = match self.mode {
+            Some(mode) => permission_string(mode),
+            None if self.readonly => "read-only".to_string(),
+            None => "read-write".to_string(),
+        };
+        format!(
+            "modified {} | {} | {}",
+            modified,
+            human_size(self.size),
+            permissions
+        )
+    }
+}
+
+/// Format `time` in the local time zone
+pub fn format_local_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// Byte count in binary units, e.g. `512 B` or `1.5 MiB`
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// `ls -l` style permission string for a regular file
+fn permission_string(mode: u32) -> String {
+    let mut text = String::from("-");
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_human_size_units() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(human_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[test]
+    fn test_permission_string() {
+        assert_eq!(permission_string(0o644), "-rw-r--r--");
+        assert_eq!(permission_string(0o100755), "-rwxr-xr-x");
+        assert_eq!(permission_string(0o400), "-r--------");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_describe_fixture_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), vec![b'x'; 1536]).unwrap();
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o640)).unwrap();
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(file.path())
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let meta = FileMeta::read(file.path()).unwrap();
+        assert_eq!(meta.modified, Some(mtime));
+        let formatted = meta.describe(|time| {
+            let secs = time
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            format!("@{}", secs)
+        });
+        assert_eq!(formatted, "modified @1700000000 | 1.5 KiB | -rw-r-----");
+    }
+
+    #[test]
+    fn test_describe_without_mode_or_mtime() {
+        let meta = FileMeta {
+            size: 10,
+            modified: None,
+            mode: None,
+            readonly: true,
+        };
+        assert_eq!(
+            meta.describe(format_local_time),
+            "modified unknown | 10 B | read-only"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Log data piped to rlless, as in `kubectl logs pod | rlless`.
+//!
+//! A reader thread collects the input into an `AdaptiveFileAccessor` that is returned right away
+//! and grows as data arrives, so the first page shows before the input ends. Input is kept in
+//! memory up to a threshold and then moved to a temp file that further input is appended to.
+//! Every `PUBLISH_INTERVAL` a second thread replaces the accessor's content with one covering
+//! everything read so far, if anything new arrived. Both kinds of storage are shared with the
+//! published versions rather than copied into them: an `AppendBuffer` in memory, a fresh
+//! mapping of the temp file once spilled.
+
+use crate::error::{Result, RllessError};
+use crate::file_handler::adaptive::{
+    AdaptiveFileAccessor, AppendBuffer, ByteSource, MappedTempFile,
+};
+use crate::file_handler::terminator::LineTerminator;
+use parking_lot::Mutex;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use tokio::sync::watch;
+
+/// Path the accessor reports for piped input, shown on the status line
+pub const STDIN_PATH: &str = "<stdin>";
+
+/// Time between updates of the accessor while input arrives
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Bytes asked for per read
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// How much piped input has been read, and whether the input has ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PipeProgress {
+    pub bytes: u64,
+    pub finished: bool,
+}
+
+/// Handle on the thread reading piped input into an accessor
+#[derive(Debug)]
+pub struct PipedInput {
+    progress: watch::Receiver<PipeProgress>,
+    error: Arc<Mutex<Option<std::io::Error>>>,
+}
+
+impl PipedInput {
+    /// Start reading `reader` on its own thread into an accessor reporting `STDIN_PATH`
+    ///
+    /// Lines of the accessor end at `terminator`. Input stays in memory until it passes
+    /// `memory_threshold` bytes and then moves to a temp file. The threads are not joined: an
+    /// input that never ends, like a followed log, simply goes away with the process.
+    pub fn spawn<R: Read + Send + 'static>(
+        reader: R,
+        memory_threshold: u64,
+        terminator: LineTerminator,
+    ) -> (Arc<AdaptiveFileAccessor>, Self) {
+        let accessor = Arc::new(
+            AdaptiveFileAccessor::new(
+                ByteSource::InMemory(Vec::new()),
+                0,
+                PathBuf::from(STDIN_PATH),
+            )
+            .with_line_terminator(terminator),
+        );
+        let (progress_tx, progress) = watch::channel(PipeProgress::default());
+        let error = Arc::new(Mutex::new(None));
+        let collected = Arc::new(Mutex::new(Collected::Memory(AppendBuffer::with_capacity(
+            CHUNK_BYTES,
+        ))));
+        let (done_tx, done_rx) = mpsc::channel();
+
+        let reading = Arc::clone(&collected);
+        std::thread::spawn(move || {
+            let _ = done_tx.send(collect(reader, &reading, memory_threshold));
+        });
+
+        let target = Arc::clone(&accessor);
+        let thread_error = Arc::clone(&error);
+        std::thread::spawn(move || {
+            if let Err(err) = publish(&collected, &target, &done_rx, |bytes| {
+                progress_tx.send_replace(PipeProgress {
+                    bytes,
+                    finished: false,
+                });
+            }) {
+                log::warn!(
+                    "piped input: read failed after {} bytes",
+                    collected.lock().len()
+                );
+                *thread_error.lock() = Some(err);
+            }
+            progress_tx.send_replace(PipeProgress {
+                bytes: collected.lock().len(),
+                finished: true,
+            });
+        });
+
+        (accessor, Self { progress, error })
+    }
+
+    /// Receiver that changes whenever more input has been made visible
+    pub fn subscribe(&self) -> watch::Receiver<PipeProgress> {
+        self.progress.clone()
+    }
+
+    /// Wait until the input has ended and everything read is in the accessor
+    pub async fn finished(mut self) -> Result<()> {
+        self.progress
+            .wait_for(|progress| progress.finished)
+            .await
+            .map_err(|_| RllessError::other("Piped input reader went away"))?;
+        match self.error.lock().take() {
+            Some(err) => Err(RllessError::file_error("Failed to read piped input", err)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Where the input read so far is kept
+enum Collected {
+    Memory(AppendBuffer),
+    Spilled {
+        writer: File,
+        temp_file: Arc<MappedTempFile>,
+        len: u64,
+    },
+}
+
+impl Collected {
+    fn len(&self) -> u64 {
+        match self {
+            Collected::Memory(data) => data.as_slice().len() as u64,
+            Collected::Spilled { len, .. } => *len,
+        }
+    }
+
+    fn append(&mut self, bytes: &[u8], memory_threshold: u64) -> std::io::Result<()> {
+        match self {
+            Collected::Memory(data) => {
+                data.extend_from_slice(bytes);
+                if data.as_slice().len() as u64 > memory_threshold {
+                    *self = Self::spill(data.as_slice())?;
+                }
+            }
+            Collected::Spilled { writer, len, .. } => {
+                writer.write_all(bytes)?;
+                *len += bytes.len() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    fn spill(data: &[u8]) -> std::io::Result<Self> {
+        let mut temp_file = NamedTempFile::new()?;
+        temp_file.write_all(data)?;
+        let writer = temp_file.as_file().try_clone()?;
+        let (_, temp_file) =
+            MappedTempFile::map(temp_file).map_err(|err| std::io::Error::other(err.to_string()))?;
+        log::info!("piped input: moved {} bytes to a temp file", data.len());
+        Ok(Collected::Spilled {
+            writer,
+            temp_file: Arc::new(temp_file),
+            len: data.len() as u64,
+        })
+    }
+
+    /// Source covering everything collected so far
+    fn source(&self) -> Result<ByteSource> {
+        match self {
+            Collected::Memory(data) => Ok(ByteSource::Streamed(data.share())),
+            Collected::Spilled { temp_file, .. } => Ok(ByteSource::Piped {
+                mmap: temp_file.remap()?,
+                _temp_file: Arc::clone(temp_file),
+            }),
+        }
+    }
+}
+
+/// Read `reader` to its end into `collected`
+fn collect(
+    mut reader: impl Read,
+    collected: &Mutex<Collected>,
+    memory_threshold: u64,
+) -> std::io::Result<()> {
+    let mut chunk = vec![0; CHUNK_BYTES];
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        collected.lock().append(&chunk[..read], memory_threshold)?;
+    }
+}
+
+/// Show what `collected` holds in `accessor` every `PUBLISH_INTERVAL` until `done` reports the
+/// end of the input, then once more; returns how the reading ended
+fn publish(
+    collected: &Mutex<Collected>,
+    accessor: &AdaptiveFileAccessor,
+    done: &mpsc::Receiver<std::io::Result<()>>,
+    mut published: impl FnMut(u64),
+) -> std::io::Result<()> {
+    let mut shown = 0;
+    loop {
+        let outcome = match done.recv_timeout(PUBLISH_INTERVAL) {
+            Ok(outcome) => Some(outcome),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => {
+                Some(Err(std::io::Error::other("piped input reader stopped")))
+            }
+        };
+        let update = {
+            let collected = collected.lock();
+            (collected.len() != shown).then(|| (collected.source(), collected.len()))
+        };
+        if let Some((source, len)) = update {
+            let source = source.map_err(|err| std::io::Error::other(err.to_string()))?;
+            accessor.replace_source(source);
+            shown = len;
+            published(len);
+        }
+        if let Some(outcome) = outcome {
+            return outcome;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::adaptive::PinnedAccessor;
+    use crate::file_handler::FileAccessor;
+    use std::io::Cursor;
+    use std::sync::mpsc;
+
+    /// Reader handing out the chunks sent to it, ending when the sender is dropped
+    struct ChannelReader(mpsc::Receiver<Vec<u8>>);
+
+    impl Read for ChannelReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.recv() {
+                Ok(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                Err(_) => Ok(0),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lines_are_readable_before_the_input_ends() {
+        let (chunks, rx) = mpsc::channel();
+        let (accessor, input) =
+            PipedInput::spawn(ChannelReader(rx), 1024 * 1024, LineTerminator::Newline);
+        let mut progress = input.subscribe();
+        assert_eq!(accessor.file_path(), std::path::Path::new(STDIN_PATH));
+
+        chunks.send(b"first\nsecond\n".to_vec()).unwrap();
+        progress.wait_for(|p| p.bytes == 13).await.unwrap();
+        assert_eq!(
+            accessor.read_from_byte(0, 10).await.unwrap(),
+            vec!["first", "second"]
+        );
+        assert_eq!(accessor.last_page_start(1).await.unwrap(), 6);
+        let first = accessor.pin();
+
+        chunks.send(b"third\n".to_vec()).unwrap();
+        drop(chunks);
+        input.finished().await.unwrap();
+        assert_eq!(accessor.file_size(), 19);
+        assert_eq!(accessor.last_page_start(1).await.unwrap(), 13);
+
+        // Both versions read the buffer the input went into, rather than copies of it
+        let streamed = |pinned: &PinnedAccessor| match pinned.source() {
+            ByteSource::Streamed(bytes) => bytes.as_slice().as_ptr(),
+            other => panic!("expected streamed bytes, got {other:?}"),
+        };
+        assert_eq!(streamed(&first), streamed(&accessor.pin()));
+        assert_eq!(first.file_size(), 13);
+    }
+
+    #[tokio::test]
+    async fn test_input_past_the_threshold_moves_to_a_temp_file() {
+        let content: Vec<u8> = (0..20_000)
+            .flat_map(|i| format!("piped line {i}\n").into_bytes())
+            .collect();
+        let (accessor, input) =
+            PipedInput::spawn(Cursor::new(content.clone()), 4096, LineTerminator::Newline);
+        input.finished().await.unwrap();
+
+        assert!(matches!(accessor.pin().source(), ByteSource::Piped { .. }));
+        assert_eq!(accessor.file_size(), content.len() as u64);
+        assert_eq!(
+            accessor.read_bytes(0, content.len()).await.unwrap(),
+            content
+        );
+        let last = accessor.last_page_start(1).await.unwrap();
+        assert_eq!(
+            accessor.read_from_byte(last, 1).await.unwrap(),
+            vec!["piped line 19999"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_input_gives_an_empty_accessor() {
+        let (accessor, input) =
+            PipedInput::spawn(Cursor::new(Vec::new()), 4096, LineTerminator::Newline);
+        input.finished().await.unwrap();
+        assert_eq!(accessor.file_size(), 0);
+        assert!(accessor.read_from_byte(0, 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+//! Memory pressure detection.
+//!
+//! Used to decide whether in-memory byte sources should be spilled to disk. Only cgroup v2 limits
+//! are consulted; when no limit is visible the process is assumed to have room to spare.
+
+use std::path::Path;
+
+/// Location of the cgroup v2 interface files for the current (namespaced) cgroup.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Remaining headroom below which the cgroup is considered under pressure.
+const MIN_HEADROOM_BYTES: u64 = 256 * 1024 * 1024; // 256MB
+
+/// Check whether the enclosing cgroup is close to its memory limit
+pub fn cgroup_memory_pressure() -> bool {
+    let read = |name: &str| std::fs::read_to_string(Path::new(CGROUP_ROOT).join(name)).ok();
+    match (read("memory.max"), read("memory.current")) {
+        (Some(max), Some(current)) => headroom_is_low(&max, &current),
+        _ => false,
+    }
+}
+
+/// Compare the contents of `memory.max` and `memory.current`
+fn headroom_is_low(max: &str, current: &str) -> bool {
+    // `memory.max` reads "max" when the cgroup is unlimited
+    let (Ok(max), Ok(current)) = (max.trim().parse::<u64>(), current.trim().parse::<u64>()) else {
+        return false;
+    };
+    max.saturating_sub(current) < MIN_HEADROOM_BYTES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headroom_is_low() {
+        const GB: u64 = 1024 * 1024 * 1024;
+
+        assert!(!headroom_is_low("max\n", "123456\n"));
+        assert!(!headroom_is_low(
+            &format!("{}\n", 4 * GB),
+            &format!("{}\n", GB)
+        ));
+        assert!(headroom_is_low(
+            &format!("{}\n", GB),
+            &format!("{}\n", GB - 1024)
+        ));
+        // Usage above the limit (possible transiently) still counts as pressure
+        assert!(headroom_is_low(&format!("{}", GB), &format!("{}", 2 * GB)));
+        assert!(!headroom_is_low("garbage", "0"));
+    }
+}
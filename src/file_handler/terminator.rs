@@ -0,0 +1,34 @@
+//! The byte that ends a line.
+//!
+//! Lines normally end at `\n`. With `--null` they end at NUL instead, for tools that write
+//! NUL-separated records (`find -print0` captures, some audit logs); a record may then hold
+//! newlines of its own, which the renderer draws as a visible symbol. Paging, line numbers and
+//! searches all split the content at the accessor's terminator.
+
+/// What separates the lines of a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineTerminator {
+    /// `\n`, as in ordinary text files
+    #[default]
+    Newline,
+    /// `\0`, for NUL-separated records
+    Nul,
+}
+
+impl LineTerminator {
+    /// The terminating byte
+    pub fn byte(self) -> u8 {
+        match self {
+            LineTerminator::Newline => b'\n',
+            LineTerminator::Nul => b'\0',
+        }
+    }
+
+    /// Name shown by `:options`
+    pub fn name(self) -> &'static str {
+        match self {
+            LineTerminator::Newline => "newline",
+            LineTerminator::Nul => "nul",
+        }
+    }
+}
@@ -3,11 +3,16 @@
 //! Phase 1 introduces empty shells so we can migrate existing logic in later steps
 //! without a massive diff.
 
+pub mod command;
+pub mod keymap;
 pub mod raw;
 pub mod service;
 
 // Public re-exports for convenience. Modules outside this crate should prefer importing
 // from `crate::input` rather than reaching into submodules.
+pub use command::{BookmarkCommand, ColonCommand};
+pub use keymap::{Keymap, SharedKeymap};
+pub use raw::PrecisionScroll;
 pub use service::{
     spawn_input_thread, InputAction, InputService, InputState, InputStateMachine, ScrollDirection,
     SearchDirection,
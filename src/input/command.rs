@@ -0,0 +1,291 @@
+//! Colon commands.
+//!
+//! Parses the buffer entered at the `:` prompt into a typed command for the render coordinator.
+//! Errors are plain strings because they are shown verbatim in the status line.
+
+use crate::input::SearchDirection;
+use crate::search::timestamps::TimeTarget;
+use std::path::PathBuf;
+
+/// Commands accepted at the `:` prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColonCommand {
+    /// Jump past the run of lines identical to the current top line (`skip-dups`,
+    /// `skip-dups-back`).
+    SkipDuplicates(SearchDirection),
+    /// Forget all recorded search patterns (`clear-history`).
+    ClearHistory,
+    /// List every effective setting and where it came from in an overlay (`options`).
+    Options,
+    /// Show coordinator metrics such as viewport latency and coalesced commands (`metrics`).
+    Metrics,
+    /// List the named patterns usable as `@name` in searches (`patterns`).
+    Patterns,
+    /// Search for any line of a file (`loadset FILE`).
+    LoadSet(PathBuf),
+    /// Write the screen to a file, with ANSI styling for `.ans` files (`snapshot FILE`).
+    Snapshot(PathBuf),
+    /// Write the whole file to a new file in the background (`save FILE`, or `s`).
+    Save(PathBuf),
+    /// Feed the whole file to a shell command in the background (`pipe COMMAND`, or `|`).
+    Pipe(String),
+    /// Keep the active search highlighted in a color of its own after the next search replaces
+    /// it (`keep`), or stop highlighting the kept patterns (`keep clear`).
+    Keep { clear: bool },
+    /// Show a locator that reopens the file at the current position and search (`loc`).
+    Locator,
+    /// Add, list or jump to named bookmarks (`bookmark`).
+    Bookmark(BookmarkCommand),
+    /// Go to the first log record stamped at or after a time (`time 14:32:05`,
+    /// `time 2024-06-01T14:32:05`).
+    Time(TimeTarget),
+}
+
+/// What `:bookmark` does with its arguments
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookmarkCommand {
+    /// Label the line at the top of the screen (`bookmark add LABEL`).
+    Add(String),
+    /// List the bookmarks with their numbers in an overlay (`bookmark`, `bookmark list`).
+    List,
+    /// Go to the bookmark with this number (`bookmark N`).
+    Jump(usize),
+}
+
+impl ColonCommand {
+    /// Parse a colon command buffer (without the leading `:`).
+    pub fn parse(buffer: &str) -> Result<Self, String> {
+        let mut words = buffer.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err("No command entered".to_string());
+        };
+
+        let command = match name {
+            "skip-dups" => ColonCommand::SkipDuplicates(SearchDirection::Forward),
+            "skip-dups-back" => ColonCommand::SkipDuplicates(SearchDirection::Backward),
+            "clear-history" => ColonCommand::ClearHistory,
+            "options" => ColonCommand::Options,
+            "metrics" => ColonCommand::Metrics,
+            "patterns" => ColonCommand::Patterns,
+            "loc" => ColonCommand::Locator,
+            "keep" => match words.next() {
+                None => ColonCommand::Keep { clear: false },
+                Some("clear") => ColonCommand::Keep { clear: true },
+                Some(other) => return Err(format!("Unexpected argument for keep: {}", other)),
+            },
+            "bookmark" => {
+                return parse_bookmark(buffer.trim_start()[name.len()..].trim())
+                    .map(ColonCommand::Bookmark);
+            }
+            "time" => {
+                let time = buffer.trim_start()[name.len()..].trim();
+                if time.is_empty() {
+                    return Err("Missing time for time".to_string());
+                }
+                return TimeTarget::parse(time)
+                    .map(ColonCommand::Time)
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid time: {} (expected HH:MM[:SS] or YYYY-MM-DDTHH:MM[:SS])",
+                            time
+                        )
+                    });
+            }
+            "pipe" => {
+                let command = buffer.trim_start()[name.len()..].trim();
+                if command.is_empty() {
+                    return Err("Missing command for pipe".to_string());
+                }
+                return Ok(ColonCommand::Pipe(command.to_string()));
+            }
+            "loadset" | "snapshot" | "save" => {
+                // The rest of the line, so file names may contain spaces
+                let path = PathBuf::from(buffer.trim_start()[name.len()..].trim());
+                if path.as_os_str().is_empty() {
+                    return Err(format!("Missing file name for {}", name));
+                }
+                return Ok(match name {
+                    "loadset" => ColonCommand::LoadSet(path),
+                    "save" => ColonCommand::Save(path),
+                    _ => ColonCommand::Snapshot(path),
+                });
+            }
+            other => return Err(format!("Unknown command: {}", other)),
+        };
+
+        if let Some(extra) = words.next() {
+            return Err(format!("Unexpected argument for {}: {}", name, extra));
+        }
+        Ok(command)
+    }
+
+    /// The command shows its result in the line overlay, which then takes the keys
+    pub fn opens_overlay(&self) -> bool {
+        matches!(
+            self,
+            ColonCommand::Options
+                | ColonCommand::Metrics
+                | ColonCommand::Patterns
+                | ColonCommand::Locator
+                | ColonCommand::Bookmark(BookmarkCommand::List)
+        )
+    }
+}
+
+fn parse_bookmark(args: &str) -> Result<BookmarkCommand, String> {
+    match args.split_once(char::is_whitespace) {
+        _ if args.is_empty() || args == "list" => Ok(BookmarkCommand::List),
+        Some(("add", label)) => {
+            // The label may be quoted to make clear where it starts and ends
+            let label = label.trim();
+            let label = label
+                .strip_prefix('"')
+                .and_then(|label| label.strip_suffix('"'))
+                .unwrap_or(label);
+            if label.trim().is_empty() {
+                return Err("Missing label for bookmark add".to_string());
+            }
+            Ok(BookmarkCommand::Add(label.to_string()))
+        }
+        None if args == "add" => Err("Missing label for bookmark add".to_string()),
+        None => match args.parse() {
+            Ok(number) if number > 0 => Ok(BookmarkCommand::Jump(number)),
+            _ => Err(format!("Unknown bookmark command: {}", args)),
+        },
+        Some(_) => Err(format!("Unknown bookmark command: {}", args)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_skip_dups_variants() {
+        assert_eq!(
+            ColonCommand::parse("skip-dups"),
+            Ok(ColonCommand::SkipDuplicates(SearchDirection::Forward))
+        );
+        assert_eq!(
+            ColonCommand::parse("  skip-dups-back "),
+            Ok(ColonCommand::SkipDuplicates(SearchDirection::Backward))
+        );
+        assert_eq!(
+            ColonCommand::parse("clear-history"),
+            Ok(ColonCommand::ClearHistory)
+        );
+        assert_eq!(ColonCommand::parse("options"), Ok(ColonCommand::Options));
+        assert_eq!(ColonCommand::parse("metrics"), Ok(ColonCommand::Metrics));
+        assert_eq!(ColonCommand::parse("patterns"), Ok(ColonCommand::Patterns));
+        assert_eq!(ColonCommand::parse("loc"), Ok(ColonCommand::Locator));
+        assert_eq!(
+            ColonCommand::parse("keep"),
+            Ok(ColonCommand::Keep { clear: false })
+        );
+        assert_eq!(
+            ColonCommand::parse("keep clear"),
+            Ok(ColonCommand::Keep { clear: true })
+        );
+        assert_eq!(
+            ColonCommand::parse("loadset  request ids.txt "),
+            Ok(ColonCommand::LoadSet(PathBuf::from("request ids.txt")))
+        );
+        assert_eq!(
+            ColonCommand::parse("snapshot /tmp/view.ans"),
+            Ok(ColonCommand::Snapshot(PathBuf::from("/tmp/view.ans")))
+        );
+        assert_eq!(
+            ColonCommand::parse("save  all errors.log"),
+            Ok(ColonCommand::Save(PathBuf::from("all errors.log")))
+        );
+        assert_eq!(
+            ColonCommand::parse("pipe grep -c ERROR | tee counts "),
+            Ok(ColonCommand::Pipe("grep -c ERROR | tee counts".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_time_commands() {
+        let Ok(ColonCommand::Time(target)) = ColonCommand::parse("time 14:32:05") else {
+            panic!("expected a time");
+        };
+        assert_eq!(target.to_string(), "14:32:05");
+        let Ok(ColonCommand::Time(target)) = ColonCommand::parse(" time 2024-06-01 14:32 ") else {
+            panic!("expected a time");
+        };
+        assert_eq!(target.to_string(), "2024-06-01 14:32:00");
+        assert_eq!(
+            ColonCommand::parse("time"),
+            Err("Missing time for time".to_string())
+        );
+        assert!(ColonCommand::parse("time noon")
+            .is_err_and(|message| message.starts_with("Invalid time: noon")));
+    }
+
+    #[test]
+    fn parses_bookmark_commands() {
+        let bookmark = |buffer| ColonCommand::parse(buffer);
+        assert_eq!(
+            bookmark(r#"bookmark add "suspect GC pause""#),
+            Ok(ColonCommand::Bookmark(BookmarkCommand::Add(
+                "suspect GC pause".to_string()
+            )))
+        );
+        assert_eq!(
+            bookmark("bookmark add  deploy started "),
+            Ok(ColonCommand::Bookmark(BookmarkCommand::Add(
+                "deploy started".to_string()
+            )))
+        );
+        assert_eq!(
+            bookmark("bookmark"),
+            Ok(ColonCommand::Bookmark(BookmarkCommand::List))
+        );
+        assert_eq!(
+            bookmark("bookmark list"),
+            Ok(ColonCommand::Bookmark(BookmarkCommand::List))
+        );
+        assert_eq!(
+            bookmark("bookmark 3"),
+            Ok(ColonCommand::Bookmark(BookmarkCommand::Jump(3)))
+        );
+        assert!(bookmark("bookmark list").unwrap().opens_overlay());
+        assert_eq!(
+            bookmark("bookmark add"),
+            Err("Missing label for bookmark add".to_string())
+        );
+        assert!(bookmark("bookmark 0").is_err());
+        assert!(bookmark("bookmark drop 2").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_or_malformed_commands() {
+        assert_eq!(
+            ColonCommand::parse(""),
+            Err("No command entered".to_string())
+        );
+        assert_eq!(
+            ColonCommand::parse("frobnicate"),
+            Err("Unknown command: frobnicate".to_string())
+        );
+        assert!(ColonCommand::parse("skip-dups 3").is_err());
+        assert!(ColonCommand::parse("keep all").is_err());
+        assert!(ColonCommand::parse("keep clear now").is_err());
+        assert_eq!(
+            ColonCommand::parse("loadset"),
+            Err("Missing file name for loadset".to_string())
+        );
+        assert_eq!(
+            ColonCommand::parse("snapshot "),
+            Err("Missing file name for snapshot".to_string())
+        );
+        assert_eq!(
+            ColonCommand::parse("save"),
+            Err("Missing file name for save".to_string())
+        );
+        assert_eq!(
+            ColonCommand::parse("pipe  "),
+            Err("Missing command for pipe".to_string())
+        );
+    }
+}
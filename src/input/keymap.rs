@@ -0,0 +1,372 @@
+//! User key bindings for navigation mode.
+//!
+//! Bindings are given as chord names (`j`, `ctrl-d`, `alt-n` or `esc-n`, `pagedown`) and compiled once into
+//! a `Keymap`, so the input thread never parses names or allocates while handling a key: plain
+//! ASCII keys are looked up by index, anything else in a small hash map. Keys without a user
+//! binding fall through to the built-in `less` bindings.
+//!
+//! The compiled map sits behind a `SharedKeymap` handle; `replace` swaps in a rebuilt map in one
+//! step, so a key is always resolved against either the old bindings or the new ones.
+//!
+//! `--keymap FILE` loads bindings from a file with one `chord command` pair per line, using the
+//! `lesskey` command names where `less` has one:
+//!
+//! ```text
+//! # Emacs-style paging
+//! ctrl-v  forw-screen
+//! alt-v   back-screen
+//! ```
+
+use crate::error::{Result, RllessError};
+use crate::input::{InputAction, ScrollDirection};
+use parking_lot::RwLock;
+use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Modifier combinations with their own ASCII table: none, Ctrl, Alt, Ctrl+Alt
+const MODIFIER_SLOTS: usize = 4;
+
+/// Compiled key bindings.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// ASCII keys, indexed by `modifier slot * 128 + character`
+    ascii: Vec<Option<InputAction>>,
+    other: HashMap<(KeyCode, KeyModifiers), InputAction>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            ascii: vec![None; MODIFIER_SLOTS * 128],
+            other: HashMap::new(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind `chord` to `action`, replacing any earlier binding of the same chord
+    ///
+    /// Only actions that leave the input mode unchanged can be bound; prompts keep their
+    /// built-in keys.
+    pub fn bind(&mut self, chord: &str, action: InputAction) -> Result<()> {
+        if !is_bindable(&action) {
+            return Err(RllessError::other(format!(
+                "{:?} cannot be bound to a key",
+                action
+            )));
+        }
+        let (code, modifiers) = parse_chord(chord)?;
+        match ascii_index(code, modifiers) {
+            Some(index) => self.ascii[index] = Some(action),
+            None => {
+                self.other.insert((code, modifiers), action);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compile the bindings in a keymap file
+    ///
+    /// Blank lines and lines starting with `#` are skipped; later lines override earlier ones.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut keymap = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: String| {
+                RllessError::other(format!("keymap line {}: {}", index + 1, message))
+            };
+            let mut fields = line.split_whitespace();
+            let (Some(chord), Some(command), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid(format!("expected `chord command`, got {:?}", line)));
+            };
+            let action = parse_command(command)
+                .ok_or_else(|| invalid(format!("unknown command {:?}", command)))?;
+            keymap
+                .bind(chord, action)
+                .map_err(|err| invalid(err.to_string()))?;
+        }
+        Ok(keymap)
+    }
+
+    /// Read and compile the keymap file at `path`
+    pub async fn load(path: &Path) -> Result<Self> {
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| RllessError::file_error("Failed to read keymap", e))?;
+        Self::parse(&text)
+    }
+
+    /// The user binding for a key, if there is one
+    pub fn lookup(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<&InputAction> {
+        let modifiers = normalize(code, modifiers);
+        match ascii_index(code, modifiers) {
+            Some(index) => self.ascii[index].as_ref(),
+            None => self.other.get(&(code, modifiers)),
+        }
+    }
+}
+
+/// Cloneable handle to the keymap in use.
+#[derive(Debug, Clone, Default)]
+pub struct SharedKeymap {
+    current: Arc<RwLock<Arc<Keymap>>>,
+}
+
+impl SharedKeymap {
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(keymap))),
+        }
+    }
+
+    /// Swap in a rebuilt keymap for every holder of this handle
+    pub fn replace(&self, keymap: Keymap) {
+        *self.current.write() = Arc::new(keymap);
+    }
+
+    /// The user binding for a key under the current keymap
+    pub fn lookup(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<InputAction> {
+        self.current.read().lookup(code, modifiers).cloned()
+    }
+}
+
+/// The action named by a keymap command
+fn parse_command(command: &str) -> Option<InputAction> {
+    let scroll = |direction| InputAction::Scroll {
+        direction,
+        lines: 1,
+    };
+    let action = match command {
+        "forw-line" => scroll(ScrollDirection::Down),
+        "back-line" => scroll(ScrollDirection::Up),
+        "forw-screen" => InputAction::PageDown,
+        "back-screen" => InputAction::PageUp,
+        "forw-scroll" => InputAction::HalfPageDown,
+        "back-scroll" => InputAction::HalfPageUp,
+        "goto-line" => InputAction::GoToStart,
+        "goto-end" => InputAction::GoToEnd,
+        "next-section" => InputAction::NextSection,
+        "prev-section" => InputAction::PreviousSection,
+        "next-record" => InputAction::NextRecord,
+        "prev-record" => InputAction::PreviousRecord,
+        "repeat-search" => InputAction::NextMatch,
+        "reverse-search" => InputAction::PreviousMatch,
+        "repeat-non-match" => InputAction::NextNonMatch,
+        "reverse-non-match" => InputAction::PreviousNonMatch,
+        "search-word" => InputAction::SearchCurrentMatchWord,
+        "status" => InputAction::ShowFileInfo,
+        "presentation" => InputAction::TogglePresentation,
+        "follow" => InputAction::ToggleFollow,
+        "undo-hilite" => InputAction::ToggleHighlights,
+        "cycle-pattern" => InputAction::CyclePrimaryPattern,
+        "quit" => InputAction::Quit,
+        _ => return None,
+    };
+    Some(action)
+}
+
+fn is_bindable(action: &InputAction) -> bool {
+    matches!(
+        action,
+        InputAction::Scroll { .. }
+            | InputAction::PageUp
+            | InputAction::PageDown
+            | InputAction::HalfPageUp
+            | InputAction::HalfPageDown
+            | InputAction::GoToStart
+            | InputAction::GoToEnd
+            | InputAction::PreviousSection
+            | InputAction::NextSection
+            | InputAction::PreviousRecord
+            | InputAction::NextRecord
+            | InputAction::Quit
+            | InputAction::Interrupt
+            | InputAction::TogglePresentation
+            | InputAction::ToggleFollow
+            | InputAction::ToggleHighlights
+            | InputAction::CyclePrimaryPattern
+            | InputAction::NextMatch
+            | InputAction::PreviousMatch
+            | InputAction::NextNonMatch
+            | InputAction::PreviousNonMatch
+            | InputAction::SearchCurrentMatchWord
+            | InputAction::ShowFileInfo
+    )
+}
+
+/// Terminals report `G` as Shift+G; the character alone already says it is uppercase
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> KeyModifiers {
+    match code {
+        KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+        _ => modifiers,
+    }
+}
+
+fn ascii_index(code: KeyCode, modifiers: KeyModifiers) -> Option<usize> {
+    let KeyCode::Char(ch) = code else {
+        return None;
+    };
+    if !ch.is_ascii() {
+        return None;
+    }
+    let slot = match modifiers {
+        KeyModifiers::NONE => 0,
+        KeyModifiers::CONTROL => 1,
+        KeyModifiers::ALT => 2,
+        m if m == KeyModifiers::CONTROL | KeyModifiers::ALT => 3,
+        _ => return None,
+    };
+    Some(slot * 128 + ch as usize)
+}
+
+/// Parse `ctrl-alt-x`, `esc-)`, `G`, `space`, `pagedown`, `f5`, … into a key and its modifiers
+///
+/// `esc-` is the `less` spelling of `alt-`: terminals send both as ESC followed by the key.
+fn parse_chord(chord: &str) -> Result<(KeyCode, KeyModifiers)> {
+    let invalid = || RllessError::other(format!("Unknown key {:?}", chord));
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = chord;
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        if lower.starts_with("ctrl-") && rest.len() > 5 {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = &rest[5..];
+        } else if (lower.starts_with("alt-") || lower.starts_with("esc-")) && rest.len() > 4 {
+            modifiers |= KeyModifiers::ALT;
+            rest = &rest[4..];
+        } else {
+            break;
+        }
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) => KeyCode::Char(ch),
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(invalid()),
+            },
+        },
+    };
+    Ok((code, normalize(code, modifiers)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chords_compile_to_key_lookups() {
+        let mut keymap = Keymap::new();
+        keymap.bind("ctrl-d", InputAction::PageDown).unwrap();
+        keymap.bind("Alt-Ctrl-x", InputAction::Quit).unwrap();
+        keymap.bind("G", InputAction::GoToStart).unwrap();
+        keymap.bind("f5", InputAction::GoToEnd).unwrap();
+        keymap.bind("space", InputAction::NextMatch).unwrap();
+
+        let ctrl_alt = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('d'), KeyModifiers::CONTROL),
+            Some(&InputAction::PageDown)
+        );
+        assert_eq!(keymap.lookup(KeyCode::Char('d'), KeyModifiers::NONE), None);
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('x'), ctrl_alt),
+            Some(&InputAction::Quit)
+        );
+        // Reported with Shift by the terminal
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('G'), KeyModifiers::SHIFT),
+            Some(&InputAction::GoToStart)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::F(5), KeyModifiers::NONE),
+            Some(&InputAction::GoToEnd)
+        );
+        assert_eq!(
+            keymap.lookup(KeyCode::Char(' '), KeyModifiers::NONE),
+            Some(&InputAction::NextMatch)
+        );
+
+        assert!(keymap.bind("ctrl-", InputAction::Quit).is_err());
+        assert!(keymap.bind("f13", InputAction::Quit).is_err());
+        assert!(keymap.bind("hyper-x", InputAction::Quit).is_err());
+        // Prompts own their keys
+        assert!(keymap.bind("s", InputAction::StartFilter).is_err());
+    }
+
+    #[test]
+    fn test_replace_swaps_bindings_for_every_handle() {
+        let shared = SharedKeymap::default();
+        let input_thread = shared.clone();
+        assert_eq!(input_thread.lookup(KeyCode::Down, KeyModifiers::NONE), None);
+
+        let mut keymap = Keymap::new();
+        keymap
+            .bind(
+                "down",
+                InputAction::Scroll {
+                    direction: ScrollDirection::Down,
+                    lines: 5,
+                },
+            )
+            .unwrap();
+        shared.replace(keymap);
+        assert_eq!(
+            input_thread.lookup(KeyCode::Down, KeyModifiers::NONE),
+            Some(InputAction::Scroll {
+                direction: ScrollDirection::Down,
+                lines: 5,
+            })
+        );
+    }
+    #[test]
+    fn test_keymap_files_name_commands_like_lesskey() {
+        let keymap = Keymap::parse(
+            "# Emacs-style paging\n\
+             ctrl-v  forw-screen\n\
+             \n\
+             alt-v   back-screen\n\
+             ctrl-v  goto-end\n",
+        )
+        .unwrap();
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('v'), KeyModifiers::ALT),
+            Some(&InputAction::PageUp)
+        );
+        // The later line wins
+        assert_eq!(
+            keymap.lookup(KeyCode::Char('v'), KeyModifiers::CONTROL),
+            Some(&InputAction::GoToEnd)
+        );
+
+        let error = |text| Keymap::parse(text).unwrap_err().to_string();
+        assert!(error("j forw-line\nk\n").contains("keymap line 2: expected"));
+        assert!(error("j scroll-down").contains("keymap line 1: unknown command \"scroll-down\""));
+        assert!(error("hyper-j quit").contains("Unknown key"));
+    }
+}
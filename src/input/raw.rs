@@ -3,9 +3,22 @@
 //!
 //! This module now owns scroll coalescing, so repeated wheel events are merged before they reach
 //! the state machine.
+//!
+//! It also joins split ESC sequences. Terminals send Alt+key as ESC followed by the key, and
+//! crossterm reports the pair as one Alt chord when both bytes arrive in the same read. When they
+//! arrive apart (slow links, multiplexers), a lone ESC is held back briefly so that ESC `u` still
+//! reaches the state machine as Alt+u instead of Esc (cancel) followed by `u`.
+//!
+//! While precision scrolling is on, each wheel notch is passed on as one line right away; see
+//! `PrecisionScroll`.
+
+mod escape;
+mod precision;
 
 use crate::error::Result;
 use crate::input::ScrollDirection;
+use escape::EscapeJoiner;
+pub use precision::PrecisionScroll;
 use ratatui::crossterm::event::{self, Event, KeyEvent, MouseEvent, MouseEventKind};
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
@@ -16,6 +29,8 @@ const MOUSE_SCROLL_LINES: u64 = 3;
 const DEFAULT_POLL_TIMEOUT_MS: u64 = 16;
 /// Default coalescing window in milliseconds for scroll bursts.
 const DEFAULT_COALESCE_WINDOW_MS: u64 = 12;
+/// How long a lone ESC waits for the key that may complete an Alt chord.
+const DEFAULT_ESC_TIMEOUT_MS: u64 = 50;
 
 /// Low-level events surfaced by the raw input collector.
 #[derive(Debug, Clone, PartialEq)]
@@ -35,6 +50,8 @@ pub enum RawInputEvent {
 /// higher-level processing.
 pub struct RawInputCollector {
     scroll_coalescer: ScrollCoalescer,
+    escape: EscapeJoiner,
+    precision: PrecisionScroll,
     pending_events: VecDeque<RawInputEvent>,
 }
 
@@ -43,18 +60,33 @@ impl RawInputCollector {
     pub fn new() -> Self {
         Self {
             scroll_coalescer: ScrollCoalescer::with_default_window(),
+            escape: EscapeJoiner::with_default_timeout(),
+            precision: PrecisionScroll::default(),
             pending_events: VecDeque::new(),
         }
     }
 
+    /// Read the wheel mode from `precision` on every wheel event
+    pub fn with_precision_scroll(mut self, precision: PrecisionScroll) -> Self {
+        self.precision = precision;
+        self
+    }
+
     /// Check whether the collector has any queued events or pending scroll aggregation.
     pub fn is_idle(&self) -> bool {
-        self.pending_events.is_empty() && self.scroll_coalescer.is_empty()
+        self.pending_events.is_empty() && self.scroll_coalescer.is_empty() && self.escape.is_empty()
     }
 
     /// Process a synthetic event (primarily used by unit tests).
     pub fn process_event(&mut self, event: Event) {
-        self.enqueue_event(event);
+        self.enqueue_event(event, Instant::now());
+    }
+
+    /// Deliver a held-back ESC now instead of waiting for a key that may complete a chord.
+    pub fn release_escape(&mut self) {
+        if let Some(esc) = self.escape.flush() {
+            self.pending_events.push_back(RawInputEvent::Key(esc));
+        }
     }
 
     /// Retrieve the next raw input event, blocking up to `timeout`.
@@ -63,14 +95,18 @@ impl RawInputCollector {
             return Ok(Some(event));
         }
 
-        let poll_timeout = timeout.unwrap_or(Duration::from_millis(DEFAULT_POLL_TIMEOUT_MS));
+        let mut poll_timeout = timeout.unwrap_or(Duration::from_millis(DEFAULT_POLL_TIMEOUT_MS));
+        // Wake up in time to deliver a lone ESC once its wait is over
+        if let Some(deadline) = self.escape.deadline() {
+            poll_timeout = poll_timeout.min(deadline.saturating_duration_since(Instant::now()));
+        }
 
         if !event::poll(poll_timeout)? {
             return Ok(self.try_flush_scroll());
         }
 
         let event = event::read()?;
-        self.enqueue_event(event);
+        self.enqueue_event(event, Instant::now());
         Ok(self.try_flush_scroll())
     }
 
@@ -85,30 +121,43 @@ impl RawInputCollector {
         self.try_flush_scroll()
     }
 
-    fn enqueue_event(&mut self, event: Event) {
+    fn enqueue_event(&mut self, event: Event, now: Instant) {
         match event {
             Event::Key(key_event) => {
                 self.flush_scroll();
-                self.pending_events.push_back(RawInputEvent::Key(key_event));
+                for key in self.escape.push(key_event, now) {
+                    self.pending_events.push_back(RawInputEvent::Key(key));
+                }
             }
             Event::Resize(width, height) => {
+                self.release_escape();
                 self.flush_scroll();
                 self.pending_events
                     .push_back(RawInputEvent::Resize { width, height });
             }
-            Event::Mouse(mouse_event) => self.queue_scroll(mouse_event),
+            Event::Mouse(mouse_event) => {
+                self.release_escape();
+                self.queue_scroll(mouse_event, now);
+            }
             _ => {}
         }
     }
 
-    fn queue_scroll(&mut self, mouse_event: MouseEvent) {
+    fn queue_scroll(&mut self, mouse_event: MouseEvent, now: Instant) {
         let direction = match mouse_event.kind {
             MouseEventKind::ScrollUp => ScrollDirection::Up,
             MouseEventKind::ScrollDown => ScrollDirection::Down,
             _ => return,
         };
 
-        let now = Instant::now();
+        if self.precision.is_enabled() {
+            self.flush_scroll();
+            self.pending_events.push_back(RawInputEvent::Scroll {
+                direction,
+                lines: 1,
+            });
+            return;
+        }
         if let Some((dir, lines)) = self
             .scroll_coalescer
             .push(direction, MOUSE_SCROLL_LINES, now)
@@ -130,7 +179,14 @@ impl RawInputCollector {
     }
 
     fn try_flush_scroll(&mut self) -> Option<RawInputEvent> {
-        if let Some((dir, lines)) = self.scroll_coalescer.flush_if_stale(Instant::now()) {
+        self.try_flush_at(Instant::now())
+    }
+
+    fn try_flush_at(&mut self, now: Instant) -> Option<RawInputEvent> {
+        if let Some(esc) = self.escape.flush_if_stale(now) {
+            self.pending_events.push_back(RawInputEvent::Key(esc));
+        }
+        if let Some((dir, lines)) = self.scroll_coalescer.flush_if_stale(now) {
             return Some(RawInputEvent::Scroll {
                 direction: dir,
                 lines,
@@ -229,7 +285,7 @@ mod tests {
     use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
     use std::time::Duration;
 
-    fn key_press(code: KeyCode) -> Event {
+    pub(super) fn key_press(code: KeyCode) -> Event {
         Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
     }
 
@@ -0,0 +1,155 @@
+//! Joining an ESC that arrived apart from the key after it into an Alt chord.
+
+use super::DEFAULT_ESC_TIMEOUT_MS;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::time::{Duration, Instant};
+
+/// Holds a lone ESC until the next key shows whether it started an Alt chord.
+#[derive(Debug)]
+pub(super) struct EscapeJoiner {
+    timeout: Duration,
+    pending: Option<(KeyEvent, Instant)>,
+}
+
+impl EscapeJoiner {
+    pub(super) fn with_default_timeout() -> Self {
+        Self::new(Duration::from_millis(DEFAULT_ESC_TIMEOUT_MS))
+    }
+
+    fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            pending: None,
+        }
+    }
+
+    /// Feed a key and return the keys that are ready, in order.
+    pub(super) fn push(&mut self, key: KeyEvent, now: Instant) -> Vec<KeyEvent> {
+        let mut ready = Vec::new();
+        if let Some((esc, at)) = self.pending.take() {
+            if now.duration_since(at) < self.timeout && completes_chord(&key) {
+                ready.push(KeyEvent {
+                    modifiers: key.modifiers | KeyModifiers::ALT,
+                    ..key
+                });
+                return ready;
+            }
+            ready.push(esc);
+        }
+
+        if is_lone_escape(&key) {
+            self.pending = Some((key, now));
+        } else {
+            ready.push(key);
+        }
+        ready
+    }
+
+    pub(super) fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, at)| at + self.timeout)
+    }
+
+    pub(super) fn flush_if_stale(&mut self, now: Instant) -> Option<KeyEvent> {
+        match self.deadline() {
+            Some(deadline) if now >= deadline => self.flush(),
+            _ => None,
+        }
+    }
+
+    pub(super) fn flush(&mut self) -> Option<KeyEvent> {
+        self.pending.take().map(|(esc, _)| esc)
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.pending.is_none()
+    }
+}
+
+fn is_lone_escape(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Esc
+        && key.modifiers == KeyModifiers::NONE
+        && key.kind == KeyEventKind::Press
+}
+
+/// Whether `key` after an ESC forms an Alt chord: a printable key, possibly shifted
+fn completes_chord(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char(ch) if !ch.is_control())
+        && (key.modifiers - KeyModifiers::SHIFT).is_empty()
+        && key.kind == KeyEventKind::Press
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::raw::tests::key_press;
+    use crate::input::raw::{RawInputCollector, RawInputEvent};
+
+    fn key_event(code: KeyCode, modifiers: KeyModifiers) -> RawInputEvent {
+        RawInputEvent::Key(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn split_escape_sequence_becomes_an_alt_chord() {
+        let mut collector = RawInputCollector::new();
+        let start = Instant::now();
+
+        collector.enqueue_event(key_press(KeyCode::Esc), start);
+        assert_eq!(collector.try_flush_at(start), None);
+        assert!(!collector.is_idle());
+        collector.enqueue_event(
+            key_press(KeyCode::Char(')')),
+            start + Duration::from_millis(5),
+        );
+        assert_eq!(
+            collector.try_flush_at(start + Duration::from_millis(5)),
+            Some(key_event(KeyCode::Char(')'), KeyModifiers::ALT))
+        );
+        assert!(collector.is_idle());
+    }
+
+    #[test]
+    fn lone_escape_is_delivered_after_the_timeout() {
+        let timeout = Duration::from_millis(DEFAULT_ESC_TIMEOUT_MS);
+        let mut collector = RawInputCollector::new();
+        let start = Instant::now();
+
+        collector.enqueue_event(key_press(KeyCode::Esc), start);
+        assert_eq!(collector.try_flush_at(start + timeout / 2), None);
+        assert_eq!(
+            collector.try_flush_at(start + timeout),
+            Some(key_event(KeyCode::Esc, KeyModifiers::NONE))
+        );
+
+        // Too late to be a chord: ESC then a plain `u`
+        collector.enqueue_event(key_press(KeyCode::Esc), start);
+        collector.enqueue_event(key_press(KeyCode::Char('u')), start + timeout * 2);
+        assert_eq!(
+            collector.try_flush_at(start + timeout * 2),
+            Some(key_event(KeyCode::Esc, KeyModifiers::NONE))
+        );
+        assert_eq!(
+            collector.try_flush_at(start + timeout * 2),
+            Some(key_event(KeyCode::Char('u'), KeyModifiers::NONE))
+        );
+
+        // Keys that cannot complete a chord release the ESC in front of them
+        collector.enqueue_event(key_press(KeyCode::Esc), start);
+        collector.enqueue_event(key_press(KeyCode::Enter), start);
+        assert_eq!(
+            collector.try_flush_at(start),
+            Some(key_event(KeyCode::Esc, KeyModifiers::NONE))
+        );
+        assert_eq!(
+            collector.try_flush_at(start),
+            Some(key_event(KeyCode::Enter, KeyModifiers::NONE))
+        );
+
+        collector.enqueue_event(key_press(KeyCode::Esc), start);
+        collector.release_escape();
+        assert_eq!(
+            collector.try_flush_at(start),
+            Some(key_event(KeyCode::Esc, KeyModifiers::NONE))
+        );
+        assert!(collector.is_idle());
+    }
+}
@@ -0,0 +1,90 @@
+//! Precision scrolling: one line per wheel notch, with bursts left unmerged.
+//!
+//! Wheel notches normally move three lines and bursts are merged into one scroll, which is
+//! smooth for skimming but too coarse for stepping through a diff line by line. The render
+//! coordinator flips the mode (`-m`, `--precise-scroll`) while the input thread reads it on every
+//! wheel event, so the two share a flag instead of passing messages.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cloneable handle to the precision scrolling switch.
+#[derive(Debug, Clone, Default)]
+pub struct PrecisionScroll {
+    enabled: Arc<AtomicBool>,
+}
+
+impl PrecisionScroll {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+
+    /// Whether wheel notches currently move a single line each
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Switch the mode for every holder of this handle
+    pub fn set(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::raw::{RawInputCollector, RawInputEvent, MOUSE_SCROLL_LINES};
+    use crate::input::ScrollDirection;
+    use ratatui::crossterm::event::{Event, KeyModifiers, MouseEvent, MouseEventKind};
+    use std::time::{Duration, Instant};
+
+    fn notch(collector: &mut RawInputCollector, now: Instant) {
+        collector.enqueue_event(
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::ScrollDown,
+                column: 0,
+                row: 0,
+                modifiers: KeyModifiers::NONE,
+            }),
+            now,
+        );
+    }
+
+    fn down(lines: u64) -> Option<RawInputEvent> {
+        Some(RawInputEvent::Scroll {
+            direction: ScrollDirection::Down,
+            lines,
+        })
+    }
+
+    #[test]
+    fn collector_follows_the_switch_while_running() {
+        let precision = PrecisionScroll::default();
+        let mut collector = RawInputCollector::new().with_precision_scroll(precision.clone());
+        let start = Instant::now();
+
+        // Off: a burst of two notches is merged once the window passes
+        notch(&mut collector, start);
+        notch(&mut collector, start);
+        let later = start + Duration::from_secs(1);
+        assert_eq!(collector.try_flush_at(later), down(MOUSE_SCROLL_LINES * 2));
+
+        // On: every notch is its own one-line scroll, delivered without waiting
+        precision.set(true);
+        notch(&mut collector, later);
+        notch(&mut collector, later);
+        assert_eq!(collector.try_flush_at(later), down(1));
+        assert_eq!(collector.try_flush_at(later), down(1));
+        assert!(collector.is_idle());
+
+        precision.set(false);
+        notch(&mut collector, later);
+        assert_eq!(collector.try_flush_at(later), None);
+        assert_eq!(
+            collector.try_flush_at(later + Duration::from_secs(1)),
+            down(MOUSE_SCROLL_LINES)
+        );
+    }
+}
@@ -2,23 +2,47 @@
 //!
 //! Consumes coalesced raw events, runs the `less`-style input state machine, and yields
 //! domain-level `InputAction`s that the render coordinator consumes.
-
-use crate::error::Result;
-use crate::input::raw::{RawInputCollector, RawInputEvent};
-use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
-use tokio::sync::mpsc::UnboundedSender;
+//!
+//! - `navigation`: keys that browse the file
+//! - `count`: numeric prefixes such as `10j` or `3n`
+//! - `search`: the `/` and `?` prompts
+//! - `history`: browsing the search history with Up and Down
+//! - `prompts`: the `-`, `:`, `&` and `%` prompts and the line overlay
+//! - `editing`: typing and Backspace at every text prompt
+//! - `events`: `InputService` and the input thread
+
+use crate::input::keymap::SharedKeymap;
+use grep_matcher::Matcher;
+use grep_regex::RegexMatcher;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+mod count;
+mod editing;
+mod events;
+mod history;
+mod navigation;
+mod prompts;
+mod search;
+
+pub use events::{spawn_input_thread, InputService};
+use history::SearchHistory;
+use navigation::navigation_alt_key;
 
 /// Current input mode (`less` navigation vs search prompt).
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputState {
     Navigation,
-    SearchInput { direction: SearchDirection },
+    SearchInput {
+        direction: SearchDirection,
+    },
     Command,
+    ColonCommand,
+    FilterInput,
     PercentInput,
+    /// The current line is shown in full in an overlay
+    LinePeek,
+    /// The matches of the active search are listed in a panel
+    MatchList,
 }
 
 /// Direction for forward/backward search.
@@ -36,6 +60,14 @@ impl SearchDirection {
             SearchDirection::Backward => '?',
         }
     }
+
+    /// The opposite direction.
+    pub fn reverse(self) -> Self {
+        match self {
+            SearchDirection::Forward => SearchDirection::Backward,
+            SearchDirection::Backward => SearchDirection::Forward,
+        }
+    }
 }
 
 /// Direction for scroll actions emitted by the state machine.
@@ -54,8 +86,20 @@ pub enum InputAction {
     },
     PageUp,
     PageDown,
+    /// Scroll up half a window (`u`, `Ctrl-U`).
+    HalfPageUp,
+    /// Scroll down half a window (`d`, `Ctrl-D`).
+    HalfPageDown,
     GoToStart,
     GoToEnd,
+    /// Jump to the start of the previous section, e.g. gzip member (`{`).
+    PreviousSection,
+    /// Jump to the start of the next section (`}`).
+    NextSection,
+    /// Jump to the start of the current or previous log record (`[`).
+    PreviousRecord,
+    /// Jump to the start of the next log record (`]`).
+    NextRecord,
     Quit,
     /// User-requested interrupt (typically `Ctrl+C`).
     Interrupt,
@@ -71,6 +115,44 @@ pub enum InputAction {
     },
     NextMatch,
     PreviousMatch,
+    /// Jump the way `n` goes to the nearest line the active search does not match (ESC-n).
+    NextNonMatch,
+    /// Jump the way `N` goes to the nearest line the active search does not match (ESC-N).
+    PreviousNonMatch,
+    /// Search for the text of the current match (`*`).
+    SearchCurrentMatchWord,
+    /// Filter to the lines holding the text of the current match (`&*`).
+    FilterCurrentMatchWord,
+    /// Show file information (`=`, Ctrl-G).
+    ShowFileInfo,
+    /// Hide or restore the status line for screen sharing (`P`).
+    TogglePresentation,
+    /// Follow data appended to the file, or stop following it (`F`).
+    ToggleFollow,
+    /// Hide the active search's highlights, or show them again, keeping the search (ESC-u).
+    ToggleHighlights,
+    /// Make the next pattern kept with `:keep` the one `n`/`N` move between (ESC-p).
+    CyclePrimaryPattern,
+    /// Show the top line in full in a wrapped overlay (`o`).
+    OpenLinePeek,
+    ScrollLinePeek {
+        direction: ScrollDirection,
+        lines: u64,
+    },
+    CloseLinePeek,
+    /// List the matches of the active search in a panel (`M`).
+    OpenMatchList,
+    MoveMatchSelection {
+        direction: ScrollDirection,
+        lines: u64,
+    },
+    /// Move the match list selection by a panel's height.
+    PageMatchList(ScrollDirection),
+    /// Close the match list and show the selected match.
+    JumpToListedMatch,
+    CloseMatchList,
+    /// Answer yes to a question on the status line (`y`); any other key answers no.
+    ConfirmPrompt,
     Resize {
         width: u16,
         height: u16,
@@ -81,10 +163,34 @@ pub enum InputAction {
     ExecuteCommand {
         buffer: String,
     },
+    StartColonCommand,
+    UpdateColonCommandBuffer(String),
+    CancelColonCommand,
+    ExecuteColonCommand {
+        buffer: String,
+    },
+    StartFilter,
+    UpdateFilterBuffer(String),
+    CancelFilter,
+    /// Show only lines matching `pattern`; an empty pattern clears the filter.
+    ApplyFilter {
+        pattern: String,
+    },
     StartPercentInput,
     UpdatePercentBuffer(String),
     CancelPercentInput,
-    SubmitPercent(u8),
+    /// Jump to a position given in tenths of a percent (`%42.7` is 427), at most 1000.
+    SubmitPercent {
+        tenths: u16,
+    },
+    /// Digits typed ahead of a command in navigation mode; empty once the count is dropped.
+    UpdateCountBuffer(String),
+    /// `action` typed after a count it applies to: paging, half-paging, `NextMatch` or
+    /// `PreviousMatch`. A counted `Scroll` carries the count in its line count instead.
+    Counted {
+        count: u64,
+        action: Box<InputAction>,
+    },
     NoAction,
     InvalidInput,
 }
@@ -94,9 +200,17 @@ pub struct InputStateMachine {
     state: InputState,
     search_buffer: String,
     command_buffer: String,
+    colon_buffer: String,
+    filter_buffer: String,
     percent_buffer: String,
-    search_history: Vec<String>,
-    history_cursor: Option<usize>,
+    count_buffer: String,
+    search_history: SearchHistory,
+    /// Patterns that are searched for but never recorded in the history
+    history_exclude: Vec<RegexMatcher>,
+    /// User bindings consulted before the built-in ones in navigation mode
+    keymap: SharedKeymap,
+    /// Enter closed the search prompt and no other key has come since; see `handle_key_event`
+    search_submitted: bool,
 }
 
 impl InputStateMachine {
@@ -105,12 +219,34 @@ impl InputStateMachine {
             state: InputState::Navigation,
             search_buffer: String::new(),
             command_buffer: String::new(),
+            colon_buffer: String::new(),
+            filter_buffer: String::new(),
             percent_buffer: String::new(),
-            search_history: Vec::new(),
-            history_cursor: None,
+            count_buffer: String::new(),
+            search_history: SearchHistory::default(),
+            history_exclude: Vec::new(),
+            keymap: SharedKeymap::default(),
+            search_submitted: false,
         }
     }
 
+    /// Keep search patterns matching any of `exclude` out of the history
+    pub fn with_history_exclude(mut self, exclude: Vec<RegexMatcher>) -> Self {
+        self.history_exclude = exclude;
+        self
+    }
+
+    /// Resolve navigation keys through `keymap` first, falling back to the built-in bindings
+    pub fn with_keymap(mut self, keymap: SharedKeymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// Forget every recorded search pattern
+    pub fn clear_history(&mut self) {
+        self.search_history.clear();
+    }
+
     fn clear_percent_buffer(&mut self) {
         self.percent_buffer.clear();
     }
@@ -119,288 +255,49 @@ impl InputStateMachine {
         if key_event.kind != KeyEventKind::Press {
             return InputAction::NoAction;
         }
-
-        match (self.state, key_event.code, key_event.modifiers) {
-            (InputState::Navigation, KeyCode::Char('%'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                self.state = InputState::PercentInput;
-                self.clear_percent_buffer();
-                InputAction::StartPercentInput
-            }
-            (InputState::Navigation, KeyCode::Char('j'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::Scroll {
-                    direction: ScrollDirection::Down,
-                    lines: 1,
-                }
-            }
-            (InputState::Navigation, KeyCode::Down, _) => InputAction::Scroll {
-                direction: ScrollDirection::Down,
-                lines: 1,
-            },
-            (InputState::Navigation, KeyCode::Char('k'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::Scroll {
-                    direction: ScrollDirection::Up,
-                    lines: 1,
-                }
-            }
-            (InputState::Navigation, KeyCode::Up, _) => InputAction::Scroll {
-                direction: ScrollDirection::Up,
-                lines: 1,
-            },
-            (InputState::Navigation, KeyCode::Char(' '), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::PageDown
-            }
-            (InputState::Navigation, KeyCode::Char('f'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::PageDown
-            }
-            (InputState::Navigation, KeyCode::PageDown, _) => InputAction::PageDown,
-            (InputState::Navigation, KeyCode::Char('b'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::PageUp
-            }
-            (InputState::Navigation, KeyCode::PageUp, _) => InputAction::PageUp,
-            (InputState::Navigation, KeyCode::Char('g'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::GoToStart
-            }
-            (InputState::Navigation, KeyCode::Char('G'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::GoToEnd
-            }
-            (InputState::Navigation, KeyCode::Char('-'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                self.state = InputState::Command;
-                self.command_buffer.clear();
-                InputAction::StartCommand
-            }
-            (InputState::Navigation, KeyCode::Char('q'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::Quit
-            }
-            (InputState::Navigation, KeyCode::Char('c'), KeyModifiers::CONTROL) => {
-                InputAction::Interrupt
-            }
-            (InputState::Navigation, KeyCode::Char('n'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::NextMatch
-            }
-            (InputState::Navigation, KeyCode::Char('N'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                InputAction::PreviousMatch
-            }
-            (InputState::Navigation, KeyCode::Char('/'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                self.state = InputState::SearchInput {
-                    direction: SearchDirection::Forward,
-                };
-                self.search_buffer.clear();
-                self.history_cursor = None;
-                InputAction::StartSearch(SearchDirection::Forward)
-            }
-            (InputState::Navigation, KeyCode::Char('?'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                self.state = InputState::SearchInput {
-                    direction: SearchDirection::Backward,
-                };
-                self.search_buffer.clear();
-                self.history_cursor = None;
-                InputAction::StartSearch(SearchDirection::Backward)
-            }
-            (InputState::SearchInput { .. }, KeyCode::Char('c'), KeyModifiers::CONTROL) => {
-                self.state = InputState::Navigation;
-                self.search_buffer.clear();
-                self.history_cursor = None;
-                InputAction::CancelSearch
-            }
-            (InputState::SearchInput { direction }, KeyCode::Char(ch), modifiers)
-                if (ch.is_ascii_graphic() || ch == ' ')
-                    && !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                self.history_cursor = None;
-                self.search_buffer.push(ch);
-                InputAction::UpdateSearchBuffer {
-                    direction,
-                    buffer: self.search_buffer.clone(),
-                }
-            }
-            (InputState::SearchInput { direction }, KeyCode::Backspace, _) => {
-                self.history_cursor = None;
-                self.search_buffer.pop();
-                if self.search_buffer.is_empty() {
-                    self.state = InputState::Navigation;
-                    InputAction::CancelSearch
-                } else {
-                    InputAction::UpdateSearchBuffer {
-                        direction,
-                        buffer: self.search_buffer.clone(),
-                    }
-                }
-            }
-            (InputState::SearchInput { direction }, KeyCode::Enter, _) => {
-                let pattern = self.search_buffer.clone();
-                self.state = InputState::Navigation;
-                self.search_buffer.clear();
-                self.history_cursor = None;
-
-                if pattern.trim().is_empty() {
-                    InputAction::CancelSearch
-                } else {
-                    let trimmed = pattern.trim().to_string();
-                    self.record_history(&trimmed);
-                    InputAction::ExecuteSearch {
-                        pattern: trimmed,
-                        direction,
-                    }
-                }
-            }
-            (InputState::SearchInput { .. }, KeyCode::Esc, _) => {
-                self.state = InputState::Navigation;
-                self.search_buffer.clear();
-                self.history_cursor = None;
-                InputAction::CancelSearch
-            }
-            (InputState::SearchInput { direction }, KeyCode::Up, _) => {
-                if self.search_history.is_empty() {
-                    return InputAction::NoAction;
-                }
-
-                let next_index = match self.history_cursor {
-                    None => self.search_history.len().saturating_sub(1),
-                    Some(0) => 0,
-                    Some(idx) => idx.saturating_sub(1),
-                };
-
-                self.history_cursor = Some(next_index);
-                if let Some(entry) = self.search_history.get(next_index) {
-                    self.search_buffer = entry.clone();
-                }
-                InputAction::UpdateSearchBuffer {
-                    direction,
-                    buffer: self.search_buffer.clone(),
-                }
-            }
-            (InputState::SearchInput { direction }, KeyCode::Down, _) => {
-                if self.search_history.is_empty() {
-                    return InputAction::NoAction;
-                }
-
-                match self.history_cursor {
-                    None => InputAction::NoAction,
-                    Some(idx) if idx + 1 < self.search_history.len() => {
-                        let next_index = idx + 1;
-                        self.history_cursor = Some(next_index);
-                        if let Some(entry) = self.search_history.get(next_index) {
-                            self.search_buffer = entry.clone();
-                        }
-                        InputAction::UpdateSearchBuffer {
-                            direction,
-                            buffer: self.search_buffer.clone(),
-                        }
-                    }
-                    Some(_) => {
-                        self.history_cursor = None;
-                        self.search_buffer.clear();
-                        InputAction::UpdateSearchBuffer {
-                            direction,
-                            buffer: self.search_buffer.clone(),
-                        }
-                    }
-                }
-            }
-            (InputState::Command, KeyCode::Esc, _)
-            | (InputState::Command, KeyCode::Char('c'), KeyModifiers::CONTROL) => {
-                self.state = InputState::Navigation;
-                self.command_buffer.clear();
-                InputAction::CancelCommand
-            }
-            (InputState::Command, KeyCode::Enter, _) => {
-                let buffer = self.command_buffer.clone();
-                self.state = InputState::Navigation;
-                self.command_buffer.clear();
-                InputAction::ExecuteCommand { buffer }
-            }
-            (InputState::Command, KeyCode::Backspace, _) => {
-                if self.command_buffer.pop().is_some() {
-                    InputAction::UpdateCommandBuffer(self.command_buffer.clone())
-                } else {
-                    self.state = InputState::Navigation;
-                    InputAction::CancelCommand
-                }
-            }
-            (InputState::Command, KeyCode::Char(ch), modifiers)
-                if (ch.is_ascii_graphic() || ch == ' ')
-                    && !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                self.command_buffer.push(ch);
-                InputAction::UpdateCommandBuffer(self.command_buffer.clone())
-            }
-            (InputState::Command, _, _) => InputAction::InvalidInput,
-            (InputState::PercentInput, KeyCode::Char(ch @ '0'..='9'), modifiers)
-                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
-            {
-                if self.percent_buffer.len() < 3 {
-                    self.percent_buffer.push(ch);
-                }
-                InputAction::UpdatePercentBuffer(self.percent_buffer.clone())
-            }
-            (InputState::PercentInput, KeyCode::Backspace, _) => {
-                if self.percent_buffer.pop().is_some() {
-                    InputAction::UpdatePercentBuffer(self.percent_buffer.clone())
-                } else {
-                    self.state = InputState::Navigation;
-                    InputAction::CancelPercentInput
-                }
-            }
-            (InputState::PercentInput, KeyCode::Enter, _) => {
-                let buffer = self.percent_buffer.clone();
-                self.clear_percent_buffer();
-                self.state = InputState::Navigation;
-
-                if buffer.is_empty() {
-                    return InputAction::CancelPercentInput;
-                }
-
-                match buffer.parse::<u16>() {
-                    Ok(value) => InputAction::SubmitPercent(value.min(100) as u8),
-                    Err(_) => InputAction::InvalidInput,
-                }
-            }
-            (InputState::PercentInput, KeyCode::Esc, _) => {
-                self.clear_percent_buffer();
-                self.state = InputState::Navigation;
-                InputAction::CancelPercentInput
+        // A held Enter keeps arriving after it submitted the search; the repeats are not meant
+        // for the view behind the prompt
+        let after_search = std::mem::take(&mut self.search_submitted);
+        if after_search && key_event.code == KeyCode::Enter && self.state == InputState::Navigation
+        {
+            self.search_submitted = true;
+            return InputAction::NoAction;
+        }
+        if self.state == InputState::Navigation {
+            if let Some(action) = self.keymap.lookup(key_event.code, key_event.modifiers) {
+                return self.apply_count(action);
             }
-            (InputState::PercentInput, KeyCode::Char('c'), modifiers)
-                if modifiers.contains(KeyModifiers::CONTROL) =>
-            {
-                self.clear_percent_buffer();
-                self.state = InputState::Navigation;
-                InputAction::CancelPercentInput
+            if let Some(action) = self.count_key(key_event.code, key_event.modifiers) {
+                return action;
             }
-            (InputState::PercentInput, _, _) => InputAction::InvalidInput,
-            _ => {
-                self.clear_percent_buffer();
-                InputAction::InvalidInput
+            if let Some(action) = navigation_alt_key(key_event.code, key_event.modifiers) {
+                return self.apply_count(action);
             }
         }
+        // An unbound Alt+character chord is not its plain key: it neither types into a prompt
+        // nor cancels it, and does not fire the navigation binding of the letter. Other keys,
+        // such as Alt+arrows or Alt+Backspace, keep their plain meaning.
+        if matches!(key_event.code, KeyCode::Char(_))
+            && key_event.modifiers.contains(KeyModifiers::ALT)
+            && !key_event.modifiers.contains(KeyModifiers::CONTROL)
+        {
+            return InputAction::NoAction;
+        }
+
+        let (code, modifiers) = (key_event.code, key_event.modifiers);
+        match self.state {
+            InputState::Navigation => {
+                let action = self.navigation_key(code, modifiers);
+                self.apply_count(action)
+            }
+            InputState::SearchInput { direction } => self.search_key(direction, code, modifiers),
+            InputState::Command => self.command_key(code, modifiers),
+            InputState::ColonCommand => self.colon_key(code, modifiers),
+            InputState::FilterInput => self.filter_key(code, modifiers),
+            InputState::PercentInput => self.percent_key(code, modifiers),
+            InputState::LinePeek => self.peek_key(code, modifiers),
+            InputState::MatchList => self.match_list_key(code, modifiers),
+        }
     }
 
     pub fn get_search_buffer(&self) -> &str {
@@ -412,471 +309,155 @@ impl InputStateMachine {
     }
 
     fn record_history(&mut self, pattern: &str) {
-        if pattern.is_empty() {
-            return;
+        if !self.is_history_excluded(pattern) {
+            self.search_history.record(pattern);
         }
-        if self
-            .search_history
-            .last()
-            .is_some_and(|last| last == pattern)
-        {
-            return;
-        }
-        self.search_history.push(pattern.to_string());
     }
-}
 
-impl Default for InputStateMachine {
-    fn default() -> Self {
-        Self::new()
+    fn is_history_excluded(&self, pattern: &str) -> bool {
+        self.history_exclude
+            .iter()
+            .any(|matcher| matcher.is_match(pattern.as_bytes()).unwrap_or(false))
     }
 }
 
-/// Service responsible for producing high-level `InputAction`s from terminal events.
-pub struct InputService {
-    state_machine: InputStateMachine,
-    raw_input: RawInputCollector,
-}
-
-impl InputService {
-    pub fn new() -> Self {
-        Self {
-            state_machine: InputStateMachine::new(),
-            raw_input: RawInputCollector::new(),
-        }
-    }
-
-    pub fn poll_actions(&mut self, timeout: Option<Duration>) -> Result<Vec<InputAction>> {
-        let mut actions = Vec::new();
-
-        if let Some(raw_event) = self.raw_input.poll_event(timeout)? {
-            if let Some(action) = self.process_raw_event(raw_event) {
-                actions.push(action);
-            }
-
-            while let Some(extra_event) = self.raw_input.try_flush() {
-                if let Some(action) = self.process_raw_event(extra_event) {
-                    actions.push(action);
-                }
-            }
-        }
-
-        Ok(actions)
-    }
-
-    pub fn process_event(&mut self, event: Event) -> Vec<InputAction> {
-        let mut actions = Vec::new();
-        self.raw_input.process_event(event);
-        while let Some(raw_event) = self.raw_input.try_flush() {
-            if let Some(action) = self.process_raw_event(raw_event) {
-                actions.push(action);
-            }
-        }
-        actions
-    }
-
-    fn process_raw_event(&mut self, event: RawInputEvent) -> Option<InputAction> {
-        let action = match event {
-            RawInputEvent::Key(key_event) => self.state_machine.handle_key_event(key_event),
-            RawInputEvent::Resize { width, height } => InputAction::Resize { width, height },
-            RawInputEvent::Scroll { direction, lines } => InputAction::Scroll { direction, lines },
-        };
-
-        match action {
-            InputAction::NoAction | InputAction::InvalidInput => None,
-            _ => Some(action),
-        }
-    }
-}
-
-impl Default for InputService {
+impl Default for InputStateMachine {
     fn default() -> Self {
         Self::new()
     }
 }
 
-/// Spawn a blocking thread that polls for terminal events and forwards actions to the render loop.
-pub fn spawn_input_thread(
-    tx: UnboundedSender<InputAction>,
-    shutdown: Arc<AtomicBool>,
-    poll_interval: Duration,
-) -> thread::JoinHandle<()> {
-    thread::spawn(move || {
-        let mut service = InputService::new();
-        while !shutdown.load(Ordering::SeqCst) {
-            match service.poll_actions(Some(poll_interval)) {
-                Ok(actions) => {
-                    for action in actions {
-                        if tx.send(action).is_err() {
-                            return;
-                        }
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Input thread error: {}", err);
-                    break;
-                }
-            }
-        }
-    })
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
-    use std::time::Duration;
+    use crate::input::keymap::Keymap;
+    use ratatui::crossterm::event::{Event, KeyEvent};
 
-    fn key(code: KeyCode) -> Event {
+    pub(super) fn key(code: KeyCode) -> Event {
         Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
     }
 
-    fn mouse(kind: MouseEventKind) -> Event {
-        Event::Mouse(MouseEvent {
-            kind,
-            column: 0,
-            row: 0,
-            modifiers: KeyModifiers::NONE,
-        })
+    pub(super) fn ctrl_char(ch: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::CONTROL))
     }
 
-    #[test]
-    fn mouse_scrolls_are_coalesced_upstream() {
-        let mut service = InputService::new();
-        assert!(service
-            .process_event(mouse(MouseEventKind::ScrollDown))
-            .is_empty());
-        assert!(service
-            .process_event(mouse(MouseEventKind::ScrollDown))
-            .is_empty());
-        std::thread::sleep(Duration::from_millis(13));
-        let actions = service.process_event(Event::Resize(80, 24));
+    pub(super) fn type_and_enter(
+        service: &mut InputService,
+        prefix: char,
+        text: &str,
+    ) -> Vec<InputAction> {
+        service.process_event(key(KeyCode::Char(prefix)));
+        for ch in text.chars() {
+            service.process_event(key(KeyCode::Char(ch)));
+        }
+        service.process_event(key(KeyCode::Enter))
+    }
 
-        assert_eq!(
-            actions,
-            vec![
-                InputAction::Scroll {
-                    direction: ScrollDirection::Down,
-                    lines: 6,
-                },
-                InputAction::Resize {
-                    width: 80,
-                    height: 24,
-                },
-            ]
-        );
+    pub(super) fn recall_latest(service: &mut InputService) -> Vec<InputAction> {
+        service.process_event(key(KeyCode::Char('/')));
+        let recalled = service.process_event(key(KeyCode::Up));
+        service.process_event(key(KeyCode::Esc));
+        recalled
     }
 
     #[test]
-    fn keyboard_events_pass_through_state_machine() {
-        let mut service = InputService::new();
-        let down = service.process_event(key(KeyCode::Char('j')));
-        let up = service.process_event(key(KeyCode::Char('k')));
+    fn user_bindings_beat_defaults_in_navigation_only() {
+        let mut keymap = Keymap::new();
+        keymap.bind("j", InputAction::PageDown).unwrap();
+        keymap.bind("ctrl-n", InputAction::NextMatch).unwrap();
+        let mut service = InputService::new().with_keymap(SharedKeymap::new(keymap));
 
         assert_eq!(
-            down,
-            vec![InputAction::Scroll {
-                direction: ScrollDirection::Down,
-                lines: 1,
-            }]
+            service.process_event(key(KeyCode::Char('j'))),
+            vec![InputAction::PageDown]
         );
         assert_eq!(
-            up,
-            vec![InputAction::Scroll {
-                direction: ScrollDirection::Up,
-                lines: 1,
-            }]
+            service.process_event(ctrl_char('n')),
+            vec![InputAction::NextMatch]
         );
-    }
-
-    #[test]
-    fn poll_actions_flushes_pending_events() {
-        let mut service = InputService::new();
-        service
-            .raw_input
-            .process_event(mouse(MouseEventKind::ScrollUp));
-        std::thread::sleep(Duration::from_millis(13));
-        let actions = service
-            .poll_actions(Some(Duration::from_millis(1)))
-            .unwrap();
-
+        // Unbound keys keep their built-in meaning
         assert_eq!(
-            actions,
+            service.process_event(key(KeyCode::Char('k'))),
             vec![InputAction::Scroll {
                 direction: ScrollDirection::Up,
-                lines: 3,
+                lines: 1,
             }]
         );
-    }
-
-    #[test]
-    fn mixed_events_preserve_order() {
-        let mut service = InputService::new();
-        assert!(service
-            .process_event(mouse(MouseEventKind::ScrollUp))
-            .is_empty());
-        std::thread::sleep(Duration::from_millis(13));
-        let actions = service.process_event(key(KeyCode::Char('j')));
-
-        assert_eq!(
-            actions,
-            vec![
-                InputAction::Scroll {
-                    direction: ScrollDirection::Up,
-                    lines: 3,
-                },
-                InputAction::Scroll {
-                    direction: ScrollDirection::Down,
-                    lines: 1,
-                },
-            ]
-        );
-    }
-
-    #[test]
-    fn percent_jump_emits_action() {
-        let mut service = InputService::new();
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('%'))),
-            vec![InputAction::StartPercentInput]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('5'))),
-            vec![InputAction::UpdatePercentBuffer("5".to_string())]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('0'))),
-            vec![InputAction::UpdatePercentBuffer("50".to_string())]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Enter)),
-            vec![InputAction::SubmitPercent(50)]
-        );
-    }
-
-    #[test]
-    fn percent_jump_backspace_cancels_when_empty() {
-        let mut service = InputService::new();
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('%'))),
-            vec![InputAction::StartPercentInput]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('1'))),
-            vec![InputAction::UpdatePercentBuffer("1".to_string())]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Backspace)),
-            vec![InputAction::UpdatePercentBuffer(String::new())]
-        );
-
+        // Inside a prompt `j` is text
+        service.process_event(key(KeyCode::Char('/')));
         assert_eq!(
-            service.process_event(key(KeyCode::Backspace)),
-            vec![InputAction::CancelPercentInput]
+            service.process_event(key(KeyCode::Char('j'))),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "j".to_string(),
+            }]
         );
     }
 
-    fn ctrl_char(ch: char) -> Event {
-        Event::Key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::CONTROL))
-    }
-
     #[test]
-    fn percent_jump_ctrl_c_cancels() {
-        let mut service = InputService::new();
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('%'))),
-            vec![InputAction::StartPercentInput]
-        );
+    fn alt_chords_are_not_read_as_escape_or_their_plain_key() {
+        let alt = |ch| Event::Key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::ALT));
+        let mut keymap = Keymap::new();
+        keymap.bind("esc-)", InputAction::NextSection).unwrap();
+        let mut service = InputService::new().with_keymap(SharedKeymap::new(keymap));
 
         assert_eq!(
-            service.process_event(key(KeyCode::Char('2'))),
-            vec![InputAction::UpdatePercentBuffer("2".to_string())]
+            service.process_event(alt(')')),
+            vec![InputAction::NextSection]
         );
-
+        // Unbound: not `k` (line up)
+        assert_eq!(service.process_event(alt('k')), vec![]);
+        // ESC-u is built in, and not `u` (half page up)
         assert_eq!(
-            service.process_event(ctrl_char('c')),
-            vec![InputAction::CancelPercentInput]
+            service.process_event(alt('u')),
+            vec![InputAction::ToggleHighlights]
         );
-    }
-
-    #[test]
-    fn percent_jump_escape_cancels() {
-        let mut service = InputService::new();
-
         assert_eq!(
-            service.process_event(key(KeyCode::Char('%'))),
-            vec![InputAction::StartPercentInput]
+            service.process_event(alt('p')),
+            vec![InputAction::CyclePrimaryPattern]
         );
-
         assert_eq!(
-            service.process_event(key(KeyCode::Esc)),
-            vec![InputAction::CancelPercentInput]
+            service.process_event(alt('n')),
+            vec![InputAction::NextNonMatch]
         );
-    }
-
-    #[test]
-    fn ctrl_c_interrupts_navigation() {
-        let mut service = InputService::new();
         assert_eq!(
-            service.process_event(ctrl_char('c')),
-            vec![InputAction::Interrupt]
+            service.process_event(Event::Key(KeyEvent::new(
+                KeyCode::Char('N'),
+                KeyModifiers::ALT | KeyModifiers::SHIFT
+            ))),
+            vec![InputAction::PreviousNonMatch]
         );
-    }
-
-    #[test]
-    fn search_history_navigation_allows_recall() {
-        let mut service = InputService::new();
-
-        // First search: "/f"
-        service.process_event(key(KeyCode::Char('/')));
-        service.process_event(key(KeyCode::Char('f')));
-        service.process_event(key(KeyCode::Enter));
 
-        // Second search: "/bar"
+        // Mid-typing, ESC-u leaves the prompt and its text alone; ESC by itself still cancels
         service.process_event(key(KeyCode::Char('/')));
-        service.process_event(key(KeyCode::Char('b')));
-        service.process_event(key(KeyCode::Char('a')));
-        service.process_event(key(KeyCode::Char('r')));
-        service.process_event(key(KeyCode::Enter));
-
-        // Start a new search session to recall history
+        service.process_event(key(KeyCode::Char('x')));
+        assert_eq!(service.process_event(alt('u')), vec![]);
         assert_eq!(
-            service.process_event(key(KeyCode::Char('/'))),
-            vec![InputAction::StartSearch(SearchDirection::Forward)]
-        );
-
-        // Up -> recalls most recent entry "bar"
-        assert_eq!(
-            service.process_event(key(KeyCode::Up)),
+            service.process_event(key(KeyCode::Char('y'))),
             vec![InputAction::UpdateSearchBuffer {
                 direction: SearchDirection::Forward,
-                buffer: "bar".to_string(),
+                buffer: "xy".to_string(),
             }]
         );
-
-        // Another Up -> older entry "f"
+        // Alt with a key other than a character still edits the prompt
+        let alt_key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::ALT));
         assert_eq!(
-            service.process_event(key(KeyCode::Up)),
+            service.process_event(alt_key(KeyCode::Backspace)),
             vec![InputAction::UpdateSearchBuffer {
                 direction: SearchDirection::Forward,
-                buffer: "f".to_string(),
+                buffer: "x".to_string(),
             }]
         );
-
-        // Down -> returns to "bar"
-        assert_eq!(
-            service.process_event(key(KeyCode::Down)),
-            vec![InputAction::UpdateSearchBuffer {
-                direction: SearchDirection::Forward,
-                buffer: "bar".to_string(),
-            }]
-        );
-
-        // Down past latest entry -> clears buffer
-        assert_eq!(
-            service.process_event(key(KeyCode::Down)),
-            vec![InputAction::UpdateSearchBuffer {
-                direction: SearchDirection::Forward,
-                buffer: String::new(),
-            }]
-        );
-
-        // Typing after recall resets the cursor
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('z'))),
-            vec![InputAction::UpdateSearchBuffer {
-                direction: SearchDirection::Forward,
-                buffer: "z".to_string(),
-            }]
-        );
-
-        // Up again recalls the latest history entry
-        assert_eq!(
-            service.process_event(key(KeyCode::Up)),
-            vec![InputAction::UpdateSearchBuffer {
-                direction: SearchDirection::Forward,
-                buffer: "bar".to_string(),
-            }]
-        );
-    }
-
-    #[test]
-    fn command_mode_updates_buffer_and_executes() {
-        let mut service = InputService::new();
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('-'))),
-            vec![InputAction::StartCommand]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('i'))),
-            vec![InputAction::UpdateCommandBuffer("i".to_string())]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Backspace)),
-            vec![InputAction::UpdateCommandBuffer(String::new())]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('r'))),
-            vec![InputAction::UpdateCommandBuffer("r".to_string())]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Enter)),
-            vec![InputAction::ExecuteCommand {
-                buffer: "r".to_string(),
-            }]
-        );
-    }
-
-    #[test]
-    fn command_mode_cancel_clears_buffer() {
-        let mut service = InputService::new();
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('-'))),
-            vec![InputAction::StartCommand]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('w'))),
-            vec![InputAction::UpdateCommandBuffer("w".to_string())]
-        );
-
         assert_eq!(
             service.process_event(key(KeyCode::Esc)),
-            vec![InputAction::CancelCommand]
-        );
-    }
-
-    #[test]
-    fn command_mode_backspace_when_empty_exits() {
-        let mut service = InputService::new();
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('-'))),
-            vec![InputAction::StartCommand]
+            vec![InputAction::CancelSearch]
         );
-
         assert_eq!(
-            service.process_event(key(KeyCode::Backspace)),
-            vec![InputAction::CancelCommand]
-        );
-
-        assert_eq!(
-            service.process_event(key(KeyCode::Char('-'))),
-            vec![InputAction::StartCommand]
+            service.process_event(alt_key(KeyCode::Down)),
+            vec![InputAction::Scroll {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            }]
         );
     }
 }
@@ -0,0 +1,214 @@
+//! Numeric prefixes for navigation commands, as in less: `10j`, `5 space`, `3n`, `50%`.
+
+use super::{InputAction, InputState, InputStateMachine};
+use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+
+/// Longest count accepted; further digits are ignored
+const MAX_COUNT_DIGITS: usize = 9;
+
+impl InputStateMachine {
+    /// Add a digit to the pending count, or `None` if the key is not part of one
+    ///
+    /// A count cannot start with `0`.
+    pub(super) fn count_key(
+        &mut self,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+    ) -> Option<InputAction> {
+        let KeyCode::Char(digit @ '0'..='9') = code else {
+            return None;
+        };
+        if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+            || (digit == '0' && self.count_buffer.is_empty())
+        {
+            return None;
+        }
+        if self.count_buffer.len() < MAX_COUNT_DIGITS {
+            self.count_buffer.push(digit);
+        }
+        Some(InputAction::UpdateCountBuffer(self.count_buffer.clone()))
+    }
+
+    /// Apply the pending count, if any, to the action of the key that ended it
+    ///
+    /// Scrolling moves `count` times as many lines, paging and `n`/`N` are repeated `count`
+    /// times, and `%` jumps to `count` percent. Other keys drop the count; a key with no action
+    /// (e.g. `Esc`) only clears it from the status line.
+    pub(super) fn apply_count(&mut self, action: InputAction) -> InputAction {
+        if self.count_buffer.is_empty() {
+            return action;
+        }
+        let count: u64 = self.count_buffer.parse().unwrap_or(1);
+        self.count_buffer.clear();
+        match action {
+            InputAction::Scroll { direction, lines } => InputAction::Scroll {
+                direction,
+                lines: lines.saturating_mul(count),
+            },
+            InputAction::PageUp
+            | InputAction::PageDown
+            | InputAction::HalfPageUp
+            | InputAction::HalfPageDown
+            | InputAction::NextMatch
+            | InputAction::PreviousMatch => InputAction::Counted {
+                count,
+                action: Box::new(action),
+            },
+            InputAction::StartPercentInput => {
+                self.state = InputState::Navigation;
+                InputAction::SubmitPercent {
+                    tenths: count.min(100) as u16 * 10,
+                }
+            }
+            InputAction::InvalidInput | InputAction::NoAction => {
+                InputAction::UpdateCountBuffer(String::new())
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keymap::Keymap;
+    use crate::input::service::tests::key;
+    use crate::input::{InputService, ScrollDirection, SharedKeymap};
+
+    fn type_count(service: &mut InputService, digits: &str) -> Vec<InputAction> {
+        digits
+            .chars()
+            .flat_map(|digit| service.process_event(key(KeyCode::Char(digit))))
+            .collect()
+    }
+
+    #[test]
+    fn digits_build_a_count_for_the_next_motion() {
+        let mut service = InputService::new();
+        assert_eq!(
+            type_count(&mut service, "10"),
+            vec![
+                InputAction::UpdateCountBuffer("1".to_string()),
+                InputAction::UpdateCountBuffer("10".to_string()),
+            ]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('j'))),
+            vec![InputAction::Scroll {
+                direction: ScrollDirection::Down,
+                lines: 10,
+            }]
+        );
+        // Consumed: the next motion moves once
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('k'))),
+            vec![InputAction::Scroll {
+                direction: ScrollDirection::Up,
+                lines: 1,
+            }]
+        );
+
+        type_count(&mut service, "3");
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('n'))),
+            vec![InputAction::Counted {
+                count: 3,
+                action: Box::new(InputAction::NextMatch),
+            }]
+        );
+        type_count(&mut service, "5");
+        assert_eq!(
+            service.process_event(key(KeyCode::Char(' '))),
+            vec![InputAction::Counted {
+                count: 5,
+                action: Box::new(InputAction::PageDown),
+            }]
+        );
+
+        // Other commands drop the count
+        type_count(&mut service, "7");
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('g'))),
+            vec![InputAction::GoToStart]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('j'))),
+            vec![InputAction::Scroll {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn escape_and_invalid_keys_clear_the_count() {
+        let mut service = InputService::new();
+        for clearing in [KeyCode::Esc, KeyCode::Char('Z')] {
+            type_count(&mut service, "42");
+            assert_eq!(
+                service.process_event(key(clearing)),
+                vec![InputAction::UpdateCountBuffer(String::new())]
+            );
+            assert_eq!(
+                service.process_event(key(KeyCode::Char('j'))),
+                vec![InputAction::Scroll {
+                    direction: ScrollDirection::Down,
+                    lines: 1,
+                }]
+            );
+        }
+        // A leading zero starts nothing
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('0'))),
+            vec![InputAction::InvalidInput]
+        );
+    }
+
+    #[test]
+    fn count_then_percent_jumps_and_percent_prompt_still_takes_digits() {
+        let mut service = InputService::new();
+        type_count(&mut service, "50");
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('%'))),
+            vec![InputAction::SubmitPercent { tenths: 500 }]
+        );
+        // Back in navigation: the next digits are a count again
+        assert_eq!(
+            type_count(&mut service, "2"),
+            vec![InputAction::UpdateCountBuffer("2".to_string())]
+        );
+        service.process_event(key(KeyCode::Esc));
+
+        // Without a count `%` opens the prompt, where digits are the percentage
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('%'))),
+            vec![InputAction::StartPercentInput]
+        );
+        assert_eq!(
+            type_count(&mut service, "25"),
+            vec![
+                InputAction::UpdatePercentBuffer("2".to_string()),
+                InputAction::UpdatePercentBuffer("25".to_string()),
+            ]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::SubmitPercent { tenths: 250 }]
+        );
+    }
+
+    #[test]
+    fn counts_apply_to_user_bindings() {
+        let mut keymap = Keymap::new();
+        keymap.bind("ctrl-v", InputAction::PageDown).unwrap();
+        let mut service = InputService::new().with_keymap(SharedKeymap::new(keymap));
+        type_count(&mut service, "2");
+        assert_eq!(
+            service.process_event(crate::input::service::tests::ctrl_char('v')),
+            vec![InputAction::Counted {
+                count: 2,
+                action: Box::new(InputAction::PageDown),
+            }]
+        );
+    }
+}
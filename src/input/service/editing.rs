@@ -0,0 +1,55 @@
+//! Editing the text typed at a prompt.
+//!
+//! Every text prompt takes the same keys the same way: any printable character is typed, and
+//! Backspace removes the last grapheme cluster, so an accented letter built from a combining mark
+//! or an emoji with a skin tone modifier goes in one keystroke rather than leaving half of itself
+//! behind.
+
+use ratatui::crossterm::event::KeyModifiers;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Whether `ch` typed with `modifiers` is text for the prompt buffer
+pub(super) fn is_prompt_text(ch: char, modifiers: KeyModifiers) -> bool {
+    !ch.is_control() && !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+/// Remove the last grapheme cluster of `buffer`; `false` if it was already empty
+pub(super) fn pop_grapheme(buffer: &mut String) -> bool {
+    match buffer.grapheme_indices(true).next_back() {
+        Some((start, _)) => {
+            buffer.truncate(start);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backspace_removes_whole_graphemes() {
+        let mut buffer = "ae\u{301}\u{1F44D}\u{1F3FD}".to_string();
+        assert!(pop_grapheme(&mut buffer));
+        assert_eq!(buffer, "ae\u{301}");
+        assert!(pop_grapheme(&mut buffer));
+        assert_eq!(buffer, "a");
+        assert!(pop_grapheme(&mut buffer));
+        assert!(!pop_grapheme(&mut buffer));
+        assert_eq!(buffer, "");
+    }
+
+    #[test]
+    fn printable_characters_are_text() {
+        assert!(is_prompt_text('a', KeyModifiers::NONE));
+        assert!(is_prompt_text('é', KeyModifiers::SHIFT));
+        assert!(is_prompt_text('\u{301}', KeyModifiers::NONE));
+        assert!(is_prompt_text('\u{1F3FD}', KeyModifiers::NONE));
+        assert!(!is_prompt_text('\t', KeyModifiers::NONE));
+        assert!(!is_prompt_text(
+            'x',
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        ));
+    }
+}
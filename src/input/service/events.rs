@@ -0,0 +1,239 @@
+//! Turning terminal events into actions on the input thread.
+
+use super::{InputAction, InputStateMachine};
+use crate::error::Result;
+use crate::input::keymap::SharedKeymap;
+use crate::input::raw::{PrecisionScroll, RawInputCollector, RawInputEvent};
+use grep_regex::RegexMatcher;
+use ratatui::crossterm::event::Event;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Service responsible for producing high-level `InputAction`s from terminal events.
+pub struct InputService {
+    pub(super) state_machine: InputStateMachine,
+    raw_input: RawInputCollector,
+}
+
+impl InputService {
+    pub fn new() -> Self {
+        Self {
+            state_machine: InputStateMachine::new(),
+            raw_input: RawInputCollector::new(),
+        }
+    }
+
+    /// Keep search patterns matching any of `exclude` out of the history
+    pub fn with_history_exclude(mut self, exclude: Vec<RegexMatcher>) -> Self {
+        self.state_machine = self.state_machine.with_history_exclude(exclude);
+        self
+    }
+
+    /// Resolve navigation keys through `keymap` first, see `InputStateMachine::with_keymap`
+    pub fn with_keymap(mut self, keymap: SharedKeymap) -> Self {
+        self.state_machine = self.state_machine.with_keymap(keymap);
+        self
+    }
+
+    /// Scroll one line per wheel notch while `precision` is on
+    pub fn with_precision_scroll(mut self, precision: PrecisionScroll) -> Self {
+        self.raw_input = self.raw_input.with_precision_scroll(precision);
+        self
+    }
+
+    pub fn poll_actions(&mut self, timeout: Option<Duration>) -> Result<Vec<InputAction>> {
+        let mut actions = Vec::new();
+
+        if let Some(raw_event) = self.raw_input.poll_event(timeout)? {
+            if let Some(action) = self.process_raw_event(raw_event) {
+                actions.push(action);
+            }
+
+            while let Some(extra_event) = self.raw_input.try_flush() {
+                if let Some(action) = self.process_raw_event(extra_event) {
+                    actions.push(action);
+                }
+            }
+        }
+
+        Ok(actions)
+    }
+
+    /// Process one complete event; a lone ESC here is final rather than the start of a chord
+    pub fn process_event(&mut self, event: Event) -> Vec<InputAction> {
+        let mut actions = Vec::new();
+        self.raw_input.process_event(event);
+        self.raw_input.release_escape();
+        while let Some(raw_event) = self.raw_input.try_flush() {
+            if let Some(action) = self.process_raw_event(raw_event) {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    fn process_raw_event(&mut self, event: RawInputEvent) -> Option<InputAction> {
+        let action = match event {
+            RawInputEvent::Key(key_event) => self.state_machine.handle_key_event(key_event),
+            RawInputEvent::Resize { width, height } => InputAction::Resize { width, height },
+            RawInputEvent::Scroll { direction, lines } => InputAction::Scroll { direction, lines },
+        };
+
+        // Keys that do nothing still go through, so the render loop can answer them
+        match action {
+            InputAction::NoAction => None,
+            _ => Some(action),
+        }
+    }
+}
+
+impl Default for InputService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Spawn a blocking thread that polls for terminal events and forwards actions to the render loop.
+pub fn spawn_input_thread(
+    tx: UnboundedSender<InputAction>,
+    shutdown: Arc<AtomicBool>,
+    poll_interval: Duration,
+    history_exclude: Vec<RegexMatcher>,
+    keymap: SharedKeymap,
+    precision: PrecisionScroll,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut service = InputService::new()
+            .with_history_exclude(history_exclude)
+            .with_keymap(keymap)
+            .with_precision_scroll(precision);
+        while !shutdown.load(Ordering::SeqCst) {
+            match service.poll_actions(Some(poll_interval)) {
+                Ok(actions) => {
+                    for action in actions {
+                        if tx.send(action).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Input thread error: {}", err);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::service::tests::key;
+    use crate::input::ScrollDirection;
+    use ratatui::crossterm::event::{KeyCode, KeyModifiers, MouseEvent, MouseEventKind};
+
+    fn mouse(kind: MouseEventKind) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn mouse_scrolls_are_coalesced_upstream() {
+        let mut service = InputService::new();
+        assert!(service
+            .process_event(mouse(MouseEventKind::ScrollDown))
+            .is_empty());
+        assert!(service
+            .process_event(mouse(MouseEventKind::ScrollDown))
+            .is_empty());
+        std::thread::sleep(Duration::from_millis(13));
+        let actions = service.process_event(Event::Resize(80, 24));
+
+        assert_eq!(
+            actions,
+            vec![
+                InputAction::Scroll {
+                    direction: ScrollDirection::Down,
+                    lines: 6,
+                },
+                InputAction::Resize {
+                    width: 80,
+                    height: 24,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn keyboard_events_pass_through_state_machine() {
+        let mut service = InputService::new();
+        let down = service.process_event(key(KeyCode::Char('j')));
+        let up = service.process_event(key(KeyCode::Char('k')));
+
+        assert_eq!(
+            down,
+            vec![InputAction::Scroll {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            }]
+        );
+        assert_eq!(
+            up,
+            vec![InputAction::Scroll {
+                direction: ScrollDirection::Up,
+                lines: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn poll_actions_flushes_pending_events() {
+        let mut service = InputService::new();
+        service
+            .raw_input
+            .process_event(mouse(MouseEventKind::ScrollUp));
+        std::thread::sleep(Duration::from_millis(13));
+        let actions = service
+            .poll_actions(Some(Duration::from_millis(1)))
+            .unwrap();
+
+        assert_eq!(
+            actions,
+            vec![InputAction::Scroll {
+                direction: ScrollDirection::Up,
+                lines: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn mixed_events_preserve_order() {
+        let mut service = InputService::new();
+        assert!(service
+            .process_event(mouse(MouseEventKind::ScrollUp))
+            .is_empty());
+        std::thread::sleep(Duration::from_millis(13));
+        let actions = service.process_event(key(KeyCode::Char('j')));
+
+        assert_eq!(
+            actions,
+            vec![
+                InputAction::Scroll {
+                    direction: ScrollDirection::Up,
+                    lines: 3,
+                },
+                InputAction::Scroll {
+                    direction: ScrollDirection::Down,
+                    lines: 1,
+                },
+            ]
+        );
+    }
+}
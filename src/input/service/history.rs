@@ -0,0 +1,212 @@
+//! Browsing the search history with Up and Down.
+//!
+//! The history is a list of patterns, newest last. Up starts browsing at the newest entry and
+//! steps to older ones; Down steps back towards the newest. The text typed before browsing
+//! began is put aside, and Down past the newest entry brings it back rather than clearing the
+//! prompt. Typing into a recalled entry makes it the text being typed and ends browsing.
+//! Searching for a recalled entry keeps the text put aside, so Down at the next prompt brings
+//! it back.
+
+/// Search patterns, newest last, and where Up and Down have got to
+#[derive(Debug, Default)]
+pub(super) struct SearchHistory {
+    entries: Vec<String>,
+    /// Entry shown at the prompt while browsing
+    cursor: Option<usize>,
+    /// Text typed before browsing began
+    stash: String,
+}
+
+impl SearchHistory {
+    /// Remember `pattern` as the newest entry unless it already is
+    pub(super) fn record(&mut self, pattern: &str) {
+        if pattern.is_empty() || self.latest() == Some(pattern) {
+            return;
+        }
+        self.entries.push(pattern.to_string());
+    }
+
+    pub(super) fn latest(&self) -> Option<&str> {
+        self.entries.last().map(String::as_str)
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.cursor = None;
+        self.stash.clear();
+    }
+
+    /// A prompt opened or closed without a search: browsing starts over at the next Up
+    pub(super) fn reset(&mut self) {
+        self.cursor = None;
+    }
+
+    /// The prompt text was edited: it is what is being typed now, not a recalled entry
+    pub(super) fn edited(&mut self) {
+        self.cursor = None;
+        self.stash.clear();
+    }
+
+    /// The prompt text was searched for. A recalled entry leaves the text put aside for the
+    /// next prompt; searching for typed text uses it up.
+    pub(super) fn executed(&mut self) {
+        if self.cursor.take().is_none() {
+            self.stash.clear();
+        }
+    }
+
+    /// Up: the entry before the one shown, or the newest when `buffer` is being typed
+    pub(super) fn older(&mut self, buffer: &str) -> Option<&str> {
+        let index = match self.cursor {
+            Some(index) => index.saturating_sub(1),
+            None => {
+                let newest = self.entries.len().checked_sub(1)?;
+                // An empty prompt keeps text put aside by an earlier one
+                if !buffer.is_empty() {
+                    self.stash = buffer.to_string();
+                }
+                newest
+            }
+        };
+        self.cursor = Some(index);
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Down: the entry after the one shown, then the text put aside. `None` when there is
+    /// nothing to bring back.
+    pub(super) fn newer(&mut self) -> Option<String> {
+        match self.cursor {
+            Some(index) if index + 1 < self.entries.len() => {
+                self.cursor = Some(index + 1);
+                Some(self.entries[index + 1].clone())
+            }
+            Some(_) => {
+                self.cursor = None;
+                Some(std::mem::take(&mut self.stash))
+            }
+            None if !self.stash.is_empty() => Some(std::mem::take(&mut self.stash)),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> SearchHistory {
+        let mut history = SearchHistory::default();
+        for entry in entries {
+            history.record(entry);
+        }
+        history
+    }
+
+    #[test]
+    fn up_and_down_step_through_entries_and_stop_at_the_oldest() {
+        let mut history = history(&["a", "b", "c"]);
+        assert_eq!(history.older(""), Some("c"));
+        assert_eq!(history.older("c"), Some("b"));
+        assert_eq!(history.older("b"), Some("a"));
+        assert_eq!(history.older("a"), Some("a"));
+        assert_eq!(history.newer().as_deref(), Some("b"));
+        assert_eq!(history.newer().as_deref(), Some("c"));
+        // Past the newest: the prompt was empty before browsing
+        assert_eq!(history.newer().as_deref(), Some(""));
+        // Not browsing and nothing put aside
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn down_past_the_newest_restores_the_typed_text() {
+        let mut history = history(&["a", "b"]);
+        assert_eq!(history.older("draft"), Some("b"));
+        assert_eq!(history.older("b"), Some("a"));
+        assert_eq!(history.newer().as_deref(), Some("b"));
+        assert_eq!(history.newer().as_deref(), Some("draft"));
+
+        // Browsing again puts the restored text aside again
+        assert_eq!(history.older("draft"), Some("b"));
+        assert_eq!(history.newer().as_deref(), Some("draft"));
+    }
+
+    #[test]
+    fn editing_a_recalled_entry_makes_it_the_typed_text() {
+        let mut history = history(&["a", "b"]);
+        history.older("draft");
+        history.edited();
+        assert_eq!(history.newer(), None);
+
+        // Up from the edited entry starts at the newest and puts the edit aside
+        assert_eq!(history.older("bx"), Some("b"));
+        assert_eq!(history.newer().as_deref(), Some("bx"));
+    }
+
+    #[test]
+    fn searching_for_a_recalled_entry_keeps_the_typed_text() {
+        let mut history = history(&["a", "b"]);
+        history.older("draft");
+        history.older("b");
+        history.record("a");
+        history.executed();
+        // The recalled entry is now the newest, once
+        history.record("a");
+        assert_eq!(history.entries, ["a", "b", "a"]);
+
+        // The next prompt brings the text back with Down, or after browsing
+        history.reset();
+        assert_eq!(history.older(""), Some("a"));
+        assert_eq!(history.newer().as_deref(), Some("draft"));
+        history.older("draft");
+        history.reset();
+        assert_eq!(history.newer().as_deref(), Some("draft"));
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn searching_for_typed_text_uses_up_what_was_put_aside() {
+        let mut history = history(&["a"]);
+        history.older("draft");
+        history.edited();
+        history.record("typed");
+        history.executed();
+        history.reset();
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn empty_history_has_nothing_to_recall() {
+        let mut history = SearchHistory::default();
+        assert_eq!(history.older("draft"), None);
+        assert_eq!(history.newer(), None);
+        history.record("");
+        assert_eq!(history.latest(), None);
+    }
+
+    #[test]
+    fn deep_history_saturates_at_the_oldest_and_walks_back() {
+        let entries: Vec<String> = (0..5000).map(|i| format!("p{i}")).collect();
+        let mut history = SearchHistory::default();
+        for entry in &entries {
+            history.record(entry);
+        }
+        for _ in 0..6000 {
+            history.older("draft");
+        }
+        assert_eq!(history.older("p0"), Some("p0"));
+        for _ in 0..4999 {
+            history.newer();
+        }
+        assert_eq!(history.newer().as_deref(), Some("draft"));
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn clear_forgets_entries_and_typed_text() {
+        let mut history = history(&["a"]);
+        history.older("draft");
+        history.clear();
+        assert_eq!(history.newer(), None);
+        assert_eq!(history.older(""), None);
+    }
+}
@@ -0,0 +1,452 @@
+//! Keys that browse the file.
+
+use super::{InputAction, InputState, InputStateMachine, ScrollDirection, SearchDirection};
+use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+
+/// Built-in Alt chords (ESC followed by the key, as `less` spells them) while browsing the file
+pub(super) fn navigation_alt_key(code: KeyCode, modifiers: KeyModifiers) -> Option<InputAction> {
+    match (code, modifiers) {
+        (KeyCode::Char('u'), KeyModifiers::ALT) => Some(InputAction::ToggleHighlights),
+        (KeyCode::Char('p'), KeyModifiers::ALT) => Some(InputAction::CyclePrimaryPattern),
+        (KeyCode::Char('n'), KeyModifiers::ALT) => Some(InputAction::NextNonMatch),
+        // Some terminals report the shift as well as the uppercase letter
+        (KeyCode::Char('N'), modifiers) if modifiers - KeyModifiers::SHIFT == KeyModifiers::ALT => {
+            Some(InputAction::PreviousNonMatch)
+        }
+        _ => None,
+    }
+}
+
+impl InputStateMachine {
+    /// Keys pressed while browsing the file
+    pub(super) fn navigation_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> InputAction {
+        match (code, modifiers) {
+            (KeyCode::Char('%'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::PercentInput;
+                self.clear_percent_buffer();
+                InputAction::StartPercentInput
+            }
+            (KeyCode::Char('j'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::Scroll {
+                    direction: ScrollDirection::Down,
+                    lines: 1,
+                }
+            }
+            (KeyCode::Down, _) => InputAction::Scroll {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            },
+            (KeyCode::Char('k'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::Scroll {
+                    direction: ScrollDirection::Up,
+                    lines: 1,
+                }
+            }
+            (KeyCode::Up, _) => InputAction::Scroll {
+                direction: ScrollDirection::Up,
+                lines: 1,
+            },
+            (KeyCode::Char(' '), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::PageDown
+            }
+            (KeyCode::Char('f'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::PageDown
+            }
+            (KeyCode::PageDown, _) => InputAction::PageDown,
+            (KeyCode::Char('b'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::PageUp
+            }
+            (KeyCode::PageUp, _) => InputAction::PageUp,
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) => InputAction::HalfPageDown,
+            (KeyCode::Char('d'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::HalfPageDown
+            }
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => InputAction::HalfPageUp,
+            (KeyCode::Char('u'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::HalfPageUp
+            }
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) => InputAction::ShowFileInfo,
+            (KeyCode::Char('g'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::GoToStart
+            }
+            (KeyCode::Char('G'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::GoToEnd
+            }
+            (KeyCode::Char('{'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::PreviousSection
+            }
+            (KeyCode::Char('}'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::NextSection
+            }
+            (KeyCode::Char('['), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::PreviousRecord
+            }
+            (KeyCode::Char(']'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::NextRecord
+            }
+            (KeyCode::Char('-'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::Command;
+                self.command_buffer.clear();
+                InputAction::StartCommand
+            }
+            (KeyCode::Char(':'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::ColonCommand;
+                self.colon_buffer.clear();
+                InputAction::StartColonCommand
+            }
+            // Shortcuts for `:save ` and `:pipe `, waiting for the file name or command
+            (KeyCode::Char(ch @ ('s' | '|')), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::ColonCommand;
+                self.colon_buffer = if ch == 's' { "save " } else { "pipe " }.to_string();
+                InputAction::UpdateColonCommandBuffer(self.colon_buffer.clone())
+            }
+            (KeyCode::Char('&'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::FilterInput;
+                self.filter_buffer.clear();
+                InputAction::StartFilter
+            }
+            (KeyCode::Char('q'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::Quit
+            }
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => InputAction::Interrupt,
+            (KeyCode::Char('='), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::ShowFileInfo
+            }
+            (KeyCode::Char('o'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::LinePeek;
+                InputAction::OpenLinePeek
+            }
+            (KeyCode::Char('M'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::MatchList;
+                InputAction::OpenMatchList
+            }
+            (KeyCode::Char('P'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::TogglePresentation
+            }
+            (KeyCode::Char('F'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::ToggleFollow
+            }
+            (KeyCode::Char('y' | 'Y'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::ConfirmPrompt
+            }
+            (KeyCode::Char('n'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::NextMatch
+            }
+            (KeyCode::Char('N'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::PreviousMatch
+            }
+            (KeyCode::Char('*'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                InputAction::SearchCurrentMatchWord
+            }
+            (KeyCode::Char('/'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::SearchInput {
+                    direction: SearchDirection::Forward,
+                };
+                self.search_buffer.clear();
+                self.search_history.reset();
+                InputAction::StartSearch(SearchDirection::Forward)
+            }
+            (KeyCode::Char('?'), modifiers)
+                if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::SearchInput {
+                    direction: SearchDirection::Backward,
+                };
+                self.search_buffer.clear();
+                self.search_history.reset();
+                InputAction::StartSearch(SearchDirection::Backward)
+            }
+            _ => {
+                self.clear_percent_buffer();
+                InputAction::InvalidInput
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::service::tests::{ctrl_char, key};
+    use crate::input::InputService;
+    use ratatui::crossterm::event::{Event, KeyEvent};
+
+    #[test]
+    fn ctrl_c_interrupts_navigation() {
+        let mut service = InputService::new();
+        assert_eq!(
+            service.process_event(ctrl_char('c')),
+            vec![InputAction::Interrupt]
+        );
+    }
+
+    #[test]
+    fn s_and_bar_start_save_and_pipe_commands() {
+        let mut service = InputService::new();
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('|'))),
+            vec![InputAction::UpdateColonCommandBuffer("pipe ".to_string())]
+        );
+        for ch in "wc -l".chars() {
+            service.process_event(key(KeyCode::Char(ch)));
+        }
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::ExecuteColonCommand {
+                buffer: "pipe wc -l".to_string(),
+            }]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('s'))),
+            vec![InputAction::UpdateColonCommandBuffer("save ".to_string())]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Esc)),
+            vec![InputAction::CancelColonCommand]
+        );
+    }
+
+    #[test]
+    fn d_and_u_scroll_half_pages_with_or_without_ctrl() {
+        let mut service = InputService::new();
+        for (ch, action) in [
+            ('d', InputAction::HalfPageDown),
+            ('u', InputAction::HalfPageUp),
+        ] {
+            assert_eq!(
+                service.process_event(key(KeyCode::Char(ch))),
+                vec![action.clone()]
+            );
+            assert_eq!(service.process_event(ctrl_char(ch)), vec![action]);
+        }
+        // Ctrl alone no longer slips through the guards of plain keys
+        assert_eq!(
+            service.process_event(ctrl_char('j')),
+            vec![InputAction::InvalidInput]
+        );
+        assert!(service
+            .process_event(Event::Key(KeyEvent::new(
+                KeyCode::Char('j'),
+                KeyModifiers::ALT,
+            )))
+            .is_empty());
+    }
+
+    #[test]
+    fn line_peek_routes_keys_to_the_overlay() {
+        let mut service = InputService::new();
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('o'))),
+            vec![InputAction::OpenLinePeek]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('j'))),
+            vec![InputAction::ScrollLinePeek {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            }]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Up)),
+            vec![InputAction::ScrollLinePeek {
+                direction: ScrollDirection::Up,
+                lines: 1,
+            }]
+        );
+        // Navigation keys do nothing while the overlay is open
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('G'))),
+            vec![InputAction::InvalidInput]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('q'))),
+            vec![InputAction::CloseLinePeek]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('j'))),
+            vec![InputAction::Scroll {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            }]
+        );
+
+        service.process_event(key(KeyCode::Char('o')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Esc)),
+            vec![InputAction::CloseLinePeek]
+        );
+    }
+
+    #[test]
+    fn match_list_routes_keys_to_the_panel() {
+        let mut service = InputService::new();
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('M'))),
+            vec![InputAction::OpenMatchList]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('j'))),
+            vec![InputAction::MoveMatchSelection {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            }]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('b'))),
+            vec![InputAction::PageMatchList(ScrollDirection::Up)]
+        );
+        // Navigation keys do nothing while the panel is open
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('G'))),
+            vec![InputAction::InvalidInput]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::JumpToListedMatch]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('n'))),
+            vec![InputAction::NextMatch]
+        );
+
+        service.process_event(key(KeyCode::Char('M')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Esc)),
+            vec![InputAction::CloseMatchList]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('q'))),
+            vec![InputAction::Quit]
+        );
+    }
+
+    #[test]
+    fn equals_and_ctrl_g_show_file_info() {
+        let mut service = InputService::new();
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('='))),
+            vec![InputAction::ShowFileInfo]
+        );
+        assert_eq!(
+            service.process_event(ctrl_char('g')),
+            vec![InputAction::ShowFileInfo]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('g'))),
+            vec![InputAction::GoToStart]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('P'))),
+            vec![InputAction::TogglePresentation]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('F'))),
+            vec![InputAction::ToggleFollow]
+        );
+    }
+
+    #[test]
+    fn brackets_jump_between_sections_and_records() {
+        let mut service = InputService::new();
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('{'))),
+            vec![InputAction::PreviousSection]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('}'))),
+            vec![InputAction::NextSection]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('['))),
+            vec![InputAction::PreviousRecord]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char(']'))),
+            vec![InputAction::NextRecord]
+        );
+    }
+
+    #[test]
+    fn y_confirms_and_n_keeps_its_meaning() {
+        let mut service = InputService::new();
+        for ch in ['y', 'Y'] {
+            assert_eq!(
+                service.process_event(key(KeyCode::Char(ch))),
+                vec![InputAction::ConfirmPrompt]
+            );
+        }
+        // `n` reaches the coordinator as usual, which treats it as declining a prompt
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('n'))),
+            vec![InputAction::NextMatch]
+        );
+        // Typed inside a search prompt, `y` is just text
+        service.process_event(key(KeyCode::Char('/')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('y'))),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "y".to_string(),
+            }]
+        );
+    }
+}
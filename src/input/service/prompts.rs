@@ -0,0 +1,589 @@
+//! The `-`, `:`, `&` and `%` prompts and the line overlay.
+
+use super::editing::{is_prompt_text, pop_grapheme};
+use super::{InputAction, InputState, InputStateMachine, ScrollDirection};
+use crate::input::ColonCommand;
+use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+
+impl InputStateMachine {
+    /// Keys typed at the `-` prompt
+    pub(super) fn command_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> InputAction {
+        match (code, modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                self.state = InputState::Navigation;
+                self.command_buffer.clear();
+                InputAction::CancelCommand
+            }
+            (KeyCode::Enter, _) => {
+                let buffer = self.command_buffer.clone();
+                self.state = InputState::Navigation;
+                self.command_buffer.clear();
+                InputAction::ExecuteCommand { buffer }
+            }
+            (KeyCode::Backspace, _) => {
+                if pop_grapheme(&mut self.command_buffer) {
+                    InputAction::UpdateCommandBuffer(self.command_buffer.clone())
+                } else {
+                    self.state = InputState::Navigation;
+                    InputAction::CancelCommand
+                }
+            }
+            (KeyCode::Char(ch), modifiers) if is_prompt_text(ch, modifiers) => {
+                self.command_buffer.push(ch);
+                InputAction::UpdateCommandBuffer(self.command_buffer.clone())
+            }
+            _ => InputAction::InvalidInput,
+        }
+    }
+
+    /// Keys typed at the `:` prompt
+    pub(super) fn colon_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> InputAction {
+        match (code, modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                self.state = InputState::Navigation;
+                self.colon_buffer.clear();
+                InputAction::CancelColonCommand
+            }
+            (KeyCode::Enter, _) => {
+                let buffer = std::mem::take(&mut self.colon_buffer);
+                self.state = InputState::Navigation;
+                match ColonCommand::parse(&buffer) {
+                    // The history lives here, so wipe it before the coordinator confirms the
+                    // command
+                    Ok(ColonCommand::ClearHistory) => self.clear_history(),
+                    // The coordinator opens the overlay; keys scroll and close it from now on
+                    Ok(command) if command.opens_overlay() => self.state = InputState::LinePeek,
+                    _ => {}
+                }
+                InputAction::ExecuteColonCommand { buffer }
+            }
+            (KeyCode::Backspace, _) => {
+                if pop_grapheme(&mut self.colon_buffer) {
+                    InputAction::UpdateColonCommandBuffer(self.colon_buffer.clone())
+                } else {
+                    self.state = InputState::Navigation;
+                    InputAction::CancelColonCommand
+                }
+            }
+            (KeyCode::Char(ch), modifiers) if is_prompt_text(ch, modifiers) => {
+                self.colon_buffer.push(ch);
+                InputAction::UpdateColonCommandBuffer(self.colon_buffer.clone())
+            }
+            _ => InputAction::InvalidInput,
+        }
+    }
+
+    /// Keys typed at the `&` prompt
+    pub(super) fn filter_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> InputAction {
+        match (code, modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                self.state = InputState::Navigation;
+                self.filter_buffer.clear();
+                InputAction::CancelFilter
+            }
+            (KeyCode::Enter, _) => {
+                let pattern = std::mem::take(&mut self.filter_buffer);
+                self.state = InputState::Navigation;
+                InputAction::ApplyFilter { pattern }
+            }
+            // `*` as the whole pattern filters by the current match, as `*` searches for it
+            (KeyCode::Char('*'), modifiers)
+                if self.filter_buffer.is_empty()
+                    && !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.state = InputState::Navigation;
+                InputAction::FilterCurrentMatchWord
+            }
+            (KeyCode::Backspace, _) => {
+                if pop_grapheme(&mut self.filter_buffer) {
+                    InputAction::UpdateFilterBuffer(self.filter_buffer.clone())
+                } else {
+                    self.state = InputState::Navigation;
+                    InputAction::CancelFilter
+                }
+            }
+            (KeyCode::Char(ch), modifiers) if is_prompt_text(ch, modifiers) => {
+                self.filter_buffer.push(ch);
+                InputAction::UpdateFilterBuffer(self.filter_buffer.clone())
+            }
+            _ => InputAction::InvalidInput,
+        }
+    }
+
+    /// Keys typed at the `%` prompt
+    pub(super) fn percent_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> InputAction {
+        match (code, modifiers) {
+            (KeyCode::Char(ch @ '0'..='9'), modifiers)
+                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                // Up to three whole digits and a single decimal
+                let room = match self.percent_buffer.split_once('.') {
+                    Some((_, decimals)) => decimals.is_empty(),
+                    None => self.percent_buffer.len() < 3,
+                };
+                if room {
+                    self.percent_buffer.push(ch);
+                }
+                InputAction::UpdatePercentBuffer(self.percent_buffer.clone())
+            }
+            (KeyCode::Char('.'), modifiers)
+                if !modifiers.contains(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                    && !self.percent_buffer.contains('.') =>
+            {
+                self.percent_buffer.push('.');
+                InputAction::UpdatePercentBuffer(self.percent_buffer.clone())
+            }
+            (KeyCode::Backspace, _) => {
+                if self.percent_buffer.pop().is_some() {
+                    InputAction::UpdatePercentBuffer(self.percent_buffer.clone())
+                } else {
+                    self.state = InputState::Navigation;
+                    InputAction::CancelPercentInput
+                }
+            }
+            (KeyCode::Enter, _) => {
+                let buffer = self.percent_buffer.clone();
+                self.clear_percent_buffer();
+                self.state = InputState::Navigation;
+
+                if buffer.is_empty() {
+                    return InputAction::CancelPercentInput;
+                }
+
+                match parse_percent_tenths(&buffer) {
+                    Some(tenths) => InputAction::SubmitPercent {
+                        tenths: tenths.min(1000),
+                    },
+                    None => InputAction::InvalidInput,
+                }
+            }
+            (KeyCode::Esc, _) => {
+                self.clear_percent_buffer();
+                self.state = InputState::Navigation;
+                InputAction::CancelPercentInput
+            }
+            (KeyCode::Char('c'), modifiers) if modifiers.contains(KeyModifiers::CONTROL) => {
+                self.clear_percent_buffer();
+                self.state = InputState::Navigation;
+                InputAction::CancelPercentInput
+            }
+            _ => InputAction::InvalidInput,
+        }
+    }
+
+    /// Keys pressed while a line is shown in the overlay
+    pub(super) fn peek_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> InputAction {
+        match (code, modifiers) {
+            (KeyCode::Esc, _)
+            | (KeyCode::Char('c'), KeyModifiers::CONTROL)
+            | (KeyCode::Char('q'), _) => {
+                self.state = InputState::Navigation;
+                InputAction::CloseLinePeek
+            }
+            (KeyCode::Char('j') | KeyCode::Down, _) => InputAction::ScrollLinePeek {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            },
+            (KeyCode::Char('k') | KeyCode::Up, _) => InputAction::ScrollLinePeek {
+                direction: ScrollDirection::Up,
+                lines: 1,
+            },
+            _ => InputAction::InvalidInput,
+        }
+    }
+
+    /// Keys pressed while the match list is open
+    pub(super) fn match_list_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> InputAction {
+        match (code, modifiers) {
+            (KeyCode::Esc, _)
+            | (KeyCode::Char('c'), KeyModifiers::CONTROL)
+            | (KeyCode::Char('q'), _) => {
+                self.state = InputState::Navigation;
+                InputAction::CloseMatchList
+            }
+            (KeyCode::Enter, _) => {
+                self.state = InputState::Navigation;
+                InputAction::JumpToListedMatch
+            }
+            (KeyCode::Char('j') | KeyCode::Down, _) => InputAction::MoveMatchSelection {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            },
+            (KeyCode::Char('k') | KeyCode::Up, _) => InputAction::MoveMatchSelection {
+                direction: ScrollDirection::Up,
+                lines: 1,
+            },
+            (KeyCode::Char(' ' | 'f') | KeyCode::PageDown, _) => {
+                InputAction::PageMatchList(ScrollDirection::Down)
+            }
+            (KeyCode::Char('b') | KeyCode::PageUp, _) => {
+                InputAction::PageMatchList(ScrollDirection::Up)
+            }
+            _ => InputAction::InvalidInput,
+        }
+    }
+}
+
+/// Parse `42`, `42.7` or `.5` into tenths of a percent
+fn parse_percent_tenths(buffer: &str) -> Option<u16> {
+    let (whole, decimal) = buffer.split_once('.').unwrap_or((buffer, ""));
+    if whole.is_empty() && decimal.is_empty() {
+        return None;
+    }
+    let whole: u16 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let decimal: u16 = match decimal {
+        "" => 0,
+        digit if digit.len() == 1 => digit.parse().ok()?,
+        _ => return None,
+    };
+    Some(whole.checked_mul(10)? + decimal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::service::tests::{ctrl_char, key, type_and_enter};
+    use crate::input::InputService;
+
+    #[test]
+    fn percent_jump_emits_action() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('%'))),
+            vec![InputAction::StartPercentInput]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('5'))),
+            vec![InputAction::UpdatePercentBuffer("5".to_string())]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('0'))),
+            vec![InputAction::UpdatePercentBuffer("50".to_string())]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::SubmitPercent { tenths: 500 }]
+        );
+    }
+
+    #[test]
+    fn percent_jump_accepts_one_decimal() {
+        let mut service = InputService::new();
+        service.process_event(key(KeyCode::Char('%')));
+        for ch in ['4', '2', '.'] {
+            service.process_event(key(KeyCode::Char(ch)));
+        }
+        // A second dot is rejected and leaves the buffer alone
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('.'))),
+            vec![InputAction::InvalidInput]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('7'))),
+            vec![InputAction::UpdatePercentBuffer("42.7".to_string())]
+        );
+        // Only one decimal digit is kept
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('9'))),
+            vec![InputAction::UpdatePercentBuffer("42.7".to_string())]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::SubmitPercent { tenths: 427 }]
+        );
+
+        service.process_event(key(KeyCode::Char('%')));
+        service.process_event(key(KeyCode::Char('.')));
+        service.process_event(key(KeyCode::Char('5')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::SubmitPercent { tenths: 5 }]
+        );
+
+        assert_eq!(parse_percent_tenths("999.9"), Some(9999));
+        assert_eq!(parse_percent_tenths("7."), Some(70));
+        assert_eq!(parse_percent_tenths("."), None);
+        assert_eq!(parse_percent_tenths("1.23"), None);
+    }
+
+    #[test]
+    fn percent_jump_backspace_cancels_when_empty() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('%'))),
+            vec![InputAction::StartPercentInput]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('1'))),
+            vec![InputAction::UpdatePercentBuffer("1".to_string())]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Backspace)),
+            vec![InputAction::UpdatePercentBuffer(String::new())]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Backspace)),
+            vec![InputAction::CancelPercentInput]
+        );
+    }
+
+    #[test]
+    fn percent_jump_ctrl_c_cancels() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('%'))),
+            vec![InputAction::StartPercentInput]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('2'))),
+            vec![InputAction::UpdatePercentBuffer("2".to_string())]
+        );
+
+        assert_eq!(
+            service.process_event(ctrl_char('c')),
+            vec![InputAction::CancelPercentInput]
+        );
+    }
+
+    #[test]
+    fn percent_jump_escape_cancels() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('%'))),
+            vec![InputAction::StartPercentInput]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Esc)),
+            vec![InputAction::CancelPercentInput]
+        );
+    }
+
+    #[test]
+    fn options_command_keeps_keys_in_the_overlay() {
+        let mut service = InputService::new();
+        type_and_enter(&mut service, ':', "options");
+
+        // `q` closes the overlay instead of quitting
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('j'))),
+            vec![InputAction::ScrollLinePeek {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            }]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('q'))),
+            vec![InputAction::CloseLinePeek]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('q'))),
+            vec![InputAction::Quit]
+        );
+    }
+
+    #[test]
+    fn command_mode_updates_buffer_and_executes() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('-'))),
+            vec![InputAction::StartCommand]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('i'))),
+            vec![InputAction::UpdateCommandBuffer("i".to_string())]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Backspace)),
+            vec![InputAction::UpdateCommandBuffer(String::new())]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('r'))),
+            vec![InputAction::UpdateCommandBuffer("r".to_string())]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::ExecuteCommand {
+                buffer: "r".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn colon_command_mode_collects_buffer_with_spaces() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char(':'))),
+            vec![InputAction::StartColonCommand]
+        );
+        for ch in "skip dups".chars() {
+            service.process_event(key(KeyCode::Char(ch)));
+        }
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::ExecuteColonCommand {
+                buffer: "skip dups".to_string(),
+            }]
+        );
+        assert_eq!(service.state_machine.get_state(), InputState::Navigation);
+
+        service.process_event(key(KeyCode::Char(':')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Backspace)),
+            vec![InputAction::CancelColonCommand]
+        );
+    }
+
+    #[test]
+    fn filter_mode_applies_or_clears_pattern() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('&'))),
+            vec![InputAction::StartFilter]
+        );
+        for ch in "err".chars() {
+            service.process_event(key(KeyCode::Char(ch)));
+        }
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::ApplyFilter {
+                pattern: "err".to_string(),
+            }]
+        );
+        assert_eq!(service.state_machine.get_state(), InputState::Navigation);
+
+        // Submitting an empty filter clears it
+        service.process_event(key(KeyCode::Char('&')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::ApplyFilter {
+                pattern: String::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn ampersand_star_filters_by_the_current_match() {
+        let mut service = InputService::new();
+        service.process_event(key(KeyCode::Char('&')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('*'))),
+            vec![InputAction::FilterCurrentMatchWord]
+        );
+        assert_eq!(service.state_machine.get_state(), InputState::Navigation);
+
+        // Later in a pattern `*` is typed as is
+        service.process_event(key(KeyCode::Char('&')));
+        service.process_event(key(KeyCode::Char('a')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('*'))),
+            vec![InputAction::UpdateFilterBuffer("a*".to_string())]
+        );
+    }
+
+    #[test]
+    fn command_mode_cancel_clears_buffer() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('-'))),
+            vec![InputAction::StartCommand]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('w'))),
+            vec![InputAction::UpdateCommandBuffer("w".to_string())]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Esc)),
+            vec![InputAction::CancelCommand]
+        );
+    }
+
+    #[test]
+    fn command_mode_backspace_when_empty_exits() {
+        let mut service = InputService::new();
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('-'))),
+            vec![InputAction::StartCommand]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Backspace)),
+            vec![InputAction::CancelCommand]
+        );
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('-'))),
+            vec![InputAction::StartCommand]
+        );
+    }
+
+    #[test]
+    fn backspace_removes_whole_graphemes_at_every_prompt() {
+        // An accent built from a combining mark, then a thumbs up with a skin tone
+        let typed = "xe\u{301}\u{1F44D}\u{1F3FD}";
+        for (prefix, update, submit) in [
+            (
+                '-',
+                InputAction::UpdateCommandBuffer as fn(String) -> InputAction,
+                InputAction::ExecuteCommand {
+                    buffer: "x".to_string(),
+                },
+            ),
+            (
+                ':',
+                InputAction::UpdateColonCommandBuffer,
+                InputAction::ExecuteColonCommand {
+                    buffer: "x".to_string(),
+                },
+            ),
+            (
+                '&',
+                InputAction::UpdateFilterBuffer,
+                InputAction::ApplyFilter {
+                    pattern: "x".to_string(),
+                },
+            ),
+        ] {
+            let mut service = InputService::new();
+            service.process_event(key(KeyCode::Char(prefix)));
+            for ch in typed.chars() {
+                service.process_event(key(KeyCode::Char(ch)));
+            }
+            assert_eq!(
+                service.process_event(key(KeyCode::Backspace)),
+                vec![update("xe\u{301}".to_string())]
+            );
+            assert_eq!(
+                service.process_event(key(KeyCode::Backspace)),
+                vec![update("x".to_string())]
+            );
+            assert_eq!(service.process_event(key(KeyCode::Enter)), vec![submit]);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! The `/` and `?` search prompts.
+
+use super::editing::{is_prompt_text, pop_grapheme};
+use super::{InputAction, InputState, InputStateMachine, SearchDirection};
+use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+
+impl InputStateMachine {
+    /// Keys typed at the `/` and `?` prompts
+    pub(super) fn search_key(
+        &mut self,
+        direction: SearchDirection,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+    ) -> InputAction {
+        match (code, modifiers) {
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                self.state = InputState::Navigation;
+                self.search_buffer.clear();
+                self.search_history.reset();
+                InputAction::CancelSearch
+            }
+            (KeyCode::Char('t'), KeyModifiers::CONTROL) => {
+                // Turn `/` into `?` and back without losing what was typed
+                let direction = direction.reverse();
+                self.state = InputState::SearchInput { direction };
+                InputAction::UpdateSearchBuffer {
+                    direction,
+                    buffer: self.search_buffer.clone(),
+                }
+            }
+            (KeyCode::Char(ch), modifiers) if is_prompt_text(ch, modifiers) => {
+                self.search_history.edited();
+                self.search_buffer.push(ch);
+                InputAction::UpdateSearchBuffer {
+                    direction,
+                    buffer: self.search_buffer.clone(),
+                }
+            }
+            (KeyCode::Backspace, _) => {
+                self.search_history.edited();
+                pop_grapheme(&mut self.search_buffer);
+                if self.search_buffer.is_empty() {
+                    self.search_history.reset();
+                    self.state = InputState::Navigation;
+                    InputAction::CancelSearch
+                } else {
+                    InputAction::UpdateSearchBuffer {
+                        direction,
+                        buffer: self.search_buffer.clone(),
+                    }
+                }
+            }
+            (KeyCode::Enter, _) => {
+                let pattern = self.search_buffer.clone();
+                self.state = InputState::Navigation;
+                self.search_buffer.clear();
+                self.search_submitted = true;
+
+                // Whitespace is significant: `/  ` searches for a run of spaces. An empty buffer
+                // repeats the latest search in the prompt's direction, as in less.
+                if !pattern.is_empty() {
+                    self.record_history(&pattern);
+                    self.search_history.executed();
+                    InputAction::ExecuteSearch { pattern, direction }
+                } else if let Some(pattern) = self.search_history.latest() {
+                    let pattern = pattern.to_string();
+                    self.search_history.reset();
+                    InputAction::ExecuteSearch { pattern, direction }
+                } else {
+                    self.search_history.reset();
+                    InputAction::CancelSearch
+                }
+            }
+            (KeyCode::Esc, _) => {
+                self.state = InputState::Navigation;
+                self.search_buffer.clear();
+                self.search_history.reset();
+                InputAction::CancelSearch
+            }
+            (KeyCode::Up, _) => match self.search_history.older(&self.search_buffer) {
+                Some(entry) => {
+                    self.search_buffer = entry.to_string();
+                    InputAction::UpdateSearchBuffer {
+                        direction,
+                        buffer: self.search_buffer.clone(),
+                    }
+                }
+                None => InputAction::NoAction,
+            },
+            (KeyCode::Down, _) => match self.search_history.newer() {
+                Some(entry) => {
+                    self.search_buffer = entry;
+                    InputAction::UpdateSearchBuffer {
+                        direction,
+                        buffer: self.search_buffer.clone(),
+                    }
+                }
+                None => InputAction::NoAction,
+            },
+            _ => {
+                self.clear_percent_buffer();
+                InputAction::InvalidInput
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keymap::{Keymap, SharedKeymap};
+    use crate::input::service::tests::{ctrl_char, key, recall_latest, type_and_enter};
+    use crate::input::InputService;
+    use grep_regex::RegexMatcher;
+
+    #[test]
+    fn ctrl_t_flips_search_direction_and_keeps_the_buffer() {
+        let mut service = InputService::new();
+        service.process_event(key(KeyCode::Char('/')));
+        service.process_event(key(KeyCode::Char('t')));
+        service.process_event(key(KeyCode::Char('o')));
+
+        assert_eq!(
+            service.process_event(ctrl_char('t')),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Backward,
+                buffer: "to".to_string(),
+            }]
+        );
+        // Typing continues in the new direction
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('p'))),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Backward,
+                buffer: "top".to_string(),
+            }]
+        );
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::ExecuteSearch {
+                pattern: "top".to_string(),
+                direction: SearchDirection::Backward,
+            }]
+        );
+
+        // Flipping twice is a no-op, and works on an empty buffer
+        service.process_event(key(KeyCode::Char('?')));
+        service.process_event(ctrl_char('t'));
+        assert_eq!(
+            service.process_event(ctrl_char('t')),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Backward,
+                buffer: String::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn held_enter_submits_the_search_once() {
+        let mut keymap = Keymap::new();
+        keymap.bind("enter", InputAction::PageDown).unwrap();
+        let mut service = InputService::new().with_keymap(SharedKeymap::new(keymap));
+        service.process_event(key(KeyCode::Char('/')));
+        service.process_event(key(KeyCode::Char('x')));
+
+        let actions: Vec<_> = (0..4)
+            .flat_map(|_| service.process_event(key(KeyCode::Enter)))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![InputAction::ExecuteSearch {
+                pattern: "x".to_string(),
+                direction: SearchDirection::Forward,
+            }]
+        );
+        // Any other key ends the repeat, and Enter is bound again
+        service.process_event(key(KeyCode::Char('j')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::PageDown]
+        );
+    }
+
+    #[test]
+    fn search_history_navigation_allows_recall() {
+        let mut service = InputService::new();
+
+        // First search: "/f"
+        service.process_event(key(KeyCode::Char('/')));
+        service.process_event(key(KeyCode::Char('f')));
+        service.process_event(key(KeyCode::Enter));
+
+        // Second search: "/bar"
+        service.process_event(key(KeyCode::Char('/')));
+        service.process_event(key(KeyCode::Char('b')));
+        service.process_event(key(KeyCode::Char('a')));
+        service.process_event(key(KeyCode::Char('r')));
+        service.process_event(key(KeyCode::Enter));
+
+        // Start a new search session to recall history
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('/'))),
+            vec![InputAction::StartSearch(SearchDirection::Forward)]
+        );
+
+        // Up -> recalls most recent entry "bar"
+        assert_eq!(
+            service.process_event(key(KeyCode::Up)),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "bar".to_string(),
+            }]
+        );
+
+        // Another Up -> older entry "f"
+        assert_eq!(
+            service.process_event(key(KeyCode::Up)),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "f".to_string(),
+            }]
+        );
+
+        // Down -> returns to "bar"
+        assert_eq!(
+            service.process_event(key(KeyCode::Down)),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "bar".to_string(),
+            }]
+        );
+
+        // Down past latest entry -> restores the empty prompt typed before browsing
+        assert_eq!(
+            service.process_event(key(KeyCode::Down)),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: String::new(),
+            }]
+        );
+
+        // Typing after recall resets the cursor
+        assert_eq!(
+            service.process_event(key(KeyCode::Char('z'))),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "z".to_string(),
+            }]
+        );
+
+        // Up again recalls the latest history entry
+        assert_eq!(
+            service.process_event(key(KeyCode::Up)),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "bar".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn browsing_puts_the_typed_text_aside_until_down_brings_it_back() {
+        let mut service = InputService::new();
+        type_and_enter(&mut service, '/', "older");
+        type_and_enter(&mut service, '/', "newer");
+
+        service.process_event(key(KeyCode::Char('/')));
+        service.process_event(key(KeyCode::Char('d')));
+        service.process_event(key(KeyCode::Up));
+        service.process_event(key(KeyCode::Up));
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::ExecuteSearch {
+                pattern: "older".to_string(),
+                direction: SearchDirection::Forward,
+            }]
+        );
+
+        // The next prompt still has the text typed before the recall
+        service.process_event(key(KeyCode::Char('/')));
+        assert_eq!(
+            service.process_event(key(KeyCode::Down)),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "d".to_string(),
+            }]
+        );
+        assert_eq!(service.process_event(key(KeyCode::Down)), vec![]);
+    }
+
+    #[test]
+    fn excluded_patterns_are_searched_but_not_recorded() {
+        let exclude = vec![
+            RegexMatcher::new("token=").unwrap(),
+            RegexMatcher::new("^[0-9a-f]{32}$").unwrap(),
+        ];
+        let mut service = InputService::new().with_history_exclude(exclude);
+
+        type_and_enter(&mut service, '/', "error");
+        for secret in ["token=abc", "0123456789abcdef0123456789abcdef"] {
+            assert_eq!(
+                type_and_enter(&mut service, '/', secret),
+                vec![InputAction::ExecuteSearch {
+                    pattern: secret.to_string(),
+                    direction: SearchDirection::Forward,
+                }]
+            );
+        }
+
+        assert_eq!(
+            recall_latest(&mut service),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "error".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn clear_history_command_forgets_patterns() {
+        let mut service = InputService::new();
+        type_and_enter(&mut service, '/', "error");
+
+        assert_eq!(
+            type_and_enter(&mut service, ':', "clear-history"),
+            vec![InputAction::ExecuteColonCommand {
+                buffer: "clear-history".to_string(),
+            }]
+        );
+        assert!(recall_latest(&mut service).is_empty());
+    }
+
+    #[test]
+    fn empty_search_repeats_the_latest_pattern_or_cancels() {
+        let mut service = InputService::new();
+        // Nothing to repeat yet
+        assert_eq!(
+            type_and_enter(&mut service, '/', ""),
+            vec![InputAction::CancelSearch]
+        );
+
+        type_and_enter(&mut service, '/', "older");
+        type_and_enter(&mut service, '/', "timeout");
+        assert_eq!(
+            type_and_enter(&mut service, '?', ""),
+            vec![InputAction::ExecuteSearch {
+                pattern: "timeout".to_string(),
+                direction: SearchDirection::Backward,
+            }]
+        );
+        // The history is unchanged and the prompt closed
+        assert_eq!(
+            recall_latest(&mut service),
+            vec![InputAction::UpdateSearchBuffer {
+                direction: SearchDirection::Forward,
+                buffer: "timeout".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn whitespace_only_search_is_executed_verbatim() {
+        let mut service = InputService::new();
+        service.process_event(key(KeyCode::Char('/')));
+        service.process_event(key(KeyCode::Char(' ')));
+        service.process_event(key(KeyCode::Char(' ')));
+
+        assert_eq!(
+            service.process_event(key(KeyCode::Enter)),
+            vec![InputAction::ExecuteSearch {
+                pattern: "  ".to_string(),
+                direction: SearchDirection::Forward,
+            }]
+        );
+    }
+
+    #[test]
+    fn backspace_removes_whole_graphemes() {
+        let mut service = InputService::new();
+        service.process_event(key(KeyCode::Char('/')));
+        for ch in "xe\u{301}\u{1F44D}\u{1F3FD}".chars() {
+            service.process_event(key(KeyCode::Char(ch)));
+        }
+
+        for expected in ["xe\u{301}", "x"] {
+            assert_eq!(
+                service.process_event(key(KeyCode::Backspace)),
+                vec![InputAction::UpdateSearchBuffer {
+                    direction: SearchDirection::Forward,
+                    buffer: expected.to_string(),
+                }]
+            );
+        }
+        assert_eq!(
+            service.process_event(key(KeyCode::Backspace)),
+            vec![InputAction::CancelSearch]
+        );
+    }
+}
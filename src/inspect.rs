@@ -0,0 +1,324 @@
+//! Quick report on a file before opening it.
+//!
+//! `--inspect` prints what rlless can tell about a file from a bounded sample without loading
+//! it: compression, text encoding, line terminators, BOM, the longest line in the sample and a
+//! line count extrapolated from the sample. Only the first `sample_bytes` of content are read
+//! (decompressed, for compressed files), so the report is quick even for very large files.
+
+use crate::error::{Result, RllessError};
+use crate::file_handler::meta::human_size;
+use crate::file_handler::{decompress_prefix, detect_compression, CompressionType};
+use std::path::Path;
+
+/// Default number of content bytes sampled.
+pub const DEFAULT_SAMPLE_BYTES: usize = 8 * 1024 * 1024;
+
+/// Text encoding guessed from the sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Ascii,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Not valid UTF-8 but free of NUL bytes, e.g. Latin-1
+    Other8Bit,
+    /// Contains NUL bytes without looking like UTF-16
+    Binary,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Ascii => "ascii",
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Other8Bit => "8-bit (not utf-8)",
+            Encoding::Binary => "binary",
+        }
+    }
+}
+
+/// Line terminators counted in the sample.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineEndings {
+    pub lf: u64,
+    pub crlf: u64,
+    /// `\r` not followed by `\n`
+    pub cr: u64,
+}
+
+impl LineEndings {
+    /// `lf`, `crlf`, `cr`, `mixed`, or `none` when the sample holds no terminator
+    pub fn style(&self) -> &'static str {
+        match (self.lf > 0, self.crlf > 0, self.cr > 0) {
+            (false, false, false) => "none",
+            (true, false, false) => "lf",
+            (false, true, false) => "crlf",
+            (false, false, true) => "cr",
+            _ => "mixed",
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.lf + self.crlf + self.cr
+    }
+}
+
+/// Everything `--inspect` reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectReport {
+    pub compression: CompressionType,
+    /// Size of the file on disk
+    pub disk_size: u64,
+    pub encoding: Encoding,
+    pub bom: bool,
+    pub line_endings: LineEndings,
+    /// Longest line in the sample, in bytes, without its terminator
+    pub longest_line: u64,
+    pub sampled_bytes: u64,
+    /// The sample is the whole (decompressed) content
+    pub complete: bool,
+    /// Line count, exact when `complete`; `None` for a partially sampled compressed file, whose
+    /// decompressed size is unknown
+    pub lines: Option<u64>,
+}
+
+impl InspectReport {
+    /// Human-readable report, one `field: value` per line
+    pub fn to_text(&self) -> String {
+        let sample = if self.complete {
+            "whole file".to_string()
+        } else {
+            format!("first {}", human_size(self.sampled_bytes))
+        };
+        let lines = match (self.lines, self.complete) {
+            (Some(lines), true) => lines.to_string(),
+            (Some(lines), false) => format!("~{} (estimated from the {})", lines, sample),
+            (None, _) => "unknown (compressed, only the start was sampled)".to_string(),
+        };
+        [
+            format!("compression:   {}", self.compression.name()),
+            format!("size on disk:  {}", human_size(self.disk_size)),
+            format!(
+                "encoding:      {}{}",
+                self.encoding.name(),
+                if self.bom { " with BOM" } else { "" }
+            ),
+            format!("line endings:  {}", self.line_endings.style()),
+            format!("longest line:  {} bytes ({})", self.longest_line, sample),
+            format!("lines:         {}", lines),
+        ]
+        .join("\n")
+    }
+
+    /// One JSON object; `lines` is `null` when unknown
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"compression":"{}","disk_size":{},"encoding":"{}","bom":{},"line_endings":"{}","longest_line":{},"sampled_bytes":{},"complete":{},"lines":{}}}"#,
+            self.compression.name(),
+            self.disk_size,
+            self.encoding.name(),
+            self.bom,
+            self.line_endings.style(),
+            self.longest_line,
+            self.sampled_bytes,
+            self.complete,
+            self.lines
+                .map_or_else(|| "null".to_string(), |lines| lines.to_string())
+        )
+    }
+}
+
+/// Build the report for `path` from its first `sample_bytes` bytes of content
+pub async fn inspect(path: &Path, sample_bytes: usize) -> Result<InspectReport> {
+    let disk_size = tokio::fs::metadata(path)
+        .await
+        .map_err(|e| RllessError::file_error("Failed to get file metadata", e))?
+        .len();
+    let compression = detect_compression(path).await?;
+    let (sample, complete) = decompress_prefix(path, compression, sample_bytes).await?;
+
+    let (encoding, bom) = sniff_encoding(&sample);
+    let (line_endings, longest_line) = scan_lines(&sample);
+    // An unterminated last line still counts as a line
+    let mut sample_lines = line_endings.total();
+    if complete && !sample.is_empty() && !matches!(sample.last(), Some(b'\n' | b'\r')) {
+        sample_lines += 1;
+    }
+    let lines = if complete {
+        Some(sample_lines)
+    } else if compression.is_compressed() {
+        None
+    } else {
+        let estimate = u128::from(sample_lines) * u128::from(disk_size) / sample.len() as u128;
+        Some(estimate as u64)
+    };
+
+    Ok(InspectReport {
+        compression,
+        disk_size,
+        encoding,
+        bom,
+        line_endings,
+        longest_line,
+        sampled_bytes: sample.len() as u64,
+        complete,
+        lines,
+    })
+}
+
+/// Guess the encoding from a BOM, NUL byte placement and UTF-8 validity
+fn sniff_encoding(sample: &[u8]) -> (Encoding, bool) {
+    if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return (Encoding::Utf8, true);
+    }
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return (Encoding::Utf16Le, true);
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return (Encoding::Utf16Be, true);
+    }
+
+    // ASCII text in UTF-16 has a NUL in every other byte
+    let head = &sample[..sample.len().min(4096)];
+    let zeros_at = |parity: usize| {
+        head.iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    let pairs = head.len() / 2;
+    if pairs > 0 && odd * 10 >= pairs * 4 && even * 10 < pairs {
+        return (Encoding::Utf16Le, false);
+    }
+    if pairs > 0 && even * 10 >= pairs * 4 && odd * 10 < pairs {
+        return (Encoding::Utf16Be, false);
+    }
+    if even + odd > 0 {
+        return (Encoding::Binary, false);
+    }
+
+    let valid = match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        // A character cut off by the end of the sample is not an error
+        Err(error) => error.error_len().is_none(),
+    };
+    match (valid, sample.is_ascii()) {
+        (true, true) => (Encoding::Ascii, false),
+        (true, false) => (Encoding::Utf8, false),
+        (false, _) => (Encoding::Other8Bit, false),
+    }
+}
+
+/// Count line terminators and measure the longest line
+fn scan_lines(sample: &[u8]) -> (LineEndings, u64) {
+    let mut endings = LineEndings::default();
+    let mut longest = 0u64;
+    let mut line_start = 0;
+    let mut index = 0;
+    while let Some(offset) = memchr::memchr2(b'\n', b'\r', &sample[index..]) {
+        let end = index + offset;
+        longest = longest.max((end - line_start) as u64);
+        index = end + 1;
+        if sample[end] == b'\n' {
+            endings.lf += 1;
+        } else if sample.get(index) == Some(&b'\n') {
+            endings.crlf += 1;
+            index += 1;
+        } else {
+            endings.cr += 1;
+        }
+        line_start = index;
+    }
+    longest = longest.max((sample.len() - line_start) as u64);
+    (endings, longest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn fixture(content: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        file.flush().unwrap();
+        file
+    }
+
+    #[tokio::test]
+    async fn test_crlf_file_with_bom_is_fully_sampled() {
+        let file = fixture(b"\xEF\xBB\xBFfirst\r\nsecond line\r\nlast");
+        let report = inspect(file.path(), DEFAULT_SAMPLE_BYTES).await.unwrap();
+
+        assert_eq!(report.compression, CompressionType::None);
+        assert_eq!((report.encoding, report.bom), (Encoding::Utf8, true));
+        assert_eq!(report.line_endings.style(), "crlf");
+        assert_eq!(report.longest_line, 11);
+        assert!(report.complete);
+        assert_eq!(report.lines, Some(3));
+        assert_eq!(
+            report.to_json(),
+            r#"{"compression":"none","disk_size":27,"encoding":"utf-8","bom":true,"line_endings":"crlf","longest_line":11,"sampled_bytes":27,"complete":true,"lines":3}"#
+        );
+        assert!(report.to_text().contains("lines:         3"));
+    }
+
+    #[tokio::test]
+    async fn test_line_count_is_extrapolated_from_the_sample() {
+        // 1000 lines of 10 bytes, sampled over the first 100 bytes
+        let content: String = (0..1000).map(|i| format!("line {:04}\n", i)).collect();
+        let file = fixture(content.as_bytes());
+        let report = inspect(file.path(), 100).await.unwrap();
+
+        assert!(!report.complete);
+        assert_eq!(report.sampled_bytes, 100);
+        assert_eq!(report.lines, Some(1000));
+        assert_eq!((report.encoding, report.bom), (Encoding::Ascii, false));
+        assert!(report
+            .to_text()
+            .contains("~1000 (estimated from the first 100 B)"));
+    }
+
+    #[tokio::test]
+    async fn test_compressed_sample_reports_unknown_line_count() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all("déjà vu\nnext\rold mac\n".repeat(50).as_bytes())
+            .unwrap();
+        let file = fixture(&encoder.finish().unwrap());
+
+        let report = inspect(file.path(), 64).await.unwrap();
+        assert_eq!(report.compression, CompressionType::Gzip);
+        assert_eq!(report.encoding, Encoding::Utf8);
+        assert_eq!(report.line_endings.style(), "mixed");
+        assert_eq!(report.lines, None);
+        assert!(report
+            .to_json()
+            .ends_with(r#""complete":false,"lines":null}"#));
+
+        let report = inspect(file.path(), DEFAULT_SAMPLE_BYTES).await.unwrap();
+        assert_eq!(report.lines, Some(150));
+    }
+
+    #[test]
+    fn test_encoding_sniffing() {
+        let utf16le: Vec<u8> = "log line\n".bytes().flat_map(|b| [b, 0]).collect();
+        assert_eq!(sniff_encoding(&utf16le), (Encoding::Utf16Le, false));
+        let utf16be: Vec<u8> = "log line\n".bytes().flat_map(|b| [0, b]).collect();
+        assert_eq!(sniff_encoding(&utf16be), (Encoding::Utf16Be, false));
+        assert_eq!(sniff_encoding(b"\xFF\xFEx\x00"), (Encoding::Utf16Le, true));
+        assert_eq!(sniff_encoding(b"caf\xE9\n"), (Encoding::Other8Bit, false));
+        assert_eq!(
+            sniff_encoding(b"\x7FELF\x02\x01\x01\x00\x00\x00\x00\x00"),
+            (Encoding::Binary, false)
+        );
+        // "é" cut in half by the end of the sample
+        assert_eq!(sniff_encoding(b"caf\xC3"), (Encoding::Utf8, false));
+    }
+}
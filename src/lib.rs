@@ -20,6 +20,14 @@
 //! - [`search`] - Search engine integration with ripgrep
 //! - [`render::ui`](crate::render::ui) - Terminal user interface components
 //! - [`app`] - Application core and component coordination
+//! - [`bookmarks`] - Named bookmarks kept per file between sessions
+//! - [`export`] - Byte-exact range export for piping to other tools
+//! - [`inspect`] - Sample-based file report (`--inspect`)
+//! - [`line_mode`] - Accessible line-mode pager for screen readers
+//! - [`logging`] - What log records may contain, and `--log-file`
+//! - [`match_list`] - Headless match listing for other tools
+//! - [`settings`] - Effective settings and their provenance
+//! - [`util`] - Crash-safe writes for files kept between runs, and cancellation tokens
 
 // Core modules
 pub mod error;
@@ -31,7 +39,15 @@ pub mod render;
 
 // Core components
 pub mod app;
+pub mod bookmarks;
+pub mod export;
+pub mod inspect;
+pub mod line_mode;
+pub mod logging;
+pub mod match_list;
 pub mod search;
+pub mod settings;
+pub mod util;
 
 // Re-export commonly used types for convenience
 pub use error::{Result, RllessError};
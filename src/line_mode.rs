@@ -0,0 +1,307 @@
+//! Accessible line-mode frontend.
+//!
+//! `--accessible` replaces the full-screen TUI with a plain sequential pager for screen readers:
+//! nothing is redrawn in place and the alternate screen is never entered. Each command is read
+//! as a line from stdin in cooked mode, and its result is appended to stdout, so everything
+//! shown stays in the terminal's scrollback in reading order. File text and patterns are printed
+//! through `sanitize_status_text`, so escape sequences in the file cannot drive the terminal.
+//!
+//! Commands: `Enter` (or a space) prints the next page, `b` the previous one, `g`/`G` the first
+//! and last page, `/pattern` and `?pattern` print the next/previous match with surrounding
+//! context, `n`/`N` repeat the search, `=` reports the position, `h` lists the commands and `q`
+//! quits.
+
+use crate::error::Result;
+use crate::file_handler::FileAccessor;
+use crate::input::SearchDirection;
+use crate::render::ui::state::sanitize_status_text;
+use crate::search::{SearchEngine, SearchOptions};
+use std::io::Write;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Lines printed before and after a match.
+pub const MATCH_CONTEXT: usize = 2;
+
+/// Prompt printed after each command's output, without a newline.
+pub const PROMPT: &str = ": ";
+
+const HELP: &str = "\
+Enter or space: next page
+b: previous page
+g: first page, G: last page
+/pattern: search forward, ?pattern: search backward
+n: repeat search, N: repeat search in the other direction
+=: position
+q: quit";
+
+/// Line-mode pager over one file.
+pub struct LinePager<E: SearchEngine, W: Write> {
+    accessor: Arc<dyn FileAccessor>,
+    engine: E,
+    options: SearchOptions,
+    page_lines: usize,
+    /// Start of the last printed page, or of the last match
+    top: u64,
+    /// Where the next page starts
+    next: u64,
+    last_search: Option<(String, SearchDirection)>,
+    output: W,
+}
+
+impl<E: SearchEngine, W: Write> LinePager<E, W> {
+    pub fn new(
+        accessor: Arc<dyn FileAccessor>,
+        engine: E,
+        options: SearchOptions,
+        page_lines: usize,
+        output: W,
+    ) -> Self {
+        Self {
+            accessor,
+            engine,
+            options,
+            page_lines: page_lines.max(1),
+            top: 0,
+            next: 0,
+            last_search: None,
+            output,
+        }
+    }
+
+    /// Print the first page, then run commands from `input` until `q` or end of input
+    pub async fn run<R: AsyncBufRead + Unpin>(&mut self, input: R) -> Result<()> {
+        self.print_page(0).await?;
+        self.prompt()?;
+        let mut commands = input.lines();
+        while let Some(command) = commands.next_line().await? {
+            if !self.command(&command).await? {
+                break;
+            }
+            self.prompt()?;
+        }
+        self.output.flush()?;
+        Ok(())
+    }
+
+    /// Run one command line; returns false when the pager should exit
+    pub async fn command(&mut self, command: &str) -> Result<bool> {
+        let command = command.trim_end_matches('\r');
+        if let Some(pattern) = command.strip_prefix('/') {
+            self.search(pattern, SearchDirection::Forward).await?;
+            return Ok(true);
+        }
+        if let Some(pattern) = command.strip_prefix('?') {
+            self.search(pattern, SearchDirection::Backward).await?;
+            return Ok(true);
+        }
+
+        match command.trim() {
+            "" => self.print_page(self.next).await?,
+            "b" => {
+                let start = self
+                    .accessor
+                    .prev_page_start(self.top, self.page_lines)
+                    .await?;
+                self.print_page(start).await?;
+            }
+            "g" => self.print_page(0).await?,
+            "G" => {
+                let start = self.accessor.last_page_start(self.page_lines).await?;
+                self.print_page(start).await?;
+            }
+            "n" | "N" => match self.last_search.clone() {
+                Some((pattern, direction)) => {
+                    let direction = if command.trim() == "N" {
+                        direction.reverse()
+                    } else {
+                        direction
+                    };
+                    self.find(&pattern, direction).await?;
+                }
+                None => writeln!(self.output, "No previous search")?,
+            },
+            "=" => {
+                let size = self.accessor.file_size();
+                let percent = (self.top * 100).checked_div(size).unwrap_or(100);
+                writeln!(
+                    self.output,
+                    "{}: byte {} of {}, {}%",
+                    self.accessor.file_path().display(),
+                    self.top,
+                    size,
+                    percent
+                )?;
+            }
+            "h" => writeln!(self.output, "{}", HELP)?,
+            "q" => return Ok(false),
+            other => writeln!(self.output, "Unknown command {:?}, h for help", other)?,
+        }
+        Ok(true)
+    }
+
+    fn prompt(&mut self) -> Result<()> {
+        write!(self.output, "{}", PROMPT)?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    async fn print_page(&mut self, start: u64) -> Result<()> {
+        let lines = self.accessor.read_from_byte(start, self.page_lines).await?;
+        for line in &lines {
+            writeln!(self.output, "{}", sanitize_status_text(line))?;
+        }
+        self.top = start;
+        self.next = self
+            .accessor
+            .next_page_start(start, lines.len().max(1))
+            .await?;
+        if self.next >= self.accessor.file_size() {
+            writeln!(self.output, "(END)")?;
+        }
+        Ok(())
+    }
+
+    /// Start a search; an empty pattern repeats the last one in the given direction
+    async fn search(&mut self, pattern: &str, direction: SearchDirection) -> Result<()> {
+        let pattern = match (pattern.is_empty(), &self.last_search) {
+            (true, Some((last, _))) => last.clone(),
+            (true, None) => {
+                writeln!(self.output, "No previous search")?;
+                return Ok(());
+            }
+            (false, _) => pattern.to_string(),
+        };
+        self.last_search = Some((pattern.clone(), direction));
+        self.find(&pattern, direction).await
+    }
+
+    /// Print the next match in `direction` from the current line with its context
+    async fn find(&mut self, pattern: &str, direction: SearchDirection) -> Result<()> {
+        let found = match direction {
+            SearchDirection::Forward => {
+                let from = self.accessor.next_page_start(self.top, 1).await?;
+                self.engine
+                    .search_from(pattern, from, &self.options, None, None)
+                    .await
+            }
+            SearchDirection::Backward => {
+                self.engine
+                    .search_prev(pattern, self.top, &self.options, None, None)
+                    .await
+            }
+        };
+        let line = match found {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                writeln!(
+                    self.output,
+                    "Pattern not found: {}",
+                    sanitize_status_text(pattern)
+                )?;
+                return Ok(());
+            }
+            Err(err) => {
+                for line in err.to_string().lines() {
+                    writeln!(self.output, "{}", sanitize_status_text(line))?;
+                }
+                return Ok(());
+            }
+        };
+
+        // Like `grep -C`: `>` marks the matching line, context lines are indented
+        let start = self.accessor.prev_page_start(line, MATCH_CONTEXT).await?;
+        let mut before = 0;
+        let mut position = start;
+        while position < line {
+            position = self.accessor.next_page_start(position, 1).await?;
+            before += 1;
+        }
+        let lines = self
+            .accessor
+            .read_from_byte(start, before + 1 + MATCH_CONTEXT)
+            .await?;
+        writeln!(self.output, "--")?;
+        for (index, text) in lines.iter().enumerate() {
+            let marker = if index == before { ">" } else { " " };
+            writeln!(self.output, "{} {}", marker, sanitize_status_text(text))?;
+        }
+
+        self.top = line;
+        self.next = self
+            .accessor
+            .next_page_start(start, lines.len().max(1))
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileAccessorFactory;
+    use crate::search::RipgrepEngine;
+    use std::io::Cursor;
+    use tempfile::NamedTempFile;
+    use tokio::io::BufReader;
+
+    async fn session(content: &str, page_lines: usize, commands: &str) -> String {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file.flush().unwrap();
+        let accessor: Arc<dyn FileAccessor> =
+            Arc::new(FileAccessorFactory::create(file.path()).await.unwrap());
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        let mut output = Vec::new();
+        let mut pager = LinePager::new(
+            accessor,
+            engine,
+            SearchOptions::default(),
+            page_lines,
+            &mut output,
+        );
+        pager
+            .run(BufReader::new(Cursor::new(commands)))
+            .await
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn numbered(count: usize) -> String {
+        (1..=count).map(|i| format!("line {i}\n")).collect()
+    }
+
+    #[tokio::test]
+    async fn test_pages_are_appended_in_order() {
+        let output = session(&numbered(7), 3, "\n \nb\nq\nnot reached\n").await;
+        assert_eq!(
+            output,
+            "line 1\nline 2\nline 3\n: \
+             line 4\nline 5\nline 6\n: \
+             line 7\n(END)\n: \
+             line 4\nline 5\nline 6\n: "
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_prints_match_with_context() {
+        let content = "alpha\nbeta\ngamma\nERROR one\ndelta\nepsilon\nzeta\nERROR two\n";
+        let output = session(content, 2, "/ERROR\nn\nn\n?\nwhat\n").await;
+        let expected = [
+            "alpha\nbeta\n: ",
+            "--\n  beta\n  gamma\n> ERROR one\n  delta\n  epsilon\n: ",
+            "--\n  epsilon\n  zeta\n> ERROR two\n: ",
+            "Pattern not found: ERROR\n: ",
+            // An empty pattern repeats the last one, here backward
+            "--\n  beta\n  gamma\n> ERROR one\n  delta\n  epsilon\n: ",
+            "Unknown command \"what\", h for help\n: ",
+        ];
+        assert_eq!(output, expected.concat());
+    }
+    #[tokio::test]
+    async fn test_escape_sequences_are_not_passed_to_the_terminal() {
+        let content = "plain\n\x1b]0;title\x07\x1b[31mred\x1b[0m\n";
+        let output = session(content, 5, "/\x07none\nq\n").await;
+        assert_eq!(output, "plain\nred\n(END)\n: Pattern not found: ^Gnone\n: ");
+    }
+}
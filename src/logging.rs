@@ -0,0 +1,131 @@
+//! Diagnostic logging policy.
+//!
+//! Log records carry byte offsets, request ids, lengths and timings only. Line contents and
+//! search patterns are the user's data and may be sensitive, so they reach a log only through
+//! `redact`, which writes their length unless `--log-sensitive` was given.
+//!
+//! The regex and grep crates do not follow this policy (they log compiled patterns at debug
+//! level), so their records are dropped unless `--log-sensitive` was given. Other dependencies
+//! log no user data and pass through, so `RUST_LOG=tokio=debug` works as usual.
+//!
+//! `--log-file PATH` appends records to a file, filtered by `RUST_LOG` (default `info`); without
+//! it records go to stderr as usual for `env_logger`.
+
+use crate::error::{Result, RllessError};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static LOG_SENSITIVE: AtomicBool = AtomicBool::new(false);
+
+/// Crates whose records may quote search patterns or file content.
+const SENSITIVE_CRATES: &[&str] = &[
+    "grep_matcher",
+    "grep_regex",
+    "grep_searcher",
+    "regex",
+    "regex_automata",
+    "regex_syntax",
+];
+
+/// Allow `redact` to write user data as is (`--log-sensitive`)
+pub fn set_log_sensitive(enabled: bool) {
+    LOG_SENSITIVE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether user data may appear in log records
+pub fn log_sensitive() -> bool {
+    LOG_SENSITIVE.load(Ordering::Relaxed)
+}
+
+/// User data in a log record, see `redact`.
+#[derive(Debug, Clone, Copy)]
+pub struct Redacted<'a>(&'a str);
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if log_sensitive() {
+            write!(f, "{:?}", self.0)
+        } else {
+            write!(f, "<{} bytes>", self.0.len())
+        }
+    }
+}
+
+/// Wrap user data (a pattern, line text, an error quoting either) for a log record
+///
+/// ```
+/// use rlless::logging::redact;
+///
+/// assert_eq!(format!("pattern={}", redact("password=hunter2")), "pattern=<16 bytes>");
+/// ```
+pub fn redact(text: &str) -> Redacted<'_> {
+    Redacted(text)
+}
+
+/// Logger that passes on only the records this policy allows.
+pub struct PolicyLogger<L> {
+    inner: L,
+}
+
+impl<L: log::Log> PolicyLogger<L> {
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+
+    fn permits(metadata: &log::Metadata) -> bool {
+        if log_sensitive() {
+            return true;
+        }
+        let target = metadata.target();
+        let krate = target.split("::").next().unwrap_or(target);
+        !SENSITIVE_CRATES.contains(&krate)
+    }
+}
+
+impl<L: log::Log> log::Log for PolicyLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        Self::permits(metadata) && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if Self::permits(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the logger: to stderr filtered by `RUST_LOG`, or appending to `log_file` filtered by
+/// `RUST_LOG` with `info` as the default level
+pub fn init(log_file: Option<&Path>) -> Result<()> {
+    let mut builder = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| {
+                    RllessError::file_error(
+                        format!("Failed to open log file {}", path.display()),
+                        e,
+                    )
+                })?;
+            let mut builder =
+                env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+            builder.target(env_logger::Target::Pipe(Box::new(file)));
+            builder
+        }
+        None => env_logger::Builder::from_default_env(),
+    };
+    let logger = builder.build();
+    let max_level = logger.filter();
+    log::set_boxed_logger(Box::new(PolicyLogger::new(logger)))
+        .map_err(|e| RllessError::other(format!("Failed to start logging: {}", e)))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
@@ -3,82 +3,116 @@
 //! A fast, memory-efficient terminal log viewer designed to handle extremely large files.
 
 use anyhow::Result;
-use clap::{Arg, ArgAction, Command};
-use rlless::search::SearchOptions;
-use std::path::PathBuf;
+use clap::ArgMatches;
+use grep_regex::RegexMatcher;
+use rlless::export::{dump_file, export_range, is_plain_output, ByteRange, ExportOptions};
+use rlless::file_handler::{
+    cgroup_memory_pressure, newest_match, FileAccessorFactory, LineTerminator,
+};
+use rlless::inspect::{inspect, DEFAULT_SAMPLE_BYTES};
+use rlless::line_mode::LinePager;
+use rlless::logging::{self, set_log_sensitive};
+use rlless::match_list::{list_matches, ListFormat, ListOptions};
+use rlless::render::service::DEFAULT_RECORD_START;
+use rlless::render::ui::codepoints::DEFAULT_PLACEHOLDER;
+use rlless::search::{PatternLibrary, PatternSet, RipgrepEngine, SearchEngine, SearchOptions};
+use rlless::FileAccessor;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod cli;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging for development
-    env_logger::init();
-
     // Parse command-line arguments
-    let matches = Command::new("rlless")
-        .version(rlless::VERSION)
-        .about("A high-performance terminal log viewer for large files")
-        .long_about(
-            "rlless is a terminal-based log viewer that can handle extremely large files \
-             (40GB+) with SIMD-optimized search and memory-efficient streaming.",
-        )
-        .arg(
-            Arg::new("file")
-                .help("Path to the log file to view")
-                .required(true)
-                .index(1),
-        )
-        .arg(
-            Arg::new("ignore-case")
-                .short('i')
-                .long("ignore-case")
-                .help("Perform case-insensitive searches by default")
-                .action(ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("literal")
-                .long("literal")
-                .help("Treat search patterns as literal strings")
-                .action(ArgAction::SetTrue),
-        )
-        .arg(
-            Arg::new("regex")
-                .long("regex")
-                .help("Treat search patterns as regular expressions (default)")
-                .action(ArgAction::SetTrue)
-                .conflicts_with("literal"),
-        )
-        .arg(
-            Arg::new("word")
-                .long("word")
-                .short('w')
-                .help("Match whole words only")
-                .action(ArgAction::SetTrue),
-        )
-        .get_matches();
-
-    // Get the file path argument
-    let file_path = PathBuf::from(
-        matches
-            .get_one::<String>("file")
-            .expect("file argument is required"),
-    );
+    let matches = cli::command().get_matches();
+
+    // Logs hold offsets, ids and timings; patterns and text only with --log-sensitive
+    set_log_sensitive(matches.get_flag("log-sensitive"));
+    logging::init(matches.get_one::<PathBuf>("log-file").map(PathBuf::as_path))?;
+
+    // A file argument that does not exist may be a `:loc` locator naming a position in a file
+    let locator = match matches.get_one::<String>("file") {
+        Some(arg) if !Path::new(arg).exists() => parse_locator(arg)?,
+        _ => None,
+    };
+
+    // Get the file path argument, or the newest file matching `--latest`; `None` reads stdin
+    let file_path = match (
+        matches.get_one::<String>("latest"),
+        matches.get_one::<String>("file"),
+    ) {
+        (None, Some(_)) if locator.is_some() => {
+            locator.as_ref().map(|locator| locator.path.clone())
+        }
+        (Some(pattern), _) => Some(
+            newest_match(Path::new(pattern))?
+                .ok_or_else(|| anyhow::anyhow!("No file matches {}", pattern))?,
+        ),
+        (None, Some(path)) if path != "-" => Some(PathBuf::from(path)),
+        (None, Some(_)) => None,
+        (None, None) if std::io::stdin().is_terminal() => {
+            anyhow::bail!("Missing file name (or pipe data to rlless)")
+        }
+        (None, None) => None,
+    };
+
+    if let Some(file_path) = &file_path {
+        // Validate file exists
+        if !file_path.exists() {
+            anyhow::bail!("File does not exist: {}", file_path.display());
+        }
 
-    // Validate file exists
-    if !file_path.exists() {
-        anyhow::bail!("File does not exist: {}", file_path.display());
+        if !file_path.is_file() {
+            anyhow::bail!("Path is not a regular file: {}", file_path.display());
+        }
     }
 
-    if !file_path.is_file() {
-        anyhow::bail!("Path is not a regular file: {}", file_path.display());
+    // Headless report: sample the start of the file and describe it
+    if matches.get_flag("inspect") {
+        let Some(file_path) = &file_path else {
+            anyhow::bail!("--inspect needs a file name");
+        };
+        let report = inspect(file_path, DEFAULT_SAMPLE_BYTES).await?;
+        if matches.get_flag("json") {
+            println!("{}", report.to_json());
+        } else {
+            println!("file:          {}", file_path.display());
+            println!("{}", report.to_text());
+        }
+        return Ok(());
+    }
+
+    // Headless export: stream the requested range and skip the TUI entirely
+    if let Some(spec) = matches.get_one::<String>("export-range") {
+        let range = ByteRange::parse(spec)?;
+        let options = ExportOptions {
+            normalize_newlines: matches.get_flag("normalize-newlines"),
+        };
+        let accessor = open_whole(file_path.as_deref(), &matches).await?;
+        let stdout = std::io::stdout();
+        let mut sink = std::io::BufWriter::new(stdout.lock());
+        export_range(accessor.as_ref(), range, options, &mut sink).await?;
+        sink.flush()?;
+        return Ok(());
     }
 
     // Initialize the Application and start the interactive event loop
-    use rlless::render::ui::TerminalUI;
+    use rlless::input::{Keymap, SharedKeymap};
+    use rlless::render::ui::{
+        ChromeMode, InvalidKeyFeedback, JumpTarget, PositionStyle, TerminalUI,
+    };
+    use rlless::settings::{Settings, StartupValues};
     use rlless::Application;
 
     let mut search_options = SearchOptions::default();
     if matches.get_flag("ignore-case") {
         search_options.case_sensitive = false;
     }
+    if matches.get_flag("smart-case") {
+        search_options.smart_case = true;
+    }
     if matches.get_flag("literal") {
         search_options.regex_mode = false;
     }
@@ -88,20 +122,376 @@ async fn main() -> Result<()> {
     if matches.get_flag("word") {
         search_options.whole_word = true;
     }
+    if matches.get_flag("multiline") {
+        search_options.multiline = true;
+    }
 
-    let ui_renderer = Box::new(TerminalUI::new()?);
-    let mut app = Application::new(&file_path, ui_renderer, search_options).await?;
+    // Headless match listing: stream one record per match and exit with 1 when nothing matched
+    if let Some(pattern) = matches.get_one::<String>("list-matches") {
+        let options = ListOptions {
+            max_matches: matches.get_one::<u64>("max-matches").copied(),
+            format: if matches.get_flag("json") {
+                ListFormat::Json
+            } else {
+                ListFormat::Tsv
+            },
+        };
+        let accessor = open_whole(file_path.as_deref(), &matches).await?;
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        // Compile up front so an invalid pattern is an error rather than "no matches"
+        engine.get_line_matches(pattern, "", &search_options)?;
+        let matcher = |line: &str| {
+            engine
+                .get_line_matches(pattern, line, &search_options)
+                .unwrap_or_default()
+        };
+        let stdout = std::io::stdout();
+        let mut sink = std::io::BufWriter::new(stdout.lock());
+        let found = list_matches(accessor.as_ref(), &matcher, options, &mut sink).await?;
+        sink.flush()?;
+        std::process::exit(if found > 0 { 0 } else { 1 });
+    }
+
+    // Redirected output: behave like `cat` rather than drawing the TUI into a file or pipe
+    if is_plain_output(&std::io::stdout()) {
+        let accessor = open_whole(file_path.as_deref(), &matches).await?;
+        let stdout = std::io::stdout();
+        let mut sink = std::io::BufWriter::new(stdout.lock());
+        dump_file(accessor.as_ref(), &mut sink).await?;
+        sink.flush()?;
+        return Ok(());
+    }
+
+    let terminator = line_terminator(&matches);
+
+    // Accessible mode: append pages and matches to the scrollback instead of drawing the TUI
+    if matches.get_flag("accessible") {
+        // Commands are read from stdin, so it cannot carry the data as well
+        let Some(file_path) = &file_path else {
+            anyhow::bail!("--accessible needs a file name");
+        };
+        let accessor: Arc<dyn FileAccessor> = Arc::new(
+            FileAccessorFactory::create(file_path)
+                .await?
+                .with_line_terminator(terminator),
+        );
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        // Leave a row for the prompt
+        let page_lines = ratatui::crossterm::terminal::size()
+            .map(|(_, rows)| usize::from(rows).saturating_sub(1))
+            .unwrap_or(23);
+        let stdout = std::io::stdout();
+        let mut pager = LinePager::new(accessor, engine, search_options, page_lines, stdout.lock());
+        // Tokio's stdin reads on a blocking thread, keeping the runtime free for the worker
+        pager
+            .run(tokio::io::BufReader::new(tokio::io::stdin()))
+            .await?;
+        return Ok(());
+    }
+
+    // Spill when asked to, or automatically when the cgroup is close to its memory limit
+    let low_memory = matches.get_flag("low-memory") || cgroup_memory_pressure();
+
+    let pattern_set = match matches.get_one::<PathBuf>("patterns-from") {
+        Some(path) => Some(PatternSet::load(path).await?),
+        None => None,
+    };
+    let keymap = match matches.get_one::<PathBuf>("keymap") {
+        Some(path) => Keymap::load(path).await?,
+        None => Keymap::default(),
+    };
+
+    let ui_renderer = Box::new(TerminalUI::new()?.with_window_title(matches.get_flag("title")));
+    let position_style = matches
+        .get_one::<String>("position")
+        .and_then(|name| PositionStyle::from_name(name))
+        .unwrap_or_default();
+    let invalid_key_feedback = matches
+        .get_one::<String>("invalid-key")
+        .and_then(|name| InvalidKeyFeedback::from_name(name))
+        .unwrap_or_default();
+    let jump_target = matches
+        .get_one::<JumpTarget>("jump-target")
+        .copied()
+        .unwrap_or_default();
+    let history_exclude = matches
+        .get_many::<String>("history-exclude")
+        .into_iter()
+        .flatten()
+        .map(|pattern| {
+            RegexMatcher::new(pattern)
+                .map_err(|err| anyhow::anyhow!("Invalid --history-exclude pattern: {}", err))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let frequent_match_limit = matches
+        .get_one::<u64>("frequent-match-limit")
+        .copied()
+        .filter(|&limit| limit > 0);
+    let record_start = matches
+        .get_one::<String>("record-start")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_RECORD_START);
+    RegexMatcher::new(record_start)
+        .map_err(|err| anyhow::anyhow!("Invalid --record-start pattern: {}", err))?;
+    let mut patterns = PatternLibrary::default();
+    for definition in matches.get_many::<String>("pattern").into_iter().flatten() {
+        patterns
+            .define(definition)
+            .map_err(|err| anyhow::anyhow!("Invalid --pattern: {}", err))?;
+    }
+
+    let cache_budget_mib = matches
+        .get_one::<u64>("cache-budget")
+        .copied()
+        .unwrap_or(32);
+    let settings = Settings::from_args(
+        &matches,
+        StartupValues {
+            search_options: &search_options,
+            position: position_style.name(),
+            invalid_key: invalid_key_feedback.name(),
+            jump_target,
+            frequent_match_limit,
+            record_start,
+            patterns: &patterns,
+            cache_budget_mib,
+            low_memory,
+            terminator,
+        },
+    );
+
+    let app = match &file_path {
+        Some(file_path) => {
+            Application::new(
+                file_path,
+                ui_renderer,
+                search_options,
+                low_memory,
+                terminator,
+            )
+            .await?
+        }
+        None => Application::from_stdin(ui_renderer, search_options, low_memory, terminator),
+    };
+    let mut app = app
+        .with_position_style(position_style)
+        .with_chrome(if matches.get_flag("presentation") {
+            ChromeMode::Presentation
+        } else {
+            ChromeMode::Full
+        })
+        .with_show_active_pattern(matches.get_flag("show-active-pattern"))
+        .with_follow(matches.get_flag("follow"))
+        .with_precise_scroll(matches.get_flag("precise-scroll"))
+        .with_line_numbers(matches.get_flag("line-numbers"))
+        .with_wrap(matches.get_flag("wrap"))
+        .with_match_bell(!matches.get_flag("no-bell"))
+        .with_invalid_key_feedback(invalid_key_feedback)
+        .with_jump_target(jump_target)
+        .with_growth_hint(!matches.get_flag("no-growth-hint"))
+        .with_incremental_search(!matches.get_flag("no-incsearch"))
+        .with_state_dir(rlless::bookmarks::state_dir())
+        .with_placeholder(placeholder(&matches))
+        .with_history_exclude(history_exclude)
+        .with_keymap(SharedKeymap::new(keymap))
+        .with_frequent_match_limit(frequent_match_limit)
+        .with_record_start(record_start)
+        .with_pattern_library(patterns)
+        .with_cache_budget(cache_budget_mib as usize * 1024 * 1024)
+        .with_settings(settings);
+    if let Some(pattern) = matches.get_one::<String>("latest") {
+        app = app.with_latest(Path::new(pattern));
+    }
+    if let Some(set) = pattern_set {
+        app = app.with_pattern_set(set);
+    }
+    if let Some(locator) = locator {
+        app = app.with_start_byte(locator.byte);
+        if let Some(pattern) = &locator.pattern {
+            app = app.with_search(pattern, locator.options);
+        }
+    }
 
     app.run().await?;
 
     Ok(())
 }
 
+/// Placeholder for unreliable codepoints from `--placeholder` and `--no-placeholder`
+fn placeholder(matches: &ArgMatches) -> Option<char> {
+    if matches.get_flag("no-placeholder") {
+        return None;
+    }
+    Some(
+        matches
+            .get_one::<char>("placeholder")
+            .copied()
+            .unwrap_or(DEFAULT_PLACEHOLDER),
+    )
+}
+
+/// Position named by a `:loc` locator
+#[derive(Debug, PartialEq)]
+struct Locator {
+    path: PathBuf,
+    byte: u64,
+    pattern: Option<String>,
+    options: SearchOptions,
+}
+
+/// Split a locator (`app.log@byte=N&pattern=P&flags=i,re,w`) into its parts
+///
+/// Returns `None` when `arg` has no locator suffix, so it is taken as a plain file name. See
+/// `rlless::render::service::locator` for the syntax.
+fn parse_locator(arg: &str) -> Result<Option<Locator>> {
+    let Some((path, fields)) = arg.rsplit_once('@') else {
+        return Ok(None);
+    };
+    if path.is_empty() || !fields.starts_with("byte=") {
+        return Ok(None);
+    }
+
+    let mut locator = Locator {
+        path: PathBuf::from(path),
+        byte: 0,
+        pattern: None,
+        options: SearchOptions::default(),
+    };
+    for field in fields.split('&') {
+        let (key, value) = field
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid locator field: {}", field))?;
+        match key {
+            "byte" => {
+                locator.byte = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid byte offset in locator: {}", value))?
+            }
+            "pattern" => locator.pattern = Some(percent_decode(value)?),
+            "flags" => locator.options = SearchOptions::parse_summary(value)?,
+            other => anyhow::bail!("Unknown locator field: {}", other),
+        }
+    }
+    Ok(Some(locator))
+}
+
+/// Undo the `%XX` escapes of a locator pattern
+fn percent_decode(text: &str) -> Result<String> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte != b'%' {
+            decoded.push(byte);
+            rest = tail;
+            continue;
+        }
+        let escaped = tail
+            .get(..2)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid escape in locator pattern: {}", text))?;
+        decoded.push(escaped);
+        rest = &tail[2..];
+    }
+    String::from_utf8(decoded)
+        .map_err(|_| anyhow::anyhow!("Locator pattern is not valid UTF-8: {}", text))
+}
+
+/// The record separator `--null` asks for
+fn line_terminator(matches: &ArgMatches) -> LineTerminator {
+    if matches.get_flag("null") {
+        LineTerminator::Nul
+    } else {
+        LineTerminator::Newline
+    }
+}
+
+/// Accessor over the whole input, reading piped input to its end first
+async fn open_whole(
+    file_path: Option<&Path>,
+    matches: &ArgMatches,
+) -> Result<Arc<dyn FileAccessor>> {
+    let terminator = line_terminator(matches);
+    Ok(match file_path {
+        Some(file_path) => Arc::new(
+            FileAccessorFactory::create(file_path)
+                .await?
+                .with_line_terminator(terminator),
+        ),
+        None => {
+            let (accessor, input) =
+                FileAccessorFactory::create_from_stdin(matches.get_flag("low-memory"), terminator);
+            input.finished().await?;
+            accessor
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use rlless::render::protocol::SearchHighlightSpec;
+    use rlless::render::service::locator;
+    use rlless::render::ui::ViewState;
+    use rlless::search::PatternModifiers;
+
     #[test]
     fn test_version_constant() {
         // Ensure version is accessible
         assert!(!rlless::VERSION.is_empty());
     }
+
+    #[test]
+    fn generated_locators_parse_back_to_the_same_position() {
+        let mut view_state = ViewState::new("/srv/logs/app@blue.log", 80, 24);
+        view_state.viewport_top_byte = 123_456_789;
+        let searches = [
+            None,
+            Some(SearchOptions::default()),
+            Some(SearchOptions {
+                case_sensitive: false,
+                regex_mode: false,
+                whole_word: true,
+                ..SearchOptions::default()
+            }),
+        ];
+        for options in searches {
+            let search = options.map(|options| SearchHighlightSpec {
+                pattern: Arc::from("timeout after 30s & user=\"bob@example\" 100% ✗"),
+                options,
+                modifiers: PatternModifiers::default(),
+            });
+            let parsed = parse_locator(&locator(&view_state, search.as_ref()))
+                .unwrap()
+                .expect("a locator");
+            assert_eq!(parsed.path, view_state.file_path);
+            assert_eq!(parsed.byte, 123_456_789);
+            assert_eq!(
+                parsed.pattern.as_deref(),
+                search.as_ref().map(|search| &*search.pattern)
+            );
+            if let Some(search) = search {
+                assert_eq!(parsed.options, search.options);
+            }
+        }
+    }
+
+    #[test]
+    fn plain_file_names_and_broken_locators() {
+        assert_eq!(parse_locator("app.log").unwrap(), None);
+        assert_eq!(parse_locator("user@host.log").unwrap(), None);
+        assert!(parse_locator("app.log@byte=12x").is_err());
+        assert!(parse_locator("app.log@byte=1&pattern=%G1").is_err());
+        assert!(parse_locator("app.log@byte=1&flags=q").is_err());
+        assert!(parse_locator("app.log@byte=1&flags=i,q").is_err());
+        assert!(parse_locator("app.log@byte=1&flags=iw").is_err());
+    }
+
+    #[test]
+    fn list_matches_refuses_multiline() {
+        let parse = |args: &[&str]| cli::command().try_get_matches_from(args.iter().copied());
+        let error = parse(&["rlless", "-U", "--list-matches", "A\\nframe", "f"]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(parse(&["rlless", "--list-matches", "frame", "f"]).is_ok());
+    }
 }
@@ -0,0 +1,417 @@
+//! Headless match listing for other tools.
+//!
+//! `--list-matches` streams every match of a pattern to stdout as one record per line instead of
+//! starting the TUI. The file is read in bounded chunks and each record is written as soon as its
+//! line is scanned, so result sets far larger than memory can be piped into other programs.
+//!
+//! Positions are absolute byte offsets into the file and line numbers count lines from 1, split
+//! at the accessor's line terminator, so with `--null` they count NUL-separated records. Lines
+//! longer than `SEGMENT_BYTES` are scanned and reported a segment at a time, like the viewer shows
+//! them, so memory stays bounded and a record never carries a huge line. A trailing `\r` is not
+//! part of the line text, and invalid UTF-8 is shown as U+FFFD.
+
+use crate::error::Result;
+use crate::file_handler::{segment_cut, FileAccessor, SEGMENT_BYTES};
+use crate::search::filter::LineMatcher;
+use std::borrow::Cow;
+use std::io::Write;
+
+/// Number of bytes read from the accessor per scan step.
+const LIST_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Record layout written for each match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListFormat {
+    /// `start_byte\tend_byte\tline_number\tline_text`
+    #[default]
+    Tsv,
+    /// One JSON object per line, see `MatchRecord::to_json`
+    Json,
+}
+
+/// Options for `list_matches`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListOptions {
+    /// Stop after this many matches
+    pub max_matches: Option<u64>,
+    pub format: ListFormat,
+}
+
+/// One match as written to the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchRecord<'a> {
+    /// Byte offset of the first matched byte
+    pub start: u64,
+    /// Byte offset just past the match
+    pub end: u64,
+    /// 1-based number of the line holding the match
+    pub line_number: u64,
+    /// The line without its terminator, or the segment holding the match in a long line
+    pub text: &'a str,
+}
+
+impl MatchRecord<'_> {
+    /// Tab-separated record; the line text is the last field, so tabs inside it are kept as is
+    pub fn to_tsv(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            self.start, self.end, self.line_number, self.text
+        )
+    }
+
+    /// JSON record with the fixed keys `start`, `end`, `line` and `text`, in that order
+    ///
+    /// ```
+    /// use rlless::match_list::MatchRecord;
+    ///
+    /// let record = MatchRecord {
+    ///     start: 16,
+    ///     end: 21,
+    ///     line_number: 2,
+    ///     text: "ERROR \"disk\"\tfull",
+    /// };
+    /// assert_eq!(
+    ///     record.to_json(),
+    ///     r#"{"start":16,"end":21,"line":2,"text":"ERROR \"disk\"\tfull"}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"start":{},"end":{},"line":{},"text":"{}"}}"#,
+            self.start,
+            self.end,
+            self.line_number,
+            json_escape(self.text)
+        )
+    }
+}
+
+/// Write a record for every match of `matcher` in `accessor` to `sink`.
+///
+/// Returns the number of matches written.
+pub async fn list_matches<W: Write>(
+    accessor: &dyn FileAccessor,
+    matcher: LineMatcher<'_>,
+    options: ListOptions,
+    sink: &mut W,
+) -> Result<u64> {
+    list_matches_chunked(accessor, matcher, options, sink, LIST_CHUNK_SIZE).await
+}
+
+async fn list_matches_chunked<W: Write>(
+    accessor: &dyn FileAccessor,
+    matcher: LineMatcher<'_>,
+    options: ListOptions,
+    sink: &mut W,
+    chunk_size: usize,
+) -> Result<u64> {
+    let file_size = accessor.file_size();
+    let sep = accessor.line_terminator().byte();
+    let mut lister = Lister {
+        matcher,
+        options,
+        sink,
+        written: 0,
+        line_number: 0,
+        in_line: false,
+    };
+    // Bytes of a line not yet terminated, starting at `pending_start`; at most a segment
+    // plus a chunk
+    let mut pending: Vec<u8> = Vec::new();
+    let mut pending_start = 0u64;
+    let mut position = 0u64;
+
+    while position < file_size && !lister.done() {
+        let chunk = accessor.read_bytes(position, chunk_size).await?;
+        if chunk.is_empty() {
+            break;
+        }
+        position += chunk.len() as u64;
+        pending.extend_from_slice(&chunk);
+
+        let mut consumed = 0;
+        while !lister.done() {
+            let rest = &pending[consumed..];
+            match memchr::memchr(sep, rest) {
+                Some(end) if end <= SEGMENT_BYTES => {
+                    lister.line(pending_start + consumed as u64, &rest[..end], true)?;
+                    consumed += end + 1;
+                }
+                // The line runs past a segment: list one and keep going in the same line
+                _ if rest.len() > SEGMENT_BYTES => {
+                    let cut = segment_cut(&pending, consumed);
+                    lister.line(
+                        pending_start + consumed as u64,
+                        &pending[consumed..cut],
+                        false,
+                    )?;
+                    consumed = cut;
+                }
+                _ => break,
+            }
+        }
+        pending.drain(..consumed);
+        pending_start += consumed as u64;
+    }
+
+    if !pending.is_empty() && !lister.done() {
+        lister.line(pending_start, &pending, true)?;
+    }
+    Ok(lister.written)
+}
+
+struct Lister<'a, 'm, W: Write> {
+    matcher: LineMatcher<'m>,
+    options: ListOptions,
+    sink: &'a mut W,
+    written: u64,
+    line_number: u64,
+    // The last part listed was a segment of a line that continues
+    in_line: bool,
+}
+
+impl<W: Write> Lister<'_, '_, W> {
+    fn done(&self) -> bool {
+        self.options
+            .max_matches
+            .is_some_and(|max| self.written >= max)
+    }
+
+    /// Write the matches in the line, or segment of one, starting at byte `start`
+    ///
+    /// `ends_line` is false for a segment that the rest of its line follows.
+    fn line(&mut self, start: u64, bytes: &[u8], ends_line: bool) -> Result<()> {
+        if !self.in_line {
+            self.line_number += 1;
+        }
+        self.in_line = !ends_line;
+        let bytes = match ends_line {
+            true => bytes.strip_suffix(b"\r").unwrap_or(bytes),
+            false => bytes,
+        };
+        let text = LineText::decode(bytes);
+        for (match_start, match_end) in (self.matcher)(&text.text) {
+            if self.done() {
+                break;
+            }
+            let record = MatchRecord {
+                start: start + text.byte_offset(match_start) as u64,
+                end: start + text.byte_offset(match_end) as u64,
+                line_number: self.line_number,
+                text: &text.text,
+            };
+            let encoded = match self.options.format {
+                ListFormat::Tsv => record.to_tsv(),
+                ListFormat::Json => record.to_json(),
+            };
+            writeln!(self.sink, "{}", encoded)?;
+            self.written += 1;
+        }
+        Ok(())
+    }
+}
+
+/// A line decoded for matching, mapping offsets in the text back to offsets in the line
+///
+/// Invalid UTF-8 is replaced by U+FFFD, whose three bytes rarely match the bytes it stands for,
+/// so offsets after a replacement are shifted.
+struct LineText<'a> {
+    text: Cow<'a, str>,
+    // `(text offset, line offset)` at each replacement and just after it
+    anchors: Vec<(usize, usize)>,
+}
+
+impl<'a> LineText<'a> {
+    fn decode(bytes: &'a [u8]) -> Self {
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return Self {
+                text: Cow::Borrowed(text),
+                anchors: Vec::new(),
+            };
+        }
+        let mut text = String::with_capacity(bytes.len() + 8);
+        let mut anchors = Vec::new();
+        let mut position = 0;
+        while position < bytes.len() {
+            let rest = &bytes[position..];
+            let (valid, invalid) = match std::str::from_utf8(rest) {
+                Ok(valid) => (valid, 0),
+                Err(error) => {
+                    let valid = error.valid_up_to();
+                    let invalid = error.error_len().unwrap_or(rest.len() - valid);
+                    let prefix = std::str::from_utf8(&rest[..valid]).unwrap_or_default();
+                    (prefix, invalid)
+                }
+            };
+            text.push_str(valid);
+            position += valid.len();
+            if invalid > 0 {
+                anchors.push((text.len(), position));
+                text.push('\u{FFFD}');
+                position += invalid;
+                anchors.push((text.len(), position));
+            }
+        }
+        Self {
+            text: Cow::Owned(text),
+            anchors,
+        }
+    }
+
+    /// Offset in the line of the character at `offset` in the text
+    fn byte_offset(&self, offset: usize) -> usize {
+        match self.anchors.partition_point(|&(text, _)| text <= offset) {
+            0 => offset,
+            index => {
+                let (text, line) = self.anchors[index - 1];
+                line + (offset - text)
+            }
+        }
+    }
+}
+
+/// Escape `text` for use inside a JSON string literal
+pub(crate) fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if u32::from(ch) < 0x20 => escaped.push_str(&format!("\\u{:04x}", u32::from(ch))),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::{FileAccessorFactory, LineTerminator};
+    use tempfile::NamedTempFile;
+
+    fn find_err(line: &str) -> Vec<(usize, usize)> {
+        line.match_indices("err")
+            .map(|(i, m)| (i, i + m.len()))
+            .collect()
+    }
+
+    async fn list(content: &[u8], options: ListOptions, chunk_size: usize) -> (u64, String) {
+        list_split(content, LineTerminator::Newline, options, chunk_size).await
+    }
+
+    async fn list_split(
+        content: &[u8],
+        terminator: LineTerminator,
+        options: ListOptions,
+        chunk_size: usize,
+    ) -> (u64, String) {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        file.flush().unwrap();
+        let accessor = FileAccessorFactory::create(file.path())
+            .await
+            .unwrap()
+            .with_line_terminator(terminator);
+        let mut out = Vec::new();
+        let count = list_matches_chunked(&accessor, &find_err, options, &mut out, chunk_size)
+            .await
+            .unwrap();
+        (count, String::from_utf8(out).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_lists_every_match_with_absolute_offsets() {
+        let content = b"ok\nerr one\r\nfine\nerr err\nlast err";
+        for chunk_size in [1, 5, LIST_CHUNK_SIZE] {
+            let (count, out) = list(content, ListOptions::default(), chunk_size).await;
+            assert_eq!(count, 4);
+            assert_eq!(
+                out,
+                "3\t6\t2\terr one\n\
+                 17\t20\t4\terr err\n\
+                 21\t24\t4\terr err\n\
+                 30\t33\t5\tlast err\n"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nul_separated_records_are_counted_as_lines() {
+        let content = b"alpha\0beta err\0gamma\0err\nstill four\0";
+        for chunk_size in [1, 5, LIST_CHUNK_SIZE] {
+            let (count, out) = list_split(
+                content,
+                LineTerminator::Nul,
+                ListOptions::default(),
+                chunk_size,
+            )
+            .await;
+            assert_eq!(count, 2);
+            assert_eq!(out, "11\t14\t2\tbeta err\n21\t24\t4\terr\nstill four\n");
+            assert!(!out.contains('\0'));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_matches_and_json_output() {
+        let options = ListOptions {
+            max_matches: Some(2),
+            format: ListFormat::Json,
+        };
+        let (count, out) = list(b"err \"a\"\nerr err\n", options, 4).await;
+        assert_eq!(count, 2);
+        assert_eq!(
+            out,
+            "{\"start\":0,\"end\":3,\"line\":1,\"text\":\"err \\\"a\\\"\"}\n\
+             {\"start\":8,\"end\":11,\"line\":2,\"text\":\"err err\"}\n"
+        );
+
+        let (count, out) = list(b"nothing here\n", ListOptions::default(), 4).await;
+        assert_eq!((count, out.as_str()), (0, ""));
+    }
+
+    #[tokio::test]
+    async fn test_offsets_after_invalid_utf8_are_file_offsets() {
+        // Each invalid byte becomes a three-byte U+FFFD in the text
+        let (count, out) = list(b"\xff\xfe err\n", ListOptions::default(), 4).await;
+        assert_eq!(count, 1);
+        assert_eq!(out, "3\t6\t1\t\u{FFFD}\u{FFFD} err\n");
+    }
+
+    #[tokio::test]
+    async fn test_long_line_is_listed_a_segment_at_a_time() {
+        let mut content = vec![b'a'; SEGMENT_BYTES + 10];
+        content[5..8].copy_from_slice(b"err");
+        content[SEGMENT_BYTES + 2..SEGMENT_BYTES + 5].copy_from_slice(b"err");
+        content.extend_from_slice(b"\nerr\n");
+        let (count, out) = list(&content, ListOptions::default(), 4096).await;
+        assert_eq!(count, 3);
+        // (start, end, line number, text length) for each record
+        let records: Vec<(usize, usize, usize, usize)> = out
+            .lines()
+            .map(|record| {
+                let fields: Vec<&str> = record.splitn(4, '\t').collect();
+                let number = |field: &str| field.parse::<usize>().unwrap();
+                (
+                    number(fields[0]),
+                    number(fields[1]),
+                    number(fields[2]),
+                    fields[3].len(),
+                )
+            })
+            .collect();
+        let seg = SEGMENT_BYTES;
+        assert_eq!(
+            records,
+            vec![
+                (5, 8, 1, seg),
+                (seg + 2, seg + 5, 1, 10),
+                (seg + 11, seg + 14, 2, 3)
+            ]
+        );
+    }
+}
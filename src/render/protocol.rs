@@ -1,14 +1,21 @@
 //! Protocol definitions shared between the render coordinator and the search worker.
 
 use crate::error::RllessError;
+use crate::file_handler::FileMeta;
 use crate::input::SearchDirection;
-use crate::search::SearchOptions;
-use std::sync::atomic::AtomicBool;
+use crate::search::timestamps::TimeTarget;
+use crate::search::{PatternModifiers, SearchOptions};
+use crate::util::CancellationToken;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Identifier attached to cross-thread requests so responses can be correlated.
 pub type RequestId = u64;
 
+/// Fewest content rows a viewport ever has; smaller `page_lines` requests are raised to this.
+pub const MIN_PAGE_LINES: usize = 1;
+
 /// How the viewport worker should interpret a navigation intent.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ViewportRequest {
@@ -20,18 +27,73 @@ pub enum ViewportRequest {
     EndOfFile,
 }
 
+/// Line-based location of a page's top row, for the `lines` position style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinePosition {
+    /// 1-based line number of the top row
+    pub top: u64,
+    /// Lines in the whole file, None until counting has reached its end
+    pub total: Option<u64>,
+}
+
 /// Active search context used to compute highlights inside the viewport worker.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SearchHighlightSpec {
     pub pattern: Arc<str>,
+    /// Options the search runs with, inline modifiers applied
     pub options: SearchOptions,
+    /// Inline modifiers the pattern started with, kept so a change to the global options
+    /// leaves them in force
+    pub modifiers: PatternModifiers,
+}
+
+/// Origin of a highlighted span, used to pick its style when rendering.
+///
+/// Variants are listed by precedence: where spans overlap, the earlier variant is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightKind {
+    /// Match of the active search pattern.
+    Search,
+    /// Match of the pattern kept most recently with `:keep`.
+    Kept1,
+    /// Match of the pattern kept before that one.
+    Kept2,
+    /// Match of the active filter pattern (drawn in a secondary style).
+    Filter,
+}
+
+/// Most patterns `:keep` holds highlighted besides the active search.
+pub const MAX_KEPT_PATTERNS: usize = 2;
+
+impl HighlightKind {
+    /// Kind of the matches of kept pattern `index`, counting from the most recent
+    pub fn kept(index: usize) -> Option<Self> {
+        [HighlightKind::Kept1, HighlightKind::Kept2]
+            .get(index)
+            .copied()
+    }
 }
 
+/// Highlighted byte range `[start, end)` within a line.
+pub type HighlightSpan = (usize, usize, HighlightKind);
+
 /// Directional traversal for repeating a search.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MatchTraversal {
     Next,
     Previous,
+    /// The way `Next` goes, to the nearest row the pattern does not match (ESC-n)
+    NextNonMatch,
+    /// The way `Previous` goes, to the nearest row the pattern does not match (ESC-N)
+    PreviousNonMatch,
+}
+
+impl MatchTraversal {
+    /// Whether this looks for a row the pattern misses, which is a jump that leaves the current
+    /// match alone and is answered with `PositionFound`
+    pub fn is_non_match(self) -> bool {
+        matches!(self, Self::NextNonMatch | Self::PreviousNonMatch)
+    }
 }
 
 /// Canonical search state shared with the background worker so it can
@@ -44,14 +106,71 @@ pub struct SearchContext {
     pub last_match_byte: Option<u64>,
 }
 
+/// New search held back by the frequency check, sent back for the user to confirm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequentMatches {
+    pub pattern: Arc<str>,
+    pub direction: SearchDirection,
+    pub options: SearchOptions,
+    pub skip_origin_row: bool,
+    /// Estimated matches per megabyte near the search origin
+    pub matches_per_mb: u64,
+}
+
+/// Bytes a search read and how long it took, so the status line can note a slow search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanStats {
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+/// Long-running worker operation that reports its progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProgressOperation {
+    Search,
+    Count,
+    Decompress,
+    Export,
+}
+
+impl ProgressOperation {
+    /// Verb shown next to the percentage on the status line
+    pub fn label(self) -> &'static str {
+        match self {
+            ProgressOperation::Search => "searching",
+            ProgressOperation::Count => "counting",
+            ProgressOperation::Decompress => "decompressing",
+            ProgressOperation::Export => "exporting",
+        }
+    }
+}
+
+/// Where `:save` and `:pipe` write the file's content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportTarget {
+    /// A file that must not exist yet
+    File(PathBuf),
+    /// Shell command reading the content on its standard input
+    Command(String),
+}
+
 /// Commands sent from the render coordinator to the search/paging worker.
 #[derive(Debug, Clone)]
 pub enum SearchCommand {
     LoadViewport {
         request_id: RequestId,
         top: ViewportRequest,
+        /// Rows to load, at least `MIN_PAGE_LINES`
         page_lines: usize,
         highlights: Option<Arc<SearchHighlightSpec>>,
+        /// When set, only lines matching this spec are shown and its matches are highlighted
+        /// in the secondary style.
+        filter: Option<Arc<SearchHighlightSpec>>,
+        /// Start of the line the last search jumped to; the page says which of its rows that is.
+        current_match: Option<u64>,
+        /// Set once a newer viewport load supersedes this one; checked while a filter scans
+        /// for matching lines.
+        cancel_flag: CancellationToken,
     },
     ExecuteSearch {
         request_id: RequestId,
@@ -59,22 +178,194 @@ pub enum SearchCommand {
         direction: SearchDirection,
         options: SearchOptions,
         origin_byte: u64,
-        // Carry the cancellation flag with the work item so the worker can observe it while
-        // running; a standalone cancel command would queue behind the job we want to abort.
-        cancel_flag: Arc<AtomicBool>,
+        /// Rows below `origin_byte` of the line the search counts from, the row `-j` puts matches
+        /// on; 0 is the row at `origin_byte`
+        origin_row: usize,
+        /// Start at the row after that line (before it, searching backward), as less does for a
+        /// typed search and for `n`, so the match already shown is not found again
+        skip_origin_row: bool,
+        /// Ask for confirmation instead of searching when a sample of the file holds more than
+        /// this many matches per megabyte; `None` skips the check.
+        frequency_limit: Option<u64>,
+        /// Only show where the pattern typed so far matches: the worker leaves its search context
+        /// alone, and errors come back as the completion's message.
+        preview: bool,
+        // Carry the cancellation token with the work item so the worker can observe it while
+        // running; `Cancel` would queue behind the job we want to abort.
+        cancel_flag: CancellationToken,
     },
     NavigateMatch {
         request_id: RequestId,
         traversal: MatchTraversal,
         current_top: u64,
+        /// Rows below `current_top` of the line `n` counts from, as for `ExecuteSearch`
+        origin_row: usize,
+        /// Matches to move, at least one (`3n` moves three); a non-match traversal moves once
+        count: u64,
         // Same rationale as above: piggyback the token on the specific request.
-        cancel_flag: Arc<AtomicBool>,
+        cancel_flag: CancellationToken,
+    },
+    /// Jump past the run of lines identical to the one at `current_top`.
+    SkipDuplicates {
+        request_id: RequestId,
+        direction: SearchDirection,
+        current_top: u64,
+        cancel_flag: CancellationToken,
+    },
+    /// Jump to the nearest line matching the `record_start` regex: after the row at `current_top`
+    /// going forward, before it going backward.
+    NavigateRecord {
+        request_id: RequestId,
+        direction: SearchDirection,
+        record_start: Arc<str>,
+        current_top: u64,
+        cancel_flag: CancellationToken,
+    },
+    /// Jump to the first record, a line matching `record_start`, stamped at or after `target`.
+    /// A target without a date is on the day of the first record at or after `current_top`.
+    NavigateTime {
+        request_id: RequestId,
+        target: TimeTarget,
+        record_start: Arc<str>,
+        current_top: u64,
+        cancel_flag: CancellationToken,
+    },
+    /// Fetch the whole logical line containing the row that starts at `row_start`.
+    PeekLine {
+        request_id: RequestId,
+        row_start: u64,
+        highlights: Option<Arc<SearchHighlightSpec>>,
+        filter: Option<Arc<SearchHighlightSpec>>,
+    },
+    /// List up to `limit` lines matching `search`, from the first one starting at or after
+    /// `from`, for the match list (`M`); answered with `MatchBatch` responses, the last of them
+    /// saying where to go on.
+    CollectMatches {
+        request_id: RequestId,
+        search: Arc<SearchHighlightSpec>,
+        from: u64,
+        limit: usize,
+        cancel_flag: CancellationToken,
+    },
+    /// Write the whole file to `target` in the background, reporting `Progress` as it goes;
+    /// answered with `ExportFinished`.
+    Export {
+        request_id: RequestId,
+        target: ExportTarget,
+        cancel_flag: CancellationToken,
+    },
+    /// Cancel the operation answering `request_id` that is still running in the background,
+    /// such as an export; answered by that operation, and not at all when nothing runs for it.
+    /// The coordinator cancels the operation's token as well, which also reaches a command
+    /// still waiting in the queue.
+    Cancel {
+        request_id: RequestId,
+    },
+    /// Report the file's metadata for the info display.
+    DescribeFile,
+    /// Pick up data appended to the file since it was last checked; answered with `FileGrew`
+    /// only when there was some.
+    CheckFileGrowth,
+    /// Switch to the newest file matching `pattern` (`--latest`) when it is not the one being
+    /// read; answered with `FileSwitched` only when it switched.
+    SwitchToLatest {
+        pattern: PathBuf,
     },
+    /// Report the line number of the top row with every viewport (`-N`).
+    ShowLineNumbers(bool),
+    /// Report the line position of the top row with every viewport (`--position lines`).
+    ShowLinePosition(bool),
+    /// Highlight the active search in pages and the line overlay that bring no highlights of
+    /// their own; ESC-u turns this off without ending the search.
+    ShowHighlights(bool),
+    /// Highlight these patterns as well as the search, each in a style of its own, most recently
+    /// kept first and at most `MAX_KEPT_PATTERNS` of them (`:keep`); hidden along with the
+    /// search's highlights.
+    KeepPatterns(Vec<Arc<SearchHighlightSpec>>),
     UpdateSearchContext(SearchContext),
     ClearSearchContext,
     Shutdown,
 }
 
+impl SearchCommand {
+    /// Short name of the command for log records
+    pub fn name(&self) -> &'static str {
+        match self {
+            SearchCommand::LoadViewport { .. } => "load-viewport",
+            SearchCommand::ExecuteSearch { .. } => "execute-search",
+            SearchCommand::NavigateMatch { .. } => "navigate-match",
+            SearchCommand::SkipDuplicates { .. } => "skip-duplicates",
+            SearchCommand::NavigateRecord { .. } => "navigate-record",
+            SearchCommand::NavigateTime { .. } => "navigate-time",
+            SearchCommand::PeekLine { .. } => "peek-line",
+            SearchCommand::CollectMatches { .. } => "collect-matches",
+            SearchCommand::Export { .. } => "export",
+            SearchCommand::Cancel { .. } => "cancel",
+            SearchCommand::DescribeFile => "describe-file",
+            SearchCommand::CheckFileGrowth => "check-file-growth",
+            SearchCommand::SwitchToLatest { .. } => "switch-to-latest",
+            SearchCommand::ShowLineNumbers(_) => "show-line-numbers",
+            SearchCommand::ShowLinePosition(_) => "show-line-position",
+            SearchCommand::ShowHighlights(_) => "show-highlights",
+            SearchCommand::KeepPatterns(_) => "keep-patterns",
+            SearchCommand::UpdateSearchContext(_) => "update-search-context",
+            SearchCommand::ClearSearchContext => "clear-search-context",
+            SearchCommand::Shutdown => "shutdown",
+        }
+    }
+
+    pub fn request_id(&self) -> Option<RequestId> {
+        match self {
+            SearchCommand::LoadViewport { request_id, .. }
+            | SearchCommand::ExecuteSearch { request_id, .. }
+            | SearchCommand::NavigateMatch { request_id, .. }
+            | SearchCommand::SkipDuplicates { request_id, .. }
+            | SearchCommand::NavigateRecord { request_id, .. }
+            | SearchCommand::NavigateTime { request_id, .. }
+            | SearchCommand::PeekLine { request_id, .. }
+            | SearchCommand::CollectMatches { request_id, .. }
+            | SearchCommand::Export { request_id, .. }
+            | SearchCommand::Cancel { request_id } => Some(*request_id),
+            SearchCommand::DescribeFile
+            | SearchCommand::CheckFileGrowth
+            | SearchCommand::SwitchToLatest { .. }
+            | SearchCommand::ShowLineNumbers(_)
+            | SearchCommand::ShowLinePosition(_)
+            | SearchCommand::ShowHighlights(_)
+            | SearchCommand::KeepPatterns(_)
+            | SearchCommand::UpdateSearchContext(_)
+            | SearchCommand::ClearSearchContext
+            | SearchCommand::Shutdown => None,
+        }
+    }
+
+    /// Token the command's operation checks as it runs, for those that can take a while
+    pub fn cancel_token(&self) -> Option<&CancellationToken> {
+        match self {
+            SearchCommand::LoadViewport { cancel_flag, .. }
+            | SearchCommand::ExecuteSearch { cancel_flag, .. }
+            | SearchCommand::NavigateMatch { cancel_flag, .. }
+            | SearchCommand::SkipDuplicates { cancel_flag, .. }
+            | SearchCommand::NavigateRecord { cancel_flag, .. }
+            | SearchCommand::NavigateTime { cancel_flag, .. }
+            | SearchCommand::CollectMatches { cancel_flag, .. }
+            | SearchCommand::Export { cancel_flag, .. } => Some(cancel_flag),
+            SearchCommand::PeekLine { .. }
+            | SearchCommand::Cancel { .. }
+            | SearchCommand::DescribeFile
+            | SearchCommand::CheckFileGrowth
+            | SearchCommand::SwitchToLatest { .. }
+            | SearchCommand::ShowLineNumbers(_)
+            | SearchCommand::ShowLinePosition(_)
+            | SearchCommand::ShowHighlights(_)
+            | SearchCommand::KeepPatterns(_)
+            | SearchCommand::UpdateSearchContext(_)
+            | SearchCommand::ClearSearchContext
+            | SearchCommand::Shutdown => None,
+        }
+    }
+}
+
 /// Responses emitted by the search/paging worker back to the coordinator.
 #[derive(Debug)]
 pub enum SearchResponse {
@@ -82,20 +373,149 @@ pub enum SearchResponse {
         request_id: RequestId,
         top_byte: u64,
         lines: Vec<String>,
-        highlights: Vec<Vec<(usize, usize)>>,
+        highlights: Vec<Vec<HighlightSpan>>,
         at_eof: bool,
+        /// The last line shown is the file's final line and has no terminating newline.
+        noeol: bool,
+        /// Some lines had more highlight spans than `MAX_LINE_SPANS`; only the first were kept.
+        highlights_truncated: bool,
         file_size: u64,
+        /// Line number of the first line shown, when line numbers are on and it is known yet;
+        /// never set for filtered views.
+        top_line: Option<u64>,
+        /// Line number of the first line shown and the file's line total, when the position is
+        /// shown in lines and the first line's number is known yet
+        line_position: Option<LinePosition>,
+        /// Row of `lines` starting at the requested `current_match`, if it is on the page
+        current_match_row: Option<usize>,
     },
     SearchCompleted {
         request_id: RequestId,
         match_byte: Option<u64>,
         message: Option<String>,
+        /// The search was held back because its pattern matches very often; it runs only once
+        /// the user confirms.
+        confirm: Option<FrequentMatches>,
+        /// How much the search read and how long it took, for searches that scanned the file
+        scan: Option<ScanStats>,
     },
     SearchCancelled {
         request_id: RequestId,
     },
+    /// Where a jump to a computed position (`:skip-dups`, `[`/`]`) lands; unlike
+    /// `SearchCompleted` it has nothing to do with the active search.
+    PositionFound {
+        request_id: RequestId,
+        byte: Option<u64>,
+        /// Why there is nowhere to go when `byte` is `None`
+        message: Option<String>,
+    },
+    LinePeeked {
+        request_id: RequestId,
+        /// Byte offset where `text` starts
+        start: u64,
+        text: String,
+        highlights: Vec<HighlightSpan>,
+        /// The line exceeded `MAX_PEEK_BYTES` and `text` holds only part of it.
+        truncated: bool,
+    },
+    /// Lines found by `CollectMatches` `request_id`: where each starts and the beginning of its
+    /// text, in file order. Batches arrive as they fill; the `last` one gives the line the next
+    /// request should start from, `None` when the end of the file was reached.
+    MatchBatch {
+        request_id: RequestId,
+        matches: Vec<(u64, String)>,
+        last: bool,
+        resume_from: Option<u64>,
+    },
+    FileDescribed {
+        meta: Option<FileMeta>,
+        /// Where the file really is, with symlinks and relative parts resolved
+        canonical_path: Option<PathBuf>,
+        file_size: u64,
+    },
+    /// The file grew to `file_size` bytes, which reads now cover. `new_match` is the first match
+    /// of the active search in the appended bytes, if there is one.
+    FileGrew {
+        file_size: u64,
+        new_match: Option<u64>,
+    },
+    /// Reads now go to the file at `path`, `file_size` bytes long with `sections` starting at
+    /// the given offsets.
+    FileSwitched {
+        path: PathBuf,
+        file_size: u64,
+        sections: Vec<u64>,
+    },
+    /// The file was truncated or replaced on disk and has been read again; reads now cover the
+    /// new content. Sent ahead of the response to the command that noticed it.
+    FileReloaded {
+        file_size: u64,
+        sections: Vec<u64>,
+        message: String,
+    },
+    /// How far the search `request_id` has scanned, sent now and then while it runs; the
+    /// search stops at `total_bytes` when nothing matches on the way.
+    SearchProgress {
+        request_id: RequestId,
+        bytes_scanned: u64,
+        total_bytes: u64,
+    },
+    /// The export `request_id` ended, as `message` describes. `partial` names the file a
+    /// cancelled or failed save left behind.
+    ExportFinished {
+        request_id: RequestId,
+        message: String,
+        partial: Option<PathBuf>,
+    },
+    /// Share of `operation` done so far, from 0.0 to 1.0; 1.0 means it finished.
+    Progress {
+        operation: ProgressOperation,
+        fraction: f32,
+    },
     Error {
         request_id: RequestId,
         error: RllessError,
     },
 }
+
+impl SearchResponse {
+    /// Short name of the response for log records
+    pub fn name(&self) -> &'static str {
+        match self {
+            SearchResponse::ViewportLoaded { .. } => "viewport-loaded",
+            SearchResponse::SearchCompleted { .. } => "search-completed",
+            SearchResponse::SearchCancelled { .. } => "search-cancelled",
+            SearchResponse::SearchProgress { .. } => "search-progress",
+            SearchResponse::PositionFound { .. } => "position-found",
+            SearchResponse::LinePeeked { .. } => "line-peeked",
+            SearchResponse::MatchBatch { .. } => "match-batch",
+            SearchResponse::FileDescribed { .. } => "file-described",
+            SearchResponse::FileGrew { .. } => "file-grew",
+            SearchResponse::FileSwitched { .. } => "file-switched",
+            SearchResponse::FileReloaded { .. } => "file-reloaded",
+            SearchResponse::ExportFinished { .. } => "export-finished",
+            SearchResponse::Progress { .. } => "progress",
+            SearchResponse::Error { .. } => "error",
+        }
+    }
+
+    pub fn request_id(&self) -> Option<RequestId> {
+        match self {
+            SearchResponse::ViewportLoaded { request_id, .. }
+            | SearchResponse::SearchCompleted { request_id, .. }
+            | SearchResponse::SearchCancelled { request_id }
+            | SearchResponse::SearchProgress { request_id, .. }
+            | SearchResponse::PositionFound { request_id, .. }
+            | SearchResponse::LinePeeked { request_id, .. }
+            | SearchResponse::MatchBatch { request_id, .. }
+            | SearchResponse::ExportFinished { request_id, .. }
+            | SearchResponse::Error { request_id, .. } => Some(*request_id),
+            SearchResponse::FileDescribed { .. }
+            | SearchResponse::FileGrew { .. }
+            | SearchResponse::FileSwitched { .. }
+            | SearchResponse::FileReloaded { .. }
+            | SearchResponse::Progress { .. } => None,
+        }
+    }
+}
@@ -1,39 +1,376 @@
 //! Render coordination helpers.
 //!
 //! Provides the state machine that mediates between input actions, search commands, and view
-//! updates. The high-level render loop currently lives in `Application::run`, but will be migrated
-//! into this module across subsequent phases.
-
-use crate::error::{Result, RllessError};
-use crate::input::{InputAction, ScrollDirection};
+//! updates. `RenderLoopState` holds the state; its behaviour is spread over submodules:
+//!
+//! - `actions`: applying input actions
+//! - `navigation`: scrolling, paging and jumps
+//! - `search`: starting searches and moving between matches
+//! - `prompts`: the `-`, `:` and `&` prompts
+//! - `sets`: searching for a pattern set loaded from a file
+//! - `responses`: applying worker responses
+//! - `tail`: follow mode and growing input
+//! - `feedback`: answering keys that do nothing
+//! - `latest`: moving on to a newer `--latest` file
+//! - `matches`: the match list panel (`M`)
+//! - `locator`: shareable locators for the current position (`:loc`)
+//! - `dispatch`: sending commands to the worker through the outbox
+//! - `coordinator`: the render loop (`RenderCoordinator`)
+
+use crate::bookmarks::Bookmarks;
+use crate::file_handler::PipeProgress;
+use crate::input::{PrecisionScroll, SearchDirection};
 use crate::render::protocol::{
-    MatchTraversal, RequestId, SearchCommand, SearchHighlightSpec, SearchResponse, ViewportRequest,
+    FrequentMatches, ProgressOperation, RequestId, SearchCommand, SearchHighlightSpec,
 };
-use crate::render::ui::ViewState;
-use crate::search::SearchOptions;
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::render::ui::{ChromeMode, InvalidKeyFeedback, JumpTarget, TailMode, ViewState};
+use crate::search::pattern_set::describe_set;
+use crate::search::{MemoryBudget, PatternLibrary, PatternModifiers, SearchOptions};
+use crate::settings::{on_off, Settings};
+use crate::util::CancellationToken;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::mpsc::{Sender, UnboundedReceiver};
-use tokio::time::{self, Duration};
+use tokio::sync::watch;
+use tokio::time::{Duration, Instant};
+
+mod actions;
+mod coordinator;
+mod dispatch;
+mod feedback;
+mod latest;
+mod locator;
+mod matches;
+mod navigation;
+mod preview;
+mod prompts;
+mod responses;
+mod search;
+mod sets;
+mod tail;
+
+pub use coordinator::RenderCoordinator;
+use dispatch::WorkerLink;
+pub use locator::locator;
+use matches::MatchListSource;
+use preview::SearchPreview;
+
+/// Upper bound on the render cadence (~60 Hz). Actions and worker responses are applied as soon
+/// as they arrive; only drawing is throttled to this interval.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Minimum time between progress changes on the status line, so a stream of progress events
+/// repaints a few times per second rather than on every frame.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Time between checks for data appended to the file while following it.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Time between checks for data appended to the file while not following it.
+const GROWTH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time between looks for a newer file matching `--latest` while following the tail.
+const LATEST_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long the status line stays in reverse video for `--invalid-key flash`.
+const FLASH_DURATION: Duration = Duration::from_millis(150);
+
+/// Keys that do nothing which also get a hint on the status line, per session.
+const INVALID_KEY_HINTS: u32 = 3;
+
+/// Searches taking longer than this say how much they scanned and how long it took.
+const SLOW_SEARCH: Duration = Duration::from_secs(2);
+
+/// Matches per megabyte near the search origin above which a new search asks for confirmation.
+pub const DEFAULT_FREQUENT_MATCH_LIMIT: u64 = 20_000;
+
+/// Lines that start a log record for `[`/`]`: an ISO-8601 date and time, optionally bracketed.
+pub const DEFAULT_RECORD_START: &str = r"^\[?\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}";
 
 /// Tracks render-related state that must persist across input actions and worker responses.
 pub struct RenderLoopState {
     search_state: Option<Arc<SearchHighlightSpec>>,
+    // Direction of the most recently queued search, shown with its pattern once it is active.
+    search_direction: SearchDirection,
     search_options: SearchOptions,
     pending_options_update: bool,
+    // Send time of the most recent viewport request, used to measure load latency.
+    viewport_requested_at: Option<(RequestId, Instant)>,
+    // Flag of the newest viewport load, set when a later one supersedes it
+    viewport_cancel_flag: Option<CancellationToken>,
+    last_viewport_latency: Option<Duration>,
+    // Outstanding `:skip-dups` or `[`/`]` jump and its cancel flag. Kept apart from searches: it
+    // never touches the search context or the current match.
+    position_request: Option<(RequestId, CancellationToken)>,
+    // Viewport load issued to show a search result; its first highlight becomes the current match.
+    match_viewport_request: Option<RequestId>,
+    current_match_text: Option<String>,
+    // Start of the line the last search jumped to, marked on every page showing it.
+    current_match: Option<u64>,
+    // Row a search jump puts the matching line on; see `jump_request`.
+    jump_target: JumpTarget,
+    // Viewport load showing the line a search or ESC-n / ESC-N jumped to, with that line.
+    landing_request: Option<(RequestId, u64)>,
+    // Viewport top and line of the last such jump once it loaded; see `origin_line`.
+    landing: Option<(u64, u64)>,
+    // ESC-n / ESC-N jump in flight, whose line goes on the `-j` row as a match does.
+    non_match_request: Option<RequestId>,
+    filter_state: Option<Arc<SearchHighlightSpec>>,
+    // Set until a viewport loads under a newly applied filter; a viewport error meanwhile
+    // rejects the filter.
+    filter_pending: bool,
+    // Follow mode state; `None` while not following.
+    tail_mode: Option<TailMode>,
+    // Ring the bell when appended data matches the search; see `set_match_bell`.
+    match_bell: bool,
+    bell_pending: bool,
+    // How a key that does nothing is answered; see `set_invalid_key_feedback`.
+    invalid_key_feedback: InvalidKeyFeedback,
+    // When the status line flash for such a key ends; see `end_flash`.
+    flash_until: Option<Instant>,
+    // Hints about such keys shown so far this session.
+    key_hints_shown: u32,
+    // Say once that the file grows while not following it; see `set_growth_hint`.
+    growth_hint: bool,
+    // Pattern of `--latest`, re-checked for a newer file while following; see `set_latest`.
+    latest: Option<PathBuf>,
+    // Piped input still being read; see `watch_input`.
+    input: Option<watch::Receiver<PipeProgress>>,
+    // Latest progress per operation received since it was last shown; see `apply_progress`.
+    pending_progress: BTreeMap<ProgressOperation, f32>,
+    // Progress currently on the status line, in whole percent.
+    shown_progress: BTreeMap<ProgressOperation, u8>,
+    progress_shown_at: Option<Instant>,
+    // Search whose `SearchProgress` is on the status line, until it answers.
+    search_progress_request: Option<RequestId>,
+    // Outstanding fetch for the line overlay.
+    peek_request: Option<RequestId>,
+    // Search listed by the open match list and its outstanding fetch; see `open_match_list`.
+    match_source: Option<MatchListSource>,
+    // Running `:save` or `:pipe` and its cancel flag; one at a time.
+    export_request: Option<(RequestId, CancellationToken)>,
+    // File a stopped `:save` left behind while the prompt to delete it is showing.
+    partial_export: Option<PathBuf>,
+    // See `set_frequent_match_limit`; `None` disables the check.
+    frequent_match_limit: Option<u64>,
+    // Regex matching the first line of a log record; see `set_record_start`.
+    record_start: Arc<str>,
+    // Named patterns expanded from `@name` in searches; see `set_pattern_library`.
+    patterns: PatternLibrary,
+    // Search held back by the frequency check while the y/n prompt is showing.
+    unconfirmed_search: Option<(FrequentMatches, PatternModifiers)>,
+    // A count prefix is on the status line; see `uncount`.
+    count_shown: bool,
+    // Wheel mode shared with the input thread, toggled by `-m`.
+    precision_scroll: PrecisionScroll,
+    // Startup settings with their provenance, listed by `:options`.
+    settings: Settings,
+    // Commands waiting for room in the worker channel, oldest first; see `dispatch`.
+    outbox: VecDeque<SearchCommand>,
+    // Commands dropped from the outbox because a newer one superseded them.
+    coalesced_commands: u64,
+    // Whether the worker channel is open; see `worker_closed`.
+    worker_link: WorkerLink,
+    /// Budget shared with the worker's caches, listed by `:metrics`
+    cache_budget: Option<MemoryBudget>,
+    // See `set_incremental_search`.
+    incremental_search: bool,
+    // Previews for the open search prompt; see `schedule_preview`.
+    preview: Option<SearchPreview>,
+    // `:bookmark` labels for this file; see `set_bookmarks`.
+    bookmarks: Bookmarks,
+    // Request, origin byte, direction and `skip_origin_row` of the last search queued; see
+    // `queue_search`.
+    search_origin: Option<(RequestId, u64, SearchDirection, bool)>,
+    // ESC-u hid the active search's highlights; see `toggle_highlights`.
+    highlights_hidden: bool,
+    // Patterns highlighted besides the search, most recently kept first; see `keep_search`.
+    kept_patterns: Vec<Arc<SearchHighlightSpec>>,
 }
 
 impl RenderLoopState {
     pub fn new(search_options: SearchOptions) -> Self {
         Self {
             search_state: None,
+            search_direction: SearchDirection::Forward,
             search_options,
             pending_options_update: false,
+            viewport_requested_at: None,
+            viewport_cancel_flag: None,
+            last_viewport_latency: None,
+            position_request: None,
+            match_viewport_request: None,
+            jump_target: JumpTarget::default(),
+            landing_request: None,
+            landing: None,
+            non_match_request: None,
+            current_match_text: None,
+            current_match: None,
+            filter_state: None,
+            filter_pending: false,
+            tail_mode: None,
+            match_bell: true,
+            growth_hint: true,
+            bell_pending: false,
+            invalid_key_feedback: InvalidKeyFeedback::default(),
+            flash_until: None,
+            key_hints_shown: 0,
+            latest: None,
+            input: None,
+            pending_progress: BTreeMap::new(),
+            shown_progress: BTreeMap::new(),
+            progress_shown_at: None,
+            search_progress_request: None,
+            peek_request: None,
+            match_source: None,
+            export_request: None,
+            partial_export: None,
+            frequent_match_limit: Some(DEFAULT_FREQUENT_MATCH_LIMIT),
+            record_start: Arc::from(DEFAULT_RECORD_START),
+            patterns: PatternLibrary::default(),
+            unconfirmed_search: None,
+            count_shown: false,
+            precision_scroll: PrecisionScroll::default(),
+            settings: Settings::default(),
+            outbox: VecDeque::new(),
+            coalesced_commands: 0,
+            worker_link: WorkerLink::Up,
+            cache_budget: None,
+            incremental_search: true,
+            preview: None,
+            bookmarks: Bookmarks::default(),
+            search_origin: None,
+            highlights_hidden: false,
+            kept_patterns: Vec::new(),
+        }
+    }
+
+    /// Startup settings and where each came from, shown by `:options`
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+    }
+
+    /// Startup settings with the values this coordinator owns replaced by their current ones
+    pub fn effective_settings(&self, view_state: &ViewState) -> Settings {
+        let mut settings = self.settings.clone();
+        settings.update("ignore-case", on_off(!self.search_options.case_sensitive));
+        settings.update("smart-case", on_off(self.search_options.smart_case));
+        settings.update("regex", on_off(self.search_options.regex_mode));
+        settings.update("word", on_off(self.search_options.whole_word));
+        settings.update("multiline", on_off(self.search_options.multiline));
+        settings.update("position", view_state.position_style.name());
+        settings.update(
+            "presentation",
+            on_off(view_state.chrome == ChromeMode::Presentation),
+        );
+        settings.update("follow", on_off(self.tail_mode.is_some()));
+        settings.update("precise-scroll", on_off(self.precision_scroll.is_enabled()));
+        settings.update("line-numbers", on_off(view_state.line_numbers));
+        settings.update("wrap", on_off(view_state.wrap));
+        settings.update("jump-target", self.jump_target.name());
+        settings.update(
+            "frequent-match-limit",
+            self.frequent_match_limit
+                .map_or_else(|| "off".to_string(), |limit| limit.to_string()),
+        );
+        settings
+    }
+
+    /// Ask before running a new search whose pattern matches more than `limit` times per
+    /// megabyte near the viewport; `None` runs every search straight away
+    pub fn set_frequent_match_limit(&mut self, limit: Option<u64>) {
+        self.frequent_match_limit = limit;
+    }
+
+    /// Share the wheel mode with the input thread, so `-m` can switch it
+    pub fn set_precision_scroll(&mut self, precision: PrecisionScroll) {
+        self.precision_scroll = precision;
+    }
+
+    /// Treat lines matching the regex `pattern` as the first line of a log record, for `[`/`]`
+    pub fn set_record_start(&mut self, pattern: &str) {
+        self.record_start = Arc::from(pattern);
+    }
+
+    /// Expand `@name` in regex searches from `patterns`, listed by `:patterns`
+    pub fn set_pattern_library(&mut self, patterns: PatternLibrary) {
+        self.patterns = patterns;
+    }
+
+    /// Start with the file's saved `bookmarks`, marking them in the gutter
+    pub fn set_bookmarks(&mut self, bookmarks: Bookmarks, view_state: &mut ViewState) {
+        view_state.set_bookmarks(bookmarks.offsets());
+        self.bookmarks = bookmarks;
+    }
+
+    /// Report the usage of the budget the worker's caches charge in `:metrics`
+    pub fn set_cache_budget(&mut self, budget: MemoryBudget) {
+        self.cache_budget = Some(budget);
+    }
+
+    /// Move batched progress onto the status line; called once per frame
+    ///
+    /// Only the latest value per operation is kept between calls. Percentage changes are shown
+    /// at most once per `PROGRESS_INTERVAL`, while a finished operation disappears right away.
+    /// Returns whether the status line changed.
+    pub fn apply_progress(&mut self, view_state: &mut ViewState, now: Instant) -> bool {
+        if self.pending_progress.is_empty() {
+            return false;
+        }
+
+        let mut shown = self.shown_progress.clone();
+        let mut finished = false;
+        for (&operation, &fraction) in &self.pending_progress {
+            if fraction >= 1.0 {
+                finished |= shown.remove(&operation).is_some();
+            } else {
+                shown.insert(operation, (fraction.max(0.0) * 100.0) as u8);
+            }
+        }
+        if shown == self.shown_progress {
+            self.pending_progress.clear();
+            return false;
+        }
+        let throttled = self
+            .progress_shown_at
+            .is_some_and(|at| now.duration_since(at) < PROGRESS_INTERVAL);
+        if throttled && !finished {
+            // Keep the values pending for a later frame
+            return false;
         }
+
+        self.pending_progress.clear();
+        self.progress_shown_at = Some(now);
+        view_state.status_line.progress = (!shown.is_empty()).then(|| {
+            shown
+                .iter()
+                .map(|(operation, percent)| format!("{} {}%", operation.label(), percent))
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+        self.shown_progress = shown;
+        true
+    }
+
+    /// Time between sending the most recently applied viewport request and receiving its
+    /// response. `None` until the first coordinator-issued viewport load completes.
+    pub fn last_viewport_latency(&self) -> Option<Duration> {
+        self.last_viewport_latency
     }
 
+    /// Highlight for viewport loads: a preview's while one is on screen, else the active search's
+    /// unless ESC-u hid it
     pub fn highlight_spec(&self) -> Option<Arc<SearchHighlightSpec>> {
-        self.search_state.clone()
+        self.preview_highlight().or_else(|| {
+            self.search_state
+                .clone()
+                .filter(|_| !self.highlights_hidden)
+        })
+    }
+
+    /// Pattern restricting which lines are shown, if a filter is active
+    pub fn filter_spec(&self) -> Option<Arc<SearchHighlightSpec>> {
+        self.filter_state.clone()
     }
 
     pub fn search_options(&self) -> &SearchOptions {
@@ -45,22 +382,46 @@ impl RenderLoopState {
         self.refresh_active_search();
     }
 
+    /// Text of the match the viewport last landed on, if any
+    pub fn current_match_text(&self) -> Option<&str> {
+        self.current_match_text.as_deref()
+    }
+
     pub fn clear_search(&mut self, view_state: &mut ViewState) {
         self.search_state = None;
         self.pending_options_update = false;
+        self.current_match_text = None;
+        view_state.status_line.active_pattern = None;
+        self.current_match = None;
+        view_state.set_match_row(None);
         view_state.clear_highlights();
     }
 
-    pub fn set_search(&mut self, search: Arc<SearchHighlightSpec>) {
+    pub fn set_search(&mut self, search: Arc<SearchHighlightSpec>, view_state: &mut ViewState) {
+        if search.options.pattern_set {
+            view_state
+                .status_line
+                .set_active_pattern(self.search_direction, &describe_set(&search.pattern));
+        } else {
+            view_state.status_line.set_active_pattern(
+                self.search_direction,
+                &format!("{}{}", search.modifiers.label(), search.pattern),
+            );
+        }
         self.search_state = Some(search);
         self.pending_options_update = false;
     }
 
     fn refresh_active_search(&mut self) {
         if let Some(spec) = self.search_state.as_ref() {
+            // The pattern's own modifiers still win over the new global options
             let updated = Arc::new(SearchHighlightSpec {
                 pattern: Arc::clone(&spec.pattern),
-                options: self.search_options.clone(),
+                options: spec.modifiers.apply(SearchOptions {
+                    pattern_set: spec.options.pattern_set,
+                    ..self.search_options.clone()
+                }),
+                modifiers: spec.modifiers,
             });
             self.search_state = Some(updated);
         } else {
@@ -69,24 +430,15 @@ impl RenderLoopState {
     }
 
     fn search_options_summary(&self) -> String {
-        format!(
-            "search options: case={} regex={} word={}",
-            if self.search_options.case_sensitive {
-                "sensitive"
-            } else {
-                "ignore"
-            },
-            if self.search_options.regex_mode {
-                "on"
-            } else {
-                "off"
-            },
-            if self.search_options.whole_word {
-                "on"
-            } else {
-                "off"
-            }
-        )
+        match self.search_state.as_ref() {
+            Some(search) if !search.modifiers.is_empty() => format!(
+                "search options: {} ({} search: {})",
+                self.search_options.summary(),
+                search.modifiers.label(),
+                search.options.summary()
+            ),
+            _ => format!("search options: {}", self.search_options.summary()),
+        }
     }
 
     fn ensure_active_search(&self, view_state: &mut ViewState) -> bool {
@@ -99,716 +451,4 @@ impl RenderLoopState {
             false
         }
     }
-
-    async fn queue_viewport_update(
-        &self,
-        request: ViewportRequest,
-        view_state: &mut ViewState,
-        search_tx: &mut Sender<SearchCommand>,
-        next_request_id: &mut RequestId,
-        latest_view_request: &mut Option<RequestId>,
-    ) -> Result<bool> {
-        view_state.at_eof = false;
-        self.request_viewport(
-            request,
-            view_state,
-            search_tx,
-            next_request_id,
-            latest_view_request,
-        )
-        .await?;
-        Ok(true)
-    }
-
-    async fn queue_match_navigation(
-        &self,
-        traversal: MatchTraversal,
-        view_state: &mut ViewState,
-        search_tx: &mut Sender<SearchCommand>,
-        next_request_id: &mut RequestId,
-        latest_search_request: &mut Option<RequestId>,
-        search_cancel_flag: &mut Option<Arc<AtomicBool>>,
-    ) -> Result<bool> {
-        let request_id = *next_request_id;
-        *next_request_id += 1;
-        *latest_search_request = Some(request_id);
-        let cancel_flag = Arc::new(AtomicBool::new(false));
-        *search_cancel_flag = Some(Arc::clone(&cancel_flag));
-        search_tx
-            .send(SearchCommand::NavigateMatch {
-                request_id,
-                traversal,
-                current_top: view_state.viewport_top_byte,
-                cancel_flag,
-            })
-            .await
-            .map_err(|_| RllessError::other("search worker unavailable"))?;
-        Ok(true)
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    pub async fn process_action(
-        &mut self,
-        action: InputAction,
-        view_state: &mut ViewState,
-        search_tx: &mut Sender<SearchCommand>,
-        next_request_id: &mut RequestId,
-        latest_view_request: &mut Option<RequestId>,
-        latest_search_request: &mut Option<RequestId>,
-        search_cancel_flag: &mut Option<Arc<AtomicBool>>,
-        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
-    ) -> Result<bool> {
-        match action {
-            InputAction::Interrupt => {
-                if latest_search_request.is_some() {
-                    if let Some(flag) = search_cancel_flag {
-                        // Flip the token that travels with the in-flight command; the worker
-                        // checks it cooperatively so we do not rely on inserting a follow-up
-                        // cancel command into the queue.
-                        flag.store(true, Ordering::SeqCst);
-                        view_state
-                            .status_line
-                            .set_message("Cancelling search…".to_string());
-                        return Ok(true);
-                    }
-                }
-                Ok(false)
-            }
-            InputAction::Quit => Ok(false),
-            InputAction::Scroll { direction, lines } => {
-                let delta = match direction {
-                    ScrollDirection::Up => -(lines as i64),
-                    ScrollDirection::Down => lines as i64,
-                };
-                self.queue_viewport_update(
-                    ViewportRequest::RelativeLines {
-                        anchor: view_state.viewport_top_byte,
-                        lines: delta,
-                    },
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                )
-                .await
-            }
-            InputAction::PageUp => {
-                self.queue_viewport_update(
-                    ViewportRequest::RelativeLines {
-                        anchor: view_state.viewport_top_byte,
-                        lines: -(view_state.lines_per_page() as i64),
-                    },
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                )
-                .await
-            }
-            InputAction::PageDown => {
-                self.queue_viewport_update(
-                    ViewportRequest::RelativeLines {
-                        anchor: view_state.viewport_top_byte,
-                        lines: view_state.lines_per_page() as i64,
-                    },
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                )
-                .await
-            }
-            InputAction::GoToStart => {
-                self.queue_viewport_update(
-                    ViewportRequest::Absolute(0),
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                )
-                .await
-            }
-            InputAction::GoToEnd => {
-                self.queue_viewport_update(
-                    ViewportRequest::EndOfFile,
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                )
-                .await
-            }
-            InputAction::StartSearch(direction) => {
-                view_state.status_line.set_search_prompt(direction);
-                Ok(true)
-            }
-            InputAction::UpdateSearchBuffer { direction, buffer } => {
-                view_state
-                    .status_line
-                    .update_search_prompt(direction, buffer);
-                Ok(true)
-            }
-            InputAction::CancelSearch => {
-                view_state.status_line.clear_search_prompt();
-                view_state.status_line.message = None;
-                pending_search_state.take();
-                *latest_search_request = None;
-                search_cancel_flag.take();
-                self.request_viewport(
-                    ViewportRequest::Absolute(view_state.viewport_top_byte),
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                )
-                .await?;
-                Ok(true)
-            }
-            InputAction::ExecuteSearch { pattern, direction } => {
-                let trimmed = pattern.trim();
-                if trimmed.is_empty() {
-                    view_state.status_line.clear_search_prompt();
-                    view_state.status_line.message = None;
-                    pending_search_state.take();
-                    search_cancel_flag.take();
-                    let _ = search_tx.send(SearchCommand::ClearSearchContext).await;
-                    self.clear_search(view_state);
-                    self.request_viewport(
-                        ViewportRequest::Absolute(view_state.viewport_top_byte),
-                        view_state,
-                        search_tx,
-                        next_request_id,
-                        latest_view_request,
-                    )
-                    .await?;
-                    return Ok(true);
-                }
-
-                let options = self.search_options.clone();
-                let pattern: Arc<str> = Arc::from(trimmed.to_string());
-                let request_id = *next_request_id;
-                *next_request_id += 1;
-                *latest_search_request = Some(request_id);
-                let highlight = Arc::new(SearchHighlightSpec {
-                    pattern: Arc::clone(&pattern),
-                    options: options.clone(),
-                });
-                pending_search_state.replace((request_id, Arc::clone(&highlight)));
-                let cancel_flag = Arc::new(AtomicBool::new(false));
-                *search_cancel_flag = Some(Arc::clone(&cancel_flag));
-
-                search_tx
-                    .send(SearchCommand::ExecuteSearch {
-                        request_id,
-                        pattern,
-                        direction,
-                        options,
-                        origin_byte: view_state.viewport_top_byte,
-                        cancel_flag,
-                    })
-                    .await
-                    .map_err(|_| RllessError::other("search worker unavailable"))?;
-                Ok(true)
-            }
-            InputAction::NextMatch => {
-                if !self.ensure_active_search(view_state) {
-                    if self.pending_options_update {
-                        view_state
-                            .status_line
-                            .set_message("Search options updated; start a new search.".to_string());
-                    }
-                    return Ok(true);
-                }
-                self.queue_match_navigation(
-                    MatchTraversal::Next,
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_search_request,
-                    search_cancel_flag,
-                )
-                .await
-            }
-            InputAction::PreviousMatch => {
-                if !self.ensure_active_search(view_state) {
-                    if self.pending_options_update {
-                        view_state
-                            .status_line
-                            .set_message("Search options updated; start a new search.".to_string());
-                    }
-                    return Ok(true);
-                }
-                self.queue_match_navigation(
-                    MatchTraversal::Previous,
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_search_request,
-                    search_cancel_flag,
-                )
-                .await
-            }
-            InputAction::Resize { width, height } => {
-                if view_state.update_terminal_size(width, height) {
-                    self.request_viewport(
-                        ViewportRequest::Absolute(view_state.viewport_top_byte),
-                        view_state,
-                        search_tx,
-                        next_request_id,
-                        latest_view_request,
-                    )
-                    .await?;
-                }
-                Ok(true)
-            }
-            InputAction::StartPercentInput => {
-                view_state.status_line.set_message("goto: %".to_string());
-                Ok(true)
-            }
-            InputAction::UpdatePercentBuffer(buffer) => {
-                let display = if buffer.is_empty() {
-                    "goto: %".to_string()
-                } else {
-                    format!("goto: %{}", buffer)
-                };
-                view_state.status_line.set_message(display);
-                Ok(true)
-            }
-            InputAction::CancelPercentInput => {
-                view_state.status_line.clear_message();
-                Ok(true)
-            }
-            InputAction::SubmitPercent(percent) => {
-                let Some(file_size) = view_state.file_size else {
-                    view_state
-                        .status_line
-                        .set_message("Cannot jump: file size unknown".to_string());
-                    return Ok(true);
-                };
-
-                if file_size == 0 {
-                    view_state
-                        .status_line
-                        .set_message("Cannot jump: file is empty".to_string());
-                    return Ok(true);
-                }
-
-                if percent >= 100 {
-                    view_state
-                        .status_line
-                        .set_message("goto: 100% (EOF)".to_string());
-                    return self
-                        .queue_viewport_update(
-                            ViewportRequest::EndOfFile,
-                            view_state,
-                            search_tx,
-                            next_request_id,
-                            latest_view_request,
-                        )
-                        .await;
-                }
-
-                let target = ((percent as u128) * (file_size as u128) / 100) as u64;
-                view_state
-                    .status_line
-                    .set_message(format!("goto: {}%", percent));
-                self.queue_viewport_update(
-                    ViewportRequest::Absolute(target),
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                )
-                .await
-            }
-            InputAction::StartCommand => {
-                view_state.status_line.set_message("command: -".to_string());
-                Ok(true)
-            }
-            InputAction::UpdateCommandBuffer(buffer) => {
-                view_state.status_line.set_message(if buffer.is_empty() {
-                    "command: -".to_string()
-                } else {
-                    format!("command: -{}", buffer)
-                });
-                Ok(true)
-            }
-            InputAction::CancelCommand => {
-                view_state.status_line.clear_message();
-                Ok(true)
-            }
-            InputAction::ExecuteCommand { buffer } => {
-                if buffer.is_empty() {
-                    view_state
-                        .status_line
-                        .set_message("No command entered".to_string());
-                    return Ok(true);
-                }
-
-                let mut options_changed = false;
-                for flag in buffer.chars() {
-                    match flag {
-                        'i' | 'I' => {
-                            self.search_options.case_sensitive =
-                                !self.search_options.case_sensitive;
-                            options_changed = true;
-                        }
-                        'r' | 'R' => {
-                            if !self.search_options.regex_mode {
-                                self.search_options.regex_mode = true;
-                                options_changed = true;
-                            }
-                        }
-                        'n' | 'N' => {
-                            if self.search_options.regex_mode {
-                                self.search_options.regex_mode = false;
-                                options_changed = true;
-                            }
-                        }
-                        'w' | 'W' => {
-                            self.search_options.whole_word = !self.search_options.whole_word;
-                            options_changed = true;
-                        }
-                        other => {
-                            view_state
-                                .status_line
-                                .set_message(format!("Unknown command flag: {}", other));
-                            return Ok(true);
-                        }
-                    }
-                }
-
-                if options_changed {
-                    self.refresh_active_search();
-                    view_state
-                        .status_line
-                        .set_message(self.search_options_summary());
-                    self.request_viewport(
-                        ViewportRequest::Absolute(view_state.viewport_top_byte),
-                        view_state,
-                        search_tx,
-                        next_request_id,
-                        latest_view_request,
-                    )
-                    .await?;
-                } else {
-                    view_state
-                        .status_line
-                        .set_message("Search options unchanged".to_string());
-                }
-
-                Ok(true)
-            }
-            InputAction::NoAction | InputAction::InvalidInput => Ok(true),
-        }
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    pub async fn handle_response(
-        &mut self,
-        response: SearchResponse,
-        view_state: &mut ViewState,
-        latest_view_request: &mut Option<RequestId>,
-        latest_search_request: &mut Option<RequestId>,
-        search_cancel_flag: &mut Option<Arc<AtomicBool>>,
-        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
-        search_tx: &mut Sender<SearchCommand>,
-        next_request_id: &mut RequestId,
-    ) -> Result<()> {
-        match response {
-            SearchResponse::ViewportLoaded {
-                request_id,
-                top_byte,
-                lines,
-                highlights,
-                at_eof,
-                file_size,
-            } => {
-                if Some(request_id) != *latest_view_request {
-                    return Ok(());
-                }
-                *latest_view_request = None;
-                view_state.navigate_to_byte(top_byte);
-                view_state.at_eof = at_eof;
-                view_state.update_viewport_content(lines, highlights);
-                view_state.file_size = Some(file_size);
-            }
-            SearchResponse::SearchCompleted {
-                request_id,
-                match_byte,
-                message,
-            } => {
-                if Some(request_id) != *latest_search_request {
-                    return Ok(());
-                }
-                *latest_search_request = None;
-                search_cancel_flag.take();
-
-                if let Some(msg) = message {
-                    // Worker signals errors/not-found via `message`; treat this as a failed search
-                    // completion and drop any provisional highlight.
-                    view_state.status_line.clear_search_prompt();
-                    view_state.status_line.set_message(msg);
-                    if let Some((pending_id, _)) = pending_search_state {
-                        if *pending_id == request_id {
-                            pending_search_state.take();
-                            let _ = search_tx.send(SearchCommand::ClearSearchContext).await;
-                            *latest_search_request = None;
-                            self.clear_search(view_state);
-                        }
-                    }
-                } else if let Some(byte) = match_byte {
-                    // Successful search: promote the pending highlight and jump to the match.
-                    view_state.status_line.clear_search_prompt();
-                    view_state.status_line.message = None;
-                    if let Some((pending_id, state)) = pending_search_state.take() {
-                        if pending_id == request_id {
-                            self.set_search(state);
-                        }
-                    }
-                    view_state.at_eof = false;
-                    let request_id = self
-                        .request_viewport(
-                            ViewportRequest::Absolute(byte),
-                            view_state,
-                            search_tx,
-                            next_request_id,
-                            latest_view_request,
-                        )
-                        .await?;
-                    *latest_view_request = Some(request_id);
-                }
-            }
-            SearchResponse::SearchCancelled { request_id } => {
-                if Some(request_id) != *latest_search_request {
-                    return Ok(());
-                }
-                *latest_search_request = None;
-                search_cancel_flag.take();
-                pending_search_state.take();
-                let _ = search_tx.send(SearchCommand::ClearSearchContext).await;
-                view_state.status_line.clear_search_prompt();
-                view_state
-                    .status_line
-                    .set_message("Search cancelled".to_string());
-            }
-            SearchResponse::Error { request_id, error } => {
-                if Some(request_id) == *latest_view_request {
-                    *latest_view_request = None;
-                }
-                if Some(request_id) == *latest_search_request {
-                    *latest_search_request = None;
-                    pending_search_state.take();
-                }
-                search_cancel_flag.take();
-                view_state
-                    .status_line
-                    .set_message(format!("Operation failed: {}", error));
-            }
-        }
-        Ok(())
-    }
-
-    async fn request_viewport(
-        &self,
-        top: ViewportRequest,
-        view_state: &ViewState,
-        search_tx: &mut Sender<SearchCommand>,
-        next_request_id: &mut RequestId,
-        latest_view_request: &mut Option<RequestId>,
-    ) -> Result<RequestId> {
-        let request_id = *next_request_id;
-        *next_request_id += 1;
-        let _ = latest_view_request.replace(request_id);
-        search_tx
-            .send(SearchCommand::LoadViewport {
-                request_id,
-                top,
-                page_lines: view_state.lines_per_page() as usize,
-                highlights: self.highlight_spec(),
-            })
-            .await
-            .map_err(|_| RllessError::other("search worker unavailable"))?;
-        Ok(request_id)
-    }
-}
-
-/// Orchestrates the main render loop once channels have been wired.
-pub struct RenderCoordinator;
-
-impl RenderCoordinator {
-    #[allow(clippy::too_many_arguments)]
-    async fn process_pending_actions(
-        state: &mut RenderLoopState,
-        actions: &mut Vec<InputAction>,
-        view_state: &mut ViewState,
-        search_tx: &mut Sender<SearchCommand>,
-        next_request_id: &mut RequestId,
-        latest_view_request: &mut Option<RequestId>,
-        latest_search_request: &mut Option<RequestId>,
-        search_cancel_flag: &mut Option<Arc<AtomicBool>>,
-        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
-    ) -> Result<bool> {
-        for action in actions.drain(..) {
-            if !state
-                .process_action(
-                    action,
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                    latest_search_request,
-                    search_cancel_flag,
-                    pending_search_state,
-                )
-                .await?
-            {
-                return Ok(false);
-            }
-        }
-        Ok(true)
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    async fn drain_search_responses(
-        state: &mut RenderLoopState,
-        view_state: &mut ViewState,
-        search_resp_rx: &mut tokio::sync::mpsc::Receiver<SearchResponse>,
-        latest_view_request: &mut Option<RequestId>,
-        latest_search_request: &mut Option<RequestId>,
-        search_cancel_flag: &mut Option<Arc<AtomicBool>>,
-        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
-        search_tx: &mut Sender<SearchCommand>,
-        next_request_id: &mut RequestId,
-    ) -> Result<()> {
-        while let Ok(response) = search_resp_rx.try_recv() {
-            state
-                .handle_response(
-                    response,
-                    view_state,
-                    latest_view_request,
-                    latest_search_request,
-                    search_cancel_flag,
-                    pending_search_state,
-                    search_tx,
-                    next_request_id,
-                )
-                .await?;
-        }
-        Ok(())
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    pub async fn run(
-        state: &mut RenderLoopState,
-        view_state: &mut ViewState,
-        ui_renderer: &mut dyn crate::render::ui::UIRenderer,
-        input_rx: &mut UnboundedReceiver<InputAction>,
-        search_tx: &mut Sender<SearchCommand>,
-        search_resp_rx: &mut tokio::sync::mpsc::Receiver<SearchResponse>,
-        next_request_id: &mut RequestId,
-        latest_view_request: &mut Option<RequestId>,
-        latest_search_request: &mut Option<RequestId>,
-        search_cancel_flag: &mut Option<Arc<AtomicBool>>,
-        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
-    ) -> Result<()> {
-        let mut interval = time::interval(Duration::from_millis(16));
-        let mut action_buffer = Vec::new();
-        let mut running = true;
-
-        while running {
-            interval.tick().await;
-
-            while let Ok(action) = input_rx.try_recv() {
-                action_buffer.push(action);
-            }
-
-            running = running
-                && Self::process_pending_actions(
-                    state,
-                    &mut action_buffer,
-                    view_state,
-                    search_tx,
-                    next_request_id,
-                    latest_view_request,
-                    latest_search_request,
-                    search_cancel_flag,
-                    pending_search_state,
-                )
-                .await?;
-
-            if !running {
-                break;
-            }
-
-            Self::drain_search_responses(
-                state,
-                view_state,
-                search_resp_rx,
-                latest_view_request,
-                latest_search_request,
-                search_cancel_flag,
-                pending_search_state,
-                search_tx,
-                next_request_id,
-            )
-            .await?;
-
-            ui_renderer.render(view_state)?;
-        }
-
-        Ok(())
-    }
-}
-
-#[cfg(test)]
-mod state_tests {
-    use super::*;
-    use crate::input::InputStateMachine;
-    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-
-    fn key(code: KeyCode) -> KeyEvent {
-        KeyEvent::new(code, KeyModifiers::NONE)
-    }
-
-    #[test]
-    fn navigation_scrolls_and_pages() {
-        let mut sm = InputStateMachine::new();
-        assert_eq!(
-            sm.handle_key_event(key(KeyCode::Char('j'))),
-            InputAction::Scroll {
-                direction: ScrollDirection::Down,
-                lines: 1,
-            }
-        );
-        assert_eq!(
-            sm.handle_key_event(key(KeyCode::Char('k'))),
-            InputAction::Scroll {
-                direction: ScrollDirection::Up,
-                lines: 1,
-            }
-        );
-    }
-
-    #[test]
-    fn percent_jump_requires_digits() {
-        let mut sm = InputStateMachine::new();
-        assert_eq!(
-            sm.handle_key_event(key(KeyCode::Char('%'))),
-            InputAction::StartPercentInput
-        );
-
-        assert_eq!(
-            sm.handle_key_event(key(KeyCode::Char('1'))),
-            InputAction::UpdatePercentBuffer("1".to_string())
-        );
-        assert_eq!(
-            sm.handle_key_event(key(KeyCode::Char('0'))),
-            InputAction::UpdatePercentBuffer("10".to_string())
-        );
-        assert_eq!(
-            sm.handle_key_event(key(KeyCode::Enter)),
-            InputAction::SubmitPercent(10)
-        );
-    }
 }
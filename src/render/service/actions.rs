@@ -0,0 +1,626 @@
+//! Applying input actions to the view and the worker.
+
+use super::RenderLoopState;
+use crate::error::{Result, RllessError};
+use crate::input::{InputAction, SearchDirection};
+use crate::render::protocol::{
+    MatchTraversal, RequestId, SearchCommand, SearchHighlightSpec, ViewportRequest,
+};
+use crate::render::ui::{LinePeek, PromptOptions, ViewState};
+use crate::search::{verbatim_pattern, PatternModifiers};
+use crate::util::CancellationToken;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tokio::time::Instant;
+
+impl RenderLoopState {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn process_action(
+        &mut self,
+        action: InputAction,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+    ) -> Result<bool> {
+        let (count, action) = self.uncount(action, view_state);
+        if let Some((held, modifiers)) = self.unconfirmed_search.take() {
+            match action {
+                InputAction::ConfirmPrompt => {
+                    view_state.status_line.clear_message();
+                    return self
+                        .queue_search(
+                            held.pattern.to_string(),
+                            held.options,
+                            modifiers,
+                            held.direction,
+                            held.skip_origin_row,
+                            None,
+                            view_state,
+                            search_tx,
+                            next_request_id,
+                            latest_search_request,
+                            search_cancel_flag,
+                            pending_search_state,
+                        )
+                        .await;
+                }
+                // Not an answer; keep asking
+                InputAction::Resize { .. } | InputAction::Quit => {
+                    self.unconfirmed_search = Some((held, modifiers));
+                }
+                _ => {
+                    view_state
+                        .status_line
+                        .set_message("Search cancelled".to_string());
+                    return Ok(true);
+                }
+            }
+        }
+
+        if let Some(path) = self.partial_export.take() {
+            match action {
+                InputAction::ConfirmPrompt => {
+                    let message = match std::fs::remove_file(&path) {
+                        Ok(()) => format!("Deleted {}", path.display()),
+                        Err(e) => {
+                            RllessError::file_error(format!("Cannot delete {}", path.display()), e)
+                                .summary()
+                        }
+                    };
+                    view_state.status_line.set_message(message);
+                    return Ok(true);
+                }
+                InputAction::Resize { .. } | InputAction::Quit => {
+                    self.partial_export = Some(path);
+                }
+                _ => {
+                    view_state
+                        .status_line
+                        .set_message(format!("Kept {}", path.display()));
+                    return Ok(true);
+                }
+            }
+        }
+
+        if self.leave_following_for(&action, view_state) {
+            return Ok(true);
+        }
+
+        match action {
+            InputAction::Interrupt => {
+                // The worker checks the token as it goes, so this reaches the operation even
+                // while it runs
+                let running = [
+                    (
+                        latest_search_request.zip(search_cancel_flag.clone()),
+                        "search",
+                    ),
+                    (self.position_request.clone(), "jump"),
+                    (self.export_request.clone(), "export"),
+                ];
+                let Some((request_id, token, what)) = running
+                    .into_iter()
+                    .find_map(|(request, what)| request.map(|(id, token)| (id, token, what)))
+                else {
+                    return Ok(false);
+                };
+                self.cancel_request(search_tx, request_id, &token)?;
+                view_state
+                    .status_line
+                    .set_message(format!("Cancelling {}…", what));
+                Ok(true)
+            }
+            InputAction::Quit => Ok(false),
+            InputAction::Scroll { direction, lines } if view_state.peek.is_some() => {
+                // Mouse wheel events bypass the input state machine
+                view_state.scroll_peek(direction, lines);
+                Ok(true)
+            }
+            InputAction::Scroll { direction, lines } if view_state.match_list.is_some() => {
+                self.move_match_selection(direction, lines, view_state, search_tx, next_request_id)
+            }
+            InputAction::Scroll { .. }
+            | InputAction::PageUp
+            | InputAction::PageDown
+            | InputAction::HalfPageUp
+            | InputAction::HalfPageDown
+            | InputAction::GoToStart
+            | InputAction::PreviousSection
+            | InputAction::NextSection
+            | InputAction::PreviousRecord
+            | InputAction::NextRecord
+            | InputAction::GoToEnd
+            | InputAction::SubmitPercent { .. } => {
+                self.navigate(
+                    action,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::ToggleFollow => {
+                self.toggle_following(view_state, search_tx, next_request_id, latest_view_request)
+                    .await
+            }
+            InputAction::CyclePrimaryPattern => {
+                self.cycle_primary_pattern(
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::ToggleHighlights => {
+                self.toggle_highlights(view_state, search_tx, next_request_id, latest_view_request)
+                    .await?;
+                Ok(true)
+            }
+            InputAction::StartSearch(direction) => {
+                self.finish_preview();
+                view_state
+                    .status_line
+                    .set_search_prompt(direction, PromptOptions::from(&self.search_options));
+                Ok(true)
+            }
+            InputAction::UpdateSearchBuffer { direction, buffer } => {
+                self.schedule_preview(&buffer, direction, view_state, Instant::now());
+                view_state.status_line.update_search_prompt(
+                    direction,
+                    buffer,
+                    PromptOptions::from(&self.search_options),
+                );
+                Ok(true)
+            }
+            InputAction::CancelSearch => {
+                view_state.status_line.clear_search_prompt();
+                view_state.status_line.message = None;
+                pending_search_state.take();
+                *latest_search_request = None;
+                search_cancel_flag.take();
+                let origin = self
+                    .finish_preview()
+                    .unwrap_or(view_state.viewport_top_byte);
+                self.request_viewport(
+                    ViewportRequest::Absolute(origin),
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await?;
+                Ok(true)
+            }
+            InputAction::ExecuteSearch { pattern, direction } => {
+                if let Some(origin) = self.finish_preview() {
+                    // Search from where the prompt was opened, not from the last preview
+                    self.request_viewport(
+                        ViewportRequest::Absolute(origin),
+                        view_state,
+                        search_tx,
+                        next_request_id,
+                        latest_view_request,
+                    )
+                    .await?;
+                    view_state.viewport_top_byte = origin;
+                }
+                if pattern.is_empty() {
+                    view_state.status_line.clear_search_prompt();
+                    view_state.status_line.message = None;
+                    pending_search_state.take();
+                    search_cancel_flag.take();
+                    let _ = self.dispatch(search_tx, SearchCommand::ClearSearchContext);
+                    self.clear_search(view_state);
+                    self.request_viewport(
+                        ViewportRequest::Absolute(view_state.viewport_top_byte),
+                        view_state,
+                        search_tx,
+                        next_request_id,
+                        latest_view_request,
+                    )
+                    .await?;
+                    return Ok(true);
+                }
+
+                // `\c`, `\C` and `\l` apply to this search only
+                let (modifiers, stripped) = PatternModifiers::strip(&pattern);
+                if stripped.is_empty() {
+                    view_state.status_line.clear_search_prompt();
+                    view_state
+                        .status_line
+                        .set_message(format!("Nothing to search for after {}", modifiers.label()));
+                    return Ok(true);
+                }
+                let options = modifiers.apply(self.search_options.clone());
+                let pattern = stripped.to_string();
+
+                // Expand here rather than in the input thread so the history keeps `@name`
+                let pattern = if options.regex_mode {
+                    match self.patterns.expand(&pattern) {
+                        Ok(Some(expanded)) => {
+                            view_state
+                                .status_line
+                                .set_message(format!("Searching {}", expanded));
+                            expanded
+                        }
+                        Ok(None) => pattern,
+                        Err(message) => {
+                            view_state.status_line.clear_search_prompt();
+                            view_state.status_line.set_message(message);
+                            return Ok(true);
+                        }
+                    }
+                } else {
+                    pattern
+                };
+
+                // As in less, a match on the top line is not found "again"
+                self.queue_search(
+                    pattern,
+                    options,
+                    modifiers,
+                    direction,
+                    true,
+                    self.frequent_match_limit,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_search_request,
+                    search_cancel_flag,
+                    pending_search_state,
+                )
+                .await
+            }
+            InputAction::SearchCurrentMatchWord => {
+                let Some(text) = self.current_match_text.clone() else {
+                    view_state
+                        .status_line
+                        .set_message("No current match".to_string());
+                    return Ok(true);
+                };
+                let pattern = verbatim_pattern(&text, &self.search_options);
+                self.queue_search(
+                    pattern,
+                    self.search_options.clone(),
+                    PatternModifiers::default(),
+                    SearchDirection::Forward,
+                    true,
+                    self.frequent_match_limit,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_search_request,
+                    search_cancel_flag,
+                    pending_search_state,
+                )
+                .await
+            }
+            InputAction::FilterCurrentMatchWord => {
+                let Some(text) = self.current_match_text.clone() else {
+                    view_state
+                        .status_line
+                        .set_message("No current match".to_string());
+                    return Ok(true);
+                };
+                let pattern = verbatim_pattern(&text, &self.search_options);
+                self.apply_filter(
+                    pattern,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::ShowFileInfo => {
+                self.dispatch(search_tx, SearchCommand::DescribeFile)?;
+                Ok(true)
+            }
+            InputAction::NextMatch => {
+                if !self.ensure_active_search(view_state) {
+                    if self.pending_options_update {
+                        view_state
+                            .status_line
+                            .set_message("Search options updated; start a new search.".to_string());
+                    }
+                    return Ok(true);
+                }
+                self.queue_match_navigation(
+                    MatchTraversal::Next,
+                    count,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_search_request,
+                    search_cancel_flag,
+                )
+                .await
+            }
+            InputAction::PreviousMatch => {
+                if !self.ensure_active_search(view_state) {
+                    if self.pending_options_update {
+                        view_state
+                            .status_line
+                            .set_message("Search options updated; start a new search.".to_string());
+                    }
+                    return Ok(true);
+                }
+                self.queue_match_navigation(
+                    MatchTraversal::Previous,
+                    count,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_search_request,
+                    search_cancel_flag,
+                )
+                .await
+            }
+            InputAction::NextNonMatch | InputAction::PreviousNonMatch => {
+                if !self.ensure_active_search(view_state) {
+                    return Ok(true);
+                }
+                let traversal = if action == InputAction::NextNonMatch {
+                    MatchTraversal::NextNonMatch
+                } else {
+                    MatchTraversal::PreviousNonMatch
+                };
+                view_state.status_line.clear_message();
+                let (current_top, origin_row) = self.origin_line(view_state);
+                self.queue_position_jump(search_tx, next_request_id, |request_id, cancel_flag| {
+                    SearchCommand::NavigateMatch {
+                        request_id,
+                        traversal,
+                        current_top,
+                        origin_row,
+                        count: 1,
+                        cancel_flag,
+                    }
+                })?;
+                self.non_match_request = self.position_request.as_ref().map(|&(id, _)| id);
+                Ok(true)
+            }
+            InputAction::Resize { width, height } => {
+                if view_state.update_terminal_size(width, height) {
+                    self.request_viewport(
+                        ViewportRequest::Absolute(view_state.viewport_top_byte),
+                        view_state,
+                        search_tx,
+                        next_request_id,
+                        latest_view_request,
+                    )
+                    .await?;
+                }
+                Ok(true)
+            }
+            InputAction::OpenLinePeek => {
+                let request_id = *next_request_id;
+                *next_request_id += 1;
+                self.peek_request = Some(request_id);
+                view_state.peek = Some(LinePeek::default());
+                self.dispatch(
+                    search_tx,
+                    SearchCommand::PeekLine {
+                        request_id,
+                        row_start: view_state.viewport_top_byte,
+                        highlights: self.highlight_spec(),
+                        filter: self.filter_spec(),
+                    },
+                )?;
+                Ok(true)
+            }
+            InputAction::ScrollLinePeek { direction, lines } => {
+                view_state.scroll_peek(direction, lines);
+                Ok(true)
+            }
+            InputAction::CloseLinePeek => {
+                self.peek_request = None;
+                view_state.peek = None;
+                Ok(true)
+            }
+            InputAction::OpenMatchList => {
+                view_state.status_line.clear_message();
+                self.open_match_list(view_state, search_tx, next_request_id)
+            }
+            InputAction::MoveMatchSelection { direction, lines } => {
+                self.move_match_selection(direction, lines, view_state, search_tx, next_request_id)
+            }
+            InputAction::PageMatchList(direction) => {
+                let rows = view_state.match_list_rows() as u64;
+                self.move_match_selection(direction, rows, view_state, search_tx, next_request_id)
+            }
+            InputAction::JumpToListedMatch => {
+                self.jump_to_listed_match(
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::CloseMatchList => {
+                self.close_match_list(view_state);
+                Ok(true)
+            }
+            InputAction::TogglePresentation => {
+                view_state.status_line.clear_message();
+                if view_state.set_chrome(view_state.chrome.toggled()) {
+                    self.request_viewport(
+                        ViewportRequest::Absolute(view_state.viewport_top_byte),
+                        view_state,
+                        search_tx,
+                        next_request_id,
+                        latest_view_request,
+                    )
+                    .await?;
+                }
+                Ok(true)
+            }
+            InputAction::StartPercentInput => {
+                view_state.status_line.set_message("goto: %".to_string());
+                Ok(true)
+            }
+            InputAction::UpdatePercentBuffer(buffer) => {
+                let display = if buffer.is_empty() {
+                    "goto: %".to_string()
+                } else {
+                    format!("goto: %{}", buffer)
+                };
+                view_state.status_line.set_message(display);
+                Ok(true)
+            }
+            InputAction::CancelPercentInput => {
+                view_state.status_line.clear_message();
+                Ok(true)
+            }
+            InputAction::UpdateCountBuffer(buffer) => {
+                if buffer.is_empty() {
+                    view_state.status_line.clear_message();
+                } else {
+                    view_state
+                        .status_line
+                        .set_message(format!("count: {}", buffer));
+                    self.count_shown = true;
+                }
+                Ok(true)
+            }
+            InputAction::StartCommand => {
+                view_state.status_line.set_message("command: -".to_string());
+                Ok(true)
+            }
+            InputAction::UpdateCommandBuffer(buffer) => {
+                view_state.status_line.set_message(if buffer.is_empty() {
+                    "command: -".to_string()
+                } else {
+                    format!("command: -{}", buffer)
+                });
+                Ok(true)
+            }
+            InputAction::CancelCommand => {
+                view_state.status_line.clear_message();
+                Ok(true)
+            }
+            InputAction::ExecuteCommand { buffer } => {
+                self.execute_flag_command(
+                    &buffer,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::StartColonCommand => {
+                view_state.status_line.set_message(":".to_string());
+                Ok(true)
+            }
+            InputAction::UpdateColonCommandBuffer(buffer) => {
+                view_state.status_line.set_message(format!(":{}", buffer));
+                Ok(true)
+            }
+            InputAction::CancelColonCommand => {
+                view_state.status_line.clear_message();
+                Ok(true)
+            }
+            InputAction::ExecuteColonCommand { buffer } => {
+                self.execute_colon_command(
+                    &buffer,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_search_request,
+                    latest_view_request,
+                    search_cancel_flag,
+                    pending_search_state,
+                )
+                .await
+            }
+            InputAction::StartFilter => {
+                view_state.status_line.set_message("&".to_string());
+                Ok(true)
+            }
+            InputAction::UpdateFilterBuffer(buffer) => {
+                view_state.status_line.set_message(format!("&{}", buffer));
+                Ok(true)
+            }
+            InputAction::CancelFilter => {
+                view_state.status_line.clear_message();
+                Ok(true)
+            }
+            InputAction::ApplyFilter { pattern } => {
+                self.apply_filter(
+                    pattern,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            // Only meaningful while a y/n question is showing, handled above
+            InputAction::ConfirmPrompt => Ok(true),
+            // Unwrapped by `uncount` above
+            InputAction::Counted { .. } => Ok(true),
+            InputAction::InvalidInput => {
+                self.reject_key(view_state, Instant::now());
+                Ok(true)
+            }
+            InputAction::NoAction => Ok(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchOptions;
+    use crate::search::MAX_PATTERN_BYTES;
+
+    #[tokio::test]
+    async fn execute_search_keeps_whitespace_and_rejects_oversized_patterns() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        for pattern in ["  ".to_string(), "x".repeat(MAX_PATTERN_BYTES + 1)] {
+            state
+                .process_action(
+                    InputAction::ExecuteSearch {
+                        pattern,
+                        direction: SearchDirection::Forward,
+                    },
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_view_request,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                )
+                .await
+                .unwrap();
+        }
+
+        match search_rx.try_recv() {
+            Ok(SearchCommand::ExecuteSearch { pattern, .. }) => assert_eq!(&*pattern, "  "),
+            other => panic!("unexpected command: {other:?}"),
+        }
+        assert!(search_rx.try_recv().is_err());
+        assert!(view_state
+            .status_line
+            .message
+            .as_deref()
+            .is_some_and(|message| message.starts_with("Pattern too long")));
+    }
+}
@@ -0,0 +1,519 @@
+//! The render loop.
+
+use super::{
+    RenderLoopState, FOLLOW_POLL_INTERVAL, FRAME_INTERVAL, GROWTH_POLL_INTERVAL,
+    LATEST_POLL_INTERVAL,
+};
+use crate::error::Result;
+use crate::input::InputAction;
+use crate::render::protocol::{RequestId, SearchCommand, SearchHighlightSpec, SearchResponse};
+use crate::render::ui::ViewState;
+use crate::util::CancellationToken;
+use std::sync::Arc;
+use tokio::sync::mpsc::{Sender, UnboundedReceiver};
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+/// Orchestrates the main render loop once channels have been wired.
+pub struct RenderCoordinator;
+
+impl RenderCoordinator {
+    #[allow(clippy::too_many_arguments)]
+    async fn process_pending_actions(
+        state: &mut RenderLoopState,
+        actions: &mut Vec<InputAction>,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+    ) -> Result<bool> {
+        for action in actions.drain(..) {
+            if !state
+                .process_action(
+                    action,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                    latest_search_request,
+                    search_cancel_flag,
+                    pending_search_state,
+                )
+                .await?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn drain_search_responses(
+        state: &mut RenderLoopState,
+        view_state: &mut ViewState,
+        search_resp_rx: &mut tokio::sync::mpsc::Receiver<SearchResponse>,
+        latest_view_request: &mut Option<RequestId>,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+    ) -> Result<bool> {
+        let mut changed = false;
+        while let Ok(response) = search_resp_rx.try_recv() {
+            changed |= !matches!(
+                response,
+                SearchResponse::Progress { .. } | SearchResponse::SearchProgress { .. }
+            );
+            state
+                .handle_response(
+                    response,
+                    view_state,
+                    latest_view_request,
+                    latest_search_request,
+                    search_cancel_flag,
+                    pending_search_state,
+                    search_tx,
+                    next_request_id,
+                )
+                .await?;
+        }
+        Ok(changed)
+    }
+
+    /// Drive the render loop until the user quits or the input source goes away.
+    ///
+    /// Input actions and worker responses are handled the moment they arrive so navigation does
+    /// not wait for the next frame; drawing happens at most once per `FRAME_INTERVAL` and only
+    /// when something changed. The select is biased: a due frame tick comes first, so a steady
+    /// stream of actions or responses cannot starve drawing, and then input, so actions queued
+    /// before a response are applied first.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn run(
+        state: &mut RenderLoopState,
+        view_state: &mut ViewState,
+        ui_renderer: &mut dyn crate::render::ui::UIRenderer,
+        input_rx: &mut UnboundedReceiver<InputAction>,
+        search_tx: &mut Sender<SearchCommand>,
+        search_resp_rx: &mut tokio::sync::mpsc::Receiver<SearchResponse>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+    ) -> Result<()> {
+        let mut frame = time::interval(FRAME_INTERVAL);
+        frame.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut follow_poll = time::interval(FOLLOW_POLL_INTERVAL);
+        follow_poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        // The file was just read: the first check can wait a full interval
+        let mut growth_poll =
+            time::interval_at(Instant::now() + GROWTH_POLL_INTERVAL, GROWTH_POLL_INTERVAL);
+        growth_poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut latest_poll = time::interval(LATEST_POLL_INTERVAL);
+        latest_poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut action_buffer = Vec::new();
+        let mut needs_render = true;
+
+        loop {
+            tokio::select! {
+                biased;
+
+                _ = frame.tick() => {
+                    state.flush_outbox(search_tx)?;
+                    needs_render |= state.apply_worker_status(view_state);
+                    needs_render |= state
+                        .preview_if_due(
+                            view_state,
+                            search_tx,
+                            next_request_id,
+                            latest_view_request,
+                            Instant::now(),
+                        )
+                        .await?;
+                    needs_render |= state.apply_progress(view_state, Instant::now());
+                    needs_render |= state.end_flash(view_state, Instant::now());
+                    if needs_render {
+                        ui_renderer.render(view_state)?;
+                        needs_render = false;
+                    }
+                    if state.take_bell() {
+                        ui_renderer.bell()?;
+                    }
+                }
+                action = input_rx.recv() => {
+                    let Some(action) = action else {
+                        // Input thread exited; nothing can drive the UI any more.
+                        break;
+                    };
+                    action_buffer.push(action);
+                    while let Ok(action) = input_rx.try_recv() {
+                        action_buffer.push(action);
+                    }
+
+                    let running = Self::process_pending_actions(
+                        state,
+                        &mut action_buffer,
+                        view_state,
+                        search_tx,
+                        next_request_id,
+                        latest_view_request,
+                        latest_search_request,
+                        search_cancel_flag,
+                        pending_search_state,
+                    )
+                    .await?;
+                    if !running {
+                        break;
+                    }
+                    needs_render = true;
+                }
+                _ = follow_poll.tick(), if state.tail_mode().is_some() => {
+                    state.dispatch(search_tx, SearchCommand::CheckFileGrowth)?;
+                }
+                _ = growth_poll.tick(), if state.watches_growth() => {
+                    state.dispatch(search_tx, SearchCommand::CheckFileGrowth)?;
+                }
+                _ = latest_poll.tick(), if state.watches_latest() => {
+                    state.check_latest(search_tx)?;
+                }
+                () = state.input_grew() => {
+                    state
+                        .handle_file_growth(
+                            view_state,
+                            search_tx,
+                            next_request_id,
+                            latest_view_request,
+                        )
+                        .await?;
+                }
+                response = search_resp_rx.recv(), if state.worker_is_up() => {
+                    let Some(response) = response else {
+                        // Keep the UI up so the status line can say what happened
+                        state.worker_closed();
+                        continue;
+                    };
+                    // Progress only reaches the screen through `apply_progress` on a frame tick
+                    let mut changed = !matches!(
+                        response,
+                        SearchResponse::Progress { .. } | SearchResponse::SearchProgress { .. }
+                    );
+                    state
+                        .handle_response(
+                            response,
+                            view_state,
+                            latest_view_request,
+                            latest_search_request,
+                            search_cancel_flag,
+                            pending_search_state,
+                            search_tx,
+                            next_request_id,
+                        )
+                        .await?;
+                    changed |= Self::drain_search_responses(
+                        state,
+                        view_state,
+                        search_resp_rx,
+                        latest_view_request,
+                        latest_search_request,
+                        search_cancel_flag,
+                        pending_search_state,
+                        search_tx,
+                        next_request_id,
+                    )
+                    .await?;
+                    needs_render |= changed;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::PROGRESS_INTERVAL;
+    use super::*;
+    use crate::input::ScrollDirection;
+    use crate::render::protocol::ProgressOperation;
+    use crate::render::ui::MockUIRenderer;
+    use crate::search::SearchOptions;
+    use tokio::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn actions_and_responses_are_applied_without_waiting_for_a_frame() {
+        let (input_tx, mut input_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let (resp_tx, mut resp_rx) = tokio::sync::mpsc::channel(8);
+
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut renderer = MockUIRenderer::new();
+        let mut next_request_id: RequestId = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        let coordinator = RenderCoordinator::run(
+            &mut state,
+            &mut view_state,
+            &mut renderer,
+            &mut input_rx,
+            &mut search_tx,
+            &mut resp_rx,
+            &mut next_request_id,
+            &mut latest_view_request,
+            &mut latest_search_request,
+            &mut search_cancel_flag,
+            &mut pending_search_state,
+        );
+
+        let driver = async {
+            // Let the coordinator reach its first await before timing anything.
+            time::sleep(Duration::from_millis(1)).await;
+
+            let sent_at = Instant::now();
+            input_tx
+                .send(InputAction::Scroll {
+                    direction: ScrollDirection::Down,
+                    lines: 1,
+                })
+                .unwrap();
+            let request_id = match search_rx.recv().await.unwrap() {
+                SearchCommand::LoadViewport { request_id, .. } => request_id,
+                other => panic!("unexpected command: {other:?}"),
+            };
+            // Virtual time only advances when every task is idle, so a coordinator that waited
+            // for the frame timer would show up here as a multi-millisecond delay.
+            assert!(
+                sent_at.elapsed() < Duration::from_millis(1),
+                "action should be forwarded without waiting for a frame tick"
+            );
+
+            resp_tx
+                .send(SearchResponse::ViewportLoaded {
+                    request_id,
+                    top_byte: 6,
+                    lines: vec!["second".to_string()],
+                    highlights: vec![Vec::new()],
+                    at_eof: false,
+                    noeol: false,
+                    highlights_truncated: false,
+                    file_size: 12,
+                    top_line: None,
+                    line_position: None,
+                    current_match_row: None,
+                })
+                .await
+                .unwrap();
+
+            // The response is applied before virtual time can advance past this sleep.
+            time::sleep(Duration::from_millis(1)).await;
+            input_tx.send(InputAction::Quit).unwrap();
+        };
+
+        let (result, ()) = tokio::join!(coordinator, driver);
+        result.unwrap();
+
+        assert_eq!(view_state.viewport_top_byte, 6);
+        assert_eq!(view_state.visible_lines, vec!["second".to_string()]);
+        let latency = state.last_viewport_latency().expect("latency recorded");
+        assert!(latency < Duration::from_millis(1));
+        assert!(renderer.render_count >= 1);
+    }
+
+    #[tokio::test]
+    async fn quit_is_processed_while_the_worker_channel_is_full() {
+        let (input_tx, mut input_rx) = tokio::sync::mpsc::unbounded_channel();
+        // Nobody reads the commands: the worker is stalled
+        let (mut search_tx, _search_rx) = tokio::sync::mpsc::channel(2);
+        let (_resp_tx, mut resp_rx) = tokio::sync::mpsc::channel(8);
+
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut renderer = MockUIRenderer::new();
+        let mut next_request_id: RequestId = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        for _ in 0..50 {
+            input_tx.send(InputAction::PageDown).unwrap();
+        }
+        input_tx.send(InputAction::ShowFileInfo).unwrap();
+        input_tx.send(InputAction::PageDown).unwrap();
+        input_tx.send(InputAction::Quit).unwrap();
+
+        let coordinator = RenderCoordinator::run(
+            &mut state,
+            &mut view_state,
+            &mut renderer,
+            &mut input_rx,
+            &mut search_tx,
+            &mut resp_rx,
+            &mut next_request_id,
+            &mut latest_view_request,
+            &mut latest_search_request,
+            &mut search_cancel_flag,
+            &mut pending_search_state,
+        );
+        time::timeout(Duration::from_secs(1), coordinator)
+            .await
+            .expect("quit was blocked behind the full channel")
+            .unwrap();
+
+        // Two loads fit in the channel; of the other 49 only the newest is still waiting, behind
+        // the file info request that was queued before it
+        assert_eq!(state.queued_commands(), 2);
+        assert_eq!(state.coalesced_commands(), 48);
+        assert!(matches!(
+            state.outbox.front(),
+            Some(SearchCommand::DescribeFile)
+        ));
+    }
+
+    #[tokio::test]
+    async fn ampersand_star_filters_by_the_current_match_text() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        act!(InputAction::FilterCurrentMatchWord);
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("No current match")
+        );
+        assert!(search_rx.try_recv().is_err());
+        assert!(state.filter_spec().is_none());
+
+        // Escaped, so the text is matched as it was shown rather than as a regex
+        state.current_match_text = Some("open(a.log)".to_string());
+        act!(InputAction::FilterCurrentMatchWord);
+        let Ok(SearchCommand::LoadViewport { filter, .. }) = search_rx.try_recv() else {
+            panic!("expected viewport load");
+        };
+        let filter = filter.expect("a filter");
+        assert_eq!(&*filter.pattern, r"open\(a\.log\)");
+        assert_eq!(filter.options, SearchOptions::default());
+        assert_eq!(
+            view_state.status_line.filter.as_deref(),
+            Some(r"open\(a\.log\)")
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn progress_bursts_render_a_few_times_per_second() {
+        let (input_tx, mut input_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (mut search_tx, _search_rx) = tokio::sync::mpsc::channel(8);
+        let (resp_tx, mut resp_rx) = tokio::sync::mpsc::channel(8);
+
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut renderer = MockUIRenderer::new();
+        let mut next_request_id: RequestId = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        let coordinator = RenderCoordinator::run(
+            &mut state,
+            &mut view_state,
+            &mut renderer,
+            &mut input_rx,
+            &mut search_tx,
+            &mut resp_rx,
+            &mut next_request_id,
+            &mut latest_view_request,
+            &mut latest_search_request,
+            &mut search_cancel_flag,
+            &mut pending_search_state,
+        );
+
+        let driver = async {
+            // 500 updates over one second, several per frame
+            for step in 0..500 {
+                resp_tx
+                    .send(SearchResponse::Progress {
+                        operation: ProgressOperation::Search,
+                        fraction: step as f32 / 500.0,
+                    })
+                    .await
+                    .unwrap();
+                time::sleep(Duration::from_millis(2)).await;
+            }
+            time::sleep(PROGRESS_INTERVAL).await;
+            input_tx.send(InputAction::Quit).unwrap();
+        };
+
+        let (result, ()) = tokio::join!(coordinator, driver);
+        result.unwrap();
+
+        // The initial frame plus one per progress interval, instead of one per frame
+        assert!(
+            renderer.render_count <= 7,
+            "rendered {} frames",
+            renderer.render_count
+        );
+        assert_eq!(
+            view_state.status_line.progress.as_deref(),
+            Some("searching 99%")
+        );
+    }
+
+    #[test]
+    fn finished_progress_clears_without_waiting() {
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let start = Instant::now();
+        let progress = |state: &mut RenderLoopState, operation, fraction| {
+            state.pending_progress.insert(operation, fraction);
+        };
+
+        progress(&mut state, ProgressOperation::Decompress, 0.5);
+        progress(&mut state, ProgressOperation::Count, 0.104);
+        assert!(state.apply_progress(&mut view_state, start));
+        assert_eq!(
+            view_state.status_line.progress.as_deref(),
+            Some("counting 10%, decompressing 50%")
+        );
+
+        // Too soon for a new percentage, but a finished operation is dropped at once
+        progress(&mut state, ProgressOperation::Count, 0.2);
+        assert!(!state.apply_progress(&mut view_state, start));
+        progress(&mut state, ProgressOperation::Decompress, 1.0);
+        assert!(state.apply_progress(&mut view_state, start));
+        assert_eq!(
+            view_state.status_line.progress.as_deref(),
+            Some("counting 20%")
+        );
+    }
+}
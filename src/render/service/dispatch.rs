@@ -0,0 +1,463 @@
+//! Sending commands to the search worker.
+
+use super::RenderLoopState;
+use crate::error::Result;
+use crate::render::protocol::{RequestId, SearchCommand, ViewportRequest};
+use crate::render::ui::ViewState;
+use crate::util::CancellationToken;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender;
+use tokio::time::Instant;
+
+/// Whether commands reach the search worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum WorkerLink {
+    Up,
+    /// The channel closed: nothing restarts the worker, so commands are dropped and the status
+    /// line advises a restart
+    Lost,
+}
+
+impl RenderLoopState {
+    pub(super) async fn queue_viewport_update(
+        &mut self,
+        request: ViewportRequest,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        view_state.at_eof = false;
+        self.request_viewport(
+            request,
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await?;
+        Ok(true)
+    }
+
+    pub(super) fn is_position_request(&self, request_id: RequestId) -> bool {
+        matches!(&self.position_request, Some((id, _)) if *id == request_id)
+    }
+
+    /// Send a jump to a position the worker computes (`:skip-dups`, `[`/`]`); the answer
+    /// arrives as `PositionFound`
+    ///
+    /// `command` builds the request from its id and cancel flag. Only the newest jump matters,
+    /// so one still in flight is cancelled.
+    pub(super) fn queue_position_jump(
+        &mut self,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        command: impl FnOnce(RequestId, CancellationToken) -> SearchCommand,
+    ) -> Result<bool> {
+        let request_id = *next_request_id;
+        *next_request_id += 1;
+        let cancel_flag = CancellationToken::new();
+        if let Some((_, superseded)) = self
+            .position_request
+            .replace((request_id, cancel_flag.clone()))
+        {
+            superseded.cancel();
+        }
+        self.dispatch(search_tx, command(request_id, cancel_flag))?;
+        Ok(true)
+    }
+
+    /// Stop the operation answering `request_id`, which runs under `token`
+    ///
+    /// Flipping the token reaches the command wherever it is: in the outbox, in the channel or
+    /// running in the worker. `Cancel` reaches work the worker left running in the background.
+    /// The operation still answers, as cancelled, unless it was one nobody waits for.
+    pub(super) fn cancel_request(
+        &mut self,
+        search_tx: &Sender<SearchCommand>,
+        request_id: RequestId,
+        token: &CancellationToken,
+    ) -> Result<()> {
+        token.cancel();
+        self.dispatch(search_tx, SearchCommand::Cancel { request_id })
+    }
+
+    /// Hand `command` to the worker without waiting for room in the channel
+    ///
+    /// Awaiting a full channel would stall action processing, and with it `Quit`, behind a
+    /// backlog of viewport loads. Instead the command waits in the outbox until `flush_outbox`
+    /// finds room. A waiting command is dropped once a newer one supersedes it (see
+    /// `supersedes`), so the outbox never holds more than one command of each kind.
+    pub(super) fn dispatch(
+        &mut self,
+        search_tx: &Sender<SearchCommand>,
+        command: SearchCommand,
+    ) -> Result<()> {
+        log::trace!(
+            "coordinator: dispatch {} request={:?} outbox={}",
+            command.name(),
+            command.request_id(),
+            self.outbox.len()
+        );
+        self.flush_outbox(search_tx)?;
+        match self.worker_link {
+            WorkerLink::Lost => {
+                log::debug!("coordinator: worker lost, dropped {}", command.name());
+            }
+            WorkerLink::Up if self.outbox.is_empty() => match search_tx.try_send(command) {
+                Ok(()) => {}
+                Err(TrySendError::Full(command)) => self.enqueue(command),
+                Err(TrySendError::Closed(_)) => self.worker_closed(),
+            },
+            WorkerLink::Up => self.enqueue(command),
+        }
+        Ok(())
+    }
+
+    /// The worker channel was found closed
+    ///
+    /// The worker only stops on `Shutdown` or by panicking, and nothing spawns it again, so it is
+    /// given up at once: queued commands are dropped and later ones are never sent.
+    pub(super) fn worker_closed(&mut self) {
+        if self.worker_link == WorkerLink::Up {
+            log::error!(
+                "coordinator: search worker gone, dropping {} queued command(s)",
+                self.outbox.len()
+            );
+        }
+        self.outbox.clear();
+        self.worker_link = WorkerLink::Lost;
+    }
+
+    /// Whether the worker channel is open as far as the coordinator knows
+    pub fn worker_is_up(&self) -> bool {
+        self.worker_link == WorkerLink::Up
+    }
+
+    /// Show a lost worker on the status line; returns whether that changed it
+    ///
+    /// The notice is a standing alert rather than a message, so it is set once instead of on
+    /// every action, and stays for the rest of the session.
+    pub fn apply_worker_status(&self, view_state: &mut ViewState) -> bool {
+        let alert = match self.worker_link {
+            WorkerLink::Up => None,
+            WorkerLink::Lost => Some("search worker stopped; restart rlless to search again"),
+        };
+        if view_state.status_line.alert.as_deref() == alert {
+            return false;
+        }
+        view_state.status_line.alert = alert.map(str::to_string);
+        true
+    }
+
+    fn enqueue(&mut self, command: SearchCommand) {
+        let before = self.outbox.len();
+        self.outbox.retain(|queued| !supersedes(&command, queued));
+        let dropped = before - self.outbox.len();
+        if dropped > 0 {
+            log::debug!(
+                "coordinator: {} queued command(s) superseded by {}",
+                dropped,
+                command.name()
+            );
+        }
+        self.coalesced_commands += dropped as u64;
+        self.outbox.push_back(command);
+    }
+
+    /// Move commands waiting in the outbox into the worker channel while it has room
+    pub fn flush_outbox(&mut self, search_tx: &Sender<SearchCommand>) -> Result<()> {
+        if self.worker_link == WorkerLink::Lost {
+            return Ok(());
+        }
+        while let Some(command) = self.outbox.pop_front() {
+            match search_tx.try_send(command) {
+                Ok(()) => {}
+                Err(TrySendError::Full(command)) => {
+                    self.outbox.push_front(command);
+                    break;
+                }
+                Err(TrySendError::Closed(_)) => {
+                    self.worker_closed();
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Commands still waiting for room in the worker channel
+    pub fn queued_commands(&self) -> usize {
+        self.outbox.len()
+    }
+
+    /// Commands dropped so far because a newer one superseded them while queued
+    pub fn coalesced_commands(&self) -> u64 {
+        self.coalesced_commands
+    }
+
+    pub(super) async fn request_viewport(
+        &mut self,
+        top: ViewportRequest,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<RequestId> {
+        view_state.expect_page(top);
+        let request_id = *next_request_id;
+        *next_request_id += 1;
+        let _ = latest_view_request.replace(request_id);
+        self.viewport_requested_at = Some((request_id, Instant::now()));
+        let cancel_flag = CancellationToken::new();
+        if let Some(superseded) = self.viewport_cancel_flag.replace(cancel_flag.clone()) {
+            superseded.cancel();
+        }
+        self.dispatch(
+            search_tx,
+            SearchCommand::LoadViewport {
+                request_id,
+                top,
+                page_lines: view_state.lines_per_page() as usize,
+                highlights: self.highlight_spec(),
+                filter: self.filter_spec(),
+                current_match: self.current_match,
+                cancel_flag,
+            },
+        )?;
+        Ok(request_id)
+    }
+}
+
+/// Whether `newer` makes the queued command pointless to send
+///
+/// Each pair shares a request slot, so the coordinator would ignore the queued command's answer,
+/// or repeats an idempotent context change. A navigation never supersedes the queued search it
+/// depends on, a preview never supersedes a real search, and `Shutdown` is never dropped.
+fn supersedes(newer: &SearchCommand, queued: &SearchCommand) -> bool {
+    use SearchCommand::*;
+    if is_position_jump(newer) || is_position_jump(queued) {
+        return is_position_jump(newer) && is_position_jump(queued);
+    }
+    matches!(
+        (newer, queued),
+        (LoadViewport { .. }, LoadViewport { .. })
+            | (
+                ExecuteSearch { preview: false, .. },
+                ExecuteSearch { .. } | NavigateMatch { .. }
+            )
+            | (
+                ExecuteSearch { preview: true, .. },
+                ExecuteSearch { preview: true, .. }
+            )
+            | (NavigateMatch { .. }, NavigateMatch { .. })
+            | (PeekLine { .. }, PeekLine { .. })
+            | (CollectMatches { .. }, CollectMatches { .. })
+            | (DescribeFile, DescribeFile)
+            | (CheckFileGrowth, CheckFileGrowth)
+            | (SwitchToLatest { .. }, SwitchToLatest { .. })
+            | (ShowLineNumbers(_), ShowLineNumbers(_))
+            | (ShowLinePosition(_), ShowLinePosition(_))
+            | (ShowHighlights(_), ShowHighlights(_))
+            | (KeepPatterns(_), KeepPatterns(_))
+            | (ClearSearchContext, ClearSearchContext)
+    )
+}
+
+/// Whether `command` answers with `PositionFound`, sharing the slot `queue_position_jump` keeps
+fn is_position_jump(command: &SearchCommand) -> bool {
+    match command {
+        SearchCommand::SkipDuplicates { .. }
+        | SearchCommand::NavigateRecord { .. }
+        | SearchCommand::NavigateTime { .. } => true,
+        SearchCommand::NavigateMatch { traversal, .. } => traversal.is_non_match(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::SearchDirection;
+    use crate::render::protocol::MatchTraversal;
+    use crate::search::SearchOptions;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn queued_commands_are_flushed_in_order_once_the_worker_catches_up() {
+        let (search_tx, mut search_rx) = tokio::sync::mpsc::channel(1);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let load = |request_id| SearchCommand::LoadViewport {
+            request_id,
+            top: ViewportRequest::Absolute(0),
+            page_lines: 10,
+            highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
+        };
+
+        state.dispatch(&search_tx, load(1)).unwrap();
+        state.dispatch(&search_tx, load(2)).unwrap();
+        state
+            .dispatch(&search_tx, SearchCommand::DescribeFile)
+            .unwrap();
+        state.dispatch(&search_tx, load(3)).unwrap();
+        assert_eq!(
+            (state.queued_commands(), state.coalesced_commands()),
+            (2, 1)
+        );
+
+        let mut received = Vec::new();
+        while let Ok(command) = search_rx.try_recv() {
+            received.push(match command {
+                SearchCommand::LoadViewport { request_id, .. } => format!("load {request_id}"),
+                other => format!("{other:?}"),
+            });
+            state.flush_outbox(&search_tx).unwrap();
+        }
+        assert_eq!(received, vec!["load 1", "DescribeFile", "load 3"]);
+        assert_eq!(state.queued_commands(), 0);
+    }
+
+    fn load(request_id: RequestId) -> SearchCommand {
+        SearchCommand::LoadViewport {
+            request_id,
+            top: ViewportRequest::Absolute(0),
+            page_lines: 10,
+            highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_closed_worker_channel_is_given_up_at_once() {
+        let (search_tx, search_rx) = tokio::sync::mpsc::channel(8);
+        drop(search_rx);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+
+        state.dispatch(&search_tx, load(1)).unwrap();
+        assert!(!state.worker_is_up());
+        assert_eq!(state.queued_commands(), 0);
+        assert!(state.apply_worker_status(&mut view_state));
+        assert!(view_state
+            .format_status_line()
+            .ends_with("restart rlless to search again"));
+
+        // Later commands are dropped without touching the status line again
+        state.dispatch(&search_tx, load(2)).unwrap();
+        state.flush_outbox(&search_tx).unwrap();
+        assert_eq!(state.queued_commands(), 0);
+        assert!(!state.apply_worker_status(&mut view_state));
+    }
+
+    #[tokio::test]
+    async fn queued_commands_are_dropped_when_the_worker_channel_closes() {
+        let (search_tx, search_rx) = tokio::sync::mpsc::channel(1);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+
+        state.dispatch(&search_tx, load(1)).unwrap();
+        state
+            .dispatch(&search_tx, SearchCommand::DescribeFile)
+            .unwrap();
+        assert_eq!(state.queued_commands(), 1);
+        drop(search_rx);
+        state.flush_outbox(&search_tx).unwrap();
+        assert!(!state.worker_is_up());
+        assert_eq!(state.queued_commands(), 0);
+    }
+
+    #[tokio::test]
+    async fn every_kind_of_queued_command_is_coalesced() {
+        let (search_tx, _search_rx) = tokio::sync::mpsc::channel(1);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        state
+            .dispatch(&search_tx, SearchCommand::ClearSearchContext)
+            .unwrap();
+        let cancel_flag = || CancellationToken::new();
+        for request_id in 0..100 {
+            let commands = [
+                SearchCommand::ExecuteSearch {
+                    request_id,
+                    pattern: Arc::from("err"),
+                    direction: SearchDirection::Forward,
+                    origin_byte: 0,
+                    origin_row: 0,
+                    skip_origin_row: false,
+                    options: SearchOptions::default(),
+                    frequency_limit: None,
+                    preview: false,
+                    cancel_flag: cancel_flag(),
+                },
+                SearchCommand::NavigateMatch {
+                    request_id,
+                    traversal: MatchTraversal::Next,
+                    current_top: 0,
+                    origin_row: 0,
+                    count: 1,
+                    cancel_flag: cancel_flag(),
+                },
+                SearchCommand::SkipDuplicates {
+                    request_id,
+                    direction: SearchDirection::Forward,
+                    current_top: 0,
+                    cancel_flag: cancel_flag(),
+                },
+                SearchCommand::PeekLine {
+                    request_id,
+                    row_start: 0,
+                    highlights: None,
+                    filter: None,
+                },
+                SearchCommand::DescribeFile,
+                SearchCommand::CheckFileGrowth,
+                SearchCommand::SwitchToLatest {
+                    pattern: "app-*.log".into(),
+                },
+            ];
+            for command in commands {
+                state.dispatch(&search_tx, command).unwrap();
+            }
+        }
+
+        // The last search and the navigation after it both survive
+        assert_eq!(state.queued_commands(), 7);
+        assert_eq!(state.coalesced_commands(), 99 * 7);
+        assert!(matches!(
+            state.outbox.front(),
+            Some(SearchCommand::ExecuteSearch { request_id: 99, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn a_new_viewport_load_cancels_the_one_in_flight() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+
+        let mut flags = Vec::new();
+        for byte in [0, 100] {
+            state
+                .request_viewport(
+                    ViewportRequest::Absolute(byte),
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_view_request,
+                )
+                .await
+                .unwrap();
+            let Ok(SearchCommand::LoadViewport { cancel_flag, .. }) = search_rx.try_recv() else {
+                panic!("expected viewport load");
+            };
+            flags.push(cancel_flag);
+        }
+        assert!(flags[0].is_cancelled());
+        assert!(!flags[1].is_cancelled());
+    }
+}
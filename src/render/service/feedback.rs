@@ -0,0 +1,120 @@
+//! Answering keys that do nothing.
+//!
+//! An unbound key, or one a prompt has no use for, rings the bell, flashes the status line or is
+//! ignored, per `--invalid-key`. The first few of a session also say so on the status line.
+
+use super::{RenderLoopState, FLASH_DURATION, INVALID_KEY_HINTS};
+use crate::render::ui::{InvalidKeyFeedback, ViewState};
+use tokio::time::Instant;
+
+impl RenderLoopState {
+    /// How to answer a key that does nothing (the bell by default)
+    pub fn set_invalid_key_feedback(&mut self, feedback: InvalidKeyFeedback) {
+        self.invalid_key_feedback = feedback;
+    }
+
+    /// Answer a key that does nothing with the configured feedback
+    pub(super) fn reject_key(&mut self, view_state: &mut ViewState, now: Instant) {
+        match self.invalid_key_feedback {
+            InvalidKeyFeedback::Bell => self.bell_pending = true,
+            InvalidKeyFeedback::Flash => {
+                view_state.status_line.flash = true;
+                self.flash_until = Some(now + FLASH_DURATION);
+            }
+            InvalidKeyFeedback::None => {}
+        }
+        // A prompt or a message already on the status line says more than the hint would
+        let status = &view_state.status_line;
+        if self.key_hints_shown < INVALID_KEY_HINTS
+            && status.message.is_none()
+            && status.search_prompt.is_none()
+        {
+            self.key_hints_shown += 1;
+            view_state
+                .status_line
+                .set_message("Unbound key (the README lists the keys)".to_string());
+        }
+    }
+
+    /// Take the status line out of reverse video once its flash is over; returns whether it was
+    pub fn end_flash(&mut self, view_state: &mut ViewState, now: Instant) -> bool {
+        match self.flash_until {
+            Some(until) if now >= until => {
+                self.flash_until = None;
+                view_state.status_line.flash = false;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputAction;
+    use crate::render::protocol::SearchCommand;
+    use crate::search::SearchOptions;
+
+    async fn press_unbound_key(state: &mut RenderLoopState, view_state: &mut ViewState) {
+        let (mut search_tx, _search_rx) = tokio::sync::mpsc::channel::<SearchCommand>(8);
+        assert!(state
+            .process_action(
+                InputAction::InvalidInput,
+                view_state,
+                &mut search_tx,
+                &mut 1,
+                &mut None,
+                &mut None,
+                &mut None,
+                &mut None,
+            )
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn invalid_key_rings_the_bell_by_default_and_hints_a_few_times() {
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+
+        for _ in 0..INVALID_KEY_HINTS {
+            press_unbound_key(&mut state, &mut view_state).await;
+            assert!(state.take_bell());
+            assert!(!view_state.status_line.flash);
+            assert_eq!(
+                view_state.status_line.message.as_deref(),
+                Some("Unbound key (the README lists the keys)")
+            );
+            view_state.status_line.clear_message();
+        }
+        press_unbound_key(&mut state, &mut view_state).await;
+        assert!(state.take_bell());
+        assert!(view_state.status_line.message.is_none());
+    }
+
+    #[tokio::test]
+    async fn invalid_key_flashes_the_status_line_when_configured() {
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        state.set_invalid_key_feedback(InvalidKeyFeedback::Flash);
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.status_line.set_message("goto: 4".to_string());
+
+        press_unbound_key(&mut state, &mut view_state).await;
+        assert!(view_state.status_line.flash);
+        assert!(!state.take_bell());
+        // The prompt stays
+        assert_eq!(view_state.status_line.message.as_deref(), Some("goto: 4"));
+
+        let now = Instant::now();
+        assert!(!state.end_flash(&mut view_state, now));
+        assert!(view_state.status_line.flash);
+        assert!(state.end_flash(&mut view_state, now + FLASH_DURATION));
+        assert!(!view_state.status_line.flash);
+        assert!(!state.end_flash(&mut view_state, now + FLASH_DURATION));
+
+        state.set_invalid_key_feedback(InvalidKeyFeedback::None);
+        press_unbound_key(&mut state, &mut view_state).await;
+        assert!(!view_state.status_line.flash && !state.take_bell());
+    }
+}
@@ -0,0 +1,127 @@
+//! Moving on to a newer file matching `--latest` while following the tail.
+
+use super::RenderLoopState;
+use crate::error::Result;
+use crate::render::protocol::{RequestId, SearchCommand, ViewportRequest};
+use crate::render::ui::{TailMode, ViewState};
+use std::path::PathBuf;
+use tokio::sync::mpsc::Sender;
+
+impl RenderLoopState {
+    /// Move on to the newest file matching `pattern` (`--latest`) while following the tail
+    pub fn set_latest(&mut self, pattern: PathBuf) {
+        self.latest = Some(pattern);
+    }
+
+    /// Whether to look for a newer `--latest` file: only while stuck to the tail, so a view
+    /// scrolled away from it is never pulled into another file
+    pub fn watches_latest(&self) -> bool {
+        self.latest.is_some() && self.tail_mode == Some(TailMode::Sticky)
+    }
+
+    /// Ask the worker to switch to a newer `--latest` file if one appeared
+    pub fn check_latest(&mut self, search_tx: &Sender<SearchCommand>) -> Result<()> {
+        if !self.watches_latest() {
+            return Ok(());
+        }
+        let pattern = self.latest.clone().unwrap_or_default();
+        self.dispatch(search_tx, SearchCommand::SwitchToLatest { pattern })
+    }
+
+    /// Show the file the worker switched to, from its end
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn handle_file_switch(
+        &mut self,
+        path: PathBuf,
+        file_size: u64,
+        sections: Vec<u64>,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into(),
+        );
+        view_state
+            .status_line
+            .set_message(format!("switched to {}", name));
+        view_state.file_path = path;
+        view_state.file_size = Some(file_size);
+        view_state.set_sections(sections);
+        self.queue_viewport_update(
+            ViewportRequest::EndOfFile,
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::protocol::SearchResponse;
+    use crate::search::SearchOptions;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn newer_latest_file_is_only_looked_for_at_the_tail() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("logs/app-01.log", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        state.set_latest(PathBuf::from("logs/app-*.log"));
+        assert!(!state.watches_latest());
+        state.set_following(true, &mut view_state);
+        assert!(state.watches_latest());
+        state.check_latest(&search_tx).unwrap();
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::SwitchToLatest { pattern }) if pattern == Path::new("logs/app-*.log")
+        ));
+
+        // Scrolled away from the tail: no switching underneath the reader
+        state.update_tail_mode(TailMode::Paused, &mut view_state);
+        assert!(!state.watches_latest());
+        state.check_latest(&search_tx).unwrap();
+        assert!(search_rx.try_recv().is_err());
+
+        state.update_tail_mode(TailMode::Sticky, &mut view_state);
+        state
+            .handle_response(
+                SearchResponse::FileSwitched {
+                    path: PathBuf::from("logs/app-02.log"),
+                    file_size: 42,
+                    sections: Vec::new(),
+                },
+                &mut view_state,
+                &mut latest_view_request,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+                &mut search_tx,
+                &mut next_request_id,
+            )
+            .await
+            .unwrap();
+        assert_eq!(view_state.file_path, PathBuf::from("logs/app-02.log"));
+        assert_eq!(view_state.file_size, Some(42));
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("switched to app-02.log")
+        );
+        let Ok(SearchCommand::LoadViewport { top, .. }) = search_rx.try_recv() else {
+            panic!("expected viewport load");
+        };
+        assert_eq!(top, ViewportRequest::EndOfFile);
+    }
+}
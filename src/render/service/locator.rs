@@ -0,0 +1,96 @@
+//! Shareable locators for the current position (`:loc`).
+//!
+//! A locator names the file, the byte at the top of the viewport and the active search:
+//! `app.log@byte=123456789&pattern=timeout&flags=i,re,w`. Passing it as the file argument opens
+//! the file at that byte with the search active (see `parse_locator` in `main.rs`). `flags` is
+//! the `SearchOptions::summary` of the search, left out when the options are the defaults. The
+//! pattern is percent-encoded where it holds characters the locator syntax or a shell would
+//! trip over.
+
+use crate::render::protocol::SearchHighlightSpec;
+use crate::render::ui::ViewState;
+use crate::search::SearchOptions;
+use std::fmt::Write;
+
+/// Locator for the viewport top of `view_state`, with `search` when it is a single pattern
+pub fn locator(view_state: &ViewState, search: Option<&SearchHighlightSpec>) -> String {
+    let mut text = format!(
+        "{}@byte={}",
+        view_state.file_path.display(),
+        view_state.viewport_top_byte
+    );
+    // A pattern set lives in a file the recipient may not have
+    if let Some(search) = search.filter(|search| !search.options.pattern_set) {
+        text.push_str("&pattern=");
+        text.push_str(&encode(&search.pattern));
+        if search.options != SearchOptions::default() {
+            text.push_str("&flags=");
+            text.push_str(&search.options.summary());
+        }
+    }
+    text
+}
+
+/// `text` with `%`, the separators and anything but printable ASCII as `%XX` escapes
+fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_graphic() && !b"%&=@#'\"`".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            // Writing into a String cannot fail
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::PatternModifiers;
+    use std::sync::Arc;
+
+    fn spec(pattern: &str, options: SearchOptions) -> SearchHighlightSpec {
+        SearchHighlightSpec {
+            pattern: Arc::from(pattern),
+            options,
+            modifiers: PatternModifiers::default(),
+        }
+    }
+
+    #[test]
+    fn locator_names_the_top_byte_and_the_search() {
+        let mut view_state = ViewState::new("/var/log/app.log", 80, 24);
+        view_state.viewport_top_byte = 123_456_789;
+        assert_eq!(
+            locator(&view_state, None),
+            "/var/log/app.log@byte=123456789"
+        );
+
+        let search = spec(
+            "user=bob & 50%",
+            SearchOptions {
+                case_sensitive: false,
+                whole_word: true,
+                ..SearchOptions::default()
+            },
+        );
+        assert_eq!(
+            locator(&view_state, Some(&search)),
+            "/var/log/app.log@byte=123456789&pattern=user%3Dbob%20%26%2050%25&flags=i,re,w"
+        );
+
+        let set = spec(
+            "a\nb",
+            SearchOptions {
+                pattern_set: true,
+                ..SearchOptions::default()
+            },
+        );
+        assert_eq!(
+            locator(&view_state, Some(&set)),
+            "/var/log/app.log@byte=123456789"
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! The match list panel (`M`).
+//!
+//! The panel lists the lines matching the active search, `MATCH_LIST_BATCH` at a time: the
+//! first batch is fetched on opening and the next one once the selection nears the end of what
+//! is listed, so a 40GB file is never scanned further than the user scrolls. Enter shows the
+//! selected line at the top of the screen; Esc closes the panel and leaves the view as it was.
+
+use super::search::pattern_label;
+use super::RenderLoopState;
+use crate::error::Result;
+use crate::input::ScrollDirection;
+use crate::render::protocol::{RequestId, SearchCommand, SearchHighlightSpec, ViewportRequest};
+use crate::render::ui::{MatchList, TailMode, ViewState};
+use crate::util::CancellationToken;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+/// Matching lines fetched per request
+pub const MATCH_LIST_BATCH: usize = 1_000;
+
+/// Search the open match list shows, and its outstanding fetch
+pub(super) struct MatchListSource {
+    search: Arc<SearchHighlightSpec>,
+    request: Option<(RequestId, CancellationToken)>,
+}
+
+impl RenderLoopState {
+    /// Open the match list on the matches of the active search, from the start of the file
+    pub(super) fn open_match_list(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+    ) -> Result<bool> {
+        self.close_match_list(view_state);
+        let Some(search) = self.search_state.clone() else {
+            // Open all the same: the input thread already routes keys to the panel
+            view_state.match_list = Some(MatchList {
+                title: "matches".to_string(),
+                message: Some("No active search; search with / first".to_string()),
+                ..MatchList::default()
+            });
+            return Ok(true);
+        };
+        view_state.match_list = Some(MatchList {
+            title: format!("matches of {}", pattern_label(&search)),
+            more_from: Some(0),
+            ..MatchList::default()
+        });
+        self.match_source = Some(MatchListSource {
+            search,
+            request: None,
+        });
+        self.fetch_more_matches(view_state, search_tx, next_request_id)?;
+        Ok(true)
+    }
+
+    /// Move the selection, fetching the next batch when it nears the end of the list
+    pub(super) fn move_match_selection(
+        &mut self,
+        direction: ScrollDirection,
+        lines: u64,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+    ) -> Result<bool> {
+        view_state.move_match_selection(direction, lines);
+        self.fetch_more_matches(view_state, search_tx, next_request_id)?;
+        Ok(true)
+    }
+
+    /// Close the match list and bring the selected line to the top of the screen
+    pub(super) async fn jump_to_listed_match(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        let Some(list) = self.close_match_list(view_state) else {
+            return Ok(true);
+        };
+        let Some(byte) = list.selected_byte() else {
+            return Ok(true);
+        };
+        view_state.status_line.set_message(format!(
+            "Match {} of {}",
+            list.selected + 1,
+            list.count_label()
+        ));
+        self.update_tail_mode(TailMode::Paused, view_state);
+        self.queue_viewport_update(
+            ViewportRequest::Absolute(byte),
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await
+    }
+
+    /// Close the match list, stopping its fetch, and return what it listed
+    pub(super) fn close_match_list(&mut self, view_state: &mut ViewState) -> Option<MatchList> {
+        if let Some((_, cancel_flag)) = self.match_source.take().and_then(|source| source.request) {
+            cancel_flag.cancel();
+        }
+        view_state.match_list.take()
+    }
+
+    /// Add a batch of matches to the list when it answers the outstanding fetch
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn apply_match_batch(
+        &mut self,
+        request_id: RequestId,
+        matches: Vec<(u64, String)>,
+        last: bool,
+        resume_from: Option<u64>,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+    ) -> Result<()> {
+        if !self.is_match_list_request(request_id) {
+            return Ok(());
+        }
+        let matches: Vec<(u64, String)> = matches
+            .into_iter()
+            .map(|(byte, text)| (byte, view_state.displayed(text, Vec::new()).0))
+            .collect();
+        let Some(list) = view_state.match_list.as_mut() else {
+            return Ok(());
+        };
+        list.entries.extend(matches);
+        if !last {
+            return Ok(());
+        }
+        list.loading = false;
+        list.more_from = resume_from;
+        if list.entries.is_empty() && resume_from.is_none() {
+            list.message = Some("Pattern not found".to_string());
+        }
+        if let Some(source) = self.match_source.as_mut() {
+            source.request = None;
+        }
+        // The selection may have reached the end while the batch was on its way
+        self.fetch_more_matches(view_state, search_tx, next_request_id)
+    }
+
+    /// Explain a failed fetch inside the panel
+    pub(super) fn match_list_failed(&mut self, message: String, view_state: &mut ViewState) {
+        if let Some(source) = self.match_source.as_mut() {
+            source.request = None;
+        }
+        if let Some(list) = view_state.match_list.as_mut() {
+            list.loading = false;
+            list.more_from = None;
+            list.message = Some(message);
+        }
+    }
+
+    pub(super) fn is_match_list_request(&self, request_id: RequestId) -> bool {
+        self.match_source
+            .as_ref()
+            .and_then(|source| source.request.as_ref())
+            .is_some_and(|(current, _)| *current == request_id)
+    }
+
+    /// Ask for the next batch when the selection is within a panel's height of the end
+    fn fetch_more_matches(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+    ) -> Result<()> {
+        let rows = view_state.match_list_rows();
+        let Some(list) = view_state.match_list.as_mut() else {
+            return Ok(());
+        };
+        let (Some(source), Some(from)) = (self.match_source.as_mut(), list.more_from) else {
+            return Ok(());
+        };
+        if !list.wants_more(rows) {
+            return Ok(());
+        }
+        let request_id = *next_request_id;
+        *next_request_id += 1;
+        let cancel_flag = CancellationToken::new();
+        source.request = Some((request_id, cancel_flag.clone()));
+        list.loading = true;
+        let search = Arc::clone(&source.search);
+        self.dispatch(
+            search_tx,
+            SearchCommand::CollectMatches {
+                request_id,
+                search,
+                from,
+                limit: MATCH_LIST_BATCH,
+                cancel_flag,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputAction;
+    use crate::render::protocol::SearchResponse;
+    use crate::search::{PatternModifiers, SearchOptions};
+
+    #[tokio::test]
+    async fn match_list_fills_in_batches_and_jumps_to_the_selection() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        // Three rows of entries fit inside the panel's border
+        let mut view_state = ViewState::new("/test", 80, 6);
+        view_state.navigate_to_byte(4096);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        let batch = |request_id, range: std::ops::Range<u64>, last, resume_from| {
+            SearchResponse::MatchBatch {
+                request_id,
+                matches: range.map(|i| (i * 100, format!("error {i}"))).collect(),
+                last,
+                resume_from,
+            }
+        };
+
+        act!(InputAction::OpenMatchList);
+        let list = view_state.match_list.as_ref().unwrap();
+        assert_eq!(
+            list.message.as_deref(),
+            Some("No active search; search with / first")
+        );
+        assert!(search_rx.try_recv().is_err());
+        act!(InputAction::CloseMatchList);
+        assert!(view_state.match_list.is_none());
+
+        state.search_state = Some(Arc::new(SearchHighlightSpec {
+            pattern: Arc::from("error"),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        }));
+        act!(InputAction::OpenMatchList);
+        let Ok(SearchCommand::CollectMatches {
+            request_id,
+            from: 0,
+            limit: MATCH_LIST_BATCH,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected the first batch to be fetched");
+        };
+        assert_eq!(
+            view_state.match_list.as_ref().unwrap().title,
+            "matches of /error"
+        );
+
+        respond!(batch(request_id, 0..4, false, None));
+        respond!(batch(request_id, 4..6, true, Some(600)));
+        let list = view_state.match_list.as_ref().unwrap();
+        assert_eq!(list.entries.len(), 6);
+        assert_eq!(list.entries[1], (100, "error 1".to_string()));
+        assert!(!list.loading);
+        // Nowhere near the end yet
+        assert!(search_rx.try_recv().is_err());
+
+        act!(InputAction::PageMatchList(ScrollDirection::Down));
+        let Ok(SearchCommand::CollectMatches {
+            request_id,
+            from: 600,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected the next batch from where the last one stopped");
+        };
+        // A stale batch is ignored
+        respond!(batch(request_id - 1, 90..95, true, None));
+        respond!(batch(request_id, 6..8, true, None));
+        assert_eq!(view_state.match_list.as_ref().unwrap().count_label(), "8");
+
+        act!(InputAction::MoveMatchSelection {
+            direction: ScrollDirection::Down,
+            lines: 1,
+        });
+        act!(InputAction::JumpToListedMatch);
+        assert!(view_state.match_list.is_none());
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Match 5 of 8")
+        );
+        match search_rx.try_recv() {
+            Ok(SearchCommand::LoadViewport {
+                top: ViewportRequest::Absolute(400),
+                ..
+            }) => {}
+            other => panic!("expected a jump to the selected line, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn closing_the_match_list_stops_its_fetch_and_keeps_the_view() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.navigate_to_byte(4096);
+        state.search_state = Some(Arc::new(SearchHighlightSpec {
+            pattern: Arc::from("error"),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        }));
+
+        state
+            .open_match_list(&mut view_state, &mut search_tx, &mut 1)
+            .unwrap();
+        let Ok(SearchCommand::CollectMatches { cancel_flag, .. }) = search_rx.try_recv() else {
+            panic!("expected the first batch to be fetched");
+        };
+        state.close_match_list(&mut view_state);
+        assert!(cancel_flag.is_cancelled());
+        assert!(view_state.match_list.is_none());
+        assert_eq!(view_state.viewport_top_byte, 4096);
+        assert!(search_rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,658 @@
+//! Scrolling, paging and jumps that move the viewport.
+
+use super::RenderLoopState;
+use crate::error::Result;
+use crate::input::{InputAction, ScrollDirection, SearchDirection};
+use crate::render::protocol::{RequestId, SearchCommand, ViewportRequest};
+use crate::render::ui::{JumpTarget, TailMode, ViewState};
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+impl RenderLoopState {
+    /// Put the matching line of a search jump on the row `-j` asks for
+    pub fn set_jump_target(&mut self, target: JumpTarget) {
+        self.jump_target = target;
+    }
+
+    /// Page to load for a search that found a match on the line starting at `byte`
+    ///
+    /// The lines above it are counted back from the match.
+    pub(super) fn jump_request(&self, byte: u64, view_state: &ViewState) -> ViewportRequest {
+        let context = self.jump_target.context(view_state.lines_per_page());
+        match context {
+            0 => ViewportRequest::Absolute(byte),
+            lines => ViewportRequest::RelativeLines {
+                anchor: byte,
+                lines: -i64::from(lines),
+            },
+        }
+    }
+
+    /// Line a typed search, `n`/`N` and ESC-n/ESC-N count from, as a byte and the rows below it
+    ///
+    /// That is the row `-j` puts matches on, as in less. Until the view moves, it is the line the
+    /// last jump landed on, which the start or end of the file may have kept off that row. Only
+    /// the top row's start is known in a filtered view.
+    pub(super) fn origin_line(&self, view_state: &ViewState) -> (u64, usize) {
+        let top = view_state.viewport_top_byte;
+        match self.landing {
+            Some((landed_top, line)) if landed_top == top => (line, 0),
+            _ if view_state.status_line.filter.is_some() => (top, 0),
+            _ => (
+                top,
+                usize::from(self.jump_target.context(view_state.lines_per_page())),
+            ),
+        }
+    }
+
+    /// Split a count prefix off `action`, turning counted paging into one longer scroll
+    ///
+    /// Returns the count (1 without a prefix) with the action it applies to. The count shown
+    /// while it was typed is cleared by whichever key ends it.
+    pub(super) fn uncount(
+        &mut self,
+        action: InputAction,
+        view_state: &mut ViewState,
+    ) -> (u64, InputAction) {
+        if self.count_shown && !matches!(action, InputAction::UpdateCountBuffer(_)) {
+            self.count_shown = false;
+            view_state.status_line.clear_message();
+        }
+        let mut count = 1u64;
+        let mut action = action;
+        while let InputAction::Counted {
+            count: n,
+            action: inner,
+        } = action
+        {
+            count = count.saturating_mul(n);
+            action = *inner;
+        }
+        let (direction, page) = match action {
+            InputAction::PageUp => (ScrollDirection::Up, view_state.lines_per_page() as u64),
+            InputAction::PageDown => (ScrollDirection::Down, view_state.lines_per_page() as u64),
+            InputAction::HalfPageUp => (ScrollDirection::Up, half_page(view_state)),
+            InputAction::HalfPageDown => (ScrollDirection::Down, half_page(view_state)),
+            action => return (count, action),
+        };
+        if count == 1 {
+            return (1, action);
+        }
+        let lines = count.saturating_mul(page).min(i64::MAX as u64);
+        (1, InputAction::Scroll { direction, lines })
+    }
+
+    /// Move the viewport for a scroll, paging or jump action
+    pub(super) async fn navigate(
+        &mut self,
+        action: InputAction,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        if view_state.wrap {
+            let page = view_state.lines_per_page() as i64;
+            let rows = match action {
+                InputAction::Scroll {
+                    direction: ScrollDirection::Up,
+                    lines,
+                } => Some(-(lines as i64)),
+                InputAction::Scroll {
+                    direction: ScrollDirection::Down,
+                    lines,
+                } => Some(lines as i64),
+                InputAction::PageUp => Some(-page),
+                InputAction::PageDown => Some(page),
+                InputAction::HalfPageUp => Some(-(half_page(view_state) as i64)),
+                InputAction::HalfPageDown => Some(half_page(view_state) as i64),
+                _ => None,
+            };
+            if let Some(rows) = rows {
+                return self
+                    .scroll_wrapped(
+                        rows,
+                        view_state,
+                        search_tx,
+                        next_request_id,
+                        latest_view_request,
+                    )
+                    .await;
+            }
+        }
+
+        match action {
+            InputAction::Scroll { direction, lines } => {
+                let delta = match direction {
+                    ScrollDirection::Up => {
+                        self.update_tail_mode(TailMode::Paused, view_state);
+                        -(lines as i64)
+                    }
+                    ScrollDirection::Down => lines as i64,
+                };
+                self.queue_viewport_update(
+                    ViewportRequest::RelativeLines {
+                        anchor: view_state.viewport_top_byte,
+                        lines: delta,
+                    },
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::PageUp => {
+                self.update_tail_mode(TailMode::Paused, view_state);
+                self.queue_viewport_update(
+                    ViewportRequest::RelativeLines {
+                        anchor: view_state.viewport_top_byte,
+                        lines: -(view_state.lines_per_page() as i64),
+                    },
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::PageDown => {
+                self.queue_viewport_update(
+                    ViewportRequest::RelativeLines {
+                        anchor: view_state.viewport_top_byte,
+                        lines: view_state.lines_per_page() as i64,
+                    },
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::HalfPageUp => {
+                self.update_tail_mode(TailMode::Paused, view_state);
+                self.queue_viewport_update(
+                    ViewportRequest::RelativeLines {
+                        anchor: view_state.viewport_top_byte,
+                        lines: -(half_page(view_state) as i64),
+                    },
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::HalfPageDown => {
+                self.queue_viewport_update(
+                    ViewportRequest::RelativeLines {
+                        anchor: view_state.viewport_top_byte,
+                        lines: half_page(view_state) as i64,
+                    },
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::GoToStart => {
+                self.update_tail_mode(TailMode::Paused, view_state);
+                self.queue_viewport_update(
+                    ViewportRequest::Absolute(0),
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::PreviousSection | InputAction::NextSection => {
+                let target = if action == InputAction::NextSection {
+                    view_state.next_section_start()
+                } else {
+                    view_state.prev_section_start()
+                };
+                let Some(target) = target else {
+                    let message = if view_state.sections.len() < 2 {
+                        "No sections in this file"
+                    } else if action == InputAction::NextSection {
+                        "Already in the last member"
+                    } else {
+                        "Already in the first member"
+                    };
+                    view_state.status_line.set_message(message.to_string());
+                    return Ok(true);
+                };
+                self.update_tail_mode(TailMode::Paused, view_state);
+                self.queue_viewport_update(
+                    ViewportRequest::Absolute(target),
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::PreviousRecord | InputAction::NextRecord => {
+                let direction = if action == InputAction::NextRecord {
+                    SearchDirection::Forward
+                } else {
+                    SearchDirection::Backward
+                };
+                self.update_tail_mode(TailMode::Paused, view_state);
+                let record_start = Arc::clone(&self.record_start);
+                let current_top = view_state.viewport_top_byte;
+                self.queue_position_jump(search_tx, next_request_id, |request_id, cancel_flag| {
+                    SearchCommand::NavigateRecord {
+                        request_id,
+                        direction,
+                        record_start,
+                        current_top,
+                        cancel_flag,
+                    }
+                })
+            }
+            InputAction::GoToEnd => {
+                self.update_tail_mode(TailMode::Sticky, view_state);
+                self.queue_viewport_update(
+                    ViewportRequest::EndOfFile,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            InputAction::SubmitPercent { tenths } => {
+                let Some(file_size) = view_state.file_size else {
+                    view_state
+                        .status_line
+                        .set_message("Cannot jump: file size unknown".to_string());
+                    return Ok(true);
+                };
+
+                if file_size == 0 {
+                    view_state
+                        .status_line
+                        .set_message("Cannot jump: file is empty".to_string());
+                    return Ok(true);
+                }
+
+                if tenths >= 1000 {
+                    view_state
+                        .status_line
+                        .set_message("goto: 100% (EOF)".to_string());
+                    return self
+                        .queue_viewport_update(
+                            ViewportRequest::EndOfFile,
+                            view_state,
+                            search_tx,
+                            next_request_id,
+                            latest_view_request,
+                        )
+                        .await;
+                }
+
+                // Integer math keeps a 0.1% step exact even on multi-GB files
+                let target = (u128::from(tenths) * u128::from(file_size) / 1000) as u64;
+                let percent = if tenths % 10 == 0 {
+                    format!("{}", tenths / 10)
+                } else {
+                    format!("{}.{}", tenths / 10, tenths % 10)
+                };
+                view_state
+                    .status_line
+                    .set_message(format!("goto: {}%", percent));
+                self.queue_viewport_update(
+                    ViewportRequest::Absolute(target),
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            // Only the actions above are routed here
+            _ => Ok(true),
+        }
+    }
+
+    /// Move by `rows` screen rows with wrapping on
+    ///
+    /// Moves within the loaded page need no worker round trip; see `ViewState::scroll_rows`.
+    async fn scroll_wrapped(
+        &mut self,
+        rows: i64,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        if rows < 0 {
+            self.update_tail_mode(TailMode::Paused, view_state);
+        }
+        let Some(lines) = view_state.scroll_rows(rows) else {
+            return Ok(true);
+        };
+        self.queue_viewport_update(
+            ViewportRequest::RelativeLines {
+                anchor: view_state.viewport_top_byte,
+                lines,
+            },
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await
+    }
+}
+
+/// Lines moved by `d` / `u`: half a window, at least one
+fn half_page(view_state: &ViewState) -> u64 {
+    (view_state.lines_per_page() as u64 / 2).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputStateMachine;
+    use crate::search::SearchOptions;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn navigation_scrolls_and_pages() {
+        let mut sm = InputStateMachine::new();
+        assert_eq!(
+            sm.handle_key_event(key(KeyCode::Char('j'))),
+            InputAction::Scroll {
+                direction: ScrollDirection::Down,
+                lines: 1,
+            }
+        );
+        assert_eq!(
+            sm.handle_key_event(key(KeyCode::Char('k'))),
+            InputAction::Scroll {
+                direction: ScrollDirection::Up,
+                lines: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn percent_jump_requires_digits() {
+        let mut sm = InputStateMachine::new();
+        assert_eq!(
+            sm.handle_key_event(key(KeyCode::Char('%'))),
+            InputAction::StartPercentInput
+        );
+
+        assert_eq!(
+            sm.handle_key_event(key(KeyCode::Char('1'))),
+            InputAction::UpdatePercentBuffer("1".to_string())
+        );
+        assert_eq!(
+            sm.handle_key_event(key(KeyCode::Char('0'))),
+            InputAction::UpdatePercentBuffer("10".to_string())
+        );
+        assert_eq!(
+            sm.handle_key_event(key(KeyCode::Enter)),
+            InputAction::SubmitPercent { tenths: 100 }
+        );
+    }
+
+    #[test]
+    fn counted_paging_becomes_one_scroll_and_the_count_message_clears() {
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let page = view_state.lines_per_page() as u64;
+        view_state.status_line.set_message("count: 3".to_string());
+        state.count_shown = true;
+
+        let counted = |action| InputAction::Counted {
+            count: 3,
+            action: Box::new(action),
+        };
+        assert_eq!(
+            state.uncount(counted(InputAction::PageUp), &mut view_state),
+            (
+                1,
+                InputAction::Scroll {
+                    direction: ScrollDirection::Up,
+                    lines: 3 * page,
+                }
+            )
+        );
+        assert!(view_state.status_line.message.is_none());
+        assert_eq!(
+            state.uncount(counted(InputAction::NextMatch), &mut view_state),
+            (3, InputAction::NextMatch)
+        );
+        assert_eq!(
+            state.uncount(InputAction::PageDown, &mut view_state),
+            (1, InputAction::PageDown)
+        );
+    }
+
+    #[tokio::test]
+    async fn decimal_percent_jump_targets_exact_byte() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.file_size = Some(40_000_000_000);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        state
+            .process_action(
+                InputAction::SubmitPercent { tenths: 427 },
+                &mut view_state,
+                &mut search_tx,
+                &mut next_request_id,
+                &mut latest_view_request,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+            )
+            .await
+            .unwrap();
+
+        match search_rx.try_recv() {
+            Ok(SearchCommand::LoadViewport { top, .. }) => {
+                assert_eq!(top, ViewportRequest::Absolute(17_080_000_000))
+            }
+            other => panic!("unexpected command: {other:?}"),
+        }
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("goto: 42.7%")
+        );
+    }
+
+    #[tokio::test]
+    async fn half_pages_move_half_a_window() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        // 23 content rows under the status line
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.viewport_top_byte = 500;
+        let mut next_request_id = 1;
+
+        for (action, lines) in [
+            (InputAction::HalfPageDown, 11),
+            (InputAction::HalfPageUp, -11),
+        ] {
+            state
+                .navigate(
+                    action,
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut None,
+                )
+                .await
+                .unwrap();
+            match search_rx.try_recv() {
+                Ok(SearchCommand::LoadViewport { top, .. }) => {
+                    assert_eq!(top, ViewportRequest::RelativeLines { anchor: 500, lines })
+                }
+                other => panic!("unexpected command: {other:?}"),
+            }
+        }
+        assert_eq!(
+            state.uncount(
+                InputAction::Counted {
+                    count: 2,
+                    action: Box::new(InputAction::HalfPageDown),
+                },
+                &mut view_state,
+            ),
+            (
+                1,
+                InputAction::Scroll {
+                    direction: ScrollDirection::Down,
+                    lines: 22,
+                }
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn wrapped_scrolling_moves_rows_and_loads_past_the_page() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        // Ten columns and four content rows; the first line takes three of them
+        let mut view_state = ViewState::new("/test", 10, 5);
+        view_state.set_wrap(true);
+        view_state.viewport_top_byte = 100;
+        view_state.update_viewport_content(
+            vec!["x".repeat(30), "b".into(), "c".into(), "d".into()],
+            Vec::new(),
+        );
+        let mut next_request_id = 1;
+        let down = |lines| InputAction::Scroll {
+            direction: ScrollDirection::Down,
+            lines,
+        };
+
+        // Two rows can be scrolled past without a new page
+        for _ in 0..2 {
+            state
+                .navigate(
+                    down(1),
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut None,
+                )
+                .await
+                .unwrap();
+        }
+        assert!(search_rx.try_recv().is_err());
+        assert_eq!(view_state.wrap_skip(), 2);
+        assert_eq!(view_state.highlighted_lines()[0].text, "x".repeat(10));
+
+        // A page down goes past the loaded lines: the next page starts at the line holding row 6
+        state
+            .navigate(
+                InputAction::PageDown,
+                &mut view_state,
+                &mut search_tx,
+                &mut next_request_id,
+                &mut None,
+            )
+            .await
+            .unwrap();
+        match search_rx.try_recv() {
+            Ok(SearchCommand::LoadViewport { top, .. }) => assert_eq!(
+                top,
+                ViewportRequest::RelativeLines {
+                    anchor: 100,
+                    lines: 4
+                }
+            ),
+            other => panic!("unexpected command: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn braces_jump_to_gzip_member_boundaries() {
+        use crate::file_handler::{FileAccessor, FileAccessorFactory};
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut file = tempfile::Builder::new().suffix(".gz").tempfile().unwrap();
+        for member in ["one\n", "two two\n", "three\n"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(member.as_bytes()).unwrap();
+            file.write_all(&encoder.finish().unwrap()).unwrap();
+        }
+        file.flush().unwrap();
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        assert_eq!(accessor.sections(), &[0, 4, 12]);
+
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new(file.path(), 80, 24);
+        view_state.file_size = Some(accessor.file_size());
+        view_state.set_sections(accessor.sections().to_vec());
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        // Act, then deliver the viewport load it queued, if any
+        macro_rules! jump {
+            ($action:expr) => {{
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap();
+                match search_rx.try_recv() {
+                    Ok(SearchCommand::LoadViewport {
+                        top: ViewportRequest::Absolute(byte),
+                        ..
+                    }) => {
+                        view_state.navigate_to_byte(byte);
+                        Some(byte)
+                    }
+                    Ok(other) => panic!("unexpected command: {other:?}"),
+                    Err(_) => None,
+                }
+            }};
+        }
+
+        assert_eq!(jump!(InputAction::NextSection), Some(4));
+        assert_eq!(jump!(InputAction::NextSection), Some(12));
+        assert_eq!(view_state.status_line.section, Some((3, 3)));
+        assert_eq!(jump!(InputAction::NextSection), None);
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Already in the last member")
+        );
+
+        // From inside a member, `{` goes back to where that member starts
+        view_state.navigate_to_byte(8);
+        assert_eq!(jump!(InputAction::PreviousSection), Some(4));
+        assert_eq!(jump!(InputAction::PreviousSection), Some(0));
+        assert_eq!(jump!(InputAction::PreviousSection), None);
+    }
+}
@@ -0,0 +1,554 @@
+//! Showing where a search pattern matches while it is still being typed.
+//!
+//! Each edit at the search prompt restarts a short delay; once typing pauses for `PREVIEW_DELAY`
+//! the pattern typed so far is searched for from where the prompt was opened, and the viewport
+//! moves to its first match with the partial pattern highlighted. Previews leave the active
+//! search, its highlight and the search history alone: Enter searches for the finished pattern
+//! from the original position as if nothing had been previewed, and Esc returns there.
+
+use super::RenderLoopState;
+use crate::error::Result;
+use crate::input::SearchDirection;
+use crate::render::protocol::{RequestId, SearchCommand, SearchHighlightSpec, ViewportRequest};
+use crate::render::ui::ViewState;
+use crate::search::{PatternModifiers, MAX_PATTERN_BYTES};
+use crate::util::CancellationToken;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tokio::time::{Duration, Instant};
+
+/// Pause in typing after which the pattern typed so far is previewed.
+const PREVIEW_DELAY: Duration = Duration::from_millis(150);
+
+/// Previews for one opening of the search prompt
+#[derive(Debug)]
+pub(super) struct SearchPreview {
+    /// Viewport top when typing began; Esc returns here
+    origin: u64,
+    /// Line previews search from, as `origin_line` gave it when typing began
+    origin_line: (u64, usize),
+    /// Pattern waiting for typing to pause, with when the pause is long enough
+    due: Option<(Instant, String, SearchDirection)>,
+    /// Outstanding preview search, its cancel flag and the highlight it would show
+    request: Option<(RequestId, CancellationToken, Arc<SearchHighlightSpec>)>,
+    /// Highlight of the match on screen, drawn instead of the active search's
+    shown: Option<Arc<SearchHighlightSpec>>,
+    /// The viewport has left `origin` for a preview
+    moved: bool,
+}
+
+impl RenderLoopState {
+    /// Preview the first match while a search pattern is typed; off for files where every
+    /// keystroke starting a search would cost too much
+    pub fn set_incremental_search(&mut self, enabled: bool) {
+        self.incremental_search = enabled;
+    }
+
+    /// Outstanding preview search, whose completion `show_preview` handles
+    pub(super) fn preview_request(&self) -> Option<RequestId> {
+        self.preview
+            .as_ref()
+            .and_then(|preview| preview.request.as_ref())
+            .map(|&(request_id, ..)| request_id)
+    }
+
+    /// Highlight of the preview on screen, if one is
+    pub(super) fn preview_highlight(&self) -> Option<Arc<SearchHighlightSpec>> {
+        self.preview
+            .as_ref()
+            .and_then(|preview| preview.shown.clone())
+    }
+
+    /// Preview `buffer` once typing pauses
+    pub(super) fn schedule_preview(
+        &mut self,
+        buffer: &str,
+        direction: SearchDirection,
+        view_state: &ViewState,
+        now: Instant,
+    ) {
+        if !self.incremental_search {
+            return;
+        }
+        let origin_line = self.origin_line(view_state);
+        let preview = self.preview.get_or_insert(SearchPreview {
+            origin: view_state.viewport_top_byte,
+            origin_line,
+            due: None,
+            request: None,
+            shown: None,
+            moved: false,
+        });
+        preview.due = Some((now + PREVIEW_DELAY, buffer.to_string(), direction));
+    }
+
+    /// End previewing for this prompt, returning where the viewport was when it opened if a
+    /// preview moved it away
+    pub(super) fn finish_preview(&mut self) -> Option<u64> {
+        let preview = self.preview.take()?;
+        if let Some((_, cancel_flag, _)) = preview.request {
+            cancel_flag.cancel();
+        }
+        preview.moved.then_some(preview.origin)
+    }
+
+    /// Start the preview whose delay is over by `now`; called once per frame
+    ///
+    /// An emptied prompt returns to the origin. Returns whether the view changed.
+    pub async fn preview_if_due(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+        now: Instant,
+    ) -> Result<bool> {
+        let Some(preview) = self.preview.as_mut() else {
+            return Ok(false);
+        };
+        if !preview.due.as_ref().is_some_and(|&(due, ..)| due <= now) {
+            return Ok(false);
+        }
+        let Some((_, pattern, direction)) = preview.due.take() else {
+            return Ok(false);
+        };
+        if let Some((_, cancel_flag, _)) = preview.request.take() {
+            cancel_flag.cancel();
+        }
+
+        let (modifiers, stripped) = PatternModifiers::strip(&pattern);
+        if stripped.is_empty() {
+            view_state.status_line.set_prompt_note(None);
+            return self
+                .return_to_origin(view_state, search_tx, next_request_id, latest_view_request)
+                .await;
+        }
+        let options = modifiers.apply(self.search_options.clone());
+        let pattern = stripped.to_string();
+        let pattern = if options.regex_mode {
+            match self.patterns.expand(&pattern) {
+                Ok(expanded) => expanded.unwrap_or(pattern),
+                Err(message) => {
+                    view_state.status_line.set_prompt_note(Some(message));
+                    return Ok(true);
+                }
+            }
+        } else {
+            pattern
+        };
+        if pattern.len() > MAX_PATTERN_BYTES {
+            view_state
+                .status_line
+                .set_prompt_note(Some("pattern too long".to_string()));
+            return Ok(true);
+        }
+
+        let pattern: Arc<str> = Arc::from(pattern);
+        let request_id = *next_request_id;
+        *next_request_id += 1;
+        let cancel_flag = CancellationToken::new();
+        let highlight = Arc::new(SearchHighlightSpec {
+            pattern: Arc::clone(&pattern),
+            options: options.clone(),
+            modifiers,
+        });
+        let (origin_byte, origin_row) = preview.origin_line;
+        preview.request = Some((request_id, cancel_flag.clone(), highlight));
+        self.dispatch(
+            search_tx,
+            SearchCommand::ExecuteSearch {
+                request_id,
+                pattern,
+                direction,
+                options,
+                origin_byte,
+                origin_row,
+                skip_origin_row: true,
+                frequency_limit: None,
+                preview: true,
+                cancel_flag,
+            },
+        )?;
+        Ok(false)
+    }
+
+    /// Show the outcome of the outstanding preview: its match, or a note on the prompt and the
+    /// viewport back at the origin
+    pub(super) async fn show_preview(
+        &mut self,
+        match_byte: Option<u64>,
+        message: Option<String>,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<()> {
+        let Some(preview) = self.preview.as_mut() else {
+            return Ok(());
+        };
+        let Some((_, _, highlight)) = preview.request.take() else {
+            return Ok(());
+        };
+        view_state.status_line.set_prompt_note(message);
+        let Some(byte) = match_byte else {
+            self.return_to_origin(view_state, search_tx, next_request_id, latest_view_request)
+                .await?;
+            return Ok(());
+        };
+        preview.shown = Some(highlight);
+        preview.moved = true;
+        view_state.at_eof = false;
+        self.request_viewport(
+            ViewportRequest::Absolute(byte),
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Drop the preview's highlight and go back to the origin if a preview left it
+    async fn return_to_origin(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        let Some(preview) = self.preview.as_mut() else {
+            return Ok(false);
+        };
+        preview.shown = None;
+        if !std::mem::take(&mut preview.moved) {
+            return Ok(true);
+        }
+        let origin = preview.origin;
+        self.request_viewport(
+            ViewportRequest::Absolute(origin),
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputAction;
+    use crate::render::protocol::SearchResponse;
+    use crate::search::SearchOptions;
+
+    #[tokio::test]
+    async fn typing_previews_the_latest_pattern_and_esc_returns_to_the_origin() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.viewport_top_byte = 100;
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! preview_after {
+            ($delay:expr) => {
+                state
+                    .preview_if_due(
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        Instant::now() + $delay,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        let typed = |buffer: &str| InputAction::UpdateSearchBuffer {
+            direction: SearchDirection::Forward,
+            buffer: buffer.to_string(),
+        };
+
+        act!(InputAction::StartSearch(SearchDirection::Forward));
+        act!(typed("ti"));
+        // Nothing is searched until typing pauses
+        preview_after!(Duration::ZERO);
+        assert!(search_rx.try_recv().is_err());
+        preview_after!(PREVIEW_DELAY);
+        let Ok(SearchCommand::ExecuteSearch {
+            request_id: stale,
+            origin_byte: 100,
+            origin_row: 0,
+            preview: true,
+            frequency_limit: None,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected a preview search");
+        };
+
+        act!(typed("tim"));
+        preview_after!(PREVIEW_DELAY);
+        let Ok(SearchCommand::ExecuteSearch {
+            request_id,
+            pattern,
+            origin_byte: 100,
+            origin_row: 0,
+            preview: true,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected a preview search");
+        };
+        assert_eq!(&*pattern, "tim");
+
+        // The answer to the superseded preview is dropped
+        respond!(SearchResponse::SearchCompleted {
+            request_id: stale,
+            match_byte: Some(150),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        assert!(search_rx.try_recv().is_err());
+
+        respond!(SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: Some(400),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        let Ok(SearchCommand::LoadViewport {
+            top: ViewportRequest::Absolute(400),
+            highlights: Some(highlights),
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected the match to be shown");
+        };
+        assert_eq!(&*highlights.pattern, "tim");
+        // Nothing was committed as the active search
+        assert!(latest_search_request.is_none());
+        assert!(pending_search_state.is_none());
+        assert!(view_state.status_line.search_prompt.is_some());
+
+        act!(InputAction::CancelSearch);
+        let Ok(SearchCommand::LoadViewport {
+            top: ViewportRequest::Absolute(100),
+            highlights: None,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected a return to the origin");
+        };
+        assert_eq!(state.preview_request(), None);
+    }
+
+    #[tokio::test]
+    async fn invalid_partial_pattern_is_noted_and_enter_searches_from_the_origin() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! preview {
+            ($buffer:expr) => {{
+                act!(InputAction::UpdateSearchBuffer {
+                    direction: SearchDirection::Forward,
+                    buffer: $buffer.to_string(),
+                });
+                state
+                    .preview_if_due(
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        Instant::now() + PREVIEW_DELAY,
+                    )
+                    .await
+                    .unwrap();
+                let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv()
+                else {
+                    panic!("expected a preview search");
+                };
+                request_id
+            }};
+        }
+
+        act!(InputAction::StartSearch(SearchDirection::Forward));
+        let request_id = preview!("a");
+        respond!(SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: Some(40),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::LoadViewport { .. })
+        ));
+
+        let request_id = preview!("a(");
+        respond!(SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: None,
+            message: Some("regex parse error: unclosed group".to_string()),
+            confirm: None,
+            scan: None,
+        });
+        // Noted beside the prompt rather than replacing it, and the view goes back
+        assert_eq!(
+            view_state.status_line.prompt_note.as_deref(),
+            Some("regex parse error: unclosed group")
+        );
+        assert!(view_state.status_line.message.is_none());
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::LoadViewport {
+                top: ViewportRequest::Absolute(0),
+                ..
+            })
+        ));
+
+        let request_id = preview!("a(b)");
+        respond!(SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: Some(80),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        assert_eq!(view_state.status_line.prompt_note, None);
+        search_rx.try_recv().unwrap();
+
+        act!(InputAction::ExecuteSearch {
+            pattern: "a(b)".to_string(),
+            direction: SearchDirection::Forward,
+        });
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::LoadViewport {
+                top: ViewportRequest::Absolute(0),
+                ..
+            })
+        ));
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::ExecuteSearch {
+                origin_byte: 0,
+                origin_row: 0,
+                preview: false,
+                ..
+            })
+        ));
+        assert!(pending_search_state.is_some());
+    }
+
+    #[tokio::test]
+    async fn no_previews_when_turned_off() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        state.set_incremental_search(false);
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+
+        state.schedule_preview(
+            "error",
+            SearchDirection::Forward,
+            &view_state,
+            Instant::now(),
+        );
+        state
+            .preview_if_due(
+                &mut view_state,
+                &mut search_tx,
+                &mut next_request_id,
+                &mut latest_view_request,
+                Instant::now() + PREVIEW_DELAY,
+            )
+            .await
+            .unwrap();
+        assert!(search_rx.try_recv().is_err());
+    }
+}
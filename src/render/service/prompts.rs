@@ -0,0 +1,1094 @@
+//! Commands typed at the `-`, `:` and `&` prompts.
+
+use super::{locator, RenderLoopState};
+use crate::bookmarks::dropped_notice;
+use crate::error::Result;
+use crate::file_handler::meta::human_size;
+use crate::input::{BookmarkCommand, ColonCommand};
+use crate::render::protocol::{
+    ExportTarget, RequestId, SearchCommand, SearchHighlightSpec, ViewportRequest,
+};
+use crate::render::ui::snapshot::{snapshot, SnapshotFormat};
+use crate::render::ui::{ColorTheme, JumpTarget, LinePeek, PositionStyle, TailMode, ViewState};
+use crate::search::{PatternModifiers, MAX_PATTERN_BYTES};
+use crate::util::write_atomic;
+use crate::util::CancellationToken;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+impl RenderLoopState {
+    /// Apply the option flags typed after `-`
+    pub(super) async fn execute_flag_command(
+        &mut self,
+        buffer: &str,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        if buffer.is_empty() {
+            view_state
+                .status_line
+                .set_message("No command entered".to_string());
+            return Ok(true);
+        }
+
+        let mut options_changed = false;
+        let lines_before = view_state.position_style == PositionStyle::Lines;
+        let mut position_changed = false;
+        let mut scroll_changed = false;
+        let mut numbers_changed = false;
+        let mut wrap_changed = false;
+        let mut jump_changed = false;
+        for (at, flag) in buffer.char_indices() {
+            match flag {
+                'i' | 'I' => {
+                    // An explicit case setting ends smart case
+                    self.search_options.case_sensitive = !self.search_options.case_sensitive;
+                    self.search_options.smart_case = false;
+                    options_changed = true;
+                }
+                's' => {
+                    self.search_options.smart_case = !self.search_options.smart_case;
+                    options_changed = true;
+                }
+                'r' | 'R' => {
+                    if !self.search_options.regex_mode {
+                        self.search_options.regex_mode = true;
+                        options_changed = true;
+                    }
+                }
+                'n' => {
+                    if self.search_options.regex_mode {
+                        self.search_options.regex_mode = false;
+                        options_changed = true;
+                    }
+                }
+                'w' | 'W' => {
+                    self.search_options.whole_word = !self.search_options.whole_word;
+                    options_changed = true;
+                }
+                'U' => {
+                    self.search_options.multiline = !self.search_options.multiline;
+                    options_changed = true;
+                }
+                'p' | 'P' => {
+                    view_state.position_style = view_state.position_style.next();
+                    position_changed = true;
+                }
+                'm' | 'M' => {
+                    let precise = !self.precision_scroll.is_enabled();
+                    self.precision_scroll.set(precise);
+                    view_state.status_line.precise_scroll = precise;
+                    scroll_changed = true;
+                }
+                'N' => {
+                    view_state.set_line_numbers(!view_state.line_numbers);
+                    numbers_changed = true;
+                }
+                'S' => {
+                    view_state.set_wrap(!view_state.wrap);
+                    wrap_changed = true;
+                }
+                'j' => {
+                    // Like less, the rest of the buffer is the value; without one, say the
+                    // current target
+                    let value = &buffer[at + 1..];
+                    if !value.is_empty() {
+                        let Some(target) = JumpTarget::parse(value) else {
+                            view_state
+                                .status_line
+                                .set_message(format!("Invalid jump target: {}", value));
+                            return Ok(true);
+                        };
+                        self.jump_target = target;
+                    }
+                    jump_changed = true;
+                    break;
+                }
+                other => {
+                    view_state
+                        .status_line
+                        .set_message(format!("Unknown command flag: {}", other));
+                    return Ok(true);
+                }
+            }
+        }
+
+        if numbers_changed {
+            self.dispatch(
+                search_tx,
+                SearchCommand::ShowLineNumbers(view_state.line_numbers),
+            )?;
+        }
+        let lines_after = view_state.position_style == PositionStyle::Lines;
+        if lines_after != lines_before {
+            self.dispatch(search_tx, SearchCommand::ShowLinePosition(lines_after))?;
+        }
+
+        if options_changed {
+            self.refresh_active_search();
+            view_state
+                .status_line
+                .set_message(self.search_options_summary());
+            self.request_viewport(
+                ViewportRequest::Absolute(view_state.viewport_top_byte),
+                view_state,
+                search_tx,
+                next_request_id,
+                latest_view_request,
+            )
+            .await?;
+        } else if position_changed {
+            view_state.status_line.set_message(format!(
+                "Position shown as {}",
+                view_state.position_style.name()
+            ));
+            if lines_after && !lines_before {
+                // The page is reloaded for the worker to report the line of its top row
+                self.request_viewport(
+                    ViewportRequest::Absolute(view_state.viewport_top_byte),
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await?;
+            }
+        } else if numbers_changed {
+            view_state.status_line.set_message(
+                if view_state.line_numbers {
+                    "Line numbers on"
+                } else {
+                    "Line numbers off"
+                }
+                .to_string(),
+            );
+            // The page is reloaded for the worker to report the number of its top line
+            self.request_viewport(
+                ViewportRequest::Absolute(view_state.viewport_top_byte),
+                view_state,
+                search_tx,
+                next_request_id,
+                latest_view_request,
+            )
+            .await?;
+        } else if wrap_changed {
+            view_state.status_line.set_message(
+                if view_state.wrap {
+                    "Long lines wrap"
+                } else {
+                    "Long lines are cut at the screen edge"
+                }
+                .to_string(),
+            );
+        } else if jump_changed {
+            view_state.status_line.set_message(match self.jump_target {
+                JumpTarget::TOP => "Search jumps put the match on the top row".to_string(),
+                JumpTarget::Lines(lines) => {
+                    format!("Search jumps keep {} lines above the match", lines)
+                }
+                JumpTarget::Center => {
+                    "Search jumps put the match in the middle of the screen".to_string()
+                }
+            });
+        } else if scroll_changed {
+            view_state.status_line.set_message(
+                if self.precision_scroll.is_enabled() {
+                    "Precision scrolling on: one line per wheel notch"
+                } else {
+                    "Precision scrolling off"
+                }
+                .to_string(),
+            );
+        } else {
+            view_state
+                .status_line
+                .set_message("Search options unchanged".to_string());
+        }
+
+        Ok(true)
+    }
+
+    /// Run a command typed after `:`
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn execute_colon_command(
+        &mut self,
+        buffer: &str,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_search_request: &mut Option<RequestId>,
+        latest_view_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+    ) -> Result<bool> {
+        match ColonCommand::parse(buffer) {
+            Ok(ColonCommand::SkipDuplicates(direction)) => {
+                view_state.status_line.clear_message();
+                let current_top = view_state.viewport_top_byte;
+                self.queue_position_jump(search_tx, next_request_id, |request_id, cancel_flag| {
+                    SearchCommand::SkipDuplicates {
+                        request_id,
+                        direction,
+                        current_top,
+                        cancel_flag,
+                    }
+                })
+            }
+            Ok(ColonCommand::Time(target)) => {
+                view_state.status_line.clear_message();
+                self.update_tail_mode(TailMode::Paused, view_state);
+                let record_start = Arc::clone(&self.record_start);
+                let current_top = view_state.viewport_top_byte;
+                self.queue_position_jump(search_tx, next_request_id, |request_id, cancel_flag| {
+                    SearchCommand::NavigateTime {
+                        request_id,
+                        target,
+                        record_start,
+                        current_top,
+                        cancel_flag,
+                    }
+                })
+            }
+            Ok(ColonCommand::Options) => {
+                view_state.status_line.clear_message();
+                self.peek_request = None;
+                view_state.peek = Some(LinePeek {
+                    title: Some("options".to_string()),
+                    text: self.effective_settings(view_state).lines().join("\n"),
+                    ..LinePeek::default()
+                });
+                Ok(true)
+            }
+            Ok(ColonCommand::Metrics) => {
+                view_state.status_line.clear_message();
+                self.peek_request = None;
+                let latency = self.last_viewport_latency.map_or_else(
+                    || "n/a".to_string(),
+                    |latency| format!("{:.1} ms", latency.as_secs_f64() * 1000.0),
+                );
+                let cache_memory = self.cache_budget.as_ref().map_or_else(
+                    || "n/a".to_string(),
+                    |budget| {
+                        format!(
+                            "{} of {}",
+                            human_size(budget.used() as u64),
+                            human_size(budget.cap() as u64)
+                        )
+                    },
+                );
+                view_state.peek = Some(LinePeek {
+                    title: Some("metrics".to_string()),
+                    text: [
+                        format!("viewport latency          {}", latency),
+                        format!("queued worker commands    {}", self.outbox.len()),
+                        format!("coalesced commands        {}", self.coalesced_commands),
+                        format!("worker cache memory       {}", cache_memory),
+                    ]
+                    .join("\n"),
+                    ..LinePeek::default()
+                });
+                Ok(true)
+            }
+            Ok(ColonCommand::Patterns) => {
+                view_state.status_line.clear_message();
+                self.peek_request = None;
+                let width = self
+                    .patterns
+                    .entries()
+                    .iter()
+                    .map(|entry| entry.name.len())
+                    .max()
+                    .unwrap_or(0);
+                view_state.peek = Some(LinePeek {
+                    title: Some("patterns".to_string()),
+                    text: self
+                        .patterns
+                        .entries()
+                        .iter()
+                        .map(|entry| format!("@{:<width$}  {}", entry.name, entry.regex))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    ..LinePeek::default()
+                });
+                Ok(true)
+            }
+            Ok(ColonCommand::LoadSet(path)) => {
+                self.load_pattern_set(
+                    &path,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_search_request,
+                    search_cancel_flag,
+                    pending_search_state,
+                )
+                .await
+            }
+            Ok(ColonCommand::Locator) => {
+                view_state.status_line.clear_message();
+                self.peek_request = None;
+                view_state.peek = Some(LinePeek {
+                    title: Some("locator".to_string()),
+                    text: locator(view_state, self.search_state.as_deref()),
+                    ..LinePeek::default()
+                });
+                Ok(true)
+            }
+            Ok(ColonCommand::Snapshot(path)) => {
+                self.write_snapshot(&path, view_state);
+                Ok(true)
+            }
+            Ok(ColonCommand::Keep { clear }) => {
+                self.keep_search(
+                    clear,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            Ok(ColonCommand::Save(path)) => {
+                let message = format!("Saving to {}…", path.display());
+                self.start_export(
+                    ExportTarget::File(path),
+                    message,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                )
+            }
+            Ok(ColonCommand::Pipe(command)) => {
+                let message = format!("Piping to `{}`…", command);
+                self.start_export(
+                    ExportTarget::Command(command),
+                    message,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                )
+            }
+            Ok(ColonCommand::Bookmark(command)) => {
+                self.execute_bookmark_command(
+                    command,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+            Ok(ColonCommand::ClearHistory) => {
+                // The input thread has already dropped its history when it parsed the command
+                view_state
+                    .status_line
+                    .set_message("Search history cleared".to_string());
+                Ok(true)
+            }
+            Err(message) => {
+                view_state.status_line.set_message(message);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Add, list or jump to a bookmark (`:bookmark`)
+    async fn execute_bookmark_command(
+        &mut self,
+        command: BookmarkCommand,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        match command {
+            BookmarkCommand::Add(label) => {
+                let number = self.bookmarks.add(view_state.viewport_top_byte, &label);
+                view_state.set_bookmarks(self.bookmarks.offsets());
+                let message = match self.bookmarks.save(&view_state.file_path) {
+                    Ok(()) => format!("Bookmark {}: {}", number, label),
+                    Err(error) => {
+                        log::warn!("coordinator: saving bookmarks failed: {}", error.chain());
+                        format!("Bookmark {} not saved: {}", number, error.summary())
+                    }
+                };
+                view_state.status_line.set_message(message);
+                Ok(true)
+            }
+            BookmarkCommand::List => {
+                view_state.status_line.clear_message();
+                self.peek_request = None;
+                let text = if self.bookmarks.is_empty() {
+                    "No bookmarks; add one with :bookmark add LABEL".to_string()
+                } else {
+                    self.bookmarks
+                        .entries()
+                        .iter()
+                        .enumerate()
+                        .map(|(index, bookmark)| format!("{:>3}  {}", index + 1, bookmark.label))
+                        .chain(std::iter::once("Jump to one with :bookmark N".to_string()))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                view_state.peek = Some(LinePeek {
+                    title: Some("bookmarks".to_string()),
+                    text,
+                    ..LinePeek::default()
+                });
+                Ok(true)
+            }
+            BookmarkCommand::Jump(number) => {
+                let Some(bookmark) = self.bookmarks.get(number) else {
+                    view_state
+                        .status_line
+                        .set_message(format!("No bookmark {}", number));
+                    return Ok(true);
+                };
+                let (byte, message) = (bookmark.byte, bookmark.label.clone());
+                view_state.status_line.set_message(message);
+                self.update_tail_mode(TailMode::Paused, view_state);
+                self.queue_viewport_update(
+                    ViewportRequest::Absolute(byte),
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Have the worker write the file to `target` (`:save`, `:pipe`), unless an export runs
+    fn start_export(
+        &mut self,
+        target: ExportTarget,
+        message: String,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+    ) -> Result<bool> {
+        if self.export_request.is_some() {
+            view_state
+                .status_line
+                .set_message("An export is already running (Ctrl-C cancels it)".to_string());
+            return Ok(true);
+        }
+        let request_id = *next_request_id;
+        *next_request_id += 1;
+        let cancel_flag = CancellationToken::new();
+        self.export_request = Some((request_id, cancel_flag.clone()));
+        self.dispatch(
+            search_tx,
+            SearchCommand::Export {
+                request_id,
+                target,
+                cancel_flag,
+            },
+        )?;
+        view_state.status_line.set_message(message);
+        Ok(true)
+    }
+
+    /// Forget bookmarks past the end of a file that shrank to `file_size`, returning a notice
+    /// when there were any
+    pub(super) fn drop_stale_bookmarks(
+        &mut self,
+        file_size: u64,
+        view_state: &mut ViewState,
+    ) -> Option<String> {
+        let dropped = self.bookmarks.drop_past(file_size);
+        if dropped == 0 {
+            return None;
+        }
+        view_state.set_bookmarks(self.bookmarks.offsets());
+        if let Err(error) = self.bookmarks.save(&view_state.file_path) {
+            log::warn!("coordinator: saving bookmarks failed: {}", error.chain());
+        }
+        Some(dropped_notice(dropped))
+    }
+
+    /// Write what is on screen to `path` (`:snapshot`), ANSI-styled for `.ans` files
+    ///
+    /// Taken from `view_state` with the terminal UI's default theme, so the file shows the page
+    /// as drawn whatever the terminal makes of it.
+    pub(super) fn write_snapshot(&self, path: &Path, view_state: &mut ViewState) {
+        let format = SnapshotFormat::for_path(path);
+        let text = snapshot(view_state, &ColorTheme::default(), format);
+        let message = match write_atomic(path, text.as_bytes()) {
+            Ok(()) => format!("Snapshot written to {}", path.display()),
+            Err(error) => {
+                log::warn!("coordinator: snapshot failed: {}", error.chain());
+                error.summary()
+            }
+        };
+        view_state.status_line.set_message(message);
+    }
+
+    /// Show only the lines matching `pattern`; an empty pattern removes the filter
+    pub(super) async fn apply_filter(
+        &mut self,
+        pattern: String,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        if pattern.len() > MAX_PATTERN_BYTES {
+            view_state.status_line.set_message(format!(
+                "Pattern too long ({} bytes, limit {})",
+                pattern.len(),
+                MAX_PATTERN_BYTES
+            ));
+            return Ok(true);
+        }
+
+        view_state.status_line.clear_message();
+        if pattern.is_empty() {
+            self.filter_state = None;
+            view_state.status_line.filter = None;
+        } else {
+            self.filter_state = Some(Arc::new(SearchHighlightSpec {
+                pattern: Arc::from(pattern.as_str()),
+                options: self.search_options.clone(),
+                modifiers: PatternModifiers::default(),
+            }));
+            view_state.status_line.filter = Some(pattern);
+        }
+        view_state.at_eof = false;
+        self.filter_pending = self.filter_state.is_some();
+        self.request_viewport(
+            ViewportRequest::Absolute(view_state.viewport_top_byte),
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RllessError;
+    use crate::input::InputAction;
+    use crate::render::protocol::{HighlightKind, SearchResponse};
+    use crate::render::ui::PositionStyle;
+    use crate::search::SearchOptions;
+    use crate::settings::Settings;
+
+    #[tokio::test]
+    async fn position_flag_cycles_style_and_asks_for_lines_when_needed() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        for (expected, commands) in [
+            (
+                PositionStyle::Lines,
+                vec!["ShowLinePosition(true)", "load-viewport"],
+            ),
+            (PositionStyle::Byte, vec!["ShowLinePosition(false)"]),
+            // Only the status line changes; the viewport stays as loaded
+            (PositionStyle::Percent, vec![]),
+        ] {
+            state
+                .process_action(
+                    InputAction::ExecuteCommand {
+                        buffer: "P".to_string(),
+                    },
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_view_request,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                )
+                .await
+                .unwrap();
+            assert_eq!(view_state.position_style, expected);
+            let mut sent = Vec::new();
+            while let Ok(command) = search_rx.try_recv() {
+                sent.push(match command {
+                    SearchCommand::LoadViewport { .. } => command.name().to_string(),
+                    other => format!("{other:?}"),
+                });
+            }
+            assert_eq!(sent, commands);
+        }
+
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Position shown as percent")
+        );
+    }
+
+    #[tokio::test]
+    async fn m_flag_switches_the_input_thread_to_precision_scrolling() {
+        use crate::input::PrecisionScroll;
+
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let precision = PrecisionScroll::default();
+        state.set_precision_scroll(precision.clone());
+
+        for expected in [true, false] {
+            state
+                .execute_flag_command("m", &mut view_state, &mut search_tx, &mut 1, &mut None)
+                .await
+                .unwrap();
+            assert_eq!(precision.is_enabled(), expected);
+            assert_eq!(
+                view_state.format_status_line().contains("[precise]"),
+                expected
+            );
+            let settings = state.effective_settings(&view_state);
+            let setting = settings.get("precise-scroll").unwrap();
+            assert_eq!(setting.value, if expected { "on" } else { "off" });
+        }
+        assert!(search_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn s_flag_toggles_smart_case_and_i_ends_it() {
+        let (mut search_tx, _search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+
+        for (flags, summary, smart) in [
+            ("s", "search options: s,re", "on"),
+            ("i", "search options: i,re", "off"),
+            ("is", "search options: s,re", "on"),
+            ("s", "search options: re", "off"),
+        ] {
+            state
+                .execute_flag_command(flags, &mut view_state, &mut search_tx, &mut 1, &mut None)
+                .await
+                .unwrap();
+            let message = view_state.status_line.message.clone().unwrap();
+            assert_eq!(message, summary, "{flags}");
+            let settings = state.effective_settings(&view_state);
+            assert_eq!(settings.get("smart-case").unwrap().value, smart);
+        }
+    }
+
+    #[tokio::test]
+    async fn capital_n_toggles_line_numbers_and_reloads_the_page() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.navigate_to_byte(42);
+        let mut latest_view_request = None;
+
+        for expected in [true, false] {
+            state
+                .execute_flag_command(
+                    "N",
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut 1,
+                    &mut latest_view_request,
+                )
+                .await
+                .unwrap();
+            assert_eq!(view_state.line_numbers, expected);
+            assert!(matches!(
+                search_rx.try_recv(),
+                Ok(SearchCommand::ShowLineNumbers(show)) if show == expected
+            ));
+            assert!(matches!(
+                search_rx.try_recv(),
+                Ok(SearchCommand::LoadViewport {
+                    top: ViewportRequest::Absolute(42),
+                    ..
+                })
+            ));
+            let settings = state.effective_settings(&view_state);
+            let setting = settings.get("line-numbers").unwrap();
+            assert_eq!(setting.value, if expected { "on" } else { "off" });
+        }
+        // Lowercase `n` is still literal mode
+        assert!(state.search_options.regex_mode);
+        state
+            .execute_flag_command("n", &mut view_state, &mut search_tx, &mut 1, &mut None)
+            .await
+            .unwrap();
+        assert!(!state.search_options.regex_mode);
+        assert!(!view_state.line_numbers);
+    }
+
+    #[test]
+    fn snapshot_is_written_in_the_format_of_its_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test/app.log", 20, 3);
+        view_state.update_viewport_content(
+            vec!["error: disk".to_string(), "ok".to_string()],
+            vec![vec![(7, 11, HighlightKind::Search)], Vec::new()],
+        );
+
+        let plain = dir.path().join("view.txt");
+        state.write_snapshot(&plain, &mut view_state);
+        let message = view_state.status_line.message.clone().unwrap();
+        assert_eq!(message, format!("Snapshot written to {}", plain.display()));
+        let text = std::fs::read_to_string(&plain).unwrap();
+        assert!(text.starts_with("# rlless snapshot of /test/app.log\nerror: disk\nok\napp.log | "));
+
+        let ansi = dir.path().join("view.ans");
+        state.write_snapshot(&ansi, &mut view_state);
+        let text = std::fs::read_to_string(&ansi).unwrap();
+        assert!(text.contains("disk\x1b[0m"));
+
+        state.write_snapshot(&dir.path().join("missing/view.txt"), &mut view_state);
+        let message = view_state.status_line.message.clone().unwrap();
+        assert!(message.starts_with("Failed to write"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn options_overlay_tags_each_setting_with_its_source() {
+        use crate::settings::SettingSource;
+
+        let (mut search_tx, _search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        let mut settings = Settings::default();
+        settings.record("ignore-case", "off", SettingSource::Default);
+        settings.record("position", "byte", SettingSource::Flag);
+        state.set_settings(settings);
+        view_state.position_style = PositionStyle::Byte;
+
+        for buffer in ["i", ""] {
+            let action = if buffer.is_empty() {
+                InputAction::ExecuteColonCommand {
+                    buffer: "options".to_string(),
+                }
+            } else {
+                InputAction::ExecuteCommand {
+                    buffer: buffer.to_string(),
+                }
+            };
+            state
+                .process_action(
+                    action,
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_view_request,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                )
+                .await
+                .unwrap();
+        }
+
+        let peek = view_state.peek.as_ref().expect("overlay open");
+        assert_eq!(peek.title.as_deref(), Some("options"));
+        let lines: Vec<&str> = peek.text.lines().collect();
+        // Names are padded to the longest one, `frequent-match-limit`
+        for expected in [
+            "ignore-case           on  [runtime]",
+            "position              byte  [flag]",
+            "regex                 on  [default]",
+        ] {
+            assert!(
+                lines.contains(&expected),
+                "{expected:?} missing from {lines:?}"
+            );
+        }
+        assert_eq!(peek.wrapped_rows(80), lines.len());
+    }
+
+    #[tokio::test]
+    async fn rejected_filter_is_dropped() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        state
+            .process_action(
+                InputAction::ApplyFilter {
+                    pattern: "error(".to_string(),
+                },
+                &mut view_state,
+                &mut search_tx,
+                &mut next_request_id,
+                &mut latest_view_request,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+            )
+            .await
+            .unwrap();
+        let Ok(SearchCommand::LoadViewport {
+            request_id, filter, ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected viewport load");
+        };
+        assert_eq!(
+            filter.map(|spec| spec.pattern.to_string()).as_deref(),
+            Some("error(")
+        );
+        assert_eq!(view_state.status_line.filter.as_deref(), Some("error("));
+
+        state
+            .handle_response(
+                SearchResponse::Error {
+                    request_id,
+                    error: RllessError::search("Invalid regex pattern"),
+                },
+                &mut view_state,
+                &mut latest_view_request,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+                &mut search_tx,
+                &mut next_request_id,
+            )
+            .await
+            .unwrap();
+        assert!(state.filter_spec().is_none());
+        assert!(view_state.status_line.filter.is_none());
+        assert!(view_state
+            .status_line
+            .message
+            .as_deref()
+            .is_some_and(|message| message.starts_with("Invalid filter")));
+    }
+
+    #[tokio::test]
+    async fn bookmarks_are_added_listed_jumped_to_and_saved() {
+        use crate::bookmarks::Bookmarks;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = dir.path().join("bookmarks").join("app");
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/var/log/app.log", 80, 24);
+        let (bookmarks, _) = Bookmarks::load(store.clone(), 10_000).unwrap();
+        state.set_bookmarks(bookmarks, &mut view_state);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        for (top, buffer) in [
+            (4096, "bookmark list"),
+            (4096, "bookmark add \"GC pause\""),
+            (120, "bookmark add deploy"),
+            (120, "bookmark list"),
+            (120, "bookmark 2"),
+            (120, "bookmark 3"),
+        ] {
+            view_state.navigate_to_byte(top);
+            state
+                .process_action(
+                    InputAction::ExecuteColonCommand {
+                        buffer: buffer.to_string(),
+                    },
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_view_request,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                )
+                .await
+                .unwrap();
+            let message = view_state.status_line.message.clone().unwrap_or_default();
+            match buffer {
+                "bookmark add \"GC pause\"" => assert_eq!(message, "Bookmark 1: GC pause"),
+                "bookmark add deploy" => assert_eq!(message, "Bookmark 1: deploy"),
+                "bookmark 2" => {
+                    assert_eq!(message, "GC pause");
+                    let Ok(SearchCommand::LoadViewport { top, .. }) = search_rx.try_recv() else {
+                        panic!("expected viewport load");
+                    };
+                    assert_eq!(top, ViewportRequest::Absolute(4096));
+                }
+                "bookmark 3" => assert_eq!(message, "No bookmark 3"),
+                _ => {
+                    let peek = view_state.peek.take().expect("overlay open");
+                    assert_eq!(peek.title.as_deref(), Some("bookmarks"));
+                    if top == 4096 {
+                        assert!(peek.text.starts_with("No bookmarks"));
+                    } else {
+                        assert!(peek.text.starts_with("  1  deploy\n  2  GC pause\n"));
+                    }
+                }
+            }
+        }
+        assert!(search_rx.try_recv().is_err());
+        let marked = |view_state: &mut ViewState, byte| {
+            view_state.navigate_to_byte(byte);
+            view_state.update_viewport_content(vec!["line".to_string()], Vec::new());
+            view_state.is_bookmarked(0)
+        };
+        assert!(marked(&mut view_state, 4096));
+
+        let (saved, _) = Bookmarks::load(store.clone(), 10_000).unwrap();
+        assert_eq!(saved.offsets(), [120, 4096]);
+
+        // The file shrank under the second bookmark
+        assert_eq!(state.drop_stale_bookmarks(10_000, &mut view_state), None);
+        let notice = state.drop_stale_bookmarks(1000, &mut view_state).unwrap();
+        assert_eq!(notice, "1 bookmark past the end of the file dropped");
+        assert!(!marked(&mut view_state, 4096));
+        assert!(marked(&mut view_state, 120));
+        let (saved, _) = Bookmarks::load(store, 10_000).unwrap();
+        assert_eq!(saved.offsets(), [120]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_save_asks_before_deleting_its_partial_file() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("copy.log");
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! message {
+            () => {
+                view_state.status_line.message.clone().unwrap_or_default()
+            };
+        }
+        // Start a save, cancel it, and let the worker report the file it left
+        macro_rules! stopped_save {
+            () => {{
+                // Whatever earlier actions queued
+                while search_rx.try_recv().is_ok() {}
+                act!(InputAction::ExecuteColonCommand {
+                    buffer: format!("save {}", path.display()),
+                });
+                let Ok(SearchCommand::Export {
+                    request_id,
+                    target,
+                    cancel_flag,
+                }) = search_rx.try_recv()
+                else {
+                    panic!("expected an export");
+                };
+                assert_eq!(target, ExportTarget::File(path.clone()));
+                assert!(act!(InputAction::Interrupt));
+                assert!(cancel_flag.is_cancelled());
+                assert!(matches!(
+                    search_rx.try_recv(),
+                    Ok(SearchCommand::Cancel { request_id: id }) if id == request_id
+                ));
+                assert_eq!(message!(), "Cancelling export…");
+                std::fs::write(&path, "partial").unwrap();
+                respond!(SearchResponse::ExportFinished {
+                    request_id,
+                    message: format!("Save to {} cancelled", path.display()),
+                    partial: Some(path.clone()),
+                });
+                assert!(message!().ends_with("copy.log? (y/n)"));
+            }};
+        }
+
+        act!(InputAction::ExecuteColonCommand {
+            buffer: format!("save {}", path.display()),
+        });
+        assert!(message!().starts_with("Saving to "));
+        let Ok(SearchCommand::Export { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected an export");
+        };
+        // One export at a time
+        act!(InputAction::ExecuteColonCommand {
+            buffer: "pipe wc -l".to_string(),
+        });
+        assert_eq!(
+            message!(),
+            "An export is already running (Ctrl-C cancels it)"
+        );
+        assert!(search_rx.try_recv().is_err());
+        respond!(SearchResponse::ExportFinished {
+            request_id,
+            message: "Wrote 7 B in 0.0s to copy.log".to_string(),
+            partial: None,
+        });
+        assert_eq!(message!(), "Wrote 7 B in 0.0s to copy.log");
+        // With nothing left to cancel, Ctrl-C is not handled here
+        assert!(!act!(InputAction::Interrupt));
+
+        stopped_save!();
+        act!(InputAction::Resize {
+            width: 100,
+            height: 30,
+        });
+        assert!(message!().ends_with("? (y/n)"));
+        act!(InputAction::ConfirmPrompt);
+        assert!(!path.exists());
+        assert!(message!().starts_with("Deleted "));
+
+        stopped_save!();
+        act!(InputAction::PageDown);
+        assert!(path.exists());
+        assert!(message!().starts_with("Kept "));
+    }
+}
@@ -0,0 +1,1242 @@
+//! Applying worker responses to the view.
+
+use super::{RenderLoopState, SLOW_SEARCH};
+use crate::error::Result;
+use crate::export::throughput;
+use crate::file_handler::meta::{format_local_time, human_size};
+use crate::render::protocol::{
+    HighlightKind, ProgressOperation, RequestId, ScanStats, SearchCommand, SearchHighlightSpec,
+    SearchResponse, ViewportRequest,
+};
+use crate::render::ui::{LinePeek, TailMode, ViewState};
+use crate::util::CancellationToken;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+impl RenderLoopState {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn handle_response(
+        &mut self,
+        response: SearchResponse,
+        view_state: &mut ViewState,
+        latest_view_request: &mut Option<RequestId>,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+    ) -> Result<()> {
+        log::trace!(
+            "coordinator: response {} request={:?}",
+            response.name(),
+            response.request_id()
+        );
+        if self.search_progress_request.is_some()
+            && response.request_id() == self.search_progress_request
+            && !matches!(response, SearchResponse::SearchProgress { .. })
+        {
+            // Answered or given up; its percentage goes with it
+            self.search_progress_request = None;
+            self.pending_progress.insert(ProgressOperation::Search, 1.0);
+        }
+        match response {
+            SearchResponse::ViewportLoaded {
+                request_id,
+                top_byte,
+                lines,
+                highlights,
+                at_eof,
+                noeol,
+                highlights_truncated,
+                file_size,
+                top_line,
+                line_position,
+                current_match_row,
+            } => {
+                if Some(request_id) != *latest_view_request {
+                    return Ok(());
+                }
+                *latest_view_request = None;
+                if let Some((sent_id, sent_at)) = self.viewport_requested_at.take() {
+                    if sent_id == request_id {
+                        let latency = sent_at.elapsed();
+                        log::debug!(
+                            "coordinator: viewport request={} at byte {} loaded in {:?}",
+                            request_id,
+                            top_byte,
+                            latency
+                        );
+                        self.last_viewport_latency = Some(latency);
+                    }
+                }
+                let landed_on_match = self.match_viewport_request.take() == Some(request_id);
+                if let Some((landing_id, line)) = self.landing_request {
+                    if landing_id == request_id {
+                        self.landing_request = None;
+                        self.landing = Some((top_byte, line));
+                    }
+                }
+                self.filter_pending = false;
+                view_state.navigate_to_byte(top_byte);
+                view_state.at_eof = at_eof;
+                // Wherever navigation lands decides whether new data should follow
+                let tail = if at_eof {
+                    TailMode::Sticky
+                } else {
+                    TailMode::Paused
+                };
+                self.update_tail_mode(tail, view_state);
+                view_state.set_noeol(noeol);
+                view_state.status_line.highlights_truncated = highlights_truncated;
+                view_state.update_viewport_content(lines, highlights);
+                view_state.set_top_line(top_line);
+                view_state.line_position = line_position;
+                view_state.file_size = Some(file_size);
+                view_state.set_match_row(current_match_row);
+                if landed_on_match {
+                    // The match is on the marked line, which `-j` may have put below the top row
+                    self.current_match_text = view_state.match_row().and_then(|index| {
+                        let line = view_state.visible_lines.get(index)?;
+                        let &(start, end, _) = view_state
+                            .search_highlights
+                            .get(index)?
+                            .iter()
+                            .find(|&&(_, _, kind)| kind == HighlightKind::Search)?;
+                        line.get(start..end).map(str::to_string)
+                    });
+                }
+            }
+            SearchResponse::SearchCompleted {
+                request_id,
+                match_byte,
+                message,
+                confirm,
+                scan,
+            } => {
+                if self.preview_request() == Some(request_id) {
+                    return self
+                        .show_preview(
+                            match_byte,
+                            message,
+                            view_state,
+                            search_tx,
+                            next_request_id,
+                            latest_view_request,
+                        )
+                        .await;
+                }
+                if Some(request_id) != *latest_search_request {
+                    return Ok(());
+                }
+                *latest_search_request = None;
+                search_cancel_flag.take();
+
+                if let Some(held) = confirm {
+                    // Nothing ran yet; the next key decides whether it does
+                    view_state.status_line.clear_search_prompt();
+                    view_state.status_line.set_message(format!(
+                        "Pattern matches very frequently (~{} per MB) — continue? (y/n)",
+                        held.matches_per_mb
+                    ));
+                    let modifiers = pending_search_state
+                        .take()
+                        .map(|(_, pending)| pending.modifiers)
+                        .unwrap_or_default();
+                    self.unconfirmed_search = Some((held, modifiers));
+                } else if let Some(msg) = message {
+                    // Worker signals errors/not-found via `message`; treat this as a failed search
+                    // completion and drop any provisional highlight.
+                    view_state.status_line.clear_search_prompt();
+                    view_state.status_line.set_message(match slow_scan(scan) {
+                        Some(note) => format!("{} — {}", msg, note),
+                        None => msg,
+                    });
+                    if let Some((pending_id, _)) = pending_search_state {
+                        if *pending_id == request_id {
+                            pending_search_state.take();
+                            let _ = self.dispatch(search_tx, SearchCommand::ClearSearchContext);
+                            *latest_search_request = None;
+                            self.clear_search(view_state);
+                        }
+                    }
+                } else if let Some(byte) = match_byte {
+                    // Successful search: promote the pending highlight and jump to the match.
+                    view_state.status_line.clear_search_prompt();
+                    match slow_scan(scan) {
+                        Some(note) => view_state.status_line.set_message(
+                            match view_state.file_size.filter(|&size| size > 0) {
+                                Some(size) => {
+                                    format!("Found at {}% — {}", byte * 100 / size, note)
+                                }
+                                None => format!("Found — {}", note),
+                            },
+                        ),
+                        None => view_state.status_line.clear_message(),
+                    }
+                    if let Some((pending_id, state)) = pending_search_state.take() {
+                        if pending_id == request_id {
+                            self.set_search(state, view_state);
+                        }
+                    }
+                    view_state.at_eof = false;
+                    self.current_match = Some(byte);
+                    let request_id = self
+                        .request_viewport(
+                            self.jump_request(byte, view_state),
+                            view_state,
+                            search_tx,
+                            next_request_id,
+                            latest_view_request,
+                        )
+                        .await?;
+                    *latest_view_request = Some(request_id);
+                    self.match_viewport_request = Some(request_id);
+                    self.landing_request = Some((request_id, byte));
+                }
+            }
+            SearchResponse::PositionFound {
+                request_id,
+                byte,
+                message,
+            } => {
+                if !self.is_position_request(request_id) {
+                    return Ok(());
+                }
+                self.position_request = None;
+                let non_match = self.non_match_request.take() == Some(request_id);
+                match byte {
+                    Some(byte) if non_match => {
+                        view_state.status_line.clear_message();
+                        let request_id = self
+                            .request_viewport(
+                                self.jump_request(byte, view_state),
+                                view_state,
+                                search_tx,
+                                next_request_id,
+                                latest_view_request,
+                            )
+                            .await?;
+                        *latest_view_request = Some(request_id);
+                        self.landing_request = Some((request_id, byte));
+                    }
+                    Some(byte) => {
+                        view_state.status_line.clear_message();
+                        self.request_viewport(
+                            ViewportRequest::Absolute(byte),
+                            view_state,
+                            search_tx,
+                            next_request_id,
+                            latest_view_request,
+                        )
+                        .await?;
+                    }
+                    None => view_state
+                        .status_line
+                        .set_message(message.unwrap_or_default()),
+                }
+            }
+            SearchResponse::SearchCancelled { request_id }
+                if self.is_position_request(request_id) =>
+            {
+                self.position_request = None;
+                view_state
+                    .status_line
+                    .set_message("Jump cancelled".to_string());
+            }
+            SearchResponse::SearchProgress {
+                request_id,
+                bytes_scanned,
+                total_bytes,
+            } => {
+                // Reports from a search that has been replaced are late news
+                let current = Some(request_id) == *latest_search_request
+                    || self.is_position_request(request_id);
+                if current && total_bytes > 0 {
+                    self.search_progress_request = Some(request_id);
+                    let fraction = bytes_scanned as f64 / total_bytes as f64;
+                    self.pending_progress
+                        .insert(ProgressOperation::Search, fraction as f32);
+                }
+            }
+            SearchResponse::ExportFinished {
+                request_id,
+                message,
+                partial,
+            } => {
+                if self.export_request.as_ref().map(|(id, _)| *id) != Some(request_id) {
+                    return Ok(());
+                }
+                self.export_request = None;
+                self.pending_progress.insert(ProgressOperation::Export, 1.0);
+                let message = match partial {
+                    Some(path) => {
+                        let prompt =
+                            format!("{}; delete partial {}? (y/n)", message, path.display());
+                        self.partial_export = Some(path);
+                        prompt
+                    }
+                    None => message,
+                };
+                view_state.status_line.set_message(message);
+            }
+            SearchResponse::Progress {
+                operation,
+                fraction,
+            } => {
+                // Applied once per frame by `apply_progress`
+                self.pending_progress.insert(operation, fraction);
+                let indexed = operation == ProgressOperation::Count && fraction >= 1.0;
+                let pending =
+                    view_state.line_numbers_pending() || view_state.line_position_pending();
+                if indexed && pending && latest_view_request.is_none() {
+                    // The gutter can be numbered now, and the line position given
+                    self.request_viewport(
+                        ViewportRequest::Absolute(view_state.viewport_top_byte),
+                        view_state,
+                        search_tx,
+                        next_request_id,
+                        latest_view_request,
+                    )
+                    .await?;
+                }
+            }
+            SearchResponse::LinePeeked {
+                request_id,
+                start,
+                text,
+                highlights,
+                truncated,
+            } => {
+                if self.peek_request.take() != Some(request_id) {
+                    return Ok(());
+                }
+                let (text, highlights) = view_state.displayed(text, highlights);
+                view_state.peek = Some(LinePeek {
+                    start: Some(start),
+                    text,
+                    highlights,
+                    truncated,
+                    ..LinePeek::default()
+                });
+            }
+            SearchResponse::MatchBatch {
+                request_id,
+                matches,
+                last,
+                resume_from,
+            } => {
+                self.apply_match_batch(
+                    request_id,
+                    matches,
+                    last,
+                    resume_from,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                )?;
+            }
+            SearchResponse::FileDescribed {
+                meta,
+                canonical_path,
+                file_size,
+            } => {
+                let mut info = match meta {
+                    Some(meta) => meta.describe(format_local_time),
+                    None => human_size(file_size),
+                };
+                // The status line keeps the name as given; this is where it leads
+                if let Some(path) = canonical_path {
+                    info = format!("{} | {}", path.display(), info);
+                }
+                view_state.status_line.set_message(info);
+            }
+            SearchResponse::FileSwitched {
+                path,
+                file_size,
+                sections,
+            } => {
+                self.handle_file_switch(
+                    path,
+                    file_size,
+                    sections,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await?;
+            }
+            SearchResponse::FileReloaded {
+                file_size,
+                sections,
+                message,
+            } => {
+                view_state.file_size = Some(file_size);
+                view_state.set_sections(sections);
+                let message = match self.drop_stale_bookmarks(file_size, view_state) {
+                    Some(notice) => format!("{}; {}", message, notice),
+                    None => message,
+                };
+                view_state.status_line.set_message(message);
+                // Noticed by a growth check rather than a page load: nothing else will repaint
+                if latest_view_request.is_none() {
+                    self.handle_file_growth(
+                        view_state,
+                        search_tx,
+                        next_request_id,
+                        latest_view_request,
+                    )
+                    .await?;
+                }
+            }
+            SearchResponse::FileGrew {
+                file_size,
+                new_match,
+            } => {
+                view_state.file_size = Some(file_size);
+                if new_match.is_some() && self.search_state.is_some() {
+                    self.alert_new_match(view_state);
+                } else {
+                    self.hint_growth(view_state);
+                }
+                self.handle_file_growth(
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_view_request,
+                )
+                .await?;
+            }
+            SearchResponse::SearchCancelled { request_id } => {
+                if Some(request_id) != *latest_search_request {
+                    return Ok(());
+                }
+                *latest_search_request = None;
+                search_cancel_flag.take();
+                pending_search_state.take();
+                let _ = self.dispatch(search_tx, SearchCommand::ClearSearchContext);
+                view_state.status_line.clear_search_prompt();
+                view_state
+                    .status_line
+                    .set_message("Search cancelled".to_string());
+            }
+            SearchResponse::Error { request_id, error } => {
+                if self.peek_request == Some(request_id) {
+                    // Explain inside the overlay; it stays open until closed like any other
+                    self.peek_request = None;
+                    view_state.peek = Some(LinePeek {
+                        text: format!("Could not read line: {}", error.summary()),
+                        ..LinePeek::default()
+                    });
+                    return Ok(());
+                }
+                if self.is_match_list_request(request_id) {
+                    self.match_list_failed(error.summary(), view_state);
+                    return Ok(());
+                }
+                if self.is_position_request(request_id) {
+                    self.position_request = None;
+                    view_state.status_line.set_message(error.summary());
+                    return Ok(());
+                }
+                let view_failed = Some(request_id) == *latest_view_request;
+                if view_failed {
+                    *latest_view_request = None;
+                }
+                if view_failed && std::mem::take(&mut self.filter_pending) {
+                    // Drop the rejected filter so later viewport loads don't keep failing
+                    self.filter_state = None;
+                    view_state.status_line.filter = None;
+                    view_state
+                        .status_line
+                        .set_message(format!("Invalid filter: {}", error));
+                    return Ok(());
+                }
+                if Some(request_id) == *latest_search_request {
+                    *latest_search_request = None;
+                    pending_search_state.take();
+                }
+                search_cancel_flag.take();
+                view_state.status_line.set_message(error.summary());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `scanned 29.0 GiB in 18s` for a search slower than `SLOW_SEARCH`
+///
+/// Quick searches say nothing, so the status line only mentions the cost when it is felt.
+fn slow_scan(scan: Option<ScanStats>) -> Option<String> {
+    scan.filter(|scan| scan.elapsed > SLOW_SEARCH)
+        .map(|scan| format!("scanned {}", throughput(scan.bytes, scan.elapsed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::PROGRESS_INTERVAL;
+    use super::*;
+    use crate::input::InputAction;
+    use crate::input::{ScrollDirection, SearchDirection};
+    use crate::render::protocol::{LinePosition, MatchTraversal};
+    use crate::render::ui::PositionStyle;
+    use crate::search::SearchOptions;
+
+    #[tokio::test]
+    async fn search_jumps_keep_the_requested_context_above_the_match() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        let flags = |buffer: &str| InputAction::ExecuteCommand {
+            buffer: buffer.to_string(),
+        };
+
+        act!(flags("jx"));
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Invalid jump target: x")
+        );
+        act!(flags("j3"));
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Search jumps keep 3 lines above the match")
+        );
+
+        act!(InputAction::ExecuteSearch {
+            pattern: r"req-\d+".to_string(),
+            direction: SearchDirection::Forward,
+        });
+        let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected search command");
+        };
+        respond!(SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: Some(40),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        let Ok(SearchCommand::LoadViewport {
+            request_id,
+            top,
+            current_match,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected viewport load");
+        };
+        assert_eq!(
+            top,
+            ViewportRequest::RelativeLines {
+                anchor: 40,
+                lines: -3
+            }
+        );
+        assert_eq!(current_match, Some(40));
+        respond!(SearchResponse::ViewportLoaded {
+            request_id,
+            top_byte: 28,
+            lines: ["one", "two", "six", "req-42 done"]
+                .map(str::to_string)
+                .to_vec(),
+            highlights: vec![
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+                vec![(0, 6, HighlightKind::Search)],
+            ],
+            at_eof: false,
+            noeol: false,
+            highlights_truncated: false,
+            file_size: 4096,
+            top_line: None,
+            line_position: None,
+            current_match_row: Some(3),
+        });
+        assert_eq!(view_state.match_row(), Some(3));
+        assert_eq!(state.current_match_text(), Some("req-42"));
+
+        act!(flags("jc"));
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Search jumps put the match in the middle of the screen")
+        );
+        assert_eq!(
+            state.jump_request(40, &view_state),
+            ViewportRequest::RelativeLines {
+                anchor: 40,
+                lines: -11
+            }
+        );
+
+        // Scrolling on keeps asking the worker where the match is, until the search goes
+        act!(InputAction::Scroll {
+            direction: ScrollDirection::Down,
+            lines: 1,
+        });
+        let Ok(SearchCommand::LoadViewport { current_match, .. }) = search_rx.try_recv() else {
+            panic!("expected viewport load");
+        };
+        assert_eq!(current_match, Some(40));
+        state.clear_search(&mut view_state);
+        assert_eq!(view_state.match_row(), None);
+        act!(InputAction::Scroll {
+            direction: ScrollDirection::Down,
+            lines: 1,
+        });
+        let Ok(SearchCommand::LoadViewport { current_match, .. }) = search_rx.try_recv() else {
+            panic!("expected viewport load");
+        };
+        assert_eq!(current_match, None);
+    }
+
+    #[tokio::test]
+    async fn slow_searches_say_what_they_scanned() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.file_size = Some(40_000_000_000);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+        const GIB: u64 = 1 << 30;
+
+        let scanned = |match_byte: Option<u64>, message: Option<&str>, elapsed: u64| {
+            let scan = ScanStats {
+                bytes: 29 * GIB,
+                elapsed: std::time::Duration::from_millis(elapsed),
+            };
+            (match_byte, message.map(str::to_string), Some(scan))
+        };
+        for ((match_byte, message, scan), expected) in [
+            (
+                scanned(Some(28_800_000_000), None, 18_400),
+                Some("Found at 72% — scanned 29.0 GiB in 18s"),
+            ),
+            (scanned(Some(GIB), None, 1_900), None),
+            (
+                scanned(None, Some("Pattern not found"), 3_000),
+                Some("Pattern not found — scanned 29.0 GiB in 3.0s"),
+            ),
+        ] {
+            state
+                .process_action(
+                    InputAction::ExecuteSearch {
+                        pattern: "needle".to_string(),
+                        direction: SearchDirection::Forward,
+                    },
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_view_request,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                )
+                .await
+                .unwrap();
+            let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv() else {
+                panic!("expected search command");
+            };
+            state
+                .handle_response(
+                    SearchResponse::SearchCompleted {
+                        request_id,
+                        match_byte,
+                        message,
+                        confirm: None,
+                        scan,
+                    },
+                    &mut view_state,
+                    &mut latest_view_request,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                )
+                .await
+                .unwrap();
+            while search_rx.try_recv().is_ok() {}
+            assert_eq!(view_state.status_line.message.as_deref(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn searches_count_from_the_line_a_jump_landed_on() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        // Answer the viewport load a jump to `line` sent, with the page starting at `top`
+        macro_rules! land {
+            ($line:expr, $top:expr) => {
+                let Ok(SearchCommand::LoadViewport {
+                    request_id, top, ..
+                }) = search_rx.try_recv()
+                else {
+                    panic!("expected viewport load");
+                };
+                assert_eq!(
+                    top,
+                    ViewportRequest::RelativeLines {
+                        anchor: $line,
+                        lines: -3
+                    }
+                );
+                respond!(SearchResponse::ViewportLoaded {
+                    request_id,
+                    top_byte: $top,
+                    lines: vec!["line".to_string(); 23],
+                    highlights: Vec::new(),
+                    at_eof: false,
+                    noeol: false,
+                    highlights_truncated: false,
+                    file_size: 4096,
+                    top_line: None,
+                    line_position: None,
+                    current_match_row: None,
+                });
+            };
+        }
+        // Press `n` and find the next match at `found`
+        macro_rules! next {
+            ($origin:expr, $found:expr) => {
+                act!(InputAction::NextMatch);
+                let Ok(SearchCommand::NavigateMatch {
+                    request_id,
+                    current_top,
+                    origin_row,
+                    ..
+                }) = search_rx.try_recv()
+                else {
+                    panic!("expected match navigation");
+                };
+                assert_eq!((current_top, origin_row), $origin);
+                respond!(SearchResponse::SearchCompleted {
+                    request_id,
+                    match_byte: Some($found),
+                    message: None,
+                    confirm: None,
+                    scan: None,
+                });
+            };
+        }
+
+        act!(InputAction::ExecuteCommand {
+            buffer: "j3".to_string(),
+        });
+        // Before any jump, the search starts three rows down, where its match will go
+        act!(InputAction::ExecuteSearch {
+            pattern: "req".to_string(),
+            direction: SearchDirection::Forward,
+        });
+        let Ok(SearchCommand::ExecuteSearch {
+            request_id,
+            origin_byte,
+            origin_row,
+            skip_origin_row,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected search command");
+        };
+        assert_eq!((origin_byte, origin_row, skip_origin_row), (0, 3, true));
+        respond!(SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: Some(4),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        // Only one line fits above a match this close to the start
+        land!(4, 0);
+
+        next!((4, 0), 40);
+        land!(40, 28);
+        next!((40, 0), 60);
+        land!(60, 48);
+
+        // ESC-n puts the line it finds on the same row, and the next search counts from there
+        act!(InputAction::NextNonMatch);
+        let Ok(SearchCommand::NavigateMatch {
+            request_id,
+            traversal: MatchTraversal::NextNonMatch,
+            current_top: 60,
+            origin_row: 0,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected non-match navigation");
+        };
+        respond!(SearchResponse::PositionFound {
+            request_id,
+            byte: Some(72),
+            message: None,
+        });
+        land!(72, 60);
+        act!(InputAction::ExecuteSearch {
+            pattern: "req".to_string(),
+            direction: SearchDirection::Forward,
+        });
+        let Ok(SearchCommand::ExecuteSearch {
+            origin_byte,
+            origin_row,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected search command");
+        };
+        assert_eq!((origin_byte, origin_row), (72, 0));
+
+        // Once the view moves, the row three down from the top is the origin again
+        act!(InputAction::Scroll {
+            direction: ScrollDirection::Down,
+            lines: 1,
+        });
+        let Ok(SearchCommand::LoadViewport { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected viewport load");
+        };
+        respond!(SearchResponse::ViewportLoaded {
+            request_id,
+            top_byte: 64,
+            lines: vec!["line".to_string(); 23],
+            highlights: Vec::new(),
+            at_eof: false,
+            noeol: false,
+            highlights_truncated: false,
+            file_size: 4096,
+            top_line: None,
+            line_position: None,
+            current_match_row: None,
+        });
+        next!((64, 3), 100);
+    }
+
+    #[tokio::test]
+    async fn position_jumps_leave_the_search_and_its_current_match_alone() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! loaded {
+            ($request_id:expr, $top:expr, $line:expr) => {
+                respond!(SearchResponse::ViewportLoaded {
+                    request_id: $request_id,
+                    top_byte: $top,
+                    lines: vec![$line.to_string()],
+                    highlights: vec![vec![(0, 6, HighlightKind::Search)]],
+                    at_eof: false,
+                    noeol: false,
+                    highlights_truncated: false,
+                    file_size: 4096,
+                    top_line: None,
+                    line_position: None,
+                    // The worker finds the match on the top row of its own page
+                    current_match_row: ($top == 40).then_some(0),
+                })
+            };
+        }
+
+        act!(InputAction::ExecuteSearch {
+            pattern: r"req-\d+".to_string(),
+            direction: SearchDirection::Forward,
+        });
+        let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected search command");
+        };
+        respond!(SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: Some(40),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        let Ok(SearchCommand::LoadViewport { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected viewport load");
+        };
+        loaded!(request_id, 40, "req-42 done");
+        assert_eq!(state.current_match_text(), Some("req-42"));
+
+        let skip_dups = || InputAction::ExecuteColonCommand {
+            buffer: "skip-dups".to_string(),
+        };
+        act!(skip_dups());
+        let Ok(SearchCommand::SkipDuplicates { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected skip command");
+        };
+        respond!(SearchResponse::SearchCancelled { request_id });
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Jump cancelled")
+        );
+        // The worker keeps its search context and the highlight stays
+        assert!(search_rx.try_recv().is_err());
+        assert!(state.highlight_spec().is_some());
+
+        act!(skip_dups());
+        let Ok(SearchCommand::SkipDuplicates { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected skip command");
+        };
+        respond!(SearchResponse::PositionFound {
+            request_id,
+            byte: Some(100),
+            message: None,
+        });
+        let Ok(SearchCommand::LoadViewport {
+            request_id, top, ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected viewport load");
+        };
+        assert_eq!(top, ViewportRequest::Absolute(100));
+        loaded!(request_id, 100, "req-77 done");
+        assert_eq!(view_state.viewport_top_byte, 100);
+        assert_eq!(state.current_match_text(), Some("req-42"));
+        assert!(state.highlight_spec().is_some());
+
+        // Record jumps take the same path; a newer jump cancels the one in flight, while an
+        // in-flight search keeps its own cancel flag
+        act!(InputAction::NextMatch);
+        let Ok(SearchCommand::NavigateMatch { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected match navigation");
+        };
+        act!(InputAction::NextRecord);
+        let Ok(SearchCommand::NavigateRecord {
+            cancel_flag: first, ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected record navigation");
+        };
+        act!(InputAction::NextRecord);
+        assert!(first.is_cancelled());
+        assert_eq!(latest_search_request, Some(request_id));
+        assert!(search_cancel_flag
+            .as_ref()
+            .is_some_and(|flag| !flag.is_cancelled()));
+    }
+
+    #[tokio::test]
+    async fn search_progress_shows_for_the_latest_search_until_it_answers() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+        let start = tokio::time::Instant::now();
+
+        macro_rules! search {
+            ($pattern:expr) => {{
+                state
+                    .process_action(
+                        InputAction::ExecuteSearch {
+                            pattern: $pattern.to_string(),
+                            direction: SearchDirection::Forward,
+                        },
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap();
+                let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv()
+                else {
+                    panic!("expected search command");
+                };
+                request_id
+            }};
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        let progress = |request_id, bytes_scanned| SearchResponse::SearchProgress {
+            request_id,
+            bytes_scanned,
+            total_bytes: 1000,
+        };
+
+        let first = search!("timeout");
+        respond!(progress(first, 420));
+        assert!(state.apply_progress(&mut view_state, start));
+        assert_eq!(
+            view_state.status_line.progress.as_deref(),
+            Some("searching 42%")
+        );
+
+        // Late reports of a replaced search change nothing
+        let second = search!("refused");
+        respond!(progress(first, 900));
+        assert!(!state.apply_progress(&mut view_state, start + PROGRESS_INTERVAL));
+        respond!(progress(second, 100));
+        assert!(state.apply_progress(&mut view_state, start + PROGRESS_INTERVAL));
+        assert_eq!(
+            view_state.status_line.progress.as_deref(),
+            Some("searching 10%")
+        );
+
+        // The answer clears it straight away
+        respond!(SearchResponse::SearchCompleted {
+            request_id: second,
+            match_byte: Some(640),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        assert!(state.apply_progress(&mut view_state, start + PROGRESS_INTERVAL));
+        assert_eq!(view_state.status_line.progress, None);
+    }
+
+    #[tokio::test]
+    async fn finished_indexing_reloads_a_page_shown_without_line_numbers() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.set_line_numbers(true);
+        view_state.navigate_to_byte(4096);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+        macro_rules! respond {
+            ($fraction:expr) => {
+                state
+                    .handle_response(
+                        SearchResponse::Progress {
+                            operation: ProgressOperation::Count,
+                            fraction: $fraction,
+                        },
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        respond!(0.5);
+        assert!(search_rx.try_recv().is_err());
+        assert!(state.apply_progress(&mut view_state, tokio::time::Instant::now()));
+        assert_eq!(
+            view_state.status_line.progress.as_deref(),
+            Some("counting 50%")
+        );
+
+        respond!(1.0);
+        let Ok(SearchCommand::LoadViewport {
+            request_id, top, ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected a viewport reload");
+        };
+        assert_eq!(top, ViewportRequest::Absolute(4096));
+        assert_eq!(latest_view_request, Some(request_id));
+
+        // Once numbered, nothing more to reload
+        view_state.set_top_line(Some(120));
+        latest_view_request = None;
+        respond!(1.0);
+        assert!(search_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn finished_indexing_reloads_a_page_shown_without_a_line_total() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.position_style = PositionStyle::Lines;
+        view_state.file_size = Some(8192);
+        view_state.line_position = Some(LinePosition {
+            top: 120,
+            total: None,
+        });
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+        macro_rules! indexed {
+            () => {
+                state
+                    .handle_response(
+                        SearchResponse::Progress {
+                            operation: ProgressOperation::Count,
+                            fraction: 1.0,
+                        },
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        assert_eq!(view_state.format_status_line(), "test | L120");
+        indexed!();
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::LoadViewport { .. })
+        ));
+
+        // With the total known, nothing more to reload
+        view_state.line_position = Some(LinePosition {
+            top: 120,
+            total: Some(4_000),
+        });
+        latest_view_request = None;
+        indexed!();
+        assert!(search_rx.try_recv().is_err());
+        assert_eq!(view_state.format_status_line(), "test | L120/4,000");
+    }
+}
@@ -0,0 +1,1228 @@
+//! Starting searches, moving between their matches, hiding their highlights and keeping earlier
+//! ones highlighted.
+
+use super::RenderLoopState;
+use crate::error::Result;
+use crate::input::SearchDirection;
+use crate::render::protocol::{
+    MatchTraversal, RequestId, SearchCommand, SearchContext, SearchHighlightSpec, ViewportRequest,
+    MAX_KEPT_PATTERNS,
+};
+use crate::render::ui::ViewState;
+use crate::search::pattern_set::describe_set;
+use crate::search::{check_regex, PatternModifiers, SearchOptions, MAX_PATTERN_BYTES};
+use crate::util::CancellationToken;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+impl RenderLoopState {
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn queue_match_navigation(
+        &mut self,
+        traversal: MatchTraversal,
+        count: u64,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+    ) -> Result<bool> {
+        let request_id = *next_request_id;
+        *next_request_id += 1;
+        *latest_search_request = Some(request_id);
+        let cancel_flag = CancellationToken::new();
+        *search_cancel_flag = Some(cancel_flag.clone());
+        let (current_top, origin_row) = self.origin_line(view_state);
+        self.dispatch(
+            search_tx,
+            SearchCommand::NavigateMatch {
+                request_id,
+                traversal,
+                count,
+                current_top,
+                origin_row,
+                cancel_flag,
+            },
+        )?;
+        Ok(true)
+    }
+
+    /// Dispatch a new search, registering its highlight as pending until the worker confirms
+    ///
+    /// `options` already have the pattern's inline `modifiers` applied.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn queue_search(
+        &mut self,
+        pattern: String,
+        options: SearchOptions,
+        modifiers: PatternModifiers,
+        direction: SearchDirection,
+        skip_origin_row: bool,
+        frequency_limit: Option<u64>,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+    ) -> Result<bool> {
+        // Sets are capped when loaded
+        if pattern.len() > MAX_PATTERN_BYTES && !options.pattern_set {
+            view_state.status_line.clear_search_prompt();
+            view_state.status_line.set_message(format!(
+                "Pattern too long ({} bytes, limit {})",
+                pattern.len(),
+                MAX_PATTERN_BYTES
+            ));
+            return Ok(true);
+        }
+        // Caught here, a typo leaves the active search and its highlights as they were
+        if options.regex_mode && !options.pattern_set {
+            if let Err(error) = check_regex(&pattern) {
+                view_state.status_line.clear_search_prompt();
+                view_state.status_line.set_message(error.summary());
+                return Ok(true);
+            }
+        }
+
+        let (origin_byte, origin_row) = self.origin_line(view_state);
+        if let (Some((request_id, pending)), Some(latest)) =
+            (pending_search_state.as_ref(), *latest_search_request)
+        {
+            // Sent again before it answered, as by a held Enter: running it twice would only
+            // scan the same bytes again and restart the match count
+            if *request_id == latest
+                && self.search_origin == Some((latest, origin_byte, direction, skip_origin_row))
+                && *pending.pattern == *pattern
+                && pending.options == options
+            {
+                return Ok(true);
+            }
+        }
+
+        if self.highlights_hidden {
+            // A new search is shown, as in `less`
+            self.highlights_hidden = false;
+            self.dispatch(search_tx, SearchCommand::ShowHighlights(true))?;
+        }
+
+        let pattern: Arc<str> = Arc::from(pattern);
+        self.search_direction = direction;
+        let request_id = *next_request_id;
+        *next_request_id += 1;
+        *latest_search_request = Some(request_id);
+        self.search_origin = Some((request_id, origin_byte, direction, skip_origin_row));
+        let highlight = Arc::new(SearchHighlightSpec {
+            pattern: Arc::clone(&pattern),
+            options: options.clone(),
+            modifiers,
+        });
+        pending_search_state.replace((request_id, Arc::clone(&highlight)));
+        let cancel_flag = CancellationToken::new();
+        if let Some(superseded) = search_cancel_flag.replace(cancel_flag.clone()) {
+            // The worker runs one command at a time; a search nobody waits for any more would
+            // hold this one up until it had scanned the rest of the file
+            superseded.cancel();
+        }
+
+        self.dispatch(
+            search_tx,
+            SearchCommand::ExecuteSearch {
+                request_id,
+                pattern,
+                direction,
+                options,
+                origin_byte,
+                origin_row,
+                skip_origin_row,
+                frequency_limit,
+                preview: false,
+                cancel_flag,
+            },
+        )?;
+        Ok(true)
+    }
+
+    /// ESC-u: hide the active search's highlights, or show them again
+    ///
+    /// The search stays active, so `n`/`N` keep moving between its matches, and showing the
+    /// highlights again needs no new search.
+    pub(super) async fn toggle_highlights(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<()> {
+        self.highlights_hidden = !self.highlights_hidden;
+        self.dispatch(
+            search_tx,
+            SearchCommand::ShowHighlights(!self.highlights_hidden),
+        )?;
+        let message = if self.highlights_hidden {
+            "Search highlights hidden (ESC-u shows them)"
+        } else {
+            "Search highlights shown"
+        };
+        view_state.status_line.set_message(message.to_string());
+        self.request_viewport(
+            ViewportRequest::Absolute(view_state.viewport_top_byte),
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// `:keep`: go on highlighting the active search, in a color of its own, once another
+    /// search replaces it; `:keep clear` stops highlighting the kept patterns
+    ///
+    /// At most `MAX_KEPT_PATTERNS` are kept; keeping another drops the oldest.
+    pub(super) async fn keep_search(
+        &mut self,
+        clear: bool,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        let message = if clear {
+            self.kept_patterns.clear();
+            "Kept patterns cleared".to_string()
+        } else {
+            let Some(search) = self.search_state.clone() else {
+                view_state
+                    .status_line
+                    .set_message("No active search to keep".to_string());
+                return Ok(true);
+            };
+            let message = format!(
+                "Keeping {} highlighted (ESC-p switches which pattern n/N follow)",
+                pattern_label(&search)
+            );
+            self.kept_patterns.retain(|kept| **kept != *search);
+            self.kept_patterns.insert(0, search);
+            self.kept_patterns.truncate(MAX_KEPT_PATTERNS);
+            message
+        };
+        view_state.status_line.set_message(message);
+        self.reload_kept_patterns(view_state, search_tx, next_request_id, latest_view_request)
+            .await
+    }
+
+    /// ESC-p: make the most recently kept pattern the active search, which `n`/`N` move
+    /// between, and keep the one it replaces
+    ///
+    /// Repeating it goes round the active search and the kept patterns, oldest last.
+    pub(super) async fn cycle_primary_pattern(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        let mut ring: Vec<Arc<SearchHighlightSpec>> = Vec::new();
+        for spec in self.search_state.iter().chain(&self.kept_patterns) {
+            if !ring.contains(spec) {
+                ring.push(Arc::clone(spec));
+            }
+        }
+        if ring.len() < 2 {
+            view_state
+                .status_line
+                .set_message("No other pattern kept (:keep keeps the active search)".to_string());
+            return Ok(true);
+        }
+        ring.rotate_left(1);
+        let primary = ring.remove(0);
+        self.kept_patterns = ring;
+        self.kept_patterns.truncate(MAX_KEPT_PATTERNS);
+
+        self.dispatch(
+            search_tx,
+            SearchCommand::UpdateSearchContext(SearchContext {
+                pattern: Arc::clone(&primary.pattern),
+                direction: self.search_direction,
+                options: primary.options.clone(),
+                last_match_byte: None,
+            }),
+        )?;
+        let message = format!("n/N follow {}", pattern_label(&primary));
+        self.set_search(primary, view_state);
+        self.current_match_text = None;
+        view_state.status_line.set_message(message);
+        self.reload_kept_patterns(view_state, search_tx, next_request_id, latest_view_request)
+            .await
+    }
+
+    /// Hand the kept patterns to the worker and redraw the page with them
+    async fn reload_kept_patterns(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        self.dispatch(
+            search_tx,
+            SearchCommand::KeepPatterns(self.kept_patterns.clone()),
+        )?;
+        self.request_viewport(
+            ViewportRequest::Absolute(view_state.viewport_top_byte),
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await?;
+        Ok(true)
+    }
+}
+
+/// How the status line names the pattern of `spec`
+pub(super) fn pattern_label(spec: &SearchHighlightSpec) -> String {
+    if spec.options.pattern_set {
+        describe_set(&spec.pattern)
+    } else {
+        format!("/{}", spec.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DEFAULT_FREQUENT_MATCH_LIMIT;
+    use super::*;
+    use crate::input::InputAction;
+    use crate::render::protocol::FrequentMatches;
+    use crate::render::protocol::HighlightKind;
+    use crate::render::protocol::SearchResponse;
+
+    #[tokio::test]
+    async fn star_searches_for_the_current_match_text() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! respond {
+            ($response:expr) => {
+                state
+                    .handle_response(
+                        $response,
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        act!(InputAction::SearchCurrentMatchWord);
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("No current match")
+        );
+        assert!(search_rx.try_recv().is_err());
+
+        act!(InputAction::ExecuteSearch {
+            pattern: r"req-\d+".to_string(),
+            direction: SearchDirection::Forward,
+        });
+        let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected search command");
+        };
+        respond!(SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: Some(40),
+            message: None,
+            confirm: None,
+            scan: None,
+        });
+        let Ok(SearchCommand::LoadViewport { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected viewport load");
+        };
+        respond!(SearchResponse::ViewportLoaded {
+            request_id,
+            top_byte: 40,
+            lines: vec!["GET /a?id=req-42.1 done".to_string()],
+            highlights: vec![vec![
+                (0, 3, HighlightKind::Filter),
+                (10, 16, HighlightKind::Search),
+            ]],
+            at_eof: true,
+            noeol: false,
+            highlights_truncated: false,
+            file_size: 64,
+            top_line: None,
+            line_position: None,
+            current_match_row: Some(0),
+        });
+        assert_eq!(state.current_match_text(), Some("req-42"));
+
+        act!(InputAction::SearchCurrentMatchWord);
+        match search_rx.try_recv() {
+            Ok(SearchCommand::ExecuteSearch {
+                pattern,
+                direction,
+                origin_byte,
+                origin_row: 0,
+                skip_origin_row,
+                ..
+            }) => {
+                assert_eq!(&*pattern, "req-42");
+                assert_eq!(direction, SearchDirection::Forward);
+                // Like `n`, from the row after the one holding the current match
+                assert_eq!((origin_byte, skip_origin_row), (40, true));
+            }
+            other => panic!("unexpected command: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn inline_modifiers_apply_to_one_search() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let ignore_case = SearchOptions {
+            case_sensitive: false,
+            ..SearchOptions::default()
+        };
+        let mut state = RenderLoopState::new(ignore_case.clone());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! search {
+            ($pattern:expr) => {
+                state
+                    .process_action(
+                        InputAction::ExecuteSearch {
+                            pattern: $pattern.to_string(),
+                            direction: SearchDirection::Forward,
+                        },
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        search!(r"\CError");
+        let Ok(SearchCommand::ExecuteSearch {
+            request_id,
+            pattern,
+            options,
+            skip_origin_row,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected search command");
+        };
+        assert_eq!(&*pattern, "Error");
+        // A typed search starts below the top row, as in less
+        assert!(skip_origin_row);
+        assert!(options.case_sensitive);
+        assert_eq!(state.search_options(), &ignore_case);
+        state
+            .handle_response(
+                SearchResponse::SearchCompleted {
+                    request_id,
+                    match_byte: Some(0),
+                    message: None,
+                    confirm: None,
+                    scan: None,
+                },
+                &mut view_state,
+                &mut latest_view_request,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+                &mut search_tx,
+                &mut next_request_id,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            view_state.status_line.active_pattern,
+            Some((SearchDirection::Forward, r"\CError".to_string()))
+        );
+
+        // Changing the global options keeps the override for this search
+        state.set_search_options(SearchOptions {
+            whole_word: true,
+            ..ignore_case.clone()
+        });
+        let search = state.search_state.clone().unwrap();
+        assert!(search.options.case_sensitive && search.options.whole_word);
+        assert_eq!(
+            state.search_options_summary(),
+            r"search options: i,re,w (\C search: re,w)"
+        );
+
+        // An escaped backslash is not a modifier
+        while search_rx.try_recv().is_ok() {}
+        search!(r"\\cat");
+        let Ok(SearchCommand::ExecuteSearch {
+            pattern, options, ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected search command");
+        };
+        assert_eq!(&*pattern, r"\\cat");
+        assert!(!options.case_sensitive);
+
+        search!(r"\c");
+        assert!(search_rx.try_recv().is_err());
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some(r"Nothing to search for after \c")
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_regexes_are_reported_before_anything_is_sent() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+        let active = Arc::new(SearchHighlightSpec {
+            pattern: Arc::from("error"),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        });
+        state.set_search(Arc::clone(&active), &mut view_state);
+
+        macro_rules! search {
+            ($pattern:expr) => {
+                state
+                    .process_action(
+                        InputAction::ExecuteSearch {
+                            pattern: $pattern.to_string(),
+                            direction: SearchDirection::Forward,
+                        },
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        for (pattern, message) in [
+            ("foo(", "regex error: unclosed group at position 4"),
+            (
+                "ab[c-",
+                "regex error: unclosed character class at position 3",
+            ),
+            ("x)", "regex error: unopened group at position 2"),
+            (
+                "**",
+                "regex error: repetition operator missing expression at position 1",
+            ),
+        ] {
+            search!(pattern);
+            assert_eq!(view_state.status_line.message.as_deref(), Some(message));
+            assert!(search_rx.try_recv().is_err(), "{}", pattern);
+            assert!(pending_search_state.is_none());
+            assert_eq!(latest_search_request, None);
+            assert_eq!(state.search_state.as_ref(), Some(&active));
+            assert_eq!(
+                view_state.status_line.active_pattern,
+                Some((SearchDirection::Forward, "error".to_string()))
+            );
+        }
+
+        // Literal mode never parses the pattern as a regex
+        search!(r"\lfoo(");
+        let Ok(SearchCommand::ExecuteSearch { pattern, .. }) = search_rx.try_recv() else {
+            panic!("expected search command");
+        };
+        assert_eq!(&*pattern, "foo(");
+    }
+
+    #[tokio::test]
+    async fn a_new_search_cancels_the_one_still_running() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        let mut flags = Vec::new();
+        for pattern in ["missing", "other"] {
+            state
+                .process_action(
+                    InputAction::ExecuteSearch {
+                        pattern: pattern.to_string(),
+                        direction: SearchDirection::Forward,
+                    },
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_view_request,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                )
+                .await
+                .unwrap();
+            let Ok(SearchCommand::ExecuteSearch { cancel_flag, .. }) = search_rx.try_recv() else {
+                panic!("expected search command");
+            };
+            flags.push(cancel_flag);
+        }
+        assert!(flags[0].is_cancelled());
+        assert!(!flags[1].is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn frequent_pattern_runs_only_after_confirmation() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        // Search for `e`, have the worker hold it back, and return the prompt shown
+        macro_rules! held_search {
+            () => {{
+                act!(InputAction::ExecuteSearch {
+                    pattern: "e".to_string(),
+                    direction: SearchDirection::Backward,
+                });
+                let Ok(SearchCommand::ExecuteSearch {
+                    request_id,
+                    frequency_limit,
+                    ..
+                }) = search_rx.try_recv()
+                else {
+                    panic!("expected search command");
+                };
+                assert_eq!(frequency_limit, Some(DEFAULT_FREQUENT_MATCH_LIMIT));
+                state
+                    .handle_response(
+                        SearchResponse::SearchCompleted {
+                            request_id,
+                            match_byte: None,
+                            message: None,
+                            confirm: Some(FrequentMatches {
+                                pattern: Arc::from("e"),
+                                direction: SearchDirection::Backward,
+                                options: SearchOptions::default(),
+                                skip_origin_row: false,
+                                matches_per_mb: 90_000,
+                            }),
+                            scan: None,
+                        },
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap();
+                view_state.status_line.message.clone()
+            }};
+        }
+
+        assert_eq!(
+            held_search!().as_deref(),
+            Some("Pattern matches very frequently (~90000 per MB) — continue? (y/n)")
+        );
+        assert!(pending_search_state.is_none());
+
+        // Any other key declines without acting on it
+        act!(InputAction::NextMatch);
+        assert!(search_rx.try_recv().is_err());
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Search cancelled")
+        );
+        act!(InputAction::ConfirmPrompt);
+        assert!(search_rx.try_recv().is_err());
+
+        // `y` reruns the same search without the check; resizing meanwhile is not an answer
+        assert!(held_search!().is_some());
+        act!(InputAction::Resize {
+            width: 100,
+            height: 30,
+        });
+        while search_rx.try_recv().is_ok() {}
+        act!(InputAction::ConfirmPrompt);
+        match search_rx.try_recv() {
+            Ok(SearchCommand::ExecuteSearch {
+                pattern,
+                direction,
+                frequency_limit,
+                ..
+            }) => {
+                assert_eq!(
+                    (&*pattern, direction, frequency_limit),
+                    ("e", SearchDirection::Backward, None)
+                );
+            }
+            other => panic!("expected search command, got {other:?}"),
+        }
+        assert!(view_state.status_line.message.is_none());
+
+        // With the check disabled searches are never held back
+        state.set_frequent_match_limit(None);
+        act!(InputAction::ExecuteSearch {
+            pattern: "e".to_string(),
+            direction: SearchDirection::Forward,
+        });
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::ExecuteSearch {
+                frequency_limit: None,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn active_pattern_follows_the_search_context() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! search {
+            ($pattern:expr, $direction:expr, $match_byte:expr, $message:expr) => {{
+                act!(InputAction::ExecuteSearch {
+                    pattern: $pattern.to_string(),
+                    direction: $direction,
+                });
+                let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv()
+                else {
+                    panic!("expected search command");
+                };
+                state
+                    .handle_response(
+                        SearchResponse::SearchCompleted {
+                            request_id,
+                            match_byte: $match_byte,
+                            message: $message,
+                            confirm: None,
+                            scan: None,
+                        },
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap();
+                while search_rx.try_recv().is_ok() {}
+            }};
+        }
+
+        assert_eq!(view_state.window_title(), "rlless — test");
+        search!("timeout", SearchDirection::Backward, Some(40), None);
+        assert_eq!(
+            view_state.status_line.active_pattern,
+            Some((SearchDirection::Backward, "timeout".to_string()))
+        );
+        assert_eq!(view_state.window_title(), "rlless — test — ?timeout");
+
+        // A search still in flight leaves the active one in place
+        act!(InputAction::ExecuteSearch {
+            pattern: "retries".to_string(),
+            direction: SearchDirection::Forward,
+        });
+        assert_eq!(
+            view_state.status_line.active_pattern,
+            Some((SearchDirection::Backward, "timeout".to_string()))
+        );
+        while search_rx.try_recv().is_ok() {}
+
+        search!("retry", SearchDirection::Forward, Some(80), None);
+        assert_eq!(
+            view_state.status_line.active_pattern,
+            Some((SearchDirection::Forward, "retry".to_string()))
+        );
+
+        // A failed search drops the search context and the pattern with it
+        search!(
+            "missing",
+            SearchDirection::Forward,
+            None,
+            Some("Pattern not found".to_string())
+        );
+        assert_eq!(view_state.status_line.active_pattern, None);
+        assert_eq!(view_state.window_title(), "rlless — test");
+    }
+
+    #[tokio::test]
+    async fn named_patterns_expand_before_dispatch() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        for pattern in ["@ipv4 refused", "@nope"] {
+            state
+                .process_action(
+                    InputAction::ExecuteSearch {
+                        pattern: pattern.to_string(),
+                        direction: SearchDirection::Forward,
+                    },
+                    &mut view_state,
+                    &mut search_tx,
+                    &mut next_request_id,
+                    &mut latest_view_request,
+                    &mut latest_search_request,
+                    &mut search_cancel_flag,
+                    &mut pending_search_state,
+                )
+                .await
+                .unwrap();
+        }
+
+        let Ok(SearchCommand::ExecuteSearch { pattern, .. }) = search_rx.try_recv() else {
+            panic!("expected search command");
+        };
+        assert!(pattern.starts_with(r"(?:\b(?:(?:25[0-5]"));
+        assert!(pattern.ends_with(") refused"));
+        // The unknown name is reported and nothing is sent
+        assert!(search_rx.try_recv().is_err());
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("Unknown pattern: @nope (see :patterns)")
+        );
+    }
+
+    #[tokio::test]
+    async fn repeating_a_search_still_in_flight_sends_nothing() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! search {
+            ($pattern:expr) => {
+                state
+                    .process_action(
+                        InputAction::ExecuteSearch {
+                            pattern: $pattern.to_string(),
+                            direction: SearchDirection::Forward,
+                        },
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        for _ in 0..5 {
+            search!("timeout");
+        }
+        let Ok(SearchCommand::ExecuteSearch {
+            request_id,
+            cancel_flag,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected search command");
+        };
+        assert!(search_rx.try_recv().is_err());
+        assert!(!cancel_flag.is_cancelled());
+
+        // From somewhere else it is a different search
+        view_state.navigate_to_byte(4096);
+        search!("timeout");
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::ExecuteSearch {
+                origin_byte: 4096,
+                origin_row: 0,
+                ..
+            })
+        ));
+        assert!(cancel_flag.is_cancelled());
+
+        // Once answered, the same search runs again
+        state
+            .handle_response(
+                SearchResponse::SearchCompleted {
+                    request_id: request_id + 1,
+                    match_byte: None,
+                    message: Some("Pattern not found".to_string()),
+                    confirm: None,
+                    scan: None,
+                },
+                &mut view_state,
+                &mut latest_view_request,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+                &mut search_tx,
+                &mut next_request_id,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::ClearSearchContext)
+        ));
+        search!("timeout");
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::ExecuteSearch {
+                origin_byte: 4096,
+                origin_row: 0,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn hidden_highlights_keep_the_search_until_a_new_one() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        macro_rules! search {
+            ($pattern:expr) => {
+                act!(InputAction::ExecuteSearch {
+                    pattern: $pattern.to_string(),
+                    direction: SearchDirection::Forward,
+                })
+            };
+        }
+
+        search!("timeout");
+        let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv() else {
+            panic!("expected search command");
+        };
+        state
+            .handle_response(
+                SearchResponse::SearchCompleted {
+                    request_id,
+                    match_byte: Some(40),
+                    message: None,
+                    confirm: None,
+                    scan: None,
+                },
+                &mut view_state,
+                &mut latest_view_request,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+                &mut search_tx,
+                &mut next_request_id,
+            )
+            .await
+            .unwrap();
+        while search_rx.try_recv().is_ok() {}
+
+        for shown in [false, true] {
+            act!(InputAction::ToggleHighlights);
+            assert!(matches!(
+                search_rx.try_recv(),
+                Ok(SearchCommand::ShowHighlights(show)) if show == shown
+            ));
+            let Ok(SearchCommand::LoadViewport { highlights, .. }) = search_rx.try_recv() else {
+                panic!("expected viewport load");
+            };
+            assert_eq!(highlights.is_some(), shown);
+            latest_view_request = None;
+
+            // `n` keeps going either way
+            act!(InputAction::NextMatch);
+            assert!(matches!(
+                search_rx.try_recv(),
+                Ok(SearchCommand::NavigateMatch { .. })
+            ));
+            assert!(state.search_state.is_some());
+        }
+
+        // A new search is highlighted again
+        act!(InputAction::ToggleHighlights);
+        while search_rx.try_recv().is_ok() {}
+        search!("refused");
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::ShowHighlights(true))
+        ));
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::ExecuteSearch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn kept_patterns_stay_highlighted_and_take_turns_as_primary() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        // Run a search that finds a match, leaving nothing queued
+        macro_rules! search {
+            ($pattern:expr) => {{
+                act!(InputAction::ExecuteSearch {
+                    pattern: $pattern.to_string(),
+                    direction: SearchDirection::Forward,
+                });
+                let Ok(SearchCommand::ExecuteSearch { request_id, .. }) = search_rx.try_recv()
+                else {
+                    panic!("expected search command");
+                };
+                state
+                    .handle_response(
+                        SearchResponse::SearchCompleted {
+                            request_id,
+                            match_byte: Some(40),
+                            message: None,
+                            confirm: None,
+                            scan: None,
+                        },
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap();
+                while search_rx.try_recv().is_ok() {}
+                latest_view_request = None;
+            }};
+        }
+        // The patterns sent to the worker, then the page reloaded to show them
+        macro_rules! kept {
+            () => {{
+                let Ok(SearchCommand::KeepPatterns(kept)) = search_rx.try_recv() else {
+                    panic!("expected kept patterns");
+                };
+                assert!(matches!(
+                    search_rx.try_recv(),
+                    Ok(SearchCommand::LoadViewport { .. })
+                ));
+                latest_view_request = None;
+                kept.iter()
+                    .map(|spec| spec.pattern.to_string())
+                    .collect::<Vec<_>>()
+            }};
+        }
+        macro_rules! primary {
+            () => {
+                state.search_state.as_ref().unwrap().pattern.to_string()
+            };
+        }
+        let keep = |clear| InputAction::ExecuteColonCommand {
+            buffer: if clear { "keep clear" } else { "keep" }.to_string(),
+        };
+
+        act!(keep(false));
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("No active search to keep")
+        );
+        act!(InputAction::CyclePrimaryPattern);
+        assert!(search_rx.try_recv().is_err());
+
+        search!("request-42");
+        act!(keep(false));
+        assert_eq!(kept!(), ["request-42"]);
+        search!("ERROR");
+        act!(keep(false));
+        assert_eq!(kept!(), ["ERROR", "request-42"]);
+        search!("WARN");
+        act!(keep(false));
+        // The oldest makes room
+        assert_eq!(kept!(), ["WARN", "ERROR"]);
+        search!("timeout");
+
+        act!(InputAction::CyclePrimaryPattern);
+        let Ok(SearchCommand::UpdateSearchContext(context)) = search_rx.try_recv() else {
+            panic!("expected the search context to move");
+        };
+        assert_eq!(&*context.pattern, "WARN");
+        assert_eq!(kept!(), ["ERROR", "timeout"]);
+        assert_eq!(primary!(), "WARN");
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("n/N follow /WARN")
+        );
+        // `n` moves between the matches of the new primary pattern
+        act!(InputAction::NextMatch);
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::NavigateMatch { .. })
+        ));
+
+        for (primary, others) in [
+            ("ERROR", ["timeout", "WARN"]),
+            ("timeout", ["WARN", "ERROR"]),
+        ] {
+            act!(InputAction::CyclePrimaryPattern);
+            assert!(matches!(
+                search_rx.try_recv(),
+                Ok(SearchCommand::UpdateSearchContext(_))
+            ));
+            assert_eq!(kept!(), others);
+            assert_eq!(primary!(), primary);
+        }
+
+        act!(keep(true));
+        assert!(kept!().is_empty());
+        act!(InputAction::CyclePrimaryPattern);
+        assert!(search_rx.try_recv().is_err());
+        assert_eq!(primary!(), "timeout");
+    }
+}
@@ -0,0 +1,187 @@
+//! Searching for any entry of a pattern set (`--patterns-from`, `:loadset`).
+
+use super::RenderLoopState;
+use crate::error::Result;
+use crate::input::SearchDirection;
+use crate::render::protocol::{RequestId, SearchCommand, SearchHighlightSpec};
+use crate::render::ui::ViewState;
+use crate::search::{PatternModifiers, PatternSet, SearchOptions};
+use crate::util::CancellationToken;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+impl RenderLoopState {
+    /// Search forward for any entry of `set` under the current search options
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_pattern_set(
+        &mut self,
+        set: &PatternSet,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+    ) -> Result<bool> {
+        view_state.status_line.set_message(format!(
+            "Searching {} patterns from {}",
+            set.entries().len(),
+            set.name()
+        ));
+        let options = SearchOptions {
+            pattern_set: true,
+            ..self.search_options.clone()
+        };
+        // Also run when the file opens, where a match on the first line must not be passed over
+        self.queue_search(
+            set.pattern(),
+            options,
+            PatternModifiers::default(),
+            SearchDirection::Forward,
+            false,
+            self.frequent_match_limit,
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_search_request,
+            search_cancel_flag,
+            pending_search_state,
+        )
+        .await
+    }
+
+    /// Load the set in `path` and search for it, reporting a file that cannot be used
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn load_pattern_set(
+        &mut self,
+        path: &Path,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_search_request: &mut Option<RequestId>,
+        search_cancel_flag: &mut Option<CancellationToken>,
+        pending_search_state: &mut Option<(RequestId, Arc<SearchHighlightSpec>)>,
+    ) -> Result<bool> {
+        match PatternSet::load(path).await {
+            Ok(set) => {
+                self.search_pattern_set(
+                    &set,
+                    view_state,
+                    search_tx,
+                    next_request_id,
+                    latest_search_request,
+                    search_cancel_flag,
+                    pending_search_state,
+                )
+                .await
+            }
+            Err(error) => {
+                let message = match std::error::Error::source(&error) {
+                    Some(source) => format!("{}: {}", error, source),
+                    None => error.to_string(),
+                };
+                view_state.status_line.set_message(message);
+                Ok(true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::protocol::{SearchResponse, ViewportRequest};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn loadset_searches_for_the_set_and_labels_it() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "req-17\nreq-42\n").unwrap();
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 10);
+        let (mut search_tx, mut search_rx) = mpsc::channel(8);
+        let mut next_request_id = 1;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        state
+            .load_pattern_set(
+                file.path(),
+                &mut view_state,
+                &mut search_tx,
+                &mut next_request_id,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+            )
+            .await
+            .unwrap();
+        let Ok(SearchCommand::ExecuteSearch {
+            request_id,
+            pattern,
+            options,
+            frequency_limit,
+            ..
+        }) = search_rx.try_recv()
+        else {
+            panic!("expected search command");
+        };
+        assert_eq!(pattern.as_ref(), "req-17\nreq-42");
+        assert!(options.pattern_set);
+        assert!(frequency_limit.is_some());
+
+        // Found: the status line names the set rather than showing every entry
+        let mut latest_view_request = None;
+        state
+            .handle_response(
+                SearchResponse::SearchCompleted {
+                    request_id,
+                    match_byte: Some(120),
+                    message: None,
+                    confirm: None,
+                    scan: None,
+                },
+                &mut view_state,
+                &mut latest_view_request,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+                &mut search_tx,
+                &mut next_request_id,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            view_state.status_line.active_pattern,
+            Some((SearchDirection::Forward, "set of 2 patterns".to_string()))
+        );
+        // The page is highlighted with the set
+        assert!(matches!(
+            search_rx.try_recv(),
+            Ok(SearchCommand::LoadViewport {
+                top: ViewportRequest::Absolute(120),
+                highlights: Some(spec),
+                ..
+            }) if spec.options.pattern_set
+        ));
+
+        // A missing file is reported, not searched for
+        state
+            .load_pattern_set(
+                Path::new("/nonexistent/ids.txt"),
+                &mut view_state,
+                &mut search_tx,
+                &mut next_request_id,
+                &mut latest_search_request,
+                &mut search_cancel_flag,
+                &mut pending_search_state,
+            )
+            .await
+            .unwrap();
+        assert!(search_rx.try_recv().is_err());
+        let message = view_state.status_line.message.clone().unwrap();
+        assert!(message.contains("Failed to read ids.txt"), "{message}");
+    }
+}
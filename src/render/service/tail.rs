@@ -0,0 +1,485 @@
+//! Following the end of a file or of piped input as it grows.
+
+use super::RenderLoopState;
+use crate::error::Result;
+use crate::file_handler::PipeProgress;
+use crate::input::InputAction;
+use crate::render::protocol::{RequestId, SearchCommand, ViewportRequest};
+use crate::render::ui::{TailMode, ViewState};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::watch;
+
+impl RenderLoopState {
+    /// Follow mode state, `None` while not following
+    pub fn tail_mode(&self) -> Option<TailMode> {
+        self.tail_mode
+    }
+
+    /// Turn follow mode on or off; turning it on starts out stuck to the end of the file
+    pub fn set_following(&mut self, enabled: bool, view_state: &mut ViewState) {
+        self.tail_mode = enabled.then_some(TailMode::Sticky);
+        view_state.status_line.tail = self.tail_mode;
+    }
+
+    /// `F`: start following the end of the file, or stop following it
+    pub(super) async fn toggle_following(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        if self.tail_mode.is_some() {
+            self.set_following(false, view_state);
+            return Ok(true);
+        }
+        self.set_following(true, view_state);
+        self.queue_viewport_update(
+            ViewportRequest::EndOfFile,
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await
+    }
+
+    /// Drop out of follow mode before `action` runs, as less does for any key but movement
+    ///
+    /// Returns whether leaving used the action up: an interrupt only ends following, rather than
+    /// going on to cancel something or quit.
+    pub(super) fn leave_following_for(
+        &mut self,
+        action: &InputAction,
+        view_state: &mut ViewState,
+    ) -> bool {
+        if self.tail_mode.is_none() {
+            return false;
+        }
+        match action {
+            InputAction::Scroll { .. }
+            | InputAction::PageUp
+            | InputAction::PageDown
+            | InputAction::HalfPageUp
+            | InputAction::HalfPageDown
+            | InputAction::GoToStart
+            | InputAction::GoToEnd
+            | InputAction::PreviousSection
+            | InputAction::NextSection
+            | InputAction::PreviousRecord
+            | InputAction::NextRecord
+            | InputAction::SubmitPercent { .. }
+            | InputAction::ToggleFollow
+            | InputAction::Resize { .. }
+            | InputAction::UpdateCountBuffer(_)
+            | InputAction::NoAction
+            | InputAction::InvalidInput
+            | InputAction::Quit => false,
+            InputAction::Interrupt => {
+                self.set_following(false, view_state);
+                true
+            }
+            _ => {
+                self.set_following(false, view_state);
+                false
+            }
+        }
+    }
+
+    /// Show piped input as `progress` reports more of it read
+    pub fn watch_input(&mut self, progress: watch::Receiver<PipeProgress>) {
+        self.input = Some(progress);
+    }
+
+    /// Wait until more piped input is readable; never returns once the input has ended
+    pub async fn input_grew(&mut self) {
+        let Some(input) = self.input.as_mut() else {
+            return std::future::pending().await;
+        };
+        if input.changed().await.is_err() || input.borrow().finished {
+            self.input = None;
+        }
+    }
+
+    /// Ring the terminal bell when data arriving while following matches the active search
+    pub fn set_match_bell(&mut self, enabled: bool) {
+        self.match_bell = enabled;
+    }
+
+    /// Whether the bell should ring with the next frame; clears the request
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell_pending)
+    }
+
+    /// Point out a match of the active search in newly arrived data without moving to it
+    pub(super) fn alert_new_match(&mut self, view_state: &mut ViewState) {
+        view_state
+            .status_line
+            .set_message("match in new data — press n".to_string());
+        self.bell_pending |= self.match_bell;
+    }
+
+    /// Point out once that the file is growing while not following it (on by default)
+    pub fn set_growth_hint(&mut self, enabled: bool) {
+        self.growth_hint = enabled;
+    }
+
+    /// Whether to check now and then for data appended to a file that is not being followed,
+    /// so the position and percentage reflect its current size
+    ///
+    /// Piped input reports its own growth.
+    pub fn watches_growth(&self) -> bool {
+        self.tail_mode.is_none() && self.input.is_none()
+    }
+
+    /// Note that the file grew while not following it: the first time, say how to follow it
+    pub(super) fn hint_growth(&mut self, view_state: &mut ViewState) {
+        if self.tail_mode.is_none() && std::mem::take(&mut self.growth_hint) {
+            view_state
+                .status_line
+                .set_message("file is growing — press F to follow".to_string());
+        }
+    }
+
+    /// Switch between sticky and paused while following; a no-op otherwise
+    pub(super) fn update_tail_mode(&mut self, mode: TailMode, view_state: &mut ViewState) {
+        if self.tail_mode.is_some() {
+            self.tail_mode = Some(mode);
+            view_state.status_line.tail = Some(mode);
+        }
+    }
+
+    /// React to data appended to the file: jump to the new end while sticky, and otherwise fill
+    /// a viewport that showed the end with the new lines
+    ///
+    /// Returns whether a viewport reload was requested.
+    pub async fn handle_file_growth(
+        &mut self,
+        view_state: &mut ViewState,
+        search_tx: &mut Sender<SearchCommand>,
+        next_request_id: &mut RequestId,
+        latest_view_request: &mut Option<RequestId>,
+    ) -> Result<bool> {
+        let target = if self.tail_mode == Some(TailMode::Sticky) {
+            ViewportRequest::EndOfFile
+        } else if view_state.at_eof {
+            ViewportRequest::Absolute(view_state.viewport_top_byte)
+        } else {
+            return Ok(false);
+        };
+        self.queue_viewport_update(
+            target,
+            view_state,
+            search_tx,
+            next_request_id,
+            latest_view_request,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputAction;
+    use crate::input::ScrollDirection;
+    use crate::render::protocol::{SearchHighlightSpec, SearchResponse};
+    use crate::search::{PatternModifiers, SearchOptions};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn appended_data_fills_the_end_and_sticky_tail_follows_it() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        // Deliver the viewport load that was just queued, landing at or away from the end
+        macro_rules! load {
+            (at_eof: $at_eof:expr) => {{
+                let Ok(SearchCommand::LoadViewport {
+                    request_id, top, ..
+                }) = search_rx.try_recv()
+                else {
+                    panic!("expected viewport load");
+                };
+                state
+                    .handle_response(
+                        SearchResponse::ViewportLoaded {
+                            request_id,
+                            top_byte: 0,
+                            lines: Vec::new(),
+                            highlights: Vec::new(),
+                            at_eof: $at_eof,
+                            noeol: false,
+                            highlights_truncated: false,
+                            file_size: 100,
+                            top_line: None,
+                            line_position: None,
+                            current_match_row: None,
+                        },
+                        &mut view_state,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap();
+                top
+            }};
+        }
+        macro_rules! grow {
+            () => {
+                state
+                    .handle_file_growth(
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        // Without follow mode appended data never moves the viewport
+        assert!(!grow!());
+        assert!(search_rx.try_recv().is_err());
+        // though a viewport showing the end reloads in place to show the new lines
+        view_state.at_eof = true;
+        view_state.viewport_top_byte = 40;
+        assert!(grow!());
+        assert_eq!(load!(at_eof: true), ViewportRequest::Absolute(40));
+
+        state.set_following(true, &mut view_state);
+        assert!(grow!());
+        assert_eq!(load!(at_eof: true), ViewportRequest::EndOfFile);
+        assert_eq!(view_state.status_line.tail, Some(TailMode::Sticky));
+
+        // Scrolling up pauses right away, before the viewport even loads
+        act!(InputAction::Scroll {
+            direction: ScrollDirection::Up,
+            lines: 1,
+        });
+        assert_eq!(state.tail_mode(), Some(TailMode::Paused));
+        load!(at_eof: false);
+        assert!(!grow!());
+        assert!(search_rx.try_recv().is_err());
+        assert!(view_state.format_status_line().contains("[paused]"));
+
+        // Scrolling back down to the end resumes sticking
+        act!(InputAction::Scroll {
+            direction: ScrollDirection::Down,
+            lines: 1,
+        });
+        load!(at_eof: true);
+        assert_eq!(state.tail_mode(), Some(TailMode::Sticky));
+
+        // So does G after paging away
+        act!(InputAction::PageUp);
+        load!(at_eof: false);
+        act!(InputAction::GoToEnd);
+        assert_eq!(state.tail_mode(), Some(TailMode::Sticky));
+        load!(at_eof: true);
+        assert!(grow!());
+        assert_eq!(load!(at_eof: true), ViewportRequest::EndOfFile);
+        assert!(view_state.format_status_line().contains("[following]"));
+    }
+
+    #[tokio::test]
+    async fn match_in_appended_data_alerts_without_moving_to_it() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        state.set_following(true, &mut view_state);
+        macro_rules! grew {
+            ($new_match:expr) => {
+                state
+                    .handle_response(
+                        SearchResponse::FileGrew {
+                            file_size: 200,
+                            new_match: $new_match,
+                        },
+                        &mut view_state,
+                        &mut None,
+                        &mut None,
+                        &mut None,
+                        &mut None,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        // No active search, no alert
+        grew!(Some(150));
+        assert!(view_state.status_line.message.is_none() && !state.take_bell());
+        let spec = SearchHighlightSpec {
+            pattern: Arc::from("error"),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        };
+        state.set_search(Arc::new(spec), &mut view_state);
+        grew!(None);
+        assert!(view_state.status_line.message.is_none() && !state.take_bell());
+
+        grew!(Some(150));
+        let message = view_state.status_line.message.as_deref();
+        assert_eq!(message, Some("match in new data — press n"));
+        assert!(state.take_bell() && !state.take_bell());
+        // The viewport keeps following the end rather than jumping to the match
+        while let Ok(command) = search_rx.try_recv() {
+            if let SearchCommand::LoadViewport { top, .. } = command {
+                assert_eq!(top, ViewportRequest::EndOfFile);
+            }
+        }
+        state.set_match_bell(false);
+        grew!(Some(180));
+        assert!(!state.take_bell());
+    }
+
+    #[tokio::test]
+    async fn growth_while_not_following_is_pointed_out_once() {
+        let (mut search_tx, _search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.file_size = Some(100);
+        view_state.viewport_top_byte = 50;
+        let mut next_request_id = 1;
+        macro_rules! grew {
+            ($file_size:expr) => {
+                state
+                    .handle_response(
+                        SearchResponse::FileGrew {
+                            file_size: $file_size,
+                            new_match: None,
+                        },
+                        &mut view_state,
+                        &mut None,
+                        &mut None,
+                        &mut None,
+                        &mut None,
+                        &mut search_tx,
+                        &mut next_request_id,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+        assert!(state.watches_growth());
+
+        grew!(200);
+        assert_eq!(
+            view_state.status_line.message.as_deref(),
+            Some("file is growing — press F to follow")
+        );
+        // The percentage goes by the new size
+        assert!(view_state.format_status_line().contains("25%"));
+
+        view_state.status_line.clear_message();
+        grew!(400);
+        assert!(view_state.status_line.message.is_none());
+        assert!(view_state.format_status_line().contains("12%"));
+
+        // Following checks on its own schedule and says nothing
+        state = RenderLoopState::new(SearchOptions::default());
+        state.set_following(true, &mut view_state);
+        assert!(!state.watches_growth());
+        grew!(500);
+        assert!(view_state.status_line.message.is_none());
+
+        // Nor when turned off
+        state = RenderLoopState::new(SearchOptions::default());
+        state.set_growth_hint(false);
+        grew!(600);
+        assert!(view_state.status_line.message.is_none());
+    }
+
+    #[tokio::test]
+    async fn follow_key_jumps_to_the_end_and_other_keys_leave() {
+        let (mut search_tx, mut search_rx) = tokio::sync::mpsc::channel(8);
+        let mut state = RenderLoopState::new(SearchOptions::default());
+        let mut view_state = ViewState::new("/test", 80, 24);
+        let mut next_request_id = 1;
+        let mut latest_view_request = None;
+        let mut latest_search_request = None;
+        let mut search_cancel_flag = None;
+        let mut pending_search_state = None;
+
+        macro_rules! act {
+            ($action:expr) => {
+                state
+                    .process_action(
+                        $action,
+                        &mut view_state,
+                        &mut search_tx,
+                        &mut next_request_id,
+                        &mut latest_view_request,
+                        &mut latest_search_request,
+                        &mut search_cancel_flag,
+                        &mut pending_search_state,
+                    )
+                    .await
+                    .unwrap()
+            };
+        }
+
+        assert!(act!(InputAction::ToggleFollow));
+        assert_eq!(state.tail_mode(), Some(TailMode::Sticky));
+        let Ok(SearchCommand::LoadViewport { top, .. }) = search_rx.try_recv() else {
+            panic!("expected viewport load");
+        };
+        assert_eq!(top, ViewportRequest::EndOfFile);
+        assert!(view_state
+            .format_status_line()
+            .ends_with("Waiting for data... (interrupt to abort)"));
+
+        // Movement keeps following; anything else drops out and then does its job
+        act!(InputAction::GoToEnd);
+        assert!(state.tail_mode().is_some());
+        act!(InputAction::StartSearch(
+            crate::input::SearchDirection::Forward
+        ));
+        assert_eq!(state.tail_mode(), None);
+        assert!(view_state.status_line.search_prompt.is_some());
+        assert!(!view_state.format_status_line().contains("Waiting"));
+
+        // An interrupt only ends following instead of quitting
+        act!(InputAction::ToggleFollow);
+        assert!(act!(InputAction::Interrupt));
+        assert_eq!(state.tail_mode(), None);
+        assert!(!act!(InputAction::Interrupt));
+
+        // F again stops following
+        act!(InputAction::ToggleFollow);
+        act!(InputAction::ToggleFollow);
+        assert_eq!(state.tail_mode(), None);
+    }
+}
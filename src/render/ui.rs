@@ -3,13 +3,22 @@
 //! This module hosts the concrete terminal UI implementation along with the supporting view/state
 //! structures and styling utilities.
 
+pub mod codepoints;
+pub mod highlight;
+pub mod line_cache;
 pub mod renderer;
+pub mod snapshot;
 pub mod state;
 pub mod terminal;
 pub mod theme;
 
+pub use highlight::HighlightedLine;
+pub use line_cache::LineCache;
 pub use renderer::UIRenderer;
-pub use state::{DisplayMode, StatusLine, ViewState};
+pub use state::{
+    ChromeMode, DisplayMode, InvalidKeyFeedback, JumpTarget, LinePeek, MatchList, PositionStyle,
+    PromptOptions, ScreenRow, StatusLine, TailMode, ViewState,
+};
 pub use terminal::TerminalUI;
 pub use theme::ColorTheme;
 
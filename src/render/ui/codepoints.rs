@@ -0,0 +1,208 @@
+//! Stand-ins for codepoints that terminals disagree about.
+//!
+//! Private-use characters, noncharacters and codepoints from planes with nothing assigned are
+//! drawn one column wide by some terminals, two by others, and not at all by a few. Either way
+//! the row no longer lines up with the widths the layout computed, and highlights drift off their
+//! matches. Such characters are replaced with a placeholder before a line is laid out, and its
+//! highlight spans are moved to the replaced text.
+//!
+//! Unassigned codepoints inside otherwise assigned planes are left alone: telling them apart
+//! needs the Unicode character database, and terminals mostly draw them as one column anyway.
+//!
+//! Newlines inside a NUL-separated record (`--null`) get the same treatment with
+//! `NEWLINE_SYMBOL`, since a terminal would move the cursor instead of drawing them.
+
+use crate::render::protocol::HighlightSpan;
+use unicode_width::UnicodeWidthChar;
+
+/// Placeholder used unless `--placeholder` picks another
+pub const DEFAULT_PLACEHOLDER: char = '\u{FFFD}';
+
+/// Drawn in place of a newline inside a record (SYMBOL FOR NEWLINE)
+pub const NEWLINE_SYMBOL: char = '\u{2424}';
+
+/// Whether terminals are likely to draw `ch` at a width other than the one the layout assumes
+pub fn is_unreliable(ch: char) -> bool {
+    let code = u32::from(ch);
+    matches!(
+        code,
+        // Private use: the BMP area and supplementary planes 15 and 16
+        0xE000..=0xF8FF | 0xF_0000..=0x10_FFFF
+        // Noncharacters
+        | 0xFDD0..=0xFDEF
+        // Planes 4 to 13 have no assigned characters
+        | 0x4_0000..=0xD_FFFF
+    ) || code & 0xFFFE == 0xFFFE
+}
+
+/// Check a `--placeholder` value: one character that takes a single column
+pub fn parse_placeholder(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) if ch.width() == Some(1) && !is_unreliable(ch) => Ok(ch),
+        _ => Err(format!(
+            "expected a single character one column wide, got {:?}",
+            value
+        )),
+    }
+}
+
+/// Replace the unreliable characters of `text` with `placeholder`, moving `highlights` along
+///
+/// Lines without any are returned untouched. A span edge inside a replaced character moves to
+/// the start of its placeholder.
+pub fn substitute(
+    text: String,
+    highlights: Vec<HighlightSpan>,
+    placeholder: char,
+) -> (String, Vec<HighlightSpan>) {
+    if text.is_ascii() || !text.chars().any(is_unreliable) {
+        return (text, highlights);
+    }
+    replace_chars(text, highlights, is_unreliable, placeholder)
+}
+
+/// Draw the newlines of `text` as `NEWLINE_SYMBOL`, moving `highlights` along
+pub fn show_newlines(text: String, highlights: Vec<HighlightSpan>) -> (String, Vec<HighlightSpan>) {
+    if !text.contains('\n') {
+        return (text, highlights);
+    }
+    replace_chars(text, highlights, |ch| ch == '\n', NEWLINE_SYMBOL)
+}
+
+/// Replace each character of `text` picked by `pick` with `replacement`, moving `highlights`
+fn replace_chars(
+    text: String,
+    highlights: Vec<HighlightSpan>,
+    pick: impl Fn(char) -> bool,
+    replacement: char,
+) -> (String, Vec<HighlightSpan>) {
+    // (original start, original length, replaced start) of each replaced character
+    let mut replaced = Vec::new();
+    let mut out = String::with_capacity(text.len());
+    for (start, ch) in text.char_indices() {
+        if pick(ch) {
+            replaced.push((start, ch.len_utf8(), out.len()));
+            out.push(replacement);
+        } else {
+            out.push(ch);
+        }
+    }
+
+    let width = replacement.len_utf8();
+    let moved = |offset: usize| -> usize {
+        let mut shift = 0isize;
+        for &(start, len, new_start) in &replaced {
+            if offset < start {
+                break;
+            }
+            if offset < start + len {
+                return new_start;
+            }
+            shift += width as isize - len as isize;
+        }
+        (offset as isize + shift) as usize
+    };
+    let highlights = highlights
+        .into_iter()
+        .map(|(start, end, kind)| (moved(start), moved(end), kind))
+        .collect();
+    (out, highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::protocol::HighlightKind::Search;
+    use crate::render::ui::highlight::HighlightedLine;
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn private_use_and_unassigned_codepoints_are_replaced_and_highlights_follow() {
+        // U+E0A0 (a powerline glyph) is 3 bytes, U+F0001 and U+50000 are 4 bytes each
+        let line = "a\u{E0A0}b err \u{F0001}\u{50000} err";
+        let first = line.find("err").unwrap();
+        let second = line.rfind("err").unwrap();
+        let (text, spans) = substitute(
+            line.to_string(),
+            vec![(first, first + 3, Search), (second, second + 3, Search)],
+            '·',
+        );
+        assert_eq!(text, "a·b err ·· err");
+        assert_eq!(spans, vec![(5, 8, Search), (14, 17, Search)]);
+        let laid_out = HighlightedLine::new(&text, &spans);
+        let matched: Vec<&str> = laid_out
+            .segments()
+            .into_iter()
+            .filter_map(|(text, kind)| kind.map(|_| text))
+            .collect();
+        assert_eq!(matched, vec!["err", "err"]);
+        // Every character is one column, so the second match starts at column 11
+        assert_eq!(text.width(), 14);
+        assert_eq!(text[..spans[1].0].width(), 11);
+
+        // A span covering a replaced character covers its placeholder
+        let (text, spans) = substitute(
+            "x\u{E000}y".to_string(),
+            vec![(1, 4, Search), (2, 5, Search)],
+            DEFAULT_PLACEHOLDER,
+        );
+        assert_eq!(text, "x\u{FFFD}y");
+        assert_eq!(spans, vec![(1, 4, Search), (1, 5, Search)]);
+    }
+
+    #[test]
+    fn ordinary_text_and_emoji_are_kept() {
+        for line in ["plain", "naïve 日本語 🎉", "tag\u{FE0F}"] {
+            let highlights = vec![(0, 2, Search)];
+            assert_eq!(
+                substitute(line.to_string(), highlights.clone(), '·'),
+                (line.to_string(), highlights)
+            );
+        }
+        assert!(is_unreliable('\u{FFFF}'));
+        assert!(is_unreliable('\u{1FFFE}'));
+        assert!(!is_unreliable('\u{FFFD}'));
+    }
+
+    #[test]
+    fn placeholder_must_be_one_narrow_character() {
+        assert_eq!(parse_placeholder("·"), Ok('·'));
+        assert_eq!(parse_placeholder("?"), Ok('?'));
+        for bad in ["", "ab", "日", "\u{E000}", "\t"] {
+            assert!(parse_placeholder(bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn viewport_lines_are_stored_as_drawn() {
+        use crate::render::ui::ViewState;
+
+        let line = "\u{E0B0} main \u{E0A0} fix: timeout".to_string();
+        let at = line.find("timeout").unwrap();
+        let mut view_state = ViewState::new("/test", 80, 5);
+        view_state.update_viewport_content(
+            vec![line.clone(), "plain".to_string()],
+            vec![vec![(at, at + 7, Search)]],
+        );
+        let lines = view_state.highlighted_lines();
+        assert_eq!(lines[0].text, "\u{FFFD} main \u{FFFD} fix: timeout");
+        assert_eq!(&lines[0].text[lines[0].spans[0].0.clone()], "timeout");
+        assert!(lines[1].spans.is_empty());
+
+        // With the substitution off, the text reaches the terminal unchanged
+        view_state.placeholder = None;
+        view_state.update_viewport_content(vec![line.clone()], vec![vec![(at, at + 7, Search)]]);
+        assert_eq!(view_state.highlighted_lines()[0].text, line);
+    }
+
+    #[test]
+    fn newlines_inside_a_record_are_drawn_as_a_symbol() {
+        let record = "panic: boom\n  at main.rs:3".to_string();
+        let at = record.find("main").unwrap();
+        let (text, spans) = show_newlines(record, vec![(at, at + 4, Search)]);
+        assert_eq!(text, "panic: boom\u{2424}  at main.rs:3");
+        assert_eq!(&text[spans[0].0..spans[0].1], "main");
+        assert_eq!(text.width(), 26);
+    }
+}
@@ -0,0 +1,174 @@
+//! Renderer-independent highlighted lines.
+//!
+//! `HighlightedLine` is the one place where a line's highlight spans are laid out for display:
+//! spans are clipped to the text and to character boundaries, and where spans overlap the kind
+//! with the higher precedence wins (see `HighlightKind`). The terminal renderer only maps the
+//! result to styles, and library users can draw the viewport themselves from
+//! `ViewState::highlighted_lines`.
+
+use crate::render::protocol::{HighlightKind, HighlightSpan};
+use std::ops::Range;
+
+/// Number of `HighlightKind` variants, indexed by discriminant
+const KIND_COUNT: usize = 4;
+
+/// One line of text with sorted, non-overlapping highlight spans.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightedLine<'a> {
+    pub text: &'a str,
+    /// Byte ranges into `text`; text outside every range is drawn plain
+    pub spans: Vec<(Range<usize>, HighlightKind)>,
+}
+
+impl<'a> HighlightedLine<'a> {
+    /// Lay out `highlights` over `text`
+    ///
+    /// The spans may be unsorted, overlap or reach past the end of the text.
+    pub fn new(text: &'a str, highlights: &[HighlightSpan]) -> Self {
+        // Sweep over span boundaries, counting the spans of each kind that cover the position
+        let mut events: Vec<(usize, HighlightKind, bool)> =
+            Vec::with_capacity(highlights.len() * 2);
+        for &(start, end, kind) in highlights {
+            let start = char_boundary(text, start);
+            let end = char_boundary(text, end);
+            if start < end {
+                events.push((start, kind, true));
+                events.push((end, kind, false));
+            }
+        }
+        events.sort_unstable_by_key(|&(position, ..)| position);
+
+        let mut spans: Vec<(Range<usize>, HighlightKind)> = Vec::new();
+        let mut active = [0usize; KIND_COUNT];
+        let mut cursor = 0;
+        for (position, kind, opens) in events {
+            if position > cursor {
+                if let Some(top) = top_kind(&active) {
+                    match spans.last_mut() {
+                        Some((range, last)) if range.end == cursor && *last == top => {
+                            range.end = position
+                        }
+                        _ => spans.push((cursor..position, top)),
+                    }
+                }
+                cursor = position;
+            }
+            if opens {
+                active[kind as usize] += 1;
+            } else {
+                active[kind as usize] -= 1;
+            }
+        }
+
+        Self { text, spans }
+    }
+
+    /// Consecutive pieces of the text, each with its highlight kind or `None` for plain text
+    pub fn segments(&self) -> Vec<(&'a str, Option<HighlightKind>)> {
+        let mut segments = Vec::with_capacity(self.spans.len() * 2 + 1);
+        let mut last_end = 0;
+        for (range, kind) in &self.spans {
+            if range.start > last_end {
+                segments.push((&self.text[last_end..range.start], None));
+            }
+            segments.push((&self.text[range.clone()], Some(*kind)));
+            last_end = range.end;
+        }
+        if last_end < self.text.len() || segments.is_empty() {
+            segments.push((&self.text[last_end..], None));
+        }
+        segments
+    }
+
+    /// The part of the line in `range`, with spans clipped and shifted to match
+    pub fn slice(&self, range: Range<usize>) -> HighlightedLine<'a> {
+        let spans = self
+            .spans
+            .iter()
+            .filter_map(|(span, kind)| {
+                let (start, end) = (span.start.max(range.start), span.end.min(range.end));
+                (start < end).then(|| (start - range.start..end - range.start, *kind))
+            })
+            .collect();
+        HighlightedLine {
+            text: &self.text[range],
+            spans,
+        }
+    }
+}
+
+/// Highest-precedence kind with at least one covering span
+fn top_kind(active: &[usize; KIND_COUNT]) -> Option<HighlightKind> {
+    [
+        HighlightKind::Search,
+        HighlightKind::Kept1,
+        HighlightKind::Kept2,
+        HighlightKind::Filter,
+    ]
+    .into_iter()
+    .find(|&kind| active[kind as usize] > 0)
+}
+
+/// `position` clamped to the text and moved back to the start of the character it falls in
+fn char_boundary(text: &str, position: usize) -> usize {
+    let mut position = position.min(text.len());
+    while !text.is_char_boundary(position) {
+        position -= 1;
+    }
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use HighlightKind::{Filter, Search};
+
+    #[test]
+    fn test_search_wins_over_filter_where_they_overlap() {
+        // Filtered by "error.*full" and searched for "disk", spans in any order
+        let line = HighlightedLine::new("error: disk full", &[(7, 11, Search), (0, 16, Filter)]);
+        assert_eq!(
+            line.spans,
+            vec![(0..7, Filter), (7..11, Search), (11..16, Filter)]
+        );
+        assert_eq!(
+            line.segments(),
+            vec![
+                ("error: ", Some(Filter)),
+                ("disk", Some(Search)),
+                (" full", Some(Filter)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans_are_clipped_to_the_text_and_merged() {
+        // "é" is two bytes; a span ending inside it stops before it
+        let line = HighlightedLine::new(
+            "abé cd",
+            &[
+                (0, 1, Search),
+                (1, 3, Search),
+                (4, 5, Filter),
+                (5, 99, Filter),
+            ],
+        );
+        assert_eq!(line.spans, vec![(0..2, Search), (4..7, Filter)]);
+        assert_eq!(
+            line.segments(),
+            vec![("ab", Some(Search)), ("é", None), (" cd", Some(Filter))]
+        );
+
+        let plain = HighlightedLine::new("", &[(0, 4, Search)]);
+        assert!(plain.spans.is_empty());
+        assert_eq!(plain.segments(), vec![("", None)]);
+    }
+
+    #[test]
+    fn test_slice_shifts_spans_into_the_row() {
+        let line = HighlightedLine::new("0123456789ab", &[(8, 12, Search)]);
+        assert_eq!(line.slice(0..10).spans, vec![(8..10, Search)]);
+        assert_eq!(line.slice(10..12).spans, vec![(0..2, Search)]);
+        assert_eq!(line.slice(2..6).spans, vec![]);
+    }
+}
@@ -0,0 +1,122 @@
+//! Styled viewport lines kept between frames.
+//!
+//! Most frames are drawn for the status line (progress, messages, a prompt being typed) while the
+//! viewport still shows the same page. On a large terminal, cutting every row to the screen width
+//! and styling its highlights is the bulk of such a frame, so the styled lines are built once per
+//! page and only borrowed by the frames after it.
+
+use crate::render::ui::state::ViewState;
+use crate::render::ui::terminal::TerminalUI;
+use crate::render::ui::theme::ColorTheme;
+use ratatui::text::{Line, Span};
+
+/// Styled lines of the last page drawn, with what they were built from
+#[derive(Debug, Default)]
+pub struct LineCache {
+    key: Option<LineCacheKey>,
+    lines: Vec<Line<'static>>,
+    builds: u64,
+}
+
+#[derive(Debug)]
+struct LineCacheKey {
+    content: u64,
+    width: u16,
+    theme: ColorTheme,
+}
+
+impl LineCache {
+    /// Styled lines for `view_state` under `theme`
+    ///
+    /// Rebuilt only when the page content, the viewport width or the theme changed since the
+    /// previous call; see `ViewState::content_generation`.
+    pub fn lines(&mut self, view_state: &ViewState, theme: &ColorTheme) -> Vec<Line<'_>> {
+        let fresh = self.key.as_ref().is_some_and(|key| {
+            key.content == view_state.content_generation()
+                && key.width == view_state.viewport_width
+                && key.theme == *theme
+        });
+        if !fresh {
+            self.lines = TerminalUI::content_lines(view_state, theme)
+                .into_iter()
+                .map(owned_line)
+                .collect();
+            self.key = Some(LineCacheKey {
+                content: view_state.content_generation(),
+                width: view_state.viewport_width,
+                theme: theme.clone(),
+            });
+            self.builds += 1;
+        }
+        self.lines.iter().map(borrowed_line).collect()
+    }
+
+    /// How many times the lines were built rather than reused
+    pub fn builds(&self) -> u64 {
+        self.builds
+    }
+}
+
+fn owned_line(line: Line<'_>) -> Line<'static> {
+    Line {
+        spans: line
+            .spans
+            .into_iter()
+            .map(|span| Span::styled(span.content.into_owned(), span.style))
+            .collect(),
+        style: line.style,
+        alignment: line.alignment,
+    }
+}
+
+fn borrowed_line<'a>(line: &'a Line<'static>) -> Line<'a> {
+    Line {
+        spans: line
+            .spans
+            .iter()
+            .map(|span| Span::styled(span.content.as_ref(), span.style))
+            .collect(),
+        style: line.style,
+        alignment: line.alignment,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::protocol::HighlightKind;
+
+    #[test]
+    fn lines_are_rebuilt_only_when_their_inputs_change() {
+        let theme = ColorTheme::default();
+        let mut cache = LineCache::default();
+        let mut view_state = ViewState::new("/test", 20, 5);
+        view_state.update_viewport_content(
+            vec!["error: disk full".to_string(), "ok".to_string()],
+            vec![vec![(7, 11, HighlightKind::Search)], Vec::new()],
+        );
+
+        let first = cache.lines(&view_state, &theme);
+        assert_eq!(first, TerminalUI::content_lines(&view_state, &theme));
+        // Status-only changes reuse the lines
+        view_state.status_line.set_message("Searching".to_string());
+        cache.lines(&view_state, &theme);
+        assert_eq!(cache.builds(), 1);
+
+        view_state.clear_highlights();
+        let cleared = cache.lines(&view_state, &theme);
+        assert_eq!(cleared[0].spans.len(), 1);
+        view_state.set_noeol(true);
+        assert_eq!(cache.lines(&view_state, &theme)[1].spans.len(), 2);
+        assert_eq!(cache.builds(), 3);
+
+        // The rows are cut at the screen width
+        view_state.viewport_width = 5;
+        assert_eq!(
+            cache.lines(&view_state, &theme)[0].spans[0].content,
+            "error"
+        );
+        cache.lines(&view_state, &ColorTheme::monochrome());
+        assert_eq!(cache.builds(), 5);
+    }
+}
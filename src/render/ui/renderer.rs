@@ -36,6 +36,11 @@ pub trait UIRenderer {
 
     /// Get current terminal dimensions
     fn get_terminal_size(&self) -> Result<(u16, u16)>; // (width, height)
+
+    /// Ring the terminal bell
+    fn bell(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
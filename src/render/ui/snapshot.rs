@@ -0,0 +1,209 @@
+//! Viewport snapshots for bug reports (`:snapshot FILE`).
+//!
+//! A snapshot is built from the `ViewState` with the same styled lines the terminal UI draws, not
+//! scraped from the screen: the content rows (gutter, highlights and `[noeol]` marker included)
+//! followed by the status line when one is shown. As plain text every row is cut to the screen
+//! width like on screen; as ANSI text each styled span is wrapped in SGR sequences, so `cat`
+//! shows the highlights.
+//!
+//! A plain snapshot starts with `#` comment lines naming the file and the active filter and
+//! search, so one taken from one of several sessions on the same file says which it was. An ANSI
+//! snapshot is only the screen, to be replayed with `cat`.
+
+use crate::render::ui::state::{sanitize_status_text, ViewState};
+use crate::render::ui::terminal::TerminalUI;
+use crate::render::ui::theme::ColorTheme;
+use ratatui::crossterm::style::{
+    Attribute, Color as TermColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use ratatui::crossterm::Command;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+
+/// Modifiers a snapshot keeps, with the SGR attribute for each
+const ATTRIBUTES: [(Modifier, Attribute); 5] = [
+    (Modifier::BOLD, Attribute::Bold),
+    (Modifier::DIM, Attribute::Dim),
+    (Modifier::ITALIC, Attribute::Italic),
+    (Modifier::UNDERLINED, Attribute::Underlined),
+    (Modifier::REVERSED, Attribute::Reverse),
+];
+
+/// How a snapshot is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// The characters on screen
+    Plain,
+    /// The characters with their styling as ANSI escape sequences
+    Ansi,
+}
+
+impl SnapshotFormat {
+    /// ANSI for files ending in `.ans`, plain text otherwise
+    pub fn for_path(path: &std::path::Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("ans") => SnapshotFormat::Ansi,
+            _ => SnapshotFormat::Plain,
+        }
+    }
+}
+
+/// The rows of `view_state` as drawn with `theme`, one per line
+pub fn snapshot(view_state: &ViewState, theme: &ColorTheme, format: SnapshotFormat) -> String {
+    let width = usize::from(view_state.viewport_width);
+    let mut rows: Vec<Line> = TerminalUI::content_lines(view_state, theme);
+    if view_state.shows_status_line() {
+        let status_style = Style::default().bg(theme.status_bg).fg(theme.status_fg);
+        rows.push(Line::styled(view_state.format_status_line(), status_style));
+    }
+
+    let mut text = String::new();
+    if format == SnapshotFormat::Plain {
+        write_metadata(&mut text, view_state);
+    }
+    for row in &rows {
+        let mut room = width;
+        for span in &row.spans {
+            // The text was cut to width already; only the gutter and markers can still overflow
+            let content: String = span.content.chars().take(room).collect();
+            room -= content.chars().count();
+            if content.is_empty() {
+                continue;
+            }
+            match format {
+                SnapshotFormat::Plain => text.push_str(&content),
+                SnapshotFormat::Ansi => {
+                    write_styled(&mut text, &content, row.style.patch(span.style))
+                }
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Append the comment lines naming the file, filter and search of `view_state`
+fn write_metadata(text: &mut String, view_state: &ViewState) {
+    let path = view_state.file_path.display().to_string();
+    text.push_str(&format!(
+        "# rlless snapshot of {}\n",
+        sanitize_status_text(&path)
+    ));
+    let (filter, search) = view_state.active_filter_and_search();
+    if let Some(filter) = filter {
+        text.push_str(&format!("# filter: {}\n", filter));
+    }
+    if let Some(search) = search {
+        text.push_str(&format!("# search: {}\n", search));
+    }
+}
+
+/// Append `content` wrapped in the SGR sequences for `style`, or as is for the default style
+fn write_styled(text: &mut String, content: &str, style: Style) {
+    if style == Style::default() {
+        text.push_str(content);
+        return;
+    }
+    // Writing into a String cannot fail
+    if let Some(fg) = style.fg {
+        let _ = SetForegroundColor(TermColor::from(fg)).write_ansi(text);
+    }
+    if let Some(bg) = style.bg {
+        let _ = SetBackgroundColor(TermColor::from(bg)).write_ansi(text);
+    }
+    for (modifier, attribute) in ATTRIBUTES {
+        if style.add_modifier.contains(modifier) {
+            let _ = SetAttribute(attribute).write_ansi(text);
+        }
+    }
+    text.push_str(content);
+    text.push_str("\x1b[0m");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::protocol::HighlightKind;
+    use std::path::Path;
+
+    fn fixture() -> ViewState {
+        let mut view_state = ViewState::new("/test/app.log", 24, 4);
+        view_state.file_size = Some(100);
+        view_state.update_viewport_content(
+            vec![
+                "INFO start".to_string(),
+                "ERROR disk full on /dev/sda1".to_string(),
+                "INFO done".to_string(),
+            ],
+            vec![Vec::new(), vec![(6, 10, HighlightKind::Search)], Vec::new()],
+        );
+        view_state.set_noeol(true);
+        view_state
+    }
+
+    #[test]
+    fn plain_snapshot_is_the_screen_as_text() {
+        let view_state = fixture();
+        let text = snapshot(&view_state, &ColorTheme::default(), SnapshotFormat::Plain);
+        assert_eq!(
+            text,
+            "# rlless snapshot of /test/app.log\n\
+             INFO start\n\
+             ERROR disk full on /dev/\n\
+             INFO done [noeol]\n\
+             app.log | 0%\n"
+        );
+    }
+
+    #[test]
+    fn plain_snapshot_names_the_active_filter_and_search() {
+        let mut view_state = fixture();
+        view_state.status_line.filter = Some("ERROR".to_string());
+        view_state
+            .status_line
+            .set_active_pattern(crate::input::SearchDirection::Forward, "disk");
+        let text = snapshot(&view_state, &ColorTheme::default(), SnapshotFormat::Plain);
+        assert!(text.starts_with(
+            "# rlless snapshot of /test/app.log\n\
+             # filter: &ERROR\n\
+             # search: /disk\n\
+             INFO start\n"
+        ));
+
+        // ANSI snapshots hold the screen alone
+        let text = snapshot(&view_state, &ColorTheme::default(), SnapshotFormat::Ansi);
+        assert!(text.starts_with("INFO start\n"));
+    }
+
+    #[test]
+    fn ansi_snapshot_wraps_matches_in_sgr_sequences() {
+        let theme = ColorTheme::default();
+        let view_state = fixture();
+        let text = snapshot(&view_state, &theme, SnapshotFormat::Ansi);
+        let rows: Vec<&str> = text.lines().collect();
+
+        assert_eq!(rows[0], "INFO start");
+        // Black on yellow around "disk", plain text around it
+        assert_eq!(
+            rows[1],
+            "ERROR \x1b[38;5;0m\x1b[48;5;3mdisk\x1b[0m full on /dev/"
+        );
+        assert!(rows[3].starts_with("\x1b[38;5;15m\x1b[48;5;4mapp.log"));
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        assert_eq!(
+            SnapshotFormat::for_path(Path::new("/tmp/view.ans")),
+            SnapshotFormat::Ansi
+        );
+        assert_eq!(
+            SnapshotFormat::for_path(Path::new("/tmp/view.txt")),
+            SnapshotFormat::Plain
+        );
+        assert_eq!(
+            SnapshotFormat::for_path(Path::new("view")),
+            SnapshotFormat::Plain
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Line-number gutter (`-N`).
+//!
+//! The worker reports the line number of the page's top row with every page; the rows below it
+//! are numbered consecutively. Until the number is known (a huge file is still being indexed)
+//! the gutter shows `?`, and the page is loaded again once indexing finishes. The gutter takes
+//! columns from the text, never rows, so paging is the same with and without it.
+//!
+//! While the file has bookmarks the gutter also has a marker column, with `•` on lines that
+//! start at a bookmark. Where a line starts is worked out from the page: each row of an unfiltered
+//! page starts one terminator past the end of the row above, unless that row may have been cut
+//! from a longer line. Rows whose start is not known this way are never marked.
+
+use super::ViewState;
+use crate::file_handler::SEGMENT_BYTES;
+
+/// Digits the gutter always has room for, so it keeps its width while scrolling small files
+const MIN_DIGITS: u16 = 4;
+
+/// Columns of the bookmark marker and the space after it
+const MARK_WIDTH: u16 = 2;
+
+impl ViewState {
+    /// Show or hide the gutter
+    /// Returns true if the setting actually changed
+    pub fn set_line_numbers(&mut self, line_numbers: bool) -> bool {
+        let changed = self.line_numbers != line_numbers;
+        if changed {
+            self.line_numbers = line_numbers;
+            self.top_line = None;
+            self.content_generation += 1;
+        }
+        changed
+    }
+
+    /// Record the line number of the top row, as reported with the page
+    pub fn set_top_line(&mut self, top_line: Option<u64>) {
+        if self.top_line != top_line {
+            self.top_line = top_line;
+            self.content_generation += 1;
+        }
+    }
+
+    /// Whether the gutter is showing but the page came without a line number
+    pub fn line_numbers_pending(&self) -> bool {
+        self.line_numbers && self.top_line.is_none()
+    }
+
+    /// Mark lines starting at one of `offsets` in the gutter
+    pub fn set_bookmarks(&mut self, offsets: Vec<u64>) {
+        if self.bookmarks != offsets {
+            self.bookmarks = offsets;
+            self.content_generation += 1;
+        }
+    }
+
+    /// Work out where the page's rows start, for bookmark markers
+    pub(super) fn note_row_starts(&mut self, lines: &[String]) {
+        let mut next = Some(self.viewport_top_byte);
+        let filtered = self.status_line.filter.is_some();
+        self.row_starts = lines
+            .iter()
+            .map(|line| {
+                let start = next;
+                // A segment cut can end up to three bytes short to keep a character whole
+                let whole = !filtered && line.len() + 3 < SEGMENT_BYTES;
+                next = next
+                    .filter(|_| whole)
+                    .map(|start| start + line.len() as u64 + 1);
+                start
+            })
+            .collect();
+    }
+
+    /// Whether viewport line `index` starts at a bookmark
+    pub fn is_bookmarked(&self, index: usize) -> bool {
+        self.row_starts
+            .get(index)
+            .copied()
+            .flatten()
+            .is_some_and(|start| self.bookmarks.binary_search(&start).is_ok())
+    }
+
+    /// Columns taken by the gutter: the bookmark marker while there are bookmarks, then the
+    /// widest number on the page and a space; 0 when hidden
+    ///
+    /// At most half the viewport, so the text keeps some room on a narrow terminal.
+    pub fn gutter_width(&self) -> u16 {
+        (self.mark_width() + self.number_width()).min(self.viewport_width / 2)
+    }
+
+    fn mark_width(&self) -> u16 {
+        if self.bookmarks.is_empty() {
+            0
+        } else {
+            MARK_WIDTH
+        }
+    }
+
+    fn number_width(&self) -> u16 {
+        if !self.line_numbers {
+            return 0;
+        }
+        let last = self.top_line.map_or(0, |top| {
+            top + self.visible_lines.len().saturating_sub(1) as u64
+        });
+        let digits = (last.checked_ilog10().unwrap_or(0) + 1) as u16;
+        digits.max(MIN_DIGITS) + 1
+    }
+
+    /// Columns left for the text of each row
+    pub fn text_width(&self) -> u16 {
+        self.viewport_width - self.gutter_width()
+    }
+
+    /// Gutter text for viewport row `index`: its bookmark marker, then its line number, or `?`
+    /// while unknown
+    ///
+    /// Right-aligned and padded to `gutter_width`.
+    pub fn gutter_label(&self, index: usize) -> String {
+        let width = usize::from(self.gutter_width());
+        let marks = usize::from(self.mark_width()).min(width);
+        let mark = match marks {
+            0 => "",
+            _ if self.is_bookmarked(index) => "• ",
+            _ => "  ",
+        };
+        let width = width - marks;
+        if width == 0 || !self.line_numbers {
+            return mark.to_string();
+        }
+        let label = match self.top_line {
+            Some(top) => (top + index as u64).to_string(),
+            None => "?".to_string(),
+        };
+        let mut label = format!("{:>1$} ", label, width - 1);
+        // Cut from the left, keeping the low digits that tell neighbouring rows apart
+        while label.len() > width {
+            label.remove(0);
+        }
+        format!("{}{}", mark, label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(count: usize) -> Vec<String> {
+        (0..count).map(|row| format!("row {row}")).collect()
+    }
+
+    #[test]
+    fn gutter_is_sized_for_the_last_number_on_the_page() {
+        let mut state = ViewState::new("/test", 80, 6);
+        state.update_viewport_content(rows(5), Vec::new());
+        assert_eq!(state.gutter_width(), 0);
+        assert_eq!(state.text_width(), 80);
+
+        let before = state.content_generation();
+        assert!(state.set_line_numbers(true));
+        assert!(state.content_generation() > before);
+        assert_eq!(state.gutter_label(0), "   ? ");
+        assert_eq!(state.text_width(), 75);
+
+        state.set_top_line(Some(99_998));
+        assert_eq!(state.gutter_width(), 7);
+        assert_eq!(state.gutter_label(0), " 99998 ");
+        assert_eq!(state.gutter_label(4), "100002 ");
+        // Rows, and so paging, are unaffected
+        assert_eq!(state.lines_per_page(), 5);
+
+        let mut narrow = ViewState::new("/test", 6, 6);
+        narrow.update_viewport_content(rows(1), Vec::new());
+        narrow.set_line_numbers(true);
+        narrow.set_top_line(Some(12_345));
+        assert_eq!(narrow.gutter_label(0), "45 ");
+        assert_eq!(narrow.text_width(), 3);
+    }
+
+    #[test]
+    fn bookmarked_lines_are_marked_while_their_start_is_known() {
+        let mut state = ViewState::new("/test", 80, 6);
+        state.navigate_to_byte(100);
+        // Rows start at 100, 106, 112, ...
+        state.update_viewport_content(rows(5), Vec::new());
+        let before = state.content_generation();
+        state.set_bookmarks(vec![106, 118, 5000]);
+        assert!(state.content_generation() > before);
+        assert_eq!(state.gutter_width(), 2);
+        assert_eq!(state.gutter_label(0), "  ");
+        assert_eq!(state.gutter_label(1), "• ");
+        assert_eq!(state.gutter_label(3), "• ");
+        assert_eq!(state.text_width(), 78);
+
+        state.set_line_numbers(true);
+        state.set_top_line(Some(7));
+        assert_eq!(state.gutter_label(1), "•    8 ");
+
+        // Past a row that may be part of a longer line, starts are unknown
+        let mut long = rows(3);
+        long[0] = "x".repeat(SEGMENT_BYTES);
+        state.set_line_numbers(false);
+        state.set_bookmarks(vec![106, 200 + SEGMENT_BYTES as u64 + 1]);
+        state.navigate_to_byte(200);
+        state.update_viewport_content(long, Vec::new());
+        assert!(!state.is_bookmarked(1));
+
+        // Lines of a filtered view are not contiguous
+        state.status_line.filter = Some("row".to_string());
+        state.navigate_to_byte(100);
+        state.update_viewport_content(rows(5), Vec::new());
+        assert!(!state.is_bookmarked(1));
+        state.navigate_to_byte(106);
+        state.update_viewport_content(rows(5), Vec::new());
+        assert!(state.is_bookmarked(0));
+    }
+}
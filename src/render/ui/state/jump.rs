@@ -0,0 +1,118 @@
+//! Where a search jump puts the matching line (`-j`), and the marker on that line.
+//!
+//! By default the matching line becomes the top row, as it always did. `-jN` keeps N lines of
+//! context above it and `-jc` puts it in the middle of the screen. Either way the whole line is
+//! drawn in the theme's `match_line` style while it is on screen, with the match itself in the
+//! `current_match` style, so it stays easy to spot among the lines and matches around it. The
+//! worker says which row of each page that is, since only it knows where the rows start.
+
+use super::ViewState;
+
+/// Row a search jump puts the matching line on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpTarget {
+    /// This many lines of context above the match; 0 is the top row
+    Lines(u16),
+    /// The middle of the screen
+    Center,
+}
+
+impl Default for JumpTarget {
+    fn default() -> Self {
+        JumpTarget::TOP
+    }
+}
+
+impl JumpTarget {
+    /// The matching line on the top row
+    pub const TOP: JumpTarget = JumpTarget::Lines(0);
+
+    /// Target for `N`, or `c`/`center`
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "c" | "center" => Some(JumpTarget::Center),
+            lines => lines.parse().ok().map(JumpTarget::Lines),
+        }
+    }
+
+    /// Name used on the command line and by `:options`
+    pub fn name(self) -> String {
+        match self {
+            JumpTarget::Lines(lines) => lines.to_string(),
+            JumpTarget::Center => "center".to_string(),
+        }
+    }
+
+    /// Lines shown above the match on a page of `page_lines` rows
+    ///
+    /// Always leaves the match itself on the page.
+    pub fn context(self, page_lines: u16) -> u16 {
+        let last = page_lines.saturating_sub(1);
+        match self {
+            JumpTarget::Lines(lines) => lines.min(last),
+            JumpTarget::Center => last / 2,
+        }
+    }
+}
+
+impl ViewState {
+    /// Mark viewport line `row` as the one a search jumped to, as reported with the page, or
+    /// clear the mark
+    pub fn set_match_row(&mut self, row: Option<usize>) {
+        if self.match_row != row {
+            self.match_row = row;
+            self.content_generation += 1;
+        }
+    }
+
+    /// Whether viewport line `index` is the line a search jumped to
+    pub fn is_match_line(&self, index: usize) -> bool {
+        self.match_row == Some(index)
+    }
+
+    /// Viewport line a search jumped to, if it is on screen
+    pub fn match_row(&self) -> Option<usize> {
+        self.match_row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_parse_and_fit_the_page() {
+        assert_eq!(JumpTarget::parse("0"), Some(JumpTarget::TOP));
+        assert_eq!(JumpTarget::parse("5"), Some(JumpTarget::Lines(5)));
+        assert_eq!(JumpTarget::parse("c"), Some(JumpTarget::Center));
+        assert_eq!(JumpTarget::parse("center"), Some(JumpTarget::Center));
+        assert_eq!(JumpTarget::parse("-2"), None);
+        assert_eq!(JumpTarget::parse(""), None);
+
+        assert_eq!(JumpTarget::TOP.context(23), 0);
+        assert_eq!(JumpTarget::Lines(5).context(23), 5);
+        assert_eq!(JumpTarget::Lines(50).context(23), 22);
+        assert_eq!(JumpTarget::Center.context(23), 11);
+        assert_eq!(JumpTarget::Center.context(1), 0);
+        assert_eq!(JumpTarget::Lines(3).context(0), 0);
+    }
+
+    #[test]
+    fn match_row_comes_with_each_page() {
+        let rows = |count: usize| (0..count).map(|row| format!("row {row}")).collect();
+        let mut state = ViewState::new("/test", 80, 6);
+        state.update_viewport_content(rows(5), Vec::new());
+        let before = state.content_generation();
+        state.set_match_row(Some(2));
+        assert!(state.content_generation() > before);
+        assert!(state.is_match_line(2));
+        assert!(!state.is_match_line(0));
+
+        let before = state.content_generation();
+        state.set_match_row(Some(2));
+        assert_eq!(state.content_generation(), before);
+        state.set_match_row(None);
+        assert_eq!(state.match_row(), None);
+        assert!(!state.is_match_line(2));
+    }
+}
@@ -0,0 +1,107 @@
+//! The match list panel (`M`): lines matching the active search, drawn over the content.
+
+use super::ViewState;
+use crate::input::ScrollDirection;
+
+/// Matching lines listed so far, and the one selected
+///
+/// The list fills a batch at a time: `more_from` says where the next batch starts until every
+/// match is listed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchList {
+    /// Search the matches belong to, as shown in the title
+    pub title: String,
+    /// Byte offset each matching line starts at, and the beginning of its text
+    pub entries: Vec<(u64, String)>,
+    pub selected: usize,
+    /// First entry shown
+    pub scroll: usize,
+    /// Start of the next batch to fetch, `None` once the end of the file was reached
+    pub more_from: Option<u64>,
+    /// A batch is being fetched
+    pub loading: bool,
+    /// Shown in place of the entries, such as why there are none
+    pub message: Option<String>,
+}
+
+impl MatchList {
+    /// Start of the selected line
+    pub fn selected_byte(&self) -> Option<u64> {
+        self.entries.get(self.selected).map(|&(byte, _)| byte)
+    }
+
+    /// Whether the selection is within `rows` entries of the end of a list that goes on
+    pub fn wants_more(&self, rows: usize) -> bool {
+        !self.loading
+            && self.more_from.is_some()
+            && self.message.is_none()
+            && self.selected + rows >= self.entries.len()
+    }
+
+    /// Matches listed so far, with a `+` while there may be more
+    pub fn count_label(&self) -> String {
+        match self.more_from {
+            Some(_) => format!("{}+", self.entries.len()),
+            None => self.entries.len().to_string(),
+        }
+    }
+}
+
+impl ViewState {
+    /// Entries the match list shows at once, inside the border
+    pub fn match_list_rows(&self) -> usize {
+        self.peek_text_size().1
+    }
+
+    /// Move the selection in the match list by `lines` entries, scrolling to keep it in view
+    pub fn move_match_selection(&mut self, direction: ScrollDirection, lines: u64) {
+        let rows = self.match_list_rows();
+        let Some(list) = self.match_list.as_mut() else {
+            return;
+        };
+        let lines = usize::try_from(lines).unwrap_or(usize::MAX);
+        let last = list.entries.len().saturating_sub(1);
+        list.selected = match direction {
+            ScrollDirection::Up => list.selected.saturating_sub(lines),
+            ScrollDirection::Down => list.selected.saturating_add(lines).min(last),
+        };
+        if list.selected < list.scroll {
+            list.scroll = list.selected;
+        } else if list.selected >= list.scroll + rows {
+            list.scroll = list.selected + 1 - rows;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_stays_in_view_and_asks_for_more_near_the_end() {
+        // Three entries fit inside the border
+        let mut state = ViewState::new("/test/file.log", 40, 6);
+        state.match_list = Some(MatchList {
+            entries: (0..10).map(|i| (i * 10, format!("match {i}"))).collect(),
+            more_from: Some(100),
+            ..MatchList::default()
+        });
+        assert_eq!(state.match_list_rows(), 3);
+
+        state.move_match_selection(ScrollDirection::Down, 4);
+        let list = state.match_list.as_ref().unwrap();
+        assert_eq!((list.selected, list.scroll), (4, 2));
+        assert_eq!(list.selected_byte(), Some(40));
+        assert!(!list.wants_more(3));
+        assert_eq!(list.count_label(), "10+");
+
+        state.move_match_selection(ScrollDirection::Down, 100);
+        let list = state.match_list.as_ref().unwrap();
+        assert_eq!((list.selected, list.scroll), (9, 7));
+        assert!(list.wants_more(3));
+
+        state.move_match_selection(ScrollDirection::Up, 8);
+        let list = state.match_list.as_ref().unwrap();
+        assert_eq!((list.selected, list.scroll), (1, 1));
+    }
+}
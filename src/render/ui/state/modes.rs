@@ -0,0 +1,146 @@
+//! Display modes and the line peek overlay.
+
+use super::ViewState;
+use crate::input::{ScrollDirection, SearchDirection};
+use crate::render::protocol::HighlightSpan;
+
+/// One line shown in full, wrapped over the content (`o`)
+///
+/// The same overlay lists the effective settings for `:options`; that text holds several lines
+/// and carries its own title.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinePeek {
+    /// Byte offset where `text` starts, `None` while the line is being fetched
+    pub start: Option<u64>,
+    /// Heading used instead of the line's position
+    pub title: Option<String>,
+    pub text: String,
+    pub highlights: Vec<HighlightSpan>,
+    /// Only part of a very long line was fetched
+    pub truncated: bool,
+    /// First wrapped row shown
+    pub scroll: usize,
+}
+
+impl LinePeek {
+    /// Rows the text takes when each line is wrapped every `width` characters
+    pub fn wrapped_rows(&self, width: usize) -> usize {
+        self.text
+            .split('\n')
+            .map(|line| line.chars().count().div_ceil(width.max(1)).max(1))
+            .sum()
+    }
+}
+
+impl ViewState {
+    /// Columns and rows available to the peeked line inside the overlay border
+    pub fn peek_text_size(&self) -> (usize, usize) {
+        (
+            usize::from(self.viewport_width.saturating_sub(2)).max(1),
+            usize::from(self.lines_per_page().saturating_sub(2)).max(1),
+        )
+    }
+
+    /// Scroll the peek overlay by `lines` wrapped rows, stopping at either end
+    pub fn scroll_peek(&mut self, direction: ScrollDirection, lines: u64) {
+        let (width, height) = self.peek_text_size();
+        if let Some(peek) = self.peek.as_mut() {
+            let max_scroll = peek.wrapped_rows(width).saturating_sub(height);
+            let lines = usize::try_from(lines).unwrap_or(usize::MAX);
+            peek.scroll = match direction {
+                ScrollDirection::Up => peek.scroll.saturating_sub(lines),
+                ScrollDirection::Down => peek.scroll.saturating_add(lines).min(max_scroll),
+            };
+        }
+    }
+}
+
+/// How much screen the viewer's own UI takes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChromeMode {
+    /// Status line always visible below the content
+    #[default]
+    Full,
+    /// Content only, for screen sharing; messages are hidden and prompts shown while typing
+    Presentation,
+}
+
+impl ChromeMode {
+    /// The other mode, selected by `P`
+    pub fn toggled(self) -> Self {
+        match self {
+            ChromeMode::Full => ChromeMode::Presentation,
+            ChromeMode::Presentation => ChromeMode::Full,
+        }
+    }
+}
+
+/// Current display mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayMode {
+    Normal,
+    Search(SearchDirection),
+    Command,
+    Help,
+}
+
+impl DisplayMode {
+    /// Get the mode indicator string for display
+    pub fn indicator(&self) -> &'static str {
+        match self {
+            DisplayMode::Normal => "",
+            DisplayMode::Search(SearchDirection::Forward) => "/",
+            DisplayMode::Search(SearchDirection::Backward) => "?",
+            DisplayMode::Command => ":",
+            DisplayMode::Help => "HELP",
+        }
+    }
+
+    /// Check if this mode accepts text input
+    pub fn accepts_input(&self) -> bool {
+        matches!(self, DisplayMode::Search(_) | DisplayMode::Command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_mode() {
+        assert_eq!(DisplayMode::Normal.indicator(), "");
+        assert_eq!(
+            DisplayMode::Search(SearchDirection::Forward).indicator(),
+            "/"
+        );
+        assert_eq!(
+            DisplayMode::Search(SearchDirection::Backward).indicator(),
+            "?"
+        );
+        assert_eq!(DisplayMode::Command.indicator(), ":");
+        assert_eq!(DisplayMode::Help.indicator(), "HELP");
+
+        assert!(DisplayMode::Search(SearchDirection::Forward).accepts_input());
+        assert!(DisplayMode::Command.accepts_input());
+        assert!(!DisplayMode::Normal.accepts_input());
+    }
+
+    #[test]
+    fn test_peek_scroll_stops_at_both_ends() {
+        let mut state = ViewState::new("/test/file.log", 12, 6);
+        // 10 columns and 3 rows inside the border; 45 characters wrap onto 5 rows
+        state.peek = Some(LinePeek {
+            start: Some(0),
+            text: "z".repeat(45),
+            ..LinePeek::default()
+        });
+        assert_eq!(state.peek_text_size(), (10, 3));
+
+        state.scroll_peek(ScrollDirection::Down, 1);
+        assert_eq!(state.peek.as_ref().unwrap().scroll, 1);
+        state.scroll_peek(ScrollDirection::Down, 10);
+        assert_eq!(state.peek.as_ref().unwrap().scroll, 2);
+        state.scroll_peek(ScrollDirection::Up, 10);
+        assert_eq!(state.peek.as_ref().unwrap().scroll, 0);
+    }
+}
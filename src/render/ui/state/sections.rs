@@ -0,0 +1,70 @@
+//! Sections of a file made of several gzip members.
+
+use super::ViewState;
+
+impl ViewState {
+    /// Record the file's section start offsets
+    pub fn set_sections(&mut self, sections: Vec<u64>) {
+        self.sections = sections;
+        self.status_line.section = self.section_at(self.viewport_top_byte);
+    }
+
+    /// 1-based index of the section holding `byte` and the section count
+    ///
+    /// `None` unless the file has more than one section.
+    pub fn section_at(&self, byte: u64) -> Option<(usize, usize)> {
+        if self.sections.len() < 2 {
+            return None;
+        }
+        let index = self.sections.partition_point(|&start| start <= byte).max(1);
+        Some((index, self.sections.len()))
+    }
+
+    /// Start of the first section after the viewport top
+    pub fn next_section_start(&self) -> Option<u64> {
+        self.sections
+            .iter()
+            .copied()
+            .find(|&start| start > self.viewport_top_byte)
+    }
+
+    /// Start of the last section before the viewport top
+    ///
+    /// From the middle of a section this is the start of that section.
+    pub fn prev_section_start(&self) -> Option<u64> {
+        self.sections
+            .iter()
+            .rev()
+            .copied()
+            .find(|&start| start < self.viewport_top_byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_section_index_follows_the_viewport() {
+        let mut state = ViewState::new(PathBuf::from("/test/all.log.gz"), 80, 24);
+        state.file_size = Some(300);
+        state.set_sections(vec![0, 100, 200]);
+        assert_eq!(state.next_section_start(), Some(100));
+        assert_eq!(state.prev_section_start(), None);
+        assert!(state.format_status_line().ends_with(" | member 1/3"));
+
+        state.navigate_to_byte(150);
+        assert_eq!(state.status_line.section, Some((2, 3)));
+        assert_eq!(state.next_section_start(), Some(200));
+        assert_eq!(state.prev_section_start(), Some(100));
+
+        state.navigate_to_byte(200);
+        assert_eq!(state.status_line.section, Some((3, 3)));
+        assert_eq!(state.next_section_start(), None);
+
+        // A single member is not worth a segment
+        state.set_sections(vec![0]);
+        assert_eq!(state.format_status_line(), "all.log.gz | 67%");
+    }
+}
@@ -0,0 +1,600 @@
+//! Status line content: messages, prompts and the position readout.
+
+use crate::input::SearchDirection;
+use crate::render::protocol::LinePosition;
+use crate::search::SearchOptions;
+
+/// Make text from the file or an error safe to draw on the status line
+///
+/// ANSI escape sequences (CSI, OSC and two-byte escapes) are dropped, remaining C0 controls and
+/// DEL are shown in caret notation (`^M`, `^?`) and C1 controls as `<9B>`.
+pub fn sanitize_status_text(text: &str) -> String {
+    if !text.chars().any(|ch| ch.is_control()) {
+        return text.to_string();
+    }
+
+    let mut sanitized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x1b' => match chars.next() {
+                // CSI: parameter and intermediate bytes up to a final byte in `@`..=`~`
+                Some('[') => {
+                    for next in chars.by_ref() {
+                        if ('@'..='~').contains(&next) {
+                            break;
+                        }
+                    }
+                }
+                // OSC: runs until BEL or the string terminator `ESC \`
+                Some(']') => {
+                    while let Some(next) = chars.next() {
+                        if next == '\x07' {
+                            break;
+                        }
+                        if next == '\x1b' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                Some(_) | None => {}
+            },
+            '\0'..='\x1f' => {
+                sanitized.push('^');
+                sanitized.push(char::from(ch as u8 + b'@'));
+            }
+            '\x7f' => sanitized.push_str("^?"),
+            '\u{80}'..='\u{9f}' => sanitized.push_str(&format!("<{:02X}>", ch as u32)),
+            _ => sanitized.push(ch),
+        }
+    }
+    sanitized
+}
+
+/// Search options summarised for the prompt prefix
+///
+/// Only modes that differ from the defaults get an indicator, so a plain `/` means a
+/// case-sensitive regex search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PromptOptions {
+    pub ignore_case: bool,
+    /// Case is ignored unless the pattern holds an uppercase character
+    pub smart_case: bool,
+    pub literal: bool,
+    pub whole_word: bool,
+    /// Matches may run across line ends
+    pub multiline: bool,
+}
+
+impl PromptOptions {
+    /// Compact indicator such as `[IW]`, or an empty string when no mode is active
+    pub fn indicator(&self) -> String {
+        let flags: String = [
+            (self.ignore_case, 'I'),
+            (self.smart_case, 'S'),
+            (self.literal, 'L'),
+            (self.whole_word, 'W'),
+            (self.multiline, 'U'),
+        ]
+        .iter()
+        .filter_map(|&(enabled, flag)| enabled.then_some(flag))
+        .collect();
+
+        if flags.is_empty() {
+            flags
+        } else {
+            format!("[{}]", flags)
+        }
+    }
+}
+
+impl From<&SearchOptions> for PromptOptions {
+    fn from(options: &SearchOptions) -> Self {
+        Self {
+            ignore_case: !options.case_sensitive && !options.smart_case,
+            smart_case: options.smart_case,
+            literal: !options.regex_mode,
+            whole_word: options.whole_word,
+            multiline: options.multiline,
+        }
+    }
+}
+
+/// Units of the position segment on the status line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionStyle {
+    /// Share of the file above the viewport, e.g. `37%`
+    #[default]
+    Percent,
+    /// Top line and line total, e.g. `L123,456/2,345,678`
+    Lines,
+    /// Byte offset of the viewport top, e.g. `byte 0x1F4B3C00`
+    Byte,
+}
+
+impl PositionStyle {
+    /// Style selected by the next `-P`
+    pub fn next(self) -> Self {
+        match self {
+            PositionStyle::Percent => PositionStyle::Lines,
+            PositionStyle::Lines => PositionStyle::Byte,
+            PositionStyle::Byte => PositionStyle::Percent,
+        }
+    }
+
+    /// Name used on the command line and in status messages
+    pub fn name(self) -> &'static str {
+        match self {
+            PositionStyle::Percent => "percent",
+            PositionStyle::Lines => "lines",
+            PositionStyle::Byte => "byte",
+        }
+    }
+
+    /// Style for a name returned by [`PositionStyle::name`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            PositionStyle::Percent,
+            PositionStyle::Lines,
+            PositionStyle::Byte,
+        ]
+        .into_iter()
+        .find(|style| style.name() == name)
+    }
+}
+
+/// Format `value` with `,` between groups of three digits
+fn group_thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// How a key that does nothing is answered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidKeyFeedback {
+    /// Ring the terminal bell, as less does
+    #[default]
+    Bell,
+    /// Show the status line in reverse video for a moment
+    Flash,
+    /// Ignore the key
+    None,
+}
+
+impl InvalidKeyFeedback {
+    /// Name used on the command line and by `:options`
+    pub fn name(self) -> &'static str {
+        match self {
+            InvalidKeyFeedback::Bell => "bell",
+            InvalidKeyFeedback::Flash => "flash",
+            InvalidKeyFeedback::None => "none",
+        }
+    }
+
+    /// Feedback for a name returned by [`InvalidKeyFeedback::name`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            InvalidKeyFeedback::Bell,
+            InvalidKeyFeedback::Flash,
+            InvalidKeyFeedback::None,
+        ]
+        .into_iter()
+        .find(|feedback| feedback.name() == name)
+    }
+}
+
+/// Tail-following state while follow mode is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailMode {
+    /// New data pulls the viewport to the end of the file
+    Sticky,
+    /// The user moved away from the end; new data leaves the viewport alone
+    Paused,
+}
+
+impl TailMode {
+    /// Status line marker for this mode
+    pub fn label(self) -> &'static str {
+        match self {
+            TailMode::Sticky => "[following]",
+            TailMode::Paused => "[paused]",
+        }
+    }
+}
+
+/// Status line information
+#[derive(Debug, Clone, Default)]
+pub struct StatusLine {
+    pub message: Option<String>,
+    pub search_prompt: Option<(SearchDirection, String)>,
+    pub prompt_options: PromptOptions,
+    /// Quiet remark on the pattern being typed, such as why it does not compile yet; drawn
+    /// dimmed beside the prompt and dropped with it
+    pub prompt_note: Option<String>,
+    /// Active filter pattern, shown as `&pattern`
+    pub filter: Option<String>,
+    /// Some visible lines show only part of their matches
+    pub highlights_truncated: bool,
+    /// Follow mode state, `None` when not following
+    pub tail: Option<TailMode>,
+    /// Wheel notches move one line each, shown as `[precise]`
+    pub precise_scroll: bool,
+    /// Running operations with their progress, e.g. `searching 42%`
+    pub progress: Option<String>,
+    /// Section holding the viewport top and the section count, shown as `member 2/3`
+    pub section: Option<(usize, usize)>,
+    /// Pattern of the active search and the direction it was started in
+    pub active_pattern: Option<(SearchDirection, String)>,
+    /// Standing problem, shown until it is resolved rather than until the next action
+    pub alert: Option<String>,
+    /// Drawn in reverse video, briefly, to answer a key that does nothing
+    pub flash: bool,
+}
+
+impl StatusLine {
+    /// Create a new status line
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a temporary message
+    ///
+    /// Messages often quote file content or error text, so control characters are escaped.
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(sanitize_status_text(&message));
+    }
+
+    /// Clear any temporary message
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+
+    /// Set search prompt for input mode
+    pub fn set_search_prompt(&mut self, direction: SearchDirection, options: PromptOptions) {
+        self.search_prompt = Some((direction, String::new()));
+        self.prompt_options = options;
+    }
+
+    /// Update search prompt with current buffer
+    pub fn update_search_prompt(
+        &mut self,
+        direction: SearchDirection,
+        buffer: String,
+        options: PromptOptions,
+    ) {
+        // A pasted NUL would end the row early on some terminals; the buffer itself is untouched
+        let buffer = buffer.replace('\0', "");
+        self.search_prompt = Some((direction, sanitize_status_text(&buffer)));
+        self.prompt_options = options;
+    }
+
+    /// Clear search prompt and return to normal mode
+    pub fn clear_search_prompt(&mut self) {
+        self.search_prompt = None;
+        self.prompt_note = None;
+    }
+
+    /// Note something about the pattern being typed without interrupting the prompt
+    pub fn set_prompt_note(&mut self, note: Option<String>) {
+        self.prompt_note = note.map(|note| sanitize_status_text(&note));
+    }
+
+    /// Record the pattern of the search that just became active
+    pub fn set_active_pattern(&mut self, direction: SearchDirection, pattern: &str) {
+        let pattern = pattern.replace('\0', "");
+        self.active_pattern = Some((direction, sanitize_status_text(&pattern)));
+    }
+
+    /// Format the status line for display (with position calculated on-the-fly)
+    ///
+    /// `Lines` shows `L<top>` alone while the total is unknown and falls back to the percent
+    /// display until the top line itself is known.
+    pub fn format_status_line(
+        &self,
+        filename: &str,
+        current_byte: u64,
+        total_bytes: u64,
+        at_eof: bool,
+        style: PositionStyle,
+        lines: Option<LinePosition>,
+    ) -> String {
+        if let Some((direction, buffer)) = &self.search_prompt {
+            // Show search prompt: "[I]/search_term"
+            format!(
+                "{}{}{}",
+                self.prompt_options.indicator(),
+                direction.to_char(),
+                buffer
+            )
+        } else {
+            // Calculate position on-the-fly
+            let position = if total_bytes == 0 {
+                "Empty".to_string()
+            } else if style == PositionStyle::Byte {
+                format!("byte {:#X}", current_byte)
+            } else if let (PositionStyle::Lines, Some(lines)) = (style, lines) {
+                match lines.total {
+                    Some(total) => {
+                        format!("L{}/{}", group_thousands(lines.top), group_thousands(total))
+                    }
+                    None => format!("L{}", group_thousands(lines.top)),
+                }
+            } else if at_eof {
+                "EOD".to_string() // End of Data - user hit EOF during navigation
+            } else if current_byte >= total_bytes {
+                "END".to_string() // At end of file (for other cases)
+            } else {
+                let percentage = (current_byte as f32 / total_bytes as f32) * 100.0;
+                format!("{:.0}%", percentage)
+            };
+
+            // Format status line
+            let mut line = format!("{} | {}", filename, position);
+            if let Some(tail) = self.tail {
+                line.push_str(&format!(" {}", tail.label()));
+            }
+            if self.precise_scroll {
+                line.push_str(" [precise]");
+            }
+            if let Some((index, count)) = self.section {
+                line.push_str(&format!(" | member {}/{}", index, count));
+            }
+            if let Some(ref filter) = self.filter {
+                line.push_str(&format!(" | &{}", filter));
+            }
+            if self.highlights_truncated {
+                line.push_str(" | matches truncated on some lines");
+            }
+            if let Some(ref progress) = self.progress {
+                line.push_str(&format!(" | {}", progress));
+            }
+            if let Some(ref alert) = self.alert {
+                line.push_str(&format!(" | {}", alert));
+            }
+            if let Some(ref message) = self.message {
+                line.push_str(&format!(" | {}", message));
+            } else if self.tail == Some(TailMode::Sticky) {
+                line.push_str(" | Waiting for data... (interrupt to abort)");
+            }
+            line
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nul_bytes_are_dropped_from_the_prompt_and_active_pattern() {
+        let mut status = StatusLine::new();
+        status.update_search_prompt(
+            SearchDirection::Forward,
+            "a\0b\tc".to_string(),
+            PromptOptions::default(),
+        );
+        assert_eq!(
+            status.search_prompt,
+            Some((SearchDirection::Forward, "ab^Ic".to_string()))
+        );
+        status.set_active_pattern(SearchDirection::Backward, "\0x\0");
+        assert_eq!(
+            status.active_pattern,
+            Some((SearchDirection::Backward, "x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_status_line_format() {
+        let mut status = StatusLine::new();
+
+        // Test normal status line with position
+        let formatted =
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Percent, None);
+        assert_eq!(formatted, "test.log | 50%");
+
+        // Test with message
+        status.set_message("Pattern not found".to_string());
+        let formatted =
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Percent, None);
+        assert_eq!(formatted, "test.log | 50% | Pattern not found");
+
+        // Test empty file
+        let formatted =
+            status.format_status_line("empty.log", 0, 0, false, PositionStyle::Percent, None);
+        assert_eq!(formatted, "empty.log | Empty | Pattern not found");
+
+        // Test at end
+        status.clear_message();
+        let formatted =
+            status.format_status_line("test.log", 1024, 1024, false, PositionStyle::Percent, None);
+        assert_eq!(formatted, "test.log | END");
+
+        // Test search prompt
+        status.set_search_prompt(SearchDirection::Forward, PromptOptions::default());
+        let formatted =
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Percent, None);
+        assert_eq!(formatted, "/");
+
+        status.update_search_prompt(
+            SearchDirection::Forward,
+            "search term".to_string(),
+            PromptOptions::default(),
+        );
+        let formatted =
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Percent, None);
+        assert_eq!(formatted, "/search term");
+
+        // Test EOD (End of Data) display when at_eof is true
+        status.clear_search_prompt();
+        let formatted =
+            status.format_status_line("test.log", 512, 1024, true, PositionStyle::Percent, None);
+        assert_eq!(formatted, "test.log | EOD");
+
+        // Active filter is shown ahead of any message
+        status.filter = Some("error".to_string());
+        status.set_message("Pattern not found".to_string());
+        let formatted =
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Percent, None);
+        assert_eq!(formatted, "test.log | 50% | &error | Pattern not found");
+
+        status.highlights_truncated = true;
+        let formatted =
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Percent, None);
+        assert_eq!(
+            formatted,
+            "test.log | 50% | &error | matches truncated on some lines | Pattern not found"
+        );
+
+        // Follow state sits next to the position it describes
+        status.highlights_truncated = false;
+        status.message = None;
+        status.tail = Some(TailMode::Paused);
+        let formatted =
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Percent, None);
+        assert_eq!(formatted, "test.log | 50% [paused] | &error");
+    }
+
+    #[test]
+    fn test_position_styles() {
+        let status = StatusLine::new();
+        let lines = Some(LinePosition {
+            top: 123_456,
+            total: Some(2_345_678),
+        });
+        let format = |style, lines| {
+            status.format_status_line("test.log", 0x1F4B_3C00, 0x4000_0000, false, style, lines)
+        };
+
+        assert_eq!(format(PositionStyle::Percent, lines), "test.log | 49%");
+        assert_eq!(
+            format(PositionStyle::Lines, lines),
+            "test.log | L123,456/2,345,678"
+        );
+        assert_eq!(
+            format(PositionStyle::Byte, lines),
+            "test.log | byte 0x1F4B3C00"
+        );
+        assert_eq!(
+            format(PositionStyle::Byte, None),
+            "test.log | byte 0x1F4B3C00"
+        );
+    }
+
+    #[test]
+    fn test_line_position_without_known_total() {
+        let status = StatusLine::new();
+        let counting = Some(LinePosition {
+            top: 1_000,
+            total: None,
+        });
+
+        assert_eq!(
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Lines, counting),
+            "test.log | L1,000"
+        );
+        // Before the top line is known the percent display stands in
+        assert_eq!(
+            status.format_status_line("test.log", 512, 1024, false, PositionStyle::Lines, None),
+            "test.log | 50%"
+        );
+        assert_eq!(
+            status.format_status_line("empty.log", 0, 0, false, PositionStyle::Byte, None),
+            "empty.log | Empty"
+        );
+    }
+
+    #[test]
+    fn test_position_style_cycle_and_names() {
+        let mut style = PositionStyle::default();
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            seen.push(style.name());
+            assert_eq!(PositionStyle::from_name(style.name()), Some(style));
+            style = style.next();
+        }
+        assert_eq!(style, PositionStyle::Percent);
+        assert_eq!(seen, vec!["percent", "lines", "byte"]);
+        assert_eq!(PositionStyle::from_name("rows"), None);
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1_000), "1,000");
+    }
+
+    #[test]
+    fn test_status_text_is_sanitized() {
+        assert_eq!(sanitize_status_text("plain text"), "plain text");
+        assert_eq!(sanitize_status_text("before\x1b[2Jafter"), "beforeafter");
+        assert_eq!(
+            sanitize_status_text("\x1b]0;title\x07x\x1b]8;;url\x1b\\y\x1bcz"),
+            "xyz"
+        );
+        assert_eq!(sanitize_status_text("a\rb\tc\x7f"), "a^Mb^Ic^?");
+        assert_eq!(sanitize_status_text("\u{9b}2J"), "<9B>2J");
+        assert_eq!(sanitize_status_text("dangling\x1b"), "dangling");
+
+        let mut status = StatusLine::new();
+        status.set_message("Pattern not found: \x1b[2J\x1b[31mred".to_string());
+        assert_eq!(status.message.as_deref(), Some("Pattern not found: red"));
+        status.update_search_prompt(
+            SearchDirection::Forward,
+            "\x1b[2Jmatch".to_string(),
+            PromptOptions::default(),
+        );
+        assert_eq!(
+            status.format_status_line("test.log", 0, 10, false, PositionStyle::Percent, None),
+            "/match"
+        );
+    }
+
+    #[test]
+    fn test_search_prompt_option_indicators() {
+        let mut status = StatusLine::new();
+        let prompt = |status: &mut StatusLine, options: SearchOptions| {
+            status.update_search_prompt(
+                SearchDirection::Backward,
+                "err".to_string(),
+                PromptOptions::from(&options),
+            );
+            status.format_status_line("test.log", 0, 10, false, PositionStyle::Percent, None)
+        };
+
+        assert_eq!(prompt(&mut status, SearchOptions::default()), "?err");
+
+        let ignore_case = SearchOptions {
+            case_sensitive: false,
+            ..SearchOptions::default()
+        };
+        assert_eq!(prompt(&mut status, ignore_case.clone()), "[I]?err");
+
+        // Smart case stands in for the case setting it overrides
+        let smart_case = SearchOptions {
+            smart_case: true,
+            ..ignore_case
+        };
+        assert_eq!(prompt(&mut status, smart_case), "[S]?err");
+
+        let literal_word = SearchOptions {
+            regex_mode: false,
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(prompt(&mut status, literal_word), "[LW]?err");
+
+        let all = SearchOptions {
+            case_sensitive: false,
+            regex_mode: false,
+            whole_word: true,
+            multiline: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(prompt(&mut status, all), "[ILWU]?err");
+    }
+}
@@ -0,0 +1,75 @@
+//! Fitting text into a number of columns.
+
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Prefix of `text` holding every grapheme that starts within the first `max_width` columns
+///
+/// Widths are measured per grapheme, as ratatui lays rows out, so combining marks and emoji ZWJ
+/// sequences stay whole.
+pub(super) fn clip_to_width(text: &str, max_width: usize) -> &str {
+    // No character is narrower than its byte count allows
+    if text.len() <= max_width {
+        return text;
+    }
+    let mut used = 0;
+    for (start, grapheme) in text.grapheme_indices(true) {
+        if used >= max_width {
+            return &text[..start];
+        }
+        used += grapheme.width();
+    }
+    text
+}
+
+/// `prefix` then as much of the end of `text` as fits in `max_width` columns, with `…` marking
+/// the cut
+///
+/// Prompts are typed at their end, so that is the part kept on screen.
+pub(super) fn keep_tail(prefix: &str, text: &str, max_width: usize) -> String {
+    let prefix_width = prefix.width();
+    if prefix_width + text.width() <= max_width {
+        return format!("{prefix}{text}");
+    }
+    // Room left for the tail once the prefix and the ellipsis are drawn
+    let Some(budget) = max_width.checked_sub(prefix_width + 1) else {
+        return clip_to_width(prefix, max_width).to_string();
+    };
+    let mut start = text.len();
+    let mut used = 0;
+    for (offset, grapheme) in text.grapheme_indices(true).rev() {
+        used += grapheme.width();
+        if used > budget {
+            break;
+        }
+        start = offset;
+    }
+    format!("{prefix}…{}", &text[start..])
+}
+
+/// Byte ranges of the rows `text` takes when wrapped every `width` columns
+///
+/// Graphemes stay whole: one too wide for the room left on a row starts the next row. Empty
+/// text still takes one row.
+pub(super) fn wrap_ranges(text: &str, width: usize) -> Vec<Range<usize>> {
+    let width = width.max(1);
+    // No character is narrower than its byte count allows
+    if text.len() <= width {
+        return std::iter::once(0..text.len()).collect();
+    }
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    for (offset, grapheme) in text.grapheme_indices(true) {
+        let grapheme_width = grapheme.width();
+        if used + grapheme_width > width && offset > start {
+            rows.push(start..offset);
+            start = offset;
+            used = 0;
+        }
+        used += grapheme_width;
+    }
+    rows.push(start..text.len());
+    rows
+}
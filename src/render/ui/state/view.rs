@@ -0,0 +1,587 @@
+//! Viewport state: what is on screen and how it is laid out.
+
+use super::text::keep_tail;
+use super::wrap::WrapTarget;
+use super::{sanitize_status_text, ChromeMode, LinePeek, MatchList, PositionStyle, StatusLine};
+use crate::render::protocol::{HighlightSpan, LinePosition, MIN_PAGE_LINES};
+use crate::render::ui::codepoints::{show_newlines, substitute, DEFAULT_PLACEHOLDER};
+use crate::render::ui::highlight::HighlightedLine;
+use std::path::{Path, PathBuf};
+
+/// Viewport state for rendering - focused only on what's currently visible
+#[derive(Debug)]
+pub struct ViewState {
+    /// Byte position of the first line currently in viewport (absolute file position)
+    pub viewport_top_byte: u64,
+
+    /// Lines currently visible in the viewport
+    /// String data from FileAccessor
+    pub visible_lines: Vec<String>,
+
+    /// Status line content
+    pub status_line: StatusLine,
+
+    /// File path for display
+    pub file_path: PathBuf,
+
+    /// File size in bytes (for position calculation)
+    /// None if file size is not yet known
+    pub file_size: Option<u64>,
+
+    /// Viewport dimensions
+    pub viewport_width: u16,
+    pub viewport_height: u16,
+
+    /// Search highlights by viewport-relative line number (Vec index = viewport line)
+    /// Empty Vec at index means no highlights for that line; spans carry whether they come from
+    /// the search or the filter pattern
+    pub search_highlights: Vec<Vec<HighlightSpan>>,
+
+    /// Track if user has hit EOF during navigation (for EOD status display)
+    pub at_eof: bool,
+
+    /// The last visible line is the file's final line and lacks a trailing newline
+    pub noeol: bool,
+
+    /// Units used for the position segment of the status line
+    pub position_style: PositionStyle,
+
+    /// Line number of the viewport top for the `Lines` style, None until the worker reports it
+    pub line_position: Option<LinePosition>,
+
+    /// Whether the status line takes up a row of the screen
+    pub chrome: ChromeMode,
+
+    /// Line shown in full over the content, `None` when the overlay is closed
+    pub peek: Option<LinePeek>,
+
+    /// Matches of the active search listed over the content, `None` when the panel is closed
+    pub match_list: Option<MatchList>,
+
+    /// Byte offsets where the file's sections (gzip members) start, see `set_sections`
+    pub sections: Vec<u64>,
+
+    /// Keep the active search pattern at the right of the status line, see `status_hint`
+    pub show_active_pattern: bool,
+
+    /// Drawn in place of codepoints terminals disagree about, `None` to draw them as they are;
+    /// see `codepoints`
+    pub placeholder: Option<char>,
+
+    /// Draw the line-number gutter, see `set_line_numbers`
+    pub line_numbers: bool,
+
+    /// Line number of the top row as reported by the worker, `None` while unknown
+    pub(super) top_line: Option<u64>,
+
+    /// Wrap long lines onto several rows instead of cutting them, see `set_wrap`
+    pub wrap: bool,
+
+    /// Rows of the page above the screen while wrapping, see `wrap`
+    pub(super) wrap_skip: usize,
+
+    /// Where the top of the screen goes when the requested page arrives, see `expect_page`
+    pub(super) wrap_target: Option<WrapTarget>,
+
+    /// See `content_generation`
+    pub(super) content_generation: u64,
+
+    /// Offsets of the file's bookmarks, marked in the gutter; see `set_bookmarks`
+    pub(super) bookmarks: Vec<u64>,
+
+    /// Byte offset each viewport line starts at, where known; see `note_row_starts`
+    pub(super) row_starts: Vec<Option<u64>>,
+
+    /// Viewport line a search last jumped to, drawn marked; see `set_match_row`
+    pub(super) match_row: Option<usize>,
+}
+
+impl ViewState {
+    /// Create a new viewport state
+    pub fn new(file_path: impl AsRef<Path>, viewport_width: u16, viewport_height: u16) -> Self {
+        Self {
+            viewport_top_byte: 0, // Start at beginning of file
+            visible_lines: Vec::new(),
+            status_line: StatusLine::new(),
+            file_path: file_path.as_ref().to_path_buf(),
+            file_size: None, // Will be set when content is loaded
+            viewport_width,
+            viewport_height,
+            search_highlights: Vec::new(),
+            at_eof: false, // Start not at EOF
+            noeol: false,
+            position_style: PositionStyle::default(),
+            line_position: None,
+            chrome: ChromeMode::default(),
+            peek: None,
+            match_list: None,
+            sections: Vec::new(),
+            show_active_pattern: false,
+            placeholder: Some(DEFAULT_PLACEHOLDER),
+            line_numbers: false,
+            top_line: None,
+            wrap: false,
+            wrap_skip: 0,
+            wrap_target: None,
+            content_generation: 0,
+            bookmarks: Vec::new(),
+            row_starts: Vec::new(),
+            match_row: None,
+        }
+    }
+
+    /// Get the filename for display
+    pub fn filename(&self) -> String {
+        self.file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("<unnamed>")
+            .to_string()
+    }
+
+    /// Get lines per page (viewport height minus status line, if one is shown)
+    ///
+    /// Never below `MIN_PAGE_LINES`: a terminal too short for both a content row and the status
+    /// line shows just the content row, see `shows_status_line`.
+    pub fn lines_per_page(&self) -> u16 {
+        let rows = match self.chrome {
+            ChromeMode::Full => self.viewport_height.saturating_sub(1),
+            ChromeMode::Presentation => self.viewport_height,
+        };
+        rows.max(MIN_PAGE_LINES as u16)
+    }
+
+    /// Whether the status line is drawn this frame
+    ///
+    /// In presentation mode, and on a terminal only one row high, it only appears while a search
+    /// pattern is being typed, covering the last content row.
+    pub fn shows_status_line(&self) -> bool {
+        (self.chrome == ChromeMode::Full && self.viewport_height > 1)
+            || self.status_line.search_prompt.is_some()
+    }
+
+    /// Switch chrome mode; like a resize, a change invalidates the loaded page
+    /// Returns true if the mode actually changed
+    pub fn set_chrome(&mut self, chrome: ChromeMode) -> bool {
+        let changed = self.chrome != chrome;
+        if changed {
+            self.chrome = chrome;
+            self.invalidate_content();
+        }
+        changed
+    }
+
+    /// Get the number of lines currently in the viewport
+    pub fn viewport_line_count(&self) -> usize {
+        self.visible_lines.len()
+    }
+
+    pub fn clear_highlights(&mut self) {
+        for spans in &mut self.search_highlights {
+            spans.clear();
+        }
+        self.content_generation += 1;
+    }
+
+    /// Mark whether the last visible line lacks a trailing newline
+    pub fn set_noeol(&mut self, noeol: bool) {
+        if self.noeol != noeol {
+            self.noeol = noeol;
+            self.content_generation += 1;
+        }
+    }
+
+    /// Changes whenever the methods replacing the page's lines, highlights or `[noeol]` marker
+    /// run, so a renderer can reuse what it built for an unchanged page
+    pub fn content_generation(&self) -> u64 {
+        self.content_generation
+    }
+
+    /// Navigate to a specific byte position in the file
+    pub fn navigate_to_byte(&mut self, byte_position: u64) {
+        self.viewport_top_byte = byte_position;
+        self.status_line.section = self.section_at(byte_position);
+    }
+
+    /// Screen rows with their highlights laid out for display
+    ///
+    /// Renderer-independent; the terminal UI draws exactly these spans. Rows are cut once they
+    /// fill the columns left beside the gutter, or wrapped onto the next row with `wrap` set (see
+    /// `screen_rows`). `visible_lines` keeps the full text.
+    pub fn highlighted_lines(&self) -> Vec<HighlightedLine<'_>> {
+        self.screen_rows().into_iter().map(|row| row.text).collect()
+    }
+
+    /// Update viewport with content and highlights in one operation
+    ///
+    /// Lines are stored as drawn, with `placeholder` substituted and newlines made visible.
+    pub fn update_viewport_content(
+        &mut self,
+        lines: Vec<String>,
+        highlights: Vec<Vec<HighlightSpan>>,
+    ) {
+        self.note_row_starts(&lines);
+        let mut highlights = highlights.into_iter();
+        (self.visible_lines, self.search_highlights) = lines
+            .into_iter()
+            .map(|line| self.displayed(line, highlights.next().unwrap_or_default()))
+            .unzip();
+        self.settle_wrap();
+        self.content_generation += 1;
+    }
+
+    /// `text` and its `highlights` as drawn, with `placeholder` substituted
+    ///
+    /// Only records of a NUL-separated file contain newlines; they are drawn as a symbol.
+    pub fn displayed(
+        &self,
+        text: String,
+        highlights: Vec<HighlightSpan>,
+    ) -> (String, Vec<HighlightSpan>) {
+        let (text, highlights) = show_newlines(text, highlights);
+        match self.placeholder {
+            Some(placeholder) => substitute(text, highlights, placeholder),
+            None => (text, highlights),
+        }
+    }
+
+    /// Update terminal dimensions and mark that content needs to be recalculated
+    /// Returns true if dimensions actually changed
+    pub fn update_terminal_size(&mut self, width: u16, height: u16) -> bool {
+        let changed = self.viewport_width != width || self.viewport_height != height;
+
+        if changed {
+            self.viewport_width = width;
+            self.viewport_height = height;
+            self.invalidate_content();
+        }
+
+        changed
+    }
+
+    /// Drop the loaded page after the number of content rows changed
+    fn invalidate_content(&mut self) {
+        // Clear visible content - it will need to be recalculated with new dimensions
+        self.visible_lines.clear();
+        self.search_highlights.clear();
+        // Reset EOF state since viewport size changed
+        self.at_eof = false;
+        self.noeol = false;
+        self.wrap_skip = 0;
+        self.content_generation += 1;
+    }
+
+    /// Format the complete status line for this view state
+    pub fn format_status_line(&self) -> String {
+        if let Some((direction, buffer)) = &self.status_line.search_prompt {
+            // Keep both the direction marker and the cursor end of a long pattern on screen
+            let prefix = format!(
+                "{}{}",
+                self.status_line.prompt_options.indicator(),
+                direction.to_char()
+            );
+            return keep_tail(&prefix, buffer, self.viewport_width as usize);
+        }
+        self.status_line.format_status_line(
+            &self.filename(),
+            self.viewport_top_byte,
+            self.file_size.unwrap_or(0),
+            self.at_eof,
+            self.position_style,
+            self.line_position,
+        )
+    }
+
+    /// Whether the position is shown in lines but the page came without all of it
+    pub fn line_position_pending(&self) -> bool {
+        self.position_style == PositionStyle::Lines
+            && self
+                .line_position
+                .map_or(true, |lines| lines.total.is_none())
+    }
+
+    /// The active filter as `&pattern` and the active search as `/pattern` or `?pattern`
+    ///
+    /// This is what tells sessions on the same file apart, in the window title and at the top of
+    /// snapshots.
+    pub fn active_filter_and_search(&self) -> (Option<String>, Option<String>) {
+        let filter = self
+            .status_line
+            .filter
+            .as_ref()
+            .map(|filter| format!("&{}", sanitize_status_text(filter)));
+        let search = self
+            .status_line
+            .active_pattern
+            .as_ref()
+            .map(|(direction, pattern)| format!("{}{}", direction.to_char(), pattern));
+        (filter, search)
+    }
+
+    /// Terminal window title, e.g. `rlless — app.log — &payment /timeout`
+    pub fn window_title(&self) -> String {
+        let mut title = format!("rlless — {}", sanitize_status_text(&self.filename()));
+        let (filter, search) = self.active_filter_and_search();
+        let active: Vec<String> = filter.into_iter().chain(search).collect();
+        if !active.is_empty() {
+            title.push_str(" — ");
+            title.push_str(&active.join(" "));
+        }
+        title
+    }
+
+    /// Right-aligned reminder of the active search, e.g. `/timeout`, for a status line reading
+    /// `status`
+    ///
+    /// Only shown with `show_active_pattern` set, and only when nothing else needs the space: no
+    /// prompt, message or progress is showing and the reminder fits beside `status` with a gap.
+    /// While the search prompt is open its note takes the place instead, when it fits.
+    pub fn status_hint(&self, status: &str) -> Option<String> {
+        let status_line = &self.status_line;
+        let hint = if status_line.search_prompt.is_some() {
+            status_line.prompt_note.clone()?
+        } else if !self.show_active_pattern
+            || status_line.message.is_some()
+            || status_line.progress.is_some()
+        {
+            return None;
+        } else {
+            let (direction, pattern) = status_line.active_pattern.as_ref()?;
+            format!("{}{}", direction.to_char(), pattern)
+        };
+        let needed = status.chars().count() + 1 + hint.chars().count();
+        (needed <= self.viewport_width as usize).then_some(hint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::SearchDirection;
+    use crate::render::protocol::HighlightKind;
+    use crate::render::ui::state::PromptOptions;
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn test_view_state_creation() {
+        let path = PathBuf::from("/test/file.log");
+        let state = ViewState::new(path.clone(), 80, 24);
+
+        assert_eq!(state.viewport_top_byte, 0);
+        assert_eq!(state.visible_lines.len(), 0);
+        assert_eq!(state.file_path, path);
+        assert_eq!(state.viewport_width, 80);
+        assert_eq!(state.viewport_height, 24);
+        assert!(state.file_size.is_none());
+    }
+
+    #[test]
+    fn test_active_pattern_hint_needs_room_and_a_quiet_status_line() {
+        let mut state = ViewState::new("/test/app.log", 24, 10);
+        state
+            .status_line
+            .set_active_pattern(SearchDirection::Forward, "timeout");
+        let status = state.format_status_line();
+        assert_eq!(status, "app.log | Empty");
+        // Off unless enabled
+        assert_eq!(state.status_hint(&status), None);
+
+        state.show_active_pattern = true;
+        assert_eq!(state.status_hint(&status).as_deref(), Some("/timeout"));
+        // One column short of the gap between the two
+        state.viewport_width = 23;
+        assert_eq!(state.status_hint(&status), None);
+
+        state.viewport_width = 80;
+        state.status_line.progress = Some("searching 42%".to_string());
+        assert_eq!(state.status_hint(&status), None);
+        state.status_line.progress = None;
+        state
+            .status_line
+            .set_search_prompt(SearchDirection::Forward, Default::default());
+        assert_eq!(state.status_hint(&status), None);
+        // The prompt's note takes the place instead
+        state
+            .status_line
+            .set_prompt_note(Some("unclosed group".to_string()));
+        let prompt = state.format_status_line();
+        assert_eq!(
+            state.status_hint(&prompt).as_deref(),
+            Some("unclosed group")
+        );
+        state.status_line.clear_search_prompt();
+        assert_eq!(state.status_line.prompt_note, None);
+        state.status_line.active_pattern = None;
+        assert_eq!(state.status_hint(&status), None);
+    }
+
+    #[test]
+    fn test_viewport_navigation() {
+        let path = PathBuf::from("/test/file.log");
+        let mut state = ViewState::new(path, 80, 24);
+
+        // Test byte-based navigation
+        state.navigate_to_byte(1000);
+        assert_eq!(state.viewport_top_byte, 1000);
+
+        // Test navigate to different position
+        state.navigate_to_byte(2048);
+        assert_eq!(state.viewport_top_byte, 2048);
+    }
+
+    #[test]
+    fn test_long_search_prompt_keeps_its_end_in_view() {
+        let pattern = format!("start{}end_of_pattern", "x".repeat(2000));
+        for width in [40, 200] {
+            let mut state = ViewState::new("/test/file.log", width, 24);
+            state.status_line.update_search_prompt(
+                SearchDirection::Forward,
+                pattern.clone(),
+                PromptOptions {
+                    ignore_case: true,
+                    ..PromptOptions::default()
+                },
+            );
+
+            let formatted = state.format_status_line();
+            assert_eq!(formatted.width(), width as usize);
+            assert!(formatted.starts_with("[I]/…xxx"));
+            assert!(formatted.ends_with("xend_of_pattern"));
+        }
+
+        // Wide characters are measured by the columns they take
+        let mut state = ViewState::new("/test/file.log", 40, 24);
+        state.status_line.update_search_prompt(
+            SearchDirection::Forward,
+            "日本語".repeat(20),
+            PromptOptions::default(),
+        );
+        let formatted = state.format_status_line();
+        assert!(formatted.width() <= 40);
+        assert!(formatted.starts_with("/…"));
+        assert!(formatted.ends_with("日本語"));
+
+        // Short patterns are shown whole
+        state.status_line.update_search_prompt(
+            SearchDirection::Forward,
+            "short".to_string(),
+            PromptOptions::default(),
+        );
+        assert_eq!(state.format_status_line(), "/short");
+    }
+
+    #[test]
+    fn test_presentation_mode_frees_status_row() {
+        let mut state = ViewState::new("/test/file.log", 80, 24);
+        state.visible_lines = vec!["line1".to_string()];
+        assert_eq!(state.lines_per_page(), 23);
+        assert!(state.shows_status_line());
+
+        assert!(state.set_chrome(ChromeMode::Presentation));
+        assert_eq!(state.lines_per_page(), 24);
+        assert!(state.visible_lines.is_empty());
+        assert!(!state.shows_status_line());
+        assert!(!state.set_chrome(ChromeMode::Presentation));
+
+        // Prompts still appear while a search is typed
+        state
+            .status_line
+            .set_search_prompt(SearchDirection::Forward, PromptOptions::default());
+        assert!(state.shows_status_line());
+        assert_eq!(state.lines_per_page(), 24);
+
+        assert_eq!(ChromeMode::Presentation.toggled(), ChromeMode::Full);
+    }
+
+    #[test]
+    fn test_highlighted_rows_stop_past_the_viewport_width() {
+        let mut state = ViewState::new("/test/file.log", 10, 3);
+        // Two columns, then a two-column ZWJ family and three-byte accented letters
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let accented = "e\u{301}";
+        let row = format!("ab{}{}", family, accented.repeat(20));
+        let shown = format!("ab{}{}", family, accented.repeat(6));
+        let spans = vec![
+            (0, 2, HighlightKind::Search),
+            (shown.len() - 3, shown.len() + 3, HighlightKind::Filter),
+        ];
+        state.update_viewport_content(vec![row.clone()], vec![spans]);
+
+        // Graphemes stay whole: the last accent is kept with its letter
+        let lines = state.highlighted_lines();
+        assert_eq!(lines[0].text, shown);
+        assert_eq!(
+            lines[0].spans,
+            vec![
+                (0..2, HighlightKind::Search),
+                (shown.len() - 3..shown.len(), HighlightKind::Filter)
+            ]
+        );
+        assert_eq!(state.visible_lines[0], row);
+
+        // A wider viewport lays out the rest again from the untouched row
+        state.viewport_width = 30;
+        let lines = state.highlighted_lines();
+        assert_eq!(lines[0].text, row);
+        assert_eq!(
+            lines[0].spans[1],
+            (shown.len() - 3..shown.len() + 3, HighlightKind::Filter)
+        );
+    }
+
+    #[test]
+    fn test_short_terminals_keep_one_content_row() {
+        let mut state = ViewState::new("/test/file.log", 80, 2);
+        assert_eq!(state.lines_per_page(), 1);
+        assert!(state.shows_status_line());
+
+        // No room for both: the content row wins until a prompt needs the row
+        state.viewport_height = 1;
+        assert_eq!(state.lines_per_page(), 1);
+        assert!(!state.shows_status_line());
+        state
+            .status_line
+            .set_search_prompt(SearchDirection::Forward, PromptOptions::default());
+        assert!(state.shows_status_line());
+
+        state.viewport_height = 0;
+        assert_eq!(state.lines_per_page(), 1);
+    }
+
+    #[test]
+    fn test_terminal_resize() {
+        let path = PathBuf::from("/test/file.log");
+        let mut state = ViewState::new(path, 80, 24);
+
+        // Add some mock visible content
+        state.visible_lines = vec!["line1".to_string(), "line2".to_string()];
+        state.search_highlights = vec![vec![(0, 4, HighlightKind::Search)], vec![]]; // highlight "line" in first line
+
+        // Test resize to same dimensions - should return false
+        assert!(!state.update_terminal_size(80, 24));
+        assert_eq!(state.viewport_width, 80);
+        assert_eq!(state.viewport_height, 24);
+        // Content should remain unchanged
+        assert_eq!(state.visible_lines.len(), 2);
+        assert_eq!(state.search_highlights.len(), 2);
+
+        // Test resize to different dimensions - should return true
+        assert!(state.update_terminal_size(120, 30));
+        assert_eq!(state.viewport_width, 120);
+        assert_eq!(state.viewport_height, 30);
+        // Content should be cleared for recalculation
+        assert_eq!(state.visible_lines.len(), 0);
+        assert_eq!(state.search_highlights.len(), 0);
+        assert!(!state.at_eof); // EOF state should be reset
+
+        // Test width-only change
+        state.visible_lines = vec!["test".to_string()];
+        assert!(state.update_terminal_size(100, 30));
+        assert_eq!(state.visible_lines.len(), 0);
+
+        // Test height-only change
+        state.visible_lines = vec!["test".to_string()];
+        assert!(state.update_terminal_size(100, 25));
+        assert_eq!(state.visible_lines.len(), 0);
+    }
+}
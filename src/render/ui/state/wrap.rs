@@ -0,0 +1,367 @@
+//! Soft wrapping (`-S`).
+//!
+//! With wrapping on, each line of the page takes as many screen rows as its text needs and
+//! scrolling moves by screen rows, as in less. The worker still loads one line per screen row:
+//! since a line takes at least one row, that page always fills the screen, and the rows past the
+//! bottom are simply not drawn. `wrap_skip` counts the rows above the screen, so moving within the
+//! loaded page needs no new page. Moving past it asks the worker for the page starting at the
+//! line holding the target row; when the page arrives, `update_viewport_content` places the
+//! target row at the top (see `WrapTarget`).
+
+use super::text::{clip_to_width, wrap_ranges};
+use super::ViewState;
+use crate::render::protocol::ViewportRequest;
+use crate::render::ui::highlight::HighlightedLine;
+
+/// One screen row of the page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenRow<'a> {
+    /// Index of the line the row belongs to in `visible_lines`
+    pub line: usize,
+    /// The row shows the start of its line, rather than a continuation
+    pub first: bool,
+    /// The row shows the end of its line
+    pub last: bool,
+    pub text: HighlightedLine<'a>,
+}
+
+/// Where the top of the screen goes once a requested page arrives
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum WrapTarget {
+    /// Row `row` counted from the top of `page`, the lines shown when the page was requested;
+    /// the new page starts `lines` lines after (before, when negative) the top of `page`
+    Row {
+        page: Vec<String>,
+        row: i64,
+        lines: i64,
+    },
+    /// The start of the page
+    Top,
+    /// The rows scrolled past stay as they are: the same page is loaded again
+    Keep,
+    /// The last screenful of the page (`G`, following)
+    Bottom,
+}
+
+impl ViewState {
+    /// Wrap long lines instead of cutting them at the screen edge
+    /// Returns true if the setting actually changed
+    pub fn set_wrap(&mut self, wrap: bool) -> bool {
+        let changed = self.wrap != wrap;
+        if changed {
+            self.wrap = wrap;
+            self.wrap_skip = 0;
+            self.content_generation += 1;
+        }
+        changed
+    }
+
+    /// Rows of the page scrolled past the top of the screen
+    pub fn wrap_skip(&self) -> usize {
+        self.wrap_skip
+    }
+
+    /// The page as screen rows, top to bottom, at most a screenful
+    ///
+    /// Without wrapping every line is one row cut to the width left beside the gutter, so a long
+    /// row does not pay for laying out spans that are never drawn.
+    pub fn screen_rows(&self) -> Vec<ScreenRow<'_>> {
+        let width = usize::from(self.text_width());
+        let highlights = |index: usize| {
+            self.search_highlights
+                .get(index)
+                .map(Vec::as_slice)
+                .unwrap_or(&[])
+        };
+        if !self.wrap {
+            return self
+                .visible_lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| ScreenRow {
+                    line: index,
+                    first: true,
+                    last: true,
+                    text: HighlightedLine::new(clip_to_width(line, width), highlights(index)),
+                })
+                .collect();
+        }
+
+        let screen = usize::from(self.lines_per_page());
+        let mut rows = Vec::with_capacity(screen);
+        let mut skip = self.wrap_skip;
+        for (index, line) in self.visible_lines.iter().enumerate() {
+            let ranges = wrap_ranges(line, width);
+            if skip >= ranges.len() {
+                skip -= ranges.len();
+                continue;
+            }
+            let laid_out = HighlightedLine::new(line, highlights(index));
+            let count = ranges.len();
+            for (row, range) in ranges.into_iter().enumerate().skip(skip) {
+                if rows.len() == screen {
+                    return rows;
+                }
+                rows.push(ScreenRow {
+                    line: index,
+                    first: row == 0,
+                    last: row + 1 == count,
+                    text: laid_out.slice(range),
+                });
+            }
+            skip = 0;
+        }
+        rows
+    }
+
+    /// Move the top of the screen by `delta` rows with wrapping on
+    ///
+    /// Returns `None` when the loaded page holds the target row and the screen was moved, or the
+    /// number of lines the worker should move the page by; the row lands on top once that page
+    /// arrives.
+    pub fn scroll_rows(&mut self, delta: i64) -> Option<i64> {
+        let max_skip = self.max_wrap_skip();
+        let skip = self.wrap_skip as i64;
+        let target = skip + delta;
+        let local = if delta >= 0 {
+            (target <= max_skip as i64 || self.at_eof).then(|| (target as usize).min(max_skip))
+        } else {
+            (target >= 0 || self.viewport_top_byte == 0).then(|| target.max(0) as usize)
+        };
+        if let Some(skip) = local {
+            if skip != self.wrap_skip {
+                self.wrap_skip = skip;
+                self.content_generation += 1;
+            }
+            return None;
+        }
+
+        let lines = if delta > 0 {
+            // Whole lines above the target row; the next page starts at the line holding it
+            let mut above = 0;
+            let mut lines = 0;
+            for line in &self.visible_lines {
+                let rows = self.line_rows(line) as i64;
+                if above + rows > target {
+                    break;
+                }
+                above += rows;
+                lines += 1;
+            }
+            lines
+        } else {
+            // Every line takes at least a row, so this many lines hold the rows needed
+            target
+        };
+        self.wrap_target = Some(WrapTarget::Row {
+            page: self.visible_lines.clone(),
+            row: target,
+            lines,
+        });
+        Some(lines)
+    }
+
+    /// Note where the top of the screen goes when the page for `request` arrives
+    ///
+    /// A row target set by `scroll_rows` is kept for the load it asked for.
+    pub fn expect_page(&mut self, request: ViewportRequest) {
+        let target = match request {
+            ViewportRequest::RelativeLines { lines, .. } if matches!(&self.wrap_target, Some(WrapTarget::Row { lines: row_lines, .. }) if *row_lines == lines) => {
+                return
+            }
+            ViewportRequest::EndOfFile => WrapTarget::Bottom,
+            ViewportRequest::RelativeLines { lines: 0, .. } => WrapTarget::Keep,
+            ViewportRequest::Absolute(byte) if byte == self.viewport_top_byte => WrapTarget::Keep,
+            _ => WrapTarget::Top,
+        };
+        self.wrap_target = Some(target);
+    }
+
+    /// Apply the target noted for the page just loaded, see `WrapTarget`
+    pub(super) fn settle_wrap(&mut self) {
+        let skip = match self.wrap_target.take() {
+            _ if !self.wrap => 0,
+            Some(WrapTarget::Row { page, row, lines }) => self.row_in_page(&page, row, lines),
+            Some(WrapTarget::Top) => 0,
+            Some(WrapTarget::Keep) | None => self.wrap_skip,
+            Some(WrapTarget::Bottom) => usize::MAX,
+        };
+        self.wrap_skip = skip.min(self.max_wrap_skip());
+    }
+
+    /// Where `row` of `page` is in the loaded page, which was asked to start `lines` lines away
+    ///
+    /// Near either end of the file the worker moves the page less than asked: forward it stops at
+    /// the last full page, backward at the top. The page then overlaps the old one at another
+    /// offset, found by comparing their lines.
+    fn row_in_page(&self, page: &[String], row: i64, lines: i64) -> usize {
+        let new = &self.visible_lines;
+        let overlaps = |shift: usize, old: &[String], new: &[String]| {
+            let overlap = old.len().saturating_sub(shift).min(new.len());
+            overlap > 0 && old[shift..shift + overlap] == new[..overlap]
+        };
+        let rows_of = |lines: &[String]| -> i64 {
+            lines.iter().map(|line| self.line_rows(line) as i64).sum()
+        };
+        let row = if lines >= 0 {
+            let asked = lines as usize;
+            let moved = if self.at_eof {
+                (0..=asked.min(page.len()))
+                    .rev()
+                    .find(|&shift| overlaps(shift, page, new))
+                    .unwrap_or(asked)
+            } else {
+                asked
+            };
+            row - rows_of(&page[..moved.min(page.len())])
+        } else {
+            let asked = lines.unsigned_abs() as usize;
+            let moved = if self.viewport_top_byte == 0 {
+                (0..=asked.min(new.len()))
+                    .rev()
+                    .find(|&shift| overlaps(shift, new, page))
+                    .unwrap_or(asked)
+            } else {
+                asked
+            };
+            row + rows_of(&new[..moved.min(new.len())])
+        };
+        row.max(0) as usize
+    }
+
+    /// Most rows that can be scrolled past while still filling the screen
+    fn max_wrap_skip(&self) -> usize {
+        let rows: usize = self
+            .visible_lines
+            .iter()
+            .map(|line| self.line_rows(line))
+            .sum();
+        rows.saturating_sub(usize::from(self.lines_per_page()))
+    }
+
+    /// Screen rows `line` takes when wrapped
+    fn line_rows(&self, line: &str) -> usize {
+        wrap_ranges(line, usize::from(self.text_width())).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::protocol::HighlightKind;
+
+    /// Ten columns, four content rows under the status line
+    fn wrapped(lines: &[&str]) -> ViewState {
+        let mut view_state = ViewState::new("/test", 10, 5);
+        view_state.set_wrap(true);
+        view_state.update_viewport_content(
+            lines.iter().map(|line| line.to_string()).collect(),
+            Vec::new(),
+        );
+        view_state
+    }
+
+    fn texts(view_state: &ViewState) -> Vec<&str> {
+        view_state
+            .screen_rows()
+            .into_iter()
+            .map(|row| row.text.text)
+            .collect()
+    }
+
+    #[test]
+    fn long_lines_take_several_rows_and_highlights_follow() {
+        let mut view_state = ViewState::new("/test", 10, 5);
+        view_state.set_wrap(true);
+        let line = "0123456789abcdefghij error".to_string();
+        let at = line.find("error").unwrap();
+        view_state.update_viewport_content(
+            vec![line, "next".to_string(), "after".to_string()],
+            vec![vec![(at, at + 5, HighlightKind::Search)]],
+        );
+
+        let rows = view_state.screen_rows();
+        assert_eq!(
+            texts(&view_state),
+            ["0123456789", "abcdefghij", " error", "next"]
+        );
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.line, row.first, row.last))
+                .collect::<Vec<_>>(),
+            [
+                (0, true, false),
+                (0, false, false),
+                (0, false, true),
+                (1, true, true)
+            ]
+        );
+        assert_eq!(rows[2].text.spans, vec![(1..6, HighlightKind::Search)]);
+
+        // Chopped, the same page shows every line cut at the edge
+        view_state.set_wrap(false);
+        assert_eq!(texts(&view_state), ["0123456789", "next", "after"]);
+    }
+
+    #[test]
+    fn scrolling_moves_rows_within_the_page_and_asks_for_lines_past_it() {
+        let long = "x".repeat(25);
+        let mut view_state = wrapped(&[&long, "b", "c", "d"]);
+        // Six rows, four on screen: two can be scrolled past locally
+        assert_eq!(view_state.scroll_rows(1), None);
+        assert_eq!(texts(&view_state)[0], "x".repeat(10));
+        assert_eq!(view_state.scroll_rows(1), None);
+        assert_eq!(texts(&view_state), ["xxxxx", "b", "c", "d"]);
+        assert_eq!(view_state.scroll_rows(-2), None);
+        assert_eq!(view_state.wrap_skip(), 0);
+
+        // Row 4 is past the page's last screenful; the next page starts at line `c`
+        let page = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect();
+        assert_eq!(view_state.scroll_rows(4), Some(2));
+        view_state.navigate_to_byte(28);
+        view_state.update_viewport_content(page(&["c", "d", "e", "f"]), Vec::new());
+        assert_eq!(texts(&view_state), ["c", "d", "e", "f"]);
+
+        // Back up two rows: two lines are asked for and the screen starts at `b`
+        assert_eq!(view_state.scroll_rows(-2), Some(-2));
+        view_state.navigate_to_byte(0);
+        view_state.update_viewport_content(page(&[&long, "b", "c", "d"]), Vec::new());
+        // At the top of the file the overlap with the old page tells how far the page moved
+        assert_eq!(texts(&view_state), ["xxxxx", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn a_clamped_last_page_keeps_the_target_row_in_place() {
+        let long = "y".repeat(30);
+        let mut view_state = wrapped(&["a", "b", "c", &long]);
+        // a, b, c and three rows of the long line: two rows can be scrolled past locally
+        assert_eq!(view_state.scroll_rows(2), None);
+        assert_eq!(view_state.wrap_skip(), 2);
+        assert_eq!(view_state.scroll_rows(2), Some(3));
+
+        // The file ends after `z`: the last full page starts at `b`, not at the long line
+        view_state.navigate_to_byte(2);
+        view_state.at_eof = true;
+        view_state.update_viewport_content(
+            ["b", "c", long.as_str(), "z"].map(String::from).to_vec(),
+            Vec::new(),
+        );
+        assert_eq!(
+            texts(&view_state),
+            [
+                "y".repeat(10),
+                "y".repeat(10),
+                "y".repeat(10),
+                "z".to_string()
+            ]
+        );
+
+        // `G` shows the last screenful of the page
+        view_state.expect_page(ViewportRequest::EndOfFile);
+        view_state.update_viewport_content(
+            ["b", "c", long.as_str(), "z"].map(String::from).to_vec(),
+            Vec::new(),
+        );
+        assert_eq!(view_state.wrap_skip(), 2);
+    }
+}
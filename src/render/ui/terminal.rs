@@ -5,23 +5,28 @@
 //! and SearchEngine components rather than managing data itself.
 
 use crate::error::Result;
+use crate::render::protocol::HighlightKind;
+use crate::render::ui::highlight::HighlightedLine;
+use crate::render::ui::line_cache::LineCache;
 use crate::render::ui::renderer::UIRenderer;
-use crate::render::ui::state::ViewState;
+use crate::render::ui::state::{LinePeek, MatchList, ViewState};
 use crate::render::ui::theme::ColorTheme;
 use ratatui::crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
+    },
 };
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::Style,
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::Paragraph,
+    widgets::{Block, Borders, Clear, Paragraph},
     Frame, Terminal,
 };
-use std::io::{self, Stdout};
+use std::io::{self, Stdout, Write};
 
 type CrosstermTerminal = Terminal<CrosstermBackend<Stdout>>;
 
@@ -32,6 +37,11 @@ type CrosstermTerminal = Terminal<CrosstermBackend<Stdout>>;
 pub struct TerminalUI {
     terminal: Option<CrosstermTerminal>,
     theme: ColorTheme,
+    lines: LineCache,
+    /// Keep the window title naming the file, filter and search (`--title`)
+    window_title: bool,
+    /// Title last sent to the terminal
+    shown_title: Option<String>,
 }
 
 impl TerminalUI {
@@ -40,6 +50,9 @@ impl TerminalUI {
         Ok(Self {
             terminal: None,
             theme: ColorTheme::default(),
+            lines: LineCache::default(),
+            window_title: false,
+            shown_title: None,
         })
     }
 
@@ -48,73 +61,262 @@ impl TerminalUI {
         Ok(Self {
             terminal: None,
             theme,
+            lines: LineCache::default(),
+            window_title: false,
+            shown_title: None,
         })
     }
 
+    /// Set the terminal window title from `ViewState::window_title` while running
+    pub fn with_window_title(mut self, enabled: bool) -> Self {
+        self.window_title = enabled;
+        self
+    }
+
+    /// Title to send to the terminal before drawing `view_state`, when it changed
+    fn title_update(&mut self, view_state: &ViewState) -> Option<String> {
+        if !self.window_title {
+            return None;
+        }
+        let title = view_state.window_title();
+        if self.shown_title.as_ref() == Some(&title) {
+            return None;
+        }
+        self.shown_title = Some(title.clone());
+        Some(title)
+    }
+
     /// Render content area with search highlights (helper for closure)
     fn render_content_with_data(
         frame: &mut Frame,
         area: Rect,
         view_state: &ViewState,
         theme: &ColorTheme,
+        lines: &mut LineCache,
     ) {
-        let content_lines: Vec<Line> = view_state
-            .visible_lines
+        let paragraph = Paragraph::new(lines.lines(view_state, theme));
+        frame.render_widget(paragraph, area);
+    }
+
+    /// Styled viewport lines, with a `[noeol]` marker after an unterminated final line
+    ///
+    /// With line numbers or bookmarks, each line starts with its gutter label. The label is a span of its
+    /// own, so the highlight spans, which are offsets into the line's text, need no shifting.
+    pub fn content_lines<'a>(view_state: &'a ViewState, theme: &ColorTheme) -> Vec<Line<'a>> {
+        let gutter_style = theme
+            .line_numbers
+            .map_or_else(Style::default, |color| Style::default().fg(color));
+        let rows = view_state.screen_rows();
+        // The marker goes after the end of the last line, once that is on screen
+        let ends_page = rows
+            .last()
+            .is_some_and(|row| row.last && row.line + 1 == view_state.viewport_line_count());
+        // The first search match on the line a search jumped to is the one it landed on
+        let mut current_pending = false;
+        let mut content_lines: Vec<Line> = rows
             .iter()
-            .enumerate()
-            .map(|(viewport_line_idx, line)| {
-                // Get search highlights for this viewport-relative line (if any)
-                let highlights = view_state
-                    .search_highlights
-                    .get(viewport_line_idx)
-                    .map(|ranges| ranges.as_slice())
-                    .unwrap_or(&[]);
-
-                if highlights.is_empty() {
-                    Line::from(line.as_str())
-                } else {
-                    Self::create_highlighted_line_with_theme(line.as_str(), highlights, theme)
+            .map(|row| {
+                let on_match_line = view_state.is_match_line(row.line);
+                if row.first {
+                    current_pending = on_match_line;
+                }
+                let mut styled = Self::styled_line(&row.text, theme, &mut current_pending);
+                if on_match_line {
+                    // Under the highlights, so the matches keep their own colors
+                    for span in &mut styled.spans {
+                        span.style = theme.match_line.patch(span.style);
+                    }
                 }
+                if view_state.gutter_width() > 0 {
+                    // Continuation rows of a wrapped line leave the gutter blank
+                    let label = if row.first {
+                        view_state.gutter_label(row.line)
+                    } else {
+                        " ".repeat(usize::from(view_state.gutter_width()))
+                    };
+                    styled.spans.insert(0, Span::styled(label, gutter_style));
+                }
+                styled
             })
             .collect();
 
-        let paragraph = Paragraph::new(content_lines);
-        frame.render_widget(paragraph, area);
+        if view_state.noeol && ends_page {
+            if let Some(last) = content_lines.last_mut() {
+                last.spans
+                    .push(Span::styled(" [noeol]", theme.noeol_marker));
+            }
+        }
+        content_lines
     }
 
-    /// Create a line with search highlights applied using theme colors (helper for closure)
-    fn create_highlighted_line_with_theme<'a>(
-        content: &'a str,
-        highlights: &[(usize, usize)],
+    /// Style each segment of a laid-out line with the theme color for its highlight kind
+    ///
+    /// While `current` is set, the next search match is drawn as the current match and clears it.
+    fn styled_line<'a>(
+        line: &HighlightedLine<'a>,
         theme: &ColorTheme,
+        current: &mut bool,
     ) -> Line<'a> {
-        if highlights.is_empty() {
-            return Line::from(content);
+        if line.spans.is_empty() {
+            return Line::from(line.text);
         }
 
-        let mut spans = Vec::new();
-        let mut last_end = 0;
+        let spans: Vec<Span> = line
+            .segments()
+            .into_iter()
+            .map(|(text, kind)| match kind {
+                Some(HighlightKind::Search) if std::mem::take(current) => {
+                    Span::styled(text, theme.current_match)
+                }
+                Some(HighlightKind::Search) => Span::styled(text, theme.search_match),
+                Some(HighlightKind::Kept1) => Span::styled(text, theme.kept_match[0]),
+                Some(HighlightKind::Kept2) => Span::styled(text, theme.kept_match[1]),
+                Some(HighlightKind::Filter) => Span::styled(text, theme.filter_match),
+                None => Span::raw(text),
+            })
+            .collect();
+        Line::from(spans)
+    }
 
-        for &(start, end) in highlights {
-            // Add normal text before highlight
-            if start > last_end {
-                spans.push(Span::raw(&content[last_end..start]));
-            }
+    /// Lay out and draw one frame: content plus, unless hidden, the status line below it
+    ///
+    /// The content rows come from `lines`, reused from the previous frame when the page is
+    /// unchanged.
+    pub fn draw_frame(
+        frame: &mut Frame,
+        view_state: &ViewState,
+        theme: &ColorTheme,
+        lines: &mut LineCache,
+    ) {
+        let size = frame.size();
+        let content_area = if view_state.shows_status_line() {
+            // Split screen: content area and status line
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+                .split(size);
+
+            // Render status line
+            Self::render_status_with_data(frame, chunks[1], view_state, theme);
+            chunks[0]
+        } else {
+            // Presentation mode: the content gets every row
+            size
+        };
+
+        // Render content area - highlights are now in view_state
+        Self::render_content_with_data(frame, content_area, view_state, theme, lines);
+
+        if let Some(peek) = &view_state.peek {
+            Self::render_peek(frame, content_area, peek, theme);
+        }
+        if let Some(list) = &view_state.match_list {
+            Self::render_match_list(frame, content_area, list, view_state.file_size, theme);
+        }
+    }
 
-            // Add highlighted text using theme style directly
-            if end > start && end <= content.len() {
-                spans.push(Span::styled(&content[start..end], theme.search_match));
+    /// Draw the match list inside a bordered panel covering the content area
+    ///
+    /// Each row gives how far into the file the line is and the start of its text; the
+    /// selected row is drawn in the selection style.
+    fn render_match_list(
+        frame: &mut Frame,
+        area: Rect,
+        list: &MatchList,
+        file_size: Option<u64>,
+        theme: &ColorTheme,
+    ) {
+        let mut title = match &list.message {
+            Some(_) if list.entries.is_empty() => format!(" {} ", list.title),
+            _ => format!(" {} ({}) ", list.title, list.count_label()),
+        };
+        if list.loading {
+            title.push_str("searching… ");
+        }
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let height = usize::from(block.inner(area).height);
+
+        let rows: Vec<Line> = match &list.message {
+            Some(message) => vec![Line::raw(message.as_str())],
+            None => {
+                // A resize can leave the selection below the panel; keep it in view
+                let scroll = list
+                    .scroll
+                    .max((list.selected + 1).saturating_sub(height))
+                    .min(list.selected);
+                list.entries
+                    .iter()
+                    .enumerate()
+                    .skip(scroll)
+                    .take(height)
+                    .map(|(index, (byte, text))| {
+                        let position = match file_size {
+                            Some(size) if size > 0 => format!("{:>3}%  ", byte * 100 / size),
+                            _ => String::new(),
+                        };
+                        let row = Line::raw(format!("{}{}", position, text));
+                        if index == list.selected {
+                            row.style(theme.selection)
+                        } else {
+                            row
+                        }
+                    })
+                    .collect()
             }
+        };
 
-            last_end = end;
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(rows).block(block), area);
+    }
+
+    /// Draw the peeked line wrapped inside a bordered overlay covering the content area
+    fn render_peek(frame: &mut Frame, area: Rect, peek: &LinePeek, theme: &ColorTheme) {
+        let mut title = match (&peek.title, peek.start) {
+            (Some(title), _) => format!(" {} ", title),
+            (None, Some(start)) => format!(" line at byte {} ", start),
+            (None, None) => " loading line… ".to_string(),
+        };
+        if peek.truncated {
+            title.push_str("[truncated] ");
         }
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let inner = block.inner(area);
+
+        let rows = Self::wrap_peek(peek, usize::from(inner.width), theme);
+        let height = usize::from(inner.height);
+        // A resize can leave the scroll offset past the end; keep the last page in view
+        let scroll = peek.scroll.min(rows.len().saturating_sub(height));
+        let visible: Vec<Line> = rows.into_iter().skip(scroll).take(height).collect();
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(visible).block(block), area);
+    }
 
-        // Add remaining normal text
-        if last_end < content.len() {
-            spans.push(Span::raw(&content[last_end..]));
+    /// Split the peeked text into rows of `width` characters, carrying highlights across rows
+    ///
+    /// Each line of the text starts a new row.
+    fn wrap_peek<'a>(peek: &'a LinePeek, width: usize, theme: &ColorTheme) -> Vec<Line<'a>> {
+        let text = peek.text.as_str();
+        let mut rows = Vec::new();
+        let mut line_start = 0;
+        for line in text.split('\n') {
+            let mut bounds: Vec<usize> = line
+                .char_indices()
+                .map(|(index, _)| line_start + index)
+                .step_by(width.max(1))
+                .collect();
+            if bounds.is_empty() {
+                bounds.push(line_start);
+            }
+            bounds.push(line_start + line.len());
+            rows.extend(bounds.windows(2).map(|row| (row[0], row[1])));
+            line_start += line.len() + 1;
         }
 
-        Line::from(spans)
+        let line = HighlightedLine::new(text, &peek.highlights);
+        rows.into_iter()
+            .map(|(start, end)| Self::styled_line(&line.slice(start..end), theme, &mut false))
+            .collect()
     }
 
     /// Render status line using theme colors (helper for closure)
@@ -125,36 +327,45 @@ impl TerminalUI {
         theme: &ColorTheme,
     ) {
         let status_text = view_state.format_status_line();
+        let hint = view_state.status_hint(&status_text);
 
         // Use theme colors for status line directly
-        let status_style = Style::default().bg(theme.status_bg).fg(theme.status_fg);
+        let mut status_style = Style::default().bg(theme.status_bg).fg(theme.status_fg);
+        if view_state.status_line.flash {
+            status_style = status_style.add_modifier(Modifier::REVERSED);
+        }
 
         let status = Paragraph::new(status_text).style(status_style);
         frame.render_widget(status, area);
+
+        if let Some(hint) = hint {
+            // `status_hint` only returns text that fits, so this stays inside `area`
+            let width = hint.chars().count() as u16;
+            let hint_area = Rect {
+                x: area.right() - width,
+                width,
+                ..area
+            };
+            let hint = Paragraph::new(hint).style(status_style.add_modifier(Modifier::DIM));
+            frame.render_widget(hint, hint_area);
+        }
     }
 }
 
 impl UIRenderer for TerminalUI {
     fn render(&mut self, view_state: &ViewState) -> Result<()> {
+        if self.terminal.is_none() {
+            return Ok(());
+        }
+        if let Some(title) = self.title_update(view_state) {
+            execute!(io::stdout(), SetTitle(title))?;
+        }
         if let Some(ref mut terminal) = self.terminal {
-            // Extract theme before closure to avoid borrowing issues
+            // Extract theme and cache before closure to avoid borrowing issues
             let theme = &self.theme;
+            let lines = &mut self.lines;
 
-            terminal.draw(move |frame| {
-                let size = frame.size();
-
-                // Split screen: content area and status line
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
-                    .split(size);
-
-                // Render content area - highlights are now in view_state
-                Self::render_content_with_data(frame, chunks[0], view_state, theme);
-
-                // Render status line
-                Self::render_status_with_data(frame, chunks[1], view_state, theme);
-            })?;
+            terminal.draw(move |frame| Self::draw_frame(frame, view_state, theme, lines))?;
         }
         Ok(())
     }
@@ -184,6 +395,15 @@ impl UIRenderer for TerminalUI {
         let (cols, rows) = ratatui::crossterm::terminal::size()?;
         Ok((cols, rows))
     }
+
+    fn bell(&mut self) -> Result<()> {
+        if self.terminal.is_some() {
+            let mut stdout = io::stdout();
+            stdout.write_all(b"\x07")?;
+            stdout.flush()?;
+        }
+        Ok(())
+    }
 }
 
 impl Drop for TerminalUI {
@@ -195,6 +415,8 @@ impl Drop for TerminalUI {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::input::{ScrollDirection, SearchDirection};
+    use crate::render::ui::state::ChromeMode;
     use ratatui::style::Color;
 
     #[test]
@@ -210,6 +432,36 @@ mod tests {
         assert!(ui_with_theme.is_ok());
     }
 
+    #[test]
+    fn test_window_title_is_sent_only_when_it_changes() {
+        let mut view_state = ViewState::new("/var/log/app.log", 80, 24);
+        let mut ui = TerminalUI::new().unwrap();
+        assert_eq!(ui.title_update(&view_state), None);
+
+        let mut ui = ui.with_window_title(true);
+        assert_eq!(
+            ui.title_update(&view_state).as_deref(),
+            Some("rlless — app.log")
+        );
+        assert_eq!(ui.title_update(&view_state), None);
+
+        view_state.status_line.filter = Some("payment".to_string());
+        view_state
+            .status_line
+            .set_active_pattern(SearchDirection::Forward, "timeout");
+        assert_eq!(
+            ui.title_update(&view_state).as_deref(),
+            Some("rlless — app.log — &payment /timeout")
+        );
+
+        // Clearing the search is a change too
+        view_state.status_line.active_pattern = None;
+        assert_eq!(
+            ui.title_update(&view_state).as_deref(),
+            Some("rlless — app.log — &payment")
+        );
+    }
+
     #[test]
     fn test_theme_integration() {
         let ui = TerminalUI::new().unwrap();
@@ -224,4 +476,360 @@ mod tests {
         assert_eq!(ui_with_theme.theme.status_fg, Color::White);
         assert_eq!(ui_with_theme.theme.status_bg, Color::Black);
     }
+
+    #[test]
+    fn test_filter_and_search_spans_use_distinct_styles() {
+        let theme = ColorTheme::default();
+        // "error: disk full" filtered by "error.*full" and searched for "disk"
+        let highlights = [
+            (0, 7, HighlightKind::Filter),
+            (7, 11, HighlightKind::Search),
+            (11, 16, HighlightKind::Filter),
+        ];
+
+        let line = TerminalUI::styled_line(
+            &HighlightedLine::new("error: disk full", &highlights),
+            &theme,
+            &mut false,
+        );
+
+        let rendered: Vec<(&str, Style)> = line
+            .spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style))
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                ("error: ", theme.filter_match),
+                ("disk", theme.search_match),
+                (" full", theme.filter_match),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kept_pattern_spans_use_their_slot_styles() {
+        let theme = ColorTheme::default();
+        let highlights = [
+            (0, 5, HighlightKind::Kept2),
+            (6, 11, HighlightKind::Kept1),
+            (12, 16, HighlightKind::Search),
+        ];
+
+        let line = TerminalUI::styled_line(
+            &HighlightedLine::new("ERROR req-7 slow", &highlights),
+            &theme,
+            &mut false,
+        );
+
+        let styles: Vec<Style> = line.spans.iter().map(|span| span.style).collect();
+        assert_eq!(
+            styles,
+            vec![
+                theme.kept_match[1],
+                Style::default(),
+                theme.kept_match[0],
+                Style::default(),
+                theme.search_match,
+            ]
+        );
+    }
+
+    fn draw_rows(view_state: &ViewState) -> Vec<String> {
+        let backend = ratatui::backend::TestBackend::new(
+            view_state.viewport_width,
+            view_state.viewport_height,
+        );
+        let mut terminal = Terminal::new(backend).unwrap();
+        let theme = ColorTheme::default();
+        terminal
+            .draw(|frame| {
+                TerminalUI::draw_frame(frame, view_state, &theme, &mut LineCache::default())
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_presentation_mode_draws_content_on_every_row() {
+        let mut view_state = ViewState::new("/test/app.log", 20, 4);
+        let page = |rows: u16| (1..=rows).map(|i| format!("line {i}")).collect::<Vec<_>>();
+        view_state.update_viewport_content(page(view_state.lines_per_page()), Vec::new());
+        view_state.status_line.set_message("transient".to_string());
+
+        let rows = draw_rows(&view_state);
+        assert_eq!(rows[2], "line 3");
+        assert!(rows[3].starts_with("app.log | "));
+
+        view_state.set_chrome(ChromeMode::Presentation);
+        view_state.update_viewport_content(page(view_state.lines_per_page()), Vec::new());
+        let rows = draw_rows(&view_state);
+        assert_eq!(rows, vec!["line 1", "line 2", "line 3", "line 4"]);
+
+        // Typing a search brings the prompt back over the last row
+        view_state
+            .status_line
+            .set_search_prompt(SearchDirection::Forward, Default::default());
+        let rows = draw_rows(&view_state);
+        assert_eq!(rows[3], "/");
+    }
+
+    #[test]
+    fn test_long_prompt_stays_on_the_status_row() {
+        for width in [40, 200] {
+            let mut view_state = ViewState::new("/test/app.log", width, 3);
+            view_state.update_viewport_content(vec!["line 1".into(), "line 2".into()], Vec::new());
+            view_state.status_line.update_search_prompt(
+                SearchDirection::Forward,
+                format!("{}\0tail", "(a|b)".repeat(400)),
+                Default::default(),
+            );
+
+            let rows = draw_rows(&view_state);
+            assert_eq!(rows[..2], ["line 1", "line 2"]);
+            assert!(rows[2].starts_with("/…"));
+            assert!(rows[2].ends_with("(a|b)tail"));
+            assert_eq!(rows[2].chars().count(), width as usize);
+        }
+    }
+
+    #[test]
+    fn test_one_row_terminal_shows_content_instead_of_status() {
+        let mut view_state = ViewState::new("/test/app.log", 20, 2);
+        view_state.update_viewport_content(vec!["line 1".to_string()], Vec::new());
+        let rows = draw_rows(&view_state);
+        assert_eq!(rows[0], "line 1");
+        assert!(rows[1].starts_with("app.log | "));
+
+        view_state.viewport_height = 1;
+        assert_eq!(draw_rows(&view_state), vec!["line 1"]);
+        view_state
+            .status_line
+            .set_search_prompt(SearchDirection::Forward, Default::default());
+        assert_eq!(draw_rows(&view_state), vec!["/"]);
+    }
+
+    #[test]
+    fn test_active_pattern_is_drawn_dim_at_the_right_of_the_status_line() {
+        let mut view_state = ViewState::new("/test/app.log", 30, 3);
+        view_state.show_active_pattern = true;
+        view_state
+            .status_line
+            .set_active_pattern(SearchDirection::Backward, "timeout");
+
+        let rows = draw_rows(&view_state);
+        assert_eq!(rows[2], format!("{:<22}?timeout", "app.log | Empty"));
+        let backend = ratatui::backend::TestBackend::new(30, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let theme = ColorTheme::default();
+        terminal
+            .draw(|frame| {
+                TerminalUI::draw_frame(frame, &view_state, &theme, &mut LineCache::default())
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        assert!(buffer.get(29, 2).modifier.contains(Modifier::DIM));
+        assert!(!buffer.get(0, 2).modifier.contains(Modifier::DIM));
+
+        // A message takes the space back
+        view_state
+            .status_line
+            .set_message("Pattern not found".to_string());
+        let rows = draw_rows(&view_state);
+        assert_eq!(rows[2], "app.log | Empty | Pattern not");
+    }
+
+    #[test]
+    fn test_flash_reverses_the_status_line() {
+        let mut view_state = ViewState::new("/test/app.log", 30, 3);
+        let status_modifier = |view_state: &ViewState| {
+            let backend = ratatui::backend::TestBackend::new(30, 3);
+            let mut terminal = Terminal::new(backend).unwrap();
+            let theme = ColorTheme::default();
+            terminal
+                .draw(|frame| {
+                    TerminalUI::draw_frame(frame, view_state, &theme, &mut LineCache::default())
+                })
+                .unwrap();
+            terminal.backend().buffer().get(0, 2).modifier
+        };
+        assert!(!status_modifier(&view_state).contains(Modifier::REVERSED));
+        view_state.status_line.flash = true;
+        assert!(status_modifier(&view_state).contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn test_match_list_marks_the_selected_row() {
+        let mut view_state = ViewState::new("/test/app.log", 30, 6);
+        view_state.file_size = Some(1000);
+        view_state.match_list = Some(MatchList {
+            title: "matches of /err".to_string(),
+            entries: vec![(0, "err one".to_string()), (500, "err two".to_string())],
+            selected: 1,
+            more_from: Some(600),
+            ..MatchList::default()
+        });
+
+        let rows = draw_rows(&view_state);
+        assert!(rows[0].contains(" matches of /err (2+) "), "{}", rows[0]);
+        assert_eq!(rows[1], format!("│{:<28}│", "  0%  err one"));
+        assert_eq!(rows[2], format!("│{:<28}│", " 50%  err two"));
+
+        let backend = ratatui::backend::TestBackend::new(30, 6);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let theme = ColorTheme::default();
+        terminal
+            .draw(|frame| {
+                TerminalUI::draw_frame(frame, &view_state, &theme, &mut LineCache::default())
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        assert_eq!(buffer.get(1, 2).bg, theme.selection.bg.unwrap());
+        assert_ne!(buffer.get(1, 1).bg, theme.selection.bg.unwrap());
+
+        view_state.match_list.as_mut().unwrap().message = Some("Pattern not found".to_string());
+        view_state.match_list.as_mut().unwrap().entries.clear();
+        let rows = draw_rows(&view_state);
+        assert!(rows[0].contains(" matches of /err "), "{}", rows[0]);
+        assert_eq!(rows[1], format!("│{:<28}│", "Pattern not found"));
+    }
+
+    #[test]
+    fn test_peek_overlay_wraps_line_with_highlights() {
+        let theme = ColorTheme::default();
+        let mut view_state = ViewState::new("/test/app.log", 12, 5);
+        view_state.update_viewport_content(vec!["short".to_string()], Vec::new());
+        // 10 columns inside the border: "0123456789" / "abcdefghij" / "XY"
+        view_state.peek = Some(LinePeek {
+            start: Some(42),
+            text: "0123456789abcdefghijXY".to_string(),
+            highlights: vec![(8, 12, HighlightKind::Search)],
+            ..LinePeek::default()
+        });
+
+        let rows = draw_rows(&view_state);
+        assert!(rows[0].starts_with("┌ line at"));
+        assert_eq!(rows[1], "│0123456789│");
+        assert_eq!(rows[2], "│abcdefghij│");
+        assert_eq!(rows[3], "└──────────┘");
+
+        // The search match "89ab" is styled on both sides of the wrap
+        let peek = view_state.peek.as_ref().unwrap();
+        let wrapped = TerminalUI::wrap_peek(peek, 10, &theme);
+        assert_eq!(wrapped.len(), 3);
+        assert_eq!(wrapped[0].spans[1].content.as_ref(), "89");
+        assert_eq!(wrapped[0].spans[1].style, theme.search_match);
+        assert_eq!(wrapped[1].spans[0].content.as_ref(), "ab");
+        assert_eq!(wrapped[1].spans[0].style, theme.search_match);
+
+        view_state.scroll_peek(ScrollDirection::Down, 5);
+        let rows = draw_rows(&view_state);
+        assert_eq!(rows[1], "│abcdefghij│");
+        assert_eq!(rows[2], "│XY        │");
+    }
+
+    #[test]
+    fn test_gutter_is_drawn_before_the_text_and_its_highlights() {
+        let theme = ColorTheme::default();
+        let mut view_state = ViewState::new("/test/app.log", 16, 3);
+        view_state.update_viewport_content(
+            vec!["error: disk full".to_string(), "ok".to_string()],
+            vec![vec![(7, 11, HighlightKind::Search)], Vec::new()],
+        );
+        view_state.set_line_numbers(true);
+        view_state.set_top_line(Some(9));
+
+        let rows = draw_rows(&view_state);
+        assert_eq!(rows[0], "   9 error: disk");
+        assert_eq!(rows[1], "  10 ok");
+
+        let lines = TerminalUI::content_lines(&view_state, &theme);
+        let spans: Vec<(&str, Style)> = lines[0]
+            .spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style))
+            .collect();
+        let gutter = Style::default().fg(theme.line_numbers.unwrap());
+        assert_eq!(
+            spans,
+            vec![
+                ("   9 ", gutter),
+                ("error: ", Style::default()),
+                ("disk", theme.search_match),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_line_is_styled_under_its_highlights() {
+        let theme = ColorTheme::default();
+        let mut view_state = ViewState::new("/test/app.log", 40, 3);
+        let disk = |line: &str| {
+            let start = line.find("disk").unwrap();
+            (start, start + 4, HighlightKind::Search)
+        };
+        let lines = ["disk ok", "error: disk, disk"];
+        view_state.update_viewport_content(
+            lines.map(str::to_string).to_vec(),
+            vec![
+                vec![disk(lines[0])],
+                vec![disk(lines[1]), (13, 17, HighlightKind::Search)],
+            ],
+        );
+        view_state.set_match_row(Some(1));
+
+        let spans = |view_state: &ViewState, row: usize| -> Vec<(String, Style)> {
+            TerminalUI::content_lines(view_state, &theme)[row]
+                .spans
+                .iter()
+                .map(|span| (span.content.to_string(), span.style))
+                .collect()
+        };
+        assert_eq!(spans(&view_state, 0)[0].1, theme.search_match);
+        // Only the first match of the line is the current one
+        let on_line = |style: Style| theme.match_line.patch(style);
+        assert_eq!(
+            spans(&view_state, 1),
+            vec![
+                ("error: ".to_string(), on_line(Style::default())),
+                ("disk".to_string(), on_line(theme.current_match)),
+                (", ".to_string(), on_line(Style::default())),
+                ("disk".to_string(), on_line(theme.search_match)),
+            ]
+        );
+
+        // Once the line scrolls out, nothing is marked
+        view_state.set_match_row(None);
+        assert_eq!(spans(&view_state, 1)[1].1, theme.search_match);
+    }
+
+    #[test]
+    fn test_noeol_marker_follows_unterminated_last_line() {
+        let theme = ColorTheme::default();
+        let mut view_state = ViewState::new("/test", 80, 24);
+        view_state.update_viewport_content(
+            vec!["first".to_string(), "last".to_string()],
+            vec![Vec::new(), Vec::new()],
+        );
+
+        let lines = TerminalUI::content_lines(&view_state, &theme);
+        assert_eq!(lines[1].spans.len(), 1);
+
+        view_state.set_noeol(true);
+        let lines = TerminalUI::content_lines(&view_state, &theme);
+        assert_eq!(lines[0].spans.len(), 1);
+        let marker = lines[1].spans.last().unwrap();
+        assert_eq!(marker.content.as_ref(), " [noeol]");
+        assert_eq!(marker.style, theme.noeol_marker);
+    }
 }
@@ -3,10 +3,11 @@
 //! This module provides color themes for terminal rendering using ratatui's
 //! color system directly to avoid unnecessary abstractions.
 
-use ratatui::style::{Color, Style};
+use crate::render::protocol::MAX_KEPT_PATTERNS;
+use ratatui::style::{Color, Modifier, Style};
 
 /// Color theme for terminal UI elements
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ColorTheme {
     /// Normal text color (None uses terminal default)
     pub normal_text: Option<Color>,
@@ -14,9 +15,21 @@ pub struct ColorTheme {
     /// Search match highlighting
     pub search_match: Style,
 
+    /// Matches of the patterns kept with `:keep`, most recently kept first
+    pub kept_match: [Style; MAX_KEPT_PATTERNS],
+
+    /// Filter pattern matches (secondary to search matches)
+    pub filter_match: Style,
+
+    /// Marker after a final line that lacks a trailing newline
+    pub noeol_marker: Style,
+
     /// Current/focused search match
     pub current_match: Style,
 
+    /// Whole line a search jumped to, under its highlights
+    pub match_line: Style,
+
     /// Status line background
     pub status_bg: Color,
 
@@ -39,7 +52,14 @@ impl Default for ColorTheme {
         Self {
             normal_text: None, // Use terminal default
             search_match: Style::default().fg(Color::Black).bg(Color::Yellow),
+            kept_match: [
+                Style::default().fg(Color::Black).bg(Color::LightCyan),
+                Style::default().fg(Color::Black).bg(Color::LightMagenta),
+            ],
+            filter_match: Style::default().bg(Color::DarkGray),
+            noeol_marker: Style::default().fg(Color::DarkGray),
             current_match: Style::default().fg(Color::Black).bg(Color::LightYellow),
+            match_line: Style::default().add_modifier(Modifier::UNDERLINED),
             status_bg: Color::Blue,
             status_fg: Color::White,
             line_numbers: Some(Color::DarkGray),
@@ -55,7 +75,14 @@ impl ColorTheme {
         Self {
             normal_text: None,
             search_match: Style::default().fg(Color::Black).bg(Color::White),
+            kept_match: [
+                Style::default().add_modifier(Modifier::BOLD),
+                Style::default().add_modifier(Modifier::ITALIC),
+            ],
+            filter_match: Style::default().add_modifier(Modifier::UNDERLINED),
+            noeol_marker: Style::default().add_modifier(Modifier::DIM),
             current_match: Style::default().fg(Color::White).bg(Color::Black),
+            match_line: Style::default().add_modifier(Modifier::BOLD),
             status_bg: Color::Black,
             status_fg: Color::White,
             line_numbers: None,
@@ -69,7 +96,16 @@ impl ColorTheme {
         Self {
             normal_text: Some(Color::White),
             search_match: Style::default().fg(Color::Black).bg(Color::LightYellow),
+            kept_match: [
+                Style::default().fg(Color::Black).bg(Color::LightGreen),
+                Style::default().fg(Color::Black).bg(Color::LightMagenta),
+            ],
+            filter_match: Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::UNDERLINED),
+            noeol_marker: Style::default().fg(Color::Gray),
             current_match: Style::default().fg(Color::LightYellow).bg(Color::Black),
+            match_line: Style::default().add_modifier(Modifier::UNDERLINED),
             status_bg: Color::White,
             status_fg: Color::Black,
             line_numbers: Some(Color::LightGreen),
@@ -93,6 +129,14 @@ mod tests {
         // Test search match style
         assert_eq!(theme.search_match.fg, Some(Color::Black));
         assert_eq!(theme.search_match.bg, Some(Color::Yellow));
+
+        // Filter matches stay dimmer than search matches
+        assert_eq!(theme.filter_match.bg, Some(Color::DarkGray));
+        assert_ne!(theme.filter_match, theme.search_match);
+
+        // Each kept pattern has a color of its own
+        assert_ne!(theme.kept_match[0], theme.kept_match[1]);
+        assert!(!theme.kept_match.contains(&theme.search_match));
     }
 
     #[test]
@@ -1,5 +1,22 @@
 pub mod core;
+pub mod duplicates;
+pub mod filter;
+pub mod frequency;
+pub mod highlight_memo;
+pub mod line_spans;
+pub mod memory_budget;
+pub mod modifiers;
+pub mod pattern_set;
+pub mod patterns;
+pub mod peek;
+pub mod timestamps;
 pub mod worker;
 
-pub use core::{RipgrepEngine, SearchEngine, SearchOptions};
+pub use core::{
+    check_regex, verbatim_pattern, RipgrepEngine, SearchEngine, SearchOptions, MAX_PATTERN_BYTES,
+};
+pub use memory_budget::MemoryBudget;
+pub use modifiers::PatternModifiers;
+pub use pattern_set::PatternSet;
+pub use patterns::PatternLibrary;
 pub use worker::search_worker_loop;
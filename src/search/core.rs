@@ -4,43 +4,220 @@
 //! for maximum performance. It supports regex patterns, case sensitivity, whole word matching,
 //! and provides efficient result caching.
 
+mod matcher;
+mod multiline;
+mod pattern;
+
+pub use pattern::{check_regex, verbatim_pattern};
+
+use pattern::has_uppercase;
+
 use crate::error::{Result, RllessError};
-use crate::file_handler::accessor::FileAccessor;
+use crate::file_handler::accessor::{FileAccessor, ScanProgress};
 use async_trait::async_trait;
-use grep_matcher::Matcher;
-use grep_regex::{RegexMatcher, RegexMatcherBuilder};
 use lru::LruCache;
+use matcher::{SearchCacheKey, SearchMatcher};
 use parking_lot::RwLock;
 use std::num::NonZeroUsize;
+use std::ops::Range;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::timeout;
 
+/// Longest pattern accepted by the engine. Anything larger is almost certainly an accidental
+/// paste and would make both the prompt and the matcher unusable.
+pub const MAX_PATTERN_BYTES: usize = 4 * 1024;
+
 /// Configuration options for search operations
+///
+/// With the `serde` feature the options (de)serialize field by field; a missing field takes
+/// its default and an unknown one is ignored, so options saved by an older or newer rlless
+/// still load.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SearchOptions {
     /// Enable case-sensitive search
+    #[cfg_attr(feature = "serde", serde(default = "defaults::case_sensitive"))]
     pub case_sensitive: bool,
+    /// Ignore case unless the pattern holds an uppercase character, as less and ripgrep do.
+    /// Takes the place of `case_sensitive` while set.
+    #[cfg_attr(feature = "serde", serde(default = "defaults::smart_case"))]
+    pub smart_case: bool,
     /// Match whole words only
+    #[cfg_attr(feature = "serde", serde(default = "defaults::whole_word"))]
     pub whole_word: bool,
     /// Treat pattern as regex (true) or literal string (false)
+    #[cfg_attr(feature = "serde", serde(default = "defaults::regex_mode"))]
     pub regex_mode: bool,
     /// Maximum time to spend on a single search operation (ReDoS protection)
+    #[cfg_attr(feature = "serde", serde(default = "defaults::timeout"))]
     pub timeout: Option<Duration>,
+    /// Treat each line of the pattern as a fixed string and match any of them; see
+    /// `search::pattern_set`
+    #[cfg_attr(feature = "serde", serde(default = "defaults::pattern_set"))]
+    pub pattern_set: bool,
+    /// Search the file as a whole rather than line by line, so a match may run across line
+    /// ends and `.` matches them too; see `search::core::multiline`
+    #[cfg_attr(feature = "serde", serde(default = "defaults::multiline"))]
+    pub multiline: bool,
+    /// Let a forward line search through a large mapped file run on several threads; the match
+    /// found is the same either way, so `summary` leaves it out. See
+    /// `file_handler::adaptive::parallel`
+    #[cfg_attr(feature = "serde", serde(default = "defaults::parallel"))]
+    pub parallel: bool,
+}
+
+/// Default of each `SearchOptions` field, shared by `Default` and deserialization
+mod defaults {
+    use std::time::Duration;
+
+    /// less matches case by default
+    pub(super) fn case_sensitive() -> bool {
+        true
+    }
+
+    pub(super) fn smart_case() -> bool {
+        false
+    }
+
+    /// Whole word matching is opt-in via flags
+    pub(super) fn whole_word() -> bool {
+        false
+    }
+
+    /// less treats search patterns as regex by default
+    pub(super) fn regex_mode() -> bool {
+        true
+    }
+
+    pub(super) fn timeout() -> Option<Duration> {
+        Some(Duration::from_secs(10))
+    }
+
+    pub(super) fn pattern_set() -> bool {
+        false
+    }
+
+    pub(super) fn multiline() -> bool {
+        false
+    }
+
+    pub(super) fn parallel() -> bool {
+        true
+    }
 }
 
 impl Default for SearchOptions {
     fn default() -> Self {
         Self {
-            case_sensitive: true,                   // less matches case by default
-            whole_word: false,                      // whole word matching opt-in via flags
-            regex_mode: true, // less treats search patterns as regex by default
-            timeout: Some(Duration::from_secs(10)), // 10 second default timeout
+            case_sensitive: defaults::case_sensitive(),
+            smart_case: defaults::smart_case(),
+            whole_word: defaults::whole_word(),
+            regex_mode: defaults::regex_mode(),
+            timeout: defaults::timeout(),
+            pattern_set: defaults::pattern_set(),
+            multiline: defaults::multiline(),
+            parallel: defaults::parallel(),
         }
     }
 }
 
+impl SearchOptions {
+    /// Whether searching for `pattern` tells case apart, with smart case settled
+    pub fn is_case_sensitive_for(&self, pattern: &str) -> bool {
+        if self.smart_case {
+            has_uppercase(pattern, self.regex_mode && !self.pattern_set)
+        } else {
+            self.case_sensitive
+        }
+    }
+
+    /// Compact form such as `i,re,w`, read back by `parse_summary`
+    ///
+    /// `re` or `lit` always names the pattern syntax; the other tokens appear only when set:
+    /// `i` ignores case, `s` turns on smart case, `w` matches whole words, `set` matches a
+    /// pattern set, `ml` matches across lines, and `timeout=5s` or `timeout=off` gives a timeout
+    /// other than the default.
+    pub fn summary(&self) -> String {
+        let mut tokens = Vec::new();
+        if !self.case_sensitive {
+            tokens.push("i".to_string());
+        }
+        if self.smart_case {
+            tokens.push("s".to_string());
+        }
+        tokens.push(if self.regex_mode { "re" } else { "lit" }.to_string());
+        if self.whole_word {
+            tokens.push("w".to_string());
+        }
+        if self.pattern_set {
+            tokens.push("set".to_string());
+        }
+        if self.multiline {
+            tokens.push("ml".to_string());
+        }
+        if self.timeout != defaults::timeout() {
+            tokens.push(match self.timeout {
+                None => "timeout=off".to_string(),
+                Some(limit) if limit.subsec_millis() == 0 => {
+                    format!("timeout={}s", limit.as_secs())
+                }
+                Some(limit) => format!("timeout={}ms", limit.as_millis()),
+            });
+        }
+        tokens.join(",")
+    }
+
+    /// Options from a `summary`, starting from the defaults
+    ///
+    /// Tokens may come in any order and an empty one is skipped; an unknown token is an error.
+    pub fn parse_summary(text: &str) -> Result<Self> {
+        let mut options = Self::default();
+        for token in text
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+        {
+            match token {
+                "i" => options.case_sensitive = false,
+                "s" => options.smart_case = true,
+                "re" => options.regex_mode = true,
+                "lit" => options.regex_mode = false,
+                "w" => options.whole_word = true,
+                "set" => options.pattern_set = true,
+                "ml" => options.multiline = true,
+                _ => match token.strip_prefix("timeout=") {
+                    Some(limit) => options.timeout = parse_timeout(limit)?,
+                    None => {
+                        return Err(RllessError::other(format!(
+                            "Unknown search option: {}",
+                            token
+                        )))
+                    }
+                },
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// `off`, `5s` or `500ms`
+fn parse_timeout(text: &str) -> Result<Option<Duration>> {
+    if text == "off" {
+        return Ok(None);
+    }
+    let invalid = || RllessError::other(format!("Invalid search timeout: {}", text));
+    let limit = if let Some(millis) = text.strip_suffix("ms") {
+        Duration::from_millis(millis.parse().map_err(|_| invalid())?)
+    } else if let Some(secs) = text.strip_suffix('s') {
+        Duration::from_secs(secs.parse().map_err(|_| invalid())?)
+    } else {
+        return Err(invalid());
+    };
+    Ok(Some(limit))
+}
+
 /// Core trait for search engine implementations
 ///
 /// This trait provides a unified interface for different search backends while maintaining
@@ -53,6 +230,7 @@ pub trait SearchEngine: Send + Sync {
     /// * `pattern` - Search pattern (string or regex depending on options)
     /// * `start_byte` - Byte position to start searching from (0-based, inclusive)
     /// * `options` - Search configuration options
+    /// * `progress` - Told how far the scan has got, for a status line percentage
     ///
     /// # Returns
     /// * Some(byte_position) if pattern found
@@ -68,6 +246,7 @@ pub trait SearchEngine: Send + Sync {
         start_byte: u64,
         options: &SearchOptions,
         cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
     ) -> Result<Option<u64>>;
 
     /// Search for the previous occurrence of a pattern
@@ -76,6 +255,7 @@ pub trait SearchEngine: Send + Sync {
     /// * `pattern` - Search pattern (string or regex depending on options)
     /// * `start_byte` - Byte position to start searching from (0-based, exclusive)
     /// * `options` - Search configuration options
+    /// * `progress` - Told how far the scan has got, for a status line percentage
     ///
     /// # Returns
     /// * Some(byte_position) if pattern found
@@ -86,6 +266,7 @@ pub trait SearchEngine: Send + Sync {
         start_byte: u64,
         options: &SearchOptions,
         cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
     ) -> Result<Option<u64>>;
 
     /// Get match ranges for a specific line
@@ -108,35 +289,32 @@ pub trait SearchEngine: Send + Sync {
         options: &SearchOptions,
     ) -> Result<Vec<(usize, usize)>>;
 
+    /// Get match ranges for each row of a page
+    ///
+    /// # Arguments
+    /// * `pattern` - Search pattern (string or regex depending on options)
+    /// * `bytes` - The page's bytes
+    /// * `rows` - Range of each row within `bytes`
+    /// * `options` - Search configuration options
+    ///
+    /// # Returns
+    /// * For each row, the (start, end) byte ranges `get_line_matches` gives for its text;
+    ///   a row that is not valid UTF-8 is matched as `String::from_utf8_lossy` shows it
+    ///
+    /// # Performance
+    /// * Matches valid UTF-8 rows where they lie, without a `String` per row
+    fn get_matches_in(
+        &self,
+        pattern: &str,
+        bytes: &[u8],
+        rows: &[Range<usize>],
+        options: &SearchOptions,
+    ) -> Result<Vec<Vec<(usize, usize)>>>;
+
     /// Clear internal caches and reset state
     fn clear_cache(&self);
 }
 
-/// Cache key for storing compiled search patterns and results
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct SearchCacheKey {
-    pattern: String,
-    options: SearchOptionsKey,
-}
-
-/// Hashable version of SearchOptions for caching
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct SearchOptionsKey {
-    case_sensitive: bool,
-    whole_word: bool,
-    regex_mode: bool,
-}
-
-impl From<&SearchOptions> for SearchOptionsKey {
-    fn from(options: &SearchOptions) -> Self {
-        Self {
-            case_sensitive: options.case_sensitive,
-            whole_word: options.whole_word,
-            regex_mode: options.regex_mode,
-        }
-    }
-}
-
 /// Ripgrep-based search engine implementation
 ///
 /// This implementation leverages the ripgrep ecosystem (grep-searcher, grep-matcher, grep-regex)
@@ -144,8 +322,8 @@ impl From<&SearchOptions> for SearchOptionsKey {
 pub struct RipgrepEngine {
     /// File accessor for reading file content
     file_accessor: Arc<dyn FileAccessor>,
-    /// LRU cache for compiled regex matchers
-    matcher_cache: RwLock<LruCache<SearchCacheKey, Arc<RegexMatcher>>>,
+    /// LRU cache for compiled matchers
+    matcher_cache: RwLock<LruCache<SearchCacheKey, Arc<SearchMatcher>>>,
 }
 
 impl RipgrepEngine {
@@ -165,110 +343,10 @@ impl RipgrepEngine {
         }
     }
 
-    /// Create a search function compatible with FileAccessor API
-    ///
-    /// This is the key integration point - we create a closure that captures
-    /// the compiled regex matcher and returns match ranges for FileAccessor.
-    fn create_search_function(
-        &self,
-        matcher: Arc<RegexMatcher>,
-    ) -> impl Fn(&str) -> Vec<(usize, usize)> + Send + Sync {
-        move |line: &str| {
-            let mut matches = Vec::new();
-            let line_bytes = line.as_bytes();
-
-            // Use grep-matcher to find all matches in the line
-            let mut start_pos = 0;
-            while start_pos < line_bytes.len() {
-                if let Ok(Some(m)) = matcher.find_at(line_bytes, start_pos) {
-                    matches.push((m.start(), m.end()));
-                    start_pos = m.end().max(start_pos + 1); // Prevent infinite loop on zero-width matches
-                } else {
-                    break;
-                }
-            }
-
-            matches
-        }
+    /// Search `file_accessor` from now on, keeping the compiled matchers
+    pub fn set_file_accessor(&mut self, file_accessor: Arc<dyn FileAccessor>) {
+        self.file_accessor = file_accessor;
     }
-
-    /// Get or create a compiled regex matcher for the given pattern and options
-    fn get_or_create_matcher(
-        &self,
-        pattern: &str,
-        options: &SearchOptions,
-    ) -> Result<Arc<RegexMatcher>> {
-        let cache_key = SearchCacheKey {
-            pattern: pattern.to_string(),
-            options: options.into(),
-        };
-
-        // Try to get from cache first
-        {
-            let mut cache = self.matcher_cache.write();
-            if let Some(matcher) = cache.get(&cache_key) {
-                return Ok(matcher.clone());
-            }
-        }
-
-        // Create new matcher
-        let matcher = self.create_matcher(pattern, options)?;
-        let matcher = Arc::new(matcher);
-
-        // Store in cache
-        {
-            let mut cache = self.matcher_cache.write();
-            cache.put(cache_key, matcher.clone());
-        }
-
-        Ok(matcher)
-    }
-
-    /// Create a new regex matcher with the specified options
-    fn create_matcher(&self, pattern: &str, options: &SearchOptions) -> Result<RegexMatcher> {
-        // Handle whole word matching
-        let effective_pattern = if options.whole_word && !options.regex_mode {
-            // For literal strings, wrap in word boundaries
-            format!(r"\b{}\b", escape_regex(pattern))
-        } else if options.whole_word && options.regex_mode {
-            // For regex patterns, wrap in word boundaries
-            format!(r"\b(?:{})\b", pattern)
-        } else if !options.regex_mode {
-            // For literal strings, escape regex special characters
-            escape_regex(pattern)
-        } else {
-            // For regex patterns, use as-is
-            pattern.to_string()
-        };
-
-        // Create matcher with case sensitivity configuration
-        let mut builder = RegexMatcherBuilder::new();
-        if !options.case_sensitive {
-            builder.case_insensitive(true);
-        }
-
-        builder.build(&effective_pattern).map_err(|e| {
-            RllessError::search_error(format!("Invalid regex pattern: {}", e), e.into())
-        })
-    }
-}
-
-/// Escape special regex characters in a literal string
-///
-/// This is a simple implementation to escape common regex metacharacters
-/// for literal string matching.
-fn escape_regex(s: &str) -> String {
-    let mut escaped = String::with_capacity(s.len());
-    for ch in s.chars() {
-        match ch {
-            '\\' | '^' | '$' | '.' | '[' | ']' | '|' | '(' | ')' | '?' | '*' | '+' | '{' | '}' => {
-                escaped.push('\\');
-                escaped.push(ch);
-            }
-            _ => escaped.push(ch),
-        }
-    }
-    escaped
 }
 
 #[async_trait]
@@ -279,18 +357,34 @@ impl SearchEngine for RipgrepEngine {
         start_byte: u64,
         options: &SearchOptions,
         cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
     ) -> Result<Option<u64>> {
         // Get or create matcher
         let matcher = self.get_or_create_matcher(pattern, options)?;
 
         // Create search function for FileAccessor
-        let search_fn = self.create_search_function(matcher);
+        let search_fn = self.create_search_function(Arc::clone(&matcher));
 
         // Define the search operation
         let search_operation = async {
-            self.file_accessor
-                .find_next_match(start_byte, &search_fn, cancel_flag)
+            if options.multiline {
+                multiline::find_next(
+                    self.file_accessor.as_ref(),
+                    &matcher,
+                    start_byte,
+                    cancel_flag,
+                    progress,
+                )
                 .await
+            } else if options.parallel {
+                self.file_accessor
+                    .find_next_match_parallel(start_byte, &search_fn, cancel_flag, progress)
+                    .await
+            } else {
+                self.file_accessor
+                    .find_next_match(start_byte, &search_fn, cancel_flag, progress)
+                    .await
+            }
         };
 
         // Apply timeout if specified
@@ -316,18 +410,30 @@ impl SearchEngine for RipgrepEngine {
         start_byte: u64,
         options: &SearchOptions,
         cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
     ) -> Result<Option<u64>> {
         // Get or create matcher
         let matcher = self.get_or_create_matcher(pattern, options)?;
 
         // Create search function for FileAccessor
-        let search_fn = self.create_search_function(matcher);
+        let search_fn = self.create_search_function(Arc::clone(&matcher));
 
         // Define the search operation
         let search_operation = async {
-            self.file_accessor
-                .find_prev_match(start_byte, &search_fn, cancel_flag)
+            if options.multiline {
+                multiline::find_prev(
+                    self.file_accessor.as_ref(),
+                    &matcher,
+                    start_byte,
+                    cancel_flag,
+                    progress,
+                )
                 .await
+            } else {
+                self.file_accessor
+                    .find_prev_match(start_byte, &search_fn, cancel_flag, progress)
+                    .await
+            }
         };
 
         // Apply timeout if specified
@@ -363,6 +469,27 @@ impl SearchEngine for RipgrepEngine {
         Ok(search_fn(line))
     }
 
+    fn get_matches_in(
+        &self,
+        pattern: &str,
+        bytes: &[u8],
+        rows: &[Range<usize>],
+        options: &SearchOptions,
+    ) -> Result<Vec<Vec<(usize, usize)>>> {
+        let matcher = self.get_or_create_matcher(pattern, options)?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let row = &bytes[row.clone()];
+                // Offsets must fit the text on screen, where invalid UTF-8 has been replaced
+                match std::str::from_utf8(row) {
+                    Ok(_) => matcher.find_all(row),
+                    Err(_) => matcher.find_all(String::from_utf8_lossy(row).as_bytes()),
+                }
+            })
+            .collect())
+    }
+
     fn clear_cache(&self) {
         self.matcher_cache.write().clear();
     }
@@ -415,11 +542,19 @@ mod tests {
             }
         }
 
+        async fn read_bytes(&self, start_byte: u64, length: usize) -> Result<Vec<u8>> {
+            let bytes = self.content.as_bytes();
+            let start = (start_byte as usize).min(bytes.len());
+            let end = start.saturating_add(length).min(bytes.len());
+            Ok(bytes[start..end].to_vec())
+        }
+
         async fn find_next_match(
             &self,
             start_byte: u64,
             search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
             _cancel_flag: Option<&AtomicBool>,
+            _progress: Option<ScanProgress<'_>>,
         ) -> Result<Option<u64>> {
             let start_line = self.find_line_at_byte(start_byte).unwrap_or(0);
 
@@ -437,6 +572,7 @@ mod tests {
             start_byte: u64,
             search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
             _cancel_flag: Option<&AtomicBool>,
+            _progress: Option<ScanProgress<'_>>,
         ) -> Result<Option<u64>> {
             let start_line = self
                 .find_line_at_byte(start_byte)
@@ -459,6 +595,18 @@ mod tests {
             std::path::Path::new("mock_file.txt")
         }
 
+        fn ends_with_newline(&self) -> bool {
+            self.content.is_empty() || self.content.ends_with('\n')
+        }
+
+        fn file_meta(&self) -> Option<crate::file_handler::FileMeta> {
+            None
+        }
+
+        fn sections(&self) -> &[u64] {
+            &[]
+        }
+
         async fn last_page_start(&self, max_lines: usize) -> Result<u64> {
             if self.lines.len() <= max_lines {
                 Ok(0)
@@ -487,7 +635,7 @@ mod tests {
         }
     }
 
-    fn create_test_engine() -> RipgrepEngine {
+    pub(super) fn create_test_engine() -> RipgrepEngine {
         let lines = vec![
             "The quick brown fox".to_string(),
             "jumps over the lazy dog".to_string(),
@@ -503,7 +651,10 @@ mod tests {
         let engine = create_test_engine();
         let options = SearchOptions::default();
 
-        let result = engine.search_from("fox", 0, &options, None).await.unwrap();
+        let result = engine
+            .search_from("fox", 0, &options, None, None)
+            .await
+            .unwrap();
         assert!(result.is_some());
 
         let byte_position = result.unwrap();
@@ -516,67 +667,25 @@ mod tests {
         assert_eq!(match_ranges, vec![(16, 19)]);
     }
 
-    #[tokio::test]
-    async fn test_case_insensitive_search() {
-        let engine = create_test_engine();
-        let options = SearchOptions {
-            case_sensitive: false,
-            ..Default::default()
-        };
-
-        let result = engine.search_from("FOX", 0, &options, None).await.unwrap();
-        assert!(result.is_some());
-
-        let byte_position = result.unwrap();
-        assert_eq!(byte_position, 0); // "FOX" found at start of first line (case insensitive)
-
-        // Test case insensitive matching
-        let match_ranges = engine
-            .get_line_matches("FOX", "The quick brown fox", &options)
-            .unwrap();
-        assert_eq!(match_ranges, vec![(16, 19)]);
-    }
-
-    #[tokio::test]
-    async fn test_regex_search() {
+    #[test]
+    fn test_page_matches_equal_line_matches() {
         let engine = create_test_engine();
-        let options = SearchOptions {
-            regex_mode: true,
-            ..Default::default()
-        };
-
-        let result = engine
-            .search_from(r"qu\w+k", 0, &options, None)
-            .await
-            .unwrap();
-        assert!(result.is_some());
-
-        let byte_position = result.unwrap();
-        assert_eq!(byte_position, 0); // "quick" pattern found at start of first line
-
-        // Test regex matching
-        let match_ranges = engine
-            .get_line_matches(r"qu\w+k", "The quick brown fox", &options)
+        let options = SearchOptions::default();
+        let bytes = b"fox and fox\nno match\n\xff fox\xfe fox\n";
+        let rows = [0..11, 12..20, 21..31, 32..32];
+        let matches = engine
+            .get_matches_in("fox", bytes, &rows, &options)
             .unwrap();
-        assert_eq!(match_ranges, vec![(4, 9)]); // "quick"
-    }
-
-    #[tokio::test]
-    async fn test_whole_word_search() {
-        let engine = create_test_engine();
-        let options = SearchOptions {
-            whole_word: true,
-            ..Default::default()
-        };
-
-        // Should find "box" as a whole word in line 2 ("Pack my box with five dozen liquor jugs")
-        let result = engine.search_from("box", 0, &options, None).await.unwrap();
-        assert!(result.is_some());
-        assert_eq!(result.unwrap(), 44); // Line 2 starts at byte 44
-
-        // Should NOT find "ox" as it's part of "fox"
-        let result = engine.search_from("ox", 0, &options, None).await.unwrap();
-        assert!(result.is_none());
+        for (row, matches) in rows.iter().zip(matches) {
+            let line = String::from_utf8_lossy(&bytes[row.clone()]);
+            assert_eq!(
+                matches,
+                engine.get_line_matches("fox", &line, &options).unwrap()
+            );
+        }
+        // Offsets in the invalid row refer to the replacement characters shown for it
+        let matches = engine.get_matches_in("fox", bytes, &rows[2..3], &options);
+        assert_eq!(matches.unwrap(), vec![vec![(4, 7), (11, 14)]]);
     }
 
     #[tokio::test]
@@ -586,7 +695,7 @@ mod tests {
 
         // Search backward from near end of file for "jump" - should find in line 2
         let result = engine
-            .search_prev("jump", 100, &options, None)
+            .search_prev("jump", 100, &options, None, None)
             .await
             .unwrap();
         assert!(result.is_some());
@@ -601,28 +710,22 @@ mod tests {
         let options = SearchOptions::default();
 
         // First search
-        let result1 = engine.search_from("fox", 0, &options, None).await.unwrap();
+        let result1 = engine
+            .search_from("fox", 0, &options, None, None)
+            .await
+            .unwrap();
 
         // Second search (should use cached regex matcher)
-        let result2 = engine.search_from("fox", 0, &options, None).await.unwrap();
+        let result2 = engine
+            .search_from("fox", 0, &options, None, None)
+            .await
+            .unwrap();
 
         // Both searches should return the same result
         assert_eq!(result1, result2);
         assert!(result1.is_some());
     }
 
-    #[tokio::test]
-    async fn test_invalid_regex() {
-        let engine = create_test_engine();
-        let options = SearchOptions {
-            regex_mode: true,
-            ..Default::default()
-        };
-
-        let result = engine.search_from("[invalid", 0, &options, None).await;
-        assert!(result.is_err());
-    }
-
     #[tokio::test]
     async fn test_search_no_timeout() {
         let engine = create_test_engine();
@@ -634,45 +737,128 @@ mod tests {
         };
 
         // This search should complete successfully
-        let result = engine.search_from("fox", 0, &options, None).await;
+        let result = engine.search_from("fox", 0, &options, None, None).await;
         assert!(result.is_ok());
         assert!(result.unwrap().is_some());
     }
 
     #[test]
-    fn test_get_line_matches() {
-        let engine = create_test_engine();
-        let options = SearchOptions::default();
+    fn test_options_summary_round_trips() {
+        assert_eq!(SearchOptions::default().summary(), "re");
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(options.summary(), "i,re,w");
+
+        for options in [
+            SearchOptions::default(),
+            options,
+            SearchOptions {
+                smart_case: true,
+                regex_mode: false,
+                pattern_set: true,
+                timeout: None,
+                ..Default::default()
+            },
+            SearchOptions {
+                timeout: Some(Duration::from_millis(1500)),
+                multiline: true,
+                ..Default::default()
+            },
+            SearchOptions {
+                timeout: Some(Duration::from_secs(30)),
+                ..Default::default()
+            },
+        ] {
+            let summary = options.summary();
+            assert_eq!(
+                SearchOptions::parse_summary(&summary).unwrap(),
+                options,
+                "{summary}"
+            );
+        }
+    }
 
-        // Test single match
-        let matches = engine
-            .get_line_matches("fox", "The quick brown fox", &options)
-            .unwrap();
-        assert_eq!(matches, vec![(16, 19)]);
-
-        // Test multiple matches
-        let test_line = "The quick brown fox jumps over the quiet dog";
-        let matches = engine.get_line_matches("qu", test_line, &options).unwrap();
-        // Let's find where "qu" actually appears:
-        // "The quick brown fox jumps over the quiet dog"
-        //      ^^                              ^^
-        //      4-6                             35-37
-        assert_eq!(matches, vec![(4, 6), (35, 37)]); // "qu" in "quick" and "quiet"
-
-        // Test no matches
-        let matches = engine
-            .get_line_matches("xyz", "The quick brown fox", &options)
-            .unwrap();
-        assert!(matches.is_empty());
+    #[test]
+    fn test_parse_summary_is_lenient_about_order_and_spacing() {
+        let options = SearchOptions::parse_summary(" w , lit,,i ").unwrap();
+        assert!(!options.case_sensitive);
+        assert!(!options.regex_mode);
+        assert!(options.whole_word);
+        assert_eq!(
+            SearchOptions::parse_summary("").unwrap(),
+            SearchOptions::default()
+        );
+
+        assert!(SearchOptions::parse_summary("i,q").is_err());
+        assert!(SearchOptions::parse_summary("timeout=soon").is_err());
+        assert!(SearchOptions::parse_summary("timeout=5").is_err());
+    }
+
+    /// Options written by earlier and later versions: missing fields take their defaults and
+    /// unknown ones are ignored
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_options_deserialize_across_versions() {
+        let corpus = [
+            ("{}", SearchOptions::default()),
+            // Before smart case and pattern sets
+            (
+                r#"{"case_sensitive":false,"whole_word":true,"regex_mode":true,"timeout":{"secs":10,"nanos":0}}"#,
+                SearchOptions {
+                    case_sensitive: false,
+                    whole_word: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                r#"{"case_sensitive":true,"whole_word":false,"regex_mode":false,"timeout":null}"#,
+                SearchOptions {
+                    regex_mode: false,
+                    timeout: None,
+                    ..Default::default()
+                },
+            ),
+            (
+                r#"{"smart_case":true,"pattern_set":true}"#,
+                SearchOptions {
+                    smart_case: true,
+                    pattern_set: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                r#"{"multiline":true}"#,
+                SearchOptions {
+                    multiline: true,
+                    ..Default::default()
+                },
+            ),
+            // A field from a later version
+            (
+                r#"{"whole_word":true,"invert":true}"#,
+                SearchOptions {
+                    whole_word: true,
+                    ..Default::default()
+                },
+            ),
+        ];
+        for (json, expected) in corpus {
+            let options: SearchOptions = serde_json::from_str(json).unwrap();
+            assert_eq!(options, expected, "{json}");
+        }
 
-        // Test regex mode
-        let regex_options = SearchOptions {
-            regex_mode: true,
+        let options = SearchOptions {
+            smart_case: true,
+            timeout: Some(Duration::from_millis(250)),
             ..Default::default()
         };
-        let matches = engine
-            .get_line_matches(r"\b\w{5}\b", "The quick brown fox jumps", &regex_options)
-            .unwrap();
-        assert_eq!(matches, vec![(4, 9), (10, 15), (20, 25)]); // "quick", "brown", "jumps"
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SearchOptions>(&json).unwrap(),
+            options
+        );
     }
 }
@@ -0,0 +1,499 @@
+//! Compiling patterns into cached matchers.
+
+use super::pattern::{check_regex, escape_regex, unescape_literal};
+use super::{RipgrepEngine, SearchOptions, MAX_PATTERN_BYTES};
+use crate::error::{Result, RllessError};
+use crate::search::pattern_set::{set_entries, MAX_SET_BYTES};
+use aho_corasick::{AhoCorasick, MatchKind};
+use grep_matcher::Matcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use std::sync::Arc;
+
+/// Entries from which a literal pattern set is matched with Aho-Corasick rather than as one
+/// regex alternation, which compiles slowly and grows large with many entries
+const LITERAL_SET_MIN_ENTRIES: usize = 64;
+
+/// A compiled pattern
+#[derive(Debug)]
+pub(super) enum SearchMatcher {
+    Regex(RegexMatcher),
+    /// Fixed strings of a large literal pattern set
+    Literals(AhoCorasick),
+}
+
+impl SearchMatcher {
+    /// Byte range of the first match in `haystack` at or after `start`
+    pub(super) fn find_at(&self, haystack: &[u8], start: usize) -> Option<(usize, usize)> {
+        match self {
+            SearchMatcher::Regex(matcher) => matcher
+                .find_at(haystack, start)
+                .ok()
+                .flatten()
+                .map(|m| (m.start(), m.end())),
+            SearchMatcher::Literals(automaton) => automaton
+                .find(aho_corasick::Input::new(haystack).range(start..))
+                .map(|m| (m.start(), m.end())),
+        }
+    }
+
+    /// Byte ranges of every match in `haystack`
+    pub(super) fn find_all(&self, haystack: &[u8]) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        let mut start_pos = 0;
+        while start_pos < haystack.len() {
+            if let Some((start, end)) = self.find_at(haystack, start_pos) {
+                matches.push((start, end));
+                start_pos = end.max(start_pos + 1); // Prevent infinite loop on zero-width matches
+            } else {
+                break;
+            }
+        }
+        matches
+    }
+}
+
+/// Cache key for storing compiled search patterns and results
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct SearchCacheKey {
+    pattern: String,
+    options: SearchOptionsKey,
+}
+
+/// Hashable version of SearchOptions for caching
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SearchOptionsKey {
+    case_sensitive: bool,
+    whole_word: bool,
+    regex_mode: bool,
+    pattern_set: bool,
+    multiline: bool,
+}
+
+impl From<&SearchOptions> for SearchOptionsKey {
+    fn from(options: &SearchOptions) -> Self {
+        Self {
+            case_sensitive: options.case_sensitive,
+            whole_word: options.whole_word,
+            regex_mode: options.regex_mode,
+            pattern_set: options.pattern_set,
+            multiline: options.multiline,
+        }
+    }
+}
+
+impl RipgrepEngine {
+    /// Create a search function compatible with FileAccessor API
+    ///
+    /// This is the key integration point - we create a closure that captures
+    /// the compiled regex matcher and returns match ranges for FileAccessor.
+    pub(super) fn create_search_function(
+        &self,
+        matcher: Arc<SearchMatcher>,
+    ) -> impl Fn(&str) -> Vec<(usize, usize)> + Send + Sync {
+        move |line: &str| matcher.find_all(line.as_bytes())
+    }
+
+    /// Get or create a compiled matcher for the given pattern and options
+    pub(super) fn get_or_create_matcher(
+        &self,
+        pattern: &str,
+        options: &SearchOptions,
+    ) -> Result<Arc<SearchMatcher>> {
+        // Navigation and highlighting both come here, so they settle smart case alike
+        let resolved;
+        let options = if options.smart_case {
+            resolved = SearchOptions {
+                case_sensitive: options.is_case_sensitive_for(pattern),
+                smart_case: false,
+                ..options.clone()
+            };
+            &resolved
+        } else {
+            options
+        };
+        let cache_key = SearchCacheKey {
+            pattern: pattern.to_string(),
+            options: options.into(),
+        };
+
+        // Try to get from cache first
+        {
+            let mut cache = self.matcher_cache.write();
+            if let Some(matcher) = cache.get(&cache_key) {
+                return Ok(matcher.clone());
+            }
+        }
+
+        // Create new matcher
+        let matcher = self.create_matcher(pattern, options)?;
+        let matcher = Arc::new(matcher);
+
+        // Store in cache
+        {
+            let mut cache = self.matcher_cache.write();
+            cache.put(cache_key, matcher.clone());
+        }
+
+        Ok(matcher)
+    }
+
+    /// Create a new matcher with the specified options
+    fn create_matcher(&self, pattern: &str, options: &SearchOptions) -> Result<SearchMatcher> {
+        if options.pattern_set {
+            return self.create_set_matcher(pattern, options);
+        }
+        if pattern.len() > MAX_PATTERN_BYTES {
+            return Err(RllessError::search(format!(
+                "Pattern too long ({} bytes, limit {})",
+                pattern.len(),
+                MAX_PATTERN_BYTES
+            )));
+        }
+
+        if options.regex_mode {
+            // Checked before wrapping, so positions refer to the pattern as typed
+            check_regex(pattern)?;
+        }
+
+        // Handle whole word matching
+        let effective_pattern = if options.whole_word && !options.regex_mode {
+            // For literal strings, wrap in word boundaries
+            format!(r"\b{}\b", escape_regex(&unescape_literal(pattern)))
+        } else if options.whole_word && options.regex_mode {
+            // For regex patterns, wrap in word boundaries
+            format!(r"\b(?:{})\b", pattern)
+        } else if !options.regex_mode {
+            // For literal strings, escape regex special characters
+            escape_regex(&unescape_literal(pattern))
+        } else {
+            // For regex patterns, use as-is
+            pattern.to_string()
+        };
+
+        build_regex(&effective_pattern, options)
+    }
+
+    /// Matcher for any entry of a pattern set, taken as fixed strings
+    ///
+    /// Large sets searched in literal mode use Aho-Corasick where it can honour the options;
+    /// everything else becomes one alternation of the escaped entries.
+    fn create_set_matcher(&self, pattern: &str, options: &SearchOptions) -> Result<SearchMatcher> {
+        if pattern.len() > MAX_SET_BYTES {
+            return Err(RllessError::search(format!(
+                "Pattern set too large ({} bytes, limit {})",
+                pattern.len(),
+                MAX_SET_BYTES
+            )));
+        }
+
+        let entries: Vec<&str> = set_entries(pattern).collect();
+        // Aho-Corasick folds ASCII case only and knows nothing of word boundaries
+        let fast_path = !options.regex_mode
+            && !options.whole_word
+            && entries.len() >= LITERAL_SET_MIN_ENTRIES
+            && (options.case_sensitive || pattern.is_ascii());
+        if fast_path {
+            return AhoCorasick::builder()
+                .match_kind(MatchKind::LeftmostFirst)
+                .ascii_case_insensitive(!options.case_sensitive)
+                .build(&entries)
+                .map(SearchMatcher::Literals)
+                .map_err(|e| {
+                    RllessError::search_error(format!("Invalid pattern set: {}", e), e.into())
+                });
+        }
+
+        let alternation = entries
+            .iter()
+            .map(|entry| escape_regex(entry))
+            .collect::<Vec<_>>()
+            .join("|");
+        let effective_pattern = if options.whole_word {
+            format!(r"\b(?:{})\b", alternation)
+        } else {
+            alternation
+        };
+        build_regex(&effective_pattern, options)
+    }
+}
+
+/// Compile `pattern` with the case sensitivity and line handling of `options`
+fn build_regex(pattern: &str, options: &SearchOptions) -> Result<SearchMatcher> {
+    let mut builder = RegexMatcherBuilder::new();
+    if !options.case_sensitive {
+        builder.case_insensitive(true);
+    }
+    if options.multiline {
+        // `^` and `$` keep meaning the start and end of a line within the file
+        builder.multi_line(true).dot_matches_new_line(true);
+    }
+
+    builder
+        .build(pattern)
+        .map(SearchMatcher::Regex)
+        .map_err(|e| RllessError::search_error(format!("Invalid regex pattern: {}", e), e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::core::tests::create_test_engine;
+    use crate::search::core::SearchEngine;
+
+    #[tokio::test]
+    async fn test_case_insensitive_search() {
+        let engine = create_test_engine();
+        let options = SearchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+
+        let result = engine
+            .search_from("FOX", 0, &options, None, None)
+            .await
+            .unwrap();
+        assert!(result.is_some());
+
+        let byte_position = result.unwrap();
+        assert_eq!(byte_position, 0); // "FOX" found at start of first line (case insensitive)
+
+        // Test case insensitive matching
+        let match_ranges = engine
+            .get_line_matches("FOX", "The quick brown fox", &options)
+            .unwrap();
+        assert_eq!(match_ranges, vec![(16, 19)]);
+    }
+
+    #[tokio::test]
+    async fn test_regex_search() {
+        let engine = create_test_engine();
+        let options = SearchOptions {
+            regex_mode: true,
+            ..Default::default()
+        };
+
+        let result = engine
+            .search_from(r"qu\w+k", 0, &options, None, None)
+            .await
+            .unwrap();
+        assert!(result.is_some());
+
+        let byte_position = result.unwrap();
+        assert_eq!(byte_position, 0); // "quick" pattern found at start of first line
+
+        // Test regex matching
+        let match_ranges = engine
+            .get_line_matches(r"qu\w+k", "The quick brown fox", &options)
+            .unwrap();
+        assert_eq!(match_ranges, vec![(4, 9)]); // "quick"
+    }
+
+    #[tokio::test]
+    async fn test_whole_word_search() {
+        let engine = create_test_engine();
+        let options = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+
+        // Should find "box" as a whole word in line 2 ("Pack my box with five dozen liquor jugs")
+        let result = engine
+            .search_from("box", 0, &options, None, None)
+            .await
+            .unwrap();
+        assert!(result.is_some());
+        assert_eq!(result.unwrap(), 44); // Line 2 starts at byte 44
+
+        // Should NOT find "ox" as it's part of "fox"
+        let result = engine
+            .search_from("ox", 0, &options, None, None)
+            .await
+            .unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_regex() {
+        let engine = create_test_engine();
+        let options = SearchOptions {
+            regex_mode: true,
+            ..Default::default()
+        };
+
+        let result = engine
+            .search_from("[invalid", 0, &options, None, None)
+            .await;
+        assert!(matches!(
+            result,
+            Err(RllessError::Regex { position: 1, .. })
+        ));
+
+        // The same text is fine as a literal, even as whole words
+        for whole_word in [false, true] {
+            let options = SearchOptions {
+                regex_mode: false,
+                whole_word,
+                ..Default::default()
+            };
+            assert!(engine
+                .get_line_matches("foo(", "a foo( b", &options)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_pattern_length_limit() {
+        let engine = create_test_engine();
+        let options = SearchOptions::default();
+
+        let at_limit = "a".repeat(MAX_PATTERN_BYTES);
+        assert!(engine.get_line_matches(&at_limit, "a", &options).is_ok());
+
+        let too_long = "a".repeat(MAX_PATTERN_BYTES + 1);
+        let error = engine
+            .get_line_matches(&too_long, "a", &options)
+            .unwrap_err();
+        assert!(error.to_string().contains("Pattern too long"));
+    }
+
+    #[test]
+    fn test_pattern_set_matchers() {
+        let engine = create_test_engine();
+        let mut entries: Vec<String> = (0..1_000).map(|i| format!("id-{i:03}")).collect();
+        entries.push("a.b".to_string());
+        let set = entries.join("\n");
+        // Sets are capped when loaded, not by the prompt's pattern limit
+        assert!(set.len() > MAX_PATTERN_BYTES);
+        let literal = SearchOptions {
+            regex_mode: false,
+            pattern_set: true,
+            ..Default::default()
+        };
+        let line = "got id-007 and ID-999, not a.b or axb";
+        let is_literals = |options: &SearchOptions| {
+            matches!(
+                *engine.get_or_create_matcher(&set, options).unwrap(),
+                SearchMatcher::Literals(_)
+            )
+        };
+
+        assert!(is_literals(&literal));
+        assert_eq!(
+            engine.get_line_matches(&set, line, &literal).unwrap(),
+            vec![(4, 10), (27, 30)]
+        );
+        let ignore_case = SearchOptions {
+            case_sensitive: false,
+            ..literal.clone()
+        };
+        assert!(is_literals(&ignore_case));
+        assert_eq!(
+            engine.get_line_matches(&set, line, &ignore_case).unwrap(),
+            vec![(4, 10), (15, 21), (27, 30)]
+        );
+
+        // Entries stay fixed strings under the regex engine
+        let regex = SearchOptions {
+            regex_mode: true,
+            ..literal.clone()
+        };
+        assert!(!is_literals(&regex));
+        assert_eq!(
+            engine.get_line_matches(&set, line, &regex).unwrap(),
+            vec![(4, 10), (27, 30)]
+        );
+        let word = SearchOptions {
+            whole_word: true,
+            ..literal.clone()
+        };
+        assert!(!is_literals(&word));
+        assert!(engine
+            .get_line_matches(&set, "xid-007", &word)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_smart_case_follows_the_pattern() {
+        let engine = create_test_engine();
+        let options = SearchOptions {
+            smart_case: true,
+            ..Default::default()
+        };
+
+        // All lowercase ignores case, in navigation and in highlights alike
+        assert_eq!(
+            engine
+                .search_from("pack", 0, &options, None, None)
+                .await
+                .unwrap(),
+            Some(44)
+        );
+        assert_eq!(
+            engine
+                .get_line_matches("the", "The other the", &options)
+                .unwrap(),
+            vec![(0, 3), (5, 8), (10, 13)]
+        );
+
+        // Any uppercase character makes it case-sensitive
+        assert_eq!(
+            engine
+                .search_from("PACK", 0, &options, None, None)
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            engine
+                .get_line_matches("The", "The other the", &options)
+                .unwrap(),
+            vec![(0, 3)]
+        );
+
+        // Class escapes are not uppercase text
+        assert_eq!(
+            engine
+                .get_line_matches(r"t\S+", "The other the", &options)
+                .unwrap(),
+            vec![(0, 3), (5, 9), (10, 13)]
+        );
+    }
+
+    #[test]
+    fn test_get_line_matches() {
+        let engine = create_test_engine();
+        let options = SearchOptions::default();
+
+        // Test single match
+        let matches = engine
+            .get_line_matches("fox", "The quick brown fox", &options)
+            .unwrap();
+        assert_eq!(matches, vec![(16, 19)]);
+
+        // Test multiple matches
+        let test_line = "The quick brown fox jumps over the quiet dog";
+        let matches = engine.get_line_matches("qu", test_line, &options).unwrap();
+        // Let's find where "qu" actually appears:
+        // "The quick brown fox jumps over the quiet dog"
+        //      ^^                              ^^
+        //      4-6                             35-37
+        assert_eq!(matches, vec![(4, 6), (35, 37)]); // "qu" in "quick" and "quiet"
+
+        // Test no matches
+        let matches = engine
+            .get_line_matches("xyz", "The quick brown fox", &options)
+            .unwrap();
+        assert!(matches.is_empty());
+
+        // Test regex mode
+        let regex_options = SearchOptions {
+            regex_mode: true,
+            ..Default::default()
+        };
+        let matches = engine
+            .get_line_matches(r"\b\w{5}\b", "The quick brown fox jumps", &regex_options)
+            .unwrap();
+        assert_eq!(matches, vec![(4, 9), (10, 15), (20, 25)]); // "quick", "brown", "jumps"
+    }
+}
@@ -0,0 +1,264 @@
+//! Searching across line ends.
+//!
+//! With `SearchOptions::multiline` the pattern runs over the bytes of the file rather than one
+//! line at a time, so `ERROR[^\n]*\n\s+at Dispatcher` finds a log header followed by a given
+//! stack frame; `.` matches line ends too, so `[^\n]` is how to stay on one line. The bytes are
+//! read in chunks of `CHUNK_BYTES` that begin at a line start and run on `WINDOW_BYTES` past
+//! their end: a match may cross from one chunk into the next as long as it is no longer than the
+//! window, and a longer one can be missed. A match is reported at the display row it starts on,
+//! as a line match would be, so `n` and `N` go from one match to the next however many lines
+//! each spans.
+
+use super::matcher::SearchMatcher;
+use crate::error::Result;
+use crate::file_handler::{FileAccessor, ScanProgress, SCAN_PROGRESS_STRIDE};
+use crate::util::check_cancelled;
+use std::sync::atomic::AtomicBool;
+
+/// Bytes in which matches may start per read
+const CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Bytes read past a chunk, and so the longest match found across a chunk boundary
+const WINDOW_BYTES: usize = 64 * 1024;
+
+/// Bytes read per step while walking back to a line start
+const LINE_SCAN_BYTES: usize = 64 * 1024;
+
+/// Row of the first match starting at or after `start_byte`
+pub(super) async fn find_next(
+    accessor: &dyn FileAccessor,
+    matcher: &SearchMatcher,
+    start_byte: u64,
+    cancel_flag: Option<&AtomicBool>,
+    progress: Option<ScanProgress<'_>>,
+) -> Result<Option<u64>> {
+    let file_size = accessor.file_size();
+    let sep = accessor.line_terminator().byte();
+    let mut pos = start_byte;
+    let mut next_report = start_byte + SCAN_PROGRESS_STRIDE;
+    while pos < file_size {
+        check_cancelled(cancel_flag)?;
+        if let Some(progress) = progress.filter(|_| pos >= next_report) {
+            progress(pos - start_byte);
+            next_report = pos + SCAN_PROGRESS_STRIDE;
+        }
+        let buf = accessor.read_bytes(pos, CHUNK_BYTES + WINDOW_BYTES).await?;
+        if buf.is_empty() {
+            break;
+        }
+        // Matches starting after the chunk's last line start are left to the next chunk, which
+        // sees more of what follows them
+        let chunk_end = if pos + buf.len() as u64 >= file_size {
+            buf.len()
+        } else {
+            let limit = CHUNK_BYTES.min(buf.len());
+            memchr::memrchr(sep, &buf[..limit]).map_or(limit, |at| at + 1)
+        };
+        // The leftmost match starts first, so one past the chunk means none in it
+        if let Some((start, _)) = matcher
+            .find_at(&buf, 0)
+            .filter(|&(start, _)| start < chunk_end)
+        {
+            return row_containing(accessor, pos + start as u64, sep)
+                .await
+                .map(Some);
+        }
+        pos += chunk_end as u64;
+    }
+    Ok(None)
+}
+
+/// Row of the last match starting before `start_byte`
+///
+/// "Last" is the last of the matches found reading forward through a chunk, so a match that
+/// starts inside an earlier one is not considered.
+pub(super) async fn find_prev(
+    accessor: &dyn FileAccessor,
+    matcher: &SearchMatcher,
+    start_byte: u64,
+    cancel_flag: Option<&AtomicBool>,
+    progress: Option<ScanProgress<'_>>,
+) -> Result<Option<u64>> {
+    let file_size = accessor.file_size();
+    let sep = accessor.line_terminator().byte();
+    // Exclusive end of the part still to be searched
+    let mut end = start_byte.min(file_size);
+    let mut next_report = end.saturating_sub(SCAN_PROGRESS_STRIDE);
+    while end > 0 {
+        check_cancelled(cancel_flag)?;
+        if let Some(progress) = progress.filter(|_| end <= next_report) {
+            progress(start_byte - end);
+            next_report = end.saturating_sub(SCAN_PROGRESS_STRIDE);
+        }
+        let from = end.saturating_sub(CHUNK_BYTES as u64);
+        let region = (end - from) as usize;
+        let buf = accessor.read_bytes(from, region + WINDOW_BYTES).await?;
+        if buf.is_empty() {
+            break;
+        }
+        // Begin at a line start, leaving the bytes before it to the next chunk back, unless
+        // the whole chunk is one line
+        let base = if from == 0 {
+            0
+        } else {
+            memchr::memchr(sep, &buf[..region.min(buf.len())])
+                .map(|at| at + 1)
+                .filter(|&base| base < region)
+                .unwrap_or(0)
+        };
+
+        let mut last = None;
+        let mut at = base;
+        while at <= buf.len() {
+            let Some((start, match_end)) = matcher.find_at(&buf, at) else {
+                break;
+            };
+            if start >= region {
+                break;
+            }
+            last = Some(start);
+            at = match_end.max(start + 1);
+        }
+        if let Some(start) = last {
+            return row_containing(accessor, from + start as u64, sep)
+                .await
+                .map(Some);
+        }
+        end = from + base as u64;
+    }
+    Ok(None)
+}
+
+/// Start of the display row holding `byte`
+///
+/// Walks back to the start of its line, then forward through the rows of a line long enough
+/// to have been split.
+async fn row_containing(accessor: &dyn FileAccessor, byte: u64, sep: u8) -> Result<u64> {
+    let mut line_start = 0;
+    let mut end = byte;
+    while end > 0 {
+        let from = end.saturating_sub(LINE_SCAN_BYTES as u64);
+        let chunk = accessor.read_bytes(from, (end - from) as usize).await?;
+        if let Some(at) = memchr::memrchr(sep, &chunk) {
+            line_start = from + at as u64 + 1;
+            break;
+        }
+        end = from;
+    }
+
+    let mut row = line_start;
+    loop {
+        let next = accessor.next_page_start(row, 1).await?;
+        if next <= row || next > byte {
+            return Ok(row);
+        }
+        row = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::{FileAccessorFactory, SEGMENT_BYTES};
+    use crate::search::{RipgrepEngine, SearchEngine, SearchOptions};
+    use std::io::Write;
+    use std::sync::Arc;
+    use tempfile::NamedTempFile;
+
+    async fn engine(content: &str) -> (NamedTempFile, RipgrepEngine) {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file.flush().unwrap();
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        (file, RipgrepEngine::new(Arc::from(accessor)))
+    }
+
+    fn multiline() -> SearchOptions {
+        SearchOptions {
+            multiline: true,
+            ..SearchOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn matches_across_lines_are_found_at_their_first_line() {
+        let content = "ERROR one\n\tat Worker.run\nERROR two\n\tat Dispatcher.send\n\
+                       INFO ok\nERROR three\n\tat Dispatcher.send\n";
+        let (_file, engine) = engine(content).await;
+        let pattern = r"ERROR[^\n]*\n\tat Dispatcher";
+        let second = content.find("ERROR two").unwrap() as u64;
+        let third = content.find("ERROR three").unwrap() as u64;
+
+        let found = engine
+            .search_from(pattern, 0, &multiline(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(second));
+        // Stepping on from the line after a match finds the next one, not the same one again
+        let found = engine
+            .search_from(pattern, second + 10, &multiline(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(third));
+        let found = engine
+            .search_prev(pattern, third, &multiline(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(second));
+        let found = engine
+            .search_prev(pattern, second, &multiline(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+
+        // Line by line the same pattern finds nothing
+        let found = engine
+            .search_from(pattern, 0, &SearchOptions::default(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+    }
+
+    #[tokio::test]
+    async fn matches_are_found_across_chunk_boundaries() {
+        // Filler lines push the only match over the first chunk's end
+        let filler = "filler line of some length\n".repeat(CHUNK_BYTES / 27 + 5);
+        let content = format!("{filler}header\nframe\n{filler}");
+        let (_file, engine) = engine(&content).await;
+        let header = filler.len() as u64;
+
+        let found = engine
+            .search_from(r"^header\nframe$", 0, &multiline(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(header));
+        let found = engine
+            .search_prev(
+                r"^header\nframe$",
+                content.len() as u64,
+                &multiline(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(found, Some(header));
+        // `^` holds at line starts only, chunk boundaries included
+        let found = engine
+            .search_from(r"^ine of", 0, &multiline(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+    }
+
+    #[tokio::test]
+    async fn matches_in_long_lines_are_found_at_their_row() {
+        let content = format!("{}needle\nhaystack\n", "x".repeat(SEGMENT_BYTES * 2 + 10));
+        let (_file, engine) = engine(&content).await;
+        let found = engine
+            .search_from(r"needle\nhay", 0, &multiline(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(SEGMENT_BYTES as u64 * 2));
+    }
+}
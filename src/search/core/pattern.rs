@@ -0,0 +1,186 @@
+//! Turning typed text into patterns.
+
+use super::SearchOptions;
+use crate::error::{Result, RllessError};
+
+/// Expand the escapes supported in literal mode
+///
+/// The prompt cannot receive a raw tab, so `\t` stands for one and `\\` for a literal backslash.
+/// Any other backslash sequence is kept verbatim.
+pub(super) fn unescape_literal(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Build a pattern that matches `text` verbatim under the given options
+///
+/// Regex mode escapes metacharacters; literal mode only doubles backslashes so the `\t`
+/// escape is not triggered.
+pub fn verbatim_pattern(text: &str, options: &SearchOptions) -> String {
+    if options.regex_mode {
+        escape_regex(text)
+    } else {
+        text.replace('\\', "\\\\")
+    }
+}
+
+/// Whether `pattern` holds an uppercase character, the smart-case test
+///
+/// In a regex the letter after a backslash names a class or assertion (`\S`, `\B`) and the
+/// name after `\p` a Unicode class, so neither counts.
+pub(super) fn has_uppercase(pattern: &str, regex: bool) -> bool {
+    if !regex {
+        return pattern.chars().any(char::is_uppercase);
+    }
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            if ch.is_uppercase() {
+                return true;
+            }
+            continue;
+        }
+        // `\pL` names a one-letter class, `\p{Lu}` a longer one
+        if matches!(chars.next(), Some('p' | 'P')) && chars.next() == Some('{') {
+            chars.by_ref().find(|&ch| ch == '}');
+        }
+    }
+    false
+}
+
+/// Check that `pattern` parses as a regex, saying what is wrong and where if it does not
+///
+/// The coordinator runs this before sending a search, so a typo never replaces the active
+/// search; the engine runs it again before compiling. Compiling can still fail past this point,
+/// for example on the size limit.
+pub fn check_regex(pattern: &str) -> Result<()> {
+    let error = match regex_syntax::Parser::new().parse(pattern) {
+        Ok(_) => return Ok(()),
+        Err(error) => error,
+    };
+    let (message, offset) = match &error {
+        regex_syntax::Error::Parse(error) => (error.kind().to_string(), error.span().start.offset),
+        regex_syntax::Error::Translate(error) => {
+            (error.kind().to_string(), error.span().start.offset)
+        }
+        other => (other.to_string(), 0),
+    };
+    Err(RllessError::Regex {
+        message,
+        position: pattern[..offset].chars().count() + 1,
+    })
+}
+
+/// Escape special regex characters in a literal string
+///
+/// This is a simple implementation to escape common regex metacharacters
+/// for literal string matching.
+pub(super) fn escape_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '\\' | '^' | '$' | '.' | '[' | ']' | '|' | '(' | ')' | '?' | '*' | '+' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::core::tests::create_test_engine;
+    use crate::search::core::SearchEngine;
+
+    #[test]
+    fn test_literal_tab_escape() {
+        let engine = create_test_engine();
+        let options = SearchOptions {
+            regex_mode: false,
+            ..Default::default()
+        };
+
+        let matches = engine
+            .get_line_matches(r"a\tb", "xa\tb a\\tb", &options)
+            .unwrap();
+        assert_eq!(matches, vec![(1, 4)]);
+
+        // Escaped backslash searches for the two-character sequence
+        let matches = engine
+            .get_line_matches(r"a\\tb", "xa\tb a\\tb", &options)
+            .unwrap();
+        assert_eq!(matches, vec![(5, 9)]);
+    }
+
+    #[test]
+    fn test_uppercase_in_class_escapes_does_not_count() {
+        assert!(!has_uppercase(r"error\s+\S\W\D\B\p{Lu}\PL", true));
+        assert!(has_uppercase(r"\sError", true));
+        assert!(has_uppercase(r"\p{Lu}X", true));
+        // Literal text has no escapes of that kind
+        assert!(has_uppercase(r"\S", false));
+        assert!(!has_uppercase("ünïcode", false));
+        assert!(has_uppercase("Ünïcode", false));
+    }
+
+    #[test]
+    fn test_regex_errors_say_what_and_where() {
+        for (pattern, expected) in [
+            ("foo(", "regex error: unclosed group at position 4"),
+            (
+                "[abc",
+                "regex error: unclosed character class at position 1",
+            ),
+            (
+                "a{2,1}",
+                "regex error: invalid repetition count range, the start must be <= the end at position 2",
+            ),
+            ("é*+(?z)", "regex error: unrecognized flag at position 6"),
+            (
+                r"\p{Nope}",
+                "regex error: Unicode property not found at position 1",
+            ),
+        ] {
+            let error = check_regex(pattern).unwrap_err();
+            assert!(matches!(error, RllessError::Regex { .. }));
+            assert_eq!(error.summary(), expected, "{}", pattern);
+        }
+        assert!(check_regex(r"foo\(|[a-z]{2,}").is_ok());
+    }
+
+    #[test]
+    fn test_verbatim_pattern_round_trips() {
+        let engine = create_test_engine();
+        let text = r"a.b\t(c)*";
+        let line = format!("xx {} a-b", text);
+
+        for regex_mode in [true, false] {
+            let options = SearchOptions {
+                regex_mode,
+                ..Default::default()
+            };
+            let pattern = verbatim_pattern(text, &options);
+            let matches = engine.get_line_matches(&pattern, &line, &options).unwrap();
+            assert_eq!(matches, vec![(3, 3 + text.len())]);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Duplicate-run navigation.
+//!
+//! Finds the nearest line that differs from the line at the viewport top, skipping runs of
+//! identical output. Lines are compared as raw bytes read in bounded chunks through
+//! `FileAccessor::read_bytes`, so long runs never require materialising `String`s.
+
+use crate::error::Result;
+use crate::file_handler::FileAccessor;
+use crate::util::check_cancelled;
+use std::sync::atomic::AtomicBool;
+
+/// Number of bytes fetched from the accessor per refill.
+const SCAN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Byte offset of the first line after `start` whose content differs from the line at `start`.
+///
+/// Returns `None` when every remaining line repeats the starting line.
+pub async fn next_distinct_line(
+    accessor: &dyn FileAccessor,
+    start: u64,
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<Option<u64>> {
+    next_distinct_line_chunked(accessor, start, cancel_flag, SCAN_CHUNK_SIZE).await
+}
+
+/// Byte offset of the nearest line before `start` whose content differs from the line at `start`.
+///
+/// This lands on the last line of the preceding run, so a forward skip from the result returns
+/// to the start of the run containing `start`. Returns `None` when the run reaches the top.
+pub async fn prev_distinct_line(
+    accessor: &dyn FileAccessor,
+    start: u64,
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<Option<u64>> {
+    prev_distinct_line_chunked(accessor, start, cancel_flag, SCAN_CHUNK_SIZE).await
+}
+
+async fn next_distinct_line_chunked(
+    accessor: &dyn FileAccessor,
+    start: u64,
+    cancel_flag: Option<&AtomicBool>,
+    chunk_size: usize,
+) -> Result<Option<u64>> {
+    let file_size = accessor.file_size();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut buf_start = start;
+    let mut cursor = 0usize;
+    let mut reference: Option<Vec<u8>> = None;
+    let sep = accessor.line_terminator().byte();
+
+    loop {
+        check_cancelled(cancel_flag)?;
+
+        let (line_end, next_cursor) = match memchr::memchr(sep, &buf[cursor..]) {
+            Some(offset) => (cursor + offset, cursor + offset + 1),
+            None => {
+                let read_pos = buf_start + buf.len() as u64;
+                let chunk = if read_pos < file_size {
+                    accessor.read_bytes(read_pos, chunk_size).await?
+                } else {
+                    Vec::new()
+                };
+                if !chunk.is_empty() {
+                    // Drop consumed lines before growing the buffer
+                    buf.drain(..cursor);
+                    buf_start += cursor as u64;
+                    cursor = 0;
+                    buf.extend_from_slice(&chunk);
+                    continue;
+                }
+                if cursor >= buf.len() {
+                    return Ok(None);
+                }
+                // Final line without a trailing newline
+                (buf.len(), buf.len())
+            }
+        };
+
+        let line = &buf[cursor..line_end];
+        match reference.as_deref() {
+            None => reference = Some(line.to_vec()),
+            Some(reference) if reference != line => return Ok(Some(buf_start + cursor as u64)),
+            Some(_) => {}
+        }
+        cursor = next_cursor;
+    }
+}
+
+async fn prev_distinct_line_chunked(
+    accessor: &dyn FileAccessor,
+    start: u64,
+    cancel_flag: Option<&AtomicBool>,
+    chunk_size: usize,
+) -> Result<Option<u64>> {
+    if start == 0 {
+        return Ok(None);
+    }
+
+    let reference = read_line_at(accessor, start, chunk_size).await?;
+    let sep = accessor.line_terminator().byte();
+
+    // Once filled, `buf` covers `[buf_start, line_end)`, where `line_end` is one past the newline
+    // that terminates the line being examined.
+    let mut buf: Vec<u8> = Vec::new();
+    let mut buf_start = start;
+    let mut line_end = start;
+
+    while line_end > 0 {
+        check_cancelled(cancel_flag)?;
+
+        let newline_at = line_end - 1;
+        if newline_at >= buf_start {
+            let content_end = (newline_at - buf_start) as usize;
+            if let Some(newline) = memchr::memrchr(sep, &buf[..content_end]) {
+                let line_start = buf_start + newline as u64 + 1;
+                if buf[newline + 1..content_end] != reference[..] {
+                    return Ok(Some(line_start));
+                }
+                line_end = line_start;
+                buf.truncate(newline + 1);
+                continue;
+            }
+            if buf_start == 0 {
+                return Ok((buf[..content_end] != reference[..]).then_some(0));
+            }
+        }
+
+        let read_start = buf_start.saturating_sub(chunk_size as u64);
+        let mut chunk = accessor
+            .read_bytes(read_start, (buf_start - read_start) as usize)
+            .await?;
+        chunk.extend_from_slice(&buf);
+        buf = chunk;
+        buf_start = read_start;
+    }
+
+    Ok(None)
+}
+
+/// Read the full line starting at `start`, without its newline.
+async fn read_line_at(
+    accessor: &dyn FileAccessor,
+    start: u64,
+    chunk_size: usize,
+) -> Result<Vec<u8>> {
+    let mut line = Vec::new();
+    let mut position = start;
+    let sep = accessor.line_terminator().byte();
+    loop {
+        let chunk = accessor.read_bytes(position, chunk_size).await?;
+        if chunk.is_empty() {
+            return Ok(line);
+        }
+        if let Some(newline) = memchr::memchr(sep, &chunk) {
+            line.extend_from_slice(&chunk[..newline]);
+            return Ok(line);
+        }
+        position += chunk.len() as u64;
+        line.extend_from_slice(&chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RllessError;
+    use crate::file_handler::FileAccessorFactory;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn create_test_file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().expect("Failed to create temp file");
+        file.write_all(content.as_bytes())
+            .expect("Failed to write test data");
+        file.flush().expect("Failed to flush test data");
+        file
+    }
+
+    /// `head`, then 5000 copies of `tick`, then `tail` (no trailing newline).
+    fn duplicate_run_fixture() -> (String, u64, u64) {
+        let mut content = String::from("head\n");
+        let run_start = content.len() as u64;
+        content.push_str(&"tick\n".repeat(5000));
+        let tail_start = content.len() as u64;
+        content.push_str("tail");
+        (content, run_start, tail_start)
+    }
+
+    #[tokio::test]
+    async fn test_forward_skips_duplicate_run() {
+        let (content, run_start, tail_start) = duplicate_run_fixture();
+        let file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+
+        for chunk_size in [3, 64, SCAN_CHUNK_SIZE] {
+            let skip = |start| next_distinct_line_chunked(&accessor, start, None, chunk_size);
+            assert_eq!(skip(0).await.unwrap(), Some(run_start));
+            assert_eq!(skip(run_start).await.unwrap(), Some(tail_start));
+            // Starting mid-run still lands on the first line after it
+            assert_eq!(skip(run_start + 5 * 100).await.unwrap(), Some(tail_start));
+            assert_eq!(skip(tail_start).await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reverse_skips_duplicate_run() {
+        let (content, run_start, tail_start) = duplicate_run_fixture();
+        let file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let last_tick = tail_start - 5;
+
+        for chunk_size in [3, 64, SCAN_CHUNK_SIZE] {
+            let skip = |start| prev_distinct_line_chunked(&accessor, start, None, chunk_size);
+            assert_eq!(skip(tail_start).await.unwrap(), Some(last_tick));
+            assert_eq!(skip(last_tick).await.unwrap(), Some(0));
+            assert_eq!(skip(run_start).await.unwrap(), Some(0));
+            assert_eq!(skip(0).await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_reaching_file_edges() {
+        let file = create_test_file("same\nsame\nsame\n");
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+
+        assert_eq!(next_distinct_line(&accessor, 0, None).await.unwrap(), None);
+        assert_eq!(prev_distinct_line(&accessor, 10, None).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_scan_honours_cancel_flag() {
+        let (content, _, _) = duplicate_run_fixture();
+        let file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let cancel = AtomicBool::new(true);
+
+        let result = next_distinct_line(&accessor, 0, Some(&cancel)).await;
+        assert!(matches!(result, Err(RllessError::Cancelled)));
+    }
+}
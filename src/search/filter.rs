@@ -0,0 +1,300 @@
+//! Filtered paging.
+//!
+//! While a filter is active the viewport only shows lines matching the filter pattern. These
+//! helpers translate viewport requests into positions within that filtered sequence, stepping
+//! between matching lines with the accessor's line-oriented match scans.
+
+use crate::error::Result;
+use crate::file_handler::FileAccessor;
+use crate::render::protocol::{ViewportRequest, MIN_PAGE_LINES};
+use std::sync::atomic::AtomicBool;
+
+/// Per-line match function, in the shape expected by `FileAccessor::find_next_match`.
+pub type LineMatcher<'a> = &'a (dyn for<'l> Fn(&'l str) -> Vec<(usize, usize)> + Send + Sync);
+
+/// A page of lines that passed the filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilteredPage {
+    /// Byte offset of the first line on the page (0 when nothing matches).
+    pub top_byte: u64,
+    pub lines: Vec<String>,
+    /// Byte offset each of `lines` starts at
+    pub starts: Vec<u64>,
+    /// No matching line follows the page.
+    pub at_eof: bool,
+}
+
+/// View of a file restricted to the lines accepted by `matcher`.
+pub struct LineFilter<'a> {
+    accessor: &'a dyn FileAccessor,
+    matcher: LineMatcher<'a>,
+    cancel_flag: Option<&'a AtomicBool>,
+}
+
+impl<'a> LineFilter<'a> {
+    /// Scans for matching lines stop with `RllessError::Cancelled` once `cancel_flag` is set.
+    pub fn new(
+        accessor: &'a dyn FileAccessor,
+        matcher: LineMatcher<'a>,
+        cancel_flag: Option<&'a AtomicBool>,
+    ) -> Self {
+        Self {
+            accessor,
+            matcher,
+            cancel_flag,
+        }
+    }
+
+    /// Load the page of matching lines selected by `top`.
+    ///
+    /// Mirrors unfiltered paging: absolute positions snap forward to the next matching line,
+    /// relative moves count matching lines only, and a short page at the end is pulled back so
+    /// the last page stays full.
+    pub async fn load_page(&self, top: ViewportRequest, page_lines: usize) -> Result<FilteredPage> {
+        let page_lines = page_lines.max(MIN_PAGE_LINES);
+        let Some(target) = self.resolve_target(top, page_lines).await? else {
+            return Ok(FilteredPage {
+                top_byte: 0,
+                lines: Vec::new(),
+                starts: Vec::new(),
+                at_eof: true,
+            });
+        };
+
+        let page = self.collect_page(target, page_lines).await?;
+        if page.lines.len() < page_lines {
+            if let Some(last) = self.last_page_start(page_lines).await? {
+                if last < target {
+                    return self.collect_page(last, page_lines).await;
+                }
+            }
+        }
+        Ok(page)
+    }
+
+    async fn resolve_target(&self, top: ViewportRequest, page_lines: usize) -> Result<Option<u64>> {
+        match top {
+            ViewportRequest::Absolute(byte) => match self.next_at_or_after(byte).await? {
+                Some(line) => Ok(Some(line)),
+                None => self.last_page_start(page_lines).await,
+            },
+            ViewportRequest::RelativeLines { anchor, lines } => {
+                // The anchor is normally the current top, which already matches; snap it in
+                // case the filter changed underneath it.
+                let anchor = match self.next_at_or_after(anchor).await? {
+                    Some(line) => line,
+                    None => match self.prev_before(anchor).await? {
+                        Some(line) => line,
+                        None => return Ok(None),
+                    },
+                };
+                let target = if lines >= 0 {
+                    self.step_forward(anchor, lines as usize).await?
+                } else {
+                    self.step_backward(anchor, lines.unsigned_abs() as usize)
+                        .await?
+                };
+                Ok(Some(target))
+            }
+            ViewportRequest::EndOfFile => self.last_page_start(page_lines).await,
+        }
+    }
+
+    /// Collect up to `page_lines` matching lines starting at the matching line `top`.
+    async fn collect_page(&self, top: u64, page_lines: usize) -> Result<FilteredPage> {
+        let mut lines = Vec::with_capacity(page_lines);
+        let mut starts = Vec::with_capacity(page_lines);
+        let mut next = Some(top);
+        while let Some(line_start) = next {
+            if lines.len() == page_lines {
+                break;
+            }
+            let line = self.accessor.read_from_byte(line_start, 1).await?;
+            starts.extend(line.iter().map(|_| line_start));
+            lines.extend(line);
+            next = self.next_after(line_start).await?;
+        }
+
+        Ok(FilteredPage {
+            top_byte: top,
+            lines,
+            starts,
+            at_eof: next.is_none(),
+        })
+    }
+
+    /// Start of the last page of matching lines, or `None` if no line matches.
+    async fn last_page_start(&self, page_lines: usize) -> Result<Option<u64>> {
+        let Some(last) = self.prev_before(self.accessor.file_size()).await? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            self.step_backward(last, page_lines.saturating_sub(1))
+                .await?,
+        ))
+    }
+
+    async fn step_forward(&self, mut current: u64, count: usize) -> Result<u64> {
+        for _ in 0..count {
+            match self.next_after(current).await? {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+        Ok(current)
+    }
+
+    async fn step_backward(&self, mut current: u64, count: usize) -> Result<u64> {
+        for _ in 0..count {
+            match self.prev_before(current).await? {
+                Some(prev) => current = prev,
+                None => break,
+            }
+        }
+        Ok(current)
+    }
+
+    /// First matching line starting at or after `byte`.
+    async fn next_at_or_after(&self, byte: u64) -> Result<Option<u64>> {
+        self.accessor
+            .find_next_match(byte, self.matcher, self.cancel_flag, None)
+            .await
+    }
+
+    /// First matching line after the line starting at `line_start`.
+    async fn next_after(&self, line_start: u64) -> Result<Option<u64>> {
+        let following = self.accessor.next_page_start(line_start, 1).await?;
+        if following <= line_start || following >= self.accessor.file_size() {
+            return Ok(None);
+        }
+        self.next_at_or_after(following).await
+    }
+
+    /// Nearest matching line that starts before the line at `byte`.
+    async fn prev_before(&self, byte: u64) -> Result<Option<u64>> {
+        self.accessor
+            .find_prev_match(byte, self.matcher, self.cancel_flag, None)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileAccessorFactory;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn create_test_file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().expect("Failed to create temp file");
+        file.write_all(content.as_bytes())
+            .expect("Failed to write test data");
+        file.flush().expect("Failed to flush test data");
+        file
+    }
+
+    fn contains_keep(line: &str) -> Vec<(usize, usize)> {
+        line.find("keep")
+            .map(|start| vec![(start, start + 4)])
+            .unwrap_or_default()
+    }
+
+    /// Ten lines where every third one (0, 3, 6, 9) contains `keep`.
+    fn fixture() -> String {
+        (0..10)
+            .map(|i| {
+                if i % 3 == 0 {
+                    format!("keep {i}\n")
+                } else {
+                    format!("drop {i}\n")
+                }
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pages_only_contain_matching_lines() {
+        let file = create_test_file(&fixture());
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let filter = LineFilter::new(&accessor, &contains_keep, None);
+
+        let page = filter
+            .load_page(ViewportRequest::Absolute(0), 2)
+            .await
+            .unwrap();
+        assert_eq!(page.lines, vec!["keep 0", "keep 3"]);
+        assert!(!page.at_eof);
+
+        let page = filter
+            .load_page(
+                ViewportRequest::RelativeLines {
+                    anchor: page.top_byte,
+                    lines: 1,
+                },
+                2,
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.lines, vec!["keep 3", "keep 6"]);
+        assert_eq!(page.top_byte, 21);
+
+        let page = filter
+            .load_page(ViewportRequest::EndOfFile, 2)
+            .await
+            .unwrap();
+        assert_eq!(page.lines, vec!["keep 6", "keep 9"]);
+        assert!(page.at_eof);
+    }
+
+    #[tokio::test]
+    async fn test_scrolling_past_the_end_keeps_last_page_full() {
+        let file = create_test_file(&fixture());
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let filter = LineFilter::new(&accessor, &contains_keep, None);
+
+        let page = filter
+            .load_page(
+                ViewportRequest::RelativeLines {
+                    anchor: 0,
+                    lines: 10,
+                },
+                3,
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.lines, vec!["keep 3", "keep 6", "keep 9"]);
+        assert!(page.at_eof);
+
+        // An absolute position on a non-matching line snaps forward
+        let page = filter
+            .load_page(ViewportRequest::Absolute(7), 1)
+            .await
+            .unwrap();
+        assert_eq!(page.lines, vec!["keep 3"]);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_scan_stops_the_page_load() {
+        let file = create_test_file(&fixture());
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let cancel_flag = AtomicBool::new(true);
+        let filter = LineFilter::new(&accessor, &contains_keep, Some(&cancel_flag));
+
+        let result = filter.load_page(ViewportRequest::Absolute(0), 2).await;
+        assert!(matches!(result, Err(crate::error::RllessError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_no_matching_lines_yields_empty_page() {
+        let file = create_test_file("alpha\nbeta\n");
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let filter = LineFilter::new(&accessor, &contains_keep, None);
+
+        let page = filter
+            .load_page(ViewportRequest::Absolute(0), 5)
+            .await
+            .unwrap();
+        assert!(page.lines.is_empty());
+        assert!(page.at_eof);
+    }
+}
@@ -0,0 +1,89 @@
+//! Match frequency probe.
+//!
+//! A pattern such as `e` matches nearly every line, and searching a multi-gigabyte file for it
+//! keeps the worker busy counting and highlighting for minutes. Before a new search runs, the
+//! worker samples one window of the file and estimates how many matches each megabyte holds, so
+//! the user can confirm before committing to the search.
+
+use crate::error::Result;
+use crate::file_handler::FileAccessor;
+use crate::search::filter::LineMatcher;
+
+/// Size of the sampled window. Files smaller than this are never probed.
+pub const PROBE_BYTES: usize = 1024 * 1024;
+
+/// Matches in the megabyte next to `origin`
+///
+/// The window starts at `origin` (or ends there when searching backward) and is shifted to stay
+/// inside the file. Every match in it is counted, so the figure is measured rather than
+/// extrapolated. Returns `None` for files too small to probe.
+pub async fn matches_per_mb(
+    accessor: &dyn FileAccessor,
+    matcher: LineMatcher<'_>,
+    origin: u64,
+    backward: bool,
+) -> Result<Option<u64>> {
+    let file_size = accessor.file_size();
+    let window = PROBE_BYTES as u64;
+    if file_size < window {
+        return Ok(None);
+    }
+    let start = if backward {
+        origin.min(file_size).saturating_sub(window)
+    } else {
+        origin.min(file_size - window)
+    };
+
+    let sep = accessor.line_terminator().byte();
+    let bytes = accessor.read_bytes(start, PROBE_BYTES).await?;
+    let count = bytes
+        .split(|&byte| byte == sep)
+        .map(|line| matcher(&String::from_utf8_lossy(line)).len() as u64)
+        .sum();
+    Ok(Some(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileAccessorFactory;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn count_e(line: &str) -> Vec<(usize, usize)> {
+        line.match_indices('e').map(|(i, _)| (i, i + 1)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_counts_matches_in_window_near_origin() {
+        // First megabyte is all "e", the second has none
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&"e\n".repeat(PROBE_BYTES / 2).into_bytes())
+            .unwrap();
+        file.write_all(&"x\n".repeat(PROBE_BYTES / 2).into_bytes())
+            .unwrap();
+        file.flush().unwrap();
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        let half = (PROBE_BYTES / 2) as u64;
+        let end = 2 * PROBE_BYTES as u64;
+
+        let probe = |origin, backward| matches_per_mb(&accessor, &count_e, origin, backward);
+        assert_eq!(probe(0, false).await.unwrap(), Some(half));
+        assert_eq!(probe(half, false).await.unwrap(), Some(half / 2));
+        assert_eq!(probe(end, false).await.unwrap(), Some(0));
+        assert_eq!(probe(end, true).await.unwrap(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_small_files_are_not_probed() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"eeee\n").unwrap();
+        file.flush().unwrap();
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+
+        assert_eq!(
+            matches_per_mb(&accessor, &count_e, 0, false).await.unwrap(),
+            None
+        );
+    }
+}
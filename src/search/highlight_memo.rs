@@ -0,0 +1,306 @@
+//! Per-line highlight memo.
+//!
+//! Consecutive viewports mostly show the same lines (scrolling by one line keeps all but one of
+//! them), so the worker remembers the spans computed for the previous viewport, keyed by the
+//! bytes of the line, and only runs the matcher for lines it has not just seen.
+
+use crate::error::Result;
+use crate::render::protocol::SearchHighlightSpec;
+use crate::search::memory_budget::{MemoryBudget, MemoryFootprint};
+use std::collections::{HashMap, HashSet};
+
+/// Per-line highlights `T` of the most recent viewport, valid for one search/filter spec pair.
+///
+/// Entries are charged against a `MemoryBudget`. When a charge fails, entries from the previous
+/// viewport are evicted first; if the budget is still too small the memo passes results through
+/// without keeping them.
+#[derive(Debug, Default)]
+pub struct HighlightMemo<T: MemoryFootprint> {
+    search: Option<SearchHighlightSpec>,
+    filter: Option<SearchHighlightSpec>,
+    entries: HashMap<Box<[u8]>, T>,
+    budget: MemoryBudget,
+    /// Bytes charged to `budget` for `entries`
+    charged: usize,
+}
+
+impl<T: Clone + MemoryFootprint> HighlightMemo<T> {
+    pub fn with_budget(budget: MemoryBudget) -> Self {
+        Self {
+            search: None,
+            filter: None,
+            entries: HashMap::new(),
+            budget,
+            charged: 0,
+        }
+    }
+
+    /// Highlights for `lines`, asking `compute` only about lines missing from the memo
+    ///
+    /// `compute` is called once, with the indices of the missing lines (the first of any that
+    /// repeat), and returns their highlights in the same order. The memo is dropped whenever
+    /// either spec differs from the previous call, and afterwards holds the lines of this
+    /// viewport that fit in the budget.
+    pub fn highlights_for<L: AsRef<[u8]>>(
+        &mut self,
+        search: Option<&SearchHighlightSpec>,
+        filter: Option<&SearchHighlightSpec>,
+        lines: &[L],
+        compute: impl FnOnce(&[usize]) -> Result<Vec<T>>,
+    ) -> Result<Vec<T>> {
+        if self.search.as_ref() != search || self.filter.as_ref() != filter {
+            self.evict_stale();
+            self.search = search.cloned();
+            self.filter = filter.cloned();
+        }
+
+        let mut seen = HashSet::with_capacity(lines.len());
+        let missing: Vec<usize> = (0..lines.len())
+            .filter(|&index| {
+                let line = lines[index].as_ref();
+                !self.entries.contains_key(line) && seen.insert(line)
+            })
+            .collect();
+        let computed: HashMap<&[u8], T> = if missing.is_empty() {
+            HashMap::new()
+        } else {
+            let computed = compute(&missing)?;
+            debug_assert_eq!(computed.len(), missing.len());
+            missing
+                .iter()
+                .map(|&index| lines[index].as_ref())
+                .zip(computed)
+                .collect()
+        };
+
+        let mut current = HashMap::with_capacity(lines.len());
+        let mut current_charged = 0;
+        let mut highlights = Vec::with_capacity(lines.len());
+        for line in lines {
+            let line = line.as_ref();
+            if let Some(spans) = current.get(line) {
+                highlights.push(T::clone(spans));
+                continue;
+            }
+            let (key, spans, kept) = match self.entries.remove_entry(line) {
+                Some((key, spans)) => {
+                    // Already charged; the charge moves along with the entry
+                    let cost = entry_cost(&key, &spans);
+                    self.charged -= cost;
+                    current_charged += cost;
+                    (key, spans, true)
+                }
+                None => (Box::from(line), computed[line].clone(), false),
+            };
+            highlights.push(spans.clone());
+
+            let cost = entry_cost(&key, &spans);
+            if kept || self.charge(cost) {
+                if !kept {
+                    current_charged += cost;
+                }
+                current.insert(key, spans);
+            }
+        }
+
+        self.evict_stale();
+        self.entries = current;
+        self.charged = current_charged;
+        Ok(highlights)
+    }
+
+    /// Forget every line, as when something besides the two specs changes the spans
+    pub fn clear(&mut self) {
+        self.evict_stale();
+    }
+
+    /// Charge `cost`, evicting the previous viewport's entries if that makes room
+    fn charge(&mut self, cost: usize) -> bool {
+        if self.budget.try_charge(cost) {
+            return true;
+        }
+        self.evict_stale();
+        self.budget.try_charge(cost)
+    }
+
+    /// Drop every entry held in `entries` and return its charge
+    fn evict_stale(&mut self) {
+        self.entries.clear();
+        self.budget.release(self.charged);
+        self.charged = 0;
+    }
+}
+
+impl<T: MemoryFootprint> Drop for HighlightMemo<T> {
+    fn drop(&mut self) {
+        self.budget.release(self.charged);
+    }
+}
+
+/// Bytes charged for one memo entry
+fn entry_cost<T: MemoryFootprint>(key: &[u8], spans: &T) -> usize {
+    std::mem::size_of::<Box<[u8]>>() + key.len() + spans.footprint()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::protocol::{HighlightKind, HighlightSpan};
+    use crate::search::{PatternModifiers, SearchOptions};
+    use std::sync::Arc;
+
+    fn spec(pattern: &str) -> SearchHighlightSpec {
+        SearchHighlightSpec {
+            pattern: Arc::from(pattern),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        }
+    }
+
+    fn lines(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    /// A `compute` for `page` spanning each line whole, and the lines it was asked about
+    fn whole_lines<'a>(
+        page: &'a [String],
+        computed: &'a mut Vec<String>,
+    ) -> impl FnOnce(&[usize]) -> Result<Vec<Vec<HighlightSpan>>> + 'a {
+        move |missing: &[usize]| {
+            Ok(missing
+                .iter()
+                .map(|&index| {
+                    computed.push(page[index].clone());
+                    vec![(0, page[index].len(), HighlightKind::Search)]
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_only_new_lines_are_computed() {
+        let mut memo = HighlightMemo::<Vec<HighlightSpan>>::default();
+        let search = spec("a");
+        let mut computed = Vec::new();
+
+        let page = lines(&["a1", "a2", "a3"]);
+        memo.highlights_for(
+            Some(&search),
+            None,
+            &page,
+            whole_lines(&page, &mut computed),
+        )
+        .unwrap();
+        // Scrolling by one line reuses the two lines still visible, and a repeat is asked once
+        let page = lines(&["a2", "a3", "a4", "a4"]);
+        let highlights = memo
+            .highlights_for(
+                Some(&search),
+                None,
+                &page,
+                whole_lines(&page, &mut computed),
+            )
+            .unwrap();
+
+        assert_eq!(computed, vec!["a1", "a2", "a3", "a4"]);
+        assert_eq!(highlights.len(), 4);
+        assert_eq!(highlights[2], vec![(0, 2, HighlightKind::Search)]);
+        assert_eq!(highlights[3], highlights[2]);
+    }
+
+    #[test]
+    fn test_spec_change_invalidates_memo() {
+        let mut memo = HighlightMemo::<Vec<HighlightSpan>>::default();
+        let mut computed = Vec::new();
+        let page = lines(&["x", "y"]);
+
+        for (search, filter) in [
+            (spec("x"), None),
+            (spec("x"), None),
+            (spec("y"), None),
+            (spec("y"), Some(spec("x"))),
+        ] {
+            memo.highlights_for(
+                Some(&search),
+                filter.as_ref(),
+                &page,
+                whole_lines(&page, &mut computed),
+            )
+            .unwrap();
+        }
+
+        assert_eq!(computed.len(), 6);
+    }
+
+    #[test]
+    fn test_stale_entries_are_evicted_before_new_ones_are_dropped() {
+        let search = spec("a");
+        let spans: Vec<HighlightSpan> = vec![(0, 2, HighlightKind::Search)];
+        let cost = entry_cost(b"a1", &spans);
+        // Room for exactly two lines
+        let budget = MemoryBudget::new(2 * cost);
+        let mut memo = HighlightMemo::with_budget(budget.clone());
+        let mut computed = Vec::new();
+
+        let page = lines(&["a1", "a2"]);
+        memo.highlights_for(
+            Some(&search),
+            None,
+            &page,
+            whole_lines(&page, &mut computed),
+        )
+        .unwrap();
+        assert_eq!(budget.used(), 2 * cost);
+
+        // The previous page is evicted to make room for the new one
+        let page = lines(&["a3", "a4"]);
+        memo.highlights_for(
+            Some(&search),
+            None,
+            &page,
+            whole_lines(&page, &mut computed),
+        )
+        .unwrap();
+        assert_eq!(budget.used(), 2 * cost);
+        assert!(memo.entries.contains_key(&b"a3"[..]) && memo.entries.contains_key(&b"a4"[..]));
+
+        // A third line does not fit; it is still highlighted, just not kept
+        let page = lines(&["a3", "a4", "a5"]);
+        let highlights = memo
+            .highlights_for(
+                Some(&search),
+                None,
+                &page,
+                whole_lines(&page, &mut computed),
+            )
+            .unwrap();
+        assert_eq!(highlights[2], vec![(0, 2, HighlightKind::Search)]);
+        assert!(!memo.entries.contains_key(&b"a5"[..]));
+        assert_eq!(budget.used(), 2 * cost);
+
+        drop(memo);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_tiny_budget_passes_results_through() {
+        let budget = MemoryBudget::new(1);
+        let mut memo = HighlightMemo::<Vec<HighlightSpan>>::with_budget(budget.clone());
+        let mut computed = Vec::new();
+        let page = lines(&["x", "y"]);
+
+        for _ in 0..2 {
+            let highlights = memo
+                .highlights_for(
+                    Some(&spec("x")),
+                    None,
+                    &page,
+                    whole_lines(&page, &mut computed),
+                )
+                .unwrap();
+            assert_eq!(highlights[1], vec![(0, 1, HighlightKind::Search)]);
+        }
+        assert_eq!(computed.len(), 4);
+        assert_eq!(budget.used(), 0);
+    }
+}
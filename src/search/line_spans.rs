@@ -0,0 +1,185 @@
+//! Per-line highlight span assembly.
+//!
+//! Turns the raw search, kept-pattern and filter match ranges of one line into the sorted,
+//! non-overlapping spans the renderer draws. The span count is bounded so that a pattern matching thousands of
+//! times per line (`/e` over a 20KB line) cannot stall a frame.
+
+use crate::render::protocol::{HighlightKind, HighlightSpan};
+
+/// Most highlight spans kept for a single line; later spans are dropped.
+pub const MAX_LINE_SPANS: usize = 512;
+
+/// Highlight spans for one line, and whether some were dropped to stay within `MAX_LINE_SPANS`.
+///
+/// Ranges may arrive in any order and may overlap; overlapping and adjacent ranges of the same
+/// kind are merged before the cap is applied.
+pub fn line_spans(
+    search: Vec<(usize, usize)>,
+    filter: Vec<(usize, usize)>,
+) -> (Vec<HighlightSpan>, bool) {
+    layered_spans(vec![
+        (HighlightKind::Search, search),
+        (HighlightKind::Filter, filter),
+    ])
+}
+
+/// `line_spans` for any number of layers of ranges, given in precedence order
+///
+/// Each layer's ranges are clipped around those of the layers before it.
+pub fn layered_spans(
+    layers: Vec<(HighlightKind, Vec<(usize, usize)>)>,
+) -> (Vec<HighlightSpan>, bool) {
+    let layers: Vec<_> = layers
+        .into_iter()
+        .map(|(kind, ranges)| (kind, coalesce_ranges(ranges)))
+        .collect();
+    let mut spans = merge_highlights(&layers);
+    let truncated = spans.len() > MAX_LINE_SPANS;
+    spans.truncate(MAX_LINE_SPANS);
+    (spans, truncated)
+}
+
+/// Sort `ranges` and join the ones that overlap or touch, dropping empty ranges.
+fn coalesce_ranges(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.retain(|&(start, end)| start < end);
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Combine layers of sorted, disjoint ranges into non-overlapping styled spans.
+///
+/// Earlier layers take precedence: the ranges of later ones are clipped around them.
+fn merge_highlights(layers: &[(HighlightKind, Vec<(usize, usize)>)]) -> Vec<HighlightSpan> {
+    let mut merged: Vec<HighlightSpan> = Vec::new();
+    // Ranges of the layers placed so far, sorted and disjoint
+    let mut taken: Vec<(usize, usize)> = Vec::new();
+
+    for (kind, ranges) in layers {
+        for &(start, end) in ranges {
+            let mut cursor = start;
+            for &(taken_start, taken_end) in &taken {
+                if taken_end <= cursor || taken_start >= end {
+                    continue;
+                }
+                if taken_start > cursor {
+                    merged.push((cursor, taken_start, *kind));
+                }
+                cursor = taken_end;
+            }
+            if cursor < end {
+                merged.push((cursor, end, *kind));
+            }
+        }
+        taken.extend_from_slice(ranges);
+        taken = coalesce_ranges(taken);
+    }
+
+    merged.sort_by_key(|&(start, ..)| start);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use HighlightKind::{Filter, Kept1, Kept2, Search};
+
+    fn merge(search: &[(usize, usize)], filter: &[(usize, usize)]) -> Vec<HighlightSpan> {
+        merge_highlights(&[(Search, search.to_vec()), (Filter, filter.to_vec())])
+    }
+
+    #[test]
+    fn test_coalesce_overlapping_adjacent_and_unordered_ranges() {
+        // Overlapping
+        assert_eq!(coalesce_ranges(vec![(0, 5), (3, 8)]), vec![(0, 8)]);
+        // Adjacent
+        assert_eq!(coalesce_ranges(vec![(0, 1), (1, 2), (2, 3)]), vec![(0, 3)]);
+        // Out of order, with one range nested in another and an empty one dropped
+        assert_eq!(
+            coalesce_ranges(vec![(10, 12), (4, 4), (0, 6), (2, 3)]),
+            vec![(0, 6), (10, 12)]
+        );
+    }
+
+    #[test]
+    fn test_merge_clips_filter_around_search() {
+        // Filter match spans the whole search match and extends past it on both sides
+        assert_eq!(
+            merge(&[(3, 5), (7, 12)], &[(0, 10)]),
+            vec![
+                (0, 3, Filter),
+                (3, 5, Search),
+                (5, 7, Filter),
+                (7, 12, Search)
+            ]
+        );
+        // Fully covered filter ranges disappear; disjoint ones are kept as-is
+        assert_eq!(
+            merge(&[(0, 8)], &[(2, 6), (10, 12)]),
+            vec![(0, 8, Search), (10, 12, Filter)]
+        );
+        assert_eq!(merge(&[], &[(1, 2)]), vec![(1, 2, Filter)]);
+    }
+
+    #[test]
+    fn test_line_spans_merges_unordered_input_before_clipping() {
+        let (spans, truncated) = line_spans(vec![(7, 12), (3, 5), (4, 6)], vec![(8, 10), (0, 9)]);
+        assert_eq!(
+            spans,
+            vec![
+                (0, 3, Filter),
+                (3, 6, Search),
+                (6, 7, Filter),
+                (7, 12, Search)
+            ]
+        );
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn test_kept_patterns_sit_between_search_and_filter() {
+        let (spans, _) = layered_spans(vec![
+            (Search, vec![(4, 6)]),
+            (Kept1, vec![(0, 5), (10, 14)]),
+            (Kept2, vec![(12, 16)]),
+            (Filter, vec![(0, 20)]),
+        ]);
+        assert_eq!(
+            spans,
+            vec![
+                (0, 4, Kept1),
+                (4, 6, Search),
+                (6, 10, Filter),
+                (10, 14, Kept1),
+                (14, 16, Kept2),
+                (16, 20, Filter)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_spans_caps_span_count() {
+        // Every other byte matches, so nothing merges
+        let ranges: Vec<_> = (0..MAX_LINE_SPANS + 10)
+            .map(|i| (2 * i, 2 * i + 1))
+            .collect();
+        let (spans, truncated) = line_spans(ranges, Vec::new());
+        assert_eq!(spans.len(), MAX_LINE_SPANS);
+        assert_eq!(spans.last(), Some(&(1022, 1023, Search)));
+        assert!(truncated);
+
+        // A match on every byte collapses into a single span
+        let ranges: Vec<_> = (0..4096).map(|i| (i, i + 1)).collect();
+        assert_eq!(
+            line_spans(ranges, Vec::new()),
+            (vec![(0, 4096, Search)], false)
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Shared memory budget for the worker's caches.
+//!
+//! Caches charge the bytes they hold against one `MemoryBudget` and release them when entries
+//! are dropped, so their combined size stays within a fixed cap no matter how long a session
+//! runs. A charge that would exceed the cap fails; the cache then evicts its own stale entries
+//! and retries, and if that is still not enough it keeps computing results without storing them.
+//! Current usage is listed by `:metrics`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Default cap for the worker's caches, well inside the crate's 100 MB target.
+pub const DEFAULT_CACHE_BUDGET: usize = 32 * 1024 * 1024;
+
+/// Cloneable handle to a shared byte counter with a cap.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    inner: Arc<BudgetInner>,
+}
+
+#[derive(Debug)]
+struct BudgetInner {
+    used: AtomicUsize,
+    cap: usize,
+}
+
+impl MemoryBudget {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            inner: Arc::new(BudgetInner {
+                used: AtomicUsize::new(0),
+                cap,
+            }),
+        }
+    }
+
+    /// Charge `bytes` unless that would take usage past the cap
+    ///
+    /// Returns whether the charge was made.
+    pub fn try_charge(&self, bytes: usize) -> bool {
+        let cap = self.inner.cap;
+        self.inner
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(bytes).filter(|&total| total <= cap)
+            })
+            .is_ok()
+    }
+
+    /// Return `bytes` charged earlier
+    pub fn release(&self, bytes: usize) {
+        let _ = self
+            .inner
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+
+    /// Bytes currently charged
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Acquire)
+    }
+
+    pub fn cap(&self) -> usize {
+        self.inner.cap
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_BUDGET)
+    }
+}
+
+/// Approximate number of bytes a cached value occupies, heap included.
+pub trait MemoryFootprint {
+    fn footprint(&self) -> usize;
+}
+
+impl<T> MemoryFootprint for Vec<T> {
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>() + self.capacity() * std::mem::size_of::<T>()
+    }
+}
+
+impl<A: MemoryFootprint> MemoryFootprint for (A, bool) {
+    fn footprint(&self) -> usize {
+        self.0.footprint() + std::mem::size_of::<bool>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charges_stop_at_the_cap_and_are_shared_by_clones() {
+        let budget = MemoryBudget::new(100);
+        let other = budget.clone();
+
+        assert!(budget.try_charge(60));
+        assert!(!other.try_charge(41));
+        assert!(other.try_charge(40));
+        assert_eq!(budget.used(), 100);
+
+        budget.release(70);
+        assert_eq!(other.used(), 30);
+        // Over-releasing never wraps below zero
+        other.release(1000);
+        assert_eq!(budget.used(), 0);
+        assert!(!budget.try_charge(usize::MAX));
+    }
+}
@@ -0,0 +1,117 @@
+//! Inline modifiers at the start of a search pattern.
+//!
+//! `\c` ignores case and `\C` tells case apart for this one search, whatever `-i` and smart case
+//! say, and `\l` takes the rest of the pattern literally. They may be combined, as in `\C\lfoo`,
+//! and where two disagree the later one wins. The coordinator strips them before the pattern
+//! reaches the engine and keeps them with the search, so its highlights and `n`/`N` use the same
+//! options and a later change to the global ones does not undo them. `\\c` is a backslash
+//! followed by `c` and passes through untouched.
+
+use crate::search::SearchOptions;
+
+/// Option overrides a pattern asked for with inline modifiers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatternModifiers {
+    /// `\C` (true) or `\c` (false)
+    pub case_sensitive: Option<bool>,
+    /// `\l`
+    pub literal: bool,
+}
+
+impl PatternModifiers {
+    /// Modifiers at the start of `pattern` and the pattern without them
+    pub fn strip(pattern: &str) -> (Self, &str) {
+        let mut modifiers = Self::default();
+        let mut rest = pattern;
+        while let Some(after) = rest.strip_prefix('\\') {
+            match after.chars().next() {
+                Some('c') => modifiers.case_sensitive = Some(false),
+                Some('C') => modifiers.case_sensitive = Some(true),
+                Some('l') => modifiers.literal = true,
+                _ => break,
+            }
+            rest = &after[1..];
+        }
+        (modifiers, rest)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `options` with these overrides applied
+    pub fn apply(&self, options: SearchOptions) -> SearchOptions {
+        let mut options = options;
+        if let Some(case_sensitive) = self.case_sensitive {
+            options.case_sensitive = case_sensitive;
+            options.smart_case = false;
+        }
+        if self.literal {
+            options.regex_mode = false;
+        }
+        options
+    }
+
+    /// The modifiers as typed, such as `\C\l`, or an empty string
+    pub fn label(&self) -> String {
+        let case = match self.case_sensitive {
+            Some(true) => "\\C",
+            Some(false) => "\\c",
+            None => "",
+        };
+        let literal = if self.literal { "\\l" } else { "" };
+        format!("{}{}", case, literal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leading_modifiers_are_stripped() {
+        let (modifiers, rest) = PatternModifiers::strip("\\cError");
+        assert_eq!(modifiers.case_sensitive, Some(false));
+        assert_eq!(rest, "Error");
+
+        let (modifiers, rest) = PatternModifiers::strip("\\c\\l\\Ca.b");
+        assert_eq!(
+            modifiers,
+            PatternModifiers {
+                case_sensitive: Some(true),
+                literal: true,
+            }
+        );
+        assert_eq!(rest, "a.b");
+        assert_eq!(modifiers.label(), "\\C\\l");
+
+        // Escaped, elsewhere in the pattern, or another escape: left alone
+        for pattern in ["\\\\cat", "a\\c", "\\d+\\c", "\\", ""] {
+            let (modifiers, rest) = PatternModifiers::strip(pattern);
+            assert!(modifiers.is_empty());
+            assert_eq!(rest, pattern);
+        }
+    }
+
+    #[test]
+    fn overrides_replace_the_global_options() {
+        let global = SearchOptions {
+            case_sensitive: false,
+            smart_case: true,
+            ..SearchOptions::default()
+        };
+        let (sensitive, _) = PatternModifiers::strip("\\C");
+        let options = sensitive.apply(global.clone());
+        assert!(options.case_sensitive);
+        assert!(!options.smart_case);
+        assert!(options.regex_mode);
+
+        let (literal, _) = PatternModifiers::strip("\\l");
+        let options = literal.apply(global.clone());
+        assert!(!options.regex_mode);
+        assert!(!options.case_sensitive);
+        assert!(options.smart_case);
+
+        assert_eq!(PatternModifiers::default().apply(global.clone()), global);
+    }
+}
@@ -0,0 +1,126 @@
+//! Fixed sets of alternatives loaded from a file.
+//!
+//! `--patterns-from FILE` and `:loadset FILE` search for any of the lines of FILE at once, e.g. a
+//! list of request ids. Entries are matched as fixed strings. The set travels as a single pattern
+//! with one entry per line, flagged by `SearchOptions::pattern_set`; the engine compiles it into
+//! one matcher (see `core::matcher`), so highlighting and `n`/`N` work as for any other search.
+
+use crate::error::{Result, RllessError};
+use std::path::Path;
+
+/// Most entries a set may have
+pub const MAX_SET_ENTRIES: usize = 10_000;
+
+/// Largest set file accepted, in bytes
+pub const MAX_SET_BYTES: usize = 512 * 1024;
+
+/// Entries of a pattern set and the file they came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternSet {
+    name: String,
+    entries: Vec<String>,
+}
+
+impl PatternSet {
+    /// Read the set in `path`, one entry per line
+    pub async fn load(path: &Path) -> Result<Self> {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let meta = tokio::fs::metadata(path)
+            .await
+            .map_err(|e| RllessError::file_error(format!("Failed to read {}", name), e))?;
+        if meta.len() > MAX_SET_BYTES as u64 {
+            return Err(too_large(&name, meta.len() as usize));
+        }
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| RllessError::file_error(format!("Failed to read {}", name), e))?;
+        Self::parse(name, &text)
+    }
+
+    /// Build a set from `text`, skipping blank lines and repeated entries
+    pub fn parse(name: impl Into<String>, text: &str) -> Result<Self> {
+        let name = name.into();
+        if text.len() > MAX_SET_BYTES {
+            return Err(too_large(&name, text.len()));
+        }
+        let mut seen = std::collections::HashSet::new();
+        let entries: Vec<String> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty() && seen.insert(*line))
+            .map(str::to_string)
+            .collect();
+        if entries.is_empty() {
+            return Err(RllessError::search(format!("No patterns in {}", name)));
+        }
+        if entries.len() > MAX_SET_ENTRIES {
+            return Err(RllessError::search(format!(
+                "Too many patterns in {} ({}, limit {})",
+                name,
+                entries.len(),
+                MAX_SET_ENTRIES
+            )));
+        }
+        Ok(Self { name, entries })
+    }
+
+    /// File name the set was loaded from
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// The set as a search pattern, for options with `pattern_set` on
+    pub fn pattern(&self) -> String {
+        self.entries.join("\n")
+    }
+}
+
+/// Entries of a pattern searched with `pattern_set` on
+pub fn set_entries(pattern: &str) -> impl Iterator<Item = &str> {
+    pattern.split('\n')
+}
+
+/// Short description of a set pattern for the status line
+pub fn describe_set(pattern: &str) -> String {
+    format!("set of {} patterns", set_entries(pattern).count())
+}
+
+fn too_large(name: &str, bytes: usize) -> RllessError {
+    RllessError::search(format!(
+        "Pattern set {} too large ({} bytes, limit {})",
+        name, bytes, MAX_SET_BYTES
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_skips_blank_and_repeated_lines() {
+        let set = PatternSet::parse("ids.txt", "req-1\r\n\nreq-2\n  \nreq-1\n").unwrap();
+        assert_eq!(set.entries(), ["req-1", "req-2"]);
+        assert_eq!(set.pattern(), "req-1\nreq-2");
+        assert_eq!(describe_set(&set.pattern()), "set of 2 patterns");
+    }
+
+    #[test]
+    fn parse_rejects_empty_and_oversized_sets() {
+        let empty = PatternSet::parse("ids.txt", "\n \n").unwrap_err();
+        assert!(empty.to_string().contains("No patterns in ids.txt"));
+
+        let many: String = (0..=MAX_SET_ENTRIES).map(|i| format!("{i}\n")).collect();
+        let error = PatternSet::parse("ids.txt", &many).unwrap_err();
+        assert!(error.to_string().contains("Too many patterns in ids.txt"));
+
+        let huge = "x".repeat(MAX_SET_BYTES + 1);
+        let error = PatternSet::parse("ids.txt", &huge).unwrap_err();
+        assert!(error.to_string().contains("too large"));
+    }
+}
@@ -0,0 +1,216 @@
+//! Named patterns for the search prompt.
+//!
+//! `@name` in a regex search stands for a stored pattern: the built-ins below or ones given with
+//! `--pattern NAME=REGEX`. Expansion happens in the coordinator right before the search is sent,
+//! so the history keeps the shorthand as typed. `@@` stands for a literal `@`, and an `@` right
+//! after a letter or digit (as in `user@host`) is left alone.
+
+use grep_regex::RegexMatcher;
+
+/// Patterns every library starts with, in listing order
+const BUILT_IN: &[(&str, &str)] = &[
+    (
+        "ts",
+        r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?",
+    ),
+    (
+        "uuid",
+        r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+    ),
+    (
+        "ipv4",
+        r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b",
+    ),
+    ("email", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}"),
+];
+
+/// One named pattern and whether it was given on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedPattern {
+    pub name: String,
+    pub regex: String,
+    pub user: bool,
+}
+
+/// Named patterns available as `@name` at the search prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternLibrary {
+    entries: Vec<NamedPattern>,
+}
+
+impl Default for PatternLibrary {
+    fn default() -> Self {
+        Self {
+            entries: BUILT_IN
+                .iter()
+                .map(|&(name, regex)| NamedPattern {
+                    name: name.to_string(),
+                    regex: regex.to_string(),
+                    user: false,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl PatternLibrary {
+    /// Add `definition` of the form `NAME=REGEX`, replacing a pattern of the same name
+    pub fn define(&mut self, definition: &str) -> Result<(), String> {
+        let Some((name, regex)) = definition.split_once('=') else {
+            return Err(format!("expected NAME=REGEX, got {}", definition));
+        };
+        let name = name.strip_prefix('@').unwrap_or(name);
+        if name.is_empty() || !name.chars().all(is_name_char) {
+            return Err(format!(
+                "pattern names use letters, digits, '_' and '-': {}",
+                name
+            ));
+        }
+        RegexMatcher::new(regex).map_err(|err| format!("@{}: {}", name, err))?;
+
+        let entry = NamedPattern {
+            name: name.to_string(),
+            regex: regex.to_string(),
+            user: true,
+        };
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+        Ok(())
+    }
+
+    /// Every pattern, built-ins first
+    pub fn entries(&self) -> &[NamedPattern] {
+        &self.entries
+    }
+
+    /// Patterns given on the command line
+    pub fn user_entries(&self) -> impl Iterator<Item = &NamedPattern> {
+        self.entries.iter().filter(|entry| entry.user)
+    }
+
+    fn lookup(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.regex.as_str())
+    }
+
+    /// Replace the `@name` tokens in `pattern` with their regexes
+    ///
+    /// Returns `None` when there is nothing to expand, and an error naming the first unknown
+    /// pattern.
+    pub fn expand(&self, pattern: &str) -> Result<Option<String>, String> {
+        if !pattern.contains('@') {
+            return Ok(None);
+        }
+
+        let mut expanded = String::with_capacity(pattern.len());
+        let mut changed = false;
+        let mut rest = pattern;
+        while let Some(at) = rest.find('@') {
+            let (before, tail) = rest.split_at(at);
+            expanded.push_str(before);
+            let tail = &tail[1..];
+
+            if let Some(after) = tail.strip_prefix('@') {
+                expanded.push('@');
+                changed = true;
+                rest = after;
+                continue;
+            }
+
+            let attached = expanded.chars().next_back().is_some_and(is_word_char);
+            let name_len = tail
+                .find(|ch: char| !is_name_char(ch))
+                .unwrap_or(tail.len());
+            if attached || name_len == 0 {
+                expanded.push('@');
+                rest = tail;
+                continue;
+            }
+
+            let name = &tail[..name_len];
+            let Some(regex) = self.lookup(name) else {
+                return Err(format!("Unknown pattern: @{} (see :patterns)", name));
+            };
+            expanded.push_str("(?:");
+            expanded.push_str(regex);
+            expanded.push(')');
+            changed = true;
+            rest = &tail[name_len..];
+        }
+        expanded.push_str(rest);
+
+        Ok(changed.then_some(expanded))
+    }
+}
+
+fn is_name_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_' || ch == '-'
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grep_matcher::Matcher;
+
+    #[test]
+    fn test_names_expand_to_their_regex() {
+        let library = PatternLibrary::default();
+        let expanded = library.expand("id=@uuid at @ts").unwrap().unwrap();
+        assert!(expanded.starts_with("id=(?:[0-9a-fA-F]{8}-"));
+        assert!(expanded.contains(" at (?:\\d{4}-"));
+
+        let matcher = RegexMatcher::new(&expanded).unwrap();
+        let line = "id=123e4567-e89b-12d3-a456-426614174000 at 2024-06-01T12:00:00.5Z";
+        assert!(matcher.is_match(line.as_bytes()).unwrap());
+        assert!(!matcher.is_match(b"id=nope at 2024-06-01").unwrap());
+
+        assert_eq!(library.expand("plain text").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_names_are_reported() {
+        let library = PatternLibrary::default();
+        assert_eq!(
+            library.expand("@ts @nope"),
+            Err("Unknown pattern: @nope (see :patterns)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_literal_at_signs_are_kept() {
+        let library = PatternLibrary::default();
+        // `@@` is one `@`; an `@` inside a word or without a name is not a token
+        assert_eq!(library.expand("@@ts").unwrap(), Some("@ts".to_string()));
+        assert_eq!(library.expand("user@example.com").unwrap(), None);
+        assert_eq!(library.expand("a @ b").unwrap(), None);
+        assert_eq!(
+            library.expand("from user@host via @ipv4").unwrap().unwrap(),
+            format!("from user@host via (?:{})", library.lookup("ipv4").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_user_patterns_extend_and_replace_built_ins() {
+        let mut library = PatternLibrary::default();
+        library.define("req=req-[0-9]+").unwrap();
+        library.define("@ts=\\d+").unwrap();
+        assert_eq!(
+            library.expand("@req @ts").unwrap(),
+            Some("(?:req-[0-9]+) (?:\\d+)".to_string())
+        );
+        let user: Vec<&str> = library.user_entries().map(|e| e.name.as_str()).collect();
+        assert_eq!(user, ["ts", "req"]);
+
+        assert!(library.define("no-equals").is_err());
+        assert!(library.define("bad name=x").is_err());
+        assert!(library.define("broken=(").is_err());
+    }
+}
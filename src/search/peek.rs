@@ -0,0 +1,173 @@
+//! Full-line peek.
+//!
+//! Viewport rows stop at the terminal edge and long lines are split into 4 KiB rows, so the line
+//! overlay fetches the whole logical line around a row through `FileAccessor::read_bytes`. Very
+//! long lines are capped at `MAX_PEEK_BYTES`. The match list previews a line with
+//! `read_line_start`, which reads just its beginning.
+
+use crate::error::Result;
+use crate::file_handler::FileAccessor;
+
+/// Most bytes of a single line fetched for the overlay.
+pub const MAX_PEEK_BYTES: usize = 1024 * 1024;
+
+/// Number of bytes fetched from the accessor per read.
+const SCAN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Logical line containing a display row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeekedLine {
+    /// Byte offset of the first byte in `text`
+    pub start: u64,
+    /// Line content without its newline; invalid UTF-8 is replaced
+    pub text: String,
+    /// The line was longer than the byte cap and only part of it is in `text`
+    pub truncated: bool,
+}
+
+/// The line containing the row starting at `row_start`, up to `MAX_PEEK_BYTES` long.
+///
+/// When the line is longer than the cap, at most half of the window lies before `row_start`, so
+/// the row itself is always part of the text.
+pub async fn read_full_line(accessor: &dyn FileAccessor, row_start: u64) -> Result<PeekedLine> {
+    read_full_line_chunked(accessor, row_start, MAX_PEEK_BYTES, SCAN_CHUNK_SIZE).await
+}
+
+/// The first `max_bytes` of the line starting at `line_start`, for a one-row preview
+pub async fn read_line_start(
+    accessor: &dyn FileAccessor,
+    line_start: u64,
+    max_bytes: usize,
+) -> Result<PeekedLine> {
+    read_full_line_chunked(accessor, line_start, max_bytes, max_bytes.max(1)).await
+}
+
+async fn read_full_line_chunked(
+    accessor: &dyn FileAccessor,
+    row_start: u64,
+    max_bytes: usize,
+    chunk_size: usize,
+) -> Result<PeekedLine> {
+    let file_size = accessor.file_size();
+    let row_start = row_start.min(file_size);
+    let sep = accessor.line_terminator().byte();
+
+    // Walk back to the line start, keeping at most half the budget for bytes before the row
+    let back_budget = (max_bytes / 2) as u64;
+    let mut start = row_start;
+    let mut found_line_start = start == 0;
+    while !found_line_start && row_start - start < back_budget {
+        let len = (start.min(chunk_size as u64)).min(back_budget - (row_start - start));
+        let chunk = accessor.read_bytes(start - len, len as usize).await?;
+        match memchr::memrchr(sep, &chunk) {
+            Some(offset) => {
+                start = start - len + offset as u64 + 1;
+                found_line_start = true;
+            }
+            None => {
+                start -= len;
+                found_line_start = start == 0;
+            }
+        }
+    }
+
+    let mut bytes = accessor
+        .read_bytes(start, (row_start - start) as usize)
+        .await?;
+    let mut found_line_end = false;
+    let mut pos = row_start;
+    while bytes.len() < max_bytes && pos < file_size {
+        let chunk = accessor
+            .read_bytes(pos, chunk_size.min(max_bytes - bytes.len()))
+            .await?;
+        if chunk.is_empty() {
+            break;
+        }
+        if let Some(offset) = memchr::memchr(sep, &chunk) {
+            bytes.extend_from_slice(&chunk[..offset]);
+            found_line_end = true;
+            break;
+        }
+        pos += chunk.len() as u64;
+        bytes.extend_from_slice(&chunk);
+    }
+    let at_line_end = found_line_end || start + bytes.len() as u64 >= file_size;
+
+    Ok(PeekedLine {
+        start,
+        text: String::from_utf8_lossy(&bytes).into_owned(),
+        truncated: !found_line_start || !at_line_end,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileAccessorFactory;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn create_test_file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().expect("Failed to create temp file");
+        file.write_all(content.as_bytes())
+            .expect("Failed to write test data");
+        file.flush().expect("Failed to flush test data");
+        file
+    }
+
+    #[tokio::test]
+    async fn test_reads_whole_line_around_a_row() {
+        let long = "x".repeat(10_000);
+        let content = format!("first\n{long}\nlast");
+        let file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+
+        for chunk_size in [7, 4096, SCAN_CHUNK_SIZE] {
+            let peek = |row| read_full_line_chunked(&accessor, row, MAX_PEEK_BYTES, chunk_size);
+            // A row in the middle of the long line still yields all of it
+            let line = peek(6 + 4096).await.unwrap();
+            assert_eq!(
+                (line.start, line.text.len(), line.truncated),
+                (6, 10_000, false)
+            );
+            assert_eq!(peek(0).await.unwrap().text, "first");
+            let last = peek(content.len() as u64 - 4).await.unwrap();
+            assert_eq!((last.text.as_str(), last.truncated), ("last", false));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_line_start_stops_at_the_budget() {
+        let content = format!("first\n{}\nlast", "y".repeat(500));
+        let file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+
+        let line = read_line_start(&accessor, 6, 64).await.unwrap();
+        assert_eq!((line.start, line.text.len()), (6, 64));
+        assert!(line.truncated);
+        let line = read_line_start(&accessor, 0, 64).await.unwrap();
+        assert_eq!((line.text.as_str(), line.truncated), ("first", false));
+    }
+
+    #[tokio::test]
+    async fn test_caps_lines_longer_than_the_budget() {
+        let content = format!("{}\n", "y".repeat(1000));
+        let file = create_test_file(&content);
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+
+        let line = read_full_line_chunked(&accessor, 0, 100, 16).await.unwrap();
+        assert_eq!(
+            (line.start, line.text.len(), line.truncated),
+            (0, 100, true)
+        );
+
+        // Deep inside the line the window keeps half the budget before the row
+        let line = read_full_line_chunked(&accessor, 600, 100, 16)
+            .await
+            .unwrap();
+        assert_eq!(
+            (line.start, line.text.len(), line.truncated),
+            (550, 100, true)
+        );
+    }
+}
@@ -0,0 +1,439 @@
+//! Time-based navigation (`:time`).
+//!
+//! `:time 14:32:05` or `:time 2024-06-01T14:32:05` goes to the first log record stamped at or
+//! after that time. Records are the lines matching `--record-start`, the ones `[` and `]` move
+//! between, and a record's time is the ISO-8601 date and time it starts with; an opening `[` is
+//! skipped, and so is anything after the seconds but a fraction of them. A time without a date
+//! is taken on the day of the first record at or after the viewport top.
+//!
+//! Log timestamps only go forward, so the record is found by bisecting the file: each probe
+//! reads from a midpoint to the first record after it, and the file's first record counts as
+//! one too. When two probes disagree, a later one stamped earlier, the caller scans the file from
+//! the top instead.
+
+use crate::error::Result;
+use crate::file_handler::FileAccessor;
+use crate::util::check_cancelled;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::fmt;
+use std::sync::atomic::AtomicBool;
+
+/// Number of bytes fetched from the accessor per refill.
+const SCAN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Time given to `:time`, with or without a date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeTarget {
+    pub date: Option<NaiveDate>,
+    pub time: NaiveTime,
+}
+
+impl TimeTarget {
+    /// `14:32`, `14:32:05`, `14:32:05.250`, or any of them after `2024-06-01T` or `2024-06-01 `
+    pub fn parse(text: &str) -> Option<Self> {
+        let bytes = text.trim().as_bytes();
+        match parse_date(bytes) {
+            Some((date, rest)) => {
+                let rest = rest
+                    .strip_prefix(b"T")
+                    .or_else(|| rest.strip_prefix(b" "))?;
+                let (time, rest) = parse_time(rest)?;
+                rest.is_empty().then_some(TimeTarget {
+                    date: Some(date),
+                    time,
+                })
+            }
+            None => {
+                let (time, rest) = parse_time(bytes)?;
+                rest.is_empty().then_some(TimeTarget { date: None, time })
+            }
+        }
+    }
+
+    /// The target on `date` unless it names a day of its own
+    pub fn on(self, date: NaiveDate) -> NaiveDateTime {
+        self.date.unwrap_or(date).and_time(self.time)
+    }
+}
+
+impl fmt::Display for TimeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.date {
+            Some(date) => write!(f, "{} {}", date, self.time),
+            None => write!(f, "{}", self.time),
+        }
+    }
+}
+
+/// The date and time `line` starts with, after an optional `[`
+pub fn leading_timestamp(line: &[u8]) -> Option<NaiveDateTime> {
+    let line = line.strip_prefix(b"[").unwrap_or(line);
+    let (date, rest) = parse_date(line)?;
+    let rest = rest
+        .strip_prefix(b"T")
+        .or_else(|| rest.strip_prefix(b" "))?;
+    let (time, _) = parse_time(rest)?;
+    Some(date.and_time(time))
+}
+
+/// Where bisecting the file for a time ended up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bisected {
+    /// Start of the first record at or after the target, or none
+    Found(Option<u64>),
+    /// The probes met timestamps going backwards, so bisecting cannot be trusted
+    Unordered,
+}
+
+/// Bisect the file for the first record stamped at or after `target`
+///
+/// `is_record` tells the lines that start a record, which are the only ones whose timestamps
+/// count. Each probe costs one read from a midpoint up to the next record.
+pub async fn bisect(
+    accessor: &dyn FileAccessor,
+    is_record: &(dyn Fn(&str) -> bool + Send + Sync),
+    target: NaiveDateTime,
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<Bisected> {
+    let file_size = accessor.file_size();
+    // The first record counts as a probe, so a stretch anywhere stamped before it is noticed
+    let mut probes: Vec<(u64, NaiveDateTime)> =
+        first_record(accessor, 0, file_size, is_record, cancel_flag)
+            .await?
+            .into_iter()
+            .collect();
+    let (mut low, mut high) = (0, file_size);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match first_record(accessor, mid, high, is_record, cancel_flag).await? {
+            Some((start, stamp)) => {
+                let out_of_order = probes.iter().any(|&(other, other_stamp)| {
+                    (other < start && other_stamp > stamp) || (other > start && other_stamp < stamp)
+                });
+                if out_of_order {
+                    return Ok(Bisected::Unordered);
+                }
+                probes.push((start, stamp));
+                if stamp >= target {
+                    high = mid;
+                } else {
+                    low = start + 1;
+                }
+            }
+            // Nothing stamped between here and the part already known to be late enough
+            None => high = mid,
+        }
+    }
+    match first_record(accessor, low, file_size, is_record, cancel_flag).await? {
+        Some((_, stamp)) if stamp < target => Ok(Bisected::Unordered),
+        found => Ok(Bisected::Found(found.map(|(start, _)| start))),
+    }
+}
+
+/// Date of the first record at or after `from`
+pub async fn date_from(
+    accessor: &dyn FileAccessor,
+    from: u64,
+    is_record: &(dyn Fn(&str) -> bool + Send + Sync),
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<Option<NaiveDate>> {
+    let found = first_record(accessor, from, accessor.file_size(), is_record, cancel_flag).await?;
+    Ok(found.map(|(_, stamp)| stamp.date()))
+}
+
+/// Start and timestamp of the first record starting in `from..end`
+async fn first_record(
+    accessor: &dyn FileAccessor,
+    from: u64,
+    end: u64,
+    is_record: &(dyn Fn(&str) -> bool + Send + Sync),
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<Option<(u64, NaiveDateTime)>> {
+    let mut lines = LineReader::starting_at(accessor, from).await?;
+    while let Some((start, line)) = lines.next_line().await? {
+        check_cancelled(cancel_flag)?;
+        if start >= end {
+            break;
+        }
+        let Some(stamp) = leading_timestamp(line) else {
+            continue;
+        };
+        if is_record(&String::from_utf8_lossy(line)) {
+            return Ok(Some((start, stamp)));
+        }
+    }
+    Ok(None)
+}
+
+/// `YYYY-MM-DD` at the start of `bytes`, and what follows it
+fn parse_date(bytes: &[u8]) -> Option<(NaiveDate, &[u8])> {
+    let (year, rest) = digits(bytes, 4)?;
+    let (month, rest) = digits(rest.strip_prefix(b"-")?, 2)?;
+    let (day, rest) = digits(rest.strip_prefix(b"-")?, 2)?;
+    Some((NaiveDate::from_ymd_opt(year as i32, month, day)?, rest))
+}
+
+/// `HH:MM`, `HH:MM:SS` or `HH:MM:SS.fff` (or `,fff`) at the start of `bytes`, and what follows
+fn parse_time(bytes: &[u8]) -> Option<(NaiveTime, &[u8])> {
+    let (hour, rest) = digits(bytes, 2)?;
+    let (minute, mut rest) = digits(rest.strip_prefix(b":")?, 2)?;
+    let mut second = 0;
+    let mut nanos = 0;
+    if let Some((seconds, after)) = rest.strip_prefix(b":").and_then(|rest| digits(rest, 2)) {
+        second = seconds;
+        rest = after;
+        if let Some(fraction) = rest.strip_prefix(b".").or_else(|| rest.strip_prefix(b",")) {
+            let len = fraction.iter().take_while(|b| b.is_ascii_digit()).count();
+            if len > 0 {
+                // Nanoseconds at most; further digits are read and dropped
+                let (kept, _) = digits(fraction, len.min(9))?;
+                nanos = kept * 10u32.pow(9 - len.min(9) as u32);
+                rest = &fraction[len..];
+            }
+        }
+    }
+    Some((
+        NaiveTime::from_hms_nano_opt(hour, minute, second, nanos)?,
+        rest,
+    ))
+}
+
+/// `count` ASCII digits at the start of `bytes` as a number, and what follows them
+fn digits(bytes: &[u8], count: usize) -> Option<(u32, &[u8])> {
+    if bytes.len() < count {
+        return None;
+    }
+    let (number, rest) = bytes.split_at(count);
+    if !number.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let value = number
+        .iter()
+        .fold(0, |value, &digit| value * 10 + u32::from(digit - b'0'));
+    Some((value, rest))
+}
+
+/// Lines of a file from some offset on, read in bounded chunks
+struct LineReader<'a> {
+    accessor: &'a dyn FileAccessor,
+    sep: u8,
+    buf: Vec<u8>,
+    /// File offset of `buf[0]`
+    buf_start: u64,
+    /// Start of the next line in `buf`
+    cursor: usize,
+}
+
+impl<'a> LineReader<'a> {
+    /// Lines starting at `from` or later; the rest of a line `from` falls inside is skipped
+    async fn starting_at(accessor: &'a dyn FileAccessor, from: u64) -> Result<Self> {
+        let mut reader = Self {
+            accessor,
+            sep: accessor.line_terminator().byte(),
+            buf: Vec::new(),
+            buf_start: from.saturating_sub(1),
+            cursor: 0,
+        };
+        if from > 0 {
+            // From one byte early, the first "line" ends at `from` exactly when a line starts
+            // there
+            reader.next_line().await?;
+        }
+        Ok(reader)
+    }
+
+    /// Offset and bytes of the next line, without its terminator
+    async fn next_line(&mut self) -> Result<Option<(u64, &[u8])>> {
+        loop {
+            if let Some(len) = memchr::memchr(self.sep, &self.buf[self.cursor..]) {
+                let start = self.cursor;
+                self.cursor += len + 1;
+                return Ok(Some((
+                    self.buf_start + start as u64,
+                    &self.buf[start..start + len],
+                )));
+            }
+            if !self.refill().await? {
+                if self.cursor >= self.buf.len() {
+                    return Ok(None);
+                }
+                // Final line without a terminator
+                let start = self.cursor;
+                self.cursor = self.buf.len();
+                return Ok(Some((self.buf_start + start as u64, &self.buf[start..])));
+            }
+        }
+    }
+
+    /// Read the next chunk; `false` at the end of the file
+    async fn refill(&mut self) -> Result<bool> {
+        let read_pos = self.buf_start + self.buf.len() as u64;
+        if read_pos >= self.accessor.file_size() {
+            return Ok(false);
+        }
+        let chunk = self.accessor.read_bytes(read_pos, SCAN_CHUNK_SIZE).await?;
+        if chunk.is_empty() {
+            return Ok(false);
+        }
+        // Drop consumed lines before growing the buffer
+        self.buf.drain(..self.cursor);
+        self.buf_start += self.cursor as u64;
+        self.cursor = 0;
+        self.buf.extend_from_slice(&chunk);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileAccessorFactory;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    #[test]
+    fn targets_and_line_stamps_parse() {
+        let target = TimeTarget::parse("14:32:05").unwrap();
+        assert_eq!(target.date, None);
+        assert_eq!(target.to_string(), "14:32:05");
+        let day = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert_eq!(target.on(day), at("2024-06-01 14:32:05"));
+
+        for text in ["2024-06-01T14:32:05", " 2024-06-01 14:32:05 "] {
+            let target = TimeTarget::parse(text).unwrap();
+            assert_eq!(target.on(NaiveDate::MIN), at("2024-06-01 14:32:05"));
+            assert_eq!(target.to_string(), "2024-06-01 14:32:05");
+        }
+        assert_eq!(
+            TimeTarget::parse("14:32").unwrap().on(day),
+            at("2024-06-01 14:32:00")
+        );
+        assert_eq!(
+            TimeTarget::parse("14:32:05.25").unwrap().on(day),
+            at("2024-06-01 14:32:05.250")
+        );
+        for bad in [
+            "",
+            "14",
+            "25:00",
+            "14:32:05x",
+            "2024-13-01T00:00",
+            "2024-06-01",
+        ] {
+            assert_eq!(TimeTarget::parse(bad), None, "{}", bad);
+        }
+
+        assert_eq!(
+            leading_timestamp(b"[2024-06-01T14:32:05,123Z] INFO up"),
+            Some(at("2024-06-01 14:32:05.123"))
+        );
+        assert_eq!(
+            leading_timestamp(b"2024-06-01 14:32 short"),
+            Some(at("2024-06-01 14:32:00"))
+        );
+        assert_eq!(leading_timestamp(b"  at Foo.bar(Foo.java:12)"), None);
+        assert_eq!(leading_timestamp(b"INFO 2024-06-01 14:32:05"), None);
+    }
+
+    /// One record a second from 10:00:00, each with a continuation line
+    fn log(seconds: std::ops::Range<u32>) -> String {
+        seconds
+            .map(|second| {
+                format!(
+                    "2024-06-01 10:{:02}:{:02} INFO step {}\n    detail {}\n",
+                    second / 60,
+                    second % 60,
+                    second,
+                    second
+                )
+            })
+            .collect()
+    }
+
+    async fn accessor(text: &str) -> (tempfile::NamedTempFile, Box<dyn FileAccessor>) {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), text).unwrap();
+        let accessor = FileAccessorFactory::create(file.path()).await.unwrap();
+        (file, Box::new(accessor))
+    }
+
+    #[tokio::test]
+    async fn bisecting_finds_the_first_record_at_or_after_the_target() {
+        let text = log(0..600);
+        let (_file, accessor) = accessor(&text).await;
+        let any = |_: &str| true;
+        for (target, expected) in [
+            (
+                "2024-06-01 10:04:10",
+                Some("2024-06-01 10:04:10 INFO step 250"),
+            ),
+            // Between records: the next one
+            (
+                "2024-06-01 10:04:10.5",
+                Some("2024-06-01 10:04:11 INFO step 251"),
+            ),
+            (
+                "2024-06-01 09:00:00",
+                Some("2024-06-01 10:00:00 INFO step 0"),
+            ),
+            (
+                "2024-06-01 10:09:59",
+                Some("2024-06-01 10:09:59 INFO step 599"),
+            ),
+            ("2024-06-01 10:10:00", None),
+        ] {
+            let found = bisect(accessor.as_ref(), &any, at(target), None)
+                .await
+                .unwrap();
+            let Bisected::Found(found) = found else {
+                panic!("unexpected {found:?} for {target}");
+            };
+            let line = found.map(|byte| {
+                let rest = &text[byte as usize..];
+                &rest[..rest.find('\n').unwrap()]
+            });
+            assert_eq!(line, expected, "{}", target);
+        }
+
+        // Only lines that start a record count
+        let info_only = |line: &str| line.ends_with("step 300");
+        let found = bisect(
+            accessor.as_ref(),
+            &info_only,
+            at("2024-06-01 10:00:00"),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            found,
+            Bisected::Found(Some(text.find("2024-06-01 10:05:00").unwrap() as u64))
+        );
+
+        let day = date_from(accessor.as_ref(), 5000, &any, None)
+            .await
+            .unwrap();
+        assert_eq!(day, NaiveDate::from_ymd_opt(2024, 6, 1));
+    }
+
+    #[tokio::test]
+    async fn timestamps_going_backwards_are_noticed() {
+        // The middle third was written by a host with a clock an hour behind
+        let text = log(0..200)
+            + &log(200..400).replace("2024-06-01 10:", "2024-06-01 09:")
+            + &log(400..600);
+        let (_file, accessor) = accessor(&text).await;
+        // The answer is the very first record, which bisecting alone would miss
+        let found = bisect(
+            accessor.as_ref(),
+            &|_| true,
+            at("2024-06-01 09:05:00"),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(found, Bisected::Unordered);
+    }
+}
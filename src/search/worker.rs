@@ -1,31 +1,69 @@
-use crate::error::{Result, RllessError};
+//! Search and paging worker.
+//!
+//! Runs the commands the coordinator sends, one at a time, against the file accessor.
+//!
+//! - `viewport`: loading pages with their highlights
+//! - `last_page`: locating the last page
+//! - `search`: running searches
+//! - `navigate`: moving between the matches of the active search
+//! - `lines`: duplicate runs, log records and the line overlay
+//! - `matches`: the match list
+//! - `switch`: moving on to another file
+//! - `export`: `:save` and `:pipe`, which run on a thread of their own
+
+use crate::error::RllessError;
 use crate::file_handler::FileAccessor;
 use crate::input::SearchDirection;
+use crate::logging::redact;
 use crate::render::protocol::{
-    MatchTraversal, RequestId, SearchCommand, SearchContext, SearchHighlightSpec, SearchResponse,
-    ViewportRequest,
+    HighlightSpan, RequestId, SearchCommand, SearchContext, SearchHighlightSpec, SearchResponse,
 };
-use crate::search::{RipgrepEngine, SearchEngine, SearchOptions};
+use crate::search::highlight_memo::HighlightMemo;
+use crate::search::memory_budget::MemoryBudget;
+use crate::search::{PatternModifiers, RipgrepEngine, SearchEngine};
+use crate::util::CancelRegistry;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::mpsc::{Receiver, Sender};
 
+mod export;
+mod last_page;
+mod lines;
+mod matches;
+mod navigate;
+mod search;
+mod switch;
+mod viewport;
+
+use switch::DiskState;
+
 /// Run the search/paging worker processing commands from the coordinator.
+///
+/// The worker's caches are charged against `cache_budget`.
 pub async fn search_worker_loop(
     mut rx: Receiver<SearchCommand>,
     tx: Sender<SearchResponse>,
     file_accessor: Arc<dyn FileAccessor>,
     search_engine: RipgrepEngine,
+    cache_budget: MemoryBudget,
 ) {
-    let mut state = WorkerState::new(file_accessor, search_engine);
+    let mut state = WorkerState::new(file_accessor, search_engine)
+        .with_cache_budget(cache_budget)
+        .with_progress(tx.clone());
 
     while let Some(cmd) = rx.recv().await {
         let outcome = state.handle_command(cmd).await;
-        if let Some(response) = outcome.response {
+        let mut closed = false;
+        for response in outcome.notice.into_iter().chain(outcome.response) {
             if tx.send(response).await.is_err() {
+                closed = true;
                 break;
             }
         }
+        if closed {
+            break;
+        }
 
         if outcome.done {
             break;
@@ -34,46 +72,187 @@ pub async fn search_worker_loop(
 }
 
 struct WorkerState {
+    // Accessor the commands read through: a snapshot of `shared_accessor` while one runs
     file_accessor: Arc<dyn FileAccessor>,
+    shared_accessor: Arc<dyn FileAccessor>,
     search_engine: RipgrepEngine,
     context: Option<SearchContext>,
     last_highlight: Option<Arc<SearchHighlightSpec>>,
-    // Cache of `(page_lines, start_byte)` for the last viewport to avoid redundant
-    // `last_page_start` computations while the viewport height stays constant.
-    last_page_start: Option<(usize, u64)>,
+    // Cache of `(page_lines, file_size, start_byte)` for the last viewport to avoid redundant
+    // `last_page_start` computations while the viewport height and the content stay constant.
+    last_page_start: Option<(usize, u64, u64)>,
+    /// Spans per line, and whether some were dropped for exceeding `MAX_LINE_SPANS`
+    highlight_memo: HighlightMemo<(Vec<HighlightSpan>, bool)>,
+    // Direction and viewport top of the last search that ran off the end of the file; repeating
+    // it from the same place wraps around to the other end.
+    wrap_armed: Option<(SearchDirection, u64)>,
+    // File size the state above was computed against; see `sync_file_size`.
+    known_size: u64,
+    // The file on disk as of the last check for rotation; see `reload_if_replaced`.
+    disk: Option<DiskState>,
+    // Viewports report the line number of their top row (`-N`)
+    line_numbers: bool,
+    // Viewports report the line position of their top row (`--position lines`)
+    line_position: bool,
+    // Pages without highlights of their own show the active search's; off after ESC-u
+    search_highlights: bool,
+    // Patterns highlighted besides the search, most recently kept first; see `KeepPatterns`
+    kept_patterns: Vec<Arc<SearchHighlightSpec>>,
+    // Where searches report how far they have got; see `search_progress`.
+    progress_tx: Option<Sender<SearchResponse>>,
+    // A task is reporting line indexing progress; see `watch_indexing`.
+    indexing_watched: Arc<AtomicBool>,
+    // Operations still running after their command returned, such as exports; see `Cancel`.
+    background: CancelRegistry,
 }
 
 impl WorkerState {
     fn new(file_accessor: Arc<dyn FileAccessor>, search_engine: RipgrepEngine) -> Self {
+        let known_size = file_accessor.file_size();
         Self {
+            shared_accessor: Arc::clone(&file_accessor),
             file_accessor,
             search_engine,
             context: None,
             last_highlight: None,
             last_page_start: None,
+            highlight_memo: HighlightMemo::default(),
+            wrap_armed: None,
+            known_size,
+            disk: None,
+            line_numbers: false,
+            line_position: false,
+            search_highlights: true,
+            kept_patterns: Vec::new(),
+            progress_tx: None,
+            indexing_watched: Arc::new(AtomicBool::new(false)),
+            background: CancelRegistry::default(),
         }
     }
 
+    fn with_cache_budget(mut self, budget: MemoryBudget) -> Self {
+        self.highlight_memo = HighlightMemo::with_budget(budget);
+        self
+    }
+
+    fn with_progress(mut self, tx: Sender<SearchResponse>) -> Self {
+        self.progress_tx = Some(tx);
+        self
+    }
+
+    /// Reporter for a search `request_id` that scans at most `total_bytes`, sending
+    /// `SearchProgress` ahead of its answer
+    ///
+    /// A report that finds the channel full is dropped rather than waited for: the next one
+    /// says more anyway.
+    fn search_progress(
+        &self,
+        request_id: RequestId,
+        total_bytes: u64,
+    ) -> Option<impl Fn(u64) + Send + Sync> {
+        let tx = self.progress_tx.clone()?;
+        Some(move |bytes_scanned| {
+            let _ = tx.try_send(SearchResponse::SearchProgress {
+                request_id,
+                bytes_scanned,
+                total_bytes,
+            });
+        })
+    }
+
     async fn handle_command(&mut self, cmd: SearchCommand) -> HandlerOutcome {
+        let name = cmd.name();
+        let request_id = cmd.request_id();
+        match &cmd {
+            SearchCommand::ExecuteSearch {
+                pattern,
+                direction,
+                origin_byte,
+                ..
+            } => log::debug!(
+                "worker: search request={:?} pattern={} direction={:?} origin={}",
+                request_id,
+                redact(pattern),
+                direction,
+                origin_byte
+            ),
+            SearchCommand::UpdateSearchContext(context) => log::debug!(
+                "worker: search context pattern={} direction={:?}",
+                redact(&context.pattern),
+                context.direction
+            ),
+            _ => {}
+        }
+
+        let started = Instant::now();
+        if let Some(snapshot) = self.shared_accessor.snapshot() {
+            self.file_accessor = snapshot;
+        }
+        self.sync_file_size();
+        let outcome = self.run_command(cmd).await;
+        // Don't keep a swapped-out source alive while idle
+        self.file_accessor = Arc::clone(&self.shared_accessor);
+        if let Some(response) = &outcome.response {
+            log::debug!(
+                "worker: {} request={:?} -> {} in {:?}",
+                name,
+                request_id,
+                response_summary(response),
+                started.elapsed()
+            );
+            // Search errors quote the pattern, which stays out of the log
+            if let SearchResponse::Error { error, .. } = response {
+                if !matches!(error, RllessError::SearchError { .. }) {
+                    log::warn!("worker: {} failed: {}", name, error.chain());
+                }
+            }
+        }
+        outcome
+    }
+
+    async fn run_command(&mut self, cmd: SearchCommand) -> HandlerOutcome {
         match cmd {
             SearchCommand::LoadViewport {
                 request_id,
                 top,
                 page_lines,
                 highlights,
-            } => match self
-                .load_viewport(request_id, top, page_lines, highlights)
-                .await
-            {
-                Ok(response) => HandlerOutcome::respond(response),
-                Err(error) => HandlerOutcome::respond(SearchResponse::Error { request_id, error }),
-            },
+                filter,
+                current_match,
+                cancel_flag,
+            } => {
+                let notice = self.reload_if_replaced().await;
+                let outcome = match self
+                    .load_viewport(
+                        request_id,
+                        top,
+                        page_lines,
+                        highlights,
+                        filter,
+                        current_match,
+                        cancel_flag.as_ref(),
+                    )
+                    .await
+                {
+                    Ok(response) => HandlerOutcome::respond(response),
+                    // Superseded; the coordinator no longer waits for this load
+                    Err(RllessError::Cancelled) => HandlerOutcome::continue_without_response(),
+                    Err(error) => {
+                        HandlerOutcome::respond(SearchResponse::Error { request_id, error })
+                    }
+                };
+                outcome.with_notice(notice)
+            }
             SearchCommand::ExecuteSearch {
                 request_id,
                 pattern,
                 direction,
                 options,
                 origin_byte,
+                origin_row,
+                skip_origin_row,
+                frequency_limit,
+                preview,
                 cancel_flag,
             } => HandlerOutcome::respond(
                 self.execute_search(
@@ -82,6 +261,10 @@ impl WorkerState {
                     direction,
                     options,
                     origin_byte,
+                    origin_row,
+                    skip_origin_row,
+                    frequency_limit,
+                    preview,
                     cancel_flag,
                 )
                 .await,
@@ -90,21 +273,153 @@ impl WorkerState {
                 request_id,
                 traversal,
                 current_top,
+                origin_row,
+                count,
+                cancel_flag,
+            } => HandlerOutcome::respond(
+                self.navigate_match(
+                    request_id,
+                    traversal,
+                    current_top,
+                    origin_row,
+                    count,
+                    cancel_flag,
+                )
+                .await,
+            ),
+            SearchCommand::SkipDuplicates {
+                request_id,
+                direction,
+                current_top,
+                cancel_flag,
+            } => HandlerOutcome::respond(
+                self.skip_duplicates(request_id, direction, current_top, cancel_flag)
+                    .await,
+            ),
+            SearchCommand::NavigateRecord {
+                request_id,
+                direction,
+                record_start,
+                current_top,
                 cancel_flag,
             } => HandlerOutcome::respond(
-                self.navigate_match(request_id, traversal, current_top, cancel_flag)
+                self.navigate_record(
+                    request_id,
+                    direction,
+                    record_start,
+                    current_top,
+                    cancel_flag,
+                )
+                .await,
+            ),
+            SearchCommand::NavigateTime {
+                request_id,
+                target,
+                record_start,
+                current_top,
+                cancel_flag,
+            } => HandlerOutcome::respond(
+                self.navigate_time(request_id, target, record_start, current_top, cancel_flag)
                     .await,
             ),
+            SearchCommand::PeekLine {
+                request_id,
+                row_start,
+                highlights,
+                filter,
+            } => match self
+                .peek_line(request_id, row_start, highlights, filter)
+                .await
+            {
+                Ok(response) => HandlerOutcome::respond(response),
+                Err(error) => HandlerOutcome::respond(SearchResponse::Error { request_id, error }),
+            },
+            SearchCommand::CollectMatches {
+                request_id,
+                search,
+                from,
+                limit,
+                cancel_flag,
+            } => match self
+                .collect_matches(request_id, &search, from, limit, cancel_flag.as_ref())
+                .await
+            {
+                Ok(response) => HandlerOutcome::respond(response),
+                // Closed or superseded; the coordinator no longer waits for the list
+                Err(RllessError::Cancelled) => HandlerOutcome::continue_without_response(),
+                Err(error) => HandlerOutcome::respond(SearchResponse::Error { request_id, error }),
+            },
+            SearchCommand::Export {
+                request_id,
+                target,
+                cancel_flag,
+            } => match self.start_export(request_id, target, cancel_flag) {
+                Some(response) => HandlerOutcome::respond(response),
+                None => HandlerOutcome::continue_without_response(),
+            },
+            SearchCommand::Cancel { request_id } => {
+                // Commands run one at a time, so only work left running in the background can
+                // still be going; the operation answers for itself
+                self.background.cancel(request_id);
+                HandlerOutcome::continue_without_response()
+            }
+            SearchCommand::DescribeFile => HandlerOutcome::respond(SearchResponse::FileDescribed {
+                meta: self.file_accessor.file_meta(),
+                canonical_path: self
+                    .file_accessor
+                    .identity()
+                    .map(|identity| identity.canonical_path.clone()),
+                file_size: self.file_accessor.file_size(),
+            }),
+            // The shared accessor: a snapshot would keep the old content
+            SearchCommand::CheckFileGrowth => match self.reload_if_replaced().await {
+                Some(notice) => HandlerOutcome::respond(notice),
+                None => self.check_file_growth().await,
+            },
+            SearchCommand::SwitchToLatest { pattern } => match self.switch_to_latest(pattern).await
+            {
+                Ok(Some(response)) => HandlerOutcome::respond(response),
+                Ok(None) => HandlerOutcome::continue_without_response(),
+                Err(error) => {
+                    log::warn!("worker: {}", error.chain());
+                    HandlerOutcome::continue_without_response()
+                }
+            },
+            SearchCommand::ShowLineNumbers(show) => {
+                self.line_numbers = show;
+                HandlerOutcome::continue_without_response()
+            }
+            SearchCommand::ShowLinePosition(show) => {
+                self.line_position = show;
+                HandlerOutcome::continue_without_response()
+            }
+            SearchCommand::ShowHighlights(show) => {
+                if show != self.search_highlights && !self.kept_patterns.is_empty() {
+                    // The memo's spans include the kept patterns' or lack them
+                    self.highlight_memo.clear();
+                }
+                self.search_highlights = show;
+                HandlerOutcome::continue_without_response()
+            }
+            SearchCommand::KeepPatterns(patterns) => {
+                self.kept_patterns = patterns;
+                // Its lines were highlighted without them
+                self.highlight_memo.clear();
+                HandlerOutcome::continue_without_response()
+            }
             SearchCommand::UpdateSearchContext(new_context) => {
                 self.last_highlight = Some(Arc::new(SearchHighlightSpec {
                     pattern: Arc::clone(&new_context.pattern),
                     options: new_context.options.clone(),
+                    modifiers: PatternModifiers::default(),
                 }));
                 self.context = Some(new_context);
+                self.wrap_armed = None;
                 HandlerOutcome::continue_without_response()
             }
             SearchCommand::ClearSearchContext => {
                 self.context = None;
+                self.wrap_armed = None;
                 self.last_highlight = None;
                 HandlerOutcome::continue_without_response()
             }
@@ -112,333 +427,161 @@ impl WorkerState {
         }
     }
 
-    async fn load_viewport(
-        &mut self,
-        request_id: RequestId,
-        top: ViewportRequest,
-        page_lines: usize,
-        highlights: Option<Arc<SearchHighlightSpec>>,
-    ) -> Result<SearchResponse> {
-        let target_byte = self.resolve_viewport_target(top, page_lines).await?;
-        let lines = self
-            .file_accessor
-            .read_from_byte(target_byte, page_lines)
-            .await?;
-        let highlight_spec = if let Some(spec) = highlights {
-            self.last_highlight = Some(Arc::clone(&spec));
-            Some(spec)
-        } else {
-            self.last_highlight.clone()
-        };
-
-        let highlights = if let Some(spec) = highlight_spec {
-            self.compute_highlights(spec.as_ref(), &lines)?
-        } else {
-            vec![Vec::new(); lines.len()]
-        };
-
-        let file_size = self.file_accessor.file_size();
-        let at_eof = self
-            .detect_eof(target_byte, page_lines, file_size, &lines)
-            .await?;
-
-        Ok(SearchResponse::ViewportLoaded {
-            request_id,
-            top_byte: target_byte,
-            lines,
-            highlights,
-            at_eof,
-            file_size,
-        })
+    /// Drop state that a change in file size made stale
+    ///
+    /// Appended data moves the last page and gives a search that ran off the end something new
+    /// to find, so both are forgotten; the active search and its current match stay valid.
+    /// Truncation can remove the current match itself, so the search context goes too.
+    fn sync_file_size(&mut self) {
+        let size = self.file_accessor.file_size();
+        if size == self.known_size {
+            return;
+        }
+        self.last_page_start = None;
+        self.wrap_armed = None;
+        if size < self.known_size {
+            self.context = None;
+            self.last_highlight = None;
+        }
+        self.known_size = size;
     }
+}
 
-    async fn execute_search(
-        &mut self,
-        request_id: RequestId,
-        pattern: Arc<str>,
-        direction: SearchDirection,
-        options: SearchOptions,
-        origin_byte: u64,
-        cancel_flag: Arc<AtomicBool>,
-    ) -> SearchResponse {
-        let mut new_context = SearchContext {
-            pattern: Arc::clone(&pattern),
-            direction,
-            options: options.clone(),
-            last_match_byte: None,
-        };
-
-        let search_future = match direction {
-            SearchDirection::Forward => self.search_engine.search_from(
-                pattern.as_ref(),
-                origin_byte,
-                &options,
-                Some(cancel_flag.as_ref()),
-            ),
-            SearchDirection::Backward => self.search_engine.search_prev(
-                pattern.as_ref(),
-                origin_byte,
-                &options,
-                Some(cancel_flag.as_ref()),
-            ),
-        };
-        // Responsibility for honouring the cancel token lives in the engine/accessor so we can
-        // avoid queueing a separate cancel command (the queue itself remains FIFO).
-
-        match search_future.await {
-            Ok(Some(byte)) => {
-                new_context.last_match_byte = Some(byte);
-                self.last_highlight = Some(Arc::new(SearchHighlightSpec {
-                    pattern: Arc::clone(&new_context.pattern),
-                    options: new_context.options.clone(),
-                }));
-                self.context = Some(new_context);
-                SearchResponse::SearchCompleted {
-                    request_id,
-                    match_byte: Some(byte),
-                    message: None,
-                }
-            }
-            Ok(None) => {
-                self.last_highlight = Some(Arc::new(SearchHighlightSpec {
-                    pattern: Arc::clone(&new_context.pattern),
-                    options: new_context.options.clone(),
-                }));
-                self.context = Some(new_context);
-                SearchResponse::SearchCompleted {
-                    request_id,
-                    match_byte: None,
-                    message: Some("Pattern not found".to_string()),
-                }
+impl WorkerState {
+    /// Pick up data appended to the file, answering only when there was some
+    ///
+    /// Only the appended bytes are searched for the active pattern, so following a busy file
+    /// costs in proportion to what arrived rather than to its size.
+    async fn check_file_growth(&self) -> HandlerOutcome {
+        let old_size = self.shared_accessor.file_size();
+        match self.shared_accessor.refresh().await {
+            Ok(true) => HandlerOutcome::respond(SearchResponse::FileGrew {
+                file_size: self.shared_accessor.file_size(),
+                new_match: self.match_after(old_size).await,
+            }),
+            Ok(false) => HandlerOutcome::continue_without_response(),
+            Err(error) => {
+                log::warn!("worker: growth check failed: {}", error.chain());
+                HandlerOutcome::continue_without_response()
             }
-            Err(error) => match error {
-                RllessError::Cancelled => SearchResponse::SearchCancelled { request_id },
-                other => SearchResponse::Error {
-                    request_id,
-                    error: other,
-                },
-            },
         }
     }
 
-    async fn navigate_match(
-        &mut self,
-        request_id: RequestId,
-        traversal: MatchTraversal,
-        current_top: u64,
-        cancel_flag: Arc<AtomicBool>,
-    ) -> SearchResponse {
-        let ctx_snapshot = match self.context.as_ref() {
-            Some(ctx) => (ctx.direction, ctx.options.clone(), Arc::clone(&ctx.pattern)),
-            None => {
-                return SearchResponse::SearchCompleted {
-                    request_id,
-                    match_byte: None,
-                    message: Some("No active search".to_string()),
-                };
-            }
-        };
-
-        let (direction, options, pattern) = ctx_snapshot;
-
-        let start_byte = match self
-            .start_position_for_navigation(traversal, direction, current_top)
+    /// First match of the active search at or after `start`
+    async fn match_after(&self, start: u64) -> Option<u64> {
+        let context = self.context.as_ref()?;
+        match self
+            .search_engine
+            .search_from(&context.pattern, start, &context.options, None, None)
             .await
         {
-            Ok(byte) => byte,
+            Ok(found) => found,
             Err(error) => {
-                return SearchResponse::Error { request_id, error };
+                log::warn!("worker: searching appended data failed: {}", error);
+                None
             }
-        };
-
-        let result = match (traversal, direction) {
-            (MatchTraversal::Next, SearchDirection::Forward)
-            | (MatchTraversal::Previous, SearchDirection::Backward) => {
-                self.search_engine
-                    .search_from(
-                        pattern.as_ref(),
-                        start_byte,
-                        &options,
-                        Some(cancel_flag.as_ref()),
-                    )
-                    .await
-            }
-            _ => {
-                self.search_engine
-                    .search_prev(
-                        pattern.as_ref(),
-                        start_byte,
-                        &options,
-                        Some(cancel_flag.as_ref()),
-                    )
-                    .await
-            }
-        };
-
-        match result {
-            Ok(Some(byte)) => {
-                if let Some(ctx) = self.context.as_mut() {
-                    ctx.last_match_byte = Some(byte);
-                    self.last_highlight = Some(Arc::new(SearchHighlightSpec {
-                        pattern: Arc::clone(&ctx.pattern),
-                        options: ctx.options.clone(),
-                    }));
-                }
-                SearchResponse::SearchCompleted {
-                    request_id,
-                    match_byte: Some(byte),
-                    message: None,
-                }
-            }
-            Ok(None) => SearchResponse::SearchCompleted {
-                request_id,
-                match_byte: None,
-                message: Some("Pattern not found".to_string()),
-            },
-            Err(error) => match error {
-                RllessError::Cancelled => SearchResponse::SearchCancelled { request_id },
-                other => SearchResponse::Error {
-                    request_id,
-                    error: other,
-                },
-            },
         }
     }
+}
 
-    async fn resolve_viewport_target(
-        &mut self,
-        top: ViewportRequest,
-        page_lines: usize,
-    ) -> Result<u64> {
-        let file_size = self.file_accessor.file_size();
-
-        if file_size == 0 {
-            return Ok(0);
-        }
-
-        let last_start = self.compute_last_page_start(page_lines, file_size).await?;
-
-        let mut target_byte = match top {
-            ViewportRequest::Absolute(byte) => byte,
-            ViewportRequest::RelativeLines { anchor, lines } => {
-                if lines == 0 {
-                    anchor
-                } else if lines > 0 {
-                    self.file_accessor
-                        .next_page_start(anchor, lines as usize)
-                        .await?
-                } else {
-                    self.file_accessor
-                        .prev_page_start(anchor, (-lines) as usize)
-                        .await?
-                }
-            }
-            ViewportRequest::EndOfFile => last_start.unwrap_or(0),
-        };
-
-        if let Some(last) = last_start {
-            if target_byte > last {
-                target_byte = last;
-            }
-        }
-
-        Ok(target_byte)
-    }
-
-    async fn compute_last_page_start(
-        &mut self,
-        page_lines: usize,
-        file_size: u64,
-    ) -> Result<Option<u64>> {
-        if file_size == 0 {
-            self.last_page_start = None;
-            return Ok(None);
-        }
-
-        match self.last_page_start {
-            Some((cached_lines, pos)) if cached_lines == page_lines => Ok(Some(pos)),
-            _ => {
-                let last = self.file_accessor.last_page_start(page_lines).await?;
-                self.last_page_start = Some((page_lines, last));
-                Ok(Some(last))
-            }
-        }
-    }
-
-    fn compute_highlights(
-        &self,
-        spec: &SearchHighlightSpec,
-        lines: &[String],
-    ) -> Result<Vec<Vec<(usize, usize)>>> {
-        let mut all_highlights = Vec::with_capacity(lines.len());
-        for line in lines {
-            let ranges = self
-                .search_engine
-                .get_line_matches(&spec.pattern, line, &spec.options)?;
-            all_highlights.push(ranges);
-        }
-        Ok(all_highlights)
-    }
-
-    async fn detect_eof(
-        &self,
-        top_byte: u64,
-        page_lines: usize,
-        file_size: u64,
-        lines: &[String],
-    ) -> Result<bool> {
-        if lines.is_empty() {
-            return Ok(true);
-        }
-
-        let next_start = self
-            .file_accessor
-            .next_page_start(top_byte, page_lines.max(1))
-            .await?;
-        Ok(next_start >= file_size)
-    }
-
-    async fn start_position_for_navigation(
-        &self,
-        traversal: MatchTraversal,
-        direction: SearchDirection,
-        current_top: u64,
-    ) -> Result<u64> {
-        match (traversal, direction) {
-            (MatchTraversal::Next, SearchDirection::Forward)
-            | (MatchTraversal::Previous, SearchDirection::Backward) => {
-                self.next_line_start(current_top).await
-            }
-            _ => self.prev_line_start(current_top).await,
+/// Offsets and lengths of a response, for the worker's log records
+fn response_summary(response: &SearchResponse) -> String {
+    match response {
+        SearchResponse::ViewportLoaded {
+            top_byte,
+            lines,
+            at_eof,
+            file_size,
+            ..
+        } => format!(
+            "{} lines from byte {} (eof {}, size {})",
+            lines.len(),
+            top_byte,
+            at_eof,
+            file_size
+        ),
+        SearchResponse::SearchCompleted {
+            match_byte,
+            message,
+            confirm,
+            ..
+        } => format!(
+            "match {:?} (message {}, confirm {})",
+            match_byte,
+            message.is_some(),
+            confirm.is_some()
+        ),
+        SearchResponse::PositionFound { byte, message, .. } => {
+            format!("position {:?} (message {})", byte, message.is_some())
         }
+        SearchResponse::LinePeeked {
+            start,
+            text,
+            truncated,
+            ..
+        } => format!(
+            "{} bytes from byte {} (truncated {})",
+            text.len(),
+            start,
+            truncated
+        ),
+        SearchResponse::MatchBatch {
+            matches,
+            last,
+            resume_from,
+            ..
+        } => format!(
+            "{} matches (last {}, resume from {:?})",
+            matches.len(),
+            last,
+            resume_from
+        ),
+        SearchResponse::FileDescribed { file_size, .. } => format!("size {}", file_size),
+        SearchResponse::FileGrew {
+            file_size,
+            new_match,
+        } => format!("size {} (new match {})", file_size, new_match.is_some()),
+        SearchResponse::FileSwitched { file_size, .. }
+        | SearchResponse::FileReloaded { file_size, .. } => format!("size {}", file_size),
+        // Errors may quote the pattern or line text
+        SearchResponse::Error { error, .. } => format!("error {}", redact(&error.to_string())),
+        other => other.name().to_string(),
     }
+}
 
-    async fn next_line_start(&self, current_byte: u64) -> Result<u64> {
-        let new_byte = self.file_accessor.next_page_start(current_byte, 1).await?;
-        if new_byte == self.file_accessor.file_size() {
-            Ok(current_byte)
-        } else {
-            Ok(new_byte)
-        }
-    }
+/// Failure message for a search that ran off the end of the file moving in `direction`
+fn not_found_in_direction(direction: SearchDirection) -> String {
+    let side = match direction {
+        SearchDirection::Forward => "below",
+        SearchDirection::Backward => "above",
+    };
+    format!("Pattern not found {} current position", side)
+}
 
-    async fn prev_line_start(&self, current_byte: u64) -> Result<u64> {
-        if current_byte == 0 {
-            Ok(0)
-        } else {
-            self.file_accessor.prev_page_start(current_byte, 1).await
-        }
+/// Bytes a search from `origin` in `direction` covers before running off the file
+fn scan_length(direction: SearchDirection, origin: u64, file_size: u64) -> u64 {
+    match direction {
+        SearchDirection::Forward => file_size.saturating_sub(origin),
+        SearchDirection::Backward => origin.min(file_size),
     }
 }
 
 struct HandlerOutcome {
+    // Sent ahead of `response`, e.g. that the file was reloaded before a page was read from it
+    notice: Option<SearchResponse>,
     response: Option<SearchResponse>,
     done: bool,
 }
 
 impl HandlerOutcome {
+    fn with_notice(mut self, notice: Option<SearchResponse>) -> Self {
+        self.notice = notice;
+        self
+    }
+
     fn respond(response: SearchResponse) -> Self {
         Self {
+            notice: None,
             response: Some(response),
             done: false,
         }
@@ -446,6 +589,7 @@ impl HandlerOutcome {
 
     fn continue_without_response() -> Self {
         Self {
+            notice: None,
             response: None,
             done: false,
         }
@@ -453,93 +597,9 @@ impl HandlerOutcome {
 
     fn exit() -> Self {
         Self {
+            notice: None,
             response: None,
             done: true,
         }
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::file_handler::accessor::FileAccessor;
-    use async_trait::async_trait;
-    use std::path::{Path, PathBuf};
-
-    #[derive(Debug, Clone)]
-    struct EmptyAccessor {
-        path: PathBuf,
-    }
-
-    impl Default for EmptyAccessor {
-        fn default() -> Self {
-            Self {
-                path: PathBuf::from("<empty>"),
-            }
-        }
-    }
-
-    #[async_trait]
-    impl FileAccessor for EmptyAccessor {
-        async fn read_from_byte(&self, _start_byte: u64, _max_lines: usize) -> Result<Vec<String>> {
-            Ok(Vec::new())
-        }
-
-        async fn find_next_match(
-            &self,
-            _start_byte: u64,
-            _search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
-            _cancel_flag: Option<&AtomicBool>,
-        ) -> Result<Option<u64>> {
-            Ok(None)
-        }
-
-        async fn find_prev_match(
-            &self,
-            _start_byte: u64,
-            _search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
-            _cancel_flag: Option<&AtomicBool>,
-        ) -> Result<Option<u64>> {
-            Ok(None)
-        }
-
-        fn file_size(&self) -> u64 {
-            0
-        }
-
-        fn file_path(&self) -> &Path {
-            &self.path
-        }
-
-        async fn last_page_start(&self, _max_lines: usize) -> Result<u64> {
-            Ok(0)
-        }
-
-        async fn next_page_start(&self, _current_byte: u64, _lines_to_skip: usize) -> Result<u64> {
-            Ok(0)
-        }
-
-        async fn prev_page_start(&self, _current_byte: u64, _lines_to_skip: usize) -> Result<u64> {
-            Ok(0)
-        }
-    }
-
-    #[tokio::test]
-    async fn empty_files_resolve_to_zero() {
-        let accessor: Arc<dyn FileAccessor> = Arc::new(EmptyAccessor::default());
-        let engine = RipgrepEngine::new(Arc::clone(&accessor));
-        let mut worker = WorkerState::new(accessor, engine);
-
-        for request in [
-            ViewportRequest::Absolute(10),
-            ViewportRequest::RelativeLines {
-                anchor: 25,
-                lines: 3,
-            },
-            ViewportRequest::EndOfFile,
-        ] {
-            let resolved = worker.resolve_viewport_target(request, 5).await.unwrap();
-            assert_eq!(resolved, 0);
-        }
-    }
-}
@@ -0,0 +1,294 @@
+//! `:save` and `:pipe`: writing the whole file out while the worker goes on serving pages.
+
+use super::WorkerState;
+use crate::error::{Result, RllessError};
+use crate::export::{export_range_with, throughput, ByteRange, ExportControl, ExportOptions};
+use crate::render::protocol::{ExportTarget, ProgressOperation, RequestId, SearchResponse};
+use crate::util::CancellationToken;
+use parking_lot::Mutex;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+
+/// How often a running pipe command is checked for a cancelled export
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How an export ended, for `ExportFinished`
+struct Outcome {
+    message: String,
+    partial: Option<PathBuf>,
+}
+
+impl WorkerState {
+    /// Start writing the file to `target` on a blocking thread and return at once
+    ///
+    /// The thread reports `Progress` as it goes and ends with `ExportFinished`; until then it
+    /// reads through the accessor the command saw, so growth or a reload does not change what
+    /// is written, and `Cancel` for `request_id` stops it. Returns the response to send right
+    /// away when the export cannot start.
+    pub(super) fn start_export(
+        &self,
+        request_id: RequestId,
+        target: ExportTarget,
+        cancel_flag: CancellationToken,
+    ) -> Option<SearchResponse> {
+        let Some(tx) = self.progress_tx.clone() else {
+            return Some(SearchResponse::Error {
+                request_id,
+                error: RllessError::other("Nowhere to report the export to"),
+            });
+        };
+        let accessor = Arc::clone(&self.file_accessor);
+        let handle = Handle::current();
+        let registration = self.background.register(request_id, &cancel_flag);
+        tokio::task::spawn_blocking(move || {
+            let _registration = registration;
+            let total = accessor.file_size().max(1);
+            let progress_tx = tx.clone();
+            let progress = move |done: u64| {
+                let _ = progress_tx.try_send(SearchResponse::Progress {
+                    operation: ProgressOperation::Export,
+                    fraction: (done as f64 / total as f64) as f32,
+                });
+            };
+            let control = ExportControl {
+                cancel_flag: Some(cancel_flag.as_ref()),
+                progress: Some(&progress),
+            };
+            let run = |mut sink: &mut dyn Write| {
+                handle.block_on(export_range_with(
+                    accessor.as_ref(),
+                    ByteRange::ALL,
+                    ExportOptions::default(),
+                    &mut sink,
+                    control,
+                ))
+            };
+            let outcome = match &target {
+                ExportTarget::File(path) => save(path, run),
+                ExportTarget::Command(command) => pipe(command, &cancel_flag, run),
+            };
+            let _ = tx.blocking_send(SearchResponse::ExportFinished {
+                request_id,
+                message: outcome.message,
+                partial: outcome.partial,
+            });
+        });
+        None
+    }
+}
+
+/// Write to a new file at `path`; an existing file is left alone
+fn save(path: &Path, run: impl FnOnce(&mut dyn Write) -> Result<u64>) -> Outcome {
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Outcome {
+                message: format!("{} already exists", path.display()),
+                partial: None,
+            }
+        }
+        Err(e) => {
+            let error = RllessError::file_error(format!("Cannot create {}", path.display()), e);
+            return Outcome {
+                message: error.summary(),
+                partial: None,
+            };
+        }
+    };
+    let started = Instant::now();
+    let result = run(&mut file).and_then(|bytes| {
+        file.flush()?;
+        Ok(bytes)
+    });
+    match result {
+        Ok(bytes) => Outcome {
+            message: format!(
+                "Wrote {} to {}",
+                throughput(bytes, started.elapsed()),
+                path.display()
+            ),
+            partial: None,
+        },
+        Err(RllessError::Cancelled) => Outcome {
+            message: format!("Save to {} cancelled", path.display()),
+            partial: Some(path.to_path_buf()),
+        },
+        Err(error) => Outcome {
+            message: format!("Save to {} failed: {}", path.display(), error.summary()),
+            partial: Some(path.to_path_buf()),
+        },
+    }
+}
+
+/// Feed a shell `command` on its standard input
+///
+/// The command is killed when the export is cancelled, which also frees an export blocked on a
+/// command that stopped reading. A command that exits early, like `head`, ends the export
+/// without an error.
+fn pipe(
+    command: &str,
+    cancel_flag: &CancellationToken,
+    run: impl FnOnce(&mut dyn Write) -> Result<u64>,
+) -> Outcome {
+    let mut child = match shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            let error = RllessError::file_error(format!("Cannot run `{}`", command), e);
+            return Outcome {
+                message: error.summary(),
+                partial: None,
+            };
+        }
+    };
+    let mut sink = PipeSink {
+        stdin: child.stdin.take(),
+        written: 0,
+        closed: false,
+    };
+    let stdout = child.stdout.take().map(drain_first_line);
+    let stderr = child.stderr.take().map(drain_first_line);
+
+    let child = Mutex::new(child);
+    let finished = AtomicBool::new(false);
+    let (result, elapsed, killed) = std::thread::scope(|scope| {
+        let watcher = scope.spawn(|| {
+            while !finished.load(Ordering::Acquire) {
+                if cancel_flag.is_cancelled() {
+                    let _ = child.lock().kill();
+                    return true;
+                }
+                std::thread::sleep(CANCEL_POLL_INTERVAL);
+            }
+            false
+        });
+        let started = Instant::now();
+        let result = run(&mut sink);
+        // Closing stdin lets the command see the end of its input
+        sink.stdin = None;
+        finished.store(true, Ordering::Release);
+        let mut killed = watcher.join().unwrap_or(false);
+        if !killed && cancel_flag.is_cancelled() {
+            // Stopped between chunks: a command that does not read its input would keep running
+            killed = child.lock().kill().is_ok();
+        }
+        (result, started.elapsed(), killed)
+    });
+    let status = child.into_inner().wait();
+    let first_out = stdout.and_then(|reader| reader.join().ok().flatten());
+    let first_err = stderr.and_then(|reader| reader.join().ok().flatten());
+
+    if killed || matches!(result, Err(RllessError::Cancelled)) {
+        return Outcome {
+            message: format!("Pipe to `{}` cancelled", command),
+            partial: None,
+        };
+    }
+    let message = match (result, status) {
+        (Err(error), _) if !sink.closed => {
+            format!("Pipe to `{}` failed: {}", command, error.summary())
+        }
+        (_, Err(e)) => {
+            let error = RllessError::file_error(format!("Lost `{}`", command), e);
+            error.summary()
+        }
+        (_, Ok(status)) if !status.success() => {
+            let reason = first_err.map_or(String::new(), |line| format!(": {}", line));
+            format!("`{}` failed ({}){}", command, status, reason)
+        }
+        (_, Ok(_)) => {
+            let output = first_out.map_or(String::new(), |line| format!(": {}", line));
+            format!(
+                "Piped {} to `{}`{}",
+                throughput(sink.written, elapsed),
+                command,
+                output
+            )
+        }
+    };
+    Outcome {
+        message,
+        partial: None,
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Standard input of a pipe command, noting when the command stopped reading
+struct PipeSink {
+    stdin: Option<std::process::ChildStdin>,
+    written: u64,
+    closed: bool,
+}
+
+impl Write for PipeSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let Some(stdin) = self.stdin.as_mut() else {
+            return Err(ErrorKind::BrokenPipe.into());
+        };
+        match stdin.write(buf) {
+            Ok(count) => {
+                self.written += count as u64;
+                Ok(count)
+            }
+            Err(e) => {
+                self.closed = e.kind() == ErrorKind::BrokenPipe;
+                Err(e)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.stdin.as_mut() {
+            Some(stdin) => stdin.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Read `reader` to the end on a thread of its own, keeping the first non-empty line
+///
+/// Reading all of it keeps a chatty command from blocking on a full pipe.
+fn drain_first_line(reader: impl Read + Send + 'static) -> std::thread::JoinHandle<Option<String>> {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut first = None;
+        let mut line = Vec::new();
+        while first.is_none() {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&line).trim().to_string();
+                    if !text.is_empty() {
+                        first = Some(text);
+                    }
+                }
+            }
+        }
+        let _ = std::io::copy(&mut reader, &mut std::io::sink());
+        first
+    })
+}
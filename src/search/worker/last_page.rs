@@ -0,0 +1,455 @@
+//! Locating the last page without scanning from the end more than needed.
+
+use super::WorkerState;
+use crate::error::Result;
+
+impl WorkerState {
+    /// Whether `target_byte` might lie beyond the last page start.
+    ///
+    /// If a full page of lines still follows the target, the last page necessarily starts later,
+    /// so clamping is a no-op. Checking that only costs a forward scan of a single page.
+    pub(super) async fn may_pass_last_page(
+        &self,
+        target_byte: u64,
+        page_lines: usize,
+        file_size: u64,
+    ) -> Result<bool> {
+        if target_byte >= file_size {
+            return Ok(true);
+        }
+        let next_start = self
+            .file_accessor
+            .next_page_start(target_byte, page_lines)
+            .await?;
+        Ok(next_start >= file_size)
+    }
+
+    pub(super) async fn compute_last_page_start(
+        &mut self,
+        page_lines: usize,
+        file_size: u64,
+    ) -> Result<Option<u64>> {
+        if file_size == 0 {
+            self.last_page_start = None;
+            return Ok(None);
+        }
+
+        match self.last_page_start {
+            Some((cached_lines, cached_size, pos))
+                if cached_lines == page_lines && cached_size == file_size =>
+            {
+                Ok(Some(pos))
+            }
+            _ => {
+                let last = self.file_accessor.last_page_start(page_lines).await?;
+                self.last_page_start = Some((page_lines, file_size, last));
+                Ok(Some(last))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileMeta;
+    use crate::file_handler::{FileAccessor, ScanProgress};
+    use crate::render::protocol::ViewportRequest;
+    use crate::render::protocol::{MatchTraversal, SearchResponse};
+    use crate::search::worker::{SearchCommand, SearchContext, SearchDirection};
+    use crate::search::RipgrepEngine;
+    use crate::util::CancellationToken;
+    use crate::SearchOptions;
+    use async_trait::async_trait;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn page_down_inside_final_partial_page_does_not_scroll_back() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let contents: String = (0..13).map(|i| format!("line {i}\n")).collect();
+        std::fs::write(file.path(), contents).unwrap();
+        let accessor: Arc<dyn FileAccessor> = Arc::new(
+            crate::file_handler::FileAccessorFactory::create(file.path())
+                .await
+                .unwrap(),
+        );
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        let mut worker = WorkerState::new(accessor, engine);
+        // "line 10" starts after ten 7-byte lines; only three lines remain from there
+        let tail_top = 70;
+
+        let response = worker
+            .load_viewport(
+                1,
+                ViewportRequest::RelativeLines {
+                    anchor: tail_top,
+                    lines: 10,
+                },
+                10,
+                None,
+                None,
+                None,
+                &AtomicBool::new(false),
+            )
+            .await
+            .unwrap();
+        let SearchResponse::ViewportLoaded {
+            top_byte,
+            lines,
+            at_eof,
+            ..
+        } = response
+        else {
+            panic!("unexpected response: {response:?}");
+        };
+        assert_eq!(top_byte, tail_top);
+        assert_eq!(lines, vec!["line 10", "line 11", "line 12"]);
+        assert!(at_eof);
+
+        // Paging down from above the last page still clamps to it
+        let clamped = worker
+            .resolve_viewport_target(
+                ViewportRequest::RelativeLines {
+                    anchor: 14,
+                    lines: 10,
+                },
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(clamped, 21);
+    }
+
+    /// Accessor whose content can be replaced mid-test, like a file being appended to.
+    struct GrowingAccessor {
+        path: PathBuf,
+        inner: std::sync::RwLock<Arc<dyn FileAccessor>>,
+        /// Keeps every generation's backing file alive
+        files: std::sync::Mutex<Vec<tempfile::NamedTempFile>>,
+    }
+
+    impl GrowingAccessor {
+        async fn new(content: &str) -> Self {
+            let (file, inner) = Self::generation(content).await;
+            Self {
+                path: PathBuf::from("<growing>"),
+                inner: std::sync::RwLock::new(inner),
+                files: std::sync::Mutex::new(vec![file]),
+            }
+        }
+
+        async fn set_content(&self, content: &str) {
+            let (file, inner) = Self::generation(content).await;
+            *self.inner.write().unwrap() = inner;
+            self.files.lock().unwrap().push(file);
+        }
+
+        /// Accessor over `content`, with the temp file backing it
+        async fn generation(content: &str) -> (tempfile::NamedTempFile, Arc<dyn FileAccessor>) {
+            let file = tempfile::NamedTempFile::new().unwrap();
+            std::fs::write(file.path(), content).unwrap();
+            let inner = crate::file_handler::FileAccessorFactory::create(file.path())
+                .await
+                .unwrap();
+            (file, Arc::new(inner))
+        }
+
+        fn current(&self) -> Arc<dyn FileAccessor> {
+            Arc::clone(&self.inner.read().unwrap())
+        }
+    }
+
+    #[async_trait]
+    impl FileAccessor for GrowingAccessor {
+        async fn read_from_byte(&self, start_byte: u64, max_lines: usize) -> Result<Vec<String>> {
+            self.current().read_from_byte(start_byte, max_lines).await
+        }
+
+        async fn read_bytes(&self, start_byte: u64, length: usize) -> Result<Vec<u8>> {
+            self.current().read_bytes(start_byte, length).await
+        }
+
+        async fn find_next_match(
+            &self,
+            start_byte: u64,
+            search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            cancel_flag: Option<&AtomicBool>,
+            progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            self.current()
+                .find_next_match(start_byte, search_fn, cancel_flag, progress)
+                .await
+        }
+
+        async fn find_prev_match(
+            &self,
+            start_byte: u64,
+            search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            cancel_flag: Option<&AtomicBool>,
+            progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            self.current()
+                .find_prev_match(start_byte, search_fn, cancel_flag, progress)
+                .await
+        }
+
+        fn file_size(&self) -> u64 {
+            self.current().file_size()
+        }
+
+        fn file_path(&self) -> &Path {
+            &self.path
+        }
+
+        fn ends_with_newline(&self) -> bool {
+            self.current().ends_with_newline()
+        }
+
+        fn file_meta(&self) -> Option<FileMeta> {
+            None
+        }
+
+        fn sections(&self) -> &[u64] {
+            &[]
+        }
+
+        async fn last_page_start(&self, max_lines: usize) -> Result<u64> {
+            self.current().last_page_start(max_lines).await
+        }
+
+        async fn next_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
+            self.current()
+                .next_page_start(current_byte, lines_to_skip)
+                .await
+        }
+
+        async fn prev_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
+            self.current()
+                .prev_page_start(current_byte, lines_to_skip)
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn appended_data_is_reached_by_paging_and_match_navigation() {
+        // "hit" at bytes 0 and 8
+        let original = "hit one\nhit two\nmiss\n";
+        let accessor = Arc::new(GrowingAccessor::new(original).await);
+        let shared: Arc<dyn FileAccessor> = accessor.clone();
+        let engine = RipgrepEngine::new(Arc::clone(&shared));
+        let mut worker = WorkerState::new(shared, engine);
+
+        macro_rules! send {
+            ($command:expr) => {
+                worker.handle_command($command).await.response.unwrap()
+            };
+        }
+        macro_rules! last_page {
+            () => {
+                match send!(SearchCommand::LoadViewport {
+                    request_id: 1,
+                    top: ViewportRequest::EndOfFile,
+                    page_lines: 2,
+                    highlights: None,
+                    filter: None,
+                    current_match: None,
+                    cancel_flag: CancellationToken::new(),
+                }) {
+                    SearchResponse::ViewportLoaded { lines, .. } => lines,
+                    other => panic!("unexpected response: {other:?}"),
+                }
+            };
+        }
+        macro_rules! next_match {
+            ($top:expr) => {
+                match send!(SearchCommand::NavigateMatch {
+                    request_id: 2,
+                    traversal: MatchTraversal::Next,
+                    current_top: $top,
+                    origin_row: 0,
+                    count: 1,
+                    cancel_flag: CancellationToken::new(),
+                }) {
+                    SearchResponse::SearchCompleted {
+                        match_byte,
+                        message,
+                        ..
+                    } => (match_byte, message),
+                    other => panic!("unexpected response: {other:?}"),
+                }
+            };
+        }
+
+        assert_eq!(last_page!(), vec!["hit two", "miss"]);
+        worker
+            .handle_command(SearchCommand::UpdateSearchContext(SearchContext {
+                pattern: Arc::from("hit"),
+                direction: SearchDirection::Forward,
+                options: SearchOptions::default(),
+                last_match_byte: None,
+            }))
+            .await;
+        let (match_byte, message) = next_match!(8);
+        assert_eq!(match_byte, None);
+        assert!(message.unwrap().contains("press n again to wrap"));
+
+        // Appended while the search is active: n finds the new match instead of wrapping, and
+        // the end of the file is recomputed
+        accessor
+            .set_content(&format!("{original}hit three\nlast\n"))
+            .await;
+        assert_eq!(next_match!(8), (Some(21), None));
+        assert_eq!(last_page!(), vec!["hit three", "last"]);
+
+        // Truncation drops the search context, since its matches may be gone
+        accessor.set_content("hit\n").await;
+        assert_eq!(next_match!(0), (None, Some("No active search".to_string())));
+        assert_eq!(last_page!(), vec!["hit"]);
+    }
+
+    #[tokio::test]
+    async fn top_of_file_load_skips_last_page_scan() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let contents: String = (0..100).map(|i| format!("line {i}\n")).collect();
+        std::fs::write(file.path(), contents).unwrap();
+        let inner: Arc<dyn FileAccessor> = Arc::new(
+            crate::file_handler::FileAccessorFactory::create(file.path())
+                .await
+                .unwrap(),
+        );
+        let counting = Arc::new(CountingAccessor {
+            inner,
+            last_page_calls: Default::default(),
+        });
+        let accessor: Arc<dyn FileAccessor> = counting.clone();
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        let mut worker = WorkerState::new(accessor, engine);
+        let calls = || {
+            counting
+                .last_page_calls
+                .load(std::sync::atomic::Ordering::SeqCst)
+        };
+
+        worker
+            .load_viewport(
+                1,
+                ViewportRequest::Absolute(0),
+                10,
+                None,
+                None,
+                None,
+                &AtomicBool::new(false),
+            )
+            .await
+            .unwrap();
+        worker
+            .load_viewport(
+                2,
+                ViewportRequest::RelativeLines {
+                    anchor: 0,
+                    lines: 5,
+                },
+                10,
+                None,
+                None,
+                None,
+                &AtomicBool::new(false),
+            )
+            .await
+            .unwrap();
+        assert_eq!(calls(), 0);
+
+        // Requests that can reach the tail still clamp against the last page
+        let end = worker
+            .resolve_viewport_target(ViewportRequest::EndOfFile, 10)
+            .await
+            .unwrap();
+        assert_eq!(calls(), 1);
+        let past_end = worker
+            .resolve_viewport_target(ViewportRequest::Absolute(u64::MAX), 10)
+            .await
+            .unwrap();
+        assert_eq!(past_end, end);
+    }
+
+    /// Delegating accessor that counts `last_page_start` calls.
+    struct CountingAccessor {
+        inner: Arc<dyn FileAccessor>,
+        last_page_calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl FileAccessor for CountingAccessor {
+        async fn read_from_byte(&self, start_byte: u64, max_lines: usize) -> Result<Vec<String>> {
+            self.inner.read_from_byte(start_byte, max_lines).await
+        }
+
+        async fn read_bytes(&self, start_byte: u64, length: usize) -> Result<Vec<u8>> {
+            self.inner.read_bytes(start_byte, length).await
+        }
+
+        async fn find_next_match(
+            &self,
+            start_byte: u64,
+            search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            cancel_flag: Option<&AtomicBool>,
+            progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            self.inner
+                .find_next_match(start_byte, search_fn, cancel_flag, progress)
+                .await
+        }
+
+        async fn find_prev_match(
+            &self,
+            start_byte: u64,
+            search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            cancel_flag: Option<&AtomicBool>,
+            progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            self.inner
+                .find_prev_match(start_byte, search_fn, cancel_flag, progress)
+                .await
+        }
+
+        fn file_size(&self) -> u64 {
+            self.inner.file_size()
+        }
+
+        fn file_path(&self) -> &Path {
+            self.inner.file_path()
+        }
+
+        fn ends_with_newline(&self) -> bool {
+            self.inner.ends_with_newline()
+        }
+
+        fn file_meta(&self) -> Option<FileMeta> {
+            self.inner.file_meta()
+        }
+
+        fn sections(&self) -> &[u64] {
+            self.inner.sections()
+        }
+
+        async fn last_page_start(&self, max_lines: usize) -> Result<u64> {
+            self.last_page_calls
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.inner.last_page_start(max_lines).await
+        }
+
+        async fn next_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
+            self.inner
+                .next_page_start(current_byte, lines_to_skip)
+                .await
+        }
+
+        async fn prev_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
+            self.inner
+                .prev_page_start(current_byte, lines_to_skip)
+                .await
+        }
+    }
+}
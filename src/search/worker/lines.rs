@@ -0,0 +1,229 @@
+//! Commands that work on whole lines: duplicate runs, log records and the line overlay.
+
+use super::viewport::line_highlights;
+use super::WorkerState;
+use crate::error::{Result, RllessError};
+use crate::file_handler::ScanProgress;
+use crate::input::SearchDirection;
+use crate::render::protocol::{RequestId, SearchHighlightSpec, SearchResponse};
+use crate::search::peek::read_full_line;
+use crate::search::timestamps::{self, leading_timestamp, Bisected, TimeTarget};
+use crate::search::{duplicates, SearchEngine, SearchOptions};
+use crate::util::CancellationToken;
+use std::sync::Arc;
+
+impl WorkerState {
+    pub(super) async fn skip_duplicates(
+        &self,
+        request_id: RequestId,
+        direction: SearchDirection,
+        current_top: u64,
+        cancel_flag: CancellationToken,
+    ) -> SearchResponse {
+        let accessor = self.file_accessor.as_ref();
+        let result = match direction {
+            SearchDirection::Forward => {
+                duplicates::next_distinct_line(accessor, current_top, Some(cancel_flag.as_ref()))
+                    .await
+            }
+            SearchDirection::Backward => {
+                duplicates::prev_distinct_line(accessor, current_top, Some(cancel_flag.as_ref()))
+                    .await
+            }
+        };
+
+        match result {
+            Ok(byte) => SearchResponse::PositionFound {
+                request_id,
+                byte,
+                message: byte.is_none().then(|| {
+                    match direction {
+                        SearchDirection::Forward => "No differing line below",
+                        SearchDirection::Backward => "No differing line above",
+                    }
+                    .to_string()
+                }),
+            },
+            Err(RllessError::Cancelled) => SearchResponse::SearchCancelled { request_id },
+            Err(error) => SearchResponse::Error { request_id, error },
+        }
+    }
+
+    /// Find the nearest record start, a line matching `record_start`, past the viewport top
+    ///
+    /// This is a search with a pattern of its own that leaves the active search alone. Backward
+    /// it lands on the start of the record holding the top row when that row is inside one.
+    pub(super) async fn navigate_record(
+        &self,
+        request_id: RequestId,
+        direction: SearchDirection,
+        record_start: Arc<str>,
+        current_top: u64,
+        cancel_flag: CancellationToken,
+    ) -> SearchResponse {
+        let options = record_options();
+        let result = match self
+            .start_position_for_navigation(direction, current_top)
+            .await
+        {
+            Ok(None) => Ok(None),
+            Ok(Some(start)) => match direction {
+                SearchDirection::Forward => {
+                    self.search_engine
+                        .search_from(
+                            &record_start,
+                            start,
+                            &options,
+                            Some(cancel_flag.as_ref()),
+                            None,
+                        )
+                        .await
+                }
+                SearchDirection::Backward => {
+                    self.search_engine
+                        .search_prev(
+                            &record_start,
+                            start,
+                            &options,
+                            Some(cancel_flag.as_ref()),
+                            None,
+                        )
+                        .await
+                }
+            },
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(byte) => SearchResponse::PositionFound {
+                request_id,
+                byte,
+                message: byte.is_none().then(|| {
+                    match direction {
+                        SearchDirection::Forward => "No record starts below",
+                        SearchDirection::Backward => "No record starts above",
+                    }
+                    .to_string()
+                }),
+            },
+            Err(RllessError::Cancelled) => SearchResponse::SearchCancelled { request_id },
+            Err(error) => SearchResponse::Error { request_id, error },
+        }
+    }
+
+    /// Find the first record stamped at or after `target` (`:time`)
+    ///
+    /// Records are found as for `navigate_record`. The file is bisected while its timestamps go
+    /// forward, and scanned from the top, with progress, once they are seen going backwards.
+    pub(super) async fn navigate_time(
+        &self,
+        request_id: RequestId,
+        target: TimeTarget,
+        record_start: Arc<str>,
+        current_top: u64,
+        cancel_flag: CancellationToken,
+    ) -> SearchResponse {
+        let accessor = self.file_accessor.as_ref();
+        let engine = &self.search_engine;
+        let options = record_options();
+        let is_record = |line: &str| {
+            engine
+                .get_line_matches(&record_start, line, &options)
+                .is_ok_and(|matches| !matches.is_empty())
+        };
+        let cancel = Some(cancel_flag.as_ref());
+
+        let result = async {
+            let date = match target.date {
+                Some(date) => Some(date),
+                None => {
+                    match timestamps::date_from(accessor, current_top, &is_record, cancel).await? {
+                        Some(date) => Some(date),
+                        // Past the last record: its day is the closest there is
+                        None => timestamps::date_from(accessor, 0, &is_record, cancel).await?,
+                    }
+                }
+            };
+            let Some(date) = date else {
+                return Ok((None, "No timestamped records in this file".to_string()));
+            };
+            let stamp = target.on(date);
+            let not_found = format!("No record at or after {}", stamp);
+            match timestamps::bisect(accessor, &is_record, stamp, cancel).await? {
+                Bisected::Found(byte) => Ok((byte, not_found)),
+                Bisected::Unordered => {
+                    let progress = self.search_progress(request_id, accessor.file_size());
+                    let progress = progress.as_ref().map(|report| report as ScanProgress);
+                    let at_or_after = |line: &str| {
+                        let late_enough = leading_timestamp(line.as_bytes())
+                            .is_some_and(|line_stamp| line_stamp >= stamp);
+                        if late_enough && is_record(line) {
+                            vec![(0, line.len())]
+                        } else {
+                            Vec::new()
+                        }
+                    };
+                    let byte = accessor
+                        .find_next_match(0, &at_or_after, cancel, progress)
+                        .await?;
+                    Ok((byte, format!("{} (timestamps out of order)", not_found)))
+                }
+            }
+        }
+        .await;
+
+        match result {
+            Ok((byte, message)) => SearchResponse::PositionFound {
+                request_id,
+                byte,
+                message: byte.is_none().then_some(message),
+            },
+            Err(RllessError::Cancelled) => SearchResponse::SearchCancelled { request_id },
+            Err(error) => SearchResponse::Error { request_id, error },
+        }
+    }
+
+    /// The whole logical line holding the row at `row_start`, with its highlights
+    ///
+    /// Falls back to the last search's highlights when the request carries none.
+    pub(super) async fn peek_line(
+        &mut self,
+        request_id: RequestId,
+        row_start: u64,
+        highlights: Option<Arc<SearchHighlightSpec>>,
+        filter: Option<Arc<SearchHighlightSpec>>,
+    ) -> Result<SearchResponse> {
+        let line = read_full_line(self.file_accessor.as_ref(), row_start).await?;
+        let search = highlights.or_else(|| {
+            self.last_highlight
+                .clone()
+                .filter(|_| self.search_highlights)
+        });
+        // Same span cap as viewport rows, so a match-dense megabyte line stays cheap to draw
+        let (spans, _) = line_highlights(
+            &self.search_engine,
+            search.as_deref(),
+            self.shown_kept_patterns(),
+            filter.as_deref(),
+            &line.text,
+        )?;
+
+        Ok(SearchResponse::LinePeeked {
+            request_id,
+            start: line.start,
+            text: line.text,
+            highlights: spans,
+            truncated: line.truncated,
+        })
+    }
+}
+
+/// Options record start patterns are matched with, whatever the search options are
+fn record_options() -> SearchOptions {
+    SearchOptions {
+        case_sensitive: true,
+        whole_word: false,
+        regex_mode: true,
+        ..SearchOptions::default()
+    }
+}
@@ -0,0 +1,88 @@
+//! The match list (`M`): lines matching a search, with the beginning of their text.
+
+use super::WorkerState;
+use crate::error::{Result, RllessError};
+use crate::render::protocol::{RequestId, SearchHighlightSpec, SearchResponse};
+use crate::search::peek::read_line_start;
+use crate::search::SearchEngine;
+use std::sync::atomic::AtomicBool;
+
+/// Most bytes of a matching line sent for the list; a row shows no more than a screen's width
+const MATCH_PREVIEW_BYTES: usize = 512;
+
+/// Lines per `MatchBatch`, so the first rows show up before a sparse scan goes on
+const MATCH_BATCH_LINES: usize = 100;
+
+impl WorkerState {
+    /// Up to `limit` lines matching `search`, from the first one starting at or after `from`
+    ///
+    /// Full batches are sent as they fill; the last one is returned, saying where the next
+    /// request should start.
+    pub(super) async fn collect_matches(
+        &self,
+        request_id: RequestId,
+        search: &SearchHighlightSpec,
+        from: u64,
+        limit: usize,
+        cancel_flag: &AtomicBool,
+    ) -> Result<SearchResponse> {
+        // Compile up front so an invalid pattern is an error rather than an empty list
+        self.search_engine
+            .get_line_matches(&search.pattern, "", &search.options)?;
+        let engine = &self.search_engine;
+        let matcher = |line: &str| {
+            engine
+                .get_line_matches(&search.pattern, line, &search.options)
+                .unwrap_or_default()
+        };
+        let accessor = self.file_accessor.as_ref();
+        let file_size = accessor.file_size();
+
+        let mut batch = Vec::new();
+        let mut found = 0;
+        let mut next = (from < file_size).then_some(from);
+        while found < limit {
+            let Some(start) = next else {
+                break;
+            };
+            let Some(line_start) = accessor
+                .find_next_match(start, &matcher, Some(cancel_flag), None)
+                .await?
+            else {
+                next = None;
+                break;
+            };
+            let line = read_line_start(accessor, line_start, MATCH_PREVIEW_BYTES).await?;
+            batch.push((line_start, preview(&line.text)));
+            found += 1;
+
+            let following = accessor.next_page_start(line_start, 1).await?;
+            next = (following > line_start && following < file_size).then_some(following);
+            if batch.len() < MATCH_BATCH_LINES || found == limit || next.is_none() {
+                continue;
+            }
+            if let Some(tx) = &self.progress_tx {
+                let full = SearchResponse::MatchBatch {
+                    request_id,
+                    matches: std::mem::take(&mut batch),
+                    last: false,
+                    resume_from: None,
+                };
+                if tx.send(full).await.is_err() {
+                    return Err(RllessError::cancelled());
+                }
+            }
+        }
+        Ok(SearchResponse::MatchBatch {
+            request_id,
+            matches: batch,
+            last: true,
+            resume_from: next,
+        })
+    }
+}
+
+/// `text` on one row: tabs become spaces and a carriage return before the newline goes
+fn preview(text: &str) -> String {
+    text.strip_suffix('\r').unwrap_or(text).replace('\t', " ")
+}
@@ -0,0 +1,542 @@
+//! Moving between the matches of the active search (`n` / `N`), or past them (ESC-n / ESC-N).
+
+use super::{not_found_in_direction, scan_length, WorkerState};
+use crate::error::{Result, RllessError};
+use crate::file_handler::ScanProgress;
+use crate::input::SearchDirection;
+use crate::render::protocol::{
+    MatchTraversal, RequestId, ScanStats, SearchHighlightSpec, SearchResponse,
+};
+use crate::search::{PatternModifiers, SearchEngine, SearchOptions};
+use crate::util::{check_cancelled, CancellationToken};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Rows read and matched per step while looking for one the active search does not match
+const NON_MATCH_BLOCK_ROWS: usize = 1024;
+
+impl WorkerState {
+    /// Repeat the active search (`n` / `N`), following less:
+    ///
+    /// | search started with | key | moves    | first candidate line        |
+    /// |---------------------|-----|----------|-----------------------------|
+    /// | `/`                 | `n` | forward  | the row after the origin    |
+    /// | `/`                 | `N` | backward | the row before the origin   |
+    /// | `?`                 | `n` | backward | the row before the origin   |
+    /// | `?`                 | `N` | forward  | the row after the origin    |
+    ///
+    /// The origin is the row `origin_row` rows below `current_top`: the top row, or the row `-j`
+    /// puts matches on. It holds the current match, so it is never a candidate. Running off
+    /// either end of the file reports which direction came up empty and leaves the view alone;
+    /// repeating the same key from the same place then wraps and scans the whole file from the
+    /// other end, and only if that fails too is the pattern reported as not found at all.
+    ///
+    /// A `count` above one moves that many matches at once (`3n`), stopping at the last match
+    /// when fewer are left.
+    pub(super) async fn navigate_match(
+        &mut self,
+        request_id: RequestId,
+        traversal: MatchTraversal,
+        current_top: u64,
+        origin_row: usize,
+        count: u64,
+        cancel_flag: CancellationToken,
+    ) -> SearchResponse {
+        let ctx_snapshot = match self.context.as_ref() {
+            Some(ctx) => (ctx.direction, ctx.options.clone(), Arc::clone(&ctx.pattern)),
+            None if traversal.is_non_match() => {
+                return SearchResponse::PositionFound {
+                    request_id,
+                    byte: None,
+                    message: Some("No active search".to_string()),
+                };
+            }
+            None => {
+                return SearchResponse::SearchCompleted {
+                    request_id,
+                    match_byte: None,
+                    message: Some("No active search".to_string()),
+                    confirm: None,
+                    scan: None,
+                };
+            }
+        };
+
+        let origin = match self.origin_line(current_top, origin_row).await {
+            Ok(byte) => byte,
+            Err(error) => return SearchResponse::Error { request_id, error },
+        };
+        let (direction, options, pattern) = ctx_snapshot;
+        let direction = match traversal {
+            MatchTraversal::Next | MatchTraversal::NextNonMatch => direction,
+            MatchTraversal::Previous | MatchTraversal::PreviousNonMatch => direction.reverse(),
+        };
+        if traversal.is_non_match() {
+            return self
+                .navigate_non_match(
+                    request_id,
+                    &pattern,
+                    &options,
+                    direction,
+                    origin,
+                    cancel_flag,
+                )
+                .await;
+        }
+
+        let wrap = self.wrap_armed.take() == Some((direction, origin));
+        let not_found = |worker: &mut Self, scan: Option<ScanStats>| {
+            let message = if wrap {
+                "Pattern not found".to_string()
+            } else {
+                worker.wrap_armed = Some((direction, origin));
+                let key = if traversal == MatchTraversal::Next {
+                    'n'
+                } else {
+                    'N'
+                };
+                format!(
+                    "{} (press {} again to wrap)",
+                    not_found_in_direction(direction),
+                    key
+                )
+            };
+            SearchResponse::SearchCompleted {
+                request_id,
+                match_byte: None,
+                message: Some(message),
+                confirm: None,
+                scan,
+            }
+        };
+
+        let start_byte = if wrap {
+            match direction {
+                SearchDirection::Forward => Ok(Some(0)),
+                SearchDirection::Backward => Ok(Some(self.file_accessor.file_size())),
+            }
+        } else {
+            self.start_position_for_navigation(direction, origin).await
+        };
+        let start_byte = match start_byte {
+            Ok(Some(byte)) => byte,
+            Ok(None) => return not_found(self, None),
+            Err(error) => {
+                return SearchResponse::Error { request_id, error };
+            }
+        };
+
+        let started = Instant::now();
+        let first_start = start_byte;
+        let mut found = None;
+        let mut start_byte = start_byte;
+        for step in 0..count.max(1) {
+            if let Some(previous) = found.filter(|_| step > 0) {
+                start_byte = match self
+                    .start_position_for_navigation(direction, previous)
+                    .await
+                {
+                    Ok(Some(byte)) => byte,
+                    Ok(None) => break,
+                    Err(error) => return SearchResponse::Error { request_id, error },
+                };
+            }
+            let progress = self.search_progress(
+                request_id,
+                scan_length(direction, start_byte, self.file_accessor.file_size()),
+            );
+            let progress = progress.as_ref().map(|report| report as ScanProgress);
+            let result = match direction {
+                SearchDirection::Forward => {
+                    self.search_engine
+                        .search_from(
+                            pattern.as_ref(),
+                            start_byte,
+                            &options,
+                            Some(cancel_flag.as_ref()),
+                            progress,
+                        )
+                        .await
+                }
+                SearchDirection::Backward => {
+                    self.search_engine
+                        .search_prev(
+                            pattern.as_ref(),
+                            start_byte,
+                            &options,
+                            Some(cancel_flag.as_ref()),
+                            progress,
+                        )
+                        .await
+                }
+            };
+            match result {
+                Ok(Some(byte)) => found = Some(byte),
+                Ok(None) => break,
+                Err(RllessError::Cancelled) => {
+                    return SearchResponse::SearchCancelled { request_id }
+                }
+                Err(error) => return SearchResponse::Error { request_id, error },
+            }
+        }
+
+        let Some(byte) = found else {
+            let scan = ScanStats {
+                bytes: scan_length(direction, first_start, self.file_accessor.file_size()),
+                elapsed: started.elapsed(),
+            };
+            return not_found(self, Some(scan));
+        };
+        let scan = ScanStats {
+            bytes: byte.abs_diff(first_start),
+            elapsed: started.elapsed(),
+        };
+        if let Some(ctx) = self.context.as_mut() {
+            ctx.last_match_byte = Some(byte);
+            self.last_highlight = Some(Arc::new(SearchHighlightSpec {
+                pattern: Arc::clone(&ctx.pattern),
+                options: ctx.options.clone(),
+                modifiers: PatternModifiers::default(),
+            }));
+        }
+        SearchResponse::SearchCompleted {
+            request_id,
+            match_byte: Some(byte),
+            message: None,
+            confirm: None,
+            scan: Some(scan),
+        }
+    }
+
+    /// Jump to the nearest row past `origin`, moving in `direction`, that the active search does
+    /// not match (ESC-n / ESC-N), to get past a block of matching lines
+    ///
+    /// Rows are read and matched `NON_MATCH_BLOCK_ROWS` at a time. The current match and the
+    /// search stay as they are, so the answer is a `PositionFound`.
+    async fn navigate_non_match(
+        &self,
+        request_id: RequestId,
+        pattern: &str,
+        options: &SearchOptions,
+        direction: SearchDirection,
+        origin: u64,
+        cancel_flag: CancellationToken,
+    ) -> SearchResponse {
+        let progress = self.search_progress(
+            request_id,
+            scan_length(direction, origin, self.file_accessor.file_size()),
+        );
+        let progress = progress.as_ref().map(|report| report as ScanProgress);
+        let result = match self.start_position_for_navigation(direction, origin).await {
+            Ok(Some(start)) => {
+                self.find_non_match(
+                    pattern,
+                    options,
+                    direction,
+                    start,
+                    Some(cancel_flag.as_ref()),
+                    progress,
+                )
+                .await
+            }
+            other => other,
+        };
+        match result {
+            Ok(byte) => SearchResponse::PositionFound {
+                request_id,
+                byte,
+                message: byte
+                    .is_none()
+                    .then(|| "No non-matching line found".to_string()),
+            },
+            Err(RllessError::Cancelled) => SearchResponse::SearchCancelled { request_id },
+            Err(error) => SearchResponse::Error { request_id, error },
+        }
+    }
+
+    /// First row from `start` in `direction` that `pattern` does not match
+    ///
+    /// Forward the rows start at `start`; backward they end before it.
+    async fn find_non_match(
+        &self,
+        pattern: &str,
+        options: &SearchOptions,
+        direction: SearchDirection,
+        start: u64,
+        cancel_flag: Option<&AtomicBool>,
+        progress: Option<ScanProgress<'_>>,
+    ) -> Result<Option<u64>> {
+        let accessor = self.file_accessor.as_ref();
+        let engine = &self.search_engine;
+        match direction {
+            SearchDirection::Forward => {
+                let mut pos = start;
+                loop {
+                    check_cancelled(cancel_flag)?;
+                    let page = accessor
+                        .read_from_byte_raw(pos, NON_MATCH_BLOCK_ROWS)
+                        .await?;
+                    let matches =
+                        engine.get_matches_in(pattern, &page.bytes, &page.rows, options)?;
+                    if let Some(row) = matches.iter().position(Vec::is_empty) {
+                        return Ok(Some(pos + page.rows[row].start as u64));
+                    }
+                    if page.rows.len() < NON_MATCH_BLOCK_ROWS {
+                        return Ok(None);
+                    }
+                    let next = accessor.next_page_start(pos, page.rows.len()).await?;
+                    if next <= pos {
+                        return Ok(None);
+                    }
+                    if let Some(progress) = progress {
+                        progress(next - start);
+                    }
+                    pos = next;
+                }
+            }
+            SearchDirection::Backward => {
+                // Exclusive end of the rows still to be matched
+                let mut end = start;
+                while end > 0 {
+                    check_cancelled(cancel_flag)?;
+                    let from = accessor.prev_page_start(end, NON_MATCH_BLOCK_ROWS).await?;
+                    let page = accessor
+                        .read_from_byte_raw(from, NON_MATCH_BLOCK_ROWS)
+                        .await?;
+                    let rows: Vec<_> = page
+                        .rows
+                        .into_iter()
+                        .take_while(|row| from + (row.start as u64) < end)
+                        .collect();
+                    let matches = engine.get_matches_in(pattern, &page.bytes, &rows, options)?;
+                    if let Some(row) = matches.iter().rposition(Vec::is_empty) {
+                        return Ok(Some(from + rows[row].start as u64));
+                    }
+                    if from >= end {
+                        break;
+                    }
+                    if let Some(progress) = progress {
+                        progress(start - from);
+                    }
+                    end = from;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Start of the row `origin_row` rows below `top`, the line a search counts from, or of the
+    /// last row when the file ends first
+    pub(super) async fn origin_line(&self, top: u64, origin_row: usize) -> Result<u64> {
+        if origin_row == 0 {
+            return Ok(top);
+        }
+        let file_size = self.file_accessor.file_size();
+        let line = self.file_accessor.next_page_start(top, origin_row).await?;
+        if line < file_size {
+            return Ok(line);
+        }
+        let last = self.file_accessor.prev_page_start(file_size, 1).await?;
+        Ok(last.max(top))
+    }
+
+    /// Where a repeated search moving in `direction` starts, or `None` if there is nothing
+    /// past the top row in that direction.
+    ///
+    /// Forward searches start at the next row; backward searches end just before the top row
+    /// (`search_prev` is exclusive of its start).
+    pub(super) async fn start_position_for_navigation(
+        &self,
+        direction: SearchDirection,
+        current_top: u64,
+    ) -> Result<Option<u64>> {
+        match direction {
+            SearchDirection::Forward => {
+                let next = self.file_accessor.next_page_start(current_top, 1).await?;
+                Ok((next < self.file_accessor.file_size()).then_some(next))
+            }
+            SearchDirection::Backward => Ok((current_top > 0).then_some(current_top)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileAccessor;
+    use crate::render::protocol::SearchContext;
+    use crate::search::{RipgrepEngine, SearchOptions};
+
+    #[tokio::test]
+    async fn match_navigation_follows_less_direction_table() {
+        // (search started with, key pressed, whether the repeat looks after the top row)
+        let table = [
+            (SearchDirection::Forward, MatchTraversal::Next, true),
+            (SearchDirection::Forward, MatchTraversal::Previous, false),
+            (SearchDirection::Backward, MatchTraversal::Next, false),
+            (SearchDirection::Backward, MatchTraversal::Previous, true),
+        ];
+        // `hit` matches start at bytes 0, 13, 21 and 36; the last line is unterminated.
+        // (top row, first match after it, nearest match before it), never wrapping around.
+        let positions = [
+            (0, Some(13), None),
+            (8, Some(13), Some(0)),
+            (13, Some(21), Some(0)),
+            (21, Some(36), Some(13)),
+            (31, Some(36), Some(21)),
+            (36, None, Some(21)),
+        ];
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            "hit one\nmiss\nhit two\nhit three\nmiss\nhit four",
+        )
+        .unwrap();
+        let accessor: Arc<dyn FileAccessor> = Arc::new(
+            crate::file_handler::FileAccessorFactory::create(file.path())
+                .await
+                .unwrap(),
+        );
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        let mut worker = WorkerState::new(accessor, engine);
+
+        for (direction, traversal, looks_after) in table {
+            for (top, after, before) in positions {
+                worker.context = Some(SearchContext {
+                    pattern: Arc::from("hit"),
+                    direction,
+                    options: SearchOptions::default(),
+                    last_match_byte: None,
+                });
+                let response = worker
+                    .navigate_match(1, traversal, top, 0, 1, CancellationToken::new())
+                    .await;
+                let SearchResponse::SearchCompleted { match_byte, .. } = response else {
+                    panic!("unexpected response: {response:?}");
+                };
+                let expected = if looks_after { after } else { before };
+                assert_eq!(
+                    match_byte, expected,
+                    "{direction:?} search, {traversal:?} from byte {top}"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn not_found_messages_name_direction_until_wrapped() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "hit one\nmiss\nhit two\n").unwrap();
+        let accessor: Arc<dyn FileAccessor> = Arc::new(
+            crate::file_handler::FileAccessorFactory::create(file.path())
+                .await
+                .unwrap(),
+        );
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        let mut worker = WorkerState::new(accessor, engine);
+        let below_n = "Pattern not found below current position (press n again to wrap)";
+        let above_n = "Pattern not found above current position (press n again to wrap)";
+        let below = "Pattern not found below current position";
+        let above = "Pattern not found above current position";
+        let above_shift_n = "Pattern not found above current position (press N again to wrap)";
+
+        macro_rules! search {
+            ($pattern:expr, $direction:expr, $origin:expr) => {
+                match worker
+                    .execute_search(
+                        1,
+                        Arc::from($pattern),
+                        $direction,
+                        SearchOptions::default(),
+                        $origin,
+                        0,
+                        false,
+                        None,
+                        false,
+                        CancellationToken::new(),
+                    )
+                    .await
+                {
+                    SearchResponse::SearchCompleted {
+                        match_byte,
+                        message,
+                        ..
+                    } => (match_byte, message),
+                    other => panic!("unexpected response: {other:?}"),
+                }
+            };
+        }
+        macro_rules! navigate {
+            ($traversal:expr, $top:expr) => {
+                match worker
+                    .navigate_match(1, $traversal, $top, 0, 1, CancellationToken::new())
+                    .await
+                {
+                    SearchResponse::SearchCompleted {
+                        match_byte,
+                        message,
+                        ..
+                    } => (match_byte, message),
+                    other => panic!("unexpected response: {other:?}"),
+                }
+            };
+        }
+
+        // A forward search from the top has scanned everything already
+        assert_eq!(
+            search!("absent", SearchDirection::Forward, 0),
+            (None, Some("Pattern not found".to_string()))
+        );
+        // Other searches only looked one way
+        assert_eq!(
+            search!("one", SearchDirection::Forward, 8),
+            (None, Some(below.to_string()))
+        );
+        assert_eq!(
+            search!("two", SearchDirection::Backward, 8),
+            (None, Some(above.to_string()))
+        );
+
+        // n past the last match, then n again wraps to the first
+        assert_eq!(
+            search!("hit", SearchDirection::Forward, 13),
+            (Some(13), None)
+        );
+        assert_eq!(
+            navigate!(MatchTraversal::Next, 13),
+            (None, Some(below_n.to_string()))
+        );
+        assert_eq!(navigate!(MatchTraversal::Next, 13), (Some(0), None));
+
+        // N before the first match, then N again wraps to the last
+        assert_eq!(
+            navigate!(MatchTraversal::Previous, 0),
+            (None, Some(above_shift_n.to_string()))
+        );
+        assert_eq!(navigate!(MatchTraversal::Previous, 0), (Some(13), None));
+
+        // Moving elsewhere in between starts over instead of wrapping
+        assert_eq!(
+            navigate!(MatchTraversal::Next, 13),
+            (None, Some(below_n.to_string()))
+        );
+        assert_eq!(
+            navigate!(MatchTraversal::Previous, 0),
+            (None, Some(above_shift_n.to_string()))
+        );
+
+        // A wrapped scan that still finds nothing reports the plain message
+        worker.context.as_mut().unwrap().pattern = Arc::from("absent");
+        worker.context.as_mut().unwrap().direction = SearchDirection::Backward;
+        assert_eq!(
+            navigate!(MatchTraversal::Next, 13),
+            (None, Some(above_n.to_string()))
+        );
+        assert_eq!(
+            navigate!(MatchTraversal::Next, 13),
+            (None, Some("Pattern not found".to_string()))
+        );
+    }
+}
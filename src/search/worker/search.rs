@@ -0,0 +1,376 @@
+//! Running new searches.
+
+use super::{not_found_in_direction, scan_length, WorkerState};
+use crate::error::RllessError;
+use crate::file_handler::ScanProgress;
+use crate::input::SearchDirection;
+use crate::render::protocol::{
+    FrequentMatches, RequestId, ScanStats, SearchContext, SearchHighlightSpec, SearchResponse,
+};
+use crate::search::frequency;
+use crate::search::{PatternModifiers, SearchEngine, SearchOptions};
+use crate::util::CancellationToken;
+use std::sync::Arc;
+use std::time::Instant;
+
+impl WorkerState {
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn execute_search(
+        &mut self,
+        request_id: RequestId,
+        pattern: Arc<str>,
+        direction: SearchDirection,
+        options: SearchOptions,
+        origin_byte: u64,
+        origin_row: usize,
+        skip_origin_row: bool,
+        frequency_limit: Option<u64>,
+        preview: bool,
+        cancel_flag: CancellationToken,
+    ) -> SearchResponse {
+        if !preview {
+            self.wrap_armed = None;
+        }
+        let origin_byte = match self.origin_line(origin_byte, origin_row).await {
+            Ok(byte) => byte,
+            Err(error) => return SearchResponse::Error { request_id, error },
+        };
+        let origin_byte = if skip_origin_row {
+            match self
+                .start_position_for_navigation(direction, origin_byte)
+                .await
+            {
+                Ok(Some(byte)) => byte,
+                Ok(None) => match direction {
+                    SearchDirection::Forward => self.file_accessor.file_size(),
+                    SearchDirection::Backward => 0,
+                },
+                Err(error) => return SearchResponse::Error { request_id, error },
+            }
+        } else {
+            origin_byte
+        };
+        if let Some(limit) = frequency_limit {
+            // Probe before touching the active context, so declining leaves the previous
+            // search in place
+            let engine = &self.search_engine;
+            let matcher = |line: &str| {
+                engine
+                    .get_line_matches(&pattern, line, &options)
+                    .unwrap_or_default()
+            };
+            // Compile first so an invalid pattern is reported by the search itself
+            let probe = match engine.get_line_matches(&pattern, "", &options) {
+                Ok(_) => {
+                    frequency::matches_per_mb(
+                        self.file_accessor.as_ref(),
+                        &matcher,
+                        origin_byte,
+                        direction == SearchDirection::Backward,
+                    )
+                    .await
+                }
+                Err(_) => Ok(None),
+            };
+            match probe {
+                Ok(Some(matches_per_mb)) if matches_per_mb > limit => {
+                    return SearchResponse::SearchCompleted {
+                        request_id,
+                        match_byte: None,
+                        message: None,
+                        confirm: Some(FrequentMatches {
+                            pattern,
+                            direction,
+                            options,
+                            skip_origin_row,
+                            matches_per_mb,
+                        }),
+                        scan: None,
+                    };
+                }
+                Ok(_) => {}
+                Err(error) => return SearchResponse::Error { request_id, error },
+            }
+        }
+        let mut new_context = SearchContext {
+            pattern: Arc::clone(&pattern),
+            direction,
+            options: options.clone(),
+            last_match_byte: None,
+        };
+
+        // A preview is answered long before a scan of any size would report
+        let progress = self
+            .search_progress(
+                request_id,
+                scan_length(direction, origin_byte, self.file_accessor.file_size()),
+            )
+            .filter(|_| !preview);
+        let progress = progress.as_ref().map(|report| report as ScanProgress);
+        let search_future = match direction {
+            SearchDirection::Forward => self.search_engine.search_from(
+                pattern.as_ref(),
+                origin_byte,
+                &options,
+                Some(cancel_flag.as_ref()),
+                progress,
+            ),
+            SearchDirection::Backward => self.search_engine.search_prev(
+                pattern.as_ref(),
+                origin_byte,
+                &options,
+                Some(cancel_flag.as_ref()),
+                progress,
+            ),
+        };
+        // Responsibility for honouring the cancel token lives in the engine/accessor so we can
+        // avoid queueing a separate cancel command (the queue itself remains FIFO).
+
+        let started = Instant::now();
+        let result = search_future.await;
+        // Previews are dropped long before they could be slow enough to mention
+        let scan = |bytes: u64| {
+            Some(ScanStats {
+                bytes,
+                elapsed: started.elapsed(),
+            })
+            .filter(|_| !preview)
+        };
+        match result {
+            Ok(Some(byte)) => {
+                new_context.last_match_byte = Some(byte);
+                if !preview {
+                    self.commit_context(new_context);
+                }
+                SearchResponse::SearchCompleted {
+                    request_id,
+                    match_byte: Some(byte),
+                    message: None,
+                    confirm: None,
+                    scan: scan(byte.abs_diff(origin_byte)),
+                }
+            }
+            Ok(None) => {
+                if !preview {
+                    self.commit_context(new_context);
+                }
+                // A forward search from the top has already covered the whole file. A failed new
+                // search is dropped by the coordinator, so there is nothing to repeat and wrap.
+                let whole_file = direction == SearchDirection::Forward && origin_byte == 0;
+                let message = if whole_file {
+                    "Pattern not found".to_string()
+                } else {
+                    not_found_in_direction(direction)
+                };
+                SearchResponse::SearchCompleted {
+                    request_id,
+                    match_byte: None,
+                    message: Some(message),
+                    confirm: None,
+                    scan: scan(scan_length(
+                        direction,
+                        origin_byte,
+                        self.file_accessor.file_size(),
+                    )),
+                }
+            }
+            Err(error) => match error {
+                RllessError::Cancelled => SearchResponse::SearchCancelled { request_id },
+                // A half-typed pattern is often not valid yet; the prompt notes it quietly
+                other if preview => SearchResponse::SearchCompleted {
+                    request_id,
+                    match_byte: None,
+                    message: Some(other.summary()),
+                    confirm: None,
+                    scan: None,
+                },
+                other => SearchResponse::Error {
+                    request_id,
+                    error: other,
+                },
+            },
+        }
+    }
+
+    /// Make `context` the active search that `n`/`N` and viewport highlights follow
+    fn commit_context(&mut self, context: SearchContext) {
+        self.last_highlight = Some(Arc::new(SearchHighlightSpec {
+            pattern: Arc::clone(&context.pattern),
+            options: context.options.clone(),
+            modifiers: PatternModifiers::default(),
+        }));
+        self.context = Some(context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+    use crate::file_handler::{FileAccessor, FileMeta};
+    use crate::render::protocol::MatchTraversal;
+    use crate::search::RipgrepEngine;
+    use async_trait::async_trait;
+    use std::path::Path;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    const SCAN_DELAY: Duration = Duration::from_millis(30);
+
+    #[tokio::test]
+    async fn completed_searches_report_what_they_scanned() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "alpha\nbeta\ngamma\nbeta again\n").unwrap();
+        let inner: Arc<dyn FileAccessor> = Arc::new(
+            crate::file_handler::FileAccessorFactory::create(file.path())
+                .await
+                .unwrap(),
+        );
+        let accessor: Arc<dyn FileAccessor> = Arc::new(SlowAccessor { inner });
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        let mut worker = WorkerState::new(accessor, engine);
+
+        for (pattern, found, bytes, preview) in [
+            ("gamma", Some(11), 11, false),
+            ("delta", None, 28, false),
+            ("gamma", Some(11), 0, true),
+        ] {
+            let response = worker
+                .execute_search(
+                    1,
+                    Arc::from(pattern),
+                    SearchDirection::Forward,
+                    SearchOptions::default(),
+                    0,
+                    0,
+                    false,
+                    None,
+                    preview,
+                    CancellationToken::new(),
+                )
+                .await;
+            let SearchResponse::SearchCompleted {
+                match_byte, scan, ..
+            } = response
+            else {
+                panic!("unexpected response: {response:?}");
+            };
+            assert_eq!(match_byte, found);
+            match scan {
+                Some(scan) => {
+                    assert_eq!(scan.bytes, bytes);
+                    assert!(scan.elapsed >= SCAN_DELAY);
+                }
+                // Previews are never reported
+                None => assert!(preview),
+            }
+        }
+
+        // Repeating a search counts from the row after the top one
+        worker
+            .execute_search(
+                1,
+                Arc::from("beta"),
+                SearchDirection::Forward,
+                SearchOptions::default(),
+                0,
+                0,
+                false,
+                None,
+                false,
+                CancellationToken::new(),
+            )
+            .await;
+        let response = worker
+            .navigate_match(2, MatchTraversal::Next, 6, 0, 1, CancellationToken::new())
+            .await;
+        let SearchResponse::SearchCompleted {
+            match_byte: Some(17),
+            scan: Some(scan),
+            ..
+        } = response
+        else {
+            panic!("unexpected response: {response:?}");
+        };
+        assert_eq!(scan.bytes, 6);
+        assert!(scan.elapsed >= SCAN_DELAY);
+    }
+
+    /// Delegating accessor whose scans take at least `SCAN_DELAY`.
+    struct SlowAccessor {
+        inner: Arc<dyn FileAccessor>,
+    }
+
+    #[async_trait]
+    impl FileAccessor for SlowAccessor {
+        async fn read_from_byte(&self, start_byte: u64, max_lines: usize) -> Result<Vec<String>> {
+            self.inner.read_from_byte(start_byte, max_lines).await
+        }
+
+        async fn read_bytes(&self, start_byte: u64, length: usize) -> Result<Vec<u8>> {
+            self.inner.read_bytes(start_byte, length).await
+        }
+
+        async fn find_next_match(
+            &self,
+            start_byte: u64,
+            search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            cancel_flag: Option<&AtomicBool>,
+            progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            tokio::time::sleep(SCAN_DELAY).await;
+            self.inner
+                .find_next_match(start_byte, search_fn, cancel_flag, progress)
+                .await
+        }
+
+        async fn find_prev_match(
+            &self,
+            start_byte: u64,
+            search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            cancel_flag: Option<&AtomicBool>,
+            progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            tokio::time::sleep(SCAN_DELAY).await;
+            self.inner
+                .find_prev_match(start_byte, search_fn, cancel_flag, progress)
+                .await
+        }
+
+        fn file_size(&self) -> u64 {
+            self.inner.file_size()
+        }
+
+        fn file_path(&self) -> &Path {
+            self.inner.file_path()
+        }
+
+        fn ends_with_newline(&self) -> bool {
+            self.inner.ends_with_newline()
+        }
+
+        fn file_meta(&self) -> Option<FileMeta> {
+            self.inner.file_meta()
+        }
+
+        fn sections(&self) -> &[u64] {
+            self.inner.sections()
+        }
+
+        async fn last_page_start(&self, max_lines: usize) -> Result<u64> {
+            self.inner.last_page_start(max_lines).await
+        }
+
+        async fn next_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
+            self.inner
+                .next_page_start(current_byte, lines_to_skip)
+                .await
+        }
+
+        async fn prev_page_start(&self, current_byte: u64, lines_to_skip: usize) -> Result<u64> {
+            self.inner
+                .prev_page_start(current_byte, lines_to_skip)
+                .await
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Moving on to another file: the newest of a rotated family (`--latest`), or a fresh read of
+//! the open file after log rotation truncated or replaced it.
+
+use super::WorkerState;
+use crate::error::{Result, RllessError};
+use crate::file_handler::{
+    newest_match, FileAccessor, FileAccessorFactory, FileIdentity, FileKey, STDIN_PATH,
+};
+use crate::render::protocol::SearchResponse;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What the file being read looked like on disk when last checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct DiskState {
+    key: FileKey,
+    len: u64,
+}
+
+impl DiskState {
+    fn of(path: &Path, meta: &Metadata) -> Option<Self> {
+        Some(Self {
+            key: FileKey::new(path, meta).ok()?,
+            len: meta.len(),
+        })
+    }
+}
+
+impl WorkerState {
+    /// Open the newest file matching `pattern` if it is not the file being read
+    ///
+    /// Returns `None` when nothing matches or the newest match is already open.
+    pub(super) async fn switch_to_latest(
+        &mut self,
+        pattern: PathBuf,
+    ) -> Result<Option<SearchResponse>> {
+        let newest = tokio::task::spawn_blocking(move || newest_match(&pattern))
+            .await
+            .map_err(|e| RllessError::other(format!("Listing task failed: {}", e)))??;
+        let Some(path) = newest else {
+            return Ok(None);
+        };
+        if self.is_open(&path) {
+            return Ok(None);
+        }
+
+        let accessor = FileAccessorFactory::create(&path)
+            .await
+            .map_err(|e| e.context("Failed to open the newest matching file"))?
+            .with_line_terminator(self.shared_accessor.line_terminator());
+        log::info!(
+            "worker: switched to a newer file of {} bytes",
+            accessor.file_size()
+        );
+        self.replace_accessor(Arc::new(accessor));
+        Ok(Some(SearchResponse::FileSwitched {
+            path,
+            file_size: self.shared_accessor.file_size(),
+            sections: self.shared_accessor.sections().to_vec(),
+        }))
+    }
+
+    /// Read the file afresh if it was truncated or replaced on disk since the last check
+    ///
+    /// logrotate either truncates the file in place (`copytruncate`) or moves it away and creates
+    /// a new one, so a file that shrank or whose inode changed is opened again. Returns the
+    /// notice for the coordinator when it was; failures leave the old content in place.
+    pub(super) async fn reload_if_replaced(&mut self) -> Option<SearchResponse> {
+        let path = self.shared_accessor.file_path().to_path_buf();
+        if path == Path::new(STDIN_PATH) {
+            return None;
+        }
+        let current = DiskState::of(&path, &tokio::fs::metadata(&path).await.ok()?)?;
+        // The first check only records the file as it is
+        let known = self.disk.replace(current.clone())?;
+        let replaced = current.key != known.key;
+        if !replaced && current.len >= known.len {
+            return None;
+        }
+
+        let accessor = match FileAccessorFactory::create(&path).await {
+            Ok(accessor) => accessor.with_line_terminator(self.shared_accessor.line_terminator()),
+            Err(error) => {
+                let error = error.context("Failed to reopen the rotated file");
+                log::warn!("worker: {}", error.chain());
+                return None;
+            }
+        };
+        log::info!(
+            "worker: file {} ({} -> {} bytes on disk), reloaded",
+            if replaced { "replaced" } else { "truncated" },
+            known.len,
+            current.len
+        );
+        self.replace_accessor(Arc::new(accessor));
+        self.disk = Some(current);
+        Some(SearchResponse::FileReloaded {
+            file_size: self.shared_accessor.file_size(),
+            sections: self.shared_accessor.sections().to_vec(),
+            message: format!(
+                "file {} — reloaded",
+                if replaced { "replaced" } else { "truncated" }
+            ),
+        })
+    }
+
+    /// Whether `path` leads to the file being read, possibly through a symlink or another
+    /// relative path
+    fn is_open(&self, path: &Path) -> bool {
+        if path == self.shared_accessor.file_path() {
+            return true;
+        }
+        match (self.shared_accessor.identity(), FileIdentity::resolve(path)) {
+            (Some(open), Ok(other)) => open.same_file(&other),
+            _ => false,
+        }
+    }
+
+    /// Read from `accessor` from now on, forgetting positions that belonged to the old content
+    pub(super) fn replace_accessor(&mut self, accessor: Arc<dyn FileAccessor>) {
+        self.search_engine.set_file_accessor(Arc::clone(&accessor));
+        self.file_accessor = Arc::clone(&accessor);
+        self.known_size = accessor.file_size();
+        self.shared_accessor = accessor;
+        self.last_page_start = None;
+        self.wrap_armed = None;
+        self.disk = None;
+        if let Some(context) = self.context.as_mut() {
+            context.last_match_byte = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::protocol::ViewportRequest;
+    use crate::search::RipgrepEngine;
+    use crate::util::CancellationToken;
+
+    fn load(top: ViewportRequest) -> crate::render::protocol::SearchCommand {
+        crate::render::protocol::SearchCommand::LoadViewport {
+            request_id: 1,
+            top,
+            page_lines: 5,
+            highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
+        }
+    }
+
+    async fn worker_for(path: &Path, force_mmap: bool) -> WorkerState {
+        let accessor: Arc<dyn FileAccessor> = Arc::new(
+            FileAccessorFactory::create_with_strategy(path, force_mmap)
+                .await
+                .unwrap(),
+        );
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        WorkerState::new(accessor, engine)
+    }
+
+    fn page(outcome: &super::super::HandlerOutcome) -> (u64, Vec<String>) {
+        match &outcome.response {
+            Some(SearchResponse::ViewportLoaded {
+                top_byte, lines, ..
+            }) => (*top_byte, lines.clone()),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn truncated_file_is_reloaded_and_the_viewport_clamped() {
+        for force_mmap in [false, true] {
+            let file = tempfile::NamedTempFile::new().unwrap();
+            let content: String = (0..100).map(|i| format!("old line {i:02}\n")).collect();
+            std::fs::write(file.path(), &content).unwrap();
+            let mut worker = worker_for(file.path(), force_mmap).await;
+
+            let outcome = worker
+                .handle_command(load(ViewportRequest::EndOfFile))
+                .await;
+            assert!(outcome.notice.is_none());
+            let (old_top, _) = page(&outcome);
+            assert!(worker.last_page_start.is_some());
+
+            // copytruncate: same file, shorter content
+            std::fs::write(file.path(), "a\nb\nc\n").unwrap();
+            let outcome = worker
+                .handle_command(load(ViewportRequest::Absolute(old_top)))
+                .await;
+            match &outcome.notice {
+                Some(SearchResponse::FileReloaded {
+                    file_size, message, ..
+                }) => {
+                    assert_eq!(*file_size, 6);
+                    assert_eq!(message, "file truncated — reloaded");
+                }
+                other => panic!("expected reload notice, got {other:?}"),
+            }
+            assert_eq!(
+                page(&outcome),
+                (0, vec!["a".into(), "b".into(), "c".into()])
+            );
+
+            // Later loads read the new content without reloading again
+            let outcome = worker
+                .handle_command(load(ViewportRequest::EndOfFile))
+                .await;
+            assert!(outcome.notice.is_none());
+            assert_eq!(page(&outcome).1, vec!["a", "b", "c"]);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn replaced_file_is_reloaded_even_when_larger() {
+        for force_mmap in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("app.log");
+            std::fs::write(&path, "rotated away\n").unwrap();
+            let mut worker = worker_for(&path, force_mmap).await;
+            assert!(worker
+                .handle_command(load(ViewportRequest::Absolute(0)))
+                .await
+                .notice
+                .is_none());
+
+            // Rotation by rename: a new file takes the name
+            let fresh = dir.path().join("app.log.new");
+            std::fs::write(&fresh, "new file\nwith more lines\n").unwrap();
+            std::fs::rename(&fresh, &path).unwrap();
+            let outcome = worker
+                .handle_command(load(ViewportRequest::Absolute(0)))
+                .await;
+            assert!(matches!(
+                &outcome.notice,
+                Some(SearchResponse::FileReloaded { message, .. })
+                    if message == "file replaced — reloaded"
+            ));
+            assert_eq!(page(&outcome).1, vec!["new file", "with more lines"]);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn latest_match_reached_through_a_symlink_is_not_reopened() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("app-1.log");
+        std::fs::write(&target, "first\n").unwrap();
+        let link = dir.path().join("current.log");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let mut worker = worker_for(&link, false).await;
+
+        // The newest match is the open file under its own name
+        let pattern = dir.path().join("app-*.log");
+        assert!(worker
+            .switch_to_latest(pattern.clone())
+            .await
+            .unwrap()
+            .is_none());
+
+        // A genuinely newer file is still switched to
+        let newer = dir.path().join("app-2.log");
+        std::fs::write(&newer, "second\n").unwrap();
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(&target)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        match worker.switch_to_latest(pattern).await.unwrap() {
+            Some(SearchResponse::FileSwitched { path, .. }) => assert_eq!(path, newer),
+            other => panic!("expected a switch, got {other:?}"),
+        }
+    }
+}
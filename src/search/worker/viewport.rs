@@ -0,0 +1,579 @@
+//! Loading pages of the file with their highlights.
+
+use super::WorkerState;
+use crate::error::Result;
+use crate::file_handler::RawPage;
+use crate::render::protocol::{
+    HighlightKind, HighlightSpan, LinePosition, ProgressOperation, RequestId, SearchHighlightSpec,
+    SearchResponse, ViewportRequest, MIN_PAGE_LINES,
+};
+use crate::search::filter::{LineFilter, LineMatcher};
+use crate::search::line_spans::layered_spans;
+use crate::search::{RipgrepEngine, SearchEngine};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time between reports of line indexing progress
+const INDEXING_REPORT_INTERVAL: Duration = Duration::from_millis(250);
+
+impl WorkerState {
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn load_viewport(
+        &mut self,
+        request_id: RequestId,
+        top: ViewportRequest,
+        page_lines: usize,
+        highlights: Option<Arc<SearchHighlightSpec>>,
+        filter: Option<Arc<SearchHighlightSpec>>,
+        current_match: Option<u64>,
+        cancel_flag: &AtomicBool,
+    ) -> Result<SearchResponse> {
+        let page_lines = page_lines.max(MIN_PAGE_LINES);
+        let file_size = self.file_accessor.file_size();
+        let unterminated = !self.file_accessor.ends_with_newline();
+        let filtered = filter.is_some();
+        let (target_byte, page, at_eof, noeol, current_match_row) = match filter.as_deref() {
+            Some(filter) => {
+                // Compile up front so an invalid filter surfaces as an error instead of
+                // silently hiding every line
+                self.search_engine
+                    .get_line_matches(&filter.pattern, "", &filter.options)?;
+                let engine = &self.search_engine;
+                let matcher = |line: &str| {
+                    engine
+                        .get_line_matches(&filter.pattern, line, &filter.options)
+                        .unwrap_or_default()
+                };
+                let page =
+                    LineFilter::new(self.file_accessor.as_ref(), &matcher, Some(cancel_flag))
+                        .load_page(top, page_lines)
+                        .await?;
+                // A page ending the filtered view shows the final line only if it matches
+                let noeol = unterminated
+                    && page.at_eof
+                    && !page.lines.is_empty()
+                    && self.final_line_matches(&matcher, file_size).await?;
+                let current_match_row = current_match
+                    .and_then(|start| page.starts.iter().position(|&row| row == start));
+                (
+                    page.top_byte,
+                    RawPage::from_lines(&page.lines),
+                    page.at_eof,
+                    noeol,
+                    current_match_row,
+                )
+            }
+            None => {
+                let target_byte = self.resolve_viewport_target(top, page_lines).await?;
+                let page = self
+                    .file_accessor
+                    .read_from_byte_raw(target_byte, page_lines)
+                    .await?;
+                let at_eof = self
+                    .detect_eof(target_byte, page_lines, file_size, &page)
+                    .await?;
+                let noeol = unterminated && at_eof && !page.is_empty();
+                let current_match_row = match current_match {
+                    Some(start) => {
+                        self.row_starting_at(start, target_byte, page.rows.len())
+                            .await?
+                    }
+                    None => None,
+                };
+                (target_byte, page, at_eof, noeol, current_match_row)
+            }
+        };
+
+        let highlight_spec = if let Some(spec) = highlights {
+            self.last_highlight = Some(Arc::clone(&spec));
+            Some(spec)
+        } else if self.search_highlights {
+            self.last_highlight.clone()
+        } else {
+            None
+        };
+
+        let (highlights, highlights_truncated) =
+            self.compute_highlights(highlight_spec.as_deref(), filter.as_deref(), &page)?;
+        // Only now does the page become text, for display
+        let lines = page.to_lines();
+        let top_line = if self.line_numbers && !filtered {
+            self.top_line(target_byte, lines.len()).await?
+        } else {
+            None
+        };
+        let line_position = if self.line_position {
+            self.line_position(target_byte, file_size)
+        } else {
+            None
+        };
+
+        Ok(SearchResponse::ViewportLoaded {
+            request_id,
+            top_byte: target_byte,
+            lines,
+            highlights,
+            at_eof,
+            noeol,
+            highlights_truncated,
+            file_size,
+            top_line,
+            line_position,
+            current_match_row,
+        })
+    }
+
+    /// Row of an unfiltered page of `rows` rows from `top_byte` that starts at `start`
+    ///
+    /// Steps through the rows the way paging does, so a long line cut into several rows counts
+    /// as several.
+    async fn row_starting_at(
+        &self,
+        start: u64,
+        top_byte: u64,
+        rows: usize,
+    ) -> Result<Option<usize>> {
+        let mut row_start = top_byte;
+        for row in 0..rows {
+            if row_start >= start {
+                return Ok(Some(row).filter(|_| row_start == start));
+            }
+            let next = self.file_accessor.next_page_start(row_start, 1).await?;
+            if next <= row_start {
+                break;
+            }
+            row_start = next;
+        }
+        Ok(None)
+    }
+
+    /// Line number of the page's top row, if known and the following rows are whole lines
+    ///
+    /// The gutter numbers rows consecutively, which would go wrong after a line split into
+    /// several rows; such pages show no numbers rather than wrong ones.
+    async fn top_line(&self, top_byte: u64, rows: usize) -> Result<Option<u64>> {
+        let Some(top_line) = self.file_accessor.line_number_for_byte(top_byte) else {
+            self.watch_indexing(top_byte);
+            return Ok(None);
+        };
+        if rows > 1 {
+            let last_row = self
+                .file_accessor
+                .next_page_start(top_byte, rows - 1)
+                .await?;
+            let last_line = self.file_accessor.line_number_for_byte(last_row);
+            if last_line != Some(top_line + rows as u64 - 1) {
+                return Ok(None);
+            }
+        }
+        Ok(Some(top_line))
+    }
+
+    /// Line number of `top_byte` and the line total of a file `file_size` bytes long, as far as
+    /// indexing has got
+    ///
+    /// The total is the number of the line holding the last byte. Whichever is not known yet is
+    /// left out while `watch_indexing` reports the progress towards it.
+    fn line_position(&self, top_byte: u64, file_size: u64) -> Option<LinePosition> {
+        let Some(top) = self.file_accessor.line_number_for_byte(top_byte) else {
+            self.watch_indexing(top_byte);
+            return None;
+        };
+        let last_byte = file_size.saturating_sub(1);
+        let total = self.file_accessor.line_number_for_byte(last_byte);
+        if total.is_none() {
+            self.watch_indexing(last_byte);
+        }
+        Some(LinePosition { top, total })
+    }
+
+    /// Report `Progress` of the line indexing until the number of the line at `byte` is known,
+    /// unless a report task is already running
+    ///
+    /// The fractions come from the accessor's `indexing_progress`; the last report is 1.0.
+    fn watch_indexing(&self, byte: u64) {
+        let Some(tx) = self.progress_tx.clone() else {
+            return;
+        };
+        if self.indexing_watched.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let accessor = Arc::clone(&self.shared_accessor);
+        let watched = Arc::clone(&self.indexing_watched);
+        tokio::spawn(async move {
+            loop {
+                // Asking again also restarts indexing if the content grew past what it covered
+                let fraction = match accessor.line_number_for_byte(byte) {
+                    Some(_) => 1.0,
+                    None => accessor
+                        .indexing_progress()
+                        .map_or(1.0, |progress| progress.fraction().min(0.99)),
+                };
+                let report = SearchResponse::Progress {
+                    operation: ProgressOperation::Count,
+                    fraction,
+                };
+                if tx.send(report).await.is_err() || fraction >= 1.0 {
+                    break;
+                }
+                tokio::time::sleep(INDEXING_REPORT_INTERVAL).await;
+            }
+            watched.store(false, Ordering::Release);
+        });
+    }
+
+    pub(super) async fn resolve_viewport_target(
+        &mut self,
+        top: ViewportRequest,
+        page_lines: usize,
+    ) -> Result<u64> {
+        let file_size = self.file_accessor.file_size();
+
+        if file_size == 0 {
+            return Ok(0);
+        }
+
+        let mut target_byte = match top {
+            ViewportRequest::Absolute(byte) => byte,
+            ViewportRequest::RelativeLines { anchor, lines } => {
+                if lines == 0 {
+                    anchor
+                } else if lines > 0 {
+                    self.file_accessor
+                        .next_page_start(anchor, lines as usize)
+                        .await?
+                } else {
+                    self.file_accessor
+                        .prev_page_start(anchor, (-lines) as usize)
+                        .await?
+                }
+            }
+            ViewportRequest::EndOfFile => {
+                let last_start = self.compute_last_page_start(page_lines, file_size).await?;
+                return Ok(last_start.unwrap_or(0));
+            }
+        };
+
+        // Scanning backwards for the last page start is expensive on cold multi-GB mmaps, so only
+        // do it when the target could actually sit past it.
+        if self
+            .may_pass_last_page(target_byte, page_lines, file_size)
+            .await?
+        {
+            if let Some(last) = self.compute_last_page_start(page_lines, file_size).await? {
+                if target_byte > last {
+                    target_byte = last;
+                }
+            }
+        }
+
+        // Paging forward from inside the final partial page must not scroll back up to the last
+        // page start; stay put and let the EOF check report the end.
+        if let ViewportRequest::RelativeLines { anchor, lines } = top {
+            if lines > 0 && target_byte < anchor {
+                target_byte = anchor;
+            }
+        }
+
+        Ok(target_byte)
+    }
+
+    /// Highlight spans for each row of `page`, and whether any row hit the `MAX_LINE_SPANS` cap.
+    fn compute_highlights(
+        &mut self,
+        search: Option<&SearchHighlightSpec>,
+        filter: Option<&SearchHighlightSpec>,
+        page: &RawPage,
+    ) -> Result<(Vec<Vec<HighlightSpan>>, bool)> {
+        let kept: &[Arc<SearchHighlightSpec>] = if self.search_highlights {
+            &self.kept_patterns
+        } else {
+            &[]
+        };
+        if search.is_none() && filter.is_none() && kept.is_empty() {
+            return Ok((vec![Vec::new(); page.rows.len()], false));
+        }
+
+        let engine = &self.search_engine;
+        let per_row = if let Some(spec) = search.filter(|spec| spec.options.multiline) {
+            // A match may run on past its row, so the page is matched as a whole and the memo,
+            // which works row by row, is passed by. Offsets into text with invalid UTF-8
+            // replaced only fit rows rebuilt from that text.
+            let rebuilt;
+            let page = if std::str::from_utf8(&page.bytes).is_ok() {
+                page
+            } else {
+                rebuilt = RawPage::from_lines(&page.to_lines());
+                &rebuilt
+            };
+            let whole = 0..page.bytes.len();
+            let matches = engine
+                .get_matches_in(
+                    &spec.pattern,
+                    &page.bytes,
+                    std::slice::from_ref(&whole),
+                    &spec.options,
+                )?
+                .pop()
+                .unwrap_or_default();
+            let search = first_row_ranges(&page.rows, matches);
+            page_highlights(engine, search, kept, filter, page, &page.rows)?
+        } else {
+            // Only rows that were not part of the previous viewport go through the matcher
+            let rows: Vec<&[u8]> = page.row_bytes().collect();
+            self.highlight_memo
+                .highlights_for(search, filter, &rows, |missing| {
+                    let rows: Vec<Range<usize>> =
+                        missing.iter().map(|&row| page.rows[row].clone()).collect();
+                    let search = match search {
+                        Some(spec) => engine.get_matches_in(
+                            &spec.pattern,
+                            &page.bytes,
+                            &rows,
+                            &spec.options,
+                        )?,
+                        None => vec![Vec::new(); rows.len()],
+                    };
+                    page_highlights(engine, search, kept, filter, page, &rows)
+                })?
+        };
+        let (highlights, truncated): (Vec<_>, Vec<bool>) = per_row.into_iter().unzip();
+        Ok((highlights, truncated.contains(&true)))
+    }
+
+    /// Kept patterns to highlight: none while ESC-u hides the search's highlights
+    pub(super) fn shown_kept_patterns(&self) -> &[Arc<SearchHighlightSpec>] {
+        if self.search_highlights {
+            &self.kept_patterns
+        } else {
+            &[]
+        }
+    }
+
+    async fn detect_eof(
+        &self,
+        top_byte: u64,
+        page_lines: usize,
+        file_size: u64,
+        page: &RawPage,
+    ) -> Result<bool> {
+        if page.is_empty() {
+            return Ok(true);
+        }
+
+        let next_start = self
+            .file_accessor
+            .next_page_start(top_byte, page_lines)
+            .await?;
+        Ok(next_start >= file_size)
+    }
+
+    /// Whether the last display row of the file passes `matcher`.
+    async fn final_line_matches(&self, matcher: LineMatcher<'_>, file_size: u64) -> Result<bool> {
+        let final_row = self.file_accessor.prev_page_start(file_size, 1).await?;
+        let lines = self.file_accessor.read_from_byte(final_row, 1).await?;
+        Ok(lines.first().is_some_and(|line| !matcher(line).is_empty()))
+    }
+}
+
+/// Spans of `line` for the search, then the kept patterns, then the filter, in that precedence
+pub(super) fn line_highlights(
+    engine: &RipgrepEngine,
+    search: Option<&SearchHighlightSpec>,
+    kept: &[Arc<SearchHighlightSpec>],
+    filter: Option<&SearchHighlightSpec>,
+    line: &str,
+) -> Result<(Vec<HighlightSpan>, bool)> {
+    let line_matches = |spec: Option<&SearchHighlightSpec>| match spec {
+        Some(spec) => engine.get_line_matches(&spec.pattern, line, &spec.options),
+        None => Ok(Vec::new()),
+    };
+    let mut layers = vec![(HighlightKind::Search, line_matches(search)?)];
+    for (index, spec) in kept.iter().enumerate() {
+        if let Some(kind) = HighlightKind::kept(index) {
+            layers.push((kind, line_matches(Some(spec))?));
+        }
+    }
+    layers.push((HighlightKind::Filter, line_matches(filter)?));
+    Ok(layered_spans(layers))
+}
+
+/// Spans of `rows` of `page`, given the search's ranges on each, then those of the kept patterns
+/// and the filter, in that precedence
+fn page_highlights(
+    engine: &RipgrepEngine,
+    search: Vec<Vec<(usize, usize)>>,
+    kept: &[Arc<SearchHighlightSpec>],
+    filter: Option<&SearchHighlightSpec>,
+    page: &RawPage,
+    rows: &[Range<usize>],
+) -> Result<Vec<(Vec<HighlightSpan>, bool)>> {
+    let row_matches = |spec: &SearchHighlightSpec| {
+        engine.get_matches_in(&spec.pattern, &page.bytes, rows, &spec.options)
+    };
+    let mut kept = kept
+        .iter()
+        .map(|spec| row_matches(spec))
+        .collect::<Result<Vec<_>>>()?;
+    let filter = match filter {
+        Some(spec) => row_matches(spec)?,
+        None => vec![Vec::new(); rows.len()],
+    };
+    Ok(search
+        .into_iter()
+        .zip(filter)
+        .enumerate()
+        .map(|(row, (search, filter))| {
+            let mut layers = vec![(HighlightKind::Search, search)];
+            for (index, kept) in kept.iter_mut().enumerate() {
+                if let Some(kind) = HighlightKind::kept(index) {
+                    layers.push((kind, std::mem::take(&mut kept[row])));
+                }
+            }
+            layers.push((HighlightKind::Filter, filter));
+            layered_spans(layers)
+        })
+        .collect())
+}
+
+/// Ranges of `matches` over a whole page on each of its `rows`
+///
+/// A match is marked on the row it starts on, up to the end of that row.
+fn first_row_ranges(
+    rows: &[Range<usize>],
+    matches: Vec<(usize, usize)>,
+) -> Vec<Vec<(usize, usize)>> {
+    let mut ranges = vec![Vec::new(); rows.len()];
+    for (start, end) in matches {
+        let row = rows.partition_point(|row| row.start <= start);
+        let Some(bounds) = row.checked_sub(1).map(|row| &rows[row]) else {
+            continue;
+        };
+        if start < bounds.end {
+            ranges[row - 1].push((start - bounds.start, end.min(bounds.end) - bounds.start));
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::FileMeta;
+    use crate::file_handler::{FileAccessor, ScanProgress};
+    use crate::search::RipgrepEngine;
+    use async_trait::async_trait;
+    use std::path::{Path, PathBuf};
+
+    #[tokio::test]
+    async fn empty_files_resolve_to_zero() {
+        let accessor: Arc<dyn FileAccessor> = Arc::new(EmptyAccessor::default());
+        let engine = RipgrepEngine::new(Arc::clone(&accessor));
+        let mut worker = WorkerState::new(accessor, engine);
+
+        for request in [
+            ViewportRequest::Absolute(10),
+            ViewportRequest::RelativeLines {
+                anchor: 25,
+                lines: 3,
+            },
+            ViewportRequest::EndOfFile,
+        ] {
+            let resolved = worker.resolve_viewport_target(request, 5).await.unwrap();
+            assert_eq!(resolved, 0);
+        }
+    }
+
+    #[test]
+    fn multiline_matches_are_marked_on_their_first_line() {
+        let lines: Vec<String> = ["ERROR one", "\tat Dispatcher.send", "ok ERROR", ""]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        let page = RawPage::from_lines(&lines);
+        // "ERROR one\n\tat", the newline ending "ok ERROR", and "ok"
+        let matches = vec![(0, 13), (38, 39), (30, 32)];
+        assert_eq!(
+            first_row_ranges(&page.rows, matches),
+            vec![vec![(0, 9)], vec![], vec![(0, 2)], vec![]]
+        );
+    }
+
+    #[derive(Debug, Clone)]
+    struct EmptyAccessor {
+        path: PathBuf,
+    }
+
+    impl Default for EmptyAccessor {
+        fn default() -> Self {
+            Self {
+                path: PathBuf::from("<empty>"),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl FileAccessor for EmptyAccessor {
+        async fn read_from_byte(&self, _start_byte: u64, _max_lines: usize) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn read_bytes(&self, _start_byte: u64, _length: usize) -> Result<Vec<u8>> {
+            Ok(Vec::new())
+        }
+
+        async fn find_next_match(
+            &self,
+            _start_byte: u64,
+            _search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            _cancel_flag: Option<&AtomicBool>,
+            _progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            Ok(None)
+        }
+
+        async fn find_prev_match(
+            &self,
+            _start_byte: u64,
+            _search_fn: &(dyn for<'a> Fn(&'a str) -> Vec<(usize, usize)> + Send + Sync),
+            _cancel_flag: Option<&AtomicBool>,
+            _progress: Option<ScanProgress<'_>>,
+        ) -> Result<Option<u64>> {
+            Ok(None)
+        }
+
+        fn file_size(&self) -> u64 {
+            0
+        }
+
+        fn file_path(&self) -> &Path {
+            &self.path
+        }
+
+        fn ends_with_newline(&self) -> bool {
+            true
+        }
+
+        fn file_meta(&self) -> Option<FileMeta> {
+            None
+        }
+
+        fn sections(&self) -> &[u64] {
+            &[]
+        }
+
+        async fn last_page_start(&self, _max_lines: usize) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn next_page_start(&self, _current_byte: u64, _lines_to_skip: usize) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn prev_page_start(&self, _current_byte: u64, _lines_to_skip: usize) -> Result<u64> {
+            Ok(0)
+        }
+    }
+}
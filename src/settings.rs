@@ -0,0 +1,376 @@
+//! Effective settings and where they came from.
+//!
+//! Startup options are merged from built-in defaults and command-line flags (plus environment
+//! variables for arguments that read one). Each setting keeps the layer that decided its value,
+//! so the `:options` overlay and error messages can say why a setting has the value it has.
+//! Settings changed while running are tagged as runtime changes.
+
+use crate::file_handler::LineTerminator;
+use crate::render::ui::codepoints::DEFAULT_PLACEHOLDER;
+use crate::render::ui::JumpTarget;
+use crate::search::{PatternLibrary, SearchOptions};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use std::path::PathBuf;
+
+/// Layer that decided a setting's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingSource {
+    Default,
+    Env,
+    Flag,
+    /// Changed while running, e.g. through `-i` or `P`
+    Runtime,
+}
+
+impl SettingSource {
+    /// Where the command-line argument `id` got its value from
+    pub fn of_arg(matches: &ArgMatches, id: &str) -> Self {
+        match matches.value_source(id) {
+            Some(ValueSource::CommandLine) => SettingSource::Flag,
+            Some(ValueSource::EnvVariable) => SettingSource::Env,
+            _ => SettingSource::Default,
+        }
+    }
+
+    /// Tag shown next to the value
+    pub fn tag(self) -> &'static str {
+        match self {
+            SettingSource::Default => "default",
+            SettingSource::Env => "env",
+            SettingSource::Flag => "flag",
+            SettingSource::Runtime => "runtime",
+        }
+    }
+}
+
+/// One setting with its current value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectiveSetting {
+    pub name: &'static str,
+    pub value: String,
+    pub source: SettingSource,
+}
+
+/// Settings in the order they were recorded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    entries: Vec<EffectiveSetting>,
+}
+
+/// Startup values worked out from the flags rather than read from one directly
+#[derive(Debug, Clone, Copy)]
+pub struct StartupValues<'a> {
+    pub search_options: &'a SearchOptions,
+    pub position: &'static str,
+    pub invalid_key: &'static str,
+    pub jump_target: JumpTarget,
+    pub frequent_match_limit: Option<u64>,
+    pub record_start: &'a str,
+    pub patterns: &'a PatternLibrary,
+    pub cache_budget_mib: u64,
+    /// Whether spilling is on, asked for or because of memory pressure
+    pub low_memory: bool,
+    pub terminator: LineTerminator,
+}
+
+impl Settings {
+    /// Startup settings with the layer each came from, for `:options`
+    pub fn from_args(matches: &ArgMatches, values: StartupValues<'_>) -> Self {
+        let source = |id: &str| SettingSource::of_arg(matches, id);
+        let search_options = values.search_options;
+        let mut settings = Settings::default();
+        settings.record(
+            "ignore-case",
+            on_off(!search_options.case_sensitive),
+            source("ignore-case"),
+        );
+        settings.record(
+            "smart-case",
+            on_off(search_options.smart_case),
+            source("smart-case"),
+        );
+        settings.record(
+            "regex",
+            on_off(search_options.regex_mode),
+            [source("literal"), source("regex")]
+                .into_iter()
+                .find(|&source| source != SettingSource::Default)
+                .unwrap_or(SettingSource::Default),
+        );
+        settings.record("word", on_off(search_options.whole_word), source("word"));
+        settings.record(
+            "multiline",
+            on_off(search_options.multiline),
+            source("multiline"),
+        );
+        settings.record("position", values.position, source("position"));
+        settings.record(
+            "presentation",
+            on_off(matches.get_flag("presentation")),
+            source("presentation"),
+        );
+        settings.record(
+            "follow",
+            on_off(matches.get_flag("follow")),
+            source("follow"),
+        );
+        settings.record(
+            "precise-scroll",
+            on_off(matches.get_flag("precise-scroll")),
+            source("precise-scroll"),
+        );
+        settings.record(
+            "line-numbers",
+            on_off(matches.get_flag("line-numbers")),
+            source("line-numbers"),
+        );
+        settings.record("wrap", on_off(matches.get_flag("wrap")), source("wrap"));
+        settings.record(
+            "jump-target",
+            values.jump_target.name(),
+            source("jump-target"),
+        );
+        settings.record("title", on_off(matches.get_flag("title")), source("title"));
+        settings.record("line-terminator", values.terminator.name(), source("null"));
+        settings.record(
+            "match-bell",
+            on_off(!matches.get_flag("no-bell")),
+            source("no-bell"),
+        );
+        settings.record("invalid-key", values.invalid_key, source("invalid-key"));
+        settings.record(
+            "growth-hint",
+            on_off(!matches.get_flag("no-growth-hint")),
+            source("no-growth-hint"),
+        );
+        settings.record(
+            "incsearch",
+            on_off(!matches.get_flag("no-incsearch")),
+            source("no-incsearch"),
+        );
+        settings.record(
+            "placeholder",
+            if matches.get_flag("no-placeholder") {
+                "off".to_string()
+            } else {
+                matches
+                    .get_one::<char>("placeholder")
+                    .copied()
+                    .unwrap_or(DEFAULT_PLACEHOLDER)
+                    .to_string()
+            },
+            [source("placeholder"), source("no-placeholder")]
+                .into_iter()
+                .find(|&source| source != SettingSource::Default)
+                .unwrap_or(SettingSource::Default),
+        );
+        settings.record(
+            "show-active-pattern",
+            on_off(matches.get_flag("show-active-pattern")),
+            source("show-active-pattern"),
+        );
+        settings.record(
+            "frequent-match-limit",
+            values
+                .frequent_match_limit
+                .map_or_else(|| "off".to_string(), |limit| limit.to_string()),
+            source("frequent-match-limit"),
+        );
+        settings.record("record-start", values.record_start, source("record-start"));
+        let named: Vec<String> = values
+            .patterns
+            .user_entries()
+            .map(|entry| format!("@{}", entry.name))
+            .collect();
+        settings.record(
+            "patterns",
+            if named.is_empty() {
+                "built-in only".to_string()
+            } else {
+                named.join(", ")
+            },
+            source("pattern"),
+        );
+        let excluded: Vec<&str> = matches
+            .get_many::<String>("history-exclude")
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        settings.record(
+            "history-exclude",
+            if excluded.is_empty() {
+                "none".to_string()
+            } else {
+                excluded.join(", ")
+            },
+            source("history-exclude"),
+        );
+        settings.record(
+            "cache-budget",
+            format!("{} MiB", values.cache_budget_mib),
+            source("cache-budget"),
+        );
+        settings.record(
+            "low-memory",
+            match (matches.get_flag("low-memory"), values.low_memory) {
+                (false, true) => "on (memory pressure)",
+                (_, enabled) => on_off(enabled),
+            },
+            source("low-memory"),
+        );
+        settings.record("theme", "default", SettingSource::Default);
+        settings.record(
+            "keymap",
+            matches
+                .get_one::<PathBuf>("keymap")
+                .map_or_else(|| "built-in".to_string(), |path| path.display().to_string()),
+            source("keymap"),
+        );
+        settings.record(
+            "patterns-from",
+            matches
+                .get_one::<PathBuf>("patterns-from")
+                .map_or_else(|| "none".to_string(), |path| path.display().to_string()),
+            source("patterns-from"),
+        );
+        settings.record(
+            "history-file",
+            "none (kept in memory)",
+            SettingSource::Default,
+        );
+        settings
+    }
+
+    /// Record the startup value of `name`
+    pub fn record(&mut self, name: &'static str, value: impl Into<String>, source: SettingSource) {
+        let value = value.into();
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => {
+                entry.value = value;
+                entry.source = source;
+            }
+            None => self.entries.push(EffectiveSetting {
+                name,
+                value,
+                source,
+            }),
+        }
+    }
+
+    /// Apply the value `name` has now, tagging it as a runtime change if it differs
+    pub fn update(&mut self, name: &'static str, value: impl Into<String>) {
+        let value = value.into();
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) if entry.value == value => {}
+            Some(entry) => {
+                entry.value = value;
+                entry.source = SettingSource::Runtime;
+            }
+            None => self.record(name, value, SettingSource::Default),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EffectiveSetting> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// One aligned `name  value  [source]` line per setting
+    pub fn lines(&self) -> Vec<String> {
+        let name_width = self
+            .entries
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or(0);
+        self.entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{:<name_width$}  {}  [{}]",
+                    entry.name,
+                    entry.value,
+                    entry.source.tag()
+                )
+            })
+            .collect()
+    }
+}
+
+/// `on` or `off`
+pub fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, ArgAction, Command};
+
+    fn parse(args: &[&str]) -> ArgMatches {
+        Command::new("rlless")
+            .arg(
+                Arg::new("position")
+                    .long("position")
+                    .default_value("percent"),
+            )
+            .arg(
+                Arg::new("presentation")
+                    .long("presentation")
+                    .action(ArgAction::SetTrue),
+            )
+            .try_get_matches_from(args)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sources_follow_the_deciding_layer() {
+        let matches = parse(&["rlless", "--presentation"]);
+        assert_eq!(
+            SettingSource::of_arg(&matches, "position"),
+            SettingSource::Default
+        );
+        assert_eq!(
+            SettingSource::of_arg(&matches, "presentation"),
+            SettingSource::Flag
+        );
+
+        let matches = parse(&["rlless", "--position", "byte"]);
+        assert_eq!(
+            SettingSource::of_arg(&matches, "position"),
+            SettingSource::Flag
+        );
+        assert_eq!(
+            SettingSource::of_arg(&matches, "presentation"),
+            SettingSource::Default
+        );
+    }
+
+    #[test]
+    fn test_runtime_changes_are_tagged() {
+        let mut settings = Settings::default();
+        settings.record("position", "byte", SettingSource::Flag);
+        settings.record("regex", "on", SettingSource::Default);
+
+        // Re-applying the startup value keeps its origin
+        settings.update("position", "byte");
+        settings.update("regex", "off");
+        assert_eq!(
+            settings.get("position").unwrap().source,
+            SettingSource::Flag
+        );
+        assert_eq!(
+            settings.get("regex").unwrap().source,
+            SettingSource::Runtime
+        );
+        assert_eq!(
+            settings.lines(),
+            vec!["position  byte  [flag]", "regex     off  [runtime]"]
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! Small helpers shared by several subsystems.
+
+pub mod atomic_write;
+pub mod cancel;
+
+pub use atomic_write::{load_lenient, write_atomic, write_atomic_with};
+pub use cancel::{check_cancelled, CancelRegistry, CancellationToken};
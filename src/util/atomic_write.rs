@@ -0,0 +1,183 @@
+//! Crash-safe writes and lenient loads for files rlless keeps between runs.
+//!
+//! A write goes to a temporary file next to the target, is flushed to disk and then renamed over
+//! the target, so a crash leaves either the old contents or the new ones and never a truncated
+//! mix. Loading never fails on bad contents: a file that does not parse is renamed to
+//! `<name>.corrupt-<timestamp>` for inspection and the caller starts from scratch.
+
+use crate::error::{Result, RllessError};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Replace the file at `path` with `contents`, atomically
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    write_atomic_with(path, |writer| writer.write_all(contents))
+}
+
+/// Replace the file at `path` with what `write` produces, atomically
+///
+/// If `write` fails the target is left as it was and the temporary file is removed.
+pub fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<()> {
+    let context =
+        |e: io::Error| RllessError::file_error(format!("Failed to write {}", path.display()), e);
+    let dir = parent_dir(path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp = tempfile::Builder::new()
+        .prefix(&format!(".{}.", name))
+        .suffix(".tmp")
+        .tempfile_in(dir)
+        .map_err(context)?;
+
+    let mut writer = BufWriter::new(temp);
+    write(&mut writer).map_err(context)?;
+    let temp = writer.into_inner().map_err(|e| context(e.into_error()))?;
+    temp.as_file().sync_all().map_err(context)?;
+    temp.persist(path).map_err(|e| context(e.error))?;
+
+    // The rename itself only survives a crash once the directory entry is on disk
+    #[cfg(unix)]
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(context)?;
+    Ok(())
+}
+
+/// Read and parse the file at `path`, or `None` when it does not exist
+///
+/// A file that `parse` rejects is moved aside to `<name>.corrupt-<timestamp>` and also gives
+/// `None`, so one bad write can never keep rlless from starting. Errors reading the file are
+/// returned as is.
+pub fn load_lenient<T, E: Display>(
+    path: &Path,
+    parse: impl FnOnce(&[u8]) -> std::result::Result<T, E>,
+) -> Result<Option<T>> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(RllessError::file_error(
+                format!("Failed to read {}", path.display()),
+                e,
+            ))
+        }
+    };
+
+    match parse(&bytes) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => {
+            let quarantine = quarantine_path(path);
+            log::warn!(
+                "Unreadable state file {} ({}), moved to {}",
+                path.display(),
+                err,
+                quarantine.display()
+            );
+            std::fs::rename(path, &quarantine).map_err(|e| {
+                RllessError::file_error(format!("Failed to move aside {}", path.display()), e)
+            })?;
+            Ok(None)
+        }
+    }
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn quarantine_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".corrupt-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entries(dir: &TempDir) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn parse_lines(bytes: &[u8]) -> std::result::Result<Vec<String>, String> {
+        let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+        if !text.ends_with("end\n") {
+            return Err("missing end marker".to_string());
+        }
+        Ok(text.lines().map(str::to_string).collect())
+    }
+
+    #[test]
+    fn test_write_replaces_the_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("positions");
+        write_atomic(&path, b"one\nend\n").unwrap();
+        write_atomic(&path, b"two\nend\n").unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"two\nend\n");
+        assert_eq!(entries(&dir), ["positions"]);
+    }
+
+    #[test]
+    fn test_interrupted_write_keeps_the_old_contents() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("positions");
+        write_atomic(&path, b"old\nend\n").unwrap();
+
+        // Fail half way through, as a full disk or a crash would
+        let result = write_atomic_with(&path, |writer| {
+            writer.write_all(b"new\npartial")?;
+            Err(io::Error::other("disk full"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"old\nend\n");
+        assert_eq!(entries(&dir), ["positions"]);
+    }
+
+    #[test]
+    fn test_truncated_file_is_quarantined() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("positions");
+        assert_eq!(load_lenient(&path, parse_lines).unwrap(), None);
+
+        write_atomic(&path, b"a\nend\n").unwrap();
+        assert_eq!(
+            load_lenient(&path, parse_lines).unwrap(),
+            Some(vec!["a".to_string(), "end".to_string()])
+        );
+
+        // What a non-atomic write cut short by a crash leaves behind
+        std::fs::write(&path, b"a\ne").unwrap();
+        assert_eq!(load_lenient(&path, parse_lines).unwrap(), None);
+
+        let names = entries(&dir);
+        assert_eq!(names.len(), 1);
+        assert!(names[0].starts_with("positions.corrupt-"));
+        assert_eq!(std::fs::read(dir.path().join(&names[0])).unwrap(), b"a\ne");
+
+        // The next start finds nothing and writes afresh
+        assert_eq!(load_lenient(&path, parse_lines).unwrap(), None);
+        write_atomic(&path, b"b\nend\n").unwrap();
+        assert!(load_lenient(&path, parse_lines).unwrap().is_some());
+    }
+}
@@ -0,0 +1,126 @@
+//! Cancelling long-running operations.
+//!
+//! Every long-running worker command carries a `CancellationToken`. The coordinator keeps its
+//! clone next to the request id and flips it to stop the operation; scans check the flag it wraps
+//! at chunk boundaries and stop with `RllessError::Cancelled`. Commands run one at a time, so the
+//! flag travels with the work item rather than queueing behind it as a command would.
+//!
+//! Operations that outlive their command, such as an export, are also registered by request id
+//! in a `CancelRegistry`, so `SearchCommand::Cancel` can reach them without the token.
+
+use crate::error::{Result, RllessError};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared handle asking one operation to stop; clones flip the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operation to stop; it does so at its next check
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// `RllessError::Cancelled` once the token has been cancelled
+    pub fn check(&self) -> Result<()> {
+        check_cancelled(Some(self.as_ref()))
+    }
+}
+
+/// The flag itself, for scans that take an `Option<&AtomicBool>`
+impl AsRef<AtomicBool> for CancellationToken {
+    fn as_ref(&self) -> &AtomicBool {
+        &self.0
+    }
+}
+
+/// `RllessError::Cancelled` once `cancel_flag` is set; the check scans make between chunks
+pub fn check_cancelled(cancel_flag: Option<&AtomicBool>) -> Result<()> {
+    if cancel_flag.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+        return Err(RllessError::cancelled());
+    }
+    Ok(())
+}
+
+/// Tokens of the operations running now, by request id
+#[derive(Debug, Clone, Default)]
+pub struct CancelRegistry {
+    running: Arc<Mutex<HashMap<u64, CancellationToken>>>,
+}
+
+impl CancelRegistry {
+    /// Note that `request_id` runs under `token` until the returned guard is dropped
+    pub fn register(&self, request_id: u64, token: &CancellationToken) -> Registration {
+        self.running.lock().insert(request_id, token.clone());
+        Registration {
+            registry: self.clone(),
+            request_id,
+        }
+    }
+
+    /// Cancel the operation running for `request_id`; returns whether there was one
+    pub fn cancel(&self, request_id: u64) -> bool {
+        match self.running.lock().get(&request_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// An operation's entry in a `CancelRegistry`, removed when dropped
+#[derive(Debug)]
+pub struct Registration {
+    registry: CancelRegistry,
+    request_id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry.running.lock().remove(&self.request_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_cancellation() {
+        let token = CancellationToken::new();
+        let held = token.clone();
+        assert!(token.check().is_ok());
+        held.cancel();
+        assert!(token.is_cancelled());
+        assert!(matches!(token.check(), Err(RllessError::Cancelled)));
+        assert!(check_cancelled(Some(token.as_ref())).is_err());
+        assert!(check_cancelled(None).is_ok());
+    }
+
+    #[test]
+    fn registry_reaches_operations_until_they_finish() {
+        let registry = CancelRegistry::default();
+        let token = CancellationToken::new();
+        let registration = registry.register(7, &token);
+        assert!(!registry.cancel(8));
+        assert!(!token.is_cancelled());
+        assert!(registry.cancel(7));
+        assert!(token.is_cancelled());
+
+        drop(registration);
+        assert!(!registry.cancel(7));
+    }
+}
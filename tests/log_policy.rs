@@ -0,0 +1,167 @@
+//! Runs a scripted worker session under a capturing logger and checks that log records carry
+//! offsets and lengths but none of the file's content or the search patterns.
+//!
+//! The logger is process-wide, so this binary holds a single test.
+
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Duration};
+
+use rlless::file_handler::accessor::FileAccessor;
+use rlless::input::SearchDirection;
+use rlless::logging::{set_log_sensitive, PolicyLogger};
+use rlless::render::protocol::{
+    MatchTraversal, SearchCommand, SearchHighlightSpec, SearchResponse, ViewportRequest,
+};
+use rlless::search::worker::search_worker_loop;
+use rlless::search::{MemoryBudget, PatternModifiers, SearchOptions};
+use rlless::util::CancellationToken;
+
+static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct CaptureLogger;
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        RECORDS.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+const SECRET_LINE: &str = "user=alice password=hunter2 token=tok_5f3a9c";
+
+async fn run_session(commands: Vec<SearchCommand>) {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let contents = format!("boot ok\n{SECRET_LINE}\nshutdown ok\n");
+    std::fs::write(file.path(), contents).unwrap();
+    let accessor: Arc<dyn FileAccessor> = Arc::new(
+        rlless::file_handler::FileAccessorFactory::create(file.path())
+            .await
+            .unwrap(),
+    );
+    let engine = rlless::search::RipgrepEngine::new(Arc::clone(&accessor));
+    let (cmd_tx, cmd_rx) = mpsc::channel(4);
+    let (resp_tx, mut resp_rx) = mpsc::channel(4);
+    let worker = tokio::spawn(search_worker_loop(
+        cmd_rx,
+        resp_tx,
+        accessor,
+        engine,
+        MemoryBudget::default(),
+    ));
+
+    for command in commands {
+        let expects_response = command.request_id().is_some();
+        cmd_tx.send(command).await.unwrap();
+        if expects_response {
+            let response: SearchResponse = timeout(Duration::from_secs(2), resp_rx.recv())
+                .await
+                .expect("worker response timed out")
+                .expect("worker channel closed");
+            assert!(!matches!(response, SearchResponse::SearchCancelled { .. }));
+        }
+    }
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+fn search(request_id: u64, pattern: &str) -> SearchCommand {
+    SearchCommand::ExecuteSearch {
+        request_id,
+        pattern: Arc::from(pattern),
+        direction: SearchDirection::Forward,
+        options: SearchOptions::default(),
+        origin_byte: 0,
+        origin_row: 0,
+        skip_origin_row: false,
+        frequency_limit: None,
+        preview: false,
+        cancel_flag: CancellationToken::new(),
+    }
+}
+
+fn spec(pattern: &str) -> Option<Arc<SearchHighlightSpec>> {
+    Some(Arc::new(SearchHighlightSpec {
+        pattern: Arc::from(pattern),
+        options: SearchOptions::default(),
+        modifiers: PatternModifiers::default(),
+    }))
+}
+
+fn take_records() -> String {
+    std::mem::take(&mut *RECORDS.lock().unwrap()).join("\n")
+}
+
+#[tokio::test]
+async fn logs_leave_out_patterns_and_content_unless_allowed() {
+    log::set_boxed_logger(Box::new(PolicyLogger::new(CaptureLogger))).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    run_session(vec![
+        SearchCommand::LoadViewport {
+            request_id: 1,
+            top: ViewportRequest::Absolute(0),
+            page_lines: 3,
+            highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
+        },
+        search(2, "hunter2"),
+        SearchCommand::NavigateMatch {
+            request_id: 3,
+            traversal: MatchTraversal::Next,
+            current_top: 0,
+            origin_row: 0,
+            count: 1,
+            cancel_flag: CancellationToken::new(),
+        },
+        SearchCommand::LoadViewport {
+            request_id: 4,
+            top: ViewportRequest::Absolute(8),
+            page_lines: 3,
+            highlights: spec("hunter2"),
+            filter: spec("token=tok_"),
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
+        },
+        SearchCommand::PeekLine {
+            request_id: 5,
+            row_start: 8,
+            highlights: spec("hunter2"),
+            filter: None,
+        },
+        // Regex errors quote the pattern
+        search(6, "password=(hunter2"),
+    ])
+    .await;
+
+    // Other dependencies log no user data and keep their records
+    log::debug!(target: "tokio::runtime", "dependency record");
+    log::debug!(target: "grep_regex::literal", "literal alternation hunter2");
+
+    let records = take_records();
+    assert!(records.contains("dependency record"));
+    assert!(records.contains("worker: load-viewport request=Some(1)"));
+    assert!(records.contains("worker: search request=Some(2) pattern=<7 bytes>"));
+    assert!(records.contains("accessor: opened"));
+    for secret in ["alice", "hunter2", "tok_5f3a9c", "token=tok_", "password"] {
+        assert!(
+            !records.contains(secret),
+            "{secret:?} leaked into the log:\n{records}"
+        );
+    }
+
+    set_log_sensitive(true);
+    run_session(vec![search(1, "hunter2")]).await;
+    set_log_sensitive(false);
+    let records = take_records();
+    assert!(records.contains("pattern=\"hunter2\""));
+    // Dependency records are let through as well
+    assert!(records.contains("(?:hunter2)"));
+}
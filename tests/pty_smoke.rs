@@ -0,0 +1,149 @@
+//! Drives the compiled binary through a pseudo-terminal: real raw mode, alternate screen and
+//! mouse capture, which the unit tests' mock renderers never touch.
+#![cfg(unix)]
+
+use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ROWS: u16 = 24;
+const COLS: u16 = 80;
+const WAIT: Duration = Duration::from_secs(10);
+
+/// Everything the binary wrote, raw and as a parsed screen
+struct Output {
+    raw: Vec<u8>,
+    screen: vt100::Parser,
+}
+
+fn wait_for(output: &Mutex<Output>, what: &str, ready: impl Fn(&vt100::Screen) -> bool) {
+    let deadline = Instant::now() + WAIT;
+    loop {
+        {
+            let output = output.lock().unwrap();
+            if ready(output.screen.screen()) {
+                return;
+            }
+            if Instant::now() > deadline {
+                panic!(
+                    "timed out waiting for {what}; screen:\n{}",
+                    output.screen.screen().contents()
+                );
+            }
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn row(screen: &vt100::Screen, index: u16) -> String {
+    screen
+        .contents_between(index, 0, index, COLS)
+        .trim_end()
+        .to_string()
+}
+
+#[test]
+fn search_navigation_and_quit_restore_the_terminal() {
+    let fixture = tempfile::NamedTempFile::new().unwrap();
+    let content: String = (1..=100)
+        .map(|i| {
+            if i % 10 == 7 {
+                format!("line {i:03} error: disk quota\n")
+            } else {
+                format!("line {i:03} ok\n")
+            }
+        })
+        .collect();
+    std::fs::write(fixture.path(), content).unwrap();
+
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: ROWS,
+            cols: COLS,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .unwrap();
+    let mut command = CommandBuilder::new(env!("CARGO_BIN_EXE_rlless"));
+    command.arg(fixture.path());
+    command.env("TERM", "xterm-256color");
+    let mut child = pair.slave.spawn_command(command).unwrap();
+    drop(pair.slave);
+
+    let output = Arc::new(Mutex::new(Output {
+        raw: Vec::new(),
+        screen: vt100::Parser::new(ROWS, COLS, 0),
+    }));
+    let mut reader = pair.master.try_clone_reader().unwrap();
+    let pump = {
+        let output = Arc::clone(&output);
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            // Ends with an error or EOF once the child has exited
+            while let Ok(read @ 1..) = reader.read(&mut buf) {
+                let mut output = output.lock().unwrap();
+                output.raw.extend_from_slice(&buf[..read]);
+                output.screen.process(&buf[..read]);
+            }
+        })
+    };
+    let mut writer = pair.master.take_writer().unwrap();
+    let mut keys = |keys: &str| {
+        writer.write_all(keys.as_bytes()).unwrap();
+        writer.flush().unwrap();
+    };
+
+    wait_for(&output, "the first page", |screen| {
+        row(screen, 0) == "line 001 ok" && row(screen, ROWS - 1).contains(" | ")
+    });
+    {
+        let output = output.lock().unwrap();
+        assert!(output.screen.screen().alternate_screen());
+        assert_ne!(
+            output.screen.screen().mouse_protocol_mode(),
+            vt100::MouseProtocolMode::None
+        );
+    }
+
+    keys("/error\r");
+    wait_for(&output, "the first match", |screen| {
+        row(screen, 0) == "line 007 error: disk quota"
+    });
+    keys("n");
+    wait_for(&output, "the next match", |screen| {
+        row(screen, 0) == "line 017 error: disk quota"
+    });
+    keys("G");
+    wait_for(&output, "the last page", |screen| {
+        row(screen, ROWS - 2) == "line 100 ok"
+    });
+
+    keys("q");
+    let deadline = Instant::now() + WAIT;
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("rlless did not exit after q");
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    assert!(status.success(), "exit status: {status:?}");
+    drop(pair.master);
+    pump.join().unwrap();
+
+    // Back on the main screen with mouse reporting off and the cursor shown
+    let output = output.lock().unwrap();
+    let screen = output.screen.screen();
+    assert!(!screen.alternate_screen());
+    assert_eq!(screen.mouse_protocol_mode(), vt100::MouseProtocolMode::None);
+    assert!(!screen.hide_cursor());
+    let raw = String::from_utf8_lossy(&output.raw);
+    let entered = raw.find("\x1b[?1049h").expect("alternate screen entered");
+    let left = raw.rfind("\x1b[?1049l").expect("alternate screen left");
+    assert!(entered < left);
+}
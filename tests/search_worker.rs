@@ -1,16 +1,20 @@
-use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::time::{timeout, Duration};
 
 use rlless::file_handler::accessor::FileAccessor;
+use rlless::file_handler::{LineTerminator, SEGMENT_BYTES};
 use rlless::input::SearchDirection;
 use rlless::render::protocol::{
-    MatchTraversal, SearchCommand, SearchContext, SearchHighlightSpec, SearchResponse,
-    ViewportRequest,
+    ExportTarget, FrequentMatches, HighlightKind, LinePosition, MatchTraversal, ProgressOperation,
+    SearchCommand, SearchContext, SearchHighlightSpec, SearchResponse, ViewportRequest,
 };
+use rlless::render::service::DEFAULT_RECORD_START;
+use rlless::search::timestamps::TimeTarget;
 use rlless::search::worker::search_worker_loop;
-use rlless::search::SearchOptions;
+use rlless::search::MemoryBudget;
+use rlless::search::{PatternModifiers, SearchEngine, SearchOptions, MAX_PATTERN_BYTES};
+use rlless::util::CancellationToken;
 
 const TIMEOUT_MS: u64 = 200;
 
@@ -27,6 +31,17 @@ async fn spawn_worker(
     mpsc::Sender<SearchCommand>,
     mpsc::Receiver<SearchResponse>,
     tokio::task::JoinHandle<()>,
+) {
+    spawn_worker_with_terminator(contents, LineTerminator::Newline).await
+}
+
+async fn spawn_worker_with_terminator(
+    contents: &str,
+    terminator: LineTerminator,
+) -> (
+    mpsc::Sender<SearchCommand>,
+    mpsc::Receiver<SearchResponse>,
+    tokio::task::JoinHandle<()>,
 ) {
     let (cmd_tx, cmd_rx) = mpsc::channel(4);
     let (resp_tx, resp_rx) = mpsc::channel(4);
@@ -36,11 +51,18 @@ async fn spawn_worker(
 
     let raw_accessor = rlless::file_handler::FileAccessorFactory::create(file.path())
         .await
-        .expect("create accessor");
+        .expect("create accessor")
+        .with_line_terminator(terminator);
     let accessor: Arc<dyn FileAccessor> = Arc::new(raw_accessor);
     let engine = rlless::search::RipgrepEngine::new(Arc::clone(&accessor));
 
-    let worker = tokio::spawn(search_worker_loop(cmd_rx, resp_tx, accessor, engine));
+    let worker = tokio::spawn(search_worker_loop(
+        cmd_rx,
+        resp_tx,
+        accessor,
+        engine,
+        MemoryBudget::default(),
+    ));
 
     (cmd_tx, resp_rx, worker)
 }
@@ -55,6 +77,9 @@ async fn load_viewport_returns_expected_page() {
             top: ViewportRequest::Absolute(0),
             page_lines: 3,
             highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -70,6 +95,150 @@ async fn load_viewport_returns_expected_page() {
     worker.await.unwrap();
 }
 
+#[tokio::test]
+async fn zero_page_lines_loads_a_single_line() {
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker("first\nsecond\nthird\n").await;
+    let filter = Arc::new(SearchHighlightSpec {
+        pattern: Arc::from("ir"),
+        options: SearchOptions::default(),
+        modifiers: PatternModifiers::default(),
+    });
+
+    for (request_id, top, filter, expected) in [
+        (1, ViewportRequest::Absolute(0), None, "first"),
+        (2, ViewportRequest::EndOfFile, None, "third"),
+        (3, ViewportRequest::EndOfFile, Some(filter), "third"),
+    ] {
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top,
+                page_lines: 0,
+                highlights: None,
+                filter,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded { lines, at_eof, .. } => {
+                assert_eq!(lines, vec![expected]);
+                assert_eq!(at_eof, request_id != 1);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn line_numbers_are_reported_for_unfiltered_pages_once_enabled() {
+    let mut contents = String::from("first\nsecond\nthird\nfourth\n");
+    contents.push_str(&"x".repeat(3 * SEGMENT_BYTES));
+    contents.push_str("\nlast\n");
+    let long_line = 26;
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+    let filter = Arc::new(SearchHighlightSpec {
+        pattern: Arc::from("i"),
+        options: SearchOptions::default(),
+        modifiers: PatternModifiers::default(),
+    });
+
+    for (request_id, show, top, filter, expected) in [
+        (1, false, 13, None, None),
+        (2, true, 13, None, Some(3)),
+        (3, true, 0, Some(filter), None),
+        // The second row is part of a line split over several rows
+        (4, true, long_line, None, None),
+    ] {
+        cmd_tx
+            .send(SearchCommand::ShowLineNumbers(show))
+            .await
+            .unwrap();
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top: ViewportRequest::Absolute(top),
+                page_lines: 2,
+                highlights: None,
+                filter,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded { top_line, .. } => {
+                assert_eq!(top_line, expected, "request {request_id}");
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn viewports_report_the_line_position_when_asked() {
+    let (cmd_tx, mut resp_rx, worker) =
+        spawn_worker("first\nsecond\nthird\nfourth\nno newline").await;
+
+    for (request_id, show, top, expected) in [
+        (1, false, 13, None),
+        (
+            2,
+            true,
+            13,
+            Some(LinePosition {
+                top: 3,
+                total: Some(5),
+            }),
+        ),
+        (
+            3,
+            true,
+            0,
+            Some(LinePosition {
+                top: 1,
+                total: Some(5),
+            }),
+        ),
+    ] {
+        cmd_tx
+            .send(SearchCommand::ShowLinePosition(show))
+            .await
+            .unwrap();
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top: ViewportRequest::Absolute(top),
+                page_lines: 2,
+                highlights: None,
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded { line_position, .. } => {
+                assert_eq!(line_position, expected, "request {request_id}");
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
 #[tokio::test]
 async fn load_viewport_marks_eof_when_past_file_end() {
     let (cmd_tx, mut resp_rx, worker) = spawn_worker("only\nthis\n").await;
@@ -80,20 +249,401 @@ async fn load_viewport_marks_eof_when_past_file_end() {
             top: ViewportRequest::Absolute(0),
             page_lines: 10,
             highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
 
     match next_response(&mut resp_rx).await {
-        SearchResponse::ViewportLoaded { lines, at_eof, .. } => {
+        SearchResponse::ViewportLoaded {
+            lines,
+            at_eof,
+            noeol,
+            ..
+        } => {
             assert_eq!(lines, vec!["only", "this"]);
             assert!(
                 at_eof,
                 "expected EOF flag when requesting beyond file length"
             );
+            assert!(!noeol, "terminated final line must not be marked");
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn check_file_growth_reports_appended_data() {
+    let (cmd_tx, cmd_rx) = mpsc::channel(4);
+    let (resp_tx, mut resp_rx) = mpsc::channel(4);
+    let file = tempfile::NamedTempFile::new().expect("create temp file");
+    std::fs::write(file.path(), "first\nsecond\n").expect("write contents");
+    let accessor: Arc<dyn FileAccessor> = Arc::new(
+        rlless::file_handler::FileAccessorFactory::create(file.path())
+            .await
+            .expect("create accessor"),
+    );
+    let engine = rlless::search::RipgrepEngine::new(Arc::clone(&accessor));
+    let worker = tokio::spawn(search_worker_loop(
+        cmd_rx,
+        resp_tx,
+        accessor,
+        engine,
+        MemoryBudget::default(),
+    ));
+    let load_end = |request_id| SearchCommand::LoadViewport {
+        request_id,
+        top: ViewportRequest::EndOfFile,
+        page_lines: 1,
+        highlights: None,
+        filter: None,
+        current_match: None,
+        cancel_flag: CancellationToken::new(),
+    };
+
+    // Nothing appended: no answer, so the viewport load is the next response
+    cmd_tx.send(SearchCommand::CheckFileGrowth).await.unwrap();
+    cmd_tx.send(load_end(1)).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::ViewportLoaded { lines, .. } => assert_eq!(lines, vec!["second"]),
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(file.path())
+        .and_then(|mut appended| std::io::Write::write_all(&mut appended, b"third\n"))
+        .expect("append");
+    cmd_tx.send(SearchCommand::CheckFileGrowth).await.unwrap();
+    assert!(matches!(
+        next_response(&mut resp_rx).await,
+        SearchResponse::FileGrew {
+            file_size: 19,
+            new_match: None
+        }
+    ));
+    cmd_tx.send(load_end(2)).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::ViewportLoaded {
+            lines, file_size, ..
+        } => {
+            assert_eq!(lines, vec!["third"]);
+            assert_eq!(file_size, 19);
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn check_file_growth_looks_for_the_search_in_appended_data_only() {
+    let (cmd_tx, cmd_rx) = mpsc::channel(4);
+    let (resp_tx, mut resp_rx) = mpsc::channel(4);
+    let file = tempfile::NamedTempFile::new().expect("create temp file");
+    // A match before the appended data is not news
+    std::fs::write(file.path(), "old error\n").expect("write contents");
+    let accessor: Arc<dyn FileAccessor> = Arc::new(
+        rlless::file_handler::FileAccessorFactory::create(file.path())
+            .await
+            .expect("create accessor"),
+    );
+    let engine = rlless::search::RipgrepEngine::new(Arc::clone(&accessor));
+    let worker = tokio::spawn(search_worker_loop(
+        cmd_rx,
+        resp_tx,
+        accessor,
+        engine,
+        MemoryBudget::default(),
+    ));
+    let append = |data: &[u8]| {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(file.path())
+            .and_then(|mut appended| std::io::Write::write_all(&mut appended, data))
+            .expect("append");
+    };
+
+    // Without a search nothing is looked for
+    append(b"error one\n");
+    cmd_tx.send(SearchCommand::CheckFileGrowth).await.unwrap();
+    assert!(matches!(
+        next_response(&mut resp_rx).await,
+        SearchResponse::FileGrew {
+            file_size: 20,
+            new_match: None
+        }
+    ));
+
+    cmd_tx
+        .send(SearchCommand::UpdateSearchContext(SearchContext {
+            pattern: Arc::from("error"),
+            direction: SearchDirection::Forward,
+            options: SearchOptions::default(),
+            last_match_byte: None,
+        }))
+        .await
+        .unwrap();
+    append(b"all fine\n");
+    cmd_tx.send(SearchCommand::CheckFileGrowth).await.unwrap();
+    assert!(matches!(
+        next_response(&mut resp_rx).await,
+        SearchResponse::FileGrew {
+            file_size: 29,
+            new_match: None
+        }
+    ));
+
+    append(b"still fine\nerror two\n");
+    cmd_tx.send(SearchCommand::CheckFileGrowth).await.unwrap();
+    assert!(matches!(
+        next_response(&mut resp_rx).await,
+        SearchResponse::FileGrew {
+            file_size: 50,
+            new_match: Some(40)
+        }
+    ));
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn switch_to_latest_moves_to_a_newer_matching_file() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let write = |name: &str, contents: &str, age_secs: u64| {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).expect("write contents");
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| {
+                file.set_modified(std::time::SystemTime::now() - Duration::from_secs(age_secs))
+            })
+            .expect("set mtime");
+        path
+    };
+    let first = write("app-01.log", "day one\n", 200);
+    let pattern = dir.path().join("app-*.log");
+
+    let (cmd_tx, cmd_rx) = mpsc::channel(4);
+    let (resp_tx, mut resp_rx) = mpsc::channel(4);
+    let accessor: Arc<dyn FileAccessor> = Arc::new(
+        rlless::file_handler::FileAccessorFactory::create(&first)
+            .await
+            .expect("create accessor"),
+    );
+    let engine = rlless::search::RipgrepEngine::new(Arc::clone(&accessor));
+    let worker = tokio::spawn(search_worker_loop(
+        cmd_rx,
+        resp_tx,
+        accessor,
+        engine,
+        MemoryBudget::default(),
+    ));
+    let load_end = |request_id| SearchCommand::LoadViewport {
+        request_id,
+        top: ViewportRequest::EndOfFile,
+        page_lines: 1,
+        highlights: None,
+        filter: None,
+        current_match: None,
+        cancel_flag: CancellationToken::new(),
+    };
+    let switch = || SearchCommand::SwitchToLatest {
+        pattern: pattern.clone(),
+    };
+
+    // An older match, or none newer, leaves the open file alone without answering
+    write("app-00.log", "day zero\n", 400);
+    cmd_tx.send(switch()).await.unwrap();
+    cmd_tx.send(load_end(1)).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::ViewportLoaded { lines, .. } => assert_eq!(lines, vec!["day one"]),
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    let second = write("app-02.log", "day two\nmore\n", 0);
+    cmd_tx.send(switch()).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::FileSwitched {
+            path, file_size, ..
+        } => {
+            assert_eq!(path, second);
+            assert_eq!(file_size, 13);
         }
         other => panic!("unexpected response: {other:?}"),
     }
+    cmd_tx.send(load_end(2)).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::ViewportLoaded { lines, .. } => assert_eq!(lines, vec!["more"]),
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn unterminated_final_line_is_content_and_marked_noeol() {
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker("alpha\nbeta\nomega").await;
+
+    let cases = [
+        (
+            ViewportRequest::Absolute(0),
+            None,
+            vec!["alpha", "beta"],
+            false,
+            false,
+        ),
+        (
+            ViewportRequest::EndOfFile,
+            None,
+            vec!["beta", "omega"],
+            true,
+            true,
+        ),
+        // Filtered views only mark the final line when it passes the filter
+        (
+            ViewportRequest::EndOfFile,
+            Some("alpha"),
+            vec!["alpha"],
+            true,
+            false,
+        ),
+        (
+            ViewportRequest::EndOfFile,
+            Some("ga"),
+            vec!["omega"],
+            true,
+            true,
+        ),
+    ];
+    for (request_id, (top, filter, expected_lines, expected_eof, expected_noeol)) in
+        (1..).zip(cases)
+    {
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top,
+                page_lines: 2,
+                highlights: None,
+                filter: filter.map(|pattern| {
+                    Arc::new(SearchHighlightSpec {
+                        pattern: Arc::from(pattern),
+                        options: SearchOptions::default(),
+                        modifiers: PatternModifiers::default(),
+                    })
+                }),
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded {
+                lines,
+                at_eof,
+                noeol,
+                ..
+            } => {
+                assert_eq!(lines, expected_lines);
+                assert_eq!(at_eof, expected_eof);
+                assert_eq!(noeol, expected_noeol);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn nul_separated_records_page_and_search_as_lines() {
+    // The second record spans two text lines
+    let contents = "alpha\0stack trace\n  at main\0gamma\0delta\0";
+    let (cmd_tx, mut resp_rx, worker) =
+        spawn_worker_with_terminator(contents, LineTerminator::Nul).await;
+
+    let pages = [
+        (
+            ViewportRequest::Absolute(0),
+            vec!["alpha", "stack trace\n  at main"],
+            0,
+        ),
+        (
+            ViewportRequest::RelativeLines {
+                anchor: 0,
+                lines: 1,
+            },
+            vec!["stack trace\n  at main", "gamma"],
+            6,
+        ),
+        (ViewportRequest::EndOfFile, vec!["gamma", "delta"], 28),
+    ];
+    for (request_id, (top, expected_lines, expected_top)) in (1..).zip(pages) {
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top,
+                page_lines: 2,
+                highlights: None,
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded {
+                top_byte,
+                lines,
+                noeol,
+                ..
+            } => {
+                assert_eq!(lines, expected_lines);
+                assert_eq!(top_byte, expected_top);
+                assert!(!noeol);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    // A match on the record's second text line lands on the start of the record
+    for (request_id, direction, origin_byte, expected) in [
+        (10, SearchDirection::Forward, 0, 6),
+        (11, SearchDirection::Backward, 34, 6),
+    ] {
+        cmd_tx
+            .send(SearchCommand::ExecuteSearch {
+                request_id,
+                pattern: Arc::from("at main"),
+                direction,
+                options: SearchOptions::default(),
+                origin_byte,
+                origin_row: 0,
+                skip_origin_row: false,
+                frequency_limit: None,
+                preview: false,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::SearchCompleted { match_byte, .. } => {
+                assert_eq!(match_byte, Some(expected));
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
 
     cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
     worker.await.unwrap();
@@ -110,6 +660,9 @@ async fn relative_scroll_stops_at_last_page() {
             top: ViewportRequest::Absolute(0),
             page_lines: 2,
             highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -128,6 +681,9 @@ async fn relative_scroll_stops_at_last_page() {
             },
             page_lines: 2,
             highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -151,6 +707,9 @@ async fn relative_scroll_stops_at_last_page() {
             },
             page_lines: 2,
             highlights: None,
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -166,6 +725,214 @@ async fn relative_scroll_stops_at_last_page() {
     worker.await.unwrap();
 }
 
+#[tokio::test]
+async fn half_page_scroll_stops_at_last_page() {
+    let contents: String = (1..=10).map(|n| format!("line{n}\n")).collect();
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+
+    // A four-line window moves two lines per `d`, and never past the last full page
+    let mut top = 0;
+    for (request_id, expected_first) in [(1, "line3"), (2, "line5"), (3, "line7"), (4, "line7")] {
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top: ViewportRequest::RelativeLines {
+                    anchor: top,
+                    lines: 2,
+                },
+                page_lines: 4,
+                highlights: None,
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded {
+                top_byte, lines, ..
+            } => {
+                assert_eq!(lines.first().map(String::as_str), Some(expected_first));
+                top = top_byte;
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn execute_search_can_skip_the_origin_row() {
+    let contents = "beta\nalpha\nbeta again\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+
+    for (request_id, skip_origin_row, expected) in [(1, false, 0), (2, true, 11)] {
+        cmd_tx
+            .send(SearchCommand::ExecuteSearch {
+                request_id,
+                pattern: Arc::from("beta"),
+                direction: SearchDirection::Forward,
+                options: SearchOptions::default(),
+                origin_byte: 0,
+                origin_row: 0,
+                skip_origin_row,
+                frequency_limit: None,
+                preview: false,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::SearchCompleted { match_byte, .. } => {
+                assert_eq!(match_byte, Some(expected));
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn repeating_a_search_from_its_match_moves_on() {
+    let contents = "beta one\nalpha\nbeta two\ngamma\nbeta three\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+    let line = |text: &str| contents.find(text).unwrap() as u64;
+
+    // Each search starts where the previous one left the top of the screen, as `/beta` typed
+    // again would; the top row itself is never found again
+    let steps = [
+        (SearchDirection::Forward, 0, Some(line("beta two"))),
+        (
+            SearchDirection::Forward,
+            line("beta two"),
+            Some(line("beta three")),
+        ),
+        (SearchDirection::Forward, line("beta three"), None),
+        (
+            SearchDirection::Backward,
+            line("beta three"),
+            Some(line("beta two")),
+        ),
+        (SearchDirection::Backward, line("beta two"), Some(0)),
+        (SearchDirection::Backward, 0, None),
+    ];
+    for (request_id, (direction, origin_byte, expected)) in (1..).zip(steps) {
+        cmd_tx
+            .send(SearchCommand::ExecuteSearch {
+                request_id,
+                pattern: Arc::from("beta"),
+                direction,
+                options: SearchOptions::default(),
+                origin_byte,
+                origin_row: 0,
+                skip_origin_row: true,
+                frequency_limit: None,
+                preview: false,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::SearchCompleted { match_byte, .. } => {
+                assert_eq!(match_byte, expected, "{direction:?} from {origin_byte}");
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn searches_count_from_the_jump_target_row() {
+    let contents = "l0\nhit1\nl2\nl3\nhit4\nhit5\nl6\nhit7\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+    let line = |text: &str| contents.find(text).unwrap() as u64;
+
+    // With `-j 3` the line searches count from is three rows below the top
+    cmd_tx
+        .send(SearchCommand::ExecuteSearch {
+            request_id: 1,
+            pattern: Arc::from("hit"),
+            direction: SearchDirection::Forward,
+            options: SearchOptions::default(),
+            origin_byte: 0,
+            origin_row: 3,
+            skip_origin_row: true,
+            frequency_limit: None,
+            preview: false,
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted { match_byte, .. } => {
+            assert_eq!(match_byte, Some(line("hit4")));
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    // `n` twice, each time with the match three rows down; then from a page whose fourth row
+    // would be past the end, which counts from the last row
+    let steps = [
+        (MatchTraversal::Next, line("hit1"), Some(line("hit5"))),
+        (MatchTraversal::Next, line("l2"), Some(line("hit7"))),
+        (MatchTraversal::Next, line("l6"), None),
+    ];
+    for (request_id, (traversal, current_top, expected)) in (2..).zip(steps) {
+        cmd_tx
+            .send(SearchCommand::NavigateMatch {
+                request_id,
+                traversal,
+                current_top,
+                origin_row: 3,
+                count: 1,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::SearchCompleted { match_byte, .. } => {
+                assert_eq!(match_byte, expected, "from {current_top}");
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    // ESC-n and ESC-N count from the same row
+    let steps = [
+        (MatchTraversal::NextNonMatch, line("l6")),
+        (MatchTraversal::PreviousNonMatch, line("l3")),
+    ];
+    for (request_id, (traversal, expected)) in (5..).zip(steps) {
+        cmd_tx
+            .send(SearchCommand::NavigateMatch {
+                request_id,
+                traversal,
+                current_top: line("hit1"),
+                origin_row: 3,
+                count: 1,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::PositionFound { byte, .. } => {
+                assert_eq!(byte, Some(expected), "{traversal:?}");
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
 #[tokio::test]
 async fn execute_search_followed_by_viewport_load() {
     let contents = "alpha\nbeta\ngamma\nbeta again\n";
@@ -178,7 +945,11 @@ async fn execute_search_followed_by_viewport_load() {
             direction: SearchDirection::Forward,
             options: SearchOptions::default(),
             origin_byte: 0,
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            origin_row: 0,
+            skip_origin_row: false,
+            frequency_limit: None,
+            preview: false,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -200,7 +971,11 @@ async fn execute_search_followed_by_viewport_load() {
             highlights: Some(Arc::new(SearchHighlightSpec {
                 pattern: Arc::from("beta"),
                 options: SearchOptions::default(),
+                modifiers: PatternModifiers::default(),
             })),
+            filter: None,
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -216,6 +991,54 @@ async fn execute_search_followed_by_viewport_load() {
     worker.await.unwrap();
 }
 
+#[tokio::test]
+async fn pages_report_the_row_of_the_current_match() {
+    // Lines start at 0, 8, 15, 23 and 30
+    let contents = "error a\ninfo b\nerror c\ninfo d\nerror e\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+    let filter = Arc::new(SearchHighlightSpec {
+        pattern: Arc::from("error"),
+        options: SearchOptions::default(),
+        modifiers: PatternModifiers::default(),
+    });
+
+    for (top, filter, current_match, expected) in [
+        (ViewportRequest::Absolute(0), None, Some(15), Some(2)),
+        (ViewportRequest::Absolute(8), None, Some(15), Some(1)),
+        (ViewportRequest::Absolute(0), None, Some(30), None),
+        // Rows of a filtered page are the matching lines only
+        (
+            ViewportRequest::Absolute(0),
+            Some(&filter),
+            Some(30),
+            Some(2),
+        ),
+        (ViewportRequest::Absolute(0), None, None, None),
+    ] {
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id: 1,
+                top,
+                page_lines: 3,
+                highlights: None,
+                filter: filter.cloned(),
+                current_match,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded {
+                current_match_row, ..
+            } => assert_eq!(current_match_row, expected, "{top:?} {current_match:?}"),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
 #[tokio::test]
 async fn navigate_match_advances_active_context() {
     let contents = "alpha\nbeta\nalpha again\nbeta again\n";
@@ -228,7 +1051,11 @@ async fn navigate_match_advances_active_context() {
             direction: SearchDirection::Forward,
             options: SearchOptions::default(),
             origin_byte: 0,
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            origin_row: 0,
+            skip_origin_row: false,
+            frequency_limit: None,
+            preview: false,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -246,7 +1073,9 @@ async fn navigate_match_advances_active_context() {
             request_id: 2,
             traversal: MatchTraversal::Next,
             current_top: first_match,
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            origin_row: 0,
+            count: 1,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -265,6 +1094,78 @@ async fn navigate_match_advances_active_context() {
     worker.await.unwrap();
 }
 
+#[tokio::test]
+async fn preview_search_leaves_the_active_context_alone() {
+    let contents = "alpha\nbeta\nalpha again\nbeta again\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+
+    let search = |request_id, pattern: &str, preview| SearchCommand::ExecuteSearch {
+        request_id,
+        pattern: Arc::from(pattern),
+        direction: SearchDirection::Forward,
+        options: SearchOptions::default(),
+        origin_byte: 0,
+        origin_row: 0,
+        skip_origin_row: false,
+        frequency_limit: None,
+        preview,
+        cancel_flag: CancellationToken::new(),
+    };
+
+    cmd_tx.send(search(1, "alpha", false)).await.unwrap();
+    let first_match = match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted {
+            match_byte: Some(byte),
+            ..
+        } => byte,
+        other => panic!("unexpected response: {other:?}"),
+    };
+
+    cmd_tx.send(search(2, "beta", true)).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted {
+            request_id: 2,
+            match_byte: Some(6),
+            ..
+        } => {}
+        other => panic!("unexpected response: {other:?}"),
+    }
+    // A half-typed regex is answered like a failed search rather than with an error
+    cmd_tx.send(search(3, "al(", true)).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted {
+            request_id: 3,
+            match_byte: None,
+            message: Some(_),
+            ..
+        } => {}
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    // `n` still follows the committed search
+    cmd_tx
+        .send(SearchCommand::NavigateMatch {
+            request_id: 4,
+            traversal: MatchTraversal::Next,
+            current_top: first_match,
+            origin_row: 0,
+            count: 1,
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted {
+            match_byte: Some(byte),
+            ..
+        } => assert_eq!(byte, 11),
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
 #[tokio::test]
 async fn update_context_enables_navigation_without_execute() {
     let contents = "one\ntwo\nthree\n";
@@ -285,7 +1186,9 @@ async fn update_context_enables_navigation_without_execute() {
             request_id: 1,
             traversal: MatchTraversal::Next,
             current_top: 0,
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            origin_row: 0,
+            count: 1,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -306,8 +1209,173 @@ async fn update_context_enables_navigation_without_execute() {
 }
 
 #[tokio::test]
-async fn execute_search_with_invalid_regex_returns_error() {
-    let contents = "abc\n";
+async fn hidden_highlights_leave_the_search_active() {
+    let contents = "one\ntwo\nthree\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+    cmd_tx
+        .send(SearchCommand::UpdateSearchContext(SearchContext {
+            pattern: Arc::from("t"),
+            direction: SearchDirection::Forward,
+            options: SearchOptions::default(),
+            last_match_byte: None,
+        }))
+        .await
+        .unwrap();
+
+    for (request_id, show, highlighted) in [(1, false, 0), (2, true, 2)] {
+        cmd_tx
+            .send(SearchCommand::ShowHighlights(show))
+            .await
+            .unwrap();
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top: ViewportRequest::Absolute(0),
+                page_lines: 3,
+                highlights: None,
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded { highlights, .. } => {
+                let lines = highlights.iter().filter(|spans| !spans.is_empty()).count();
+                assert_eq!(lines, highlighted, "request {request_id}");
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+
+        // `n` still finds the next match
+        cmd_tx
+            .send(SearchCommand::NavigateMatch {
+                request_id: request_id + 10,
+                traversal: MatchTraversal::Next,
+                current_top: 0,
+                origin_row: 0,
+                count: 1,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            next_response(&mut resp_rx).await,
+            SearchResponse::SearchCompleted {
+                match_byte: Some(4),
+                ..
+            }
+        ));
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn counted_navigation_skips_matches_and_stops_at_the_last() {
+    let contents = "top\nhit a\nhit b\nhit c\nhit d\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+
+    cmd_tx
+        .send(SearchCommand::UpdateSearchContext(SearchContext {
+            pattern: Arc::from("hit"),
+            direction: SearchDirection::Forward,
+            options: SearchOptions::default(),
+            last_match_byte: None,
+        }))
+        .await
+        .unwrap();
+
+    for (request_id, count, expected) in [(1, 3, "hit c"), (2, 10, "hit d")] {
+        cmd_tx
+            .send(SearchCommand::NavigateMatch {
+                request_id,
+                traversal: MatchTraversal::Next,
+                current_top: 0,
+                origin_row: 0,
+                count,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::SearchCompleted {
+                match_byte: Some(byte),
+                message: None,
+                ..
+            } => assert_eq!(byte, contents.find(expected).unwrap() as u64),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn non_match_navigation_jumps_past_matching_lines() {
+    let contents = "top\nhit a\nhit b\nplain\nhit c\nhit d\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+    let line = |text: &str| contents.find(text).unwrap() as u64;
+
+    cmd_tx
+        .send(SearchCommand::UpdateSearchContext(SearchContext {
+            pattern: Arc::from("hit"),
+            direction: SearchDirection::Forward,
+            options: SearchOptions::default(),
+            last_match_byte: None,
+        }))
+        .await
+        .unwrap();
+
+    let steps = [
+        (
+            MatchTraversal::NextNonMatch,
+            line("hit a"),
+            Some(line("plain")),
+        ),
+        (MatchTraversal::NextNonMatch, line("plain"), None),
+        (
+            MatchTraversal::PreviousNonMatch,
+            line("hit d"),
+            Some(line("plain")),
+        ),
+        (MatchTraversal::PreviousNonMatch, line("hit b"), Some(0)),
+        (MatchTraversal::PreviousNonMatch, 0, None),
+    ];
+    for (request_id, (traversal, current_top, expected)) in (1..).zip(steps) {
+        cmd_tx
+            .send(SearchCommand::NavigateMatch {
+                request_id,
+                traversal,
+                current_top,
+                origin_row: 0,
+                count: 1,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::PositionFound { byte, message, .. } => {
+                assert_eq!(byte, expected, "{traversal:?} from {current_top}");
+                assert_eq!(
+                    message.is_some(),
+                    expected.is_none(),
+                    "{traversal:?} from {current_top}"
+                );
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn execute_search_with_invalid_regex_returns_error() {
+    let contents = "abc\n";
     let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
 
     let options = SearchOptions {
@@ -322,7 +1390,11 @@ async fn execute_search_with_invalid_regex_returns_error() {
             direction: SearchDirection::Forward,
             options,
             origin_byte: 0,
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            origin_row: 0,
+            skip_origin_row: false,
+            frequency_limit: None,
+            preview: false,
+            cancel_flag: CancellationToken::new(),
         })
         .await
         .unwrap();
@@ -337,3 +1409,1060 @@ async fn execute_search_with_invalid_regex_returns_error() {
     cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
     worker.await.unwrap();
 }
+
+#[tokio::test]
+async fn execute_search_matches_whitespace_and_tab_escapes() {
+    let contents = "single space\ndouble  space\ntab\there\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+    // The regex engine reads `\t` by itself; literal mode has to expand it
+    let literal = SearchOptions {
+        regex_mode: false,
+        ..SearchOptions::default()
+    };
+
+    for (request_id, pattern, expected) in [(1, "  ", "single space\n".len()), (2, r"\t", 27)] {
+        cmd_tx
+            .send(SearchCommand::ExecuteSearch {
+                request_id,
+                pattern: Arc::from(pattern),
+                direction: SearchDirection::Forward,
+                options: literal.clone(),
+                origin_byte: 0,
+                origin_row: 0,
+                skip_origin_row: false,
+                frequency_limit: None,
+                preview: false,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+
+        match next_response(&mut resp_rx).await {
+            SearchResponse::SearchCompleted {
+                match_byte: Some(byte),
+                ..
+            } => assert_eq!(byte, expected as u64),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn execute_search_with_oversized_pattern_returns_error() {
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker("abc\n").await;
+
+    cmd_tx
+        .send(SearchCommand::ExecuteSearch {
+            request_id: 3,
+            pattern: Arc::from("a".repeat(MAX_PATTERN_BYTES + 1)),
+            direction: SearchDirection::Forward,
+            options: SearchOptions::default(),
+            origin_byte: 0,
+            origin_row: 0,
+            skip_origin_row: false,
+            frequency_limit: None,
+            preview: false,
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+
+    match next_response(&mut resp_rx).await {
+        SearchResponse::Error { request_id, error } => {
+            assert_eq!(request_id, 3);
+            assert!(error.to_string().contains("Pattern too long"));
+        }
+        other => panic!("expected error response, got {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn skip_duplicates_jumps_over_identical_runs() {
+    let mut contents = String::from("start\n");
+    contents.push_str(&"heartbeat ok\n".repeat(10_000));
+    let run_end = contents.len() as u64;
+    contents.push_str("error: disk full\n");
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+
+    for (request_id, direction, top, expected) in [
+        (1, SearchDirection::Forward, 6, Some(run_end)),
+        (2, SearchDirection::Backward, run_end, Some(run_end - 13)),
+        (3, SearchDirection::Backward, run_end - 13, Some(0)),
+        (4, SearchDirection::Forward, run_end, None),
+    ] {
+        cmd_tx
+            .send(SearchCommand::SkipDuplicates {
+                request_id,
+                direction,
+                current_top: top,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+
+        match next_response(&mut resp_rx).await {
+            SearchResponse::PositionFound {
+                request_id: id,
+                byte,
+                message,
+            } => {
+                assert_eq!(id, request_id);
+                assert_eq!(byte, expected);
+                assert_eq!(message.is_some(), expected.is_none());
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn load_viewport_with_filter_merges_filter_and_search_highlights() {
+    let contents = "info: disk ok\nerror: disk full\ninfo: net ok\nerror: net down\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+    let spec = |pattern: &str| {
+        Some(Arc::new(SearchHighlightSpec {
+            pattern: Arc::from(pattern),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        }))
+    };
+
+    cmd_tx
+        .send(SearchCommand::LoadViewport {
+            request_id: 1,
+            top: ViewportRequest::Absolute(0),
+            page_lines: 5,
+            highlights: spec("disk"),
+            filter: spec("error.*"),
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+
+    match next_response(&mut resp_rx).await {
+        SearchResponse::ViewportLoaded {
+            top_byte,
+            lines,
+            highlights,
+            at_eof,
+            ..
+        } => {
+            assert_eq!(top_byte, 14);
+            assert_eq!(lines, vec!["error: disk full", "error: net down"]);
+            assert_eq!(
+                highlights,
+                vec![
+                    vec![
+                        (0, 7, HighlightKind::Filter),
+                        (7, 11, HighlightKind::Search),
+                        (11, 16, HighlightKind::Filter),
+                    ],
+                    vec![(0, 15, HighlightKind::Filter)],
+                ]
+            );
+            assert!(at_eof);
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    // An invalid filter is reported instead of hiding every line
+    cmd_tx
+        .send(SearchCommand::LoadViewport {
+            request_id: 2,
+            top: ViewportRequest::Absolute(0),
+            page_lines: 5,
+            highlights: None,
+            filter: spec("error("),
+            current_match: None,
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        next_response(&mut resp_rx).await,
+        SearchResponse::Error { request_id: 2, .. }
+    ));
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn single_line_file_navigates_by_segment() {
+    // Minified JSON dump: one line, no newline, several segments long
+    let mut contents = String::from("[");
+    while contents.len() < 5 * SEGMENT_BYTES {
+        contents.push_str("{\"level\":\"info\"},");
+    }
+    let needle_at = contents.len();
+    contents.push_str("{\"level\":\"fatal\"}]");
+    let file_size = contents.len() as u64;
+    let seg = SEGMENT_BYTES as u64;
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+
+    let requests = [
+        // `%` jump to the middle stays in the middle instead of snapping to byte 0
+        (ViewportRequest::Absolute(file_size / 2), file_size / 2),
+        // `j` moves one segment down the line
+        (
+            ViewportRequest::RelativeLines {
+                anchor: 0,
+                lines: 1,
+            },
+            seg,
+        ),
+        // `G` shows the last two segments
+        (ViewportRequest::EndOfFile, 4 * seg),
+    ];
+    for (request_id, (top, expected_top)) in (1..).zip(requests) {
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top,
+                page_lines: 2,
+                highlights: None,
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded {
+                top_byte, lines, ..
+            } => {
+                assert_eq!(top_byte, expected_top);
+                assert_eq!(lines.len(), 2);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx
+        .send(SearchCommand::ExecuteSearch {
+            request_id: 10,
+            pattern: Arc::from("fatal"),
+            direction: SearchDirection::Forward,
+            options: SearchOptions::default(),
+            origin_byte: 0,
+            origin_row: 0,
+            skip_origin_row: false,
+            frequency_limit: None,
+            preview: false,
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted { match_byte, .. } => {
+            assert_eq!(match_byte, Some(needle_at as u64 / seg * seg));
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn frequent_pattern_is_held_back_for_confirmation() {
+    let line = format!("{}\n", "e".repeat(63));
+    let contents = format!("{}needle\n", line.repeat(2 * 1024 * 1024 / line.len()));
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+
+    let search = |request_id, pattern: &str, frequency_limit| SearchCommand::ExecuteSearch {
+        request_id,
+        pattern: Arc::from(pattern),
+        direction: SearchDirection::Forward,
+        options: SearchOptions::default(),
+        origin_byte: 0,
+        origin_row: 0,
+        skip_origin_row: false,
+        frequency_limit,
+        preview: false,
+        cancel_flag: CancellationToken::new(),
+    };
+
+    cmd_tx.send(search(1, "e", Some(1000))).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted {
+            match_byte: None,
+            message: None,
+            confirm:
+                Some(FrequentMatches {
+                    pattern,
+                    direction: SearchDirection::Forward,
+                    options: _,
+                    skip_origin_row: false,
+                    matches_per_mb,
+                }),
+            ..
+        } => {
+            assert_eq!(&*pattern, "e");
+            assert!(matches_per_mb > 1000);
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    // Rare patterns and confirmed searches run straight away
+    cmd_tx.send(search(2, "needle", Some(1000))).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted {
+            match_byte: Some(byte),
+            confirm: None,
+            ..
+        } => assert_eq!(byte as usize, contents.len() - "needle\n".len()),
+        other => panic!("unexpected response: {other:?}"),
+    }
+    cmd_tx.send(search(3, "e", None)).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCompleted {
+            match_byte: Some(0),
+            confirm: None,
+            ..
+        } => {}
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn describe_file_reports_on_disk_metadata() {
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker("one\ntwo\n").await;
+
+    cmd_tx.send(SearchCommand::DescribeFile).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::FileDescribed {
+            meta,
+            canonical_path,
+            file_size,
+        } => {
+            let meta = meta.expect("file-backed accessor has metadata");
+            assert!(canonical_path.expect("file-backed").is_absolute());
+            assert_eq!(meta.size, 8);
+            assert_eq!(file_size, 8);
+            assert!(meta.modified.is_some());
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn peek_line_returns_whole_segmented_line_with_highlights() {
+    // The long line spans three display rows; the needle sits in the last one
+    let long = format!("{}needle", "x".repeat(2 * SEGMENT_BYTES + 10));
+    let contents = format!("head\n{long}\ntail\n");
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+
+    cmd_tx
+        .send(SearchCommand::PeekLine {
+            request_id: 7,
+            row_start: 5 + SEGMENT_BYTES as u64,
+            highlights: Some(Arc::new(SearchHighlightSpec {
+                pattern: Arc::from("needle"),
+                options: SearchOptions::default(),
+                modifiers: PatternModifiers::default(),
+            })),
+            filter: None,
+        })
+        .await
+        .unwrap();
+
+    match next_response(&mut resp_rx).await {
+        SearchResponse::LinePeeked {
+            request_id,
+            start,
+            text,
+            highlights,
+            truncated,
+        } => {
+            assert_eq!(request_id, 7);
+            assert_eq!(start, 5);
+            assert_eq!(text, long);
+            assert!(!truncated);
+            let needle = long.len() - "needle".len();
+            assert_eq!(
+                highlights,
+                vec![(needle, long.len(), HighlightKind::Search)]
+            );
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn navigate_record_skips_stack_trace_lines() {
+    let first = "2024-06-01T12:00:00Z INFO starting\n";
+    let second = "2024-06-01T12:00:01Z ERROR request failed\n";
+    let trace = "java.lang.IllegalStateException: 2024-06-01 is not a record\n\
+                 \tat com.example.Handler.handle(Handler.java:42)\n\
+                 \tat com.example.Server.run(Server.java:7)\n";
+    let third = "[2024-06-01 12:00:02] INFO recovered\n";
+    let contents = format!("{first}{second}{trace}{third}");
+    let second_start = first.len() as u64;
+    let trace_line = second_start + second.len() as u64 + 60;
+    let third_start = (contents.len() - third.len()) as u64;
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+
+    for (request_id, direction, top, expected) in [
+        (1, SearchDirection::Forward, 0, Some(second_start)),
+        (2, SearchDirection::Forward, second_start, Some(third_start)),
+        // From inside the trace, back to the record it belongs to
+        (3, SearchDirection::Backward, trace_line, Some(second_start)),
+        (4, SearchDirection::Backward, second_start, Some(0)),
+        (5, SearchDirection::Backward, 0, None),
+        (6, SearchDirection::Forward, third_start, None),
+    ] {
+        cmd_tx
+            .send(SearchCommand::NavigateRecord {
+                request_id,
+                direction,
+                record_start: Arc::from(DEFAULT_RECORD_START),
+                current_top: top,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+
+        match next_response(&mut resp_rx).await {
+            SearchResponse::PositionFound {
+                request_id: id,
+                byte,
+                message,
+            } => {
+                assert_eq!(id, request_id);
+                assert_eq!(byte, expected, "request {request_id}");
+                assert_eq!(message.is_some(), expected.is_none());
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+/// One record every ten seconds from 23:50 on 2024-06-01, each followed by a trace line, with
+/// `clock` seconds added to every stamp
+fn timed_log(records: std::ops::Range<u32>, clock: i64) -> String {
+    let start = chrono::NaiveDate::from_ymd_opt(2024, 6, 1)
+        .unwrap()
+        .and_hms_opt(23, 50, 0)
+        .unwrap();
+    records
+        .map(|n| {
+            let stamp = start + chrono::Duration::seconds(i64::from(n) * 10 + clock);
+            format!(
+                "{} INFO step {n}\n\tat Step.run(Step.java:{n})\n",
+                stamp.format("%Y-%m-%dT%H:%M:%S%.3f")
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn navigate_time_finds_the_first_record_at_or_after_a_time() {
+    // 23:50:00 to 00:06:30 the next day
+    let contents = timed_log(0..100, 0);
+    let line_of = |contents: &str, step: u32| {
+        contents.find(&format!("INFO step {step}\n")).unwrap() as u64 - 24
+    };
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+    let cases = [
+        ("2024-06-01T23:55:00", 0, Some(30)),
+        ("2024-06-01 23:55:05", 0, Some(31)),
+        ("2024-06-02T00:06:30", 0, Some(99)),
+        ("2024-06-01T00:00:00", 0, Some(0)),
+        ("2024-06-02T00:06:31", 0, None),
+        // Without a date: the day of the record at the top
+        ("23:58", 0, Some(48)),
+        ("00:01", line_of(&contents, 70), Some(66)),
+    ];
+    for (request_id, (target, top, expected)) in (1..).zip(cases) {
+        cmd_tx
+            .send(SearchCommand::NavigateTime {
+                request_id,
+                target: TimeTarget::parse(target).unwrap(),
+                record_start: Arc::from(DEFAULT_RECORD_START),
+                current_top: top,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+
+        match next_response(&mut resp_rx).await {
+            SearchResponse::PositionFound {
+                request_id: id,
+                byte,
+                message,
+            } => {
+                assert_eq!(id, request_id);
+                assert_eq!(
+                    byte,
+                    expected.map(|step| line_of(&contents, step)),
+                    "{target}"
+                );
+                match byte {
+                    Some(_) => assert_eq!(message, None),
+                    None => assert!(message.unwrap().starts_with("No record at or after")),
+                }
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+
+    // The middle third stamped an hour behind: bisecting notices and the file is scanned
+    let contents = timed_log(0..33, 0) + &timed_log(33..66, -3600) + &timed_log(66..100, 0);
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+    cmd_tx
+        .send(SearchCommand::NavigateTime {
+            request_id: 1,
+            target: TimeTarget::parse("2024-06-01T23:00:00").unwrap(),
+            record_start: Arc::from(DEFAULT_RECORD_START),
+            current_top: 0,
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+    loop {
+        match next_response(&mut resp_rx).await {
+            SearchResponse::SearchProgress { .. } => continue,
+            SearchResponse::PositionFound { byte, message, .. } => {
+                assert_eq!(byte, Some(0));
+                assert_eq!(message, None);
+                break;
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+/// Lines shaped like the benchmark fixture: every 100th one an error naming a session
+fn session_log(lines: u64) -> String {
+    (0..lines)
+        .map(|n| {
+            if n % 100 == 0 {
+                format!("2024-01-01T10:00:00.000 [ERROR] auth - timeout session sess_{n:08x}\n")
+            } else {
+                format!("2024-01-01T10:00:00.000 [INFO ] api - Request {n:06} processed\n")
+            }
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn pattern_set_of_a_thousand_entries_searches_highlights_and_counts() {
+    let contents = session_log(2_000);
+    let line_start = |n: usize| -> u64 {
+        contents
+            .lines()
+            .take(n)
+            .map(|line| line.len() as u64 + 1)
+            .sum()
+    };
+    // A thousand session ids, of which the 20 errors in the file are the first
+    let set: Vec<String> = (0..1_000u64)
+        .map(|i| format!("sess_{:08x}", i * 100))
+        .collect();
+    let pattern: Arc<str> = Arc::from(set.join("\n"));
+
+    // Literal mode takes the Aho-Corasick path, regex mode the escaped alternation
+    for regex_mode in [false, true] {
+        let options = SearchOptions {
+            regex_mode,
+            case_sensitive: false,
+            pattern_set: true,
+            ..SearchOptions::default()
+        };
+        let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+        cmd_tx
+            .send(SearchCommand::ExecuteSearch {
+                request_id: 1,
+                pattern: Arc::clone(&pattern),
+                direction: SearchDirection::Forward,
+                options: options.clone(),
+                origin_byte: line_start(1),
+                origin_row: 0,
+                skip_origin_row: false,
+                frequency_limit: None,
+                preview: false,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        let first = match next_response(&mut resp_rx).await {
+            SearchResponse::SearchCompleted {
+                match_byte: Some(byte),
+                ..
+            } => byte,
+            other => panic!("unexpected response: {other:?}"),
+        };
+        assert_eq!(first, line_start(100));
+
+        cmd_tx
+            .send(SearchCommand::NavigateMatch {
+                request_id: 2,
+                traversal: MatchTraversal::Next,
+                current_top: first,
+                origin_row: 0,
+                count: 1,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            next_response(&mut resp_rx).await,
+            SearchResponse::SearchCompleted { match_byte: Some(byte), .. } if byte == line_start(200)
+        ));
+
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id: 3,
+                top: ViewportRequest::Absolute(line_start(199)),
+                page_lines: 3,
+                highlights: Some(Arc::new(SearchHighlightSpec {
+                    pattern: Arc::clone(&pattern),
+                    options: options.clone(),
+                    modifiers: PatternModifiers::default(),
+                })),
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded {
+                lines, highlights, ..
+            } => {
+                let id = lines[1].find("sess_").unwrap();
+                assert_eq!(highlights[0], vec![]);
+                assert_eq!(
+                    highlights[1],
+                    vec![(id, id + "sess_000000c8".len(), HighlightKind::Search)]
+                );
+                assert_eq!(highlights[2], vec![]);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+        cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+        worker.await.unwrap();
+
+        // Every error line matches, nothing else does
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &contents).unwrap();
+        let accessor: Arc<dyn FileAccessor> = Arc::new(
+            rlless::file_handler::FileAccessorFactory::create(file.path())
+                .await
+                .unwrap(),
+        );
+        let engine = rlless::search::RipgrepEngine::new(accessor);
+        let matching = contents
+            .lines()
+            .filter(|line| {
+                !engine
+                    .get_line_matches(&pattern, line, &options)
+                    .unwrap()
+                    .is_empty()
+            })
+            .count();
+        assert_eq!(matching, 20);
+    }
+}
+
+/// Run an export to `target` and return how it ended, checking its progress reports on the way
+async fn export(
+    cmd_tx: &mpsc::Sender<SearchCommand>,
+    resp_rx: &mut mpsc::Receiver<SearchResponse>,
+    request_id: u64,
+    target: ExportTarget,
+) -> (String, Option<std::path::PathBuf>) {
+    cmd_tx
+        .send(SearchCommand::Export {
+            request_id,
+            target,
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+    // A shell command takes a while to start
+    loop {
+        let response = timeout(Duration::from_secs(10), resp_rx.recv())
+            .await
+            .expect("export timed out")
+            .expect("worker channel closed unexpectedly");
+        match response {
+            SearchResponse::Progress {
+                operation: ProgressOperation::Export,
+                fraction,
+            } => assert!((0.0..=1.0).contains(&fraction)),
+            SearchResponse::ExportFinished {
+                request_id: finished,
+                message,
+                partial,
+            } => {
+                assert_eq!(finished, request_id);
+                return (message, partial);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn save_writes_the_file_once() {
+    let contents = "one\ntwo\nthree\n";
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(contents).await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("copy.log");
+
+    let (message, partial) =
+        export(&cmd_tx, &mut resp_rx, 1, ExportTarget::File(path.clone())).await;
+    assert!(message.starts_with("Wrote 14 B in "), "{message}");
+    assert!(message.ends_with("copy.log"), "{message}");
+    assert_eq!(partial, None);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+
+    // An existing file is never overwritten
+    std::fs::write(&path, "keep me").unwrap();
+    let (message, partial) =
+        export(&cmd_tx, &mut resp_rx, 2, ExportTarget::File(path.clone())).await;
+    assert!(message.ends_with("copy.log already exists"), "{message}");
+    assert_eq!(partial, None);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn pipe_reports_the_first_line_the_command_prints() {
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker("one\ntwo\nthree\n").await;
+
+    let target = ExportTarget::Command("wc -l | tr -d ' '".to_string());
+    let (message, _) = export(&cmd_tx, &mut resp_rx, 1, target).await;
+    assert!(message.starts_with("Piped 14 B in "), "{message}");
+    assert!(message.ends_with("to `wc -l | tr -d ' '`: 3"), "{message}");
+
+    // A command that stops reading early is not a failure
+    let target = ExportTarget::Command("head -n 1".to_string());
+    let (message, _) = export(&cmd_tx, &mut resp_rx, 2, target).await;
+    assert!(message.ends_with("to `head -n 1`: one"), "{message}");
+
+    let target = ExportTarget::Command("echo broken >&2; exit 3".to_string());
+    let (message, _) = export(&cmd_tx, &mut resp_rx, 3, target).await;
+    assert!(message.contains("failed"), "{message}");
+    assert!(message.ends_with(": broken"), "{message}");
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn kept_patterns_are_highlighted_in_their_own_kinds() {
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker("ERROR req-7\nok req-9\n").await;
+    let spec = |pattern: &str| {
+        Arc::new(SearchHighlightSpec {
+            pattern: Arc::from(pattern),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        })
+    };
+    cmd_tx
+        .send(SearchCommand::KeepPatterns(vec![
+            spec("req-\\d"),
+            spec("ERROR"),
+        ]))
+        .await
+        .unwrap();
+
+    for (request_id, show) in [(1, true), (2, false)] {
+        cmd_tx
+            .send(SearchCommand::ShowHighlights(show))
+            .await
+            .unwrap();
+        cmd_tx
+            .send(SearchCommand::LoadViewport {
+                request_id,
+                top: ViewportRequest::Absolute(0),
+                page_lines: 2,
+                highlights: Some(spec("ok")),
+                filter: None,
+                current_match: None,
+                cancel_flag: CancellationToken::new(),
+            })
+            .await
+            .unwrap();
+        let highlights = match next_response(&mut resp_rx).await {
+            SearchResponse::ViewportLoaded { highlights, .. } => highlights,
+            other => panic!("unexpected response: {other:?}"),
+        };
+        if show {
+            assert_eq!(
+                highlights,
+                vec![
+                    vec![(0, 5, HighlightKind::Kept2), (6, 11, HighlightKind::Kept1)],
+                    vec![(0, 2, HighlightKind::Search), (3, 8, HighlightKind::Kept1)],
+                ]
+            );
+        } else {
+            // ESC-u hides them along with the search's, leaving a preview's highlight
+            assert_eq!(
+                highlights,
+                vec![vec![], vec![(0, 2, HighlightKind::Search)]]
+            );
+        }
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn collect_matches_sends_full_batches_and_says_where_to_go_on() {
+    let mut contents = String::from("hit\tfirst\r\n");
+    for i in 1..125 {
+        contents.push_str(&format!("miss {i}\nhit {i}\n"));
+    }
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+    let collect = |request_id, from| SearchCommand::CollectMatches {
+        request_id,
+        search: Arc::new(SearchHighlightSpec {
+            pattern: Arc::from("hit"),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        }),
+        from,
+        limit: 120,
+        cancel_flag: CancellationToken::new(),
+    };
+
+    cmd_tx.send(collect(1, 0)).await.unwrap();
+    let mut listed = Vec::new();
+    let resume = loop {
+        match next_response(&mut resp_rx).await {
+            SearchResponse::MatchBatch {
+                request_id: 1,
+                matches,
+                last,
+                resume_from,
+            } => {
+                assert!(last || matches.len() == 100);
+                listed.extend(matches);
+                if last {
+                    break resume_from.expect("more matches follow");
+                }
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    };
+    assert_eq!(listed.len(), 120);
+    assert_eq!(listed[0], (0, "hit first".to_string()));
+    assert_eq!(listed[119].1, "hit 119");
+    assert!(contents[..listed[119].0 as usize].ends_with("miss 119\n"));
+
+    cmd_tx.send(collect(2, resume)).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::MatchBatch {
+            request_id: 2,
+            matches,
+            last: true,
+            resume_from: None,
+        } => {
+            let texts: Vec<_> = matches.iter().map(|(_, text)| text.as_str()).collect();
+            assert_eq!(
+                texts,
+                ["hit 120", "hit 121", "hit 122", "hit 123", "hit 124"]
+            );
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[tokio::test]
+async fn cancelled_operations_answer_as_cancelled_or_not_at_all() {
+    let contents = "info: start\nerror: one\nerror: one\ninfo: end\n".repeat(50);
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&contents).await;
+    let cancelled = || {
+        let token = CancellationToken::new();
+        token.cancel();
+        token
+    };
+    let spec = |pattern: &str| {
+        Arc::new(SearchHighlightSpec {
+            pattern: Arc::from(pattern),
+            options: SearchOptions::default(),
+            modifiers: PatternModifiers::default(),
+        })
+    };
+
+    let commands = [
+        SearchCommand::ExecuteSearch {
+            request_id: 1,
+            pattern: Arc::from("end"),
+            direction: SearchDirection::Forward,
+            options: SearchOptions::default(),
+            origin_byte: 0,
+            origin_row: 0,
+            skip_origin_row: false,
+            frequency_limit: None,
+            preview: false,
+            cancel_flag: cancelled(),
+        },
+        SearchCommand::SkipDuplicates {
+            request_id: 2,
+            direction: SearchDirection::Forward,
+            current_top: 12,
+            cancel_flag: cancelled(),
+        },
+        SearchCommand::NavigateRecord {
+            request_id: 3,
+            direction: SearchDirection::Forward,
+            record_start: Arc::from("^info"),
+            current_top: 0,
+            cancel_flag: cancelled(),
+        },
+    ];
+    for command in commands {
+        let request_id = command.request_id().unwrap();
+        cmd_tx.send(command).await.unwrap();
+        match next_response(&mut resp_rx).await {
+            SearchResponse::SearchCancelled { request_id: id } => assert_eq!(id, request_id),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+
+    // Navigation needs an active search to cancel
+    cmd_tx
+        .send(SearchCommand::UpdateSearchContext(SearchContext {
+            pattern: Arc::from("end"),
+            options: SearchOptions::default(),
+            direction: SearchDirection::Forward,
+            last_match_byte: None,
+        }))
+        .await
+        .unwrap();
+    cmd_tx
+        .send(SearchCommand::NavigateMatch {
+            request_id: 4,
+            traversal: MatchTraversal::Next,
+            current_top: 0,
+            origin_row: 0,
+            count: 1,
+            cancel_flag: cancelled(),
+        })
+        .await
+        .unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::SearchCancelled { request_id: 4 } => {}
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    // Nobody waits for a superseded filtered page or a closed match list
+    cmd_tx
+        .send(SearchCommand::LoadViewport {
+            request_id: 5,
+            top: ViewportRequest::Absolute(0),
+            page_lines: 5,
+            highlights: None,
+            filter: Some(spec("^error")),
+            current_match: None,
+            cancel_flag: cancelled(),
+        })
+        .await
+        .unwrap();
+    cmd_tx
+        .send(SearchCommand::CollectMatches {
+            request_id: 6,
+            search: spec("error"),
+            from: 0,
+            limit: 10,
+            cancel_flag: cancelled(),
+        })
+        .await
+        .unwrap();
+    // Nothing of that id runs any more
+    cmd_tx
+        .send(SearchCommand::Cancel { request_id: 6 })
+        .await
+        .unwrap();
+    cmd_tx.send(SearchCommand::DescribeFile).await.unwrap();
+    match next_response(&mut resp_rx).await {
+        SearchResponse::FileDescribed { .. } => {}
+        other => panic!("unexpected response: {other:?}"),
+    }
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn cancel_stops_an_export_running_in_the_background() {
+    // Far more than a pipe holds, so the export blocks on a command that never reads
+    let (cmd_tx, mut resp_rx, worker) = spawn_worker(&"0123456789abcdef\n".repeat(64 * 1024)).await;
+    cmd_tx
+        .send(SearchCommand::Export {
+            request_id: 1,
+            target: ExportTarget::Command("exec sleep 30".to_string()),
+            cancel_flag: CancellationToken::new(),
+        })
+        .await
+        .unwrap();
+    // The worker goes on serving commands meanwhile
+    cmd_tx.send(SearchCommand::DescribeFile).await.unwrap();
+    cmd_tx
+        .send(SearchCommand::Cancel { request_id: 1 })
+        .await
+        .unwrap();
+
+    let started = std::time::Instant::now();
+    loop {
+        let response = timeout(Duration::from_secs(10), resp_rx.recv())
+            .await
+            .expect("export timed out")
+            .expect("worker channel closed unexpectedly");
+        match response {
+            SearchResponse::Progress { .. } | SearchResponse::FileDescribed { .. } => {}
+            SearchResponse::ExportFinished {
+                request_id: 1,
+                message,
+                ..
+            } => {
+                assert_eq!(message, "Pipe to `exec sleep 30` cancelled");
+                break;
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    cmd_tx.send(SearchCommand::Shutdown).await.unwrap();
+    worker.await.unwrap();
+}